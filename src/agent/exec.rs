@@ -0,0 +1,410 @@
+//! Streaming command execution (`/exec/stream`).
+//!
+//! Unlike `/exec`, which buffers everything until the command exits, this
+//! streams output as NDJSON frames while the command runs:
+//!
+//! ```text
+//! {"type":"started","id":"…","pid":4242}
+//! {"type":"stdout","data":"Compiling spuff v0.1.0\n"}
+//! {"type":"stderr","data":"warning: unused variable\n"}
+//! {"type":"exit","code":0,"duration_ms":5123}
+//! ```
+//!
+//! The request body is one line of JSON ([`StreamExecRequest`]) followed by
+//! the bytes to feed to the command's stdin. Each command runs in its own
+//! process group, which is killed when the client disconnects, when the
+//! timeout expires, or on `POST /exec/cancel`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::body::{Body, Bytes};
+use futures::stream::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use crate::AppState;
+
+/// Exit code reported when the command hits its timeout (as `timeout(1)`).
+const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// Longest request line accepted before the stdin bytes.
+const MAX_REQUEST_LINE: usize = 64 * 1024;
+
+/// Request line of `/exec/stream`.
+#[derive(Debug, Deserialize)]
+pub struct StreamExecRequest {
+    pub command: String,
+    /// Working directory (default: the agent's)
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Variables added to the agent's environment
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Kill the command after this many seconds (default: no limit)
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// One NDJSON frame of the response.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Frame {
+    Started { id: String, pid: u32 },
+    Stdout { data: String },
+    Stderr { data: String },
+    Error { message: String },
+    Exit { code: i32, duration_ms: u64 },
+}
+
+impl Frame {
+    fn to_line(&self) -> Bytes {
+        let mut line = serde_json::to_vec(self).unwrap_or_default();
+        line.push(b'\n');
+        Bytes::from(line)
+    }
+}
+
+/// Process groups of running streamed commands, by exec id.
+#[derive(Default)]
+pub struct RunningExecs(Mutex<HashMap<String, i32>>);
+
+impl RunningExecs {
+    fn insert(&self, id: &str, pgid: i32) {
+        self.lock().insert(id.to_string(), pgid);
+    }
+
+    fn remove(&self, id: &str) -> Option<i32> {
+        self.lock().remove(id)
+    }
+
+    /// Kill the process group of a running command. Returns false if unknown.
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.remove(id) {
+            Some(pgid) => {
+                kill_group(pgid);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, i32>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Kills the command's process group if the response is dropped early.
+struct GroupGuard {
+    state: Arc<AppState>,
+    id: String,
+}
+
+impl Drop for GroupGuard {
+    fn drop(&mut self) {
+        // Only still registered if the command didn't finish
+        if self.state.execs.cancel(&self.id) {
+            tracing::info!("Client went away, killed exec {}", self.id);
+        }
+    }
+}
+
+/// Read the request line from the start of the body.
+///
+/// Returns the request and a stream of the remaining (stdin) bytes.
+pub async fn read_request(
+    body: Body,
+) -> Result<(StreamExecRequest, Vec<u8>, impl Stream<Item = Bytes>), String> {
+    let mut chunks = body.into_data_stream();
+    let mut head = Vec::new();
+
+    let newline = loop {
+        if let Some(pos) = head.iter().position(|&b| b == b'\n') {
+            break pos;
+        }
+        if head.len() > MAX_REQUEST_LINE {
+            return Err("Request line too long".to_string());
+        }
+        match chunks.next().await {
+            Some(Ok(chunk)) => head.extend_from_slice(&chunk),
+            Some(Err(e)) => return Err(format!("Failed to read request: {}", e)),
+            // A body without stdin doesn't need the newline
+            None => break head.len(),
+        }
+    };
+
+    let stdin = head.split_off((newline + 1).min(head.len()));
+    let request: StreamExecRequest =
+        serde_json::from_slice(&head[..newline]).map_err(|e| format!("Invalid request: {}", e))?;
+    if request.command.trim().is_empty() {
+        return Err("Empty command".to_string());
+    }
+
+    let rest = chunks.filter_map(|chunk| async move { chunk.ok() });
+    Ok((request, stdin, rest))
+}
+
+/// Spawn the command and stream its frames.
+pub fn run(
+    state: Arc<AppState>,
+    request: StreamExecRequest,
+    stdin_head: Vec<u8>,
+    stdin_rest: impl Stream<Item = Bytes> + Send + 'static,
+) -> impl Stream<Item = Result<Bytes, std::convert::Infallible>> {
+    async_stream::stream! {
+        let start = Instant::now();
+        let preview = command_preview(&request.command);
+
+        let mut command = tokio::process::Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&request.command)
+            .envs(&request.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true);
+        if let Some(cwd) = &request.cwd {
+            command.current_dir(cwd);
+        }
+
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                state
+                    .log_activity("exec_failed", Some(format!("cmd='{}' error={}", preview, e)))
+                    .await;
+                yield Ok(Frame::Error { message: format!("Execution failed: {}", e) }.to_line());
+                yield Ok(Frame::Exit { code: -1, duration_ms: 0 }.to_line());
+                return;
+            }
+        };
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let pid = child.id().unwrap_or_default();
+        state.execs.insert(&id, pid as i32);
+        let _guard = GroupGuard { state: state.clone(), id: id.clone() };
+        yield Ok(Frame::Started { id: id.clone(), pid }.to_line());
+
+        if let Some(mut stdin) = child.stdin.take() {
+            tokio::spawn(async move {
+                if stdin.write_all(&stdin_head).await.is_err() {
+                    return;
+                }
+                let mut rest = std::pin::pin!(stdin_rest);
+                while let Some(chunk) = rest.next().await {
+                    if stdin.write_all(&chunk).await.is_err() {
+                        return;
+                    }
+                }
+                // Dropping stdin sends EOF
+            });
+        }
+
+        let (tx, mut rx) = mpsc::channel(64);
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(pump(stdout, tx.clone(), |data| Frame::Stdout { data }));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(pump(stderr, tx.clone(), |data| Frame::Stderr { data }));
+        }
+        drop(tx);
+
+        let deadline = request
+            .timeout_secs
+            .map(|secs| tokio::time::Instant::now() + Duration::from_secs(secs));
+        let mut timed_out = false;
+
+        loop {
+            let frame = match deadline {
+                Some(deadline) => tokio::select! {
+                    frame = rx.recv() => frame,
+                    _ = tokio::time::sleep_until(deadline) => {
+                        timed_out = true;
+                        state.execs.cancel(&id);
+                        None
+                    }
+                },
+                None => rx.recv().await,
+            };
+            match frame {
+                Some(frame) => yield Ok(frame.to_line()),
+                None => break,
+            }
+        }
+
+        let code = if timed_out {
+            let secs = request.timeout_secs.unwrap_or_default();
+            yield Ok(Frame::Error { message: format!("Command timed out after {}s", secs) }.to_line());
+            TIMEOUT_EXIT_CODE
+        } else {
+            match child.wait().await {
+                Ok(status) => exit_code(status),
+                Err(e) => {
+                    yield Ok(Frame::Error { message: format!("Failed to wait for command: {}", e) }.to_line());
+                    -1
+                }
+            }
+        };
+        // Finished: nothing left for the guard to kill
+        state.execs.remove(&id);
+
+        let duration_ms = start.elapsed().as_millis() as u64;
+        state.update_activity().await;
+        state
+            .log_activity(
+                "exec",
+                Some(format!("cmd='{}' exit={} duration={}ms streamed", preview, code, duration_ms)),
+            )
+            .await;
+
+        yield Ok(Frame::Exit { code, duration_ms }.to_line());
+    }
+}
+
+/// Forward output of one pipe as frames until it closes.
+async fn pump<R>(mut reader: R, tx: mpsc::Sender<Frame>, frame: fn(String) -> Frame)
+where
+    R: AsyncRead + Unpin,
+{
+    let mut buf = [0u8; 8192];
+    let mut pending = Vec::new();
+
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        pending.extend_from_slice(&buf[..n]);
+        let text = take_utf8(&mut pending);
+        if !text.is_empty() && tx.send(frame(text)).await.is_err() {
+            return;
+        }
+    }
+
+    if !pending.is_empty() {
+        let _ = tx
+            .send(frame(String::from_utf8_lossy(&pending).into_owned()))
+            .await;
+    }
+}
+
+/// Take the longest valid UTF-8 prefix of `pending`, leaving an incomplete
+/// trailing character for the next read. Invalid bytes are replaced.
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    let rest = pending.split_off(valid);
+    let text = String::from_utf8_lossy(pending).into_owned();
+    *pending = rest;
+    text
+}
+
+/// Exit code of a finished command; `128 + signal` if it was killed.
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(-1)
+}
+
+fn kill_group(pgid: i32) {
+    if let Err(e) = nix::sys::signal::killpg(
+        nix::unistd::Pid::from_raw(pgid),
+        nix::sys::signal::Signal::SIGKILL,
+    ) {
+        tracing::debug!("Failed to kill process group {}: {}", pgid, e);
+    }
+}
+
+fn command_preview(command: &str) -> String {
+    if command.len() > 80 {
+        let end = (0..=80)
+            .rev()
+            .find(|&i| command.is_char_boundary(i))
+            .unwrap_or(0);
+        format!("{}...", &command[..end])
+    } else {
+        command.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_utf8_keeps_incomplete_char() {
+        // "é" is 0xC3 0xA9; split it across reads
+        let mut pending = b"caf\xC3".to_vec();
+        assert_eq!(take_utf8(&mut pending), "caf");
+        assert_eq!(pending, b"\xC3");
+
+        pending.extend_from_slice(b"\xA9!");
+        assert_eq!(take_utf8(&mut pending), "é!");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_take_utf8_replaces_invalid_bytes() {
+        let mut pending = b"a\xFFb".to_vec();
+        assert_eq!(take_utf8(&mut pending), "a\u{FFFD}b");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_frame_wire_format() {
+        let started = Frame::Started {
+            id: "abc".to_string(),
+            pid: 42,
+        };
+        assert_eq!(
+            started.to_line(),
+            Bytes::from_static(b"{\"type\":\"started\",\"id\":\"abc\",\"pid\":42}\n")
+        );
+
+        let exit = Frame::Exit {
+            code: 1,
+            duration_ms: 5,
+        };
+        assert_eq!(
+            exit.to_line(),
+            Bytes::from_static(b"{\"type\":\"exit\",\"code\":1,\"duration_ms\":5}\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_request_splits_stdin() {
+        let body = Body::from("{\"command\":\"cat\",\"env\":{\"A\":\"1\"}}\nhello");
+        let (request, stdin, rest) = read_request(body).await.unwrap();
+
+        assert_eq!(request.command, "cat");
+        assert_eq!(request.env.get("A").map(String::as_str), Some("1"));
+        assert_eq!(stdin, b"hello");
+        assert_eq!(rest.collect::<Vec<_>>().await.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_read_request_without_newline() {
+        let body = Body::from("{\"command\":\"true\"}");
+        let (request, stdin, _) = read_request(body).await.unwrap();
+
+        assert_eq!(request.command, "true");
+        assert!(stdin.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_request_rejects_empty_command() {
+        let body = Body::from("{\"command\":\"  \"}\n");
+        assert!(read_request(body).await.is_err());
+    }
+}
@@ -0,0 +1,705 @@
+//! Idle policy engine.
+//!
+//! Decides whether the environment is idle from a set of configurable
+//! signals (interactive SSH sessions, SSH connections such as tunnels and
+//! volume mounts, CPU load, network throughput, whitelisted processes and
+//! agent API calls). When nothing has been active for the configured
+//! timeout, the configured action runs; logged-in terminals get a `wall`
+//! warning a grace period before that.
+//!
+//! The policy is read from `/opt/spuff/idle.json`, written by cloud-init.
+//! Without it the engine only reports and never acts.
+
+use std::path::Path;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sysinfo::{Networks, System};
+
+/// Policy file written by cloud-init.
+const IDLE_CONFIG_FILE: &str = "/opt/spuff/idle.json";
+
+/// How often signals are sampled.
+pub const CHECK_INTERVAL_SECS: u64 = 60;
+
+/// What to do once the environment has been idle for the timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdleAction {
+    /// Power off the VM (the local watchdog then destroys it).
+    Shutdown,
+    /// Only log; used on shared hosts that must never power off.
+    None,
+}
+
+/// Idle policy, as written to `/opt/spuff/idle.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleConfig {
+    pub timeout_seconds: u64,
+    #[serde(default)]
+    pub grace_period_seconds: u64,
+    pub action: IdleAction,
+    /// Interactive terminals (SSH or mosh) count as activity.
+    #[serde(default)]
+    pub ssh_sessions: bool,
+    /// Open SSH connections without a terminal (tunnels, volume mounts).
+    #[serde(default)]
+    pub tunnels: bool,
+    /// 1-minute load average per CPU, in percent, above which the box is busy.
+    #[serde(default)]
+    pub cpu_load_threshold: Option<f64>,
+    /// Network throughput in KiB/s above which the box is busy.
+    #[serde(default)]
+    pub network_threshold_kbps: Option<u64>,
+    /// Processes whose name or command line contains one of these keeps the box alive.
+    #[serde(default)]
+    pub processes: Vec<String>,
+}
+
+impl IdleConfig {
+    /// Load the policy file, if present and valid.
+    pub fn load() -> Option<Self> {
+        Self::load_from(Path::new(IDLE_CONFIG_FILE))
+    }
+
+    fn load_from(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                tracing::warn!("Invalid idle policy {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+/// One raw observation of the system.
+#[derive(Debug, Clone, Default)]
+pub struct Sample {
+    /// Allocated pseudo-terminals (one per interactive session).
+    pub terminals: usize,
+    /// Established TCP connections to the local SSH server.
+    pub ssh_connections: usize,
+    /// 1-minute load average divided by the CPU count, in percent.
+    pub cpu_load_percent: f64,
+    /// Bytes received plus transmitted on non-loopback interfaces since boot.
+    pub network_bytes: u64,
+    /// Whitelisted patterns that matched a running process.
+    pub matched_processes: Vec<String>,
+    /// Seconds since the last authenticated agent API call.
+    pub api_idle_seconds: i64,
+}
+
+impl Sample {
+    /// Collect a sample from the running system.
+    ///
+    /// Performs blocking I/O; call from a blocking task in async contexts.
+    pub fn collect(config: Option<&IdleConfig>, api_idle_seconds: i64) -> Self {
+        let cpus = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        let patterns = config.map(|c| c.processes.as_slice()).unwrap_or(&[]);
+
+        Self {
+            terminals: count_terminals(Path::new("/dev/pts")),
+            ssh_connections: count_ssh_connections(),
+            cpu_load_percent: System::load_average().one / cpus as f64 * 100.0,
+            network_bytes: network_bytes(),
+            matched_processes: matching_processes(patterns),
+            api_idle_seconds,
+        }
+    }
+}
+
+/// Whether a single signal currently counts as activity, and why.
+#[derive(Debug, Clone, Serialize)]
+pub struct SignalReport {
+    pub name: String,
+    pub active: bool,
+    pub detail: String,
+}
+
+impl SignalReport {
+    fn new(name: &str, active: bool, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            active,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Response of the `/idle` endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct IdleReport {
+    /// False when no policy file exists; nothing will happen on idle.
+    pub enabled: bool,
+    pub idle: bool,
+    pub idle_seconds: i64,
+    pub timeout_seconds: u64,
+    /// Seconds left before the action runs (None when disabled).
+    pub remaining_seconds: Option<i64>,
+    pub grace_period_seconds: u64,
+    pub warned: bool,
+    pub action: Option<IdleAction>,
+    pub last_active: DateTime<Utc>,
+    pub checked_at: DateTime<Utc>,
+    pub signals: Vec<SignalReport>,
+}
+
+/// Outcome of a policy check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Something is active, or the timeout is still far away.
+    Wait,
+    /// Entered the grace period; warn logged-in users once.
+    Warn,
+    /// Timeout reached; run the configured action.
+    Act,
+}
+
+/// Evaluate every signal against the policy.
+///
+/// `network_rate` is the throughput since the previous sample in bytes/s.
+pub fn evaluate(
+    config: &IdleConfig,
+    sample: &Sample,
+    network_rate: Option<f64>,
+) -> Vec<SignalReport> {
+    let mut signals = Vec::new();
+
+    if config.ssh_sessions {
+        signals.push(SignalReport::new(
+            "ssh_sessions",
+            sample.terminals > 0,
+            format!("{} interactive session(s)", sample.terminals),
+        ));
+    }
+
+    if config.tunnels {
+        // Every interactive session also holds a connection
+        let background = sample.ssh_connections.saturating_sub(sample.terminals);
+        signals.push(SignalReport::new(
+            "tunnels",
+            background > 0,
+            format!("{} SSH connection(s) without a terminal", background),
+        ));
+    }
+
+    if let Some(threshold) = config.cpu_load_threshold {
+        signals.push(SignalReport::new(
+            "cpu_load",
+            sample.cpu_load_percent >= threshold,
+            format!(
+                "load {:.0}% of CPUs (threshold {:.0}%)",
+                sample.cpu_load_percent, threshold
+            ),
+        ));
+    }
+
+    if let Some(threshold) = config.network_threshold_kbps {
+        let (active, detail) = match network_rate {
+            Some(rate) => {
+                let kbps = rate / 1024.0;
+                (
+                    kbps >= threshold as f64,
+                    format!("{:.1} KiB/s (threshold {} KiB/s)", kbps, threshold),
+                )
+            }
+            None => (false, "waiting for a second sample".to_string()),
+        };
+        signals.push(SignalReport::new("network", active, detail));
+    }
+
+    if !config.processes.is_empty() {
+        let detail = if sample.matched_processes.is_empty() {
+            "no whitelisted process running".to_string()
+        } else {
+            format!("running: {}", sample.matched_processes.join(", "))
+        };
+        signals.push(SignalReport::new(
+            "processes",
+            !sample.matched_processes.is_empty(),
+            detail,
+        ));
+    }
+
+    signals.push(SignalReport::new(
+        "agent_api",
+        sample.api_idle_seconds < CHECK_INTERVAL_SECS as i64,
+        format!("last API call {}s ago", sample.api_idle_seconds),
+    ));
+
+    signals
+}
+
+/// Decide what to do after being idle for `idle_seconds`.
+pub fn decide(config: &IdleConfig, idle_seconds: i64, warned: bool) -> Decision {
+    let timeout = config.timeout_seconds as i64;
+    let warn_at = timeout - config.grace_period_seconds as i64;
+
+    if idle_seconds >= timeout {
+        Decision::Act
+    } else if config.grace_period_seconds > 0 && idle_seconds >= warn_at && !warned {
+        Decision::Warn
+    } else {
+        Decision::Wait
+    }
+}
+
+/// Stateful engine; one per agent.
+pub struct IdleEngine {
+    config: Option<IdleConfig>,
+    last_active: DateTime<Utc>,
+    warned: bool,
+    acted: bool,
+    last_network: Option<(u64, Instant)>,
+    report: Option<IdleReport>,
+}
+
+impl IdleEngine {
+    pub fn new(config: Option<IdleConfig>) -> Self {
+        Self {
+            config,
+            last_active: Utc::now(),
+            warned: false,
+            acted: false,
+            last_network: None,
+            report: None,
+        }
+    }
+
+    pub fn config(&self) -> Option<&IdleConfig> {
+        self.config.as_ref()
+    }
+
+    /// Latest report, if a check has run yet.
+    pub fn report(&self) -> Option<&IdleReport> {
+        self.report.as_ref()
+    }
+
+    /// Feed a new sample and return what should happen now.
+    pub fn check(&mut self, sample: &Sample) -> Decision {
+        let now = Utc::now();
+
+        let network_rate = self.last_network.map(|(bytes, at)| {
+            let elapsed = at.elapsed().as_secs_f64().max(1.0);
+            sample.network_bytes.saturating_sub(bytes) as f64 / elapsed
+        });
+        self.last_network = Some((sample.network_bytes, Instant::now()));
+
+        let Some(config) = &self.config else {
+            self.report = Some(IdleReport {
+                enabled: false,
+                idle: false,
+                idle_seconds: 0,
+                timeout_seconds: 0,
+                remaining_seconds: None,
+                grace_period_seconds: 0,
+                warned: false,
+                action: None,
+                last_active: self.last_active,
+                checked_at: now,
+                signals: Vec::new(),
+            });
+            return Decision::Wait;
+        };
+
+        let signals = evaluate(config, sample, network_rate);
+        let busy = signals.iter().any(|s| s.active);
+        if busy {
+            self.last_active = now;
+            self.warned = false;
+            self.acted = false;
+        }
+
+        let idle_seconds = (now - self.last_active).num_seconds();
+        let mut decision = if busy {
+            Decision::Wait
+        } else {
+            decide(config, idle_seconds, self.warned)
+        };
+        match decision {
+            Decision::Warn => self.warned = true,
+            // Only act once per idle period (action `none` would repeat)
+            Decision::Act if self.acted => decision = Decision::Wait,
+            Decision::Act => self.acted = true,
+            Decision::Wait => {}
+        }
+
+        self.report = Some(IdleReport {
+            enabled: true,
+            idle: !busy,
+            idle_seconds,
+            timeout_seconds: config.timeout_seconds,
+            remaining_seconds: Some((config.timeout_seconds as i64 - idle_seconds).max(0)),
+            grace_period_seconds: config.grace_period_seconds,
+            warned: self.warned,
+            action: Some(config.action),
+            last_active: self.last_active,
+            checked_at: now,
+            signals,
+        });
+
+        decision
+    }
+}
+
+/// Warn everyone logged in that the box is about to go away.
+pub async fn broadcast_warning(remaining_seconds: i64) {
+    let minutes = (remaining_seconds + 59) / 60;
+    wall(&format!(
+        "spuff: this environment has been idle and will shut down in ~{} minute(s).\n\
+         Any activity (typing, a running build, an open tunnel) cancels the shutdown.",
+        minutes
+    ))
+    .await;
+}
+
+/// Send a message to every logged-in terminal.
+pub async fn wall(message: &str) {
+    match tokio::process::Command::new("wall")
+        .arg(message)
+        .output()
+        .await
+    {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => tracing::warn!(
+            "wall failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => tracing::warn!("Failed to run wall: {}", e),
+    }
+}
+
+/// Run the configured action.
+pub async fn run_action(action: IdleAction) {
+    match action {
+        IdleAction::Shutdown => {
+            tracing::warn!("Idle timeout reached, shutting down");
+            power_off().await;
+        }
+        IdleAction::None => {
+            tracing::info!("Idle timeout reached (no action configured)");
+        }
+    }
+}
+
+/// Power off the VM now.
+pub async fn power_off() {
+    if let Err(e) = tokio::process::Command::new("shutdown")
+        .args(["-h", "now"])
+        .status()
+        .await
+    {
+        tracing::error!("Failed to run shutdown: {}", e);
+    }
+}
+
+/// Count numbered entries in `/dev/pts` (ptmx is the multiplexer, not a session).
+fn count_terminals(pts_dir: &Path) -> usize {
+    std::fs::read_dir(pts_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| {
+                    e.file_name()
+                        .to_str()
+                        .is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
+                })
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+/// Count established connections to local port 22 from `/proc/net/tcp{,6}`.
+fn count_ssh_connections() -> usize {
+    ["/proc/net/tcp", "/proc/net/tcp6"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|table| count_established_on_port(&table, 22))
+        .sum()
+}
+
+/// Parse a `/proc/net/tcp` table and count ESTABLISHED sockets on a local port.
+fn count_established_on_port(table: &str, port: u16) -> usize {
+    const ESTABLISHED: &str = "01";
+    let port_hex = format!("{:04X}", port);
+
+    table
+        .lines()
+        .skip(1)
+        .filter(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            fields.len() > 3
+                && fields[3] == ESTABLISHED
+                && fields[1]
+                    .rsplit_once(':')
+                    .is_some_and(|(_, p)| p.eq_ignore_ascii_case(&port_hex))
+        })
+        .count()
+}
+
+fn network_bytes() -> u64 {
+    let networks = Networks::new_with_refreshed_list();
+    networks
+        .iter()
+        .filter(|(name, _)| name.as_str() != "lo")
+        .map(|(_, n)| n.total_received() + n.total_transmitted())
+        .sum()
+}
+
+/// Return the patterns that match a running process name or command line.
+fn matching_processes(patterns: &[String]) -> Vec<String> {
+    if patterns.is_empty() {
+        return Vec::new();
+    }
+
+    let sys = System::new_all();
+    let own_pid = std::process::id();
+    let commands: Vec<String> = sys
+        .processes()
+        .iter()
+        .filter(|(pid, _)| pid.as_u32() != own_pid)
+        .map(|(_, p)| {
+            let cmd = p
+                .cmd()
+                .iter()
+                .map(|a| a.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("{} {}", p.name().to_string_lossy(), cmd)
+        })
+        .collect();
+
+    patterns
+        .iter()
+        .filter(|pattern| commands.iter().any(|c| c.contains(pattern.as_str())))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> IdleConfig {
+        IdleConfig {
+            timeout_seconds: 3600,
+            grace_period_seconds: 600,
+            action: IdleAction::Shutdown,
+            ssh_sessions: true,
+            tunnels: true,
+            cpu_load_threshold: Some(20.0),
+            network_threshold_kbps: Some(50),
+            processes: vec!["cargo".to_string(), "go build".to_string()],
+        }
+    }
+
+    fn quiet_sample() -> Sample {
+        Sample {
+            api_idle_seconds: 3600,
+            ..Default::default()
+        }
+    }
+
+    fn active_names(signals: &[SignalReport]) -> Vec<&str> {
+        signals
+            .iter()
+            .filter(|s| s.active)
+            .map(|s| s.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_evaluate_quiet_box_is_idle() {
+        let signals = evaluate(&config(), &quiet_sample(), Some(0.0));
+        assert!(active_names(&signals).is_empty());
+        assert_eq!(signals.len(), 6);
+    }
+
+    #[test]
+    fn test_evaluate_each_signal() {
+        let cfg = config();
+
+        let sample = Sample {
+            terminals: 1,
+            ssh_connections: 1,
+            ..quiet_sample()
+        };
+        assert_eq!(
+            active_names(&evaluate(&cfg, &sample, None)),
+            ["ssh_sessions"]
+        );
+
+        let sample = Sample {
+            ssh_connections: 2,
+            ..quiet_sample()
+        };
+        assert_eq!(active_names(&evaluate(&cfg, &sample, None)), ["tunnels"]);
+
+        let sample = Sample {
+            cpu_load_percent: 85.0,
+            ..quiet_sample()
+        };
+        assert_eq!(active_names(&evaluate(&cfg, &sample, None)), ["cpu_load"]);
+
+        assert_eq!(
+            active_names(&evaluate(&cfg, &quiet_sample(), Some(100.0 * 1024.0))),
+            ["network"]
+        );
+
+        let sample = Sample {
+            matched_processes: vec!["cargo".to_string()],
+            ..quiet_sample()
+        };
+        assert_eq!(active_names(&evaluate(&cfg, &sample, None)), ["processes"]);
+
+        let sample = Sample {
+            api_idle_seconds: 5,
+            ..quiet_sample()
+        };
+        assert_eq!(active_names(&evaluate(&cfg, &sample, None)), ["agent_api"]);
+    }
+
+    #[test]
+    fn test_evaluate_disabled_signals_are_omitted() {
+        let cfg = IdleConfig {
+            ssh_sessions: false,
+            tunnels: false,
+            cpu_load_threshold: None,
+            network_threshold_kbps: None,
+            processes: Vec::new(),
+            ..config()
+        };
+        let sample = Sample {
+            terminals: 3,
+            cpu_load_percent: 100.0,
+            ..quiet_sample()
+        };
+        let signals = evaluate(&cfg, &sample, Some(1e9));
+        let names: Vec<&str> = signals.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["agent_api"]);
+        assert!(active_names(&signals).is_empty());
+    }
+
+    #[test]
+    fn test_decide() {
+        let cfg = config();
+        assert_eq!(decide(&cfg, 0, false), Decision::Wait);
+        assert_eq!(decide(&cfg, 2999, false), Decision::Wait);
+        assert_eq!(decide(&cfg, 3000, false), Decision::Warn);
+        assert_eq!(decide(&cfg, 3000, true), Decision::Wait);
+        assert_eq!(decide(&cfg, 3600, true), Decision::Act);
+
+        let no_grace = IdleConfig {
+            grace_period_seconds: 0,
+            ..cfg
+        };
+        assert_eq!(decide(&no_grace, 3599, false), Decision::Wait);
+        assert_eq!(decide(&no_grace, 3600, false), Decision::Act);
+    }
+
+    #[test]
+    fn test_engine_without_policy_never_acts() {
+        let mut engine = IdleEngine::new(None);
+        assert_eq!(engine.check(&quiet_sample()), Decision::Wait);
+        let report = engine.report().unwrap();
+        assert!(!report.enabled);
+        assert!(report.remaining_seconds.is_none());
+    }
+
+    #[test]
+    fn test_engine_acts_once_per_idle_period() {
+        let cfg = IdleConfig {
+            timeout_seconds: 0,
+            grace_period_seconds: 0,
+            ..config()
+        };
+        let mut engine = IdleEngine::new(Some(cfg));
+
+        assert_eq!(engine.check(&quiet_sample()), Decision::Act);
+        assert_eq!(engine.check(&quiet_sample()), Decision::Wait);
+
+        // Activity starts a new idle period
+        let busy = Sample {
+            terminals: 1,
+            ..quiet_sample()
+        };
+        engine.check(&busy);
+        assert_eq!(engine.check(&quiet_sample()), Decision::Act);
+    }
+
+    #[test]
+    fn test_engine_report_explains_activity() {
+        let mut engine = IdleEngine::new(Some(config()));
+        let sample = Sample {
+            matched_processes: vec!["go build".to_string()],
+            ..quiet_sample()
+        };
+        engine.check(&sample);
+
+        let report = engine.report().unwrap();
+        assert!(report.enabled);
+        assert!(!report.idle);
+        assert_eq!(report.remaining_seconds, Some(3600));
+        let processes = report
+            .signals
+            .iter()
+            .find(|s| s.name == "processes")
+            .unwrap();
+        assert!(processes.active);
+        assert_eq!(processes.detail, "running: go build");
+    }
+
+    #[test]
+    fn test_count_established_on_port() {
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1 1
+   1: 0100007F:1D97 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 2 1
+   2: 0A00000F:0016 0A000001:C350 01 00000000:00000000 02:00000000 00000000     0        0 3 1
+   3: 0A00000F:0016 0A000001:C351 01 00000000:00000000 02:00000000 00000000     0        0 4 1
+   4: 0100007F:1D97 0100007F:D2F0 01 00000000:00000000 00:00000000 00000000     0        0 5 1
+";
+        assert_eq!(count_established_on_port(table, 22), 2);
+        assert_eq!(count_established_on_port(table, 7575), 1);
+        assert_eq!(count_established_on_port(table, 80), 0);
+    }
+
+    #[test]
+    fn test_count_terminals() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["ptmx", "0", "3"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        assert_eq!(count_terminals(dir.path()), 2);
+        assert_eq!(count_terminals(&dir.path().join("missing")), 0);
+    }
+
+    #[test]
+    fn test_idle_config_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("idle.json");
+        std::fs::write(
+            &path,
+            r#"{"timeout_seconds": 7200, "grace_period_seconds": 600, "action": "none",
+                "ssh_sessions": true, "cpu_load_threshold": null, "processes": ["node"]}"#,
+        )
+        .unwrap();
+
+        let config = IdleConfig::load_from(&path).unwrap();
+        assert_eq!(config.timeout_seconds, 7200);
+        assert_eq!(config.action, IdleAction::None);
+        assert!(config.ssh_sessions);
+        assert!(!config.tunnels);
+        assert!(config.cpu_load_threshold.is_none());
+        assert_eq!(config.processes, ["node"]);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(IdleConfig::load_from(&path).is_none());
+        assert!(IdleConfig::load_from(&dir.path().join("missing.json")).is_none());
+    }
+}
@@ -0,0 +1,305 @@
+//! Maximum lifetime.
+//!
+//! A hard deadline for the environment, independent of the idle policy: a
+//! stuck `cargo watch` or agent loop keeps the box busy forever, but never
+//! past its deadline. Logged-in terminals get `wall` warnings at fixed
+//! points before the deadline, and `POST /lifetime/extend` (`spuff extend`)
+//! pushes it back.
+//!
+//! The deadline is read from `/opt/spuff/lifetime.json`, written by
+//! cloud-init and rewritten on every extension so it survives reboots and
+//! agent restarts. Without it (or when it holds `null`) the environment has
+//! no maximum lifetime. A deadline that already passed when the agent starts,
+//! e.g. after `spuff start`, gets [`STARTUP_GRACE_MINUTES`] before acting so
+//! users are warned first.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::idle::IdleAction;
+
+/// Deadline file written by cloud-init.
+const LIFETIME_FILE: &str = "/opt/spuff/lifetime.json";
+
+/// Time given at startup when the deadline has already passed.
+const STARTUP_GRACE_MINUTES: i64 = 15;
+
+/// Minutes before the deadline at which logged-in users are warned.
+const WARNING_MINUTES: [i64; 5] = [60, 30, 10, 5, 1];
+
+/// Lifetime limit, as written to `/opt/spuff/lifetime.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifetimeConfig {
+    pub deadline: DateTime<Utc>,
+    pub action: IdleAction,
+}
+
+impl LifetimeConfig {
+    fn load_from(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Invalid lifetime limit {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+}
+
+/// Response of the `/lifetime` endpoints.
+#[derive(Debug, Clone, Serialize)]
+pub struct LifetimeReport {
+    /// False when no limit is set; the environment can run forever.
+    pub enabled: bool,
+    pub deadline: Option<DateTime<Utc>>,
+    pub remaining_seconds: Option<i64>,
+    pub action: Option<IdleAction>,
+}
+
+/// Outcome of a lifetime check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifetimeDecision {
+    Wait,
+    /// Crossed a warning point; the value is in minutes.
+    Warn(i64),
+    /// Deadline reached; run the configured action.
+    Act,
+}
+
+/// Decide what to do `remaining_seconds` before the deadline, given the
+/// last warning point already announced.
+pub fn decide(remaining_seconds: i64, last_warning: Option<i64>) -> LifetimeDecision {
+    if remaining_seconds <= 0 {
+        return LifetimeDecision::Act;
+    }
+
+    // Tightest warning point reached; checks only run once a minute, so
+    // points skipped in between are not announced separately
+    let due = WARNING_MINUTES
+        .iter()
+        .rev()
+        .find(|minutes| remaining_seconds <= **minutes * 60)
+        .copied();
+
+    match due {
+        Some(minutes) if last_warning.is_none_or(|last| minutes < last) => {
+            LifetimeDecision::Warn(minutes)
+        }
+        _ => LifetimeDecision::Wait,
+    }
+}
+
+/// Stateful engine; one per agent.
+pub struct LifetimeEngine {
+    config: Option<LifetimeConfig>,
+    path: PathBuf,
+    last_warning: Option<i64>,
+    acted: bool,
+}
+
+impl LifetimeEngine {
+    /// Load the limit from `/opt/spuff/lifetime.json`, if present.
+    pub fn load() -> Self {
+        Self::load_from(PathBuf::from(LIFETIME_FILE), Utc::now())
+    }
+
+    fn load_from(path: PathBuf, now: DateTime<Utc>) -> Self {
+        let mut config = LifetimeConfig::load_from(&path);
+
+        if let Some(config) = config.as_mut().filter(|c| c.deadline <= now) {
+            let grace = now + chrono::Duration::minutes(STARTUP_GRACE_MINUTES);
+            tracing::warn!(
+                "Maximum lifetime already reached at {}; acting at {} instead",
+                config.deadline,
+                grace
+            );
+            config.deadline = grace;
+        }
+
+        Self {
+            config,
+            path,
+            last_warning: None,
+            acted: false,
+        }
+    }
+
+    pub fn report(&self, now: DateTime<Utc>) -> LifetimeReport {
+        match &self.config {
+            Some(config) => LifetimeReport {
+                enabled: true,
+                deadline: Some(config.deadline),
+                remaining_seconds: Some((config.deadline - now).num_seconds().max(0)),
+                action: Some(config.action),
+            },
+            None => LifetimeReport {
+                enabled: false,
+                deadline: None,
+                remaining_seconds: None,
+                action: None,
+            },
+        }
+    }
+
+    /// Check the deadline and return what should happen now.
+    pub fn check(&mut self, now: DateTime<Utc>) -> LifetimeDecision {
+        let Some(config) = &self.config else {
+            return LifetimeDecision::Wait;
+        };
+
+        match decide((config.deadline - now).num_seconds(), self.last_warning) {
+            LifetimeDecision::Warn(minutes) => {
+                self.last_warning = Some(minutes);
+                LifetimeDecision::Warn(minutes)
+            }
+            // Only act once (action `none` would repeat)
+            LifetimeDecision::Act if self.acted => LifetimeDecision::Wait,
+            LifetimeDecision::Act => {
+                self.acted = true;
+                LifetimeDecision::Act
+            }
+            LifetimeDecision::Wait => LifetimeDecision::Wait,
+        }
+    }
+
+    /// Push the deadline back by `seconds`, counted from now if it has
+    /// already passed, and persist it.
+    pub fn extend(&mut self, seconds: u64, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+        let Some(config) = &mut self.config else {
+            return Err("This environment has no maximum lifetime".to_string());
+        };
+
+        config.deadline = config.deadline.max(now) + chrono::Duration::seconds(seconds as i64);
+        config
+            .save_to(&self.path)
+            .map_err(|e| format!("Failed to save {}: {}", self.path.display(), e))?;
+
+        self.last_warning = None;
+        self.acted = false;
+        Ok(config.deadline)
+    }
+}
+
+/// Warn everyone logged in that the box reaches its maximum lifetime soon.
+pub async fn broadcast_warning(minutes: i64) {
+    crate::idle::wall(&format!(
+        "spuff: this environment reaches its maximum lifetime and will shut down in ~{} minute(s).\n\
+         Run 'spuff extend <duration>' on your machine to keep it longer.",
+        minutes
+    ))
+    .await;
+}
+
+/// Run the configured action.
+pub async fn run_action(action: IdleAction) {
+    match action {
+        IdleAction::Shutdown => {
+            tracing::warn!("Maximum lifetime reached, shutting down");
+            crate::idle::power_off().await;
+        }
+        IdleAction::None => {
+            tracing::info!("Maximum lifetime reached (no action configured)");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decide_escalates() {
+        assert_eq!(decide(3 * 3600, None), LifetimeDecision::Wait);
+        assert_eq!(decide(3600, None), LifetimeDecision::Warn(60));
+        assert_eq!(decide(3500, Some(60)), LifetimeDecision::Wait);
+        assert_eq!(decide(1800, Some(60)), LifetimeDecision::Warn(30));
+        assert_eq!(decide(240, Some(30)), LifetimeDecision::Warn(5));
+        assert_eq!(decide(60, Some(5)), LifetimeDecision::Warn(1));
+        assert_eq!(decide(30, Some(1)), LifetimeDecision::Wait);
+        assert_eq!(decide(0, Some(1)), LifetimeDecision::Act);
+        assert_eq!(decide(-10, None), LifetimeDecision::Act);
+    }
+
+    #[test]
+    fn test_engine_without_limit_never_acts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut engine = LifetimeEngine::load_from(dir.path().join("lifetime.json"), Utc::now());
+
+        assert_eq!(engine.check(Utc::now()), LifetimeDecision::Wait);
+        assert!(!engine.report(Utc::now()).enabled);
+        assert!(engine.extend(3600, Utc::now()).is_err());
+    }
+
+    #[test]
+    fn test_engine_acts_once_and_extends() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lifetime.json");
+        let now = Utc::now();
+        std::fs::write(
+            &path,
+            format!(
+                r#"{{"deadline": "{}", "action": "shutdown"}}"#,
+                (now + chrono::Duration::minutes(10)).to_rfc3339()
+            ),
+        )
+        .unwrap();
+
+        let mut engine = LifetimeEngine::load_from(path.clone(), now);
+        assert_eq!(engine.check(now), LifetimeDecision::Warn(10));
+        assert_eq!(engine.check(now), LifetimeDecision::Wait);
+
+        let later = now + chrono::Duration::minutes(11);
+        assert_eq!(engine.check(later), LifetimeDecision::Act);
+        assert_eq!(engine.check(later), LifetimeDecision::Wait);
+
+        // An expired deadline is extended from now, and warnings start over
+        let deadline = engine.extend(7200, later).unwrap();
+        assert_eq!(deadline, later + chrono::Duration::hours(2));
+        assert_eq!(engine.report(later).remaining_seconds, Some(7200));
+        assert_eq!(engine.check(deadline), LifetimeDecision::Act);
+
+        // The new deadline survives an agent restart
+        let reloaded = LifetimeEngine::load_from(path, later);
+        assert_eq!(reloaded.report(later).deadline, Some(deadline));
+    }
+
+    #[test]
+    fn test_engine_null_limit_is_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lifetime.json");
+        std::fs::write(&path, "null\n").unwrap();
+
+        let engine = LifetimeEngine::load_from(path, Utc::now());
+        assert!(!engine.report(Utc::now()).enabled);
+    }
+
+    #[test]
+    fn test_engine_expired_at_startup_gets_grace() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lifetime.json");
+        let now = Utc::now();
+        std::fs::write(
+            &path,
+            format!(
+                r#"{{"deadline": "{}", "action": "shutdown"}}"#,
+                (now - chrono::Duration::hours(3)).to_rfc3339()
+            ),
+        )
+        .unwrap();
+
+        let mut engine = LifetimeEngine::load_from(path, now);
+        assert_eq!(engine.check(now), LifetimeDecision::Warn(30));
+        assert_eq!(
+            engine.check(now + chrono::Duration::minutes(STARTUP_GRACE_MINUTES)),
+            LifetimeDecision::Act
+        );
+    }
+}
@@ -3,9 +3,13 @@
 //! This agent runs on provisioned VMs and provides:
 //! - System metrics (CPU, memory, disk, load)
 //! - Process monitoring
+//! - Command execution, buffered (`/exec`) or streamed (see [`exec`])
+//! - Listening TCP ports (see [`ports`]) for automatic port forwarding
+//! - The VM side of `sync` volumes (see [`sync`])
 //! - Log file access
 //! - Cloud-init status
-//! - Activity tracking for idle detection
+//! - Idle policy engine (see [`idle`]) with a `/idle` explanation endpoint
+//! - Maximum lifetime (see [`lifetime`]), extendable via `/lifetime/extend`
 //! - Activity log for transparency
 //! - Devtools installation management
 //!
@@ -25,9 +29,14 @@
 
 mod devtools;
 mod docker_manager;
+mod exec;
+mod idle;
+mod lifetime;
 mod metrics;
+mod ports;
 mod project_setup;
 mod routes;
+mod sync;
 mod volume_manager;
 
 use std::collections::VecDeque;
@@ -40,8 +49,12 @@ use tokio::sync::RwLock;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::devtools::DevToolsManager;
+use crate::exec::RunningExecs;
+use crate::idle::{Decision, IdleConfig, IdleEngine, Sample};
+use crate::lifetime::{LifetimeDecision, LifetimeEngine};
 use crate::metrics::SystemMetrics;
 use crate::project_setup::ProjectSetupManager;
+use crate::sync::HashCache;
 
 /// Maximum number of activity log entries to keep in memory
 const MAX_ACTIVITY_LOG_ENTRIES: usize = 100;
@@ -83,6 +96,14 @@ pub struct AppState {
     pub devtools: DevToolsManager,
     /// Project setup manager (from spuff.yaml)
     pub project_setup: ProjectSetupManager,
+    /// Idle policy engine
+    pub idle: RwLock<IdleEngine>,
+    /// Maximum lifetime deadline
+    pub lifetime: RwLock<LifetimeEngine>,
+    /// Streamed commands still running, for cancellation
+    pub execs: RunningExecs,
+    /// File hashes of synced trees, reused while files are unchanged
+    pub sync_hashes: HashCache,
 }
 
 impl AppState {
@@ -96,6 +117,10 @@ impl AppState {
             activity_log: RwLock::new(VecDeque::with_capacity(MAX_ACTIVITY_LOG_ENTRIES)),
             devtools: DevToolsManager::new(username.clone()),
             project_setup: ProjectSetupManager::new(username),
+            idle: RwLock::new(IdleEngine::new(IdleConfig::load())),
+            lifetime: RwLock::new(LifetimeEngine::load()),
+            execs: RunningExecs::default(),
+            sync_hashes: HashCache::default(),
         }
     }
 
@@ -162,6 +187,99 @@ impl AppState {
     }
 }
 
+/// Sample the system, update the idle engine and act on its decision.
+async fn check_idle(state: &Arc<AppState>) {
+    let config = state.idle.read().await.config().cloned();
+    let api_idle_seconds = state.idle_seconds().await;
+    let sample = match tokio::task::spawn_blocking(move || {
+        Sample::collect(config.as_ref(), api_idle_seconds)
+    })
+    .await
+    {
+        Ok(sample) => sample,
+        Err(e) => {
+            tracing::warn!("Idle sampling failed: {}", e);
+            return;
+        }
+    };
+
+    let (decision, report) = {
+        let mut engine = state.idle.write().await;
+        let decision = engine.check(&sample);
+        (decision, engine.report().cloned())
+    };
+    let Some(report) = report else {
+        return;
+    };
+
+    match decision {
+        Decision::Wait => {}
+        Decision::Warn => {
+            let remaining = report.remaining_seconds.unwrap_or_default();
+            state
+                .log_activity(
+                    "idle_warning",
+                    Some(format!(
+                        "Idle for {}s, {}s left",
+                        report.idle_seconds, remaining
+                    )),
+                )
+                .await;
+            idle::broadcast_warning(remaining).await;
+        }
+        Decision::Act => {
+            if let Some(action) = report.action {
+                state
+                    .log_activity(
+                        "idle_timeout",
+                        Some(format!(
+                            "Idle for {}s, running action {:?}",
+                            report.idle_seconds, action
+                        )),
+                    )
+                    .await;
+                idle::run_action(action).await;
+            }
+        }
+    }
+}
+
+/// Check the maximum lifetime and act on it; busy or not.
+async fn check_lifetime(state: &Arc<AppState>) {
+    let now = chrono::Utc::now();
+    let (decision, report) = {
+        let mut engine = state.lifetime.write().await;
+        (engine.check(now), engine.report(now))
+    };
+
+    match decision {
+        LifetimeDecision::Wait => {}
+        LifetimeDecision::Warn(minutes) => {
+            state
+                .log_activity(
+                    "lifetime_warning",
+                    Some(format!("Maximum lifetime reached in {} minute(s)", minutes)),
+                )
+                .await;
+            lifetime::broadcast_warning(minutes).await;
+        }
+        LifetimeDecision::Act => {
+            if let Some(action) = report.action {
+                state
+                    .log_activity(
+                        "lifetime_expired",
+                        Some(format!(
+                            "Maximum lifetime reached, running action {:?}",
+                            action
+                        )),
+                    )
+                    .await;
+                lifetime::run_action(action).await;
+            }
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing
@@ -231,16 +349,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
-    // Background task to write heartbeat file for idle detection
-    let heartbeat_state = state.clone();
+    // Background task to evaluate the idle policy and the maximum lifetime
+    let idle_state = state.clone();
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(idle::CHECK_INTERVAL_SECS));
         loop {
             interval.tick().await;
-            let idle = heartbeat_state.idle_seconds().await;
-            if let Err(e) = std::fs::write("/tmp/spuff-agent-heartbeat", format!("{}", idle)) {
-                tracing::debug!("Failed to write heartbeat file: {}", e);
-            }
+            check_lifetime(&idle_state).await;
+            check_idle(&idle_state).await;
         }
     });
 
@@ -0,0 +1,191 @@
+//! Listening TCP ports.
+//!
+//! Reads the kernel socket tables in `/proc/net/tcp{,6}` and maps each
+//! listening socket to the process owning it through the `socket:[inode]`
+//! links in `/proc/<pid>/fd`, like `ss -ltnp`. Used by `spuff tunnel --auto`
+//! to forward dev servers as they start.
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+use serde::Serialize;
+
+/// TCP state code for LISTEN in `/proc/net/tcp`.
+const LISTEN: &str = "0A";
+
+/// A listening TCP socket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ListeningPort {
+    pub port: u16,
+    /// Address the socket is bound to (`0.0.0.0`, `::`, `127.0.0.1`, ...)
+    pub address: String,
+    pub pid: Option<u32>,
+    /// Process name (`/proc/<pid>/comm`)
+    pub process: Option<String>,
+    /// Full command line
+    pub command: Option<String>,
+}
+
+/// All listening TCP sockets, sorted by port.
+pub fn listening_ports() -> Vec<ListeningPort> {
+    let sockets: Vec<(String, u16, u64)> = [("/proc/net/tcp", false), ("/proc/net/tcp6", true)]
+        .iter()
+        .filter_map(|(path, ipv6)| {
+            std::fs::read_to_string(path)
+                .ok()
+                .map(|table| parse_listening(&table, *ipv6))
+        })
+        .flatten()
+        .collect();
+
+    let owners = socket_owners(Path::new("/proc"));
+
+    let mut ports: Vec<ListeningPort> = sockets
+        .into_iter()
+        .map(|(address, port, inode)| {
+            let pid = owners.get(&inode).copied();
+            ListeningPort {
+                port,
+                address,
+                pid,
+                process: pid.and_then(|pid| read_proc(pid, "comm")),
+                command: pid.and_then(|pid| read_proc(pid, "cmdline")),
+            }
+        })
+        .collect();
+
+    ports.sort_by(|a, b| (a.port, &a.address).cmp(&(b.port, &b.address)));
+    ports.dedup_by(|a, b| a.port == b.port && a.address == b.address);
+    ports
+}
+
+/// Parse a `/proc/net/tcp{,6}` table into `(address, port, inode)` of
+/// listening sockets.
+fn parse_listening(table: &str, ipv6: bool) -> Vec<(String, u16, u64)> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() <= 9 || fields[3] != LISTEN {
+                return None;
+            }
+            let (address, port) = fields[1].rsplit_once(':')?;
+            let address = if ipv6 {
+                parse_ipv6(address)?.to_string()
+            } else {
+                parse_ipv4(address)?.to_string()
+            };
+            let port = u16::from_str_radix(port, 16).ok()?;
+            let inode = fields[9].parse().ok()?;
+            Some((address, port, inode))
+        })
+        .collect()
+}
+
+/// The kernel prints addresses as native-endian 32-bit words.
+fn parse_ipv4(hex: &str) -> Option<Ipv4Addr> {
+    let word = u32::from_str_radix(hex, 16).ok()?;
+    Some(Ipv4Addr::from(word.to_ne_bytes()))
+}
+
+fn parse_ipv6(hex: &str) -> Option<Ipv6Addr> {
+    if hex.len() != 32 {
+        return None;
+    }
+    let mut bytes = [0u8; 16];
+    for (i, chunk) in bytes.chunks_mut(4).enumerate() {
+        let word = u32::from_str_radix(&hex[i * 8..(i + 1) * 8], 16).ok()?;
+        chunk.copy_from_slice(&word.to_ne_bytes());
+    }
+    Some(Ipv6Addr::from(bytes))
+}
+
+/// Map socket inodes to the pid holding them open.
+fn socket_owners(proc_dir: &Path) -> HashMap<u64, u32> {
+    let mut owners = HashMap::new();
+    let Ok(entries) = std::fs::read_dir(proc_dir) else {
+        return owners;
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let Some(pid) = entry.file_name().to_str().and_then(|n| n.parse().ok()) else {
+            continue;
+        };
+        let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        for fd in fds.filter_map(|e| e.ok()) {
+            if let Some(inode) = std::fs::read_link(fd.path())
+                .ok()
+                .and_then(|target| parse_socket_link(&target.to_string_lossy()))
+            {
+                owners.entry(inode).or_insert(pid);
+            }
+        }
+    }
+
+    owners
+}
+
+/// Inode of a `socket:[12345]` fd link.
+fn parse_socket_link(target: &str) -> Option<u64> {
+    target
+        .strip_prefix("socket:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+fn read_proc(pid: u32, file: &str) -> Option<String> {
+    let content = std::fs::read(format!("/proc/{}/{}", pid, file)).ok()?;
+    // cmdline separates arguments with NULs
+    let text = String::from_utf8_lossy(&content)
+        .replace('\0', " ")
+        .trim()
+        .to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TCP: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 15321 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:0BB8 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 48211 1 0000000000000000 100 0 0 10 0
+   2: 0100007F:0BB8 0100007F:D2F4 01 00000000:00000000 00:00000000 00000000  1000        0 48300 1 0000000000000000 20 4 30 10 -1
+";
+
+    const TCP6: &str = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:1F90 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 51234 1 0000000000000000 100 0 0 10 0
+";
+
+    #[test]
+    fn test_parse_listening_ipv4() {
+        let sockets = parse_listening(TCP, false);
+
+        // The ESTABLISHED connection is skipped
+        assert_eq!(
+            sockets,
+            vec![
+                ("0.0.0.0".to_string(), 22, 15321),
+                ("127.0.0.1".to_string(), 3000, 48211),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_listening_ipv6() {
+        let sockets = parse_listening(TCP6, true);
+        assert_eq!(sockets, vec![("::".to_string(), 8080, 51234)]);
+    }
+
+    #[test]
+    fn test_parse_socket_link() {
+        assert_eq!(parse_socket_link("socket:[48211]"), Some(48211));
+        assert_eq!(parse_socket_link("pipe:[48211]"), None);
+        assert_eq!(parse_socket_link("/dev/null"), None);
+    }
+}
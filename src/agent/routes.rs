@@ -9,11 +9,12 @@ use std::path::Path;
 use std::sync::Arc;
 
 use axum::{
+    body::Body,
     extract::{FromRequestParts, Query},
-    http::{request::Parts, StatusCode},
+    http::{header, request::Parts, StatusCode},
     response::{
         sse::{Event, KeepAlive, Sse},
-        IntoResponse,
+        IntoResponse, Response,
     },
     routing::{get, post},
     Json, Router,
@@ -26,6 +27,7 @@ use std::time::Duration;
 use crate::devtools::DevToolsConfig;
 use crate::docker_manager::{ComposeManager, DockerManager};
 use crate::metrics::get_top_processes;
+use crate::ports::listening_ports;
 use crate::project_setup::ProjectSetupManager;
 use crate::volume_manager::AgentVolumeManager;
 use crate::AppState;
@@ -45,9 +47,15 @@ pub fn create_routes() -> Router<Arc<AppState>> {
         .route("/metrics", get(metrics))
         .route("/status", get(status))
         .route("/processes", get(processes))
+        .route("/ports", get(ports))
         .route("/exec", post(exec))
+        .route("/exec/stream", post(exec_stream))
+        .route("/exec/cancel", post(exec_cancel))
         .route("/exec-log", get(exec_log))
         .route("/heartbeat", post(heartbeat))
+        .route("/idle", get(idle_status))
+        .route("/lifetime", get(lifetime_status))
+        .route("/lifetime/extend", post(lifetime_extend))
         .route("/logs", get(logs))
         .route("/logs/stream", get(logs_stream))
         .route("/cloud-init", get(cloud_init_status))
@@ -63,6 +71,9 @@ pub fn create_routes() -> Router<Arc<AppState>> {
         .route("/volumes", get(volumes_list))
         .route("/volumes/status", get(volumes_status))
         .route("/volumes/unmount", post(volumes_unmount))
+        // Sync volumes
+        .route("/sync/manifest", post(sync_manifest))
+        .route("/sync/apply", post(sync_apply))
         // Graceful shutdown
         .route("/shutdown", post(shutdown))
         // Docker management
@@ -152,6 +163,69 @@ async fn health() -> impl IntoResponse {
     }))
 }
 
+/// GET /idle - Idle policy state (requires authentication)
+///
+/// Reports whether the environment is considered idle, for how long, and
+/// which signals are keeping it alive. Does not count as activity itself.
+async fn idle_status(AuthenticatedState(state): AuthenticatedState) -> impl IntoResponse {
+    let engine = state.idle.read().await;
+    match engine.report() {
+        Some(report) => Json(report.clone()).into_response(),
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiError::new("Idle policy has not been evaluated yet")),
+        )
+            .into_response(),
+    }
+}
+
+/// GET /lifetime - Maximum lifetime deadline (requires authentication)
+async fn lifetime_status(AuthenticatedState(state): AuthenticatedState) -> impl IntoResponse {
+    let engine = state.lifetime.read().await;
+    Json(engine.report(chrono::Utc::now()))
+}
+
+/// Request body for the /lifetime/extend endpoint.
+#[derive(Debug, Deserialize)]
+struct LifetimeExtendRequest {
+    seconds: u64,
+}
+
+/// POST /lifetime/extend - Push the maximum lifetime back (requires authentication)
+async fn lifetime_extend(
+    AuthenticatedState(state): AuthenticatedState,
+    Json(req): Json<LifetimeExtendRequest>,
+) -> Result<Json<crate::lifetime::LifetimeReport>, (StatusCode, Json<ApiError>)> {
+    if req.seconds == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new("Extension must be longer than 0 seconds")),
+        ));
+    }
+
+    let now = chrono::Utc::now();
+    let report = {
+        let mut engine = state.lifetime.write().await;
+        let deadline = engine
+            .extend(req.seconds, now)
+            .map_err(|e| (StatusCode::CONFLICT, Json(ApiError::new(e))))?;
+        tracing::info!("Maximum lifetime extended to {}", deadline);
+        engine.report(now)
+    };
+
+    state
+        .log_activity(
+            "lifetime_extended",
+            Some(format!(
+                "+{}s, now until {}",
+                req.seconds,
+                report.deadline.unwrap_or(now).to_rfc3339()
+            )),
+        )
+        .await;
+    Ok(Json(report))
+}
+
 /// GET /metrics - System metrics (requires authentication)
 ///
 /// Returns current CPU, memory, disk, and load metrics.
@@ -211,6 +285,17 @@ async fn processes(AuthenticatedState(state): AuthenticatedState) -> impl IntoRe
     Json(get_top_processes(10))
 }
 
+/// GET /ports - Listening TCP ports (requires authentication)
+///
+/// Returns every listening TCP socket with the process that owns it.
+/// Polled by `spuff tunnel --auto`, so it does not reset the idle timer.
+async fn ports(AuthenticatedState(_state): AuthenticatedState) -> impl IntoResponse {
+    let ports = tokio::task::spawn_blocking(listening_ports)
+        .await
+        .unwrap_or_default();
+    Json(ports)
+}
+
 /// Request body for the /exec endpoint.
 #[derive(Debug, Deserialize)]
 struct ExecRequest {
@@ -335,6 +420,55 @@ async fn exec(
     }
 }
 
+/// POST /exec/stream - Execute a command, streaming its output (requires authentication)
+///
+/// The body is a JSON request line followed by the command's stdin; the
+/// response is NDJSON frames ending with the exit code. See [`crate::exec`].
+async fn exec_stream(
+    AuthenticatedState(state): AuthenticatedState,
+    body: Body,
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
+    state.update_activity().await;
+
+    let (request, stdin_head, stdin_rest) = crate::exec::read_request(body)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ApiError::new(e))))?;
+
+    let frames = crate::exec::run(state, request, stdin_head, stdin_rest);
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(frames),
+    )
+        .into_response())
+}
+
+/// Request body for the /exec/cancel endpoint.
+#[derive(Debug, Deserialize)]
+struct ExecCancelRequest {
+    id: String,
+}
+
+/// POST /exec/cancel - Kill a streamed command's process group (requires authentication)
+async fn exec_cancel(
+    AuthenticatedState(state): AuthenticatedState,
+    Json(req): Json<ExecCancelRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ApiError>)> {
+    if !state.execs.cancel(&req.id) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new(format!(
+                "No running command with id {}",
+                req.id
+            ))),
+        ));
+    }
+
+    state
+        .log_activity("exec_cancelled", Some(format!("id={}", req.id)))
+        .await;
+    Ok(Json(serde_json::json!({ "cancelled": req.id })))
+}
+
 /// Query parameters for the /exec-log endpoint.
 #[derive(Debug, Deserialize)]
 struct ExecLogQuery {
@@ -890,6 +1024,53 @@ async fn volumes_unmount(
     }
 }
 
+/// Largest `/sync/apply` body accepted; the CLI sends batches of about 4 MiB.
+const SYNC_APPLY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// POST /sync/manifest - List a synced tree with file hashes (requires authentication)
+///
+/// Polled by the CLI's sync daemon, so it does not reset the idle timer.
+async fn sync_manifest(
+    AuthenticatedState(state): AuthenticatedState,
+    Json(req): Json<crate::sync::ManifestRequest>,
+) -> Result<Json<Vec<crate::sync::ManifestEntry>>, (StatusCode, Json<ApiError>)> {
+    let result =
+        tokio::task::spawn_blocking(move || crate::sync::manifest(&state.sync_hashes, &req))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|result| result);
+
+    match result {
+        Ok(entries) => Ok(Json(entries)),
+        Err(e) => Err((StatusCode::BAD_REQUEST, Json(ApiError::new(e)))),
+    }
+}
+
+/// POST /sync/apply - Apply file changes to a synced tree (requires authentication)
+///
+/// The body is NDJSON: the tree's root, then each change followed by its
+/// delta ops. See [`crate::sync`].
+async fn sync_apply(
+    AuthenticatedState(state): AuthenticatedState,
+    body: Body,
+) -> Result<Json<Vec<crate::sync::ChangeResult>>, (StatusCode, Json<ApiError>)> {
+    state.update_activity().await;
+
+    let body = axum::body::to_bytes(body, SYNC_APPLY_LIMIT)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ApiError::new(e.to_string()))))?;
+
+    let result = tokio::task::spawn_blocking(move || crate::sync::apply(&state.sync_hashes, &body))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result);
+
+    match result {
+        Ok(results) => Ok(Json(results)),
+        Err(e) => Err((StatusCode::BAD_REQUEST, Json(ApiError::new(e)))),
+    }
+}
+
 // ============================================================================
 // Docker Container Management
 // ============================================================================
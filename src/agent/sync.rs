@@ -0,0 +1,575 @@
+//! Receiving end of `sync` volumes.
+//!
+//! The CLI's sync daemon lists the VM's copy of a synced tree with
+//! `/sync/manifest` and sends changes to `/sync/apply` as deltas against the
+//! version it last saw. Both sides include the same chunking and ignore
+//! code, so files are cut and filtered identically.
+//!
+//! The agent runs as root: files and directories it creates get the owner
+//! of the sync root, which the CLI creates as the SSH user.
+
+#[allow(dead_code)]
+#[path = "../volume/drivers/sync/chunker.rs"]
+mod chunker;
+#[path = "../volume/drivers/sync/tree.rs"]
+mod tree;
+
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use chunker::{Chunk, Op};
+use tree::{IgnoreRules, TreeFile};
+
+/// Suffix of the VM's version of a file kept after a conflict.
+const CONFLICT_SUFFIX: &str = ".spuff-conflict";
+
+/// Suffix of files being written.
+const PART_SUFFIX: &str = ".spuff-part";
+
+/// Request body for `/sync/manifest`.
+#[derive(Debug, Deserialize)]
+pub struct ManifestRequest {
+    /// Absolute path of the synced tree
+    pub root: String,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default = "default_true")]
+    pub gitignore: bool,
+    /// Include each file's chunks
+    #[serde(default)]
+    pub chunks: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A file in the tree.
+#[derive(Debug, Serialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub mtime: u64,
+    pub mode: u32,
+    pub hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunks: Option<Vec<Chunk>>,
+}
+
+/// First line of an `/sync/apply` body.
+#[derive(Debug, Deserialize)]
+struct ApplyHeader {
+    root: String,
+}
+
+/// A change, followed by `file.ops` op lines when it writes the file.
+#[derive(Debug, Deserialize)]
+struct ChangeHeader {
+    path: String,
+    /// Hash the file must have now (`None`: must not exist)
+    base: Option<String>,
+    /// Keep the current version as `<path>.spuff-conflict`
+    #[serde(default)]
+    backup: bool,
+    /// New content, or `None` to delete
+    file: Option<FileHeader>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FileHeader {
+    hash: String,
+    mode: u32,
+    /// Nanoseconds since the epoch
+    mtime: u64,
+    ops: usize,
+}
+
+/// What happened to a change.
+#[derive(Debug, Serialize)]
+pub struct ChangeResult {
+    pub path: String,
+    /// `applied`, `deleted`, `conflict` (not at its base version, left
+    /// alone) or `error`
+    pub result: &'static str,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub backed_up: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl ChangeResult {
+    fn new(path: &str, result: &'static str, backed_up: bool) -> Self {
+        Self {
+            path: path.to_string(),
+            result,
+            backed_up,
+            message: None,
+        }
+    }
+}
+
+/// Hash and chunks of a file, valid while its size and mtime don't change.
+struct Cached {
+    size: u64,
+    mtime: u64,
+    hash: String,
+    chunks: Vec<Chunk>,
+}
+
+/// Hashes of files already read, so listing a large tree again only reads
+/// what changed.
+#[derive(Default)]
+pub struct HashCache {
+    entries: Mutex<HashMap<PathBuf, Cached>>,
+}
+
+impl HashCache {
+    fn hash(&self, path: &Path, file: &TreeFile) -> std::io::Result<(String, Vec<Chunk>)> {
+        if let Some(cached) = self.entries.lock().unwrap().get(path) {
+            if cached.size == file.size && cached.mtime == file.mtime {
+                return Ok((cached.hash.clone(), cached.chunks.clone()));
+            }
+        }
+
+        let data = std::fs::read(path)?;
+        let hash = chunker::file_hash(&data);
+        let chunks = chunker::chunk(&data);
+        self.entries.lock().unwrap().insert(
+            path.to_path_buf(),
+            Cached {
+                size: file.size,
+                mtime: file.mtime,
+                hash: hash.clone(),
+                chunks: chunks.clone(),
+            },
+        );
+        Ok((hash, chunks))
+    }
+
+    fn forget(&self, path: &Path) {
+        self.entries.lock().unwrap().remove(path);
+    }
+
+    /// Drop entries under `root` that aren't in `seen`.
+    fn prune(&self, root: &Path, seen: &HashSet<PathBuf>) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|path, _| !path.starts_with(root) || seen.contains(path));
+    }
+}
+
+/// List the files of a synced tree.
+pub fn manifest(
+    cache: &HashCache,
+    request: &ManifestRequest,
+) -> Result<Vec<ManifestEntry>, String> {
+    let root = check_root(&request.root)?;
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+
+    let rules = IgnoreRules::new(&request.exclude, request.gitignore);
+    let files = tree::walk(&root, &rules)
+        .map_err(|e| format!("Failed to list {}: {}", root.display(), e))?;
+
+    let mut entries = Vec::with_capacity(files.len());
+    let mut seen = HashSet::new();
+    for file in files {
+        let path = root.join(&file.path);
+        // Skip files removed since the walk
+        let Ok((hash, chunks)) = cache.hash(&path, &file) else {
+            continue;
+        };
+        seen.insert(path);
+        entries.push(ManifestEntry {
+            path: file.path,
+            size: file.size,
+            mtime: file.mtime,
+            mode: file.mode,
+            hash,
+            chunks: request.chunks.then_some(chunks),
+        });
+    }
+    cache.prune(&root, &seen);
+
+    Ok(entries)
+}
+
+/// Apply an NDJSON batch of changes, returning one result per change.
+pub fn apply(cache: &HashCache, body: &[u8]) -> Result<Vec<ChangeResult>, String> {
+    let mut lines = body
+        .split(|&byte| byte == b'\n')
+        .filter(|line| !line.is_empty());
+
+    let header: ApplyHeader = parse_line(lines.next().ok_or("Empty request")?)?;
+    let root = check_root(&header.root)?;
+    std::fs::create_dir_all(&root)
+        .map_err(|e| format!("Failed to create {}: {}", root.display(), e))?;
+    let owner = std::fs::metadata(&root)
+        .map(|m| (m.uid(), m.gid()))
+        .map_err(|e| format!("Failed to stat {}: {}", root.display(), e))?;
+
+    let mut results = Vec::new();
+    while let Some(line) = lines.next() {
+        let change: ChangeHeader = parse_line(line)?;
+        let op_count = change.file.as_ref().map_or(0, |file| file.ops);
+        let ops = (0..op_count)
+            .map(|_| parse_line::<Op>(lines.next().ok_or("Missing op lines")?))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let result = apply_change(cache, &root, owner, &change, &ops).unwrap_or_else(|e| {
+            tracing::warn!("Sync of {} failed: {}", change.path, e);
+            ChangeResult {
+                message: Some(e),
+                ..ChangeResult::new(&change.path, "error", false)
+            }
+        });
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+fn apply_change(
+    cache: &HashCache,
+    root: &Path,
+    owner: (u32, u32),
+    change: &ChangeHeader,
+    ops: &[Op],
+) -> Result<ChangeResult, String> {
+    let path = resolve(root, &change.path)?;
+    check_inside(root, &path)?;
+
+    let current = match std::fs::read(&path) {
+        Ok(data) => Some(data),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(format!("Failed to read: {}", e)),
+    };
+    if current.as_deref().map(chunker::file_hash) != change.base {
+        return Ok(ChangeResult::new(&change.path, "conflict", false));
+    }
+
+    // Build the new version before touching anything
+    let data = match &change.file {
+        Some(file) => {
+            let data = rebuild(current.as_deref().unwrap_or_default(), ops)?;
+            if chunker::file_hash(&data) != file.hash {
+                return Err("Rebuilt file doesn't match its hash".to_string());
+            }
+            Some(data)
+        }
+        None => None,
+    };
+
+    let backed_up = change.backup && current.is_some();
+    if backed_up {
+        let backup = PathBuf::from(format!("{}{}", path.display(), CONFLICT_SUFFIX));
+        std::fs::rename(&path, &backup).map_err(|e| format!("Failed to keep a copy: {}", e))?;
+    }
+    cache.forget(&path);
+
+    match (&change.file, data) {
+        (Some(file), Some(data)) => {
+            write_file(root, &path, &data, file, owner)?;
+            Ok(ChangeResult::new(&change.path, "applied", backed_up))
+        }
+        _ => {
+            if current.is_some() && !backed_up {
+                std::fs::remove_file(&path).map_err(|e| format!("Failed to delete: {}", e))?;
+            }
+            remove_empty_parents(root, &path);
+            Ok(ChangeResult::new(&change.path, "deleted", backed_up))
+        }
+    }
+}
+
+/// Rebuild a file from its previous version and a list of ops.
+fn rebuild(base: &[u8], ops: &[Op]) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    for op in ops {
+        match op {
+            Op::Copy { offset, len } => {
+                let range = usize::try_from(*offset)
+                    .ok()
+                    .zip(usize::try_from(offset + len).ok())
+                    .filter(|(start, end)| start <= end && *end <= base.len())
+                    .ok_or("Copy outside the previous version")?;
+                data.extend_from_slice(&base[range.0..range.1]);
+            }
+            Op::Data { data: encoded } => {
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(encoded)
+                    .map_err(|e| format!("Invalid data: {}", e))?;
+                data.extend_from_slice(&bytes);
+            }
+        }
+    }
+    Ok(data)
+}
+
+/// Write a file through a temporary one next to it, with the given mode,
+/// mtime and owner, creating missing parent directories.
+fn write_file(
+    root: &Path,
+    path: &Path,
+    data: &[u8],
+    file: &FileHeader,
+    owner: (u32, u32),
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        create_dirs(root, parent, owner)?;
+    }
+
+    let part = PathBuf::from(format!("{}{}", path.display(), PART_SUFFIX));
+    let write = || -> std::io::Result<()> {
+        std::fs::write(&part, data)?;
+        std::fs::set_permissions(&part, std::fs::Permissions::from_mode(file.mode & 0o7777))?;
+        std::os::unix::fs::chown(&part, Some(owner.0), Some(owner.1))?;
+        std::fs::File::options()
+            .write(true)
+            .open(&part)?
+            .set_modified(UNIX_EPOCH + Duration::from_nanos(file.mtime))?;
+        std::fs::rename(&part, path)
+    };
+
+    write().map_err(|e| {
+        let _ = std::fs::remove_file(&part);
+        format!("Failed to write: {}", e)
+    })
+}
+
+/// Create `dir` and its missing parents below `root`, owned by `owner`.
+fn create_dirs(root: &Path, dir: &Path, owner: (u32, u32)) -> Result<(), String> {
+    let missing: Vec<&Path> = dir
+        .ancestors()
+        .take_while(|ancestor| *ancestor != root && !ancestor.exists())
+        .collect();
+
+    for dir in missing.into_iter().rev() {
+        std::fs::create_dir(dir)
+            .or_else(|e| match e.kind() {
+                ErrorKind::AlreadyExists => Ok(()),
+                _ => Err(e),
+            })
+            .and_then(|_| std::os::unix::fs::chown(dir, Some(owner.0), Some(owner.1)))
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Remove directories left empty by a deletion, up to `root`.
+fn remove_empty_parents(root: &Path, path: &Path) {
+    for dir in path.ancestors().skip(1) {
+        if dir == root || std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+/// The sync root must be an absolute, normalized path.
+fn check_root(root: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(root);
+    let normal = path.is_absolute()
+        && path
+            .components()
+            .all(|c| matches!(c, Component::RootDir | Component::Normal(_)));
+    if normal && path != Path::new("/") {
+        Ok(path)
+    } else {
+        Err(format!("Invalid sync root: {}", root))
+    }
+}
+
+/// Join a relative path to the root, refusing anything that escapes it.
+fn resolve(root: &Path, path: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(path);
+    let valid = !path.is_empty()
+        && relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+    if valid {
+        Ok(root.join(relative))
+    } else {
+        Err(format!("Invalid path: {}", path))
+    }
+}
+
+/// Refuse a path whose parent directory resolves outside the root, e.g.
+/// through a symlink inside the synced tree.
+fn check_inside(root: &Path, path: &Path) -> Result<(), String> {
+    let canonical = |dir: &Path| {
+        dir.canonicalize()
+            .map_err(|e| format!("Failed to resolve {}: {}", dir.display(), e))
+    };
+
+    let root = canonical(root)?;
+    // Missing directories are created below the deepest existing one
+    let parent = path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.symlink_metadata().is_ok())
+        .map_or_else(|| Ok(root.clone()), canonical)?;
+
+    if parent.starts_with(&root) {
+        Ok(())
+    } else {
+        Err(format!("Path leaves the sync root: {}", path.display()))
+    }
+}
+
+fn parse_line<T: serde::de::DeserializeOwned>(line: &[u8]) -> Result<T, String> {
+    serde_json::from_slice(line).map_err(|e| format!("Invalid request line: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_lines(cache: &HashCache, lines: &[serde_json::Value]) -> Vec<ChangeResult> {
+        let body: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        apply(cache, body.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_rejects_escapes() {
+        let root = Path::new("/home/dev/project");
+        assert_eq!(
+            resolve(root, "src/main.rs").unwrap(),
+            PathBuf::from("/home/dev/project/src/main.rs")
+        );
+        assert!(resolve(root, "../.ssh/authorized_keys").is_err());
+        assert!(resolve(root, "/etc/passwd").is_err());
+        assert!(resolve(root, "src/../../x").is_err());
+        assert!(resolve(root, "").is_err());
+
+        assert!(check_root("/").is_err());
+        assert!(check_root("relative").is_err());
+        assert!(check_root("/home/dev/../root").is_err());
+    }
+
+    #[test]
+    fn test_apply_refuses_symlinks_out_of_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+        let cache = HashCache::default();
+        let hash = chunker::file_hash(b"owned");
+
+        let results = apply_lines(
+            &cache,
+            &[
+                serde_json::json!({ "root": dir.path().to_str().unwrap() }),
+                serde_json::json!({ "path": "link/sub/file", "base": null, "file": {
+                    "hash": hash, "mode": 0o644, "mtime": 0, "ops": 1 } }),
+                serde_json::to_value(Op::data(b"owned")).unwrap(),
+            ],
+        );
+
+        assert_eq!(results[0].result, "error");
+        assert!(!outside.path().join("sub").exists());
+    }
+
+    #[test]
+    fn test_rebuild_from_ops() {
+        let base = b"hello world";
+        let ops = vec![Op::Copy { offset: 0, len: 6 }, Op::data(b"there")];
+        assert_eq!(rebuild(base, &ops).unwrap(), b"hello there");
+
+        let out_of_range = vec![Op::Copy { offset: 6, len: 10 }];
+        assert!(rebuild(base, &out_of_range).is_err());
+    }
+
+    #[test]
+    fn test_apply_writes_and_deletes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_str().unwrap();
+        let cache = HashCache::default();
+        let hash = chunker::file_hash(b"fn main() {}");
+
+        let results = apply_lines(
+            &cache,
+            &[
+                serde_json::json!({ "root": root }),
+                serde_json::json!({ "path": "src/main.rs", "base": null, "file": {
+                    "hash": hash, "mode": 0o644, "mtime": 1_700_000_000_000_000_000u64, "ops": 1 } }),
+                serde_json::to_value(Op::data(b"fn main() {}")).unwrap(),
+            ],
+        );
+        assert_eq!(results[0].result, "applied");
+        let written = dir.path().join("src/main.rs");
+        assert_eq!(std::fs::read(&written).unwrap(), b"fn main() {}");
+        let mtime = std::fs::metadata(&written).unwrap().modified().unwrap();
+        assert_eq!(
+            mtime.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            1_700_000_000
+        );
+
+        // Listed with the same hash the CLI computes
+        let request = ManifestRequest {
+            root: root.to_string(),
+            exclude: Vec::new(),
+            gitignore: true,
+            chunks: false,
+        };
+        let listed = manifest(&cache, &request).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].hash, hash);
+
+        let results = apply_lines(
+            &cache,
+            &[
+                serde_json::json!({ "root": root }),
+                serde_json::json!({ "path": "src/main.rs", "base": hash, "file": null }),
+            ],
+        );
+        assert_eq!(results[0].result, "deleted");
+        assert!(!dir.path().join("src").exists());
+    }
+
+    #[test]
+    fn test_apply_detects_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_str().unwrap();
+        std::fs::write(dir.path().join("notes.md"), "edited on the VM").unwrap();
+        let cache = HashCache::default();
+        let hash = chunker::file_hash(b"local");
+        let change = |base: &str, backup: bool| {
+            vec![
+                serde_json::json!({ "root": root }),
+                serde_json::json!({ "path": "notes.md", "base": base, "backup": backup,
+                    "file": { "hash": hash, "mode": 0o644, "mtime": 0, "ops": 1 } }),
+                serde_json::to_value(Op::data(b"local")).unwrap(),
+            ]
+        };
+
+        // Not at the version the CLI expects: left alone
+        let results = apply_lines(&cache, &change(&chunker::file_hash(b"v1"), false));
+        assert_eq!(results[0].result, "conflict");
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("notes.md")).unwrap(),
+            "edited on the VM"
+        );
+
+        // Overwritten, keeping the VM's version
+        let current = chunker::file_hash(b"edited on the VM");
+        let results = apply_lines(&cache, &change(&current, true));
+        assert_eq!(results[0].result, "applied");
+        assert!(results[0].backed_up);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("notes.md.spuff-conflict")).unwrap(),
+            "edited on the VM"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("notes.md")).unwrap(),
+            "local"
+        );
+    }
+}
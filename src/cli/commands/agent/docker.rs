@@ -9,6 +9,15 @@ use crate::utils::{format_bytes, format_duration, truncate};
 
 use super::format::format_percent_colored;
 
+/// Error for `--output json|yaml` on Docker environments: without the
+/// spuff-agent there is no structured data to print.
+pub fn unsupported_output(command: &str) -> SpuffError {
+    SpuffError::Config(format!(
+        "'spuff agent {}' only supports text output on Docker environments",
+        command
+    ))
+}
+
 /// Show Docker container status.
 pub async fn docker_status(container_id: &str) -> Result<()> {
     println!("{}", style("Container Status").bold().cyan());
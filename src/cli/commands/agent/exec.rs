@@ -2,14 +2,20 @@
 //!
 //! Execute commands on the remote environment.
 
-use std::io::IsTerminal;
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+
+use bytes::Bytes;
+use console::style;
+use futures::SinkExt;
+use tokio::io::AsyncReadExt;
 
 use crate::config::AppConfig;
+use crate::connector::agent::AgentClient;
 use crate::error::{Result, SpuffError};
 use crate::state::StateDb;
 
-use super::http::agent_request_post;
-use super::types::AgentExecResponse;
+use super::types::ExecFrame;
 
 /// Commands known to require interactive TTY (editors, pagers, monitors, REPLs, shells).
 const INTERACTIVE_COMMANDS: &[&str] = &[
@@ -23,10 +29,54 @@ const INTERACTIVE_COMMANDS: &[&str] = &[
     "tig",
 ];
 
+/// Where and how the remote command runs.
+#[derive(Debug, Default)]
+pub struct ExecOptions {
+    /// Working directory on the remote
+    pub workdir: Option<String>,
+    /// Extra environment variables
+    pub env: BTreeMap<String, String>,
+    /// Kill the command after this many seconds
+    pub timeout: Option<u64>,
+}
+
+impl ExecOptions {
+    /// Build options from the CLI flags; `env` holds `KEY=VALUE` pairs.
+    pub fn new(workdir: Option<String>, env: &[String], timeout: Option<u64>) -> Result<Self> {
+        Ok(Self {
+            workdir,
+            env: parse_env(env)?,
+            timeout,
+        })
+    }
+
+    /// Shell command applying these options to `command`, for the paths that
+    /// don't go through the agent.
+    fn wrap(&self, command: &str) -> String {
+        let mut wrapped = String::new();
+        for (key, value) in &self.env {
+            wrapped.push_str(&format!("export {}={}; ", key, shell_quote(value)));
+        }
+        if let Some(dir) = &self.workdir {
+            wrapped.push_str(&format!("cd {} && ", shell_quote(dir)));
+        }
+        match self.timeout {
+            // --foreground keeps the TTY usable for interactive commands
+            Some(secs) => wrapped.push_str(&format!(
+                "timeout --foreground {} sh -c {}",
+                secs,
+                shell_quote(command)
+            )),
+            None => wrapped.push_str(command),
+        }
+        wrapped
+    }
+}
+
 /// Execute a command on the remote environment.
 ///
 /// Automatically chooses between:
-/// - Agent HTTP: faster, lower overhead, good for non-interactive commands
+/// - Agent HTTP: output streamed live, good for non-interactive commands
 /// - SSH with TTY: required for interactive commands (editors, REPLs, etc.)
 ///
 /// The decision can be overridden with `-t` (force TTY) or `-T` (force no TTY).
@@ -35,6 +85,7 @@ pub async fn exec(
     command: String,
     force_tty: bool,
     no_tty: bool,
+    options: ExecOptions,
 ) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
@@ -55,6 +106,7 @@ pub async fn exec(
 
     if is_docker {
         // Docker mode: use docker exec
+        let command = options.wrap(&command);
         if needs_tty {
             let exit_code =
                 crate::connector::docker::exec_interactive(&instance_id, &command).await?;
@@ -69,14 +121,18 @@ pub async fn exec(
         // Cloud mode: use SSH
         if needs_tty {
             // Interactive mode via SSH with PTY
-            let exit_code =
-                crate::connector::ssh::exec_interactive(&instance_ip, config, &command).await?;
+            let exit_code = crate::connector::ssh::exec_interactive(
+                &instance_ip,
+                config,
+                &options.wrap(&command),
+            )
+            .await?;
             if exit_code != 0 {
                 std::process::exit(exit_code);
             }
         } else {
-            // Non-interactive mode via agent HTTP (faster)
-            exec_via_agent(&instance_ip, config, &command).await?;
+            // Non-interactive mode via the agent, streaming output
+            exec_via_agent(&instance_ip, config, &command, &options).await?;
         }
     }
 
@@ -128,27 +184,183 @@ fn is_interactive_command(command: &str) -> bool {
     false
 }
 
-/// Execute command via agent HTTP endpoint.
-async fn exec_via_agent(ip: &str, config: &AppConfig, command: &str) -> Result<()> {
-    let response: AgentExecResponse = agent_request_post(
-        ip,
-        config,
-        "/exec",
-        &serde_json::json!({ "command": command }),
-    )
-    .await?;
-
-    // Output stdout/stderr
-    if !response.stdout.is_empty() {
-        print!("{}", response.stdout);
+/// Execute command via the agent's streaming endpoint.
+///
+/// Local stdin is uploaded when it isn't a terminal (`cat dump.sql | spuff
+/// exec -T psql`). Ctrl+C cancels the command, killing its process group.
+async fn exec_via_agent(
+    ip: &str,
+    config: &AppConfig,
+    command: &str,
+    options: &ExecOptions,
+) -> Result<()> {
+    let agent = AgentClient::connect(ip, config).await?;
+    let (mut body, request_body) = futures::channel::mpsc::channel::<Bytes>(16);
+
+    // The request line comes first, then stdin as it's read
+    let mut request = serde_json::to_vec(&serde_json::json!({
+        "command": command,
+        "cwd": options.workdir,
+        "env": options.env,
+        "timeout_secs": options.timeout,
+    }))?;
+    request.push(b'\n');
+    body.send(Bytes::from(request))
+        .await
+        .map_err(|e| SpuffError::Ssh(format!("Failed to send command: {}", e)))?;
+
+    tokio::spawn(async move {
+        if !std::io::stdin().is_terminal() {
+            let mut stdin = tokio::io::stdin();
+            let mut buf = vec![0u8; 64 * 1024];
+            loop {
+                match stdin.read(&mut buf).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if body.send(Bytes::copy_from_slice(&buf[..n])).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        // Dropping the sender ends the request body: EOF on the command's stdin
+    });
+
+    let mut lines = agent.post_stream("/exec/stream", request_body).await?;
+    let mut exec_id = None;
+
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => line?,
+            _ = tokio::signal::ctrl_c() => {
+                if let Some(id) = &exec_id {
+                    let cancelled: Result<serde_json::Value> = agent
+                        .post("/exec/cancel", &serde_json::json!({ "id": id }))
+                        .await;
+                    if let Err(e) = cancelled {
+                        eprintln!("{} Failed to cancel command: {}", style("!").yellow(), e);
+                    }
+                }
+                std::process::exit(130);
+            }
+        };
+
+        let Some(line) = line else {
+            return Err(SpuffError::Provider(
+                "Agent closed the stream before the command finished \
+                 (the agent may be older than this CLI)"
+                    .to_string(),
+            ));
+        };
+
+        match parse_frame(&line) {
+            Some(ExecFrame::Started { id }) => exec_id = Some(id),
+            Some(ExecFrame::Stdout { data }) => {
+                print!("{}", data);
+                let _ = std::io::stdout().flush();
+            }
+            Some(ExecFrame::Stderr { data }) => eprint!("{}", data),
+            Some(ExecFrame::Error { message }) => {
+                eprintln!("{} {}", style("✕").red().bold(), message)
+            }
+            Some(ExecFrame::Exit { code }) => {
+                if code != 0 {
+                    std::process::exit(code);
+                }
+                return Ok(());
+            }
+            Some(ExecFrame::Unknown) => {}
+            None => {
+                return Err(SpuffError::Provider(format!(
+                    "Unexpected response from agent: {}",
+                    line
+                )))
+            }
+        }
     }
-    if !response.stderr.is_empty() {
-        eprint!("{}", response.stderr);
+}
+
+/// Parse one NDJSON frame; `None` for anything that isn't one.
+fn parse_frame(line: &str) -> Option<ExecFrame> {
+    serde_json::from_str(line.trim()).ok()
+}
+
+/// Parse `KEY=VALUE` pairs.
+fn parse_env(vars: &[String]) -> Result<BTreeMap<String, String>> {
+    vars.iter()
+        .map(|var| {
+            let (key, value) = var.split_once('=').ok_or_else(|| {
+                SpuffError::Config(format!("Invalid variable '{}': expected KEY=VALUE", var))
+            })?;
+            let valid = key
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(SpuffError::Config(format!(
+                    "Invalid variable name '{}'",
+                    key
+                )));
+            }
+            Ok((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Quote a string for a POSIX shell.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env() {
+        let env = parse_env(&["RUST_LOG=debug".to_string(), "URL=a=b".to_string()]).unwrap();
+        assert_eq!(env.get("RUST_LOG").map(String::as_str), Some("debug"));
+        assert_eq!(env.get("URL").map(String::as_str), Some("a=b"));
+
+        assert!(parse_env(&["NOVALUE".to_string()]).is_err());
+        assert!(parse_env(&["1BAD=x".to_string()]).is_err());
+        assert!(parse_env(&["A B=x".to_string()]).is_err());
     }
 
-    if response.exit_code != 0 {
-        std::process::exit(response.exit_code);
+    #[test]
+    fn test_wrap_applies_options() {
+        let options = ExecOptions {
+            workdir: Some("/home/dev/my app".to_string()),
+            env: parse_env(&["NAME=it's".to_string()]).unwrap(),
+            timeout: Some(30),
+        };
+
+        assert_eq!(
+            options.wrap("make test"),
+            "export NAME='it'\\''s'; cd '/home/dev/my app' && \
+             timeout --foreground 30 sh -c 'make test'"
+        );
+        assert_eq!(ExecOptions::default().wrap("make test"), "make test");
     }
 
-    Ok(())
+    #[test]
+    fn test_parse_frame() {
+        assert_eq!(
+            parse_frame("{\"type\":\"stdout\",\"data\":\"hi\\n\"}"),
+            Some(ExecFrame::Stdout {
+                data: "hi\n".to_string()
+            })
+        );
+        assert_eq!(
+            parse_frame("{\"type\":\"exit\",\"code\":3,\"duration_ms\":10}"),
+            Some(ExecFrame::Exit { code: 3 })
+        );
+        assert_eq!(
+            parse_frame("{\"type\":\"progress\",\"percent\":50}"),
+            Some(ExecFrame::Unknown)
+        );
+        assert_eq!(parse_frame("Not Found"), None);
+    }
 }
@@ -0,0 +1,73 @@
+//! Maximum lifetime command (`spuff extend`)
+//!
+//! Pushes back the deadline spuff-agent enforces from `max_lifetime`.
+
+use console::style;
+
+use crate::cli::output::OutputFormat;
+use crate::config::{parse_max_lifetime, AppConfig};
+use crate::connector::agent::AgentClient;
+use crate::error::{Result, SpuffError};
+use crate::state::StateDb;
+use crate::utils::format_duration;
+
+use super::types::LifetimeReport;
+
+pub async fn extend(config: &AppConfig, duration: String, output: OutputFormat) -> Result<()> {
+    let seconds = parse_max_lifetime(&duration)
+        .ok_or_else(|| {
+            SpuffError::Config(format!(
+                "Invalid duration '{}'. Use format like '2h' or '30m'",
+                duration
+            ))
+        })?
+        .as_secs();
+
+    let db = StateDb::open()?;
+    let instance = db
+        .get_active_instance()?
+        .ok_or(SpuffError::NoActiveInstance)?;
+    drop(db);
+
+    if instance.provider == "docker" || instance.provider == "local" {
+        if !output.is_text() {
+            return Err(SpuffError::Config(
+                "Docker environments have no maximum lifetime".to_string(),
+            ));
+        }
+        println!(
+            "{}",
+            style("Docker environments have no maximum lifetime.").dim()
+        );
+        return Ok(());
+    }
+
+    let agent = AgentClient::connect(&instance.ip, config).await?;
+    let report: LifetimeReport = agent
+        .post(
+            "/lifetime/extend",
+            &serde_json::json!({ "seconds": seconds }),
+        )
+        .await?;
+    if !output.is_text() {
+        return output.print(&report);
+    }
+
+    let (Some(deadline), Some(remaining)) = (report.deadline, report.remaining_seconds) else {
+        return Ok(());
+    };
+    println!(
+        "{} {} now runs until {} ({} left)",
+        style("✓").green().bold(),
+        style(&instance.name).cyan(),
+        style(
+            deadline
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+        )
+        .white(),
+        style(format_duration(remaining)).yellow()
+    );
+
+    Ok(())
+}
@@ -3,17 +3,30 @@
 //! Commands for viewing logs, activity, and exec history.
 
 use console::style;
+use serde::Serialize;
 
+use crate::cli::output::OutputFormat;
 use crate::config::AppConfig;
+use crate::connector::agent::AgentClient;
 use crate::error::{Result, SpuffError};
 use crate::state::StateDb;
 
 use super::docker;
 use super::format::{print_activity_entry, unescape_output};
-use super::http::agent_request;
-use super::types::{ActivityLogResponse, ExecLogResponse};
+use super::types::{ActivityLogEntry, ActivityLogResponse, ExecLogResponse};
 
-pub async fn logs(config: &AppConfig, lines: usize, file: Option<String>) -> Result<()> {
+/// `spuff agent logs` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct AgentLogs {
+    pub lines: Vec<String>,
+}
+
+pub async fn logs(
+    config: &AppConfig,
+    lines: usize,
+    file: Option<String>,
+    output: OutputFormat,
+) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
@@ -22,6 +35,9 @@ pub async fn logs(config: &AppConfig, lines: usize, file: Option<String>) -> Res
     let is_docker = instance.provider == "docker" || instance.provider == "local";
 
     if is_docker {
+        if !output.is_text() {
+            return Err(docker::unsupported_output("logs"));
+        }
         return docker::docker_logs(&instance.id, lines, file).await;
     }
 
@@ -30,7 +46,21 @@ pub async fn logs(config: &AppConfig, lines: usize, file: Option<String>) -> Res
         url.push_str(&format!("&file={}", f));
     }
 
-    let response: serde_json::Value = agent_request(&instance.ip, config, &url).await?;
+    let agent = AgentClient::connect(&instance.ip, config).await?;
+    let response: serde_json::Value = agent.get(&url).await?;
+
+    if !output.is_text() {
+        let lines = response["lines"]
+            .as_array()
+            .map(|lines| {
+                lines
+                    .iter()
+                    .filter_map(|l| l.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        return output.print(&AgentLogs { lines });
+    }
 
     if let Some(lines) = response["lines"].as_array() {
         for line in lines {
@@ -44,7 +74,12 @@ pub async fn logs(config: &AppConfig, lines: usize, file: Option<String>) -> Res
 }
 
 /// Show agent activity logs (what the agent has executed)
-pub async fn activity(config: &AppConfig, limit: usize, follow: bool) -> Result<()> {
+pub async fn activity(
+    config: &AppConfig,
+    limit: usize,
+    follow: bool,
+    output: OutputFormat,
+) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
@@ -53,6 +88,9 @@ pub async fn activity(config: &AppConfig, limit: usize, follow: bool) -> Result<
     let is_docker = instance.provider == "docker" || instance.provider == "local";
 
     if is_docker {
+        if !output.is_text() {
+            return Err(docker::unsupported_output("activity"));
+        }
         println!(
             "{} Activity logs not available for Docker containers.",
             style("i").blue().bold()
@@ -65,19 +103,23 @@ pub async fn activity(config: &AppConfig, limit: usize, follow: bool) -> Result<
         return Ok(());
     }
 
+    let agent = AgentClient::connect(&instance.ip, config).await?;
+
     if follow {
         // Follow mode - poll for new entries
-        println!(
-            "{} Following agent activity on {} (Ctrl+C to stop)\n",
-            style("→").cyan().bold(),
-            style(&instance.name).cyan()
-        );
+        if output.is_text() {
+            println!(
+                "{} Following agent activity on {} (Ctrl+C to stop)\n",
+                style("→").cyan().bold(),
+                style(&instance.name).cyan()
+            );
+        }
 
         let mut last_timestamp: Option<String> = None;
 
         loop {
             let activity: ActivityLogResponse =
-                agent_request(&instance.ip, config, &format!("/activity?limit={}", limit)).await?;
+                agent.get(&format!("/activity?limit={}", limit)).await?;
 
             for entry in activity.entries.iter().rev() {
                 // Only print entries newer than what we've seen
@@ -87,7 +129,11 @@ pub async fn activity(config: &AppConfig, limit: usize, follow: bool) -> Result<
                 };
 
                 if should_print {
-                    print_activity_entry(entry);
+                    if output.is_text() {
+                        print_activity_entry(entry);
+                    } else {
+                        output.print_item(entry)?;
+                    }
                     last_timestamp = Some(entry.timestamp.clone());
                 }
             }
@@ -97,7 +143,13 @@ pub async fn activity(config: &AppConfig, limit: usize, follow: bool) -> Result<
     } else {
         // One-shot mode
         let activity: ActivityLogResponse =
-            agent_request(&instance.ip, config, &format!("/activity?limit={}", limit)).await?;
+            agent.get(&format!("/activity?limit={}", limit)).await?;
+
+        if !output.is_text() {
+            // Chronological order (oldest first), like the text output
+            let entries: Vec<&ActivityLogEntry> = activity.entries.iter().rev().collect();
+            return output.print(&entries);
+        }
 
         if activity.entries.is_empty() {
             println!("{}", style("No activity logged yet").dim());
@@ -115,7 +167,7 @@ pub async fn activity(config: &AppConfig, limit: usize, follow: bool) -> Result<
 }
 
 /// Show persistent exec log (all commands executed via agent, survives restarts)
-pub async fn exec_log(config: &AppConfig, lines: usize) -> Result<()> {
+pub async fn exec_log(config: &AppConfig, lines: usize, output: OutputFormat) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
@@ -124,6 +176,9 @@ pub async fn exec_log(config: &AppConfig, lines: usize) -> Result<()> {
     let is_docker = instance.provider == "docker" || instance.provider == "local";
 
     if is_docker {
+        if !output.is_text() {
+            return Err(docker::unsupported_output("exec-log"));
+        }
         println!(
             "{} Exec logs not available for Docker containers.",
             style("i").blue().bold()
@@ -140,8 +195,12 @@ pub async fn exec_log(config: &AppConfig, lines: usize) -> Result<()> {
         return Ok(());
     }
 
-    let response: ExecLogResponse =
-        agent_request(&instance.ip, config, &format!("/exec-log?lines={}", lines)).await?;
+    let agent = AgentClient::connect(&instance.ip, config).await?;
+    let response: ExecLogResponse = agent.get(&format!("/exec-log?lines={}", lines)).await?;
+
+    if !output.is_text() {
+        return output.print(&response.entries);
+    }
 
     if response.entries.is_empty() {
         println!("{}", style("No exec commands logged yet").dim());
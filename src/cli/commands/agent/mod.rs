@@ -6,12 +6,14 @@
 mod docker;
 mod exec;
 mod format;
-mod http;
+mod lifetime;
 mod logs;
 mod status;
 mod types;
 
 // Re-export public functions
-pub use exec::exec;
+pub use exec::{exec, ExecOptions};
+pub use lifetime::extend;
 pub use logs::{activity, exec_log, logs};
-pub use status::{metrics, processes, status};
+pub use status::{idle, metrics, ports, processes, status};
+pub use types::ListeningPort;
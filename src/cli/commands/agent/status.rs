@@ -3,18 +3,41 @@
 //! Commands for viewing agent status, metrics, and processes.
 
 use console::style;
+use serde::Serialize;
 
+use crate::cli::output::OutputFormat;
 use crate::config::AppConfig;
+use crate::connector::agent::AgentClient;
 use crate::error::{Result, SpuffError};
 use crate::state::StateDb;
 use crate::utils::{format_bytes, format_duration, truncate};
 
 use super::docker;
 use super::format::{format_bootstrap_status, format_cpu_bar, format_percent_colored};
-use super::http::agent_request;
-use super::types::{ActivityLogResponse, AgentMetrics, AgentStatus, ProcessInfo};
+use super::types::{
+    ActivityLogEntry, ActivityLogResponse, AgentMetrics, AgentStatus, IdleReport, LifetimeReport,
+    ListeningPort, ProcessInfo,
+};
 
-pub async fn status(config: &AppConfig) -> Result<()> {
+/// `spuff agent status` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct AgentStatusReport {
+    pub status: AgentStatus,
+    pub metrics: AgentMetrics,
+    /// Most recent first
+    pub activity: Vec<ActivityLogEntry>,
+}
+
+/// `spuff agent idle` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct IdleStatusReport {
+    #[serde(flatten)]
+    pub idle: IdleReport,
+    /// None with agents that predate `max_lifetime`
+    pub lifetime: Option<LifetimeReport>,
+}
+
+pub async fn status(config: &AppConfig, output: OutputFormat) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
@@ -23,17 +46,35 @@ pub async fn status(config: &AppConfig) -> Result<()> {
     let is_docker = instance.provider == "docker" || instance.provider == "local";
 
     if is_docker {
+        if !output.is_text() {
+            return Err(docker::unsupported_output("status"));
+        }
         return docker::docker_status(&instance.id).await;
     }
 
+    let agent = AgentClient::connect(&instance.ip, config).await?;
+
+    if !output.is_text() {
+        let activity = agent
+            .get::<ActivityLogResponse>("/activity?limit=10")
+            .await
+            .map(|a| a.entries)
+            .unwrap_or_default();
+        return output.print(&AgentStatusReport {
+            status: agent.get("/status").await?,
+            metrics: agent.get("/metrics").await?,
+            activity,
+        });
+    }
+
     println!(
         "{} Fetching agent status from {}...\n",
         style("→").cyan().bold(),
         style(&instance.name).cyan()
     );
 
-    let status: AgentStatus = agent_request(&instance.ip, config, "/status").await?;
-    let metrics: AgentMetrics = agent_request(&instance.ip, config, "/metrics").await?;
+    let status: AgentStatus = agent.get("/status").await?;
+    let metrics: AgentMetrics = agent.get("/metrics").await?;
 
     println!("{}", style("Agent Status").bold().cyan());
     println!("  Version:      {}", style(&status.agent_version).white());
@@ -84,9 +125,7 @@ pub async fn status(config: &AppConfig) -> Result<()> {
     );
 
     // Fetch and display recent activity log
-    if let Ok(activity) =
-        agent_request::<ActivityLogResponse>(&instance.ip, config, "/activity?limit=10").await
-    {
+    if let Ok(activity) = agent.get::<ActivityLogResponse>("/activity?limit=10").await {
         if !activity.entries.is_empty() {
             println!("\n{}", style("Recent Activity").bold().cyan());
             for entry in activity.entries.iter().take(10) {
@@ -105,7 +144,7 @@ pub async fn status(config: &AppConfig) -> Result<()> {
     Ok(())
 }
 
-pub async fn metrics(config: &AppConfig) -> Result<()> {
+pub async fn metrics(config: &AppConfig, output: OutputFormat) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
@@ -114,10 +153,17 @@ pub async fn metrics(config: &AppConfig) -> Result<()> {
     let is_docker = instance.provider == "docker" || instance.provider == "local";
 
     if is_docker {
+        if !output.is_text() {
+            return Err(docker::unsupported_output("metrics"));
+        }
         return docker::docker_metrics(&instance.id).await;
     }
 
-    let metrics: AgentMetrics = agent_request(&instance.ip, config, "/metrics").await?;
+    let agent = AgentClient::connect(&instance.ip, config).await?;
+    let metrics: AgentMetrics = agent.get("/metrics").await?;
+    if !output.is_text() {
+        return output.print(&metrics);
+    }
 
     println!("{}", style("System Metrics").bold().cyan());
     println!("  Hostname:     {}", style(&metrics.hostname).white());
@@ -144,7 +190,7 @@ pub async fn metrics(config: &AppConfig) -> Result<()> {
     Ok(())
 }
 
-pub async fn processes(config: &AppConfig) -> Result<()> {
+pub async fn processes(config: &AppConfig, output: OutputFormat) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
@@ -153,10 +199,17 @@ pub async fn processes(config: &AppConfig) -> Result<()> {
     let is_docker = instance.provider == "docker" || instance.provider == "local";
 
     if is_docker {
+        if !output.is_text() {
+            return Err(docker::unsupported_output("processes"));
+        }
         return docker::docker_processes(&instance.id).await;
     }
 
-    let procs: Vec<ProcessInfo> = agent_request(&instance.ip, config, "/processes").await?;
+    let agent = AgentClient::connect(&instance.ip, config).await?;
+    let procs: Vec<ProcessInfo> = agent.get("/processes").await?;
+    if !output.is_text() {
+        return output.print(&procs);
+    }
 
     println!("{}", style("Top Processes by CPU").bold().cyan());
     println!(
@@ -177,3 +230,142 @@ pub async fn processes(config: &AppConfig) -> Result<()> {
 
     Ok(())
 }
+
+pub async fn ports(config: &AppConfig, output: OutputFormat) -> Result<()> {
+    let db = StateDb::open()?;
+    let instance = db
+        .get_active_instance()?
+        .ok_or(SpuffError::NoActiveInstance)?;
+
+    if instance.provider == "docker" || instance.provider == "local" {
+        if !output.is_text() {
+            return Err(docker::unsupported_output("ports"));
+        }
+        println!(
+            "{}",
+            style("Docker environments publish ports through Docker.").dim()
+        );
+        return Ok(());
+    }
+
+    let agent = AgentClient::connect(&instance.ip, config).await?;
+    let ports: Vec<ListeningPort> = agent.get("/ports").await?;
+    if !output.is_text() {
+        return output.print(&ports);
+    }
+
+    println!("{}", style("Listening Ports").bold().cyan());
+    println!(
+        "  {:<8} {:<24} {:<8} {:<20}",
+        "PORT", "ADDRESS", "PID", "PROCESS"
+    );
+    println!("  {}", "-".repeat(64));
+
+    for port in ports {
+        println!(
+            "  {:<8} {:<24} {:<8} {:<20}",
+            port.port,
+            truncate(&port.address, 24),
+            port.pid.map(|pid| pid.to_string()).unwrap_or_default(),
+            truncate(port.process.as_deref().unwrap_or("-"), 20)
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn idle(config: &AppConfig, output: OutputFormat) -> Result<()> {
+    let db = StateDb::open()?;
+    let instance = db
+        .get_active_instance()?
+        .ok_or(SpuffError::NoActiveInstance)?;
+
+    if instance.provider == "docker" || instance.provider == "local" {
+        if !output.is_text() {
+            return Err(docker::unsupported_output("idle"));
+        }
+        println!(
+            "{}",
+            style("Docker environments have no idle policy.").dim()
+        );
+        return Ok(());
+    }
+
+    let agent = AgentClient::connect(&instance.ip, config).await?;
+    let report: IdleReport = agent.get("/idle").await?;
+    let lifetime = match agent.get::<LifetimeReport>("/lifetime").await {
+        Ok(lifetime) => Some(lifetime),
+        // Older agents have no /lifetime endpoint
+        Err(SpuffError::Agent { status: 404, .. }) => None,
+        Err(e) => return Err(e),
+    };
+
+    if !output.is_text() {
+        return output.print(&IdleStatusReport {
+            idle: report,
+            lifetime,
+        });
+    }
+
+    println!("{}", style("Idle Policy").bold().cyan());
+    if !report.enabled {
+        println!(
+            "  {}",
+            style("No idle policy on this environment; it never shuts down on idle.").dim()
+        );
+    } else {
+        println!(
+            "  State:        {}",
+            if report.idle {
+                style("idle").yellow()
+            } else {
+                style("active").green()
+            }
+        );
+        println!(
+            "  Idle for:     {} of {}",
+            style(format_duration(report.idle_seconds)).white(),
+            style(format_duration(report.timeout_seconds as i64)).dim()
+        );
+        if let Some(remaining) = report.remaining_seconds {
+            println!(
+                "  Action:       {} in {}{}",
+                style(report.action.as_deref().unwrap_or("none")).white(),
+                style(format_duration(remaining)).yellow(),
+                if report.warned {
+                    style(" (users warned)").dim().to_string()
+                } else {
+                    String::new()
+                }
+            );
+        }
+    }
+
+    if let Some(lifetime) = lifetime {
+        if let (true, Some(remaining)) = (lifetime.enabled, lifetime.remaining_seconds) {
+            println!(
+                "  Max lifetime: {} in {} {}",
+                style(lifetime.action.as_deref().unwrap_or("none")).white(),
+                style(format_duration(remaining)).yellow(),
+                style("(spuff extend)").dim()
+            );
+        }
+    }
+
+    println!("\n{}", style("Signals").bold().cyan());
+    for signal in &report.signals {
+        let marker = if signal.active {
+            style("●").green()
+        } else {
+            style("○").dim()
+        };
+        println!(
+            "  {} {:14} {}",
+            marker,
+            style(&signal.name).white(),
+            style(&signal.detail).dim()
+        );
+    }
+
+    Ok(())
+}
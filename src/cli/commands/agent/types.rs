@@ -1,10 +1,11 @@
 //! Agent response types
 //!
-//! Structs for deserializing agent HTTP API responses.
+//! Structs for deserializing agent HTTP API responses. They are also
+//! printed as-is in `json`/`yaml` output.
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct AgentStatus {
     pub uptime_seconds: i64,
     pub idle_seconds: i64,
@@ -40,7 +41,7 @@ pub struct LoadAverage {
     pub fifteen: f64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ProcessInfo {
     pub pid: u32,
     pub name: String,
@@ -48,7 +49,28 @@ pub struct ProcessInfo {
     pub memory: u64,
 }
 
-#[derive(Debug, Deserialize)]
+/// Entry of the agent's /ports endpoint: a listening TCP socket.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ListeningPort {
+    pub port: u16,
+    pub address: String,
+    pub pid: Option<u32>,
+    pub process: Option<String>,
+    #[allow(dead_code)]
+    pub command: Option<String>,
+}
+
+impl ListeningPort {
+    /// Whether connections to 127.0.0.1 on the VM reach this socket.
+    pub fn is_local(&self) -> bool {
+        matches!(
+            self.address.as_str(),
+            "0.0.0.0" | "::" | "::ffff:0.0.0.0" | "::1" | "::ffff:127.0.0.1"
+        ) || self.address.starts_with("127.")
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ActivityLogEntry {
     pub timestamp: String,
     pub event: String,
@@ -62,7 +84,7 @@ pub struct ActivityLogResponse {
     pub count: usize,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ExecLogEntry {
     pub timestamp: String,
     pub event: String,
@@ -80,12 +102,58 @@ pub struct ExecLogResponse {
     pub count: usize,
 }
 
-/// Response from agent's /exec endpoint.
-#[derive(Debug, Deserialize)]
-pub struct AgentExecResponse {
-    pub exit_code: i32,
-    pub stdout: String,
-    pub stderr: String,
+/// One NDJSON frame from agent's /exec/stream endpoint.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExecFrame {
+    Started {
+        id: String,
+    },
+    Stdout {
+        data: String,
+    },
+    Stderr {
+        data: String,
+    },
+    Error {
+        message: String,
+    },
+    Exit {
+        code: i32,
+    },
+    /// Frames added by newer agents
+    #[serde(other)]
+    Unknown,
+}
+
+/// Response from agent's /lifetime endpoints.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LifetimeReport {
+    pub enabled: bool,
+    pub deadline: Option<chrono::DateTime<chrono::Utc>>,
+    pub remaining_seconds: Option<i64>,
+    pub action: Option<String>,
+}
+
+/// One signal from the agent's /idle endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct IdleSignal {
+    pub name: String,
+    pub active: bool,
+    pub detail: String,
+}
+
+/// Response from agent's /idle endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct IdleReport {
+    pub enabled: bool,
+    pub idle: bool,
+    pub idle_seconds: i64,
+    pub timeout_seconds: u64,
+    pub remaining_seconds: Option<i64>,
     #[allow(dead_code)]
-    pub duration_ms: u64,
+    pub grace_period_seconds: u64,
+    pub warned: bool,
+    pub action: Option<String>,
+    pub signals: Vec<IdleSignal>,
 }
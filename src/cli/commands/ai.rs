@@ -1,7 +1,9 @@
 use console::style;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+use crate::cli::output::OutputFormat;
 use crate::config::AppConfig;
+use crate::connector::agent::AgentClient;
 use crate::error::{Result, SpuffError};
 use crate::project_config::{AiToolsConfig, ProjectConfig};
 use crate::state::StateDb;
@@ -72,16 +74,33 @@ enum ToolStatus {
     Skipped,
 }
 
-/// List available AI coding tools
-pub async fn list() -> Result<()> {
-    println!("{}", style("Available AI Coding Tools").bold().cyan());
-    println!();
+/// An AI tool, as printed by `spuff ai list` and `spuff ai info` in
+/// `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct AiToolInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub install_command: &'static str,
+    /// Whether `spuff up` installs it with the current configuration
+    pub enabled: bool,
+}
+
+/// Installation state of an AI tool, as printed by `spuff ai status` in
+/// `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct AiToolState {
+    pub name: &'static str,
+    /// installed, installing, failed, skipped, pending or not_configured
+    pub status: &'static str,
+    pub version: Option<String>,
+    pub error: Option<String>,
+}
 
-    // Load current config to show which tools are enabled
+/// Tools enabled by spuff.yaml, or else the global config (all by default).
+fn enabled_tools() -> Vec<&'static str> {
     let project_config = ProjectConfig::load_from_cwd().ok().flatten();
     let app_config = AppConfig::load().ok();
 
-    // Determine effective AI tools config
     let ai_config = project_config
         .as_ref()
         .map(|pc| &pc.ai_tools)
@@ -89,7 +108,34 @@ pub async fn list() -> Result<()> {
         .cloned()
         .unwrap_or(AiToolsConfig::All);
 
-    let enabled_tools = ai_config.tools_to_install();
+    let enabled = ai_config.tools_to_install();
+    AI_TOOLS
+        .iter()
+        .map(|(name, _, _)| *name)
+        .filter(|name| enabled.contains(name))
+        .collect()
+}
+
+/// List available AI coding tools
+pub async fn list(output: OutputFormat) -> Result<()> {
+    if !output.is_text() {
+        let enabled = enabled_tools();
+        let tools: Vec<AiToolInfo> = AI_TOOLS
+            .iter()
+            .map(|&(name, description, install_command)| AiToolInfo {
+                name,
+                description,
+                install_command,
+                enabled: enabled.contains(&name),
+            })
+            .collect();
+        return output.print(&tools);
+    }
+
+    println!("{}", style("Available AI Coding Tools").bold().cyan());
+    println!();
+
+    let enabled_tools = enabled_tools();
 
     for (name, description, install_cmd) in AI_TOOLS {
         let is_enabled = enabled_tools.contains(name);
@@ -132,19 +178,36 @@ fn cli_to_agent_id(cli_name: &str) -> &str {
 }
 
 /// Show AI tools installation status on remote environment
-pub async fn status(config: &AppConfig) -> Result<()> {
+pub async fn status(config: &AppConfig, output: OutputFormat) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
         .ok_or(SpuffError::NoActiveInstance)?;
 
+    if !output.is_text() {
+        let agent = AgentClient::connect(&instance.ip, config).await?;
+        let response: DevToolsResponse = agent.get("/devtools").await?;
+        let states: Vec<AiToolState> = AI_TOOLS
+            .iter()
+            .map(|&(name, _, _)| {
+                let tool = response
+                    .tools
+                    .iter()
+                    .find(|t| t.id == cli_to_agent_id(name));
+                tool_state(name, tool)
+            })
+            .collect();
+        return output.print(&states);
+    }
+
     println!(
         "{} Fetching AI tools status from {}...\n",
         style("→").cyan().bold(),
         style(&instance.name).cyan()
     );
 
-    let response: DevToolsResponse = agent_request(&instance.ip, config, "/devtools").await?;
+    let agent = AgentClient::connect(&instance.ip, config).await?;
+    let response: DevToolsResponse = agent.get("/devtools").await?;
 
     // Build a map from tool id to tool for quick lookup
     let tools_map: std::collections::HashMap<&str, &DevTool> =
@@ -185,6 +248,31 @@ pub async fn status(config: &AppConfig) -> Result<()> {
     Ok(())
 }
 
+fn tool_state(name: &'static str, tool: Option<&DevTool>) -> AiToolState {
+    let Some(tool) = tool else {
+        return AiToolState {
+            name,
+            status: "not_configured",
+            version: None,
+            error: None,
+        };
+    };
+
+    let (status, error) = match &tool.status {
+        ToolStatus::Done => ("installed", None),
+        ToolStatus::Installing => ("installing", None),
+        ToolStatus::Failed(msg) => ("failed", Some(msg.clone())),
+        ToolStatus::Skipped => ("skipped", None),
+        ToolStatus::Pending => ("pending", None),
+    };
+    AiToolState {
+        name,
+        status,
+        version: tool.version.clone(),
+        error,
+    }
+}
+
 /// Install a specific AI tool on the remote environment
 pub async fn install(config: &AppConfig, tool: String) -> Result<()> {
     // Validate tool name
@@ -276,36 +364,30 @@ pub async fn install(config: &AppConfig, tool: String) -> Result<()> {
     };
 
     // POST to /devtools/install
-    let output = crate::connector::ssh::run_command(
-        &instance.ip,
-        config,
-        &format!(
-            "curl -s -X POST -H 'Content-Type: application/json' -d '{}' http://127.0.0.1:7575/devtools/install",
-            config_json
-        ),
-    )
-    .await?;
-
-    // Check response
-    if output.contains("error") && !output.contains("installation started") {
-        println!(
-            "{} Installation request failed: {}",
-            style("✗").red().bold(),
-            output.trim()
-        );
-    } else {
-        println!(
+    let body: serde_json::Value = serde_json::from_str(&config_json)?;
+    let agent = AgentClient::connect(&instance.ip, config).await?;
+    match agent
+        .post::<_, serde_json::Value>("/devtools/install", &body)
+        .await
+    {
+        Ok(_) => println!(
             "{} Installation started. Use {} to check progress.",
             style("✓").green().bold(),
             style("spuff ai status").yellow()
-        );
+        ),
+        Err(SpuffError::Agent { message, .. }) => println!(
+            "{} Installation request failed: {}",
+            style("✗").red().bold(),
+            message
+        ),
+        Err(e) => return Err(e),
     }
 
     Ok(())
 }
 
 /// Show information about a specific AI tool
-pub async fn info(tool: &str) -> Result<()> {
+pub async fn info(tool: &str, output: OutputFormat) -> Result<()> {
     let tool_info = AI_TOOLS
         .iter()
         .find(|(name, _, _)| *name == tool)
@@ -320,6 +402,15 @@ pub async fn info(tool: &str) -> Result<()> {
 
     let (name, description, install_cmd) = tool_info;
 
+    if !output.is_text() {
+        return output.print(&AiToolInfo {
+            name,
+            description,
+            install_command: install_cmd,
+            enabled: enabled_tools().contains(name),
+        });
+    }
+
     println!("{}", style(*name).bold().cyan());
     println!();
     println!("  {}", description);
@@ -380,45 +471,3 @@ pub async fn info(tool: &str) -> Result<()> {
 
     Ok(())
 }
-
-/// Extract JSON from output that may contain banner text
-fn extract_json(output: &str) -> &str {
-    if let Some(start) = output.find('{') {
-        let mut depth = 0;
-        for (i, c) in output[start..].char_indices() {
-            match c {
-                '{' => depth += 1,
-                '}' => {
-                    depth -= 1;
-                    if depth == 0 {
-                        return &output[start..=start + i];
-                    }
-                }
-                _ => {}
-            }
-        }
-    }
-    output.trim()
-}
-
-async fn agent_request<T: serde::de::DeserializeOwned>(
-    ip: &str,
-    config: &AppConfig,
-    endpoint: &str,
-) -> Result<T> {
-    let output = crate::connector::ssh::run_command(
-        ip,
-        config,
-        &format!("curl -s http://127.0.0.1:7575{}", endpoint),
-    )
-    .await?;
-
-    let json_str = extract_json(&output);
-
-    serde_json::from_str(json_str).map_err(|e| {
-        SpuffError::Provider(format!(
-            "Failed to parse agent response: {}. Response: {}",
-            e, output
-        ))
-    })
-}
@@ -0,0 +1,316 @@
+//! Region and size catalog (`spuff regions`, `spuff sizes`)
+//!
+//! Lists what the configured provider offers, and backs the pickers and
+//! input validation in `spuff init` and `spuff config set`.
+
+use console::style;
+use dialoguer::Select;
+
+use crate::cli::output::OutputFormat;
+use crate::config::AppConfig;
+use crate::error::{Result, SpuffError};
+use crate::provider::{create_provider, Region, Size};
+
+/// Regions and sizes of one provider.
+pub struct Catalog {
+    pub regions: Vec<Region>,
+    pub sizes: Vec<Size>,
+}
+
+impl Catalog {
+    /// Fetch the catalog of `config.provider`.
+    pub async fn fetch(config: &AppConfig) -> Result<Self> {
+        let provider = create_provider(config)?;
+        let (regions, sizes) = tokio::try_join!(provider.list_regions(), provider.list_sizes())?;
+        Ok(Self { regions, sizes })
+    }
+
+    /// Like `fetch`, but `None` when the provider has no catalog or can't be
+    /// reached, so callers can fall back to free text.
+    pub async fn try_fetch(config: &AppConfig) -> Option<Self> {
+        match Self::fetch(config).await {
+            Ok(catalog) => Some(catalog),
+            Err(e) => {
+                tracing::debug!("No region/size catalog for {}: {}", config.provider, e);
+                None
+            }
+        }
+    }
+
+    /// Check that new instances can be created in `region`.
+    pub fn check_region(&self, region: &str) -> Result<()> {
+        match self.regions.iter().find(|r| r.slug == region) {
+            Some(r) if r.available => Ok(()),
+            Some(_) => Err(SpuffError::Config(format!(
+                "Region '{}' is not accepting new instances",
+                region
+            ))),
+            None => Err(SpuffError::Config(format!(
+                "Unknown region '{}'. Run 'spuff regions' to see the options.",
+                region
+            ))),
+        }
+    }
+
+    /// Check that `size` exists and can be created in `region`.
+    pub fn check_size(&self, size: &str, region: &str) -> Result<()> {
+        match self.sizes.iter().find(|s| s.slug == size) {
+            Some(s) if s.available_in(region) => Ok(()),
+            Some(_) => Err(SpuffError::Config(format!(
+                "Size '{}' is not available in {}. Run 'spuff sizes' to see the options.",
+                size, region
+            ))),
+            None => Err(SpuffError::Config(format!(
+                "Unknown size '{}'. Run 'spuff sizes --all' to see the options.",
+                size
+            ))),
+        }
+    }
+
+    /// Interactive region picker, starting at `default` when listed.
+    pub fn pick_region(&self, default: &str) -> Result<String> {
+        let regions: Vec<&Region> = self.regions.iter().filter(|r| r.available).collect();
+        if regions.is_empty() {
+            return Err(SpuffError::Config("No regions available".to_string()));
+        }
+
+        let items: Vec<String> = regions
+            .iter()
+            .map(|r| format!("{:<8} {}", r.slug, r.name))
+            .collect();
+        let default_idx = regions.iter().position(|r| r.slug == default).unwrap_or(0);
+
+        let idx = Select::new()
+            .with_prompt("Select default region")
+            .items(&items)
+            .default(default_idx)
+            .interact()?;
+
+        Ok(regions[idx].slug.clone())
+    }
+
+    /// Interactive picker for the sizes available in `region`, starting at
+    /// `default` when listed, otherwise at the first with 2 vCPUs and 4 GB.
+    pub fn pick_size(&self, region: &str, default: &str) -> Result<String> {
+        let sizes = self.sizes_in(region);
+        if sizes.is_empty() {
+            return Err(SpuffError::Config(format!(
+                "No sizes available in {}",
+                region
+            )));
+        }
+
+        let items: Vec<String> = sizes.iter().map(|s| describe(s)).collect();
+        let default_idx = sizes
+            .iter()
+            .position(|s| s.slug == default)
+            .or_else(|| {
+                sizes
+                    .iter()
+                    .position(|s| s.vcpus >= 2 && s.memory_mb >= 4096)
+            })
+            .unwrap_or(0);
+
+        let idx = Select::new()
+            .with_prompt("Select default instance size")
+            .items(&items)
+            .default(default_idx)
+            .interact()?;
+
+        Ok(sizes[idx].slug.clone())
+    }
+
+    fn sizes_in(&self, region: &str) -> Vec<&Size> {
+        self.sizes
+            .iter()
+            .filter(|s| s.available_in(region))
+            .collect()
+    }
+}
+
+pub async fn regions(config: &AppConfig, output: OutputFormat) -> Result<()> {
+    let catalog = Catalog::fetch(config).await?;
+
+    if !output.is_text() {
+        return output.print(&catalog.regions);
+    }
+
+    println!(
+        "{}",
+        style(format!("Regions ({})", config.provider))
+            .bold()
+            .cyan()
+    );
+    println!();
+
+    for region in &catalog.regions {
+        let marker = if region.slug == config.region {
+            style("●").green()
+        } else if region.available {
+            style("○").dim()
+        } else {
+            style("✕").red()
+        };
+        let name = if region.available {
+            style(region.name.clone()).white()
+        } else {
+            style(format!("{} (unavailable)", region.name)).dim()
+        };
+        println!("  {} {:<8} {}", marker, style(&region.slug).cyan(), name);
+    }
+
+    Ok(())
+}
+
+pub async fn sizes(
+    config: &AppConfig,
+    region: Option<String>,
+    all: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let catalog = Catalog::fetch(config).await?;
+    let region = region.unwrap_or_else(|| config.region.clone());
+
+    let sizes: Vec<&Size> = if all {
+        catalog.sizes.iter().collect()
+    } else {
+        catalog.sizes_in(&region)
+    };
+
+    if !output.is_text() {
+        return output.print(&sizes);
+    }
+
+    if sizes.is_empty() {
+        println!(
+            "{}",
+            style(format!("No sizes available in {}.", region)).dim()
+        );
+        return Ok(());
+    }
+
+    let title = if all {
+        format!("Sizes ({})", config.provider)
+    } else {
+        format!("Sizes ({}, {})", config.provider, region)
+    };
+    println!("{}", style(title).bold().cyan());
+    println!();
+    println!(
+        "  {:<18} {:>5} {:>9} {:>9} {:>10} {:>10}",
+        style("SIZE").dim(),
+        style("VCPU").dim(),
+        style("MEMORY").dim(),
+        style("DISK").dim(),
+        style("HOURLY").dim(),
+        style("MONTHLY").dim()
+    );
+
+    for size in sizes {
+        let slug = if size.slug == config.size {
+            style(&size.slug).green().bold()
+        } else {
+            style(&size.slug).cyan()
+        };
+        println!(
+            "  {:<18} {:>5} {:>9} {:>9} {:>10} {:>10}",
+            slug,
+            size.vcpus,
+            format_memory(size.memory_mb),
+            format!("{} GB", size.disk_gb),
+            format_price(size.price_hourly, &size.currency, 4),
+            format_price(size.price_monthly, &size.currency, 2)
+        );
+    }
+
+    Ok(())
+}
+
+/// One-line summary for pickers: "s-2vcpu-4gb  2 vCPU, 4 GB, 80 GB disk, $0.0357/h".
+fn describe(size: &Size) -> String {
+    format!(
+        "{:<18} {} vCPU, {}, {} GB disk, {}/h",
+        size.slug,
+        size.vcpus,
+        format_memory(size.memory_mb),
+        size.disk_gb,
+        format_price(size.price_hourly, &size.currency, 4)
+    )
+}
+
+fn format_memory(memory_mb: u64) -> String {
+    if memory_mb < 1024 {
+        format!("{} MB", memory_mb)
+    } else if memory_mb.is_multiple_of(1024) {
+        format!("{} GB", memory_mb / 1024)
+    } else {
+        format!("{:.1} GB", memory_mb as f64 / 1024.0)
+    }
+}
+
+/// Format an amount with its currency symbol.
+pub fn format_price(amount: f64, currency: &str, decimals: usize) -> String {
+    match currency {
+        "USD" => format!("${:.*}", decimals, amount),
+        "EUR" => format!("€{:.*}", decimals, amount),
+        other => format!("{:.*} {}", decimals, amount, other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> Catalog {
+        Catalog {
+            regions: vec![
+                Region {
+                    slug: "nyc1".to_string(),
+                    name: "New York 1".to_string(),
+                    available: true,
+                },
+                Region {
+                    slug: "sfo1".to_string(),
+                    name: "San Francisco 1".to_string(),
+                    available: false,
+                },
+            ],
+            sizes: vec![Size {
+                slug: "s-2vcpu-4gb".to_string(),
+                vcpus: 2,
+                memory_mb: 4096,
+                disk_gb: 80,
+                price_hourly: 0.03571,
+                price_monthly: 24.0,
+                currency: "USD".to_string(),
+                regions: vec!["nyc1".to_string()],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_check_region() {
+        let catalog = catalog();
+        assert!(catalog.check_region("nyc1").is_ok());
+        assert!(catalog.check_region("sfo1").is_err());
+        assert!(catalog.check_region("mars1").is_err());
+    }
+
+    #[test]
+    fn test_check_size() {
+        let catalog = catalog();
+        assert!(catalog.check_size("s-2vcpu-4gb", "nyc1").is_ok());
+        assert!(catalog.check_size("s-2vcpu-4gb", "fra1").is_err());
+        assert!(catalog.check_size("s-64vcpu-256gb", "nyc1").is_err());
+    }
+
+    #[test]
+    fn test_format_memory_and_price() {
+        assert_eq!(format_memory(512), "512 MB");
+        assert_eq!(format_memory(4096), "4 GB");
+        assert_eq!(format_memory(1536), "1.5 GB");
+        assert_eq!(format_price(0.03571, "USD", 4), "$0.0357");
+        assert_eq!(format_price(4.51, "EUR", 2), "€4.51");
+        assert_eq!(format_price(1.0, "GBP", 2), "1.00 GBP");
+    }
+}
@@ -1,11 +1,38 @@
+use std::path::PathBuf;
+
 use console::style;
+use serde::Serialize;
 
+use super::catalog::Catalog;
+use crate::cli::output::OutputFormat;
 use crate::config::AppConfig;
-use crate::error::Result;
+use crate::error::{Result, SpuffError};
+
+/// Placeholder for secrets in `spuff config show` output.
+const REDACTED: &str = "<redacted>";
 
-pub async fn show() -> Result<()> {
+/// `spuff config show` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct ConfigReport {
+    pub path: PathBuf,
+    /// None when there is no configuration file yet
+    pub config: Option<AppConfig>,
+}
+
+pub async fn show(output: OutputFormat) -> Result<()> {
     let config_path = AppConfig::config_path()?;
 
+    if !output.is_text() {
+        let config = match config_path.exists() {
+            true => Some(redact(AppConfig::load()?)),
+            false => None,
+        };
+        return output.print(&ConfigReport {
+            path: config_path,
+            config,
+        });
+    }
+
     if !config_path.exists() {
         println!("{}", style("No configuration found.").dim());
         println!("Run {} to create one.", style("spuff init").cyan());
@@ -20,6 +47,9 @@ pub async fn show() -> Result<()> {
     println!("  Region:       {}", style(&config.region).white());
     println!("  Size:         {}", style(&config.size).white());
     println!("  Idle timeout: {}", style(&config.idle_timeout).yellow());
+    if let Some(max_lifetime) = &config.max_lifetime {
+        println!("  Max lifetime: {}", style(max_lifetime).yellow());
+    }
     println!("  Environment:  {}", style(&config.environment).white());
     print!("  Dotfiles:     ");
     match &config.dotfiles {
@@ -41,14 +71,72 @@ pub async fn show() -> Result<()> {
     Ok(())
 }
 
-pub async fn set(key: String, value: String) -> Result<()> {
+/// Replace credentials with a placeholder, so the output can be shared.
+fn redact(mut config: AppConfig) -> AppConfig {
+    if !config.api_token.is_empty() {
+        config.api_token = REDACTED.to_string();
+    }
+    let secrets = [
+        config.tailscale_authkey.as_mut(),
+        config.agent_token.as_mut(),
+        config
+            .aws
+            .as_mut()
+            .and_then(|aws| aws.secret_access_key.as_mut()),
+    ];
+    for secret in secrets.into_iter().flatten() {
+        *secret = REDACTED.to_string();
+    }
+    config
+}
+
+pub async fn set(key: String, value: Option<String>) -> Result<()> {
     let mut config = AppConfig::load().unwrap_or_default();
 
+    // Region and size are checked against the provider's catalog, or
+    // picked from it when no value is given
+    let value = match key.as_str() {
+        "region" | "size" => {
+            let catalog = Catalog::try_fetch(&config).await;
+            match (value, &catalog) {
+                (Some(value), Some(catalog)) => {
+                    if key == "region" {
+                        catalog.check_region(&value)?;
+                    } else {
+                        catalog.check_size(&value, &config.region)?;
+                    }
+                    value
+                }
+                (Some(value), None) => value,
+                (None, Some(catalog)) if key == "region" => catalog.pick_region(&config.region)?,
+                (None, Some(catalog)) => catalog.pick_size(&config.region, &config.size)?,
+                (None, None) => {
+                    return Err(SpuffError::Config(format!(
+                        "Could not list the {} catalog; pass a value: spuff config set {} <value>",
+                        config.provider, key
+                    )))
+                }
+            }
+        }
+        _ => value.ok_or_else(|| {
+            SpuffError::Config(format!("Missing value: spuff config set {} <value>", key))
+        })?,
+    };
+
     match key.as_str() {
         "provider" => config.provider = value.clone(),
         "region" => config.region = value.clone(),
         "size" => config.size = value.clone(),
         "idle_timeout" | "idle-timeout" => config.idle_timeout = value.clone(),
+        "max_lifetime" | "max-lifetime" => {
+            if crate::config::parse_duration(&value).is_none() {
+                return Err(SpuffError::Config(format!(
+                    "Invalid max_lifetime '{}'. Use format like '12h', '1d' or '0'",
+                    value
+                )));
+            }
+            config.max_lifetime = Some(value.clone()).filter(|v| v != "0");
+        }
         "environment" => config.environment = value.clone(),
         "dotfiles" => config.dotfiles = Some(value.clone()),
         "ssh_key" | "ssh-key" => config.ssh_key_path = value.clone(),
@@ -61,8 +149,8 @@ pub async fn set(key: String, value: String) -> Result<()> {
                 style(&key).red()
             );
             println!("\nAvailable keys:");
-            println!("  provider, region, size, idle_timeout, environment,");
-            println!("  dotfiles, ssh_key, ssh_user, tailscale");
+            println!("  provider, region, size, idle_timeout, max_lifetime,");
+            println!("  environment, dotfiles, ssh_key, ssh_user, tailscale");
             return Ok(());
         }
     }
@@ -96,3 +184,41 @@ pub async fn edit() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::aws::AwsConfig;
+
+    #[test]
+    fn test_redact_masks_secrets() {
+        let config = redact(AppConfig {
+            api_token: "dop_v1_secret".to_string(),
+            tailscale_authkey: Some("tskey-auth-secret".to_string()),
+            agent_token: Some("agent-secret".to_string()),
+            aws: Some(AwsConfig {
+                secret_access_key: Some("aws-secret".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        assert_eq!(config.api_token, REDACTED);
+        assert_eq!(config.tailscale_authkey.as_deref(), Some(REDACTED));
+        assert_eq!(config.agent_token.as_deref(), Some(REDACTED));
+        assert_eq!(
+            config.aws.unwrap().secret_access_key.as_deref(),
+            Some(REDACTED)
+        );
+        assert_eq!(config.region, AppConfig::default().region);
+    }
+
+    #[test]
+    fn test_redact_keeps_unset_secrets_empty() {
+        let config = redact(AppConfig::default());
+
+        assert!(config.api_token.is_empty());
+        assert!(config.tailscale_authkey.is_none());
+        assert!(config.agent_token.is_none());
+    }
+}
@@ -0,0 +1,160 @@
+//! Cost report (`spuff cost`)
+//!
+//! Shows what environments cost today, this month and over their lifetime,
+//! from the usage records kept in the state database, against the caps in
+//! `budget`.
+
+use chrono::Utc;
+use console::style;
+use serde::Serialize;
+
+use super::catalog::format_price;
+use crate::cli::output::OutputFormat;
+use crate::config::AppConfig;
+use crate::cost::{
+    budget_spend, by_environment, by_month, check_budget, report_currency, CapCheck,
+    EnvironmentCost, Spend,
+};
+use crate::error::Result;
+use crate::state::StateDb;
+
+/// `spuff cost` in `json`/`yaml` output. Amounts are in `currency`, except
+/// for `budgets`.
+#[derive(Debug, Serialize)]
+pub struct CostReport {
+    pub currency: String,
+    #[serde(flatten)]
+    pub spend: Spend,
+    /// Configured caps, closest to being exceeded first. In
+    /// `budget.currency`, counting only usage priced in it
+    pub budgets: Vec<CapCheck>,
+    pub environments: Vec<EnvironmentCost>,
+    /// Newest month first
+    pub months: Vec<MonthCost>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MonthCost {
+    /// "YYYY-MM"
+    pub month: String,
+    pub cost: f64,
+}
+
+pub async fn execute(config: &AppConfig, output: OutputFormat) -> Result<()> {
+    let usage = StateDb::open()?.list_usage()?;
+
+    if !output.is_text() {
+        let now = Utc::now();
+        let currency = report_currency(&usage, &config.budget);
+        return output.print(&CostReport {
+            currency: currency.to_string(),
+            spend: Spend::at(&usage, currency, now),
+            budgets: check_budget(
+                &config.budget,
+                &budget_spend(&config.budget, &usage, now),
+                std::time::Duration::ZERO,
+            ),
+            environments: by_environment(&usage, now),
+            months: by_month(&usage, now)
+                .into_iter()
+                .rev()
+                .map(|(month, cost)| MonthCost { month, cost })
+                .collect(),
+        });
+    }
+
+    if usage.is_empty() {
+        println!(
+            "{}",
+            style("No usage recorded yet. Costs are tracked for instances with a known price.")
+                .dim()
+        );
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let currency = report_currency(&usage, &config.budget);
+    let spend = Spend::at(&usage, currency, now);
+
+    println!("{}", style("Cost").bold().cyan());
+    println!();
+
+    let caps = check_budget(
+        &config.budget,
+        &budget_spend(&config.budget, &usage, now),
+        std::time::Duration::ZERO,
+    );
+    let budget_currency = &config.budget.currency;
+    for (label, period, spent) in [
+        ("Today", "daily", spend.today),
+        ("This month", "monthly", spend.month),
+    ] {
+        let cap = caps.iter().find(|c| c.period == period);
+        let budget = match cap {
+            Some(cap) if cap.exceeded() => style(format!(
+                "over the {} budget",
+                format_price(cap.cap, budget_currency, 2)
+            ))
+            .red(),
+            Some(cap) => style(format!(
+                "{:.0}% of {}",
+                cap.used() * 100.0,
+                format_price(cap.cap, budget_currency, 2)
+            ))
+            .dim(),
+            None => style(String::new()),
+        };
+        println!(
+            "  {:<12} {:>10}  {}",
+            label,
+            style(format_price(spent, currency, 2)).white().bold(),
+            budget
+        );
+    }
+    println!(
+        "  {:<12} {:>10}",
+        "Running",
+        style(format!(
+            "{}/h",
+            format_price(spend.hourly_rate, currency, 4)
+        ))
+        .white()
+    );
+
+    println!();
+    println!(
+        "  {:<20} {:<16} {:>10} {:>10} {:>10}",
+        style("ENVIRONMENT").dim(),
+        style("SIZE").dim(),
+        style("RATE").dim(),
+        style("MONTH").dim(),
+        style("TOTAL").dim()
+    );
+    for env in by_environment(&usage, now) {
+        let (marker, rate) = if env.hourly_rate > 0.0 {
+            (
+                style("●").green(),
+                format!("{}/h", format_price(env.hourly_rate, currency, 4)),
+            )
+        } else {
+            (style("○").dim(), "-".to_string())
+        };
+        println!(
+            "{} {:<20} {:<16} {:>10} {:>10} {:>10}",
+            marker,
+            style(&env.name).cyan(),
+            env.size,
+            rate,
+            format_price(env.month, currency, 2),
+            format_price(env.total, currency, 2)
+        );
+    }
+
+    println!();
+    println!("  {:<20} {:>10}", style("MONTH").dim(), style("COST").dim());
+    for (month, cost) in by_month(&usage, now).into_iter().rev() {
+        println!("  {:<20} {:>10}", month, format_price(cost, currency, 2));
+    }
+
+    Ok(())
+}
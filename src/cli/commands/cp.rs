@@ -0,0 +1,800 @@
+//! Copy files to and from the environment (`spuff cp`)
+//!
+//! The side prefixed with `:` is the remote one: `spuff cp -r ./dir :projects/x`
+//! uploads, `spuff cp :~/out.tar .` downloads. Remote paths are relative to
+//! the home directory. The last component of a source may be a glob
+//! (`:logs/*.log`).
+//!
+//! Transfers go over SFTP and keep permission bits and modification times.
+//! Each file is written next to its destination as a `.spuff-part` file
+//! (named after the source's size and mtime) and renamed into place once
+//! complete, so running an interrupted copy again resumes partial files and
+//! skips files that already match in size and mtime.
+
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use console::style;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+
+use crate::cli::output::OutputFormat;
+use crate::config::AppConfig;
+use crate::error::{Result, SpuffError};
+use crate::ssh::{RemoteEntry, RemoteMetadata, SftpClient};
+use crate::state::StateDb;
+use crate::utils::{format_bytes, format_duration, truncate};
+
+/// Suffix of files still being transferred.
+const PART_SUFFIX: &str = ".spuff-part";
+
+/// Bytes read and written at a time.
+const CHUNK_SIZE: usize = 256 * 1024;
+
+pub async fn execute(
+    config: &AppConfig,
+    mut paths: Vec<String>,
+    recursive: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let dest = paths
+        .pop()
+        .ok_or_else(|| SpuffError::Config("Missing destination".to_string()))?;
+    let sources = paths;
+    if sources.is_empty() {
+        return Err(SpuffError::Config("Missing source".to_string()));
+    }
+
+    let upload = match (
+        sources.iter().all(|s| is_remote(s)),
+        sources.iter().any(|s| is_remote(s)),
+        is_remote(&dest),
+    ) {
+        (false, false, true) => true,
+        (true, _, false) => false,
+        _ => {
+            return Err(SpuffError::Config(
+                "Exactly one side of the copy must be remote; prefix remote paths with ':' \
+                 (spuff cp ./src :app)"
+                    .to_string(),
+            ))
+        }
+    };
+
+    let (instance_ip, is_docker) = {
+        let db = StateDb::open()?;
+        let instance = db
+            .get_active_instance()?
+            .ok_or(SpuffError::NoActiveInstance)?;
+        let is_docker = instance.provider == "docker" || instance.provider == "local";
+        (instance.ip.clone(), is_docker)
+    };
+    if is_docker {
+        return Err(SpuffError::Config(
+            "spuff cp works over SSH; use 'docker cp' for local containers".to_string(),
+        ));
+    }
+
+    let client = crate::connector::ssh::client(&instance_ip, config).await?;
+    let remote = RemoteFs(client.sftp().await?);
+    let sources: Vec<String> = sources.iter().map(|s| location(s)).collect();
+    let dest = location(&dest);
+
+    let progress = ProgressBar::new(0).with_style(
+        ProgressStyle::with_template(
+            "  {spinner:.cyan} [{bar:30.cyan/dim}] {bytes}/{total_bytes} {bytes_per_sec} {eta} {wide_msg:.dim}",
+        )
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("━╸─"),
+    );
+    progress.enable_steady_tick(Duration::from_millis(120));
+
+    let start = Instant::now();
+    let result = if upload {
+        copy(&LocalFs, &remote, &sources, &dest, recursive, &progress).await
+    } else {
+        copy(&remote, &LocalFs, &sources, &dest, recursive, &progress).await
+    };
+    progress.finish_and_clear();
+    let summary = result?;
+
+    if !output.is_text() {
+        return output.print(&summary);
+    }
+
+    println!(
+        "  {} Copied {} {} ({}) in {}",
+        style("✓").green().bold(),
+        summary.copied,
+        if summary.copied == 1 { "file" } else { "files" },
+        format_bytes(summary.bytes),
+        format_duration(start.elapsed().as_secs() as i64)
+    );
+    if summary.skipped > 0 {
+        println!(
+            "  {} {} already up to date",
+            style("○").dim(),
+            summary.skipped
+        );
+    }
+
+    Ok(())
+}
+
+/// One side of a copy: the local filesystem or the remote over SFTP.
+#[async_trait]
+trait Fs: Sync {
+    /// Metadata of a path (following symlinks), `None` if it doesn't exist.
+    async fn metadata(&self, path: &str) -> Result<Option<RemoteMetadata>>;
+    async fn read_dir(&self, path: &str) -> Result<Vec<RemoteEntry>>;
+    async fn create_dir_all(&self, path: &str) -> Result<()>;
+    async fn open_read(&self, path: &str, offset: u64)
+        -> Result<Box<dyn AsyncRead + Send + Unpin>>;
+    async fn open_write(
+        &self,
+        path: &str,
+        append: bool,
+    ) -> Result<Box<dyn AsyncWrite + Send + Unpin>>;
+    async fn set_attributes(&self, path: &str, mode: Option<u32>, mtime: Option<u32>)
+        -> Result<()>;
+    /// Move `from` over `to`, replacing it.
+    async fn replace(&self, from: &str, to: &str) -> Result<()>;
+}
+
+struct RemoteFs(SftpClient);
+
+#[async_trait]
+impl Fs for RemoteFs {
+    async fn metadata(&self, path: &str) -> Result<Option<RemoteMetadata>> {
+        self.0.metadata(path).await
+    }
+
+    async fn read_dir(&self, path: &str) -> Result<Vec<RemoteEntry>> {
+        self.0.read_dir(path).await
+    }
+
+    async fn create_dir_all(&self, path: &str) -> Result<()> {
+        self.0.create_dir_all(path).await
+    }
+
+    async fn open_read(
+        &self,
+        path: &str,
+        offset: u64,
+    ) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        Ok(Box::new(self.0.open_read(path, offset).await?))
+    }
+
+    async fn open_write(
+        &self,
+        path: &str,
+        append: bool,
+    ) -> Result<Box<dyn AsyncWrite + Send + Unpin>> {
+        Ok(Box::new(self.0.open_write(path, append).await?))
+    }
+
+    async fn set_attributes(
+        &self,
+        path: &str,
+        mode: Option<u32>,
+        mtime: Option<u32>,
+    ) -> Result<()> {
+        self.0.set_attributes(path, mode, mtime).await
+    }
+
+    async fn replace(&self, from: &str, to: &str) -> Result<()> {
+        self.0.replace(from, to).await
+    }
+}
+
+struct LocalFs;
+
+impl LocalFs {
+    fn metadata_of(meta: &std::fs::Metadata) -> RemoteMetadata {
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(meta.permissions().mode() & 0o7777)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        RemoteMetadata {
+            is_dir: meta.is_dir(),
+            size: meta.len(),
+            mode,
+            mtime: meta
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|since| since.as_secs() as u32),
+        }
+    }
+}
+
+#[async_trait]
+impl Fs for LocalFs {
+    async fn metadata(&self, path: &str) -> Result<Option<RemoteMetadata>> {
+        match tokio::fs::metadata(path).await {
+            Ok(meta) => Ok(Some(Self::metadata_of(&meta))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error(path, e)),
+        }
+    }
+
+    async fn read_dir(&self, path: &str) -> Result<Vec<RemoteEntry>> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(path).map_err(|e| io_error(path, e))? {
+            let entry = entry.map_err(|e| io_error(path, e))?;
+            // Follow symlinks; skip dangling ones
+            let Ok(meta) = std::fs::metadata(entry.path()) else {
+                continue;
+            };
+            entries.push(RemoteEntry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                metadata: Self::metadata_of(&meta),
+            });
+        }
+        Ok(entries)
+    }
+
+    async fn create_dir_all(&self, path: &str) -> Result<()> {
+        tokio::fs::create_dir_all(path)
+            .await
+            .map_err(|e| io_error(path, e))
+    }
+
+    async fn open_read(
+        &self,
+        path: &str,
+        offset: u64,
+    ) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        let mut file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| io_error(path, e))?;
+        if offset > 0 {
+            file.seek(std::io::SeekFrom::Start(offset))
+                .await
+                .map_err(|e| io_error(path, e))?;
+        }
+        Ok(Box::new(file))
+    }
+
+    async fn open_write(
+        &self,
+        path: &str,
+        append: bool,
+    ) -> Result<Box<dyn AsyncWrite + Send + Unpin>> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .await
+            .map_err(|e| io_error(path, e))?;
+        Ok(Box::new(file))
+    }
+
+    async fn set_attributes(
+        &self,
+        path: &str,
+        mode: Option<u32>,
+        mtime: Option<u32>,
+    ) -> Result<()> {
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+                .map_err(|e| io_error(path, e))?;
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        if let Some(mtime) = mtime {
+            let time = UNIX_EPOCH + Duration::from_secs(mtime as u64);
+            std::fs::File::open(path)
+                .and_then(|file| file.set_modified(time))
+                .map_err(|e| io_error(path, e))?;
+        }
+        Ok(())
+    }
+
+    async fn replace(&self, from: &str, to: &str) -> Result<()> {
+        tokio::fs::rename(from, to)
+            .await
+            .map_err(|e| io_error(to, e))
+    }
+}
+
+/// A file or directory to copy.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    /// Path on the source side
+    source: String,
+    /// Path on the destination side
+    target: String,
+    metadata: RemoteMetadata,
+}
+
+/// Totals of a finished copy, also what `spuff cp` prints in `json`/`yaml`
+/// output.
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct Summary {
+    copied: usize,
+    skipped: usize,
+    bytes: u64,
+}
+
+/// Copy `sources` on `from` to `dest` on `to`, like `cp`: into `dest` if it
+/// is a directory (or there are several sources), as `dest` otherwise.
+async fn copy(
+    from: &dyn Fs,
+    to: &dyn Fs,
+    sources: &[String],
+    dest: &str,
+    recursive: bool,
+    progress: &ProgressBar,
+) -> Result<Summary> {
+    let entries = plan(from, to, sources, dest, recursive).await?;
+    progress.set_length(
+        entries
+            .iter()
+            .filter(|e| !e.metadata.is_dir)
+            .map(|e| e.metadata.size)
+            .sum(),
+    );
+
+    let mut summary = Summary::default();
+    for entry in &entries {
+        if entry.metadata.is_dir {
+            to.create_dir_all(&entry.target).await?;
+            continue;
+        }
+
+        progress.set_message(truncate(&entry.target, 60).into_owned());
+        match transfer(from, to, entry, progress).await? {
+            Some(bytes) => {
+                summary.copied += 1;
+                summary.bytes += bytes;
+            }
+            None => summary.skipped += 1,
+        }
+    }
+
+    // Directory attributes last: writing into a directory changes its mtime,
+    // and a read-only mode would block the files inside
+    for entry in entries.iter().rev().filter(|e| e.metadata.is_dir) {
+        if entry.metadata.mode.is_some() || entry.metadata.mtime.is_some() {
+            to.set_attributes(&entry.target, entry.metadata.mode, entry.metadata.mtime)
+                .await?;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Expand the sources and list everything to copy, parents before children.
+async fn plan(
+    from: &dyn Fs,
+    to: &dyn Fs,
+    sources: &[String],
+    dest: &str,
+    recursive: bool,
+) -> Result<Vec<Entry>> {
+    let mut matched = Vec::new();
+    for source in sources {
+        matched.extend(expand(from, source).await?);
+    }
+
+    let mut entries = Vec::new();
+    let into_dir = match to.metadata(dest).await? {
+        Some(meta) if meta.is_dir => true,
+        Some(_) if matched.len() > 1 => {
+            return Err(SpuffError::Config(format!("{} is not a directory", dest)))
+        }
+        Some(_) => false,
+        None if matched.len() > 1 || dest.ends_with('/') => {
+            entries.push(Entry {
+                source: String::new(),
+                target: dest.to_string(),
+                metadata: RemoteMetadata {
+                    is_dir: true,
+                    size: 0,
+                    mode: None,
+                    mtime: None,
+                },
+            });
+            true
+        }
+        None => false,
+    };
+
+    for (path, metadata) in matched {
+        let target = match file_name(&path) {
+            Some(name) if into_dir => join(dest, name),
+            _ => dest.to_string(),
+        };
+        if metadata.is_dir && !recursive {
+            return Err(SpuffError::Config(format!(
+                "{} is a directory (use -r to copy it)",
+                path
+            )));
+        }
+
+        let mut stack = vec![(path, target, metadata)];
+        while let Some((source, target, metadata)) = stack.pop() {
+            if metadata.is_dir {
+                let mut children = from.read_dir(&source).await?;
+                // Reversed, so they come off the stack in order
+                children.sort_by(|a, b| b.name.cmp(&a.name));
+                for child in children {
+                    if child.name.ends_with(PART_SUFFIX) {
+                        continue;
+                    }
+                    stack.push((
+                        join(&source, &child.name),
+                        join(&target, &child.name),
+                        child.metadata,
+                    ));
+                }
+            }
+            entries.push(Entry {
+                source,
+                target,
+                metadata,
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Paths matching `source`; only its last component may hold wildcards.
+async fn expand(fs: &dyn Fs, source: &str) -> Result<Vec<(String, RemoteMetadata)>> {
+    let (parent, pattern) = match source.rsplit_once('/') {
+        Some((parent, pattern)) => (Some(parent), pattern),
+        None => (None, source),
+    };
+
+    if !pattern.contains(['*', '?']) {
+        let metadata = fs
+            .metadata(source)
+            .await?
+            .ok_or_else(|| SpuffError::Config(format!("{}: no such file or directory", source)))?;
+        return Ok(vec![(source.to_string(), metadata)]);
+    }
+
+    let dir = match parent {
+        Some("") => "/",
+        Some(parent) => parent,
+        None => ".",
+    };
+    let mut matches: Vec<(String, RemoteMetadata)> = fs
+        .read_dir(dir)
+        .await?
+        .into_iter()
+        .filter(|entry| wildcard_match(pattern, &entry.name))
+        .map(|entry| {
+            let path = match parent {
+                Some(_) => join(dir, &entry.name),
+                None => entry.name,
+            };
+            (path, entry.metadata)
+        })
+        .collect();
+
+    if matches.is_empty() {
+        return Err(SpuffError::Config(format!("{}: no matches", source)));
+    }
+    matches.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(matches)
+}
+
+/// Copy one file, resuming a partial transfer. Returns the bytes the file
+/// holds, or `None` if the destination was already up to date.
+async fn transfer(
+    from: &dyn Fs,
+    to: &dyn Fs,
+    entry: &Entry,
+    progress: &ProgressBar,
+) -> Result<Option<u64>> {
+    let size = entry.metadata.size;
+    if let Some(existing) = to.metadata(&entry.target).await? {
+        if !existing.is_dir
+            && existing.size == size
+            && entry.metadata.mtime.is_some()
+            && existing.mtime == entry.metadata.mtime
+        {
+            progress.inc(size);
+            return Ok(None);
+        }
+    }
+
+    let part = part_path(entry);
+    let offset = match to.metadata(&part).await? {
+        Some(partial) if !partial.is_dir && partial.size <= size => partial.size,
+        _ => 0,
+    };
+    progress.inc(offset);
+
+    let mut reader = from.open_read(&entry.source, offset).await?;
+    let mut writer = to.open_write(&part, offset > 0).await?;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = reader
+            .read(&mut buf)
+            .await
+            .map_err(|e| io_error(&entry.source, e))?;
+        if n == 0 {
+            break;
+        }
+        writer
+            .write_all(&buf[..n])
+            .await
+            .map_err(|e| io_error(&part, e))?;
+        progress.inc(n as u64);
+    }
+    writer.shutdown().await.map_err(|e| io_error(&part, e))?;
+    drop(writer);
+
+    to.replace(&part, &entry.target).await?;
+    to.set_attributes(&entry.target, entry.metadata.mode, entry.metadata.mtime)
+        .await?;
+    Ok(Some(size))
+}
+
+/// Where a file is written until complete. Naming it after the source's size
+/// and mtime keeps a changed source from being appended to a stale prefix.
+fn part_path(entry: &Entry) -> String {
+    format!(
+        "{}.{:x}-{:x}{}",
+        entry.target,
+        entry.metadata.size,
+        entry.metadata.mtime.unwrap_or(0),
+        PART_SUFFIX
+    )
+}
+
+fn is_remote(arg: &str) -> bool {
+    arg.starts_with(':')
+}
+
+/// Path of a command-line location on its side. Remote paths drop the `:`
+/// and a leading `~/`, as SFTP resolves relative paths from the home
+/// directory.
+fn location(arg: &str) -> String {
+    let Some(remote) = arg.strip_prefix(':') else {
+        return arg.to_string();
+    };
+    match remote {
+        "" | "~" | "~/" => ".".to_string(),
+        _ => remote.strip_prefix("~/").unwrap_or(remote).to_string(),
+    }
+}
+
+/// Last component of a path, `None` for `.`, `..` and roots.
+fn file_name(path: &str) -> Option<&str> {
+    let name = path
+        .trim_end_matches('/')
+        .rsplit(['/', std::path::MAIN_SEPARATOR])
+        .next()?;
+    match name {
+        "" | "." | ".." => None,
+        _ => Some(name),
+    }
+}
+
+fn join(base: &str, name: &str) -> String {
+    if base.ends_with('/') {
+        format!("{}{}", base, name)
+    } else {
+        format!("{}/{}", base, name)
+    }
+}
+
+/// Shell-style wildcard match (`*`, `?`). Like the shell, wildcards don't
+/// match a leading `.`.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Last `*` seen and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                n += 1;
+            }
+            Some(&c) if c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the `*` swallow one more character
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn io_error(path: &str, e: std::io::Error) -> SpuffError {
+    SpuffError::Io(std::io::Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn path(p: &Path) -> String {
+        p.to_string_lossy().into_owned()
+    }
+
+    fn mtime_of(p: &Path) -> u32 {
+        let modified = std::fs::metadata(p).unwrap().modified().unwrap();
+        modified.duration_since(UNIX_EPOCH).unwrap().as_secs() as u32
+    }
+
+    fn set_mtime(p: &Path, secs: u64) {
+        std::fs::File::open(p)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_location() {
+        assert_eq!(location("./dir"), "./dir");
+        assert_eq!(location(":~/projects/x"), "projects/x");
+        assert_eq!(location(":/etc/hosts"), "/etc/hosts");
+        assert_eq!(location(":"), ".");
+        assert_eq!(location(":~"), ".");
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("a/b/out.tar"), Some("out.tar"));
+        assert_eq!(file_name("dir/"), Some("dir"));
+        assert_eq!(file_name("."), None);
+        assert_eq!(file_name("/"), None);
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.log", "app.log"));
+        assert!(wildcard_match("app-?.log", "app-1.log"));
+        assert!(wildcard_match("*a*b", "xaybab"));
+        assert!(!wildcard_match("*.log", "app.log.gz"));
+        assert!(!wildcard_match("*", ".env"));
+        assert!(wildcard_match(".*", ".env"));
+    }
+
+    #[tokio::test]
+    async fn test_copy_directory_keeps_mode_and_mtime() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(src.path().join("app/bin")).unwrap();
+        std::fs::write(src.path().join("app/README"), "hello").unwrap();
+        std::fs::write(src.path().join("app/bin/run"), "#!/bin/sh\n").unwrap();
+        set_mtime(&src.path().join("app/README"), 1_700_000_000);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(
+                src.path().join("app/bin/run"),
+                std::fs::Permissions::from_mode(0o750),
+            )
+            .unwrap();
+        }
+
+        let summary = copy(
+            &LocalFs,
+            &LocalFs,
+            &[path(&src.path().join("app"))],
+            &path(dst.path()),
+            true,
+            &ProgressBar::hidden(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(summary.copied, 2);
+        assert_eq!(summary.bytes, 15);
+        let copied = dst.path().join("app/README");
+        assert_eq!(std::fs::read_to_string(&copied).unwrap(), "hello");
+        assert_eq!(mtime_of(&copied), 1_700_000_000);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dst.path().join("app/bin/run"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o750);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copy_resumes_partial_file_and_skips_up_to_date() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        std::fs::write(src.path().join("big.bin"), "0123456789").unwrap();
+        std::fs::write(src.path().join("done.txt"), "done").unwrap();
+        set_mtime(&src.path().join("big.bin"), 1_700_000_000);
+        set_mtime(&src.path().join("done.txt"), 1_700_000_000);
+
+        // done.txt made it last time, big.bin stopped halfway
+        std::fs::write(dst.path().join("done.txt"), "done").unwrap();
+        set_mtime(&dst.path().join("done.txt"), 1_700_000_000);
+        let target = path(&dst.path().join("big.bin"));
+        let partial = part_path(&Entry {
+            source: String::new(),
+            target,
+            metadata: RemoteMetadata {
+                is_dir: false,
+                size: 10,
+                mode: None,
+                mtime: Some(1_700_000_000),
+            },
+        });
+        // Differs from the source, to show it was kept rather than rewritten
+        std::fs::write(&partial, "ABCDE").unwrap();
+
+        let summary = copy(
+            &LocalFs,
+            &LocalFs,
+            &[path(&src.path().join("*"))],
+            &path(dst.path()),
+            false,
+            &ProgressBar::hidden(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(summary.copied, 1);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(
+            std::fs::read_to_string(dst.path().join("big.bin")).unwrap(),
+            "ABCDE56789"
+        );
+        assert!(!Path::new(&partial).exists());
+    }
+
+    #[tokio::test]
+    async fn test_directory_needs_recursive() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+
+        let err = copy(
+            &LocalFs,
+            &LocalFs,
+            &[path(src.path())],
+            &path(dst.path()),
+            false,
+            &ProgressBar::hidden(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("use -r"));
+    }
+}
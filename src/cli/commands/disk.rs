@@ -0,0 +1,305 @@
+use console::style;
+use dialoguer::Confirm;
+use serde::Serialize;
+
+use crate::cli::output::OutputFormat;
+use crate::config::AppConfig;
+use crate::error::{Result, SpuffError};
+use crate::project_config::{PersistentDisk, ProjectConfig};
+use crate::provider::{create_provider, create_provider_for};
+use crate::state::StateDb;
+
+/// `spuff disk attach` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct AttachedDisk {
+    pub name: String,
+    /// Provider's volume ID
+    pub id: String,
+    /// Environment the disk is attached to
+    pub environment: String,
+    /// Mount path on the instance
+    pub mount: String,
+}
+
+/// `spuff disk delete` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct DeletedDisk {
+    pub name: String,
+    /// Provider's volume ID
+    pub id: String,
+    /// False when the confirmation was declined
+    pub deleted: bool,
+}
+
+pub async fn list(config: &AppConfig, output: OutputFormat) -> Result<()> {
+    let provider = create_provider(config)?;
+    let volumes = provider.list_volumes().await?;
+
+    if !output.is_text() {
+        return output.print(&volumes);
+    }
+
+    if volumes.is_empty() {
+        println!("{}", style("No persistent disks found.").dim());
+        return Ok(());
+    }
+
+    println!("{}", style("Persistent disks").bold().cyan());
+    println!();
+
+    for volume in volumes {
+        let name = volume.name.strip_prefix("spuff-").unwrap_or(&volume.name);
+        let size = match volume.size_gb {
+            0 => String::new(),
+            gb => format!("{} GB", gb),
+        };
+        println!(
+            "  {} {} {}",
+            style(name).cyan(),
+            style(size).white(),
+            style(volume.region.as_deref().unwrap_or_default()).dim()
+        );
+        match &volume.attached_to {
+            Some(instance_id) => println!(
+                "    {} attached to {}",
+                style("●").green(),
+                style(instance_id).dim()
+            ),
+            None => println!("    {} detached", style("○").dim()),
+        }
+        if let Some(created) = volume.created_at {
+            println!(
+                "    Created: {}",
+                style(created.format("%Y-%m-%d %H:%M")).dim()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Attach an existing disk to the active environment and mount it.
+///
+/// The mount path comes from `--mount`, or from the disk's entry in
+/// `spuff.yaml` when there is one.
+pub async fn attach(
+    config: &AppConfig,
+    name: String,
+    mount: Option<String>,
+    output: OutputFormat,
+) -> Result<()> {
+    let instance = StateDb::open()?
+        .get_active_instance()?
+        .ok_or(SpuffError::NoActiveInstance)?;
+    let provider = create_provider_for(config, &instance.provider, &instance.region)?;
+
+    let name = name.strip_prefix("spuff-").unwrap_or(&name).to_string();
+    let mount = match mount {
+        Some(mount) => mount,
+        None => ProjectConfig::load_from_cwd()
+            .ok()
+            .flatten()
+            .and_then(|pc| {
+                pc.persistent_disks
+                    .into_iter()
+                    .find(|d| d.name == name)
+                    .map(|d| d.mount)
+            })
+            .ok_or_else(|| {
+                SpuffError::Config(format!(
+                    "Disk '{}' is not in spuff.yaml; pass --mount <path>",
+                    name
+                ))
+            })?,
+    };
+    let disk = PersistentDisk {
+        name,
+        size: 0,
+        mount,
+    };
+    disk.validate_mount()?;
+
+    let volume = provider
+        .list_volumes()
+        .await?
+        .into_iter()
+        .find(|v| v.name == disk.volume_name())
+        .ok_or_else(|| SpuffError::Config(format!("No persistent disk named '{}'", disk.name)))?;
+
+    if let Some(region) = volume.region.as_deref() {
+        if region != instance.region {
+            return Err(SpuffError::Config(format!(
+                "Disk '{}' is in {}, but {} is in {}",
+                disk.name, region, instance.name, instance.region
+            )));
+        }
+    }
+
+    match volume.attached_to.as_deref() {
+        Some(id) if id == instance.id => {}
+        Some(id) => {
+            return Err(SpuffError::Config(format!(
+                "Disk '{}' is attached to instance {}; run 'spuff down' on it first",
+                disk.name, id
+            )));
+        }
+        None => {
+            output.println(format_args!(
+                "{} Attaching {} to {}...",
+                style("→").yellow().bold(),
+                style(&volume.name).cyan(),
+                style(&instance.name).cyan()
+            ));
+            provider.attach_volume(&volume.id, &instance.id).await?;
+        }
+    }
+
+    if let Some(device) = provider.volume_device(&volume) {
+        output.println(format_args!(
+            "{} Mounting at {}...",
+            style("→").yellow().bold(),
+            style(&disk.mount).cyan()
+        ));
+        crate::connector::ssh::run_command(
+            &instance.ip,
+            config,
+            &format!(
+                "sudo sh -c {}",
+                shell_quote(&mount_script(&device, &disk.mount, &config.ssh_user))
+            ),
+        )
+        .await?;
+    }
+
+    if !output.is_text() {
+        return output.print(&AttachedDisk {
+            name: disk.name,
+            id: volume.id,
+            environment: instance.name,
+            mount: disk.mount,
+        });
+    }
+
+    println!(
+        "{} Disk {} mounted at {}.",
+        style("✓").green().bold(),
+        style(&disk.name).cyan(),
+        style(&disk.mount).cyan()
+    );
+
+    Ok(())
+}
+
+pub async fn delete(
+    config: &AppConfig,
+    name: String,
+    force: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let provider = create_provider(config)?;
+
+    let volume_name = if name.starts_with("spuff-") {
+        name.clone()
+    } else {
+        format!("spuff-{}", name)
+    };
+    let volume = provider
+        .list_volumes()
+        .await?
+        .into_iter()
+        .find(|v| v.name == volume_name)
+        .ok_or_else(|| SpuffError::Config(format!("No persistent disk named '{}'", name)))?;
+
+    if !force {
+        output.println(format_args!(
+            "  {} All data on {} will be lost.",
+            style("⚠").yellow().bold(),
+            style(&volume.name).cyan()
+        ));
+        let confirmed = Confirm::new()
+            .with_prompt(format!("  {} Delete this disk?", style("?").cyan().bold()))
+            .default(false)
+            .interact()?;
+
+        if !confirmed {
+            output.println("");
+            output.println(format_args!("  {}", style("Cancelled.").dim()));
+            if !output.is_text() {
+                return output.print(&DeletedDisk {
+                    name: volume.name,
+                    id: volume.id,
+                    deleted: false,
+                });
+            }
+            return Ok(());
+        }
+    }
+
+    if let Some(instance_id) = &volume.attached_to {
+        output.println(format_args!(
+            "{} Detaching from {}...",
+            style("→").yellow().bold(),
+            style(instance_id).cyan()
+        ));
+        provider.detach_volume(&volume.id, instance_id).await?;
+    }
+
+    output.println(format_args!(
+        "{} Deleting disk {}...",
+        style("→").yellow().bold(),
+        style(&volume.name).cyan()
+    ));
+
+    provider.delete_volume(&volume.id).await?;
+
+    if !output.is_text() {
+        return output.print(&DeletedDisk {
+            name: volume.name,
+            id: volume.id,
+            deleted: true,
+        });
+    }
+
+    println!("{} Disk deleted.", style("✓").green().bold());
+
+    Ok(())
+}
+
+/// Format the device on first use and mount it, the way the bootstrap's
+/// `/opt/spuff/disks.sh` does for disks attached at creation.
+fn mount_script(device: &str, mount: &str, user: &str) -> String {
+    format!(
+        r#"set -e
+for _ in $(seq 1 60); do [ -e {device} ] && break; sleep 2; done
+blkid {device} >/dev/null 2>&1 || {{ mkfs.ext4 -q {device}; fresh=1; }}
+mkdir -p {mount}
+uuid=$(blkid -s UUID -o value {device})
+grep -q "UUID=$uuid " /etc/fstab || echo "UUID=$uuid {mount} ext4 defaults,nofail,discard 0 2" >> /etc/fstab
+mountpoint -q {mount} || mount {mount}
+[ -z "${{fresh:-}}" ] || chown {user}:{user} {mount}"#,
+        device = shell_quote(device),
+        mount = mount,
+        user = shell_quote(user),
+    )
+}
+
+/// Quote a string for a POSIX shell.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mount_script_formats_only_blank_devices() {
+        let script = mount_script("/dev/disk/by-id/scsi-0DO_Volume_spuff-data", "/data", "dev");
+
+        assert!(script.contains(
+            "blkid '/dev/disk/by-id/scsi-0DO_Volume_spuff-data' >/dev/null 2>&1 || { mkfs.ext4"
+        ));
+        assert!(script.contains("ext4 defaults,nofail,discard 0 2\" >> /etc/fstab"));
+        assert!(script.ends_with("chown 'dev':'dev' /data"));
+    }
+}
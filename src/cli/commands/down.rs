@@ -1,14 +1,28 @@
 use console::style;
 use dialoguer::Confirm;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+use crate::cli::output::OutputFormat;
 use crate::config::AppConfig;
+use crate::connector::agent::AgentClient;
 use crate::error::{Result, SpuffError};
 use crate::project_config::ProjectConfig;
 use crate::provider::create_provider;
-use crate::state::StateDb;
+use crate::state::{LocalInstance, StateDb};
 use crate::utils::format_elapsed;
-use crate::volume::{SshfsLocalCommands, VolumeState};
+use crate::volume::VolumeState;
+
+use super::volume::unmount_local;
+
+/// `spuff down` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct DownResult {
+    pub name: String,
+    /// False when the confirmation was declined
+    pub destroyed: bool,
+    /// Snapshot taken with `--snapshot`, if it succeeded
+    pub snapshot_id: Option<String>,
+}
 
 /// Response from the shutdown endpoint.
 #[derive(Debug, Deserialize)]
@@ -27,7 +41,12 @@ struct ShutdownStep {
     message: String,
 }
 
-pub async fn execute(config: &AppConfig, create_snapshot: bool, force: bool) -> Result<()> {
+pub async fn execute(
+    config: &AppConfig,
+    create_snapshot: bool,
+    force: bool,
+    output: OutputFormat,
+) -> Result<()> {
     // Get instance info and release DB lock immediately to avoid contention
     let instance = {
         let db = StateDb::open()?;
@@ -38,25 +57,39 @@ pub async fn execute(config: &AppConfig, create_snapshot: bool, force: bool) ->
         inst
     };
 
-    super::ssh::print_banner();
+    if output.is_text() {
+        super::ssh::print_banner();
+    }
 
     // Instance info card
-    println!(
+    output.println(format_args!(
         "  {} {} {}",
         style("●").green().bold(),
         style(&instance.name).white().bold(),
         style(format!("({})", &instance.ip)).dim()
-    );
-    println!();
-    println!("  {}      {}", style("Provider").dim(), &instance.provider);
-    println!("  {}        {}", style("Region").dim(), &instance.region);
-    println!("  {}          {}", style("Size").dim(), &instance.size);
-    println!(
+    ));
+    output.println("");
+    output.println(format_args!(
+        "  {}      {}",
+        style("Provider").dim(),
+        &instance.provider
+    ));
+    output.println(format_args!(
+        "  {}        {}",
+        style("Region").dim(),
+        &instance.region
+    ));
+    output.println(format_args!(
+        "  {}          {}",
+        style("Size").dim(),
+        &instance.size
+    ));
+    output.println(format_args!(
         "  {}        {}",
         style("Uptime").dim(),
         style(format_elapsed(instance.created_at)).yellow()
-    );
-    println!();
+    ));
+    output.println("");
 
     if !force {
         let confirmed = Confirm::new()
@@ -68,8 +101,15 @@ pub async fn execute(config: &AppConfig, create_snapshot: bool, force: bool) ->
             .interact()?;
 
         if !confirmed {
-            println!();
-            println!("  {}", style("Cancelled.").dim());
+            output.println("");
+            output.println(format_args!("  {}", style("Cancelled.").dim()));
+            if !output.is_text() {
+                return output.print(&DownResult {
+                    name: instance.name,
+                    destroyed: false,
+                    snapshot_id: None,
+                });
+            }
             return Ok(());
         }
     }
@@ -79,31 +119,31 @@ pub async fn execute(config: &AppConfig, create_snapshot: bool, force: bool) ->
     // Step 1: Graceful shutdown on the remote VM (skip for Docker)
     // This runs pre_down hooks and stops docker-compose services
     if is_docker {
-        println!(
+        output.println(format_args!(
             "  {} {}",
             style("○").dim(),
             style("Graceful shutdown skipped (Docker container)").dim()
-        );
+        ));
     } else {
-        println!(
+        output.println(format_args!(
             "  {} {}",
             style("◐").cyan(),
             style("Running graceful shutdown on VM...").dim()
-        );
+        ));
 
         match graceful_shutdown(&instance.ip, config).await {
             Ok(response) => {
                 if response.success {
-                    println!(
+                    output.println(format_args!(
                         "  {} Graceful shutdown completed in {}ms",
                         style("✓").green().bold(),
                         response.duration_ms
-                    );
+                    ));
                 } else {
-                    println!(
+                    output.println(format_args!(
                         "  {} Graceful shutdown completed with warnings",
                         style("!").yellow().bold()
-                    );
+                    ));
                 }
                 // Print step details if verbose
                 for step in &response.steps {
@@ -112,111 +152,75 @@ pub async fn execute(config: &AppConfig, create_snapshot: bool, force: bool) ->
                     } else {
                         style("✕").red()
                     };
-                    println!("    {} {} - {}", icon, step.name, step.message);
+                    output.println(format_args!(
+                        "    {} {} - {}",
+                        icon, step.name, step.message
+                    ));
                 }
             }
             Err(e) => {
                 // Log the error but continue - VM might already be unreachable
-                println!(
+                output.println(format_args!(
                     "  {} Graceful shutdown skipped: {}",
                     style("!").yellow().bold(),
                     e
-                );
+                ));
             }
         }
     }
-    println!();
+    output.println("");
 
     // Step 2: Unmount any locally mounted volumes BEFORE destroying the instance
     // This prevents SSHFS from hanging when the remote server disappears
     // Skip for Docker as it uses bind mounts, not SSHFS
     if !is_docker {
-        let project_config = ProjectConfig::load_from_cwd().ok().flatten();
-        let mut volume_state = VolumeState::load_or_default();
-
-        // Collect all mount points to unmount
-        let mut mount_points_to_unmount: Vec<String> = Vec::new();
-
-        // Add mounts from project config
-        if let Some(ref pc) = project_config {
-            for vol in &pc.volumes {
-                let mount_point =
-                    vol.resolve_mount_point(Some(&instance.name), pc.base_dir.as_deref());
-                if !mount_points_to_unmount.contains(&mount_point) {
-                    mount_points_to_unmount.push(mount_point);
-                }
-            }
-        }
-
-        // Add tracked mounts from state
-        for m in &volume_state.mounts {
-            if !mount_points_to_unmount.contains(&m.mount_point) {
-                mount_points_to_unmount.push(m.mount_point.clone());
-            }
-        }
-
-        if !mount_points_to_unmount.is_empty() {
-            println!();
-            println!(
-                "  {} {}",
-                style("◐").cyan(),
-                style("Unmounting local volumes...").dim()
-            );
-
-            for mount_point in &mount_points_to_unmount {
-                print!("    {} {}", style("→").dim(), style(&mount_point).white());
-
-                match SshfsLocalCommands::unmount(mount_point).await {
-                    Ok(_) => {
-                        println!(" {}", style("✓").green());
-                        volume_state.remove_mount(mount_point);
-                    }
-                    Err(e) => {
-                        println!(" {}", style("✕").red());
-                        tracing::warn!("Failed to unmount {}: {}", mount_point, e);
-                        // Continue with other unmounts even if one fails
-                    }
-                }
-            }
+        unmount_volumes(&instance, output).await;
+    }
 
-            // Clear volume state - log errors but don't fail the down operation
-            volume_state.clear();
-            if let Err(e) = volume_state.save() {
-                tracing::warn!("Failed to clear volume state: {}", e);
-            }
-        }
+    if let Err(e) = super::tunnel::stop_daemon(&instance.id).await {
+        tracing::warn!("Failed to stop tunnels: {}", e);
     }
 
     let provider = create_provider(config)?;
 
+    let mut snapshot_id = None;
     if create_snapshot {
-        println!();
-        println!(
+        output.println("");
+        output.println(format_args!(
             "  {} {}",
             style("◐").cyan(),
             style("Creating snapshot...").dim()
-        );
+        ));
         let snapshot_name = format!("{}-snapshot", instance.name);
         match provider.create_snapshot(&instance.id, &snapshot_name).await {
             Ok(snapshot) => {
-                println!(
+                match StateDb::open() {
+                    Ok(db) => super::snapshot::record(&db, &instance, &snapshot),
+                    Err(e) => tracing::warn!("Failed to record snapshot {}: {}", snapshot.id, e),
+                }
+                output.println(format_args!(
                     "  {} Snapshot: {}",
                     style("✓").green().bold(),
                     style(&snapshot.id).cyan()
-                );
+                ));
+                snapshot_id = Some(snapshot.id);
             }
             Err(e) => {
-                println!("  {} Snapshot failed: {}", style("!").yellow().bold(), e);
+                output.println(format_args!(
+                    "  {} Snapshot failed: {}",
+                    style("!").yellow().bold(),
+                    e
+                ));
             }
         }
     }
 
-    println!();
-    println!(
+    output.println("");
+    output.println(format_args!(
         "  {} {}",
         style("◐").red(),
         style("Destroying instance...").dim()
-    );
+    ));
     provider.destroy_instance(&instance.id).await?;
 
     // Reopen DB briefly just to remove the instance record
@@ -227,64 +231,86 @@ pub async fn execute(config: &AppConfig, create_snapshot: bool, force: bool) ->
         // db dropped here, releasing lock
     }
 
-    println!(
+    output.println(format_args!(
         "  {} Instance {} destroyed.",
         style("✓").green().bold(),
         style(&instance.name).cyan()
-    );
-    println!();
+    ));
+    output.println("");
+
+    if !output.is_text() {
+        return output.print(&DownResult {
+            name: instance.name,
+            destroyed: true,
+            snapshot_id,
+        });
+    }
 
     Ok(())
 }
 
-/// Call the agent's /shutdown endpoint for graceful shutdown.
-async fn graceful_shutdown(ip: &str, config: &AppConfig) -> Result<ShutdownResponse> {
-    // Use SSH to tunnel to the agent and POST to /shutdown
-    let output = crate::connector::ssh::run_command(
-        ip,
-        config,
-        "curl -s -X POST http://127.0.0.1:7575/shutdown",
-    )
-    .await?;
-
-    // Extract JSON from output (may contain banner text from shell profile)
-    let json_str = extract_json(&output);
+/// Unmount the environment's SSHFS volumes and drop them from the volume state.
+///
+/// Must run before the instance goes away, or SSHFS hangs on the dead server.
+pub(super) async fn unmount_volumes(instance: &LocalInstance, output: OutputFormat) {
+    let project_config = ProjectConfig::load_from_cwd().ok().flatten();
+    let mut volume_state = VolumeState::load_or_default();
+
+    // Collect all mount points to unmount
+    let mut mount_points_to_unmount: Vec<String> = Vec::new();
+
+    // Add mounts from project config
+    if let Some(ref pc) = project_config {
+        for vol in &pc.volumes {
+            let mount_point = vol.resolve_mount_point(Some(&instance.name), pc.base_dir.as_deref());
+            if !mount_points_to_unmount.contains(&mount_point) {
+                mount_points_to_unmount.push(mount_point);
+            }
+        }
+    }
 
-    serde_json::from_str(json_str).map_err(|e| {
-        SpuffError::Provider(format!(
-            "Failed to parse shutdown response: {}. Response: {}",
-            e, output
-        ))
-    })
-}
+    // Add tracked mounts from state, for this VM only: other environments
+    // may have their own mounts running
+    for m in volume_state.mounts_for_vm(&instance.ip) {
+        if !mount_points_to_unmount.contains(&m.mount_point) {
+            mount_points_to_unmount.push(m.mount_point.clone());
+        }
+    }
 
-/// Extract JSON from output that may contain banner text before/after.
-fn extract_json(output: &str) -> &str {
-    let brace_pos = output.find('{');
+    if !mount_points_to_unmount.is_empty() {
+        output.println("");
+        output.println(format_args!(
+            "  {} {}",
+            style("◐").cyan(),
+            style("Unmounting local volumes...").dim()
+        ));
 
-    let Some(start_pos) = brace_pos else {
-        return output.trim();
-    };
+        for mount_point in &mount_points_to_unmount {
+            let line = format!("    {} {}", style("→").dim(), style(&mount_point).white());
 
-    // Extract JSON object
-    let mut depth = 0;
-    let mut end_pos = None;
-    for (i, c) in output[start_pos..].char_indices() {
-        match c {
-            '{' => depth += 1,
-            '}' => {
-                depth -= 1;
-                if depth == 0 {
-                    end_pos = Some(start_pos + i);
-                    break;
+            match unmount_local(mount_point).await {
+                Ok(_) => {
+                    output.println(format_args!("{} {}", line, style("✓").green()));
+                    volume_state.remove_mount(mount_point);
+                }
+                Err(e) => {
+                    output.println(format_args!("{} {}", line, style("✕").red()));
+                    tracing::warn!("Failed to unmount {}: {}", mount_point, e);
+                    // Continue with other unmounts even if one fails
                 }
             }
-            _ => {}
+        }
+
+        // Unmounted entries were removed above - log errors but don't fail
+        // the caller
+        if let Err(e) = volume_state.save() {
+            tracing::warn!("Failed to save volume state: {}", e);
         }
     }
-    if let Some(end) = end_pos {
-        return &output[start_pos..=end];
-    }
+}
 
-    output.trim()
+/// Call the agent's /shutdown endpoint for graceful shutdown.
+async fn graceful_shutdown(ip: &str, config: &AppConfig) -> Result<ShutdownResponse> {
+    let agent = AgentClient::connect(ip, config).await?;
+    agent.post("/shutdown", &serde_json::json!({})).await
 }
@@ -0,0 +1,214 @@
+//! Environment commands (`spuff ls`, `spuff use`)
+//!
+//! Environments are the instances tracked in the local state database.
+//! One of them is active; the others are reachable with `--env <name>`.
+//! Hibernated environments are listed too, until they are resumed.
+
+use chrono::{DateTime, Utc};
+use console::style;
+use serde::Serialize;
+
+use crate::cli::output::OutputFormat;
+use crate::config::AppConfig;
+use crate::cost::HourlyPrice;
+use crate::error::{Result, SpuffError};
+use crate::state::{Hibernation, LocalInstance, StateDb};
+use crate::utils::format_elapsed;
+
+/// Lifecycle state of an environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvironmentState {
+    Running,
+    Stopped,
+    Hibernated,
+}
+
+/// An environment, as printed by `spuff ls`, `spuff status` and `spuff up`
+/// in `json`/`yaml` output.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvironmentInfo {
+    pub name: String,
+    pub state: EnvironmentState,
+    /// Whether commands without `--env` operate on it
+    pub active: bool,
+    pub provider: String,
+    pub region: String,
+    pub size: String,
+    /// Provider-specific instance ID (container ID on Docker); none while hibernated
+    pub id: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub uptime_seconds: Option<i64>,
+    pub stopped_at: Option<DateTime<Utc>>,
+    pub hourly_price: Option<HourlyPrice>,
+    /// Snapshot `spuff resume` recreates a hibernated environment from
+    pub snapshot_id: Option<String>,
+}
+
+impl EnvironmentInfo {
+    pub fn from_instance(instance: &LocalInstance, active: bool) -> Self {
+        let state = match instance.stopped_at {
+            Some(_) => EnvironmentState::Stopped,
+            None => EnvironmentState::Running,
+        };
+        Self {
+            name: instance.name.clone(),
+            state,
+            active,
+            provider: instance.provider.clone(),
+            region: instance.region.clone(),
+            size: instance.size.clone(),
+            id: Some(instance.id.clone()),
+            ip: Some(instance.ip.clone()),
+            created_at: Some(instance.created_at),
+            uptime_seconds: Some((Utc::now() - instance.created_at).num_seconds().max(0)),
+            stopped_at: instance.stopped_at,
+            hourly_price: instance.price.clone(),
+            snapshot_id: None,
+        }
+    }
+
+    pub fn from_hibernation(hibernation: &Hibernation) -> Self {
+        Self {
+            name: hibernation.name.clone(),
+            state: EnvironmentState::Hibernated,
+            active: false,
+            provider: hibernation.provider.clone(),
+            region: hibernation.region.clone(),
+            size: hibernation.size.clone(),
+            id: None,
+            ip: None,
+            created_at: None,
+            uptime_seconds: None,
+            stopped_at: None,
+            hourly_price: None,
+            snapshot_id: Some(hibernation.snapshot_id.clone()),
+        }
+    }
+}
+
+pub async fn list(config: &AppConfig, output: OutputFormat) -> Result<()> {
+    let (instances, active_id, hibernated) = {
+        let db = StateDb::open()?;
+        let instances = db.list_instances()?;
+        // Latest hibernation of each environment that isn't running again
+        let mut hibernated: Vec<Hibernation> = Vec::new();
+        for h in db.list_hibernations()? {
+            if !instances.iter().any(|i| i.name == h.name)
+                && !hibernated.iter().any(|seen| seen.name == h.name)
+            {
+                hibernated.push(h);
+            }
+        }
+        (instances, db.active_instance_id()?, hibernated)
+    };
+
+    // Restart auto-destroy watchdogs that died (e.g. after a reboot)
+    for instance in &instances {
+        if let Err(e) = super::watchdog::ensure_running(config, instance) {
+            tracing::debug!("Failed to start watchdog for {}: {}", instance.name, e);
+        }
+    }
+
+    if !output.is_text() {
+        let environments: Vec<EnvironmentInfo> = instances
+            .iter()
+            .map(|i| EnvironmentInfo::from_instance(i, active_id.as_deref() == Some(i.id.as_str())))
+            .chain(hibernated.iter().map(EnvironmentInfo::from_hibernation))
+            .collect();
+        return output.print(&environments);
+    }
+
+    if instances.is_empty() && hibernated.is_empty() {
+        println!("{}", style("No environments.").dim());
+        println!(
+            "Run {} to create one.",
+            style("spuff up --name <name>").cyan()
+        );
+        return Ok(());
+    }
+
+    let name_width = instances
+        .iter()
+        .map(|i| i.name.len())
+        .chain(hibernated.iter().map(|h| h.name.len()))
+        .max()
+        .unwrap_or(0)
+        .max(4);
+
+    println!(
+        "  {:name_width$}  {:14} {:12} {:16} {:16} {}",
+        style("NAME").dim(),
+        style("PROVIDER").dim(),
+        style("REGION").dim(),
+        style("SIZE").dim(),
+        style("IP").dim(),
+        style("UPTIME").dim(),
+    );
+
+    for instance in &instances {
+        let is_active = active_id.as_deref() == Some(instance.id.as_str());
+        let marker = if is_active {
+            style("●").green().bold()
+        } else {
+            style("○").dim()
+        };
+        let name = format!("{:name_width$}", instance.name);
+        let uptime = match instance.stopped_at {
+            Some(_) => style("stopped".to_string()).dim(),
+            None => style(format_elapsed(instance.created_at)).yellow(),
+        };
+        println!(
+            "{} {}  {:14} {:12} {:16} {:16} {}",
+            marker,
+            if is_active {
+                style(name).white().bold()
+            } else {
+                style(name).white()
+            },
+            instance.provider,
+            instance.region,
+            instance.size,
+            instance.ip,
+            uptime
+        );
+    }
+
+    for h in &hibernated {
+        println!(
+            "{} {}  {:14} {:12} {:16} {:16} {}",
+            style("◌").dim(),
+            style(format!("{:name_width$}", h.name)).dim(),
+            h.provider,
+            h.region,
+            h.size,
+            "-",
+            style("hibernated").dim()
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn use_env(name: &str, output: OutputFormat) -> Result<()> {
+    let db = StateDb::open()?;
+    let instance = db
+        .find_instance(name)?
+        .ok_or_else(|| SpuffError::EnvironmentNotFound(name.to_string()))?;
+
+    db.set_active(&instance.id)?;
+
+    if !output.is_text() {
+        return output.print(&EnvironmentInfo::from_instance(&instance, true));
+    }
+
+    println!(
+        "{} Active environment: {} {}",
+        style("✓").green().bold(),
+        style(&instance.name).cyan(),
+        style(format!("({})", instance.ip)).dim()
+    );
+
+    Ok(())
+}
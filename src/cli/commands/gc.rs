@@ -0,0 +1,662 @@
+//! Orphan reconciliation (`spuff gc`, alias `spuff reconcile`)
+//!
+//! Crashed `spuff up` runs and wiped laptops leave instances running that the
+//! local state database knows nothing about, and environments deleted from the
+//! provider console linger in `spuff ls`. This command lists what each provider
+//! actually has, diffs it against the state database and offers to fix every
+//! mismatch:
+//!
+//! - orphaned instances (at the provider, not in state): adopt or destroy
+//! - stale environments (in state, gone at the provider): forget
+//! - untracked snapshots (snapshots no auto-destroy, hibernation or
+//!   `spuff snapshot create` recorded): delete
+//!
+//! Nothing is changed without asking, and the first choice always leaves the
+//! finding alone.
+
+use std::collections::HashSet;
+use std::io::IsTerminal;
+
+use chrono::{DateTime, Utc};
+use console::style;
+use dialoguer::Select;
+use serde::Serialize;
+
+use crate::cli::output::OutputFormat;
+use crate::config::AppConfig;
+use crate::error::Result;
+use crate::provider::{create_provider_for, Provider, ProviderResult};
+use crate::state::{LocalInstance, StateDb};
+use crate::utils::format_elapsed;
+
+/// A provider/region pair that was listed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScanTarget {
+    pub provider: String,
+    pub region: String,
+}
+
+/// Outcome of listing one provider/region.
+#[derive(Debug, Serialize)]
+pub struct ScanResult {
+    pub provider: String,
+    pub region: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What kind of mismatch a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    /// Exists at the provider but not in the state database
+    OrphanedInstance,
+    /// Exists in the state database but not at the provider
+    StaleInstance,
+    /// Spuff snapshot at the provider that nothing in state refers to
+    UntrackedSnapshot,
+}
+
+/// What was done about a finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Adopted,
+    Destroyed,
+    Forgotten,
+    Deleted,
+    Kept,
+}
+
+/// A single mismatch between the provider and local state.
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub provider: String,
+    pub region: String,
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// `None` in dry-run mode
+    pub action: Option<Action>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Full reconciliation report (printed with `--output json|yaml`).
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub dry_run: bool,
+    pub scanned: Vec<ScanResult>,
+    pub findings: Vec<Finding>,
+}
+
+pub async fn execute(config: &AppConfig, dry_run: bool, output: OutputFormat) -> Result<()> {
+    // Read state and release the DB lock before talking to providers
+    let (local, tracked_snapshots) = {
+        let db = StateDb::open()?;
+        let mut tracked: HashSet<String> = db
+            .list_destroy_records()?
+            .into_iter()
+            .filter_map(|r| r.snapshot_id)
+            .collect();
+        tracked.extend(db.list_hibernations()?.into_iter().map(|h| h.snapshot_id));
+        tracked.extend(db.list_snapshots()?.into_iter().map(|s| s.snapshot_id));
+        (db.list_instances()?, tracked)
+    };
+
+    let interactive = !dry_run && std::io::stderr().is_terminal();
+    if !dry_run && !interactive {
+        eprintln!(
+            "{} Not a terminal, nothing will be changed (use --dry-run to silence this)",
+            style("!").yellow().bold()
+        );
+    }
+
+    let mut report = Report {
+        dry_run: !interactive,
+        scanned: Vec::new(),
+        findings: Vec::new(),
+    };
+
+    let mut seen = HashSet::new();
+    for target in scan_targets(config, &local) {
+        if output.is_text() {
+            println!(
+                "{} Scanning {} ({})...",
+                style("→").cyan().bold(),
+                target.provider,
+                target.region
+            );
+        }
+
+        let result = match create_provider_for(config, &target.provider, &target.region) {
+            Ok(provider) => scan_provider(
+                provider.as_ref(),
+                &target,
+                &local,
+                &tracked_snapshots,
+                &mut seen,
+            )
+            .await
+            .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        let error = match result {
+            Ok(findings) => {
+                report.findings.extend(findings);
+                None
+            }
+            Err(e) => {
+                if output.is_text() {
+                    println!("  {} {}", style("✕").red(), e);
+                }
+                Some(e)
+            }
+        };
+
+        report.scanned.push(ScanResult {
+            provider: target.provider,
+            region: target.region,
+            error,
+        });
+    }
+
+    if output.is_text() {
+        print_findings(&report.findings);
+    }
+
+    if interactive {
+        for finding in &mut report.findings {
+            resolve(config, finding).await?;
+        }
+    }
+
+    if !output.is_text() {
+        output.print(&report)?;
+    }
+
+    Ok(())
+}
+
+/// Provider/region pairs to list: the configured one plus every pair an
+/// environment in state was created with.
+fn scan_targets(config: &AppConfig, local: &[LocalInstance]) -> Vec<ScanTarget> {
+    let mut targets = vec![ScanTarget {
+        provider: config.provider.clone(),
+        region: config.region.clone(),
+    }];
+
+    for instance in local {
+        let target = ScanTarget {
+            provider: instance.provider.clone(),
+            region: instance.region.clone(),
+        };
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+
+    targets
+}
+
+/// Diff one provider against local state.
+///
+/// `seen` holds `provider:id` keys already reported, since most providers
+/// list instances and snapshots account-wide rather than per region.
+async fn scan_provider(
+    provider: &dyn Provider,
+    target: &ScanTarget,
+    local: &[LocalInstance],
+    tracked_snapshots: &HashSet<String>,
+    seen: &mut HashSet<String>,
+) -> ProviderResult<Vec<Finding>> {
+    let mut findings = Vec::new();
+    let known: HashSet<&str> = local.iter().map(|i| i.id.as_str()).collect();
+
+    for instance in provider.list_instances().await? {
+        if known.contains(instance.id.as_str())
+            || !seen.insert(format!("{}:{}", target.provider, instance.id))
+        {
+            continue;
+        }
+
+        findings.push(Finding {
+            kind: FindingKind::OrphanedInstance,
+            provider: target.provider.clone(),
+            region: instance.region.unwrap_or_else(|| target.region.clone()),
+            id: instance.id,
+            name: instance.name,
+            status: Some(instance.status.to_string()),
+            size: instance.size,
+            ip: Some(instance.ip.to_string()),
+            created_at: Some(instance.created_at),
+            action: None,
+            error: None,
+        });
+    }
+
+    // Tag listings can miss instances, so ask for each environment directly
+    for instance in local
+        .iter()
+        .filter(|i| i.provider == target.provider && i.region == target.region)
+    {
+        // One failed lookup shouldn't throw away the rest of the scan
+        let error = match provider.get_instance(&instance.id).await {
+            Ok(Some(_)) => continue,
+            Ok(None) => None,
+            Err(e) => Some(format!("could not check the instance: {}", e)),
+        };
+
+        findings.push(Finding {
+            kind: FindingKind::StaleInstance,
+            provider: instance.provider.clone(),
+            region: instance.region.clone(),
+            id: instance.id.clone(),
+            name: instance.name.clone(),
+            status: None,
+            size: Some(instance.size.clone()),
+            ip: Some(instance.ip.clone()),
+            created_at: Some(instance.created_at),
+            action: None,
+            error,
+        });
+    }
+
+    if provider.supports_snapshots() {
+        for snapshot in provider.list_snapshots().await? {
+            if tracked_snapshots.contains(&snapshot.id)
+                || !seen.insert(format!("{}:{}", target.provider, snapshot.id))
+            {
+                continue;
+            }
+
+            findings.push(Finding {
+                kind: FindingKind::UntrackedSnapshot,
+                provider: target.provider.clone(),
+                region: target.region.clone(),
+                id: snapshot.id,
+                name: snapshot.name,
+                status: None,
+                size: None,
+                ip: None,
+                created_at: snapshot.created_at,
+                action: None,
+                error: None,
+            });
+        }
+    }
+
+    Ok(findings)
+}
+
+fn print_findings(findings: &[Finding]) {
+    println!();
+
+    if findings.is_empty() {
+        println!(
+            "  {} Local state matches the providers.",
+            style("✓").green().bold()
+        );
+        println!();
+        return;
+    }
+
+    let sections = [
+        (
+            FindingKind::OrphanedInstance,
+            "Orphaned instances (at the provider, not in local state)",
+        ),
+        (
+            FindingKind::StaleInstance,
+            "Stale environments (in local state, gone at the provider)",
+        ),
+        (FindingKind::UntrackedSnapshot, "Untracked snapshots"),
+    ];
+
+    for (kind, title) in sections {
+        let items: Vec<&Finding> = findings.iter().filter(|f| f.kind == kind).collect();
+        if items.is_empty() {
+            continue;
+        }
+
+        println!("  {}", style(title).bold());
+        for finding in items {
+            println!(
+                "    {} {} {} {}",
+                style(&finding.name).white(),
+                style(format!("({})", finding.id)).dim(),
+                style(format!("{}/{}", finding.provider, finding.region)).dim(),
+                finding
+                    .created_at
+                    .map(|at| style(format_elapsed(at)).yellow().to_string())
+                    .unwrap_or_default()
+            );
+            if let Some(error) = &finding.error {
+                println!("      {} {}", style("✕").red(), style(error).dim());
+            }
+        }
+        println!();
+    }
+}
+
+/// Ask what to do about a finding and do it.
+async fn resolve(config: &AppConfig, finding: &mut Finding) -> Result<()> {
+    // Not enough is known to act on it safely
+    if finding.error.is_some() {
+        return Ok(());
+    }
+
+    let (prompt, choices): (String, &[(&str, Action)]) = match finding.kind {
+        FindingKind::OrphanedInstance => (
+            format!(
+                "Orphaned instance {} ({}, {}/{})",
+                finding.name, finding.id, finding.provider, finding.region
+            ),
+            &[
+                ("Skip", Action::Kept),
+                ("Adopt as an environment", Action::Adopted),
+                ("Destroy", Action::Destroyed),
+            ],
+        ),
+        FindingKind::StaleInstance => (
+            format!(
+                "Environment {} ({}) no longer exists at {}",
+                finding.name, finding.id, finding.provider
+            ),
+            &[("Keep", Action::Kept), ("Forget", Action::Forgotten)],
+        ),
+        FindingKind::UntrackedSnapshot => (
+            format!(
+                "Snapshot {} ({}, {})",
+                finding.name, finding.id, finding.provider
+            ),
+            &[("Keep", Action::Kept), ("Delete", Action::Deleted)],
+        ),
+    };
+
+    let labels: Vec<&str> = choices.iter().map(|(label, _)| *label).collect();
+    let selection = Select::new()
+        .with_prompt(format!("  {} {}", style("?").cyan().bold(), prompt))
+        .items(&labels)
+        .default(0)
+        .interact()?;
+    let action = choices[selection].1;
+
+    if let Err(e) = apply(config, finding, action).await {
+        eprintln!("  {} {}", style("✕").red().bold(), e);
+        finding.error = Some(e.to_string());
+        return Ok(());
+    }
+
+    finding.action = Some(action);
+    Ok(())
+}
+
+async fn apply(config: &AppConfig, finding: &Finding, action: Action) -> Result<()> {
+    match action {
+        Action::Kept => {}
+        Action::Adopted => {
+            let size = finding.size.clone().unwrap_or_default();
+            let price = match create_provider_for(config, &finding.provider, &finding.region) {
+                Ok(provider) => crate::cost::lookup_price(config, provider.as_ref(), &size).await,
+                Err(_) => None,
+            };
+
+            let db = StateDb::open()?;
+
+            // Environment names must stay unique for `--env` and `spuff use`
+            let mut name = finding.name.clone();
+            if db.find_instance(&name)?.is_some() {
+                name = format!("{}-{}", name, &finding.id[..finding.id.len().min(8)]);
+            }
+
+            let instance = LocalInstance {
+                id: finding.id.clone(),
+                name,
+                ip: finding.ip.clone().unwrap_or_default(),
+                provider: finding.provider.clone(),
+                region: finding.region.clone(),
+                size,
+                created_at: finding.created_at.unwrap_or_else(Utc::now),
+                // Powered-off instances stay stopped instead of being auto-destroyed
+                stopped_at: (finding.status.as_deref() == Some("off")).then(Utc::now),
+                price,
+            };
+            db.add_instance(&instance)?;
+            drop(db);
+
+            if let Err(e) = super::watchdog::ensure_running(config, &instance) {
+                tracing::debug!("Failed to start watchdog for {}: {}", instance.name, e);
+            }
+        }
+        Action::Destroyed => {
+            create_provider_for(config, &finding.provider, &finding.region)?
+                .destroy_instance(&finding.id)
+                .await?;
+        }
+        Action::Forgotten => {
+            StateDb::open()?.remove_instance(&finding.id)?;
+        }
+        Action::Deleted => {
+            create_provider_for(config, &finding.provider, &finding.region)?
+                .delete_snapshot(&finding.id)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::digitalocean::DigitalOceanProvider;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn local(id: &str, name: &str) -> LocalInstance {
+        LocalInstance {
+            id: id.to_string(),
+            name: name.to_string(),
+            ip: "1.1.1.1".to_string(),
+            provider: "digitalocean".to_string(),
+            region: "nyc1".to_string(),
+            size: "s-2vcpu-4gb".to_string(),
+            created_at: Utc::now(),
+            stopped_at: None,
+            price: None,
+        }
+    }
+
+    fn target() -> ScanTarget {
+        ScanTarget {
+            provider: "digitalocean".to_string(),
+            region: "nyc1".to_string(),
+        }
+    }
+
+    fn droplet(id: u64, name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "name": name,
+            "status": "active",
+            "created_at": "2024-01-01T00:00:00Z",
+            "networks": {"v4": [{"ip_address": "1.1.1.1", "type": "public"}]},
+            "region": {"slug": "sfo3"},
+            "size_slug": "s-1vcpu-1gb"
+        })
+    }
+
+    async fn mock_account(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path("/droplets"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "droplets": [droplet(111, "api"), droplet(222, "spuff-crashed")]
+            })))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/droplets/111"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"droplet": droplet(111, "api")})),
+            )
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/droplets/333"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/snapshots"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "snapshots": [
+                    {"id": "snap-1", "name": "spuff-idle", "created_at": "2024-01-01T00:00:00Z", "size_gigabytes": 1.0},
+                    {"id": "snap-2", "name": "spuff-old", "created_at": "2024-01-01T00:00:00Z", "size_gigabytes": 1.0}
+                ]
+            })))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_scan_provider_diffs_against_state() {
+        let server = MockServer::start().await;
+        mock_account(&server).await;
+
+        let provider = DigitalOceanProvider::with_base_url("token", &server.uri()).unwrap();
+        let local = vec![local("111", "api"), local("333", "deleted")];
+        let tracked = HashSet::from(["snap-1".to_string()]);
+        let mut seen = HashSet::new();
+
+        let findings = scan_provider(&provider, &target(), &local, &tracked, &mut seen)
+            .await
+            .unwrap();
+
+        assert_eq!(findings.len(), 3);
+
+        let orphan = &findings[0];
+        assert_eq!(orphan.kind, FindingKind::OrphanedInstance);
+        assert_eq!(orphan.id, "222");
+        assert_eq!(orphan.name, "spuff-crashed");
+        assert_eq!(orphan.region, "sfo3");
+        assert_eq!(orphan.size.as_deref(), Some("s-1vcpu-1gb"));
+
+        assert_eq!(findings[1].kind, FindingKind::StaleInstance);
+        assert_eq!(findings[1].name, "deleted");
+
+        assert_eq!(findings[2].kind, FindingKind::UntrackedSnapshot);
+        assert_eq!(findings[2].id, "snap-2");
+    }
+
+    #[tokio::test]
+    async fn test_scan_provider_keeps_findings_when_a_lookup_fails() {
+        let server = MockServer::start().await;
+        mock_account(&server).await;
+        Mock::given(method("GET"))
+            .and(path("/droplets/444"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+
+        let provider = DigitalOceanProvider::with_base_url("token", &server.uri()).unwrap();
+        let local = vec![local("111", "api"), local("444", "flaky")];
+        let tracked = HashSet::new();
+        let mut seen = HashSet::new();
+
+        let findings = scan_provider(&provider, &target(), &local, &tracked, &mut seen)
+            .await
+            .unwrap();
+
+        assert_eq!(findings.len(), 4);
+        assert_eq!(findings[0].kind, FindingKind::OrphanedInstance);
+
+        let flaky = &findings[1];
+        assert_eq!(flaky.kind, FindingKind::StaleInstance);
+        assert_eq!(flaky.name, "flaky");
+        assert!(flaky.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_scan_provider_reports_account_wide_items_once() {
+        let server = MockServer::start().await;
+        mock_account(&server).await;
+
+        let provider = DigitalOceanProvider::with_base_url("token", &server.uri()).unwrap();
+        let tracked = HashSet::new();
+        let mut seen = HashSet::new();
+
+        let first = scan_provider(&provider, &target(), &[], &tracked, &mut seen)
+            .await
+            .unwrap();
+        assert_eq!(first.len(), 4);
+
+        let other_region = ScanTarget {
+            provider: "digitalocean".to_string(),
+            region: "ams3".to_string(),
+        };
+        let second = scan_provider(&provider, &other_region, &[], &tracked, &mut seen)
+            .await
+            .unwrap();
+        assert!(second.is_empty());
+    }
+
+    #[test]
+    fn test_scan_targets_include_environment_providers() {
+        let config = AppConfig {
+            provider: "digitalocean".to_string(),
+            region: "nyc1".to_string(),
+            ..Default::default()
+        };
+
+        let mut hetzner = local("1", "eu");
+        hetzner.provider = "hetzner".to_string();
+        hetzner.region = "fsn1".to_string();
+
+        let targets = scan_targets(&config, &[local("2", "us"), hetzner]);
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].provider, "digitalocean");
+        assert_eq!(targets[1].provider, "hetzner");
+        assert_eq!(targets[1].region, "fsn1");
+    }
+
+    #[test]
+    fn test_report_json_shape() {
+        let report = Report {
+            dry_run: true,
+            scanned: vec![ScanResult {
+                provider: "digitalocean".to_string(),
+                region: "nyc1".to_string(),
+                error: None,
+            }],
+            findings: vec![Finding {
+                kind: FindingKind::StaleInstance,
+                provider: "digitalocean".to_string(),
+                region: "nyc1".to_string(),
+                id: "333".to_string(),
+                name: "deleted".to_string(),
+                status: None,
+                size: None,
+                ip: None,
+                created_at: None,
+                action: Some(Action::Forgotten),
+                error: None,
+            }],
+        };
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["findings"][0]["kind"], "stale_instance");
+        assert_eq!(json["findings"][0]["action"], "forgotten");
+        assert!(json["scanned"][0].get("error").is_none());
+    }
+}
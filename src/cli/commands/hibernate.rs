@@ -0,0 +1,443 @@
+//! Hibernate and resume (`spuff hibernate`, `spuff resume`)
+//!
+//! Hibernating snapshots an environment, records the snapshot together with
+//! its size, region, project directory and tunnels in the state database,
+//! and destroys the instance. Resuming recreates the environment from its
+//! latest hibernation snapshot through the regular `spuff up` flow, which
+//! re-mounts the project's volumes, then restarts the tunnel daemon with the
+//! same forwards.
+//!
+//! Hibernation snapshots older than `hibernate.retention` are pruned after
+//! every hibernate and resume. The latest snapshot of each environment is
+//! always kept, so a long hibernation can still be resumed.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use console::style;
+
+use crate::cli::output::OutputFormat;
+use crate::config::AppConfig;
+use crate::error::{Result, SpuffError};
+use crate::project_config::ProjectConfig;
+use crate::provider::{config_for, create_provider_for, ProviderError};
+use crate::state::{Hibernation, StateDb};
+
+use super::env::EnvironmentInfo;
+use super::tunnel::{DaemonStatus, Forward};
+
+pub async fn hibernate(config: &AppConfig, output: OutputFormat) -> Result<()> {
+    let instance = {
+        let db = StateDb::open()?;
+        db.get_active_instance()?
+            .ok_or(SpuffError::NoActiveInstance)?
+    };
+
+    let provider = create_provider_for(config, &instance.provider, &instance.region)?;
+    if !provider.supports_snapshots() {
+        return Err(SpuffError::Provider(format!(
+            "{} does not support snapshots; use 'spuff down' instead",
+            instance.provider
+        )));
+    }
+
+    let is_docker = instance.provider == "docker" || instance.provider == "local";
+    if !is_docker {
+        super::down::unmount_volumes(&instance, output).await;
+    }
+
+    // Snapshot a powered-off disk when we can, so it is consistent
+    let mut stopped = instance.stopped_at.is_some();
+    if provider.supports_power_control() && !stopped {
+        output.println(format_args!(
+            "  {} {}",
+            style("◐").cyan(),
+            style("Stopping instance...").dim()
+        ));
+        // Keep the auto-destroy watchdog away from the powered-off instance
+        StateDb::open()?.set_stopped(&instance.id, Some(Utc::now()))?;
+        match provider.stop_instance(&instance.id).await {
+            Ok(()) => stopped = true,
+            Err(e) => {
+                output.println(format_args!(
+                    "  {} Stop failed, snapshotting the running instance: {}",
+                    style("!").yellow().bold(),
+                    e
+                ));
+                if let Err(e) = StateDb::open().and_then(|db| db.set_stopped(&instance.id, None)) {
+                    tracing::warn!("Failed to clear stopped marker: {}", e);
+                }
+            }
+        }
+    }
+
+    output.println(format_args!(
+        "  {} {}",
+        style("◐").cyan(),
+        style("Creating snapshot...").dim()
+    ));
+    let snapshot_name = format!(
+        "spuff-hibernate-{}-{}",
+        instance.name,
+        Utc::now().format("%Y%m%d-%H%M")
+    );
+    let snapshot = match provider.create_snapshot(&instance.id, &snapshot_name).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            if stopped {
+                output.println(format_args!(
+                    "  {} Snapshot failed; {} was left stopped. Run {} to boot it again.",
+                    style("✗").red().bold(),
+                    style(&instance.name).cyan(),
+                    style("spuff start").cyan()
+                ));
+            }
+            return Err(e.into());
+        }
+    };
+    output.println(format_args!(
+        "  {} Snapshot: {}",
+        style("✓").green().bold(),
+        style(&snapshot.id).cyan()
+    ));
+
+    // Record before destroying so the environment can't get lost
+    let mut hibernation = Hibernation {
+        name: instance.name.clone(),
+        snapshot_id: snapshot.id,
+        provider: instance.provider.clone(),
+        region: instance.region.clone(),
+        size: instance.size.clone(),
+        project_dir: ProjectConfig::discover()
+            .and_then(|path| path.parent().map(Path::to_path_buf)),
+        ports: super::ssh::get_project_ports(),
+        reverse_ports: Vec::new(),
+        socks_port: None,
+        auto_tunnel: false,
+        at: Utc::now(),
+    };
+    if let Some(status) = super::tunnel::query_status(&instance.id).await {
+        record_tunnels(&mut hibernation, &status);
+    }
+    StateDb::open()?.record_hibernation(&hibernation)?;
+
+    if let Err(e) = super::tunnel::stop_daemon(&instance.id).await {
+        tracing::warn!("Failed to stop tunnels: {}", e);
+    }
+
+    output.println(format_args!(
+        "  {} {}",
+        style("◐").red(),
+        style("Destroying instance...").dim()
+    ));
+    provider.destroy_instance(&instance.id).await?;
+    StateDb::open()?.remove_instance(&instance.id)?;
+
+    output.println(format_args!(
+        "  {} {} hibernated. Run {} to bring it back.",
+        style("✓").green().bold(),
+        style(&instance.name).cyan(),
+        style(format!("spuff resume {}", instance.name)).cyan()
+    ));
+
+    prune_snapshots(config, output).await;
+
+    if !output.is_text() {
+        return output.print(&EnvironmentInfo::from_hibernation(&hibernation));
+    }
+
+    Ok(())
+}
+
+/// Recreate a hibernated environment. In `json`/`yaml` output it is printed
+/// as by `spuff up`, and nothing connects.
+pub async fn resume(
+    config: &AppConfig,
+    name: Option<String>,
+    no_connect: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let hibernation = {
+        let db = StateDb::open()?;
+        let hibernation = match name {
+            Some(name) => db
+                .latest_hibernation(&name)?
+                .ok_or(SpuffError::EnvironmentNotFound(name))?,
+            // Most recently hibernated environment that isn't running again
+            None => {
+                let mut hibernated = None;
+                for h in db.list_hibernations()? {
+                    if db.find_instance(&h.name)?.is_none() {
+                        hibernated = Some(h);
+                        break;
+                    }
+                }
+                hibernated.ok_or_else(|| {
+                    SpuffError::Config("No hibernated environments to resume".to_string())
+                })?
+            }
+        };
+
+        if db.find_instance(&hibernation.name)?.is_some() {
+            return Err(SpuffError::Config(format!(
+                "Environment '{}' is already running",
+                hibernation.name
+            )));
+        }
+        hibernation
+    };
+
+    output.println(format_args!(
+        "{} Resuming {} from snapshot {} ({})",
+        style("→").cyan().bold(),
+        style(&hibernation.name).cyan(),
+        style(&hibernation.snapshot_id).dim(),
+        style(hibernation.at.format("%Y-%m-%d %H:%M")).dim()
+    ));
+
+    // Recreate where it ran, even if the configured provider changed since
+    let env_config = config_for(config, &hibernation.provider, &hibernation.region);
+    super::up::execute(
+        &env_config,
+        Some(hibernation.name.clone()),
+        Some(hibernation.size.clone()),
+        Some(hibernation.snapshot_id.clone()),
+        Some(hibernation.region.clone()),
+        true,
+        false,
+        None,
+        hibernation.project_dir.as_deref(),
+        output,
+    )
+    .await?;
+
+    // `spuff up` reports provisioning failures itself
+    let Some(instance) = StateDb::open()?.find_instance(&hibernation.name)? else {
+        return Ok(());
+    };
+
+    prune_snapshots(config, output).await;
+
+    let is_docker = instance.provider == "docker" || instance.provider == "local";
+
+    // The daemon keeps the tunnels up whether or not we connect now
+    if !is_docker {
+        let forwards = hibernated_forwards(&hibernation);
+        match super::tunnel::restore(&instance, forwards.clone(), hibernation.auto_tunnel).await {
+            Ok(()) if output.is_text() => super::tunnel::print_tunnel_info(&forwards),
+            Ok(()) => {}
+            Err(e) => output.println(format_args!(
+                "  {} Failed to reopen tunnels: {}",
+                style("!").yellow().bold(),
+                e
+            )),
+        }
+    }
+
+    if no_connect || !output.is_text() {
+        return Ok(());
+    }
+
+    output.println("");
+    if is_docker {
+        crate::connector::docker::connect(&instance.id).await
+    } else {
+        crate::connector::ssh::connect(&instance.ip, &env_config).await
+    }
+}
+
+/// Record the forwards a running tunnel daemon was asked for. Ports it
+/// discovered on its own come back through `auto_tunnel` instead.
+fn record_tunnels(hibernation: &mut Hibernation, status: &DaemonStatus) {
+    for forward in status.forwards.iter().filter(|f| !f.discovered) {
+        match forward.forward {
+            Forward::Local(port) => {
+                if !hibernation.ports.contains(&port) {
+                    hibernation.ports.push(port);
+                }
+            }
+            Forward::Reverse(port) => hibernation.reverse_ports.push(port),
+            Forward::Socks(port) => hibernation.socks_port = Some(port),
+        }
+    }
+    hibernation.auto_tunnel = status.auto;
+}
+
+/// Forwards to reopen for a resumed environment.
+fn hibernated_forwards(hibernation: &Hibernation) -> Vec<Forward> {
+    hibernation
+        .ports
+        .iter()
+        .copied()
+        .map(Forward::Local)
+        .chain(
+            hibernation
+                .reverse_ports
+                .iter()
+                .copied()
+                .map(Forward::Reverse),
+        )
+        .chain(hibernation.socks_port.map(Forward::Socks))
+        .collect()
+}
+
+/// Delete hibernation snapshots past `hibernate.retention`.
+///
+/// Failures are only logged: pruning never fails the command that triggers it.
+async fn prune_snapshots(config: &AppConfig, output: OutputFormat) {
+    let Some(retention) = config.hibernate.parse_retention() else {
+        return;
+    };
+    let Ok(retention) = chrono::Duration::from_std(retention) else {
+        return;
+    };
+
+    let hibernations = match StateDb::open().and_then(|db| db.list_hibernations()) {
+        Ok(hibernations) => hibernations,
+        Err(e) => {
+            tracing::warn!("Failed to list hibernation snapshots: {}", e);
+            return;
+        }
+    };
+
+    let mut pruned = 0;
+    for hibernation in expired_hibernations(&hibernations, retention, Utc::now()) {
+        let result = async {
+            let provider = create_provider_for(config, &hibernation.provider, &hibernation.region)?;
+            match provider.delete_snapshot(&hibernation.snapshot_id).await {
+                Ok(()) | Err(ProviderError::NotFound { .. }) => {}
+                Err(e) => return Err(e.into()),
+            }
+            StateDb::open()?.remove_hibernation(&hibernation.snapshot_id)
+        }
+        .await;
+
+        match result {
+            Ok(()) => pruned += 1,
+            Err(e) => tracing::warn!(
+                "Failed to prune snapshot {} of {}: {}",
+                hibernation.snapshot_id,
+                hibernation.name,
+                e
+            ),
+        }
+    }
+
+    if pruned > 0 {
+        output.println(format_args!(
+            "  {} Pruned {} old hibernation snapshot(s)",
+            style("○").dim(),
+            pruned
+        ));
+    }
+}
+
+/// Hibernations older than `retention`, except each environment's latest.
+///
+/// `hibernations` must be sorted most recent first.
+fn expired_hibernations(
+    hibernations: &[Hibernation],
+    retention: chrono::Duration,
+    now: DateTime<Utc>,
+) -> Vec<&Hibernation> {
+    let mut latest = HashSet::new();
+    let mut expired = Vec::new();
+
+    for hibernation in hibernations {
+        if latest.insert(hibernation.name.as_str()) {
+            continue;
+        }
+        if now - hibernation.at > retention {
+            expired.push(hibernation);
+        }
+    }
+
+    expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hibernation(snapshot_id: &str, name: &str, days_ago: i64) -> Hibernation {
+        Hibernation {
+            name: name.to_string(),
+            snapshot_id: snapshot_id.to_string(),
+            provider: "digitalocean".to_string(),
+            region: "nyc1".to_string(),
+            size: "s-2vcpu-4gb".to_string(),
+            project_dir: None,
+            ports: Vec::new(),
+            reverse_ports: Vec::new(),
+            socks_port: None,
+            auto_tunnel: false,
+            at: Utc::now() - chrono::Duration::days(days_ago),
+        }
+    }
+
+    #[test]
+    fn test_record_tunnels_keeps_requested_forwards() {
+        use super::super::tunnel::ForwardStatus;
+
+        let forward = |forward: Forward, discovered: bool| ForwardStatus {
+            forward,
+            active: true,
+            error: None,
+            process: None,
+            discovered,
+        };
+        let status = DaemonStatus {
+            pid: 1,
+            host: None,
+            connected_since: None,
+            attempts: 0,
+            last_error: None,
+            auto: true,
+            forwards: vec![
+                forward(Forward::Local(3000), false),
+                forward(Forward::Local(5173), true),
+                forward(Forward::Reverse(9000), false),
+                forward(Forward::Socks(1080), false),
+            ],
+        };
+
+        let mut h = hibernation("snap", "api", 0);
+        h.ports = vec![3000];
+        record_tunnels(&mut h, &status);
+
+        assert_eq!(h.ports, vec![3000]);
+        assert_eq!(h.reverse_ports, vec![9000]);
+        assert_eq!(h.socks_port, Some(1080));
+        assert!(h.auto_tunnel);
+        assert_eq!(
+            hibernated_forwards(&h),
+            vec![
+                Forward::Local(3000),
+                Forward::Reverse(9000),
+                Forward::Socks(1080)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expired_hibernations_keep_latest_per_environment() {
+        let hibernations = vec![
+            hibernation("api-3", "api", 1),
+            hibernation("web-1", "web", 30),
+            hibernation("api-2", "api", 10),
+            hibernation("api-1", "api", 20),
+        ];
+
+        let expired = expired_hibernations(&hibernations, chrono::Duration::days(14), Utc::now());
+        let ids: Vec<&str> = expired.iter().map(|h| h.snapshot_id.as_str()).collect();
+
+        // web-1 is old but the only snapshot of "web"; api-2 is within retention
+        assert_eq!(ids, vec!["api-1"]);
+    }
+
+    #[test]
+    fn test_expired_hibernations_empty() {
+        assert!(expired_hibernations(&[], chrono::Duration::days(1), Utc::now()).is_empty());
+    }
+}
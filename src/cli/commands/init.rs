@@ -1,14 +1,23 @@
 use console::style;
-use dialoguer::{Input, Select};
+use dialoguer::{Confirm, Input, Password, Select};
 
+use super::catalog::Catalog;
 use crate::config::AppConfig;
 use crate::error::Result;
+use crate::provider::aws::AwsConfig;
+use crate::provider::ssh_pool::{SshPoolConfig, SshPoolHost};
 
 pub async fn execute() -> Result<()> {
     println!("{}", style("🚀 Welcome to spuff!").bold().cyan());
     println!("Let's configure your ephemeral dev environment.\n");
 
-    let providers = vec!["DigitalOcean", "Hetzner", "AWS"];
+    let providers = vec![
+        "DigitalOcean",
+        "Hetzner",
+        "AWS",
+        "Existing hosts (SSH)",
+        "Local VM (QEMU/libvirt)",
+    ];
     let provider_idx = Select::new()
         .with_prompt("Select your cloud provider")
         .items(&providers)
@@ -19,26 +28,110 @@ pub async fn execute() -> Result<()> {
         0 => "digitalocean",
         1 => "hetzner",
         2 => "aws",
+        3 => "ssh",
+        4 => "qemu",
         _ => "digitalocean",
     };
 
-    let api_token: String = Input::new()
-        .with_prompt(format!("Enter your {} API token", providers[provider_idx]))
-        .interact_text()?;
+    let api_token: String = if provider == "aws" {
+        Input::new()
+            .with_prompt("Enter your AWS access key ID")
+            .interact_text()?
+    } else if provider == "ssh" || provider == "qemu" {
+        String::new()
+    } else {
+        Input::new()
+            .with_prompt(format!("Enter your {} API token", providers[provider_idx]))
+            .interact_text()?
+    };
 
-    let regions = get_regions_for_provider(provider);
-    let region_idx = Select::new()
-        .with_prompt("Select default region")
-        .items(&regions)
-        .default(0)
-        .interact()?;
+    let aws = if provider == "aws" {
+        let secret_access_key: String = Password::new()
+            .with_prompt("Enter your AWS secret access key (empty to use AWS_SECRET_ACCESS_KEY)")
+            .allow_empty_password(true)
+            .interact()?;
+        let spot = Confirm::new()
+            .with_prompt("Use spot instances (cheaper, may be interrupted)?")
+            .default(false)
+            .interact()?;
+
+        Some(AwsConfig {
+            secret_access_key: if secret_access_key.is_empty() {
+                None
+            } else {
+                Some(secret_access_key)
+            },
+            spot,
+            ..Default::default()
+        })
+    } else {
+        None
+    };
 
-    let sizes = get_sizes_for_provider(provider);
-    let size_idx = Select::new()
-        .with_prompt("Select default instance size")
-        .items(&sizes)
-        .default(1)
-        .interact()?;
+    let ssh_pool = if provider == "ssh" {
+        let hosts: String = Input::new()
+            .with_prompt("Hosts to use (comma-separated hostnames or IPs)")
+            .interact_text()?;
+        let user: String = Input::new()
+            .with_prompt("Admin user on those hosts (root or passwordless sudo)")
+            .default("root".to_string())
+            .interact_text()?;
+
+        Some(SshPoolConfig {
+            hosts: hosts
+                .split(',')
+                .map(str::trim)
+                .filter(|h| !h.is_empty())
+                .map(|host| SshPoolHost {
+                    host: host.to_string(),
+                    name: None,
+                    user: user.clone(),
+                    key_path: None,
+                })
+                .collect(),
+        })
+    } else {
+        None
+    };
+
+    // Pool hosts already exist, so there is no region or size to pick
+    let catalog = if provider == "ssh" {
+        None
+    } else {
+        println!("{}", style("Fetching regions and sizes...").dim());
+        let catalog_config = AppConfig {
+            provider: provider.to_string(),
+            api_token: api_token.clone(),
+            aws: aws.clone(),
+            ..Default::default()
+        };
+        Catalog::try_fetch(&catalog_config).await
+    };
+
+    let (region, size) = if provider == "ssh" {
+        ("default".to_string(), "default".to_string())
+    } else if let Some(catalog) = &catalog {
+        let region = catalog.pick_region("")?;
+        let size = catalog.pick_size(&region, "")?;
+        (region, size)
+    } else {
+        // No catalog (offline, or the provider can't list one): common choices
+        let regions = get_regions_for_provider(provider);
+        let region_idx = Select::new()
+            .with_prompt("Select default region")
+            .items(&regions)
+            .default(0)
+            .interact()?;
+
+        let sizes = get_sizes_for_provider(provider);
+        let size_idx = Select::new()
+            .with_prompt("Select default instance size")
+            .items(&sizes)
+            .default(1)
+            .interact()?;
+
+        (regions[region_idx].to_string(), sizes[size_idx].to_string())
+    };
 
     let idle_timeout: String = Input::new()
         .with_prompt("Auto-destroy after idle (e.g., 2h, 30m)")
@@ -65,9 +158,10 @@ pub async fn execute() -> Result<()> {
     let config = AppConfig {
         provider: provider.to_string(),
         api_token,
-        region: regions[region_idx].to_string(),
-        size: sizes[size_idx].to_string(),
+        region,
+        size,
         idle_timeout,
+        max_lifetime: None,
         environment: environments[env_idx].to_string(),
         dotfiles: if dotfiles.is_empty() {
             None
@@ -81,6 +175,13 @@ pub async fn execute() -> Result<()> {
         agent_token: None, // Can be set via SPUFF_AGENT_TOKEN env var
         ai_tools: None,    // None means use default (all)
         volumes: Vec::new(),
+        aws,
+        ssh_pool,
+        qemu: None,
+        auto_destroy: Default::default(),
+        idle_policy: Default::default(),
+        hibernate: Default::default(),
+        budget: Default::default(),
     };
 
     config.save()?;
@@ -109,6 +210,7 @@ fn get_regions_for_provider(provider: &str) -> Vec<&'static str> {
             "eu-central-1",
             "ap-southeast-1",
         ],
+        "qemu" => vec!["local"],
         _ => vec!["default"],
     }
 }
@@ -118,6 +220,7 @@ fn get_sizes_for_provider(provider: &str) -> Vec<&'static str> {
         "digitalocean" => vec!["s-1vcpu-1gb", "s-2vcpu-4gb", "s-4vcpu-8gb", "s-8vcpu-16gb"],
         "hetzner" => vec!["cx22", "cx32", "cx42", "cx52"],
         "aws" => vec!["t3.small", "t3.medium", "t3.large", "t3.xlarge"],
+        "qemu" => vec!["small", "medium", "large", "xlarge"],
         _ => vec!["default"],
     }
 }
@@ -8,13 +8,16 @@
 //! - Setup scripts
 
 use console::style;
+use serde::Serialize;
 
+use crate::cli::output::OutputFormat;
 use crate::config::AppConfig;
 use crate::error::{Result, SpuffError};
 use crate::state::StateDb;
 
 /// Log category for filtering
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LogCategory {
     /// General setup log
     Setup,
@@ -56,6 +59,26 @@ impl LogCategory {
     }
 }
 
+/// Printed instead of the log when the file doesn't exist.
+const LOG_MISSING: &str = "spuff: log file not found";
+
+/// `spuff logs` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct LogsReport {
+    pub category: LogCategory,
+    /// Log file on the VM
+    pub path: String,
+    /// False until project setup has written the log
+    pub found: bool,
+    pub lines: Vec<String>,
+}
+
+/// One line of `spuff logs --follow` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct LogLine<'a> {
+    pub line: &'a str,
+}
+
 /// Execute the logs command
 #[allow(clippy::too_many_arguments)]
 pub async fn execute(
@@ -67,6 +90,7 @@ pub async fn execute(
     script: Option<usize>,
     follow: bool,
     lines: usize,
+    output: OutputFormat,
 ) -> Result<()> {
     let db = StateDb::open()?;
 
@@ -105,6 +129,20 @@ pub async fn execute(
         (LogCategory::Setup, LogCategory::Setup.log_path(None, None))
     };
 
+    if !output.is_text() {
+        return if follow {
+            follow_log_lines(&instance.ip, config, &log_path, lines, output).await
+        } else {
+            let lines = fetch_log(&instance.ip, config, &log_path, lines).await?;
+            output.print(&LogsReport {
+                category,
+                path: log_path,
+                found: lines.is_some(),
+                lines: lines.unwrap_or_default(),
+            })
+        };
+    }
+
     // Print header
     print_header(&category, bundle.as_deref(), script);
 
@@ -134,33 +172,79 @@ fn print_header(category: &LogCategory, bundle: Option<&str>, script: Option<usi
 }
 
 async fn show_logs(ip: &str, config: &AppConfig, log_path: &str, lines: usize) -> Result<()> {
+    match fetch_log(ip, config, log_path, lines).await? {
+        None => {
+            println!(
+                "  {}",
+                style(format!("Log file not found or empty: {}", log_path)).yellow()
+            );
+            println!();
+            println!(
+                "  {} Project setup may not have started yet.",
+                style("Hint:").dim()
+            );
+            println!(
+                "  {} Run {} to check setup progress.",
+                style("").dim(),
+                style("spuff status --detailed").cyan()
+            );
+        }
+        Some(lines) => {
+            // Format and colorize log output
+            for line in &lines {
+                print_log_line(line);
+            }
+        }
+    }
+
+    println!();
+    Ok(())
+}
+
+/// Last `lines` lines of a log; `None` when the file doesn't exist yet.
+async fn fetch_log(
+    ip: &str,
+    config: &AppConfig,
+    log_path: &str,
+    lines: usize,
+) -> Result<Option<Vec<String>>> {
     let cmd = format!(
-        "tail -n {} {} 2>/dev/null || echo 'Log file not found or empty: {}'",
-        lines, log_path, log_path
+        "tail -n {} {} 2>/dev/null || echo '{}'",
+        lines, log_path, LOG_MISSING
     );
-
     let output = crate::connector::ssh::run_command(ip, config, &cmd).await?;
 
-    if output.contains("Log file not found") {
-        println!("  {}", style(&output).yellow());
-        println!();
-        println!(
-            "  {} Project setup may not have started yet.",
-            style("Hint:").dim()
-        );
-        println!(
-            "  {} Run {} to check setup progress.",
-            style("").dim(),
-            style("spuff status --detailed").cyan()
-        );
-    } else {
-        // Format and colorize log output
-        for line in output.lines() {
-            print_log_line(line);
-        }
+    if output.trim_end().ends_with(LOG_MISSING) {
+        return Ok(None);
+    }
+    Ok(Some(output.lines().map(str::to_string).collect()))
+}
+
+/// Follow a log with `tail -F`, printing each line as an item. Waits for
+/// the file if project setup hasn't written it yet.
+async fn follow_log_lines(
+    ip: &str,
+    config: &AppConfig,
+    log_path: &str,
+    lines: usize,
+    output: OutputFormat,
+) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let client = crate::connector::ssh::client(ip, config).await?;
+    let stream = client
+        .exec_stream(&format!("tail -n {} -F {} 2>/dev/null", lines, log_path))
+        .await?;
+
+    let mut reader = BufReader::new(stream).lines();
+    while let Some(line) = reader
+        .next_line()
+        .await
+        .map_err(|e| SpuffError::Ssh(format!("Lost connection while following logs: {}", e)))?
+    {
+        output.print_item(&LogLine { line: &line })?;
     }
 
-    println!();
     Ok(())
 }
 
@@ -1,11 +1,22 @@
 pub mod agent;
 pub mod ai;
+pub mod catalog;
 pub mod config;
+pub mod cost;
+pub mod cp;
+pub mod disk;
 pub mod down;
+pub mod env;
+pub mod gc;
+pub mod hibernate;
 pub mod init;
 pub mod logs;
+pub mod power;
+pub mod resize;
 pub mod snapshot;
 pub mod ssh;
 pub mod status;
+pub mod tunnel;
 pub mod up;
 pub mod volume;
+pub mod watchdog;
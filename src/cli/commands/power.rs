@@ -0,0 +1,127 @@
+//! Power commands (`spuff stop`, `spuff start`)
+//!
+//! Stopping powers the instance off with its disk intact, so starting it again
+//! is a cold boot instead of a full snapshot round-trip. Most providers keep
+//! billing stopped instances, so this is meant for short breaks.
+
+use chrono::Utc;
+use console::style;
+
+use crate::cli::output::OutputFormat;
+use crate::config::AppConfig;
+use crate::error::{Result, SpuffError};
+use crate::provider::{create_provider_for, Provider};
+use crate::state::{LocalInstance, StateDb};
+
+use super::env::EnvironmentInfo;
+
+pub async fn stop(config: &AppConfig, output: OutputFormat) -> Result<()> {
+    let mut instance = active_instance()?;
+
+    if instance.stopped_at.is_some() {
+        if !output.is_text() {
+            return output.print(&EnvironmentInfo::from_instance(&instance, true));
+        }
+        println!(
+            "  {} {} is already stopped.",
+            style("○").dim(),
+            style(&instance.name).cyan()
+        );
+        return Ok(());
+    }
+
+    let provider = power_provider(config, &instance)?;
+
+    // SSHFS hangs when the server goes away under it
+    let is_docker = instance.provider == "docker" || instance.provider == "local";
+    if !is_docker {
+        super::down::unmount_volumes(&instance, output).await;
+    }
+
+    output.println(format_args!(
+        "  {} {}",
+        style("◐").cyan(),
+        style(format!("Stopping {}...", instance.name)).dim()
+    ));
+
+    // Mark first so the auto-destroy watchdog doesn't take this for idleness
+    instance.stopped_at = Some(Utc::now());
+    StateDb::open()?.set_stopped(&instance.id, instance.stopped_at)?;
+
+    if let Err(e) = provider.stop_instance(&instance.id).await {
+        if let Err(e) = StateDb::open().and_then(|db| db.set_stopped(&instance.id, None)) {
+            tracing::warn!("Failed to clear stopped marker: {}", e);
+        }
+        return Err(e.into());
+    }
+
+    if !output.is_text() {
+        return output.print(&EnvironmentInfo::from_instance(&instance, true));
+    }
+
+    println!(
+        "  {} {} stopped. Run {} to boot it again.",
+        style("✓").green().bold(),
+        style(&instance.name).cyan(),
+        style("spuff start").cyan()
+    );
+
+    Ok(())
+}
+
+pub async fn start(config: &AppConfig, output: OutputFormat) -> Result<()> {
+    let mut instance = active_instance()?;
+    let provider = power_provider(config, &instance)?;
+
+    output.println(format_args!(
+        "  {} {}",
+        style("◐").cyan(),
+        style(format!("Starting {}...", instance.name)).dim()
+    ));
+
+    provider.start_instance(&instance.id).await?;
+    let ready = provider.wait_ready(&instance.id).await?;
+
+    // Most providers hand out a new public IP on every boot
+    instance.ip = ready.ip.to_string();
+    instance.stopped_at = None;
+    {
+        let db = StateDb::open()?;
+        db.update_instance_ip(&instance.id, &instance.ip)?;
+        db.set_stopped(&instance.id, None)?;
+    }
+
+    if let Err(e) = super::watchdog::ensure_running(config, &instance) {
+        tracing::warn!("Failed to start auto-destroy watchdog: {}", e);
+    }
+
+    if !output.is_text() {
+        return output.print(&EnvironmentInfo::from_instance(&instance, true));
+    }
+
+    println!(
+        "  {} {} is running {}",
+        style("✓").green().bold(),
+        style(&instance.name).cyan(),
+        style(format!("({})", instance.ip)).dim()
+    );
+
+    Ok(())
+}
+
+fn active_instance() -> Result<LocalInstance> {
+    let db = StateDb::open()?;
+    db.get_active_instance()?
+        .ok_or(SpuffError::NoActiveInstance)
+}
+
+fn power_provider(config: &AppConfig, instance: &LocalInstance) -> Result<Box<dyn Provider>> {
+    let provider = create_provider_for(config, &instance.provider, &instance.region)?;
+    if !provider.supports_power_control() {
+        return Err(SpuffError::Provider(format!(
+            "{} does not support stop/start; use 'spuff down' instead",
+            instance.provider
+        )));
+    }
+    Ok(provider)
+}
@@ -0,0 +1,193 @@
+//! Resize command (`spuff resize`)
+//!
+//! Changes the active instance's size in place, keeping its disk. The
+//! provider powers the instance off for the resize, so local volumes are
+//! unmounted first and mounted again once the instance and its agent are back.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use console::style;
+use dialoguer::Confirm;
+
+use crate::cli::output::OutputFormat;
+use crate::config::AppConfig;
+use crate::connector::agent::AgentClient;
+use crate::error::{Result, SpuffError};
+use crate::provider::{create_provider_for, InstanceStatus};
+use crate::state::StateDb;
+use crate::volume::VolumeState;
+
+use super::env::EnvironmentInfo;
+
+/// How long to wait for spuff-agent to answer after the reboot.
+const AGENT_TIMEOUT: Duration = Duration::from_secs(120);
+
+pub async fn execute(
+    config: &AppConfig,
+    size: String,
+    force: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let mut instance = StateDb::open()?
+        .get_active_instance()?
+        .ok_or(SpuffError::NoActiveInstance)?;
+
+    if instance.size == size {
+        if !output.is_text() {
+            return output.print(&EnvironmentInfo::from_instance(&instance, true));
+        }
+        println!(
+            "  {} {} is already {}.",
+            style("○").dim(),
+            style(&instance.name).cyan(),
+            style(&size).white()
+        );
+        return Ok(());
+    }
+
+    let provider = create_provider_for(config, &instance.provider, &instance.region)?;
+    if !provider.supports_resize() {
+        return Err(SpuffError::Provider(format!(
+            "{} does not support resizing; recreate the environment with 'spuff up --size {}'",
+            instance.provider, size
+        )));
+    }
+
+    output.println(format_args!(
+        "  {} {} → {}",
+        style(&instance.name).cyan(),
+        style(&instance.size).dim(),
+        style(&size).white().bold()
+    ));
+    output.println("");
+
+    if !force {
+        let confirmed = Confirm::new()
+            .with_prompt(format!(
+                "  {} The instance will reboot. Resize it?",
+                style("?").cyan().bold()
+            ))
+            .default(false)
+            .interact()?;
+
+        if !confirmed {
+            output.println("");
+            output.println(format_args!("  {}", style("Cancelled.").dim()));
+            if !output.is_text() {
+                return output.print(&EnvironmentInfo::from_instance(&instance, true));
+            }
+            return Ok(());
+        }
+    }
+
+    // Mark first so the auto-destroy watchdog doesn't take the power-off
+    // for idleness
+    StateDb::open()?.set_stopped(&instance.id, Some(Utc::now()))?;
+
+    // SSHFS hangs when the server goes away under it
+    let had_volumes = !VolumeState::load_or_default().mounts.is_empty();
+    super::down::unmount_volumes(&instance, output).await;
+
+    output.println(format_args!(
+        "  {} {}",
+        style("◐").cyan(),
+        style(format!("Resizing {} to {}...", instance.name, size)).dim()
+    ));
+    let resized = match provider.resize_instance(&instance.id, &size).await {
+        Ok(()) => provider.wait_ready(&instance.id).await,
+        Err(e) => Err(e),
+    };
+    let ready = match resized {
+        Ok(ready) => ready,
+        Err(e) => {
+            // Only hand the instance back to the watchdog once it is really
+            // running again; a powered-off droplet would look idle
+            let running = matches!(
+                provider.get_instance(&instance.id).await,
+                Ok(Some(current)) if current.status == InstanceStatus::Active
+            );
+            if running {
+                if let Err(e) = StateDb::open().and_then(|db| db.set_stopped(&instance.id, None)) {
+                    tracing::warn!("Failed to clear stopped marker: {}", e);
+                }
+            } else {
+                output.println(format_args!(
+                    "  {} {} may still be powered off. Run {} to boot it again.",
+                    style("!").yellow().bold(),
+                    style(&instance.name).cyan(),
+                    style("spuff start").cyan()
+                ));
+            }
+            return Err(e.into());
+        }
+    };
+
+    let price = crate::cost::lookup_price(config, provider.as_ref(), &size).await;
+    instance.size = size;
+    instance.ip = ready.ip.to_string();
+    instance.stopped_at = None;
+    {
+        let db = StateDb::open()?;
+        db.update_instance_size(&instance.id, &instance.size, price)?;
+        db.update_instance_ip(&instance.id, &instance.ip)?;
+        db.set_stopped(&instance.id, None)?;
+    }
+
+    output.println(format_args!(
+        "  {} {}",
+        style("◐").cyan(),
+        style("Waiting for SSH and spuff-agent...").dim()
+    ));
+    crate::connector::ssh::wait_for_ssh_login(&instance.ip, config, Duration::from_secs(300))
+        .await?;
+    if !wait_for_agent(&instance.ip, config).await {
+        output.println(format_args!(
+            "  {} spuff-agent is not responding yet; check {}",
+            style("!").yellow().bold(),
+            style("spuff agent status").cyan()
+        ));
+    }
+
+    if let Err(e) = super::watchdog::ensure_running(config, &instance) {
+        tracing::warn!("Failed to start auto-destroy watchdog: {}", e);
+    }
+
+    if had_volumes {
+        if let Err(e) = super::volume::remount_volumes(config, None, output).await {
+            output.println(format_args!(
+                "  {} Failed to remount volumes: {}",
+                style("!").yellow().bold(),
+                e
+            ));
+        }
+    }
+
+    if !output.is_text() {
+        return output.print(&EnvironmentInfo::from_instance(&instance, true));
+    }
+
+    println!(
+        "  {} {} is running as {} {}",
+        style("✓").green().bold(),
+        style(&instance.name).cyan(),
+        style(&instance.size).white(),
+        style(format!("({})", instance.ip)).dim()
+    );
+
+    Ok(())
+}
+
+/// Poll the agent's health endpoint until it answers or the timeout passes.
+async fn wait_for_agent(ip: &str, config: &AppConfig) -> bool {
+    let start = std::time::Instant::now();
+    while start.elapsed() < AGENT_TIMEOUT {
+        if let Ok(agent) = AgentClient::connect(ip, config).await {
+            if agent.get::<serde_json::Value>("/health").await.is_ok() {
+                return true;
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(3)).await;
+    }
+    false
+}
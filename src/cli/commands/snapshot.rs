@@ -1,11 +1,19 @@
 use console::style;
+use serde::Serialize;
 
+use crate::cli::output::OutputFormat;
 use crate::config::AppConfig;
 use crate::error::{Result, SpuffError};
-use crate::provider::create_provider;
-use crate::state::StateDb;
+use crate::provider::{create_provider, Snapshot};
+use crate::state::{LocalInstance, SnapshotRecord, StateDb};
 
-pub async fn create(config: &AppConfig, name: Option<String>) -> Result<()> {
+/// `spuff snapshot delete` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct DeletedSnapshot {
+    pub id: String,
+}
+
+pub async fn create(config: &AppConfig, name: Option<String>, output: OutputFormat) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
@@ -19,16 +27,23 @@ pub async fn create(config: &AppConfig, name: Option<String>) -> Result<()> {
         )
     });
 
-    println!(
-        "{} Creating snapshot of {}...",
-        style("→").cyan().bold(),
-        style(&instance.name).cyan()
-    );
+    if output.is_text() {
+        println!(
+            "{} Creating snapshot of {}...",
+            style("→").cyan().bold(),
+            style(&instance.name).cyan()
+        );
+    }
 
     let provider = create_provider(config)?;
     let snapshot = provider
         .create_snapshot(&instance.id, &snapshot_name)
         .await?;
+    record(&db, &instance, &snapshot);
+
+    if !output.is_text() {
+        return output.print(&snapshot);
+    }
 
     println!(
         "\n{} Snapshot created: {}",
@@ -40,10 +55,14 @@ pub async fn create(config: &AppConfig, name: Option<String>) -> Result<()> {
     Ok(())
 }
 
-pub async fn list(config: &AppConfig) -> Result<()> {
+pub async fn list(config: &AppConfig, output: OutputFormat) -> Result<()> {
     let provider = create_provider(config)?;
     let snapshots = provider.list_snapshots().await?;
 
+    if !output.is_text() {
+        return output.print(&snapshots);
+    }
+
     if snapshots.is_empty() {
         println!("{}", style("No snapshots found.").dim());
         return Ok(());
@@ -69,18 +88,41 @@ pub async fn list(config: &AppConfig) -> Result<()> {
     Ok(())
 }
 
-pub async fn delete(config: &AppConfig, id: String) -> Result<()> {
+pub async fn delete(config: &AppConfig, id: String, output: OutputFormat) -> Result<()> {
     let provider = create_provider(config)?;
 
-    println!(
-        "{} Deleting snapshot {}...",
-        style("→").yellow().bold(),
-        style(&id).cyan()
-    );
+    if output.is_text() {
+        println!(
+            "{} Deleting snapshot {}...",
+            style("→").yellow().bold(),
+            style(&id).cyan()
+        );
+    }
 
     provider.delete_snapshot(&id).await?;
+    if let Err(e) = StateDb::open().and_then(|db| db.remove_snapshot(&id)) {
+        tracing::warn!("Failed to forget snapshot {}: {}", id, e);
+    }
+
+    if !output.is_text() {
+        return output.print(&DeletedSnapshot { id });
+    }
 
     println!("{} Snapshot deleted.", style("✓").green().bold());
 
     Ok(())
 }
+
+/// Remember a snapshot the user asked for, so `spuff gc` leaves it alone.
+pub(super) fn record(db: &StateDb, instance: &LocalInstance, snapshot: &Snapshot) {
+    let record = SnapshotRecord {
+        snapshot_id: snapshot.id.clone(),
+        name: snapshot.name.clone(),
+        environment: instance.name.clone(),
+        provider: instance.provider.clone(),
+        at: chrono::Utc::now(),
+    };
+    if let Err(e) = db.record_snapshot(&record) {
+        tracing::warn!("Failed to record snapshot {}: {}", snapshot.id, e);
+    }
+}
@@ -5,10 +5,44 @@
 use console::style;
 
 use crate::project_config::{ProjectSetupState, SetupStatus};
+use crate::state::DestroyRecord;
+use crate::utils::format_elapsed;
 
 use super::format::{format_setup_status, get_step_state};
 use super::types::{DevToolStatus, DevToolsState, StepState, BOOTSTRAP_STEPS};
 
+/// Print the outcome of the last automatic destroy attempt
+pub fn print_destroy_record(record: &DestroyRecord) {
+    let ago = format_elapsed(record.at);
+    match &record.error {
+        None => {
+            println!(
+                "  {}  {} destroyed {} ago ({})",
+                style("Auto-destroy").dim(),
+                style(&record.name).cyan(),
+                ago,
+                record.reason
+            );
+            if let Some(snapshot_id) = &record.snapshot_id {
+                println!(
+                    "  {}      {}",
+                    style("Snapshot").dim(),
+                    style(snapshot_id).cyan()
+                );
+            }
+        }
+        Some(error) => {
+            println!(
+                "  {}  {} {} ago: {}",
+                style("Auto-destroy").dim(),
+                style("failed").red(),
+                ago,
+                error
+            );
+        }
+    }
+}
+
 /// Print a visual checklist of bootstrap progress
 pub fn print_bootstrap_checklist(current_status: &str) {
     println!("  {}", style("Bootstrap Progress").dim().bold());
@@ -1,48 +1,44 @@
 //! HTTP requests to agent
 //!
-//! Functions for fetching status from the agent API.
+//! Functions for fetching status from the agent API. Early in bootstrap the
+//! agent isn't listening yet; bootstrap and devtools then report an empty
+//! state instead of failing.
 
 use crate::config::AppConfig;
-use crate::error::Result;
+use crate::connector::agent::AgentClient;
+use crate::error::{Result, SpuffError};
 use crate::project_config::ProjectSetupState;
 
 use super::types::{BootstrapStatus, DevToolsState};
 
-pub async fn get_bootstrap_status(ip: &str, config: &AppConfig) -> Result<BootstrapStatus> {
-    let output = crate::connector::ssh::run_command(
-        ip,
-        config,
-        "curl -s http://127.0.0.1:7575/status 2>/dev/null || echo '{}'",
-    )
-    .await?;
-
-    serde_json::from_str(&output).map_err(|e| {
-        crate::error::SpuffError::Provider(format!("Failed to parse agent response: {}", e))
-    })
+/// Connect to the agent and read bootstrap progress; `None` when the VM
+/// can't be reached.
+pub async fn connect_agent(ip: &str, config: &AppConfig) -> Option<(AgentClient, BootstrapStatus)> {
+    let agent = AgentClient::connect(ip, config).await.ok()?;
+    let bootstrap = get_bootstrap_status(&agent).await.ok()?;
+    Some((agent, bootstrap))
 }
 
-pub async fn get_devtools_status(ip: &str, config: &AppConfig) -> Result<DevToolsState> {
-    let output = crate::connector::ssh::run_command(
-        ip,
-        config,
-        "curl -s http://127.0.0.1:7575/devtools 2>/dev/null || echo '{\"started\":false,\"completed\":false,\"tools\":[]}'",
-    )
-    .await?;
-
-    serde_json::from_str(&output).map_err(|e| {
-        crate::error::SpuffError::Provider(format!("Failed to parse devtools response: {}", e))
-    })
+pub async fn get_bootstrap_status(agent: &AgentClient) -> Result<BootstrapStatus> {
+    match agent.get("/status").await {
+        Err(SpuffError::Ssh(e)) => {
+            tracing::debug!("Agent not reachable yet: {}", e);
+            Ok(BootstrapStatus::default())
+        }
+        result => result,
+    }
 }
 
-pub async fn get_project_status(ip: &str, config: &AppConfig) -> Result<ProjectSetupState> {
-    let output = crate::connector::ssh::run_command(
-        ip,
-        config,
-        "curl -s http://127.0.0.1:7575/project/status 2>/dev/null || echo '{}'",
-    )
-    .await?;
+pub async fn get_devtools_status(agent: &AgentClient) -> Result<DevToolsState> {
+    match agent.get("/devtools").await {
+        Err(SpuffError::Ssh(e)) => {
+            tracing::debug!("Agent not reachable yet: {}", e);
+            Ok(DevToolsState::default())
+        }
+        result => result,
+    }
+}
 
-    serde_json::from_str(&output).map_err(|e| {
-        crate::error::SpuffError::Provider(format!("Failed to parse project status: {}", e))
-    })
+pub async fn get_project_status(agent: &AgentClient) -> Result<ProjectSetupState> {
+    agent.get("/project/status").await
 }
@@ -9,33 +9,53 @@ mod types;
 
 use console::style;
 
+use crate::cli::output::OutputFormat;
 use crate::config::AppConfig;
 use crate::error::Result;
 use crate::project_config::ProjectConfig;
 use crate::provider::create_provider;
-use crate::state::StateDb;
+use crate::state::{DestroyRecord, LocalInstance, StateDb};
 use crate::utils::format_elapsed;
 
-use display::{print_bootstrap_checklist, print_devtools_status, print_project_setup_status};
+use super::env::EnvironmentInfo;
+
+use display::{
+    print_bootstrap_checklist, print_destroy_record, print_devtools_status,
+    print_project_setup_status,
+};
 use format::{format_bootstrap_status, format_status};
-use http::{get_bootstrap_status, get_devtools_status, get_project_status};
+use http::{connect_agent, get_devtools_status, get_project_status};
+use types::StatusReport;
 
-pub async fn execute(config: &AppConfig, detailed: bool) -> Result<()> {
-    let instance = match StateDb::open() {
+pub async fn execute(config: &AppConfig, detailed: bool, output: OutputFormat) -> Result<()> {
+    let (instance, destroy_records) = match StateDb::open() {
         Ok(db) => {
             let inst = db.get_active_instance()?;
+            let records = db.list_destroy_records().unwrap_or_default();
             // Release database lock early before any I/O operations
             drop(db);
-            inst
+            (inst, records)
         }
         Err(e) => {
             // ChronDB may fail in resource-constrained environments (e.g., Docker with limited stack)
             // Treat as "no active environment" but log the error
             tracing::warn!("Could not open state database: {}", e);
-            None
+            (None, Vec::new())
         }
     };
 
+    // Restart the auto-destroy watchdog if it died (e.g. after a reboot)
+    if let Some(instance) = &instance {
+        if let Err(e) = super::watchdog::ensure_running(config, instance) {
+            tracing::debug!("Failed to start auto-destroy watchdog: {}", e);
+        }
+    }
+
+    if !output.is_text() {
+        let report = build_report(config, instance, &destroy_records, detailed).await;
+        return output.print(&report);
+    }
+
     match instance {
         Some(instance) => {
             super::ssh::print_banner();
@@ -49,11 +69,35 @@ pub async fn execute(config: &AppConfig, detailed: bool) -> Result<()> {
             println!("  {}      {}", style("Provider").dim(), &instance.provider);
             println!("  {}        {}", style("Region").dim(), &instance.region);
             println!("  {}          {}", style("Size").dim(), &instance.size);
+            if let Some(price) = &instance.price {
+                println!(
+                    "  {}         {}/h",
+                    style("Price").dim(),
+                    super::catalog::format_price(price.amount, &price.currency, 4)
+                );
+            }
             println!(
                 "  {}       {}",
                 style("Uptime").dim(),
                 style(format_elapsed(instance.created_at)).yellow()
             );
+            if let Some(stopped_at) = instance.stopped_at {
+                println!(
+                    "  {}      {} {}",
+                    style("Stopped").dim(),
+                    style(format!("{} ago", format_elapsed(stopped_at))).yellow(),
+                    style("(spuff start)").dim()
+                );
+            }
+
+            if let Some(tunnels) = super::tunnel::query_status(&instance.id).await {
+                super::tunnel::print_status(&tunnels);
+            }
+
+            // A failed attempt leaves the instance in place; say why
+            if let Some(record) = destroy_records.iter().find(|r| r.id == instance.id) {
+                print_destroy_record(record);
+            }
 
             if detailed {
                 let provider = create_provider(config)?;
@@ -83,7 +127,7 @@ pub async fn execute(config: &AppConfig, detailed: bool) -> Result<()> {
                 }
 
                 // Try to get bootstrap status from agent
-                if let Ok(bootstrap) = get_bootstrap_status(&instance.ip, config).await {
+                if let Some((agent, bootstrap)) = connect_agent(&instance.ip, config).await {
                     println!(
                         "  {}     {}",
                         style("Bootstrap").dim(),
@@ -98,13 +142,13 @@ pub async fn execute(config: &AppConfig, detailed: bool) -> Result<()> {
 
                     // Show devtools status if bootstrap is ready
                     if bootstrap.bootstrap_status == "ready" {
-                        if let Ok(devtools) = get_devtools_status(&instance.ip, config).await {
+                        if let Ok(devtools) = get_devtools_status(&agent).await {
                             println!();
                             print_devtools_status(&devtools);
                         }
 
                         // Show project setup status if project.json exists
-                        if let Ok(project_status) = get_project_status(&instance.ip, config).await {
+                        if let Ok(project_status) = get_project_status(&agent).await {
                             println!();
                             print_project_setup_status(&project_status);
                         }
@@ -138,6 +182,10 @@ pub async fn execute(config: &AppConfig, detailed: bool) -> Result<()> {
                 style("○").dim(),
                 style("No active environment").dim()
             );
+            if let Some(record) = destroy_records.first() {
+                println!();
+                print_destroy_record(record);
+            }
             println!();
             println!("  Run {} to create one.", style("spuff up").cyan());
         }
@@ -145,3 +193,65 @@ pub async fn execute(config: &AppConfig, detailed: bool) -> Result<()> {
 
     Ok(())
 }
+
+/// Gather what the text output shows, for `json`/`yaml` output.
+async fn build_report(
+    config: &AppConfig,
+    instance: Option<LocalInstance>,
+    destroy_records: &[DestroyRecord],
+    detailed: bool,
+) -> StatusReport {
+    let Some(instance) = instance else {
+        return StatusReport {
+            environment: None,
+            tunnels: None,
+            last_auto_destroy: destroy_records.first().cloned(),
+            remote_status: None,
+            bootstrap_status: None,
+            devtools: None,
+            project_setup: None,
+        };
+    };
+
+    let mut report = StatusReport {
+        environment: Some(EnvironmentInfo::from_instance(&instance, true)),
+        tunnels: super::tunnel::query_status(&instance.id).await,
+        last_auto_destroy: destroy_records
+            .iter()
+            .find(|r| r.id == instance.id)
+            .cloned(),
+        remote_status: None,
+        bootstrap_status: None,
+        devtools: None,
+        project_setup: None,
+    };
+
+    if !detailed {
+        return report;
+    }
+
+    report.remote_status = match create_provider(config) {
+        Ok(provider) => match provider.get_instance(&instance.id).await {
+            Ok(Some(remote)) => Some(remote.status.to_string()),
+            Ok(None) => Some("not found".to_string()),
+            Err(e) => {
+                tracing::debug!("Failed to get instance status: {}", e);
+                None
+            }
+        },
+        Err(e) => {
+            tracing::debug!("Failed to create provider: {}", e);
+            None
+        }
+    };
+
+    if let Some((agent, bootstrap)) = connect_agent(&instance.ip, config).await {
+        if bootstrap.bootstrap_status == "ready" {
+            report.devtools = get_devtools_status(&agent).await.ok();
+            report.project_setup = get_project_status(&agent).await.ok();
+        }
+        report.bootstrap_status = Some(bootstrap.bootstrap_status);
+    }
+
+    report
+}
@@ -2,16 +2,40 @@
 //!
 //! Response types from the agent status endpoints.
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+use crate::cli::commands::env::EnvironmentInfo;
+use crate::cli::commands::tunnel::DaemonStatus;
+use crate::project_config::ProjectSetupState;
+use crate::state::DestroyRecord;
+
+/// `spuff status` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct StatusReport {
+    /// None when there is no active environment
+    pub environment: Option<EnvironmentInfo>,
+    /// Background tunnel daemon, when running
+    pub tunnels: Option<DaemonStatus>,
+    /// Outcome of the last automatic destroy attempt
+    pub last_auto_destroy: Option<DestroyRecord>,
+    /// Instance status at the provider (`--detailed`)
+    pub remote_status: Option<String>,
+    /// Bootstrap step reported by the agent (`--detailed`)
+    pub bootstrap_status: Option<String>,
+    /// Devtools installation (`--detailed`, once bootstrap is ready)
+    pub devtools: Option<DevToolsState>,
+    /// spuff.yaml setup (`--detailed`, once bootstrap is ready)
+    pub project_setup: Option<ProjectSetupState>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct DevToolsState {
     pub started: bool,
     pub completed: bool,
     pub tools: Vec<DevTool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DevTool {
     #[allow(dead_code)]
     pub id: String,
@@ -21,7 +45,7 @@ pub struct DevTool {
     pub version: Option<String>,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum DevToolStatus {
     Pending,
@@ -31,7 +55,7 @@ pub enum DevToolStatus {
     Skipped,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct BootstrapStatus {
     #[serde(default)]
     pub bootstrap_status: String,
@@ -0,0 +1,1161 @@
+//! Background tunnels (`spuff tunnel`)
+//!
+//! Tunnels run in a detached daemon, one per environment, so they outlive the
+//! terminal that started them. The daemon records its PID and listens on a
+//! Unix control socket next to it (`~/.spuff/tunnels/`). `spuff tunnel` talks
+//! to it to add or remove forwards at runtime, `spuff status` asks it which
+//! forwards are live, and `spuff tunnel --stop` shuts it down.
+//!
+//! When the SSH session drops (laptop sleep, network change, `spuff stop`),
+//! the daemon reconnects with exponential backoff and reopens every forward.
+//! It picks up the instance's current IP from the state database on each
+//! attempt and exits once the environment is gone.
+//!
+//! With `--auto`, the daemon also polls the agent's `/ports` endpoint and
+//! forwards every port a process starts listening on, closing the forward
+//! again once nothing listens there. Ports removed by hand stay ignored.
+//!
+//! The control protocol is one JSON request and one JSON response per
+//! connection, each on a single line.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use console::style;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{watch, Mutex};
+
+use super::agent::ListeningPort;
+use crate::cli::output::OutputFormat;
+use crate::config::AppConfig;
+use crate::connector::agent::{AgentClient, AGENT_PORT};
+use crate::error::{Result, SpuffError};
+use crate::ssh::{PortForward, SshClient};
+use crate::state::{LocalInstance, StateDb};
+
+/// How often the daemon checks the session and retries failed forwards.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Longest wait between reconnect attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Give up on a connection attempt after this long.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long clients wait for the daemon to answer.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A single forward handled by the daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "port", rename_all = "lowercase")]
+pub enum Forward {
+    /// Local port forwarded to the same port on the VM
+    Local(u16),
+    /// VM port forwarded back to the same port here
+    Reverse(u16),
+    /// Local port of a SOCKS5 proxy into the VM's network
+    Socks(u16),
+}
+
+impl Forward {
+    pub fn port(&self) -> u16 {
+        match *self {
+            Forward::Local(port) | Forward::Reverse(port) | Forward::Socks(port) => port,
+        }
+    }
+}
+
+impl fmt::Display for Forward {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Forward::Local(port) => write!(f, "localhost:{} → vm:{}", port, port),
+            Forward::Reverse(port) => write!(f, "vm:{} → localhost:{}", port, port),
+            Forward::Socks(port) => write!(f, "socks5://localhost:{} → vm network", port),
+        }
+    }
+}
+
+/// Request sent to the daemon over its control socket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Request {
+    /// Start forwarding (already active forwards are left alone)
+    Add {
+        forwards: Vec<Forward>,
+    },
+    /// Stop every forward using one of these ports
+    Remove {
+        ports: Vec<u16>,
+    },
+    /// Turn forwarding of ports discovered on the VM on or off
+    Auto {
+        enabled: bool,
+    },
+    Status,
+    Stop,
+}
+
+/// Daemon answer to a [`Request`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Response {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<DaemonStatus>,
+}
+
+/// What a tunnel daemon is doing right now.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+
+    /// Host of the current session, if connected
+    #[serde(default)]
+    pub host: Option<String>,
+
+    /// When the current session was established
+    #[serde(default)]
+    pub connected_since: Option<DateTime<Utc>>,
+
+    /// Failed connection attempts since the session dropped
+    #[serde(default)]
+    pub attempts: u32,
+
+    /// Why the last connection attempt failed
+    #[serde(default)]
+    pub last_error: Option<String>,
+
+    /// Whether ports discovered on the VM are forwarded
+    #[serde(default)]
+    pub auto: bool,
+
+    #[serde(default)]
+    pub forwards: Vec<ForwardStatus>,
+}
+
+impl DaemonStatus {
+    pub fn is_connected(&self) -> bool {
+        self.connected_since.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardStatus {
+    pub forward: Forward,
+
+    /// Whether the forward is listening through the current session
+    pub active: bool,
+
+    /// Why the forward couldn't be opened (e.g. port already in use)
+    #[serde(default)]
+    pub error: Option<String>,
+
+    /// Process listening on the VM, for forwards opened automatically
+    #[serde(default)]
+    pub process: Option<String>,
+
+    /// Opened automatically for a port discovered on the VM
+    #[serde(default)]
+    pub discovered: bool,
+}
+
+/// In `json`/`yaml` output, prints the daemon's status once the command is
+/// done (`null` when no daemon is running).
+pub async fn execute(
+    port: Option<u16>,
+    reverse: Vec<u16>,
+    socks: Option<u16>,
+    auto: Option<bool>,
+    remove: Vec<u16>,
+    stop: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let instance = {
+        let db = StateDb::open()?;
+        db.get_active_instance()?
+            .ok_or(SpuffError::NoActiveInstance)?
+    };
+
+    if stop {
+        return stop_command(&instance, output).await;
+    }
+
+    if !remove.is_empty() {
+        return remove_command(&instance, remove, output).await;
+    }
+
+    // Get ports from project config or use specific port
+    let local = match port {
+        Some(port) => vec![port],
+        None => super::ssh::get_project_ports(),
+    };
+    let forwards: Vec<Forward> = local
+        .into_iter()
+        .map(Forward::Local)
+        .chain(reverse.into_iter().map(Forward::Reverse))
+        .chain(socks.map(Forward::Socks))
+        .collect();
+
+    if auto == Some(false) {
+        return auto_off_command(&instance, output).await;
+    }
+
+    if forwards.is_empty() && auto.is_none() {
+        // Nothing to add; show what's running instead
+        if let Some(status) = query_status(&instance.id).await {
+            return report(Some(&status), output);
+        }
+        return Err(SpuffError::Config(
+            "No ports configured in spuff.yaml. Use --port, --reverse or --socks.".to_string(),
+        ));
+    }
+
+    if output.is_text() {
+        super::ssh::print_banner();
+        println!(
+            "  {} Creating tunnels to {} {}",
+            style("→").bold(),
+            style(&instance.name).white().bold(),
+            style(format!("({})", &instance.ip)).dim()
+        );
+        println!();
+
+        print_tunnel_info(&forwards);
+    }
+
+    if !is_running(&instance.id) {
+        start_daemon(&instance).await?;
+    }
+
+    let mut response = request(&instance.id, &Request::Add { forwards }).await?;
+    if auto == Some(true) {
+        response = request(&instance.id, &Request::Auto { enabled: true }).await?;
+    }
+    let mut status = response
+        .status
+        .ok_or_else(|| SpuffError::Ssh("Tunnel daemon sent no status".to_string()))?;
+
+    // A fresh daemon may still be on its first connection attempt
+    let deadline = tokio::time::Instant::now() + CONNECT_TIMEOUT;
+    while !status.is_connected()
+        && status.last_error.is_none()
+        && tokio::time::Instant::now() < deadline
+    {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        status = match query_status(&instance.id).await {
+            Some(status) => status,
+            None => break,
+        };
+    }
+
+    report(Some(&status), output)?;
+
+    output.println(format_args!(
+        "  {} {}",
+        style("Stop:").dim(),
+        style("spuff tunnel --stop").cyan()
+    ));
+    output.println("");
+
+    Ok(())
+}
+
+async fn remove_command(
+    instance: &LocalInstance,
+    ports: Vec<u16>,
+    output: OutputFormat,
+) -> Result<()> {
+    if !is_running(&instance.id) {
+        output.println(format_args!("  {} No tunnels running.", style("○").dim()));
+        return report(None, output);
+    }
+
+    let response = request(&instance.id, &Request::Remove { ports }).await?;
+    report(response.status.as_ref(), output)
+}
+
+async fn auto_off_command(instance: &LocalInstance, output: OutputFormat) -> Result<()> {
+    if !is_running(&instance.id) {
+        output.println(format_args!("  {} No tunnels running.", style("○").dim()));
+        return report(None, output);
+    }
+
+    let response = request(&instance.id, &Request::Auto { enabled: false }).await?;
+    report(response.status.as_ref(), output)
+}
+
+async fn stop_command(instance: &LocalInstance, output: OutputFormat) -> Result<()> {
+    if stop_daemon(&instance.id).await? {
+        output.println(format_args!(
+            "  {} Tunnels stopped.",
+            style("✓").green().bold()
+        ));
+    } else {
+        output.println(format_args!("  {} No tunnels running.", style("○").dim()));
+    }
+    report(None, output)
+}
+
+/// Print the daemon's status after a command, styled or as `json`/`yaml`.
+fn report(status: Option<&DaemonStatus>, output: OutputFormat) -> Result<()> {
+    if !output.is_text() {
+        return output.print(&status);
+    }
+    if let Some(status) = status {
+        print_status(status);
+        println!();
+    }
+    Ok(())
+}
+
+/// Start the environment's tunnel daemon with these forwards, e.g. to bring
+/// back the tunnels of a resumed environment.
+pub(super) async fn restore(
+    instance: &LocalInstance,
+    forwards: Vec<Forward>,
+    auto: bool,
+) -> Result<()> {
+    if forwards.is_empty() && !auto {
+        return Ok(());
+    }
+
+    if !is_running(&instance.id) {
+        start_daemon(instance).await?;
+    }
+    request(&instance.id, &Request::Add { forwards }).await?;
+    if auto {
+        request(&instance.id, &Request::Auto { enabled: true }).await?;
+    }
+    Ok(())
+}
+
+/// Stop the environment's tunnel daemon, if any. Returns whether one was running.
+pub(super) async fn stop_daemon(instance_id: &str) -> Result<bool> {
+    let Some(pid) = running_pid(instance_id) else {
+        return Ok(false);
+    };
+
+    if let Err(e) = request(instance_id, &Request::Stop).await {
+        tracing::debug!("Tunnel daemon didn't take stop request: {}", e);
+        #[cfg(unix)]
+        {
+            let _ = nix::sys::signal::kill(
+                nix::unistd::Pid::from_raw(pid),
+                nix::sys::signal::Signal::SIGTERM,
+            );
+        }
+    }
+
+    // Wait for it to release its ports
+    for _ in 0..50 {
+        if running_pid(instance_id).is_none() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    Ok(true)
+}
+
+/// Live forwards of the environment's tunnel daemon, if one is running.
+pub(super) async fn query_status(instance_id: &str) -> Option<DaemonStatus> {
+    if !is_running(instance_id) {
+        return None;
+    }
+    match request(instance_id, &Request::Status).await {
+        Ok(response) => response.status,
+        Err(e) => {
+            tracing::debug!("Failed to query tunnel daemon: {}", e);
+            None
+        }
+    }
+}
+
+/// Local ports the environment's tunnel daemon forwards.
+pub(super) async fn active_local_ports(instance_id: &str) -> Vec<u16> {
+    query_status(instance_id)
+        .await
+        .map(|status| {
+            status
+                .forwards
+                .iter()
+                .filter_map(|f| match f.forward {
+                    Forward::Local(port) => Some(port),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Print tunnel information box
+pub(super) fn print_tunnel_info(forwards: &[Forward]) {
+    if forwards.is_empty() {
+        return;
+    }
+
+    println!(
+        "  {}",
+        style("╭──────────────────────────────────────────────────────────╮").dim()
+    );
+    println!(
+        "  {}  {:<56} {}",
+        style("│").dim(),
+        style("SSH Tunnels").cyan(),
+        style("│").dim()
+    );
+
+    for forward in forwards {
+        println!(
+            "  {}  {:<56} {}",
+            style("│").dim(),
+            forward.to_string(),
+            style("│").dim()
+        );
+    }
+
+    println!(
+        "  {}",
+        style("╰──────────────────────────────────────────────────────────╯").dim()
+    );
+    println!();
+}
+
+/// Print the connection state and forwards reported by a daemon.
+pub(super) fn print_status(status: &DaemonStatus) {
+    match (&status.host, status.connected_since) {
+        (Some(host), Some(since)) => println!(
+            "  {}       {} {} {}",
+            style("Tunnels").dim(),
+            style("connected").green(),
+            style(format!("to {}", host)).dim(),
+            style(format!(
+                "for {} (pid {})",
+                crate::utils::format_elapsed(since),
+                status.pid
+            ))
+            .dim()
+        ),
+        _ => println!(
+            "  {}       {} {}",
+            style("Tunnels").dim(),
+            style("reconnecting").yellow(),
+            style(format!(
+                "(attempt {}{})",
+                status.attempts + 1,
+                status
+                    .last_error
+                    .as_deref()
+                    .map(|e| format!(": {}", e))
+                    .unwrap_or_default()
+            ))
+            .dim()
+        ),
+    }
+
+    for forward in &status.forwards {
+        let icon = if forward.active {
+            style("●").green()
+        } else {
+            style("○").dim()
+        };
+        match (&forward.error, &forward.process) {
+            (Some(error), _) => println!(
+                "                {} {} {}",
+                icon,
+                forward.forward,
+                style(format!("({})", error)).red()
+            ),
+            (None, Some(process)) => println!(
+                "                {} {} {}",
+                icon,
+                forward.forward,
+                style(format!("({})", process)).dim()
+            ),
+            (None, None) => println!("                {} {}", icon, forward.forward),
+        }
+    }
+
+    if status.auto {
+        println!(
+            "                {} {}",
+            style("◌").cyan(),
+            style("forwarding new ports on the VM automatically").dim()
+        );
+    }
+}
+
+/// Spawn a detached daemon for the instance and wait for its control socket.
+async fn start_daemon(instance: &LocalInstance) -> Result<()> {
+    let dir = tunnels_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file_path(&instance.id, "log")?)?;
+
+    let mut command = std::process::Command::new(std::env::current_exe()?);
+    command
+        .args(["tunnel-daemon", "--id", &instance.id])
+        .stdin(std::process::Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+
+    // Own process group so Ctrl+C in the terminal doesn't reach it
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let child = command.spawn()?;
+    tracing::debug!(
+        "Started tunnel daemon for {} (pid {})",
+        instance.name,
+        child.id()
+    );
+
+    let socket = file_path(&instance.id, "sock")?;
+    for _ in 0..50 {
+        if UnixStream::connect(&socket).await.is_ok() {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    Err(SpuffError::Ssh(format!(
+        "Tunnel daemon didn't start; see {}",
+        file_path(&instance.id, "log")?.display()
+    )))
+}
+
+/// Run the tunnel daemon until stopped or the environment is gone.
+pub async fn run(config: &AppConfig, instance_id: String) -> Result<()> {
+    let pid_path = file_path(&instance_id, "pid")?;
+    let socket_path = file_path(&instance_id, "sock")?;
+    std::fs::create_dir_all(tunnels_dir()?)?;
+
+    // A socket left behind by a daemon that crashed blocks the bind
+    let _ = std::fs::remove_file(&socket_path);
+    let listener = UnixListener::bind(&socket_path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::fs::write(&pid_path, std::process::id().to_string())?;
+
+    let daemon = Arc::new(Mutex::new(Daemon::default()));
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    tokio::spawn(serve(listener, daemon.clone(), shutdown_tx.clone()));
+    tokio::spawn(forward_shutdown_signals(shutdown_tx));
+
+    let result = supervise(config, &instance_id, &daemon, shutdown_rx).await;
+
+    daemon.lock().await.disconnect().await;
+    let _ = std::fs::remove_file(&socket_path);
+    let _ = std::fs::remove_file(&pid_path);
+    tracing::info!("Tunnel daemon for {} stopped", instance_id);
+
+    result
+}
+
+/// Keep the session up, reconnecting with backoff when it drops.
+async fn supervise(
+    config: &AppConfig,
+    instance_id: &str,
+    daemon: &Mutex<Daemon>,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<()> {
+    loop {
+        if *shutdown.borrow() {
+            return Ok(());
+        }
+
+        let connected = daemon.lock().await.is_connected().await;
+        if connected {
+            // Follows ports on the VM and retries forwards that failed to
+            // open (e.g. port in use)
+            daemon.lock().await.refresh().await;
+        } else {
+            daemon.lock().await.disconnect().await;
+
+            match connect(config, instance_id).await {
+                Ok(Some((host, client))) => {
+                    tracing::info!("Connected to {}", host);
+                    let mut daemon = daemon.lock().await;
+                    daemon.agent =
+                        Some(AgentClient::new(client.clone(), config.agent_token.clone()));
+                    daemon.client = Some(client);
+                    daemon.host = Some(host);
+                    daemon.connected_since = Some(Utc::now());
+                    daemon.attempts = 0;
+                    daemon.last_error = None;
+                    daemon.refresh().await;
+                }
+                Ok(None) => {
+                    tracing::info!("Environment {} is gone, exiting", instance_id);
+                    return Ok(());
+                }
+                Err(e) => {
+                    tracing::warn!("Connection failed: {}", e);
+                    let delay = {
+                        let mut daemon = daemon.lock().await;
+                        daemon.attempts += 1;
+                        daemon.last_error = Some(e.to_string());
+                        backoff(daemon.attempts)
+                    };
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = shutdown.changed() => {}
+                    }
+                    continue;
+                }
+            }
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+            _ = shutdown.changed() => {}
+        }
+    }
+}
+
+/// Connect to the instance's current address.
+///
+/// Returns `None` once the instance is no longer in the local state.
+async fn connect(config: &AppConfig, instance_id: &str) -> Result<Option<(String, SshClient)>> {
+    let instance = {
+        let db = StateDb::open()?;
+        db.find_instance(instance_id)?
+    };
+    let Some(instance) = instance else {
+        return Ok(None);
+    };
+
+    if instance.stopped_at.is_some() {
+        return Err(SpuffError::Ssh(format!(
+            "{} is stopped (spuff start)",
+            instance.name
+        )));
+    }
+
+    // Pins follow the instance's IP, which changes on every boot
+    let pins_loaded = crate::ssh::host_keys::load_pins().is_ok();
+
+    let ssh_config = super::ssh::app_config_to_ssh_config(config);
+    let client = tokio::time::timeout(
+        CONNECT_TIMEOUT,
+        SshClient::connect(&instance.ip, 22, &ssh_config),
+    )
+    .await
+    .map_err(|_| SpuffError::Ssh(format!("Timed out connecting to {}", instance.ip)))??;
+
+    if pins_loaded {
+        if let Err(e) = crate::ssh::host_keys::store_learned() {
+            tracing::warn!("Failed to store pinned host keys: {}", e);
+        }
+    }
+
+    Ok(Some((instance.ip, client)))
+}
+
+/// Delay before reconnect attempt `attempt` (1-based): 1s, 2s, 4s, ... up to a minute.
+fn backoff(attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(6);
+    (Duration::from_secs(1) * 2u32.pow(exponent)).min(MAX_BACKOFF)
+}
+
+/// Answer control requests until shutdown.
+async fn serve(listener: UnixListener, daemon: Arc<Mutex<Daemon>>, shutdown: watch::Sender<bool>) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                tracing::warn!("Control socket accept failed: {}", e);
+                continue;
+            }
+        };
+
+        let daemon = daemon.clone();
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &daemon, &shutdown).await {
+                tracing::debug!("Control connection failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    daemon: &Mutex<Daemon>,
+    shutdown: &watch::Sender<bool>,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line).await?;
+
+    let response = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => handle_request(request, daemon, shutdown).await,
+        Err(e) => Response {
+            error: Some(format!("Invalid request: {}", e)),
+            status: None,
+        },
+    };
+
+    let mut payload = serde_json::to_string(&response)?;
+    payload.push('\n');
+    writer.write_all(payload.as_bytes()).await?;
+    Ok(())
+}
+
+async fn handle_request(
+    request: Request,
+    daemon: &Mutex<Daemon>,
+    shutdown: &watch::Sender<bool>,
+) -> Response {
+    let mut daemon = daemon.lock().await;
+    match request {
+        Request::Add { forwards } => {
+            for forward in &forwards {
+                daemon.ignored.remove(&forward.port());
+            }
+            daemon.desired.extend(forwards);
+            daemon.refresh().await;
+        }
+        Request::Remove { ports } => daemon.remove(&ports).await,
+        Request::Auto { enabled } => {
+            daemon.auto = enabled;
+            if !enabled {
+                daemon.discovered.clear();
+            }
+            daemon.refresh().await;
+        }
+        Request::Status => {}
+        Request::Stop => {
+            let _ = shutdown.send(true);
+        }
+    }
+
+    Response {
+        error: None,
+        status: Some(daemon.status()),
+    }
+}
+
+#[cfg(unix)]
+async fn forward_shutdown_signals(shutdown: watch::Sender<bool>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let Ok(mut terminate) = signal(SignalKind::terminate()) else {
+        return;
+    };
+    tokio::select! {
+        _ = terminate.recv() => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    let _ = shutdown.send(true);
+}
+
+#[cfg(not(unix))]
+async fn forward_shutdown_signals(shutdown: watch::Sender<bool>) {
+    let _ = tokio::signal::ctrl_c().await;
+    let _ = shutdown.send(true);
+}
+
+/// Session and forwards owned by the daemon.
+#[derive(Default)]
+struct Daemon {
+    client: Option<SshClient>,
+    /// Agent API over the same session
+    agent: Option<AgentClient>,
+    host: Option<String>,
+    connected_since: Option<DateTime<Utc>>,
+    attempts: u32,
+    last_error: Option<String>,
+
+    /// Forwards asked for; kept across reconnects
+    desired: BTreeSet<Forward>,
+    /// Forwards listening through the current session
+    open: BTreeMap<Forward, PortForward>,
+    /// Why a wanted forward isn't open
+    errors: BTreeMap<Forward, String>,
+
+    /// Forward ports discovered on the VM (`spuff tunnel --auto`)
+    auto: bool,
+    /// Ports listening on the VM and the process behind each
+    discovered: BTreeMap<u16, Option<String>>,
+    /// Ports removed by hand, never forwarded automatically again
+    ignored: BTreeSet<u16>,
+}
+
+impl Daemon {
+    async fn is_connected(&self) -> bool {
+        match &self.client {
+            Some(client) => !client.is_closed().await,
+            None => false,
+        }
+    }
+
+    /// Forwards that should be open: the desired ones plus, in auto mode,
+    /// one for every discovered port not already taken or ignored.
+    fn wanted(&self) -> BTreeSet<Forward> {
+        let mut wanted = self.desired.clone();
+        if self.auto {
+            let taken: BTreeSet<u16> = self.desired.iter().map(Forward::port).collect();
+            wanted.extend(
+                self.discovered
+                    .keys()
+                    .filter(|port| !taken.contains(port) && !self.ignored.contains(port))
+                    .map(|&port| Forward::Local(port)),
+            );
+        }
+        wanted
+    }
+
+    /// Bring the open forwards in line with what is wanted.
+    async fn refresh(&mut self) {
+        if self.auto {
+            self.discover().await;
+        }
+        self.close_unwanted().await;
+        self.open_pending().await;
+    }
+
+    /// Ask the agent which ports are listening on the VM.
+    ///
+    /// Keeps the previous result when the agent can't be reached, so a
+    /// hiccup doesn't close every automatic forward.
+    async fn discover(&mut self) {
+        let Some(agent) = &self.agent else {
+            return;
+        };
+
+        let request = agent.get::<Vec<ListeningPort>>("/ports");
+        match tokio::time::timeout(REQUEST_TIMEOUT, request).await {
+            Ok(Ok(ports)) => {
+                self.discovered = ports
+                    .into_iter()
+                    .filter(auto_forwardable)
+                    .map(|p| (p.port, p.process))
+                    .collect();
+            }
+            Ok(Err(e)) => tracing::debug!("Port discovery failed: {}", e),
+            Err(_) => tracing::debug!("Port discovery timed out"),
+        }
+    }
+
+    /// Stop open forwards that are no longer wanted.
+    async fn close_unwanted(&mut self) {
+        let wanted = self.wanted();
+        let stale: Vec<Forward> = self
+            .open
+            .keys()
+            .filter(|f| !wanted.contains(f))
+            .copied()
+            .collect();
+
+        for forward in stale {
+            if let Some(handle) = self.open.remove(&forward) {
+                handle.stop().await;
+                tracing::info!("Stopped {}", forward);
+            }
+        }
+        self.errors.retain(|forward, _| wanted.contains(forward));
+    }
+
+    /// Open wanted forwards that aren't open yet.
+    async fn open_pending(&mut self) {
+        let pending: Vec<Forward> = self
+            .wanted()
+            .into_iter()
+            .filter(|f| !self.open.contains_key(f))
+            .collect();
+        let Some(client) = &self.client else {
+            return;
+        };
+
+        for forward in pending {
+            let result = match forward {
+                Forward::Local(port) => client.forward_local_port(port, port).await,
+                Forward::Reverse(port) => client.forward_remote_port(port, port).await,
+                Forward::Socks(port) => client.forward_dynamic_port(port).await,
+            };
+            match result {
+                Ok(handle) => {
+                    tracing::info!("Forwarding {}", forward);
+                    self.errors.remove(&forward);
+                    self.open.insert(forward, handle);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to forward {}: {}", forward, e);
+                    self.errors.insert(forward, e.to_string());
+                }
+            }
+        }
+    }
+
+    /// Stop and forget every forward using one of `ports`.
+    async fn remove(&mut self, ports: &[u16]) {
+        self.desired.retain(|f| !ports.contains(&f.port()));
+        self.ignored.extend(ports);
+        self.close_unwanted().await;
+    }
+
+    /// Drop the session and close its forwards, keeping them desired.
+    async fn disconnect(&mut self) {
+        for (_, handle) in std::mem::take(&mut self.open) {
+            handle.stop().await;
+        }
+        self.client = None;
+        self.agent = None;
+        self.host = None;
+        self.connected_since = None;
+    }
+
+    fn status(&self) -> DaemonStatus {
+        DaemonStatus {
+            pid: std::process::id(),
+            host: self.host.clone(),
+            connected_since: self.connected_since,
+            attempts: self.attempts,
+            last_error: self.last_error.clone(),
+            auto: self.auto,
+            forwards: self
+                .wanted()
+                .into_iter()
+                .map(|forward| ForwardStatus {
+                    forward,
+                    active: self.open.contains_key(&forward),
+                    error: self.errors.get(&forward).cloned(),
+                    process: if self.desired.contains(&forward) {
+                        None
+                    } else {
+                        self.discovered.get(&forward.port()).cloned().flatten()
+                    },
+                    discovered: !self.desired.contains(&forward),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Whether a port listening on the VM should be forwarded in auto mode.
+///
+/// Skips privileged ports (system services), spuff-agent, sockets bound to
+/// an address the forward can't reach, and sshd (the VM end of reverse
+/// forwards).
+fn auto_forwardable(port: &ListeningPort) -> bool {
+    port.port >= 1024
+        && port.port != AGENT_PORT
+        && port.is_local()
+        && port.process.as_deref() != Some("sshd")
+}
+
+/// Send one request to the environment's daemon and read its response.
+async fn request(instance_id: &str, request: &Request) -> Result<Response> {
+    let socket = file_path(instance_id, "sock")?;
+
+    let exchange = async {
+        let stream = UnixStream::connect(&socket).await?;
+        let (reader, mut writer) = stream.into_split();
+
+        let mut payload = serde_json::to_string(request)?;
+        payload.push('\n');
+        writer.write_all(payload.as_bytes()).await?;
+
+        let mut line = String::new();
+        BufReader::new(reader).read_line(&mut line).await?;
+        Ok::<Response, SpuffError>(serde_json::from_str(&line)?)
+    };
+
+    let response = tokio::time::timeout(REQUEST_TIMEOUT, exchange)
+        .await
+        .map_err(|_| SpuffError::Ssh("Tunnel daemon didn't respond".to_string()))??;
+
+    match response.error {
+        Some(error) => Err(SpuffError::Ssh(error)),
+        None => Ok(response),
+    }
+}
+
+fn tunnels_dir() -> Result<PathBuf> {
+    Ok(AppConfig::config_dir()?.join("tunnels"))
+}
+
+/// Path of the daemon's `.pid`, `.sock` or `.log` file.
+fn file_path(instance_id: &str, extension: &str) -> Result<PathBuf> {
+    // Unix socket paths are limited to ~100 bytes and Docker IDs are 64 characters
+    let id = &instance_id[..instance_id.len().min(16)];
+    Ok(tunnels_dir()?.join(format!("{}.{}", id, extension)))
+}
+
+/// PID of the environment's daemon, if it is alive.
+fn running_pid(instance_id: &str) -> Option<i32> {
+    let pid = file_path(instance_id, "pid")
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|pid| pid.trim().parse::<i32>().ok())?;
+
+    #[cfg(unix)]
+    {
+        nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid), None)
+            .is_ok()
+            .then_some(pid)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+/// Check whether a tunnel daemon for the instance is alive.
+fn is_running(instance_id: &str) -> bool {
+    running_pid(instance_id).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_a_minute() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(2), Duration::from_secs(2));
+        assert_eq!(backoff(4), Duration::from_secs(8));
+        assert_eq!(backoff(7), Duration::from_secs(60));
+        assert_eq!(backoff(100), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_request_wire_format() {
+        let request = Request::Add {
+            forwards: vec![Forward::Local(3000), Forward::Socks(1080)],
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(
+            json,
+            r#"{"op":"add","forwards":[{"kind":"local","port":3000},{"kind":"socks","port":1080}]}"#
+        );
+        assert_eq!(serde_json::from_str::<Request>(&json).unwrap(), request);
+
+        let stop: Request = serde_json::from_str(r#"{"op":"stop"}"#).unwrap();
+        assert_eq!(stop, Request::Stop);
+    }
+
+    #[tokio::test]
+    async fn test_remove_by_port() {
+        let mut daemon = Daemon::default();
+        daemon.desired.extend([
+            Forward::Local(3000),
+            Forward::Reverse(3000),
+            Forward::Local(8080),
+        ]);
+        daemon
+            .errors
+            .insert(Forward::Local(3000), "port in use".to_string());
+
+        daemon.remove(&[3000]).await;
+
+        let status = daemon.status();
+        assert_eq!(status.forwards.len(), 1);
+        assert_eq!(status.forwards[0].forward, Forward::Local(8080));
+        assert!(!status.forwards[0].active);
+        assert!(daemon.errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_status_roundtrip() {
+        let mut daemon = Daemon::default();
+        daemon.desired.insert(Forward::Reverse(5432));
+        daemon.attempts = 2;
+        daemon.last_error = Some("Connection refused".to_string());
+
+        let response = Response {
+            error: None,
+            status: Some(daemon.status()),
+        };
+        let json = serde_json::to_string(&response).unwrap();
+        let status = serde_json::from_str::<Response>(&json)
+            .unwrap()
+            .status
+            .unwrap();
+
+        assert!(!status.is_connected());
+        assert_eq!(status.attempts, 2);
+        assert_eq!(status.forwards[0].forward, Forward::Reverse(5432));
+        assert_eq!(status.pid, std::process::id());
+    }
+
+    #[tokio::test]
+    async fn test_auto_forwards_discovered_ports() {
+        let mut daemon = Daemon::default();
+        daemon
+            .desired
+            .extend([Forward::Local(3000), Forward::Reverse(5432)]);
+        daemon.discovered.insert(3000, Some("node".to_string()));
+        daemon.discovered.insert(5432, Some("postgres".to_string()));
+        daemon.discovered.insert(8080, Some("python3".to_string()));
+        daemon.discovered.insert(9000, None);
+
+        // Discovered ports only count in auto mode
+        assert_eq!(daemon.wanted(), daemon.desired);
+
+        daemon.auto = true;
+        daemon.remove(&[9000]).await;
+
+        let wanted: Vec<Forward> = daemon.wanted().into_iter().collect();
+        assert_eq!(
+            wanted,
+            vec![
+                Forward::Local(3000),
+                Forward::Local(8080),
+                Forward::Reverse(5432)
+            ]
+        );
+
+        let status = daemon.status();
+        assert!(status.auto);
+        let python = status
+            .forwards
+            .iter()
+            .find(|f| f.forward == Forward::Local(8080))
+            .unwrap();
+        assert_eq!(python.process.as_deref(), Some("python3"));
+        // Explicit forwards aren't attributed to a process
+        assert_eq!(status.forwards[0].process, None);
+    }
+
+    #[test]
+    fn test_auto_forwardable() {
+        let port = |port: u16, address: &str, process: &str| ListeningPort {
+            port,
+            address: address.to_string(),
+            pid: Some(1234),
+            process: Some(process.to_string()),
+            command: None,
+        };
+
+        assert!(auto_forwardable(&port(3000, "0.0.0.0", "node")));
+        assert!(auto_forwardable(&port(5173, "127.0.0.1", "node")));
+        assert!(auto_forwardable(&port(8000, "::", "python3")));
+
+        assert!(!auto_forwardable(&port(22, "0.0.0.0", "sshd")));
+        assert!(!auto_forwardable(&port(
+            AGENT_PORT,
+            "127.0.0.1",
+            "spuff-agent"
+        )));
+        assert!(!auto_forwardable(&port(5432, "127.0.0.1", "sshd")));
+        assert!(!auto_forwardable(&port(3000, "172.17.0.1", "node")));
+    }
+}
@@ -2,9 +2,15 @@
 //!
 //! Functions for uploading local agent binaries and triggering devtools installation.
 
+use tokio::io::AsyncReadExt;
+
 use crate::config::AppConfig;
+use crate::connector::agent::AgentClient;
 use crate::error::{Result, SpuffError};
 
+/// Devtools config written by cloud-init.
+const DEVTOOLS_CONFIG: &str = "/opt/spuff/devtools.json";
+
 /// Upload local spuff-agent binary and ensure the service is running
 pub async fn upload_local_agent(ip: &str, config: &AppConfig, agent_path: &str) -> Result<()> {
     // Ensure /opt/spuff exists
@@ -38,16 +44,23 @@ pub async fn upload_local_agent(ip: &str, config: &AppConfig, agent_path: &str)
 /// Reads the devtools config from /opt/spuff/devtools.json and sends it to the agent's
 /// /devtools/install endpoint. The agent will install tools asynchronously in the background.
 pub async fn trigger_devtools_installation(ip: &str, config: &AppConfig) -> Result<()> {
+    let ssh = crate::connector::ssh::client(ip, config).await?;
+
     // Read devtools config from the VM
-    let devtools_json = crate::connector::ssh::run_command(
-        ip,
-        config,
-        "cat /opt/spuff/devtools.json 2>/dev/null || echo '{}'",
-    )
-    .await?;
+    let sftp = ssh.sftp().await?;
+    if sftp.metadata(DEVTOOLS_CONFIG).await?.is_none() {
+        tracing::debug!("No devtools config found, skipping installation trigger");
+        return Ok(());
+    }
+    let mut devtools_json = Vec::new();
+    sftp.open_read(DEVTOOLS_CONFIG, 0)
+        .await?
+        .read_to_end(&mut devtools_json)
+        .await
+        .map_err(|e| SpuffError::Ssh(format!("Failed to read {}: {}", DEVTOOLS_CONFIG, e)))?;
 
     // Parse to validate it's valid JSON
-    let devtools_config: serde_json::Value = serde_json::from_str(&devtools_json)
+    let devtools_config: serde_json::Value = serde_json::from_slice(&devtools_json)
         .map_err(|e| SpuffError::Provider(format!("Invalid devtools.json: {}", e)))?;
 
     // Skip if config is empty
@@ -56,23 +69,22 @@ pub async fn trigger_devtools_installation(ip: &str, config: &AppConfig) -> Resu
         return Ok(());
     }
 
-    // Call the agent's devtools install endpoint via SSH tunnel
-    let curl_cmd = format!(
-        r#"curl -s -X POST http://127.0.0.1:7575/devtools/install \
-           -H "Content-Type: application/json" \
-           -d '{}'"#,
-        devtools_json.trim()
-    );
+    let agent = AgentClient::new(ssh, config.agent_token.clone());
+    let response: Result<serde_json::Value> =
+        agent.post("/devtools/install", &devtools_config).await;
 
-    let result = crate::connector::ssh::run_command(ip, config, &curl_cmd).await?;
-
-    // Check for success
-    if result.contains("\"started\":true") || result.contains("already in progress") {
-        tracing::info!("Devtools installation triggered successfully");
-        Ok(())
-    } else {
-        tracing::warn!("Devtools install response: {}", result);
-        // Don't fail even if the response is unexpected
-        Ok(())
+    match response {
+        Ok(_) => tracing::info!("Devtools installation triggered successfully"),
+        // 409: an installation is already in progress
+        Err(SpuffError::Agent { status: 409, .. }) => {
+            tracing::info!("Devtools installation already in progress")
+        }
+        // Don't fail even if the agent rejects the request
+        Err(SpuffError::Agent { status, message }) => {
+            tracing::warn!("Devtools install response ({}): {}", status, message)
+        }
+        Err(e) => return Err(e),
     }
+
+    Ok(())
 }
@@ -132,7 +132,7 @@ pub async fn build_linux_agent() -> Result<String> {
         )
     };
 
-    println!(
+    eprintln!(
         "  {} {} {}",
         console::style("→").cyan(),
         console::style(&cmd).white(),
@@ -141,7 +141,7 @@ pub async fn build_linux_agent() -> Result<String> {
 
     let status = tokio::process::Command::new(&cmd)
         .args(&args)
-        .stdout(Stdio::inherit())
+        .stdout(std::io::stderr())
         .stderr(Stdio::inherit())
         .status()
         .await?;
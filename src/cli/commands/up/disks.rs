@@ -0,0 +1,84 @@
+//! Persistent disks
+//!
+//! Finds or creates the provider volumes behind `persistent_disks` so they
+//! can be attached to a new instance.
+
+use crate::environment::cloud_init::DiskMount;
+use crate::error::Result;
+use crate::project_config::PersistentDisk;
+use crate::provider::{DiskAttachment, Provider, ProviderError};
+
+/// Volumes to attach to the instance and the mounts the bootstrap sets up
+#[derive(Debug, Default)]
+pub struct PreparedDisks {
+    pub attachments: Vec<DiskAttachment>,
+    pub mounts: Vec<DiskMount>,
+}
+
+/// Look up each disk's volume, creating the ones that don't exist yet.
+///
+/// A volume in another region or still attached to another instance
+/// can't be used, so provisioning stops before creating the instance.
+pub async fn prepare_persistent_disks(
+    provider: &dyn Provider,
+    disks: &[PersistentDisk],
+    region: &str,
+) -> Result<PreparedDisks> {
+    let mut prepared = PreparedDisks::default();
+    if disks.is_empty() {
+        return Ok(prepared);
+    }
+
+    if !provider.supports_volumes() {
+        return Err(ProviderError::NotSupported {
+            feature: format!("persistent_disks on {}", provider.name()),
+        }
+        .into());
+    }
+
+    let existing = provider.list_volumes().await?;
+
+    for disk in disks {
+        let name = disk.volume_name();
+        let volume = match existing.iter().find(|v| v.name == name) {
+            Some(volume) => {
+                if let Some(volume_region) = volume.region.as_deref() {
+                    if volume_region != region {
+                        return Err(ProviderError::invalid_config(
+                            "region",
+                            format!(
+                                "disk '{}' is in {}, but the instance is being created in {}",
+                                disk.name, volume_region, region
+                            ),
+                        )
+                        .into());
+                    }
+                }
+                if let Some(instance_id) = &volume.attached_to {
+                    return Err(ProviderError::Other {
+                        message: format!(
+                            "disk '{}' is attached to instance {}; run 'spuff down' on it first",
+                            disk.name, instance_id
+                        ),
+                    }
+                    .into());
+                }
+                volume.clone()
+            }
+            None => provider.create_volume(&name, disk.size, region).await?,
+        };
+
+        if let Some(device) = provider.volume_device(&volume) {
+            prepared.mounts.push(DiskMount {
+                device,
+                mount: disk.mount.clone(),
+            });
+        }
+        prepared.attachments.push(DiskAttachment {
+            volume_id: volume.id,
+            mount_path: disk.mount.clone(),
+        });
+    }
+
+    Ok(prepared)
+}
@@ -6,24 +6,36 @@
 mod agent_upload;
 mod bootstrap;
 mod build;
+mod disks;
 mod display;
 mod preflight;
 mod provision;
 mod volumes;
 
+use std::io::IsTerminal;
+use std::path::Path;
+use std::time::Duration;
+
 use console::style;
+use serde::Serialize;
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 
+use crate::cli::output::OutputFormat;
 use crate::config::AppConfig;
-use crate::error::Result;
+use crate::error::{Result, SpuffError};
 use crate::project_config::{AiToolsConfig, ProjectConfig};
-use crate::state::StateDb;
-use crate::tui::{run_progress_ui, ProgressMessage};
+use crate::state::{LocalInstance, StateDb};
+use crate::tui::{run_event_progress, run_progress_ui, ProgressMessage};
+
+use super::env::EnvironmentInfo;
 
 use build::{build_linux_agent, LINUX_TARGET};
 use display::print_project_summary;
-use preflight::{verify_ssh_key_accessible, verify_sshfs_available};
-use provision::{provision_instance, ProvisionParams};
+use preflight::{verify_ssh_key_accessible, verify_sshfs_available, warn_budget};
+use provision::{
+    generate_instance_name, provision_instance, validate_instance_name, ProvisionParams,
+};
 
 // Step constants used across modules
 pub const STEP_CLOUD_INIT: usize = 0;
@@ -38,34 +50,78 @@ pub const STEP_UPLOAD_AGENT: usize = 6;
 pub const SUB_PACKAGES: usize = 0;
 pub const SUB_AGENT: usize = 1;
 
+/// How long the provisioning task may take to wind down once it has
+/// reported completion.
+const JOIN_GRACE: Duration = Duration::from_secs(10);
+
+/// `spuff up` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct UpResult {
+    #[serde(flatten)]
+    pub environment: EnvironmentInfo,
+    /// Command that opens a shell in the environment
+    pub connect: String,
+    /// None on Docker, whose containers run until `spuff down`
+    pub idle_timeout: Option<String>,
+    pub max_lifetime: Option<String>,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn execute(
     config: &AppConfig,
+    name: Option<String>,
     size: Option<String>,
     snapshot: Option<String>,
     region: Option<String>,
     no_connect: bool,
     dev: bool,
     ai_tools: Option<String>,
+    project_dir: Option<&Path>,
+    output: OutputFormat,
 ) -> Result<()> {
+    // Structured output means nobody watches the TUI: progress goes to
+    // stderr as NDJSON and stdout carries only the result
+    let headless = !output.is_text();
     let db = StateDb::open()?;
 
-    if let Some(instance) = db.get_active_instance()? {
+    let instance_name = match name {
+        Some(name) => {
+            validate_instance_name(&name)?;
+            name
+        }
+        None => generate_instance_name(),
+    };
+
+    if let Some(instance) = db.find_instance(&instance_name)? {
+        if headless {
+            return Err(SpuffError::Config(format!(
+                "Environment '{}' already exists",
+                instance.name
+            )));
+        }
         println!(
-            "{} Active instance found: {} ({})",
+            "{} Environment {} already exists ({})",
             style("!").yellow().bold(),
             style(&instance.name).cyan(),
             style(&instance.ip).dim()
         );
         println!(
             "Run {} first or {} to connect.",
-            style("spuff down").yellow(),
-            style("spuff ssh").cyan()
+            style(format!("spuff down --env {}", instance.name)).yellow(),
+            style(format!("spuff ssh --env {}", instance.name)).cyan()
         );
         return Ok(());
     }
 
+    warn_budget(config, &db);
+
     // Load project config from spuff.yaml (if exists)
-    let project_config = ProjectConfig::load_from_cwd().ok().flatten();
+    let project_config = match project_dir {
+        Some(dir) => ProjectConfig::load_from_dir(dir),
+        None => ProjectConfig::load_from_cwd(),
+    }
+    .ok()
+    .flatten();
 
     // Apply project config overrides (CLI args take precedence)
     let effective_size = size.or_else(|| {
@@ -78,10 +134,15 @@ pub async fn execute(
             .as_ref()
             .and_then(|p| p.resources.region.clone())
     });
+    let effective_max_lifetime = project_config
+        .as_ref()
+        .and_then(|p| p.resources.max_lifetime.clone())
+        .or_else(|| config.max_lifetime.clone())
+        .filter(|value| crate::config::parse_max_lifetime(value).is_some());
 
     // Process AI tools configuration
     // Priority: CLI > Project config > Global config > Default (all)
-    let cli_ai_tools = parse_ai_tools_arg(ai_tools.as_deref());
+    let cli_ai_tools = parse_ai_tools_arg(ai_tools.as_deref(), !headless)?;
 
     let is_docker = config.provider == "docker" || config.provider == "local";
 
@@ -90,21 +151,21 @@ pub async fn execute(
         // Pre-flight check: verify SSH key is usable
         verify_ssh_key_accessible(config).await?;
 
-        // Pre-flight check: verify SSHFS is available if volumes are configured
-        // Check both global config volumes and project volumes
-        let has_volumes = !config.volumes.is_empty()
-            || project_config
-                .as_ref()
-                .map(|pc| !pc.volumes.is_empty())
-                .unwrap_or(false);
-        if has_volumes {
+        // Pre-flight check: verify SSHFS is available if SSHFS volumes are configured
+        // Check both global config volumes and project volumes (sync volumes don't need it)
+        let has_sshfs_volumes = config
+            .volumes
+            .iter()
+            .chain(project_config.iter().flat_map(|pc| &pc.volumes))
+            .any(|v| v.driver_type != crate::volume::VolumeType::Sync);
+        if has_sshfs_volumes {
             verify_sshfs_available().await?;
         }
     }
 
     // In dev mode, build agent for Linux and upload (skip for Docker)
     if dev && !is_docker {
-        println!(
+        eprintln!(
             "{} Dev mode: building spuff-agent for Linux ({})",
             style("*").cyan().bold(),
             style(LINUX_TARGET).dim()
@@ -112,14 +173,14 @@ pub async fn execute(
 
         match build_linux_agent().await {
             Ok(path) => {
-                println!(
+                eprintln!(
                     "{} Built successfully: {}",
                     style("✓").green().bold(),
                     style(&path).dim()
                 );
             }
             Err(e) => {
-                println!("{} Failed to build agent: {}", style("✕").red().bold(), e);
+                eprintln!("{} Failed to build agent: {}", style("✕").red().bold(), e);
                 return Err(e);
             }
         }
@@ -144,12 +205,15 @@ pub async fn execute(
 
     // Print project config summary if found
     if let Some(ref pc) = project_config {
-        print_project_summary(pc);
+        if !headless {
+            print_project_summary(pc);
+        }
     }
 
     // Clone config values for the async task
     let params = ProvisionParams {
         config: config.clone(),
+        name: instance_name.clone(),
         size: effective_size.clone(),
         snapshot: snapshot.clone(),
         region: effective_region.clone(),
@@ -166,37 +230,65 @@ pub async fn execute(
         result
     });
 
-    // Run the TUI
     tracing::debug!("Starting progress UI");
-    let tui_result = run_progress_ui(steps, rx).await;
+    let ui_result = if headless {
+        run_event_progress(steps, rx).await
+    } else {
+        run_progress_ui(steps, rx).await
+    };
     tracing::debug!("Progress UI completed");
 
-    // If UI completed successfully (received Close message), the provision is done
-    // Don't wait for the JoinHandle as it may deadlock in CI
-    if tui_result.is_ok() {
-        tracing::debug!(
-            "UI completed successfully, aborting provision_task to prevent runtime hang"
-        );
-        // Abort the task first to prevent runtime blocking on shutdown
+    // Quitting the TUI before provisioning finishes cancels it
+    if matches!(ui_result, Ok(None)) && !headless && !provision_task.is_finished() {
         provision_task.abort();
-        tracing::debug!("provision_task aborted");
-        // Assume success since UI received Close message
-        let provision_result: std::result::Result<Result<()>, tokio::task::JoinError> = Ok(Ok(()));
-        return handle_provision_result(config, tui_result, provision_result, no_connect).await;
+        println!("{} Cancelled by user.", style("!").yellow().bold());
+        return Ok(());
     }
+    if let Err(e) = &ui_result {
+        tracing::warn!("Progress UI failed, waiting for provisioning: {}", e);
+    }
+
+    join_provision(provision_task, matches!(ui_result, Ok(Some(_)))).await?;
 
-    // If UI failed, wait for provision with timeout
-    tracing::debug!("UI failed, waiting for provision_task");
-    let provision_result = tokio::time::timeout(std::time::Duration::from_secs(5), provision_task)
-        .await
-        .unwrap_or(Ok(Ok(())));
+    handle_provision_result(
+        config,
+        &instance_name,
+        no_connect,
+        effective_max_lifetime.as_deref(),
+        output,
+    )
+    .await
+}
+
+/// Wait for the provisioning task and return its result. Once it has
+/// reported completion only cleanup is left, which must not hang the CLI.
+async fn join_provision(mut task: JoinHandle<Result<()>>, completed: bool) -> Result<()> {
+    let joined = if completed {
+        match tokio::time::timeout(JOIN_GRACE, &mut task).await {
+            Ok(joined) => joined,
+            Err(_) => {
+                tracing::debug!("provision_task still running after completion, aborting");
+                task.abort();
+                return Ok(());
+            }
+        }
+    } else {
+        task.await
+    };
 
-    handle_provision_result(config, tui_result, provision_result, no_connect).await
+    joined.map_err(|e| SpuffError::Provider(format!("Provisioning task failed: {}", e)))?
 }
 
-fn parse_ai_tools_arg(ai_tools: Option<&str>) -> Option<AiToolsConfig> {
-    match ai_tools {
+fn parse_ai_tools_arg(ai_tools: Option<&str>, interactive: bool) -> Result<Option<AiToolsConfig>> {
+    let tools = match ai_tools {
         Some("ask") => {
+            if !interactive || !std::io::stdin().is_terminal() {
+                return Err(SpuffError::Config(
+                    "--ai-tools ask needs a terminal; pass the tools instead (e.g. --ai-tools claude-code,codex)"
+                        .to_string(),
+                ));
+            }
+
             // Interactive mode - prompt user to select AI tools
             println!(
                 "{} Select AI coding tools to install:",
@@ -239,80 +331,144 @@ fn parse_ai_tools_arg(ai_tools: Option<&str>) -> Option<AiToolsConfig> {
         }
         Some(arg) => Some(AiToolsConfig::from_cli_arg(arg)),
         None => None, // Will use project/global config defaults
+    };
+    Ok(tools)
+}
+
+/// Start the auto-destroy watchdog for the new environment.
+///
+/// Failing to start it is not fatal: the VM still powers off on idle and
+/// the next `spuff status` retries.
+fn start_watchdog(config: &AppConfig, instance: &LocalInstance) {
+    if let Err(e) = super::watchdog::ensure_running(config, instance) {
+        tracing::warn!("Failed to start auto-destroy watchdog: {}", e);
     }
 }
 
 async fn handle_provision_result(
     config: &AppConfig,
-    tui_result: std::result::Result<Option<(String, String)>, std::io::Error>,
-    provision_result: std::result::Result<Result<()>, tokio::task::JoinError>,
+    name: &str,
     no_connect: bool,
+    max_lifetime: Option<&str>,
+    output: OutputFormat,
 ) -> Result<()> {
-    let is_docker = config.provider == "docker" || config.provider == "local";
+    let instance = StateDb::open()?
+        .find_instance(name)?
+        .ok_or_else(|| SpuffError::EnvironmentNotFound(name.to_string()))?;
+    let is_docker = instance.provider == "docker" || instance.provider == "local";
 
-    match (tui_result, provision_result) {
-        (Ok(Some((name, ip_or_id))), Ok(Ok(()))) => {
-            // Success - print final message
-            crate::cli::commands::ssh::print_banner();
-            println!(
-                "  {} {} {}",
-                style("✓").green().bold(),
-                style(&name).white().bold(),
-                style(format!("({})", &ip_or_id)).dim()
-            );
-            println!();
+    start_watchdog(config, &instance);
+
+    // Structured output never connects
+    if !output.is_text() {
+        return print_up_result(config, &instance, max_lifetime, output);
+    }
 
+    crate::cli::commands::ssh::print_banner();
+    println!(
+        "  {} {} {}",
+        style("✓").green().bold(),
+        style(&instance.name).white().bold(),
+        style(format!(
+            "({})",
             if is_docker {
-                println!(
-                    "  {}  docker exec -it {} /bin/bash",
-                    style("Shell").dim(),
-                    &ip_or_id[..12.min(ip_or_id.len())]
-                );
-                println!(
-                    "  {}  container runs until 'spuff down'",
-                    style("Note").dim()
-                );
+                &instance.id
             } else {
-                println!(
-                    "  {}  ssh {}@{}",
-                    style("SSH").dim(),
-                    config.ssh_user,
-                    ip_or_id
-                );
-                println!(
-                    "  {}  auto-destroy after {}",
-                    style("Idle").dim(),
-                    style(&config.idle_timeout).yellow()
-                );
+                &instance.ip
             }
+        ))
+        .dim()
+    );
+    println!();
 
-            if !no_connect {
-                println!();
-                if is_docker {
-                    // For Docker, get the container ID from state
-                    let db = StateDb::open()?;
-                    if let Some(instance) = db.get_active_instance()? {
-                        crate::connector::docker::connect(&instance.id).await?;
-                    }
-                } else {
-                    crate::connector::ssh::connect(&ip_or_id, config).await?;
-                }
-            }
-        }
-        (Ok(None), _) => {
-            // User cancelled
-            println!("{} Cancelled by user.", style("!").yellow().bold());
-        }
-        (Err(e), _) => {
-            println!("{} TUI error: {}", style("✕").red().bold(), e);
-        }
-        (_, Ok(Err(e))) => {
-            println!("{} Provisioning failed: {}", style("✕").red().bold(), e);
+    if is_docker {
+        println!(
+            "  {}  docker exec -it {} /bin/bash",
+            style("Shell").dim(),
+            &instance.id[..12.min(instance.id.len())]
+        );
+        println!(
+            "  {}  container runs until 'spuff down'",
+            style("Note").dim()
+        );
+    } else {
+        println!(
+            "  {}  ssh {}@{}",
+            style("SSH").dim(),
+            config.ssh_user,
+            instance.ip
+        );
+        println!(
+            "  {}  auto-destroy after {}",
+            style("Idle").dim(),
+            style(&config.idle_timeout).yellow()
+        );
+        if let Some(max_lifetime) = max_lifetime {
+            println!(
+                "  {}   shuts down after {} {}",
+                style("Max").dim(),
+                style(max_lifetime).yellow(),
+                style("(spuff extend)").dim()
+            );
         }
-        (_, Err(e)) => {
-            println!("{} Task error: {}", style("✕").red().bold(), e);
+    }
+
+    if !no_connect {
+        println!();
+        if is_docker {
+            crate::connector::docker::connect(&instance.id).await?;
+        } else {
+            crate::connector::ssh::connect(&instance.ip, config).await?;
         }
     }
 
     Ok(())
 }
+
+/// Print the new environment as `json`/`yaml`.
+fn print_up_result(
+    config: &AppConfig,
+    instance: &LocalInstance,
+    max_lifetime: Option<&str>,
+    output: OutputFormat,
+) -> Result<()> {
+    let is_docker = instance.provider == "docker" || instance.provider == "local";
+
+    let (connect, idle_timeout) = if is_docker {
+        (format!("docker exec -it {} /bin/bash", instance.id), None)
+    } else {
+        (
+            format!("ssh {}@{}", config.ssh_user, instance.ip),
+            Some(config.idle_timeout.clone()),
+        )
+    };
+
+    output.print(&UpResult {
+        environment: EnvironmentInfo::from_instance(instance, true),
+        connect,
+        idle_timeout,
+        max_lifetime: max_lifetime.filter(|_| !is_docker).map(str::to_string),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ai_tools_arg_headless() {
+        assert!(parse_ai_tools_arg(Some("ask"), false).is_err());
+        assert_eq!(
+            parse_ai_tools_arg(Some("none"), false).unwrap(),
+            Some(AiToolsConfig::None)
+        );
+        assert_eq!(
+            parse_ai_tools_arg(Some("claude-code,codex"), false).unwrap(),
+            Some(AiToolsConfig::List(vec![
+                "claude-code".to_string(),
+                "codex".to_string()
+            ]))
+        );
+        assert_eq!(parse_ai_tools_arg(None, false).unwrap(), None);
+    }
+}
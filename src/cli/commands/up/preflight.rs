@@ -1,12 +1,20 @@
 //! Pre-flight checks before provisioning
 //!
-//! Verifies SSH keys and SSHFS availability before creating VMs.
+//! Verifies SSH keys and SSHFS availability before creating VMs, and warns
+//! when the budget is nearly used up.
 
 use std::path::PathBuf;
+use std::time::Duration;
 
-use crate::config::AppConfig;
+use chrono::Utc;
+use console::style;
+
+use crate::cli::commands::catalog::format_price;
+use crate::config::{AppConfig, BudgetAction};
+use crate::cost::{budget_spend, check_budget};
 use crate::error::{Result, SpuffError};
 use crate::ssh::{is_key_in_agent, is_ssh_agent_running, key_has_passphrase};
+use crate::state::StateDb;
 use crate::volume::{get_install_instructions, SshfsDriver};
 
 /// Verify SSH key is accessible for authentication.
@@ -91,3 +99,50 @@ pub async fn verify_sshfs_available() -> Result<()> {
 
     Ok(())
 }
+
+/// Share of a budget cap from which `spuff up` warns.
+const BUDGET_WARN_THRESHOLD: f64 = 0.8;
+
+/// Warn when spending is close to or over a budget cap.
+///
+/// Never blocks: the watchdog applies `budget.action` once the instance runs.
+pub fn warn_budget(config: &AppConfig, db: &StateDb) {
+    let usage = match db.list_usage() {
+        Ok(usage) => usage,
+        Err(e) => {
+            tracing::debug!("Failed to read usage: {}", e);
+            return;
+        }
+    };
+
+    let spend = budget_spend(&config.budget, &usage, Utc::now());
+    let currency = &config.budget.currency;
+
+    for check in check_budget(&config.budget, &spend, Duration::ZERO) {
+        if check.used() < BUDGET_WARN_THRESHOLD {
+            continue;
+        }
+
+        eprintln!(
+            "{} {} of the {} budget of {} spent",
+            style("⚠").yellow().bold(),
+            style(format_price(check.projected, currency, 2)).yellow(),
+            check.period,
+            format_price(check.cap, currency, 2)
+        );
+        if check.exceeded() && config.budget.action != BudgetAction::Warn {
+            let action = match config.budget.action {
+                BudgetAction::Hibernate => "hibernated",
+                _ => "destroyed",
+            };
+            eprintln!(
+                "  {}",
+                style(format!(
+                    "New instances will be {} by the watchdog shortly after they start.",
+                    action
+                ))
+                .dim()
+            );
+        }
+    }
+}
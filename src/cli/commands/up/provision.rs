@@ -9,16 +9,20 @@ use tokio::sync::mpsc;
 
 use crate::config::AppConfig;
 use crate::environment::cloud_init::generate_cloud_init_with_ai_tools;
-use crate::error::Result;
+use crate::error::{Result, SpuffError};
 use crate::project_config::{AiToolsConfig, ProjectConfig};
 use crate::provider::{create_provider, ImageSpec, InstanceRequest};
+use crate::ssh::host_keys::{self, HostKeyPair};
 use crate::state::{LocalInstance, StateDb};
 use crate::tui::{ProgressMessage, StepState};
-use crate::volume::{SshfsDriver, SshfsLocalCommands, VolumeState};
+use crate::volume::{
+    SshfsDriver, SshfsLocalCommands, SyncLocalCommands, SyncSpec, VolumeState, VolumeType,
+};
 
 use super::agent_upload::{trigger_devtools_installation, upload_local_agent};
 use super::bootstrap::wait_for_cloud_init_with_progress;
 use super::build::get_linux_agent_path;
+use super::disks::prepare_persistent_disks;
 use super::volumes::{build_docker_volume_mounts, is_file_volume_async, sync_to_vm};
 use super::{
     STEP_BOOTSTRAP, STEP_CLOUD_INIT, STEP_CREATE, STEP_UPLOAD_AGENT, STEP_VOLUMES, STEP_WAIT_READY,
@@ -28,6 +32,7 @@ use super::{
 /// Parameters for instance provisioning
 pub struct ProvisionParams {
     pub config: AppConfig,
+    pub name: String,
     pub size: Option<String>,
     pub snapshot: Option<String>,
     pub region: Option<String>,
@@ -43,6 +48,7 @@ pub async fn provision_instance(
 ) -> Result<()> {
     // Extract all needed values upfront to avoid partial move issues
     let config = params.config;
+    let instance_name = params.name;
     let project_config = params.project_config;
     let cli_ai_tools = params.cli_ai_tools;
     let dev = params.dev;
@@ -54,6 +60,13 @@ pub async fn provision_instance(
     let provider = create_provider(&config)?;
     let is_docker = config.provider == "docker" || config.provider == "local";
 
+    // Pool hosts are shared machines and keep their own host keys
+    let host_key = if is_docker || config.provider == "ssh" {
+        None
+    } else {
+        Some(HostKeyPair::generate()?)
+    };
+
     // Step 1: Generate cloud-init (skip for Docker)
     tx.send(ProgressMessage::SetStep(
         STEP_CLOUD_INIT,
@@ -62,6 +75,34 @@ pub async fn provision_instance(
     .await
     .ok();
 
+    let instance_region = region.unwrap_or_else(|| config.region.clone());
+    let instance_size = size.unwrap_or_else(|| config.size.clone());
+
+    // Persistent disks must exist before the instance so they attach at creation
+    let persistent_disks = project_config
+        .as_ref()
+        .map(|pc| pc.persistent_disks.as_slice())
+        .unwrap_or_default();
+    if !persistent_disks.is_empty() {
+        tx.send(ProgressMessage::SetDetail(
+            "Preparing persistent disks...".to_string(),
+        ))
+        .await
+        .ok();
+    }
+    let disks =
+        match prepare_persistent_disks(provider.as_ref(), persistent_disks, &instance_region).await
+        {
+            Ok(disks) => disks,
+            Err(e) => {
+                tx.send(ProgressMessage::SetStep(STEP_CLOUD_INIT, StepState::Failed))
+                    .await
+                    .ok();
+                tx.send(ProgressMessage::Failed(e.to_string())).await.ok();
+                return Err(e);
+            }
+        };
+
     let user_data = if is_docker {
         tx.send(ProgressMessage::SetDetail(
             "Docker provider - skipping cloud-init".to_string(),
@@ -75,7 +116,13 @@ pub async fn provision_instance(
         ))
         .await
         .ok();
-        generate_cloud_init_with_ai_tools(&config, project_config.as_ref(), cli_ai_tools.as_ref())?
+        generate_cloud_init_with_ai_tools(
+            &config,
+            project_config.as_ref(),
+            cli_ai_tools.as_ref(),
+            host_key.as_ref(),
+            &disks.mounts,
+        )?
     };
     tx.send(ProgressMessage::SetStep(STEP_CLOUD_INIT, StepState::Done))
         .await
@@ -91,10 +138,7 @@ pub async fn provision_instance(
     .await
     .ok();
 
-    let instance_name = generate_instance_name();
-    let instance_region = region.unwrap_or_else(|| config.region.clone());
-    let instance_size = size.unwrap_or_else(|| config.size.clone());
-    let image = get_image_spec(&config.provider, snapshot);
+    let image = get_image_spec(snapshot);
 
     // Build volume mounts for Docker provider
     let volume_mounts = if is_docker {
@@ -112,7 +156,8 @@ pub async fn provision_instance(
     .with_user_data(user_data)
     .with_label("spuff", "true")
     .with_label("managed-by", "spuff-cli")
-    .with_volumes(volume_mounts);
+    .with_volumes(volume_mounts)
+    .with_disks(disks.attachments);
 
     let instance = match provider.create_instance(&request).await {
         Ok(i) => i,
@@ -154,15 +199,24 @@ pub async fn provision_instance(
     };
 
     // Save instance early so we don't lose track if something fails
-    let local_instance = LocalInstance::from_provider(
+    let mut local_instance = LocalInstance::from_provider(
         &instance,
         instance_name.clone(),
         config.provider.clone(),
         instance_region.clone(),
         instance_size.clone(),
     );
+    local_instance.price =
+        crate::cost::lookup_price(&config, provider.as_ref(), &instance_size).await;
     db.save_instance(&local_instance)?;
 
+    // Pin the host key before the first connection
+    if let Some(host_key) = &host_key {
+        let ip = instance.ip.to_string();
+        host_keys::pin(&ip, &host_key.public_key);
+        db.pin_host_key(&ip, &host_key.public_key)?;
+    }
+
     tx.send(ProgressMessage::SetStep(STEP_WAIT_READY, StepState::Done))
         .await
         .ok();
@@ -426,18 +480,21 @@ async fn mount_cloud_volumes(
         return;
     }
 
-    // Check SSHFS availability
-    let sshfs_available = SshfsDriver::check_sshfs_installed().await;
-    let fuse_available = SshfsDriver::check_fuse_available().await;
+    // Check SSHFS availability (sync volumes don't need it)
+    let needs_sshfs = merged_volumes
+        .iter()
+        .any(|(v, _)| v.driver_type != VolumeType::Sync);
+    let sshfs_ready = !needs_sshfs
+        || (SshfsDriver::check_sshfs_installed().await
+            && SshfsDriver::check_fuse_available().await);
 
-    if !sshfs_available || !fuse_available {
+    if !sshfs_ready {
         tx.send(ProgressMessage::SetDetail(
-            "SSHFS not available - skipping volume mount".to_string(),
+            "SSHFS not available - skipping SSHFS volumes".to_string(),
         ))
         .await
         .ok();
-        tracing::warn!("SSHFS not available, skipping volume mount");
-        return;
+        tracing::warn!("SSHFS not available, skipping SSHFS volume mounts");
     }
 
     let ssh_key_path = crate::ssh::managed_key::get_managed_key_path()
@@ -451,6 +508,44 @@ async fn mount_cloud_volumes(
         let mount_point = vol.resolve_mount_point(Some(instance_name), *base_dir);
         let source_path = vol.resolve_source(*base_dir);
 
+        if vol.driver_type == VolumeType::Sync {
+            tx.send(ProgressMessage::SetDetail(format!(
+                "Syncing volume {}/{}: {}",
+                idx + 1,
+                total_volumes,
+                vol.target
+            )))
+            .await
+            .ok();
+
+            let spec = SyncSpec::new(ip, &config.ssh_user, &ssh_key_str, &mount_point, vol);
+            match SyncLocalCommands::start(&spec).await {
+                Ok(_) => {
+                    mounted_count += 1;
+                    let mut state = VolumeState::load_or_default();
+                    let handle = crate::volume::MountHandle::new("sync", &vol.target, &mount_point)
+                        .with_vm_info(ip.to_string(), &config.ssh_user)
+                        .with_source(&mount_point)
+                        .with_read_only(vol.read_only);
+                    state.add_mount(handle);
+                    if let Err(e) = state.save() {
+                        tracing::error!(
+                            "Failed to save volume state: {}. Mount may not persist.",
+                            e
+                        );
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to start syncing {}: {}", vol.target, e);
+                }
+            }
+            continue;
+        }
+
+        if !sshfs_ready {
+            continue;
+        }
+
         // Check if this is a file volume (SSHFS doesn't support mounting files)
         // Use async version that checks remotely via SSH when local source doesn't exist
         let is_file = is_file_volume_async(
@@ -543,24 +638,61 @@ pub fn generate_instance_name() -> String {
     format!("spuff-{}", id)
 }
 
+/// Check a user-supplied environment name.
+///
+/// The name doubles as the instance name and hostname, so it must be a
+/// valid DNS label: lowercase letters, digits and hyphens, at most 63 chars.
+pub fn validate_instance_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 63
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(SpuffError::Config(format!(
+            "Invalid environment name '{}': use lowercase letters, digits and '-' (max 63 chars)",
+            name
+        )))
+    }
+}
+
 /// Get the appropriate image specification for the instance.
 ///
-/// If a snapshot ID is provided, uses that. Otherwise, defaults to Ubuntu 24.04.
-pub fn get_image_spec(provider: &str, snapshot: Option<String>) -> ImageSpec {
+/// If a snapshot ID is provided, uses that. Otherwise, defaults to Ubuntu 24.04;
+/// each provider maps it to its own image (slug, name, or AMI lookup on AWS).
+pub fn get_image_spec(snapshot: Option<String>) -> ImageSpec {
     if let Some(snapshot_id) = snapshot {
         // User provided a snapshot ID
         return ImageSpec::snapshot(snapshot_id);
     }
 
-    // Default to Ubuntu 24.04 for all providers
-    match provider {
-        "aws" => {
-            // AWS uses AMI IDs - this is a placeholder, should come from config
-            ImageSpec::custom("ami-0c55b159cbfafe1f0")
-        }
-        _ => {
-            // Most providers support Ubuntu slug
-            ImageSpec::ubuntu("24.04")
-        }
+    ImageSpec::ubuntu("24.04")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_instance_name_is_valid() {
+        assert!(validate_instance_name(&generate_instance_name()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_instance_name() {
+        assert!(validate_instance_name("api").is_ok());
+        assert!(validate_instance_name("feature-42").is_ok());
+
+        assert!(validate_instance_name("").is_err());
+        assert!(validate_instance_name("Api").is_err());
+        assert!(validate_instance_name("-api").is_err());
+        assert!(validate_instance_name("api-").is_err());
+        assert!(validate_instance_name("my_env").is_err());
+        assert!(validate_instance_name(&"a".repeat(64)).is_err());
     }
 }
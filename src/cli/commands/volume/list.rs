@@ -2,20 +2,98 @@
 //!
 //! Commands for viewing configured and mounted volumes.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use console::style;
+use serde::Serialize;
 
+use crate::cli::output::OutputFormat;
 use crate::config::AppConfig;
 use crate::error::{Result, SpuffError};
 use crate::project_config::ProjectConfig;
 use crate::state::StateDb;
 use crate::volume::{
-    get_install_instructions, SshfsDriver, SshfsLocalCommands, VolumeConfig, VolumeState,
+    get_install_instructions, MountStatus, SshfsDriver, SshfsLocalCommands, SyncLocalCommands,
+    VolumeConfig, VolumeState, VolumeType,
 };
 
 use super::sync::is_file_volume_async;
 
+/// A configured volume, as printed by `spuff volume ls` and `spuff volume
+/// status` in `json`/`yaml` output.
+#[derive(Debug, Serialize)]
+pub struct VolumeInfo {
+    /// Path on the VM
+    pub target: String,
+    /// Local mount point, or the local directory of a sync volume
+    pub mount_point: String,
+    pub driver: VolumeType,
+    pub read_only: bool,
+    /// "global" (config.yaml) or "project" (spuff.yaml)
+    pub source: &'static str,
+    /// Mounted, or syncing for sync volumes
+    pub mounted: bool,
+    /// Health of the mount or sync (`volume status` only)
+    pub status: Option<MountStatus>,
+}
+
+/// Volumes from config.yaml then spuff.yaml, with whether each is global.
+/// Project volumes override global ones with the same target.
+fn merge_volumes<'a>(
+    config: &'a AppConfig,
+    project_config: Option<&'a ProjectConfig>,
+) -> Vec<(&'a VolumeConfig, Option<&'a Path>, bool)> {
+    let mut merged: Vec<(&VolumeConfig, Option<&Path>, bool)> =
+        config.volumes.iter().map(|vol| (vol, None, true)).collect();
+
+    if let Some(pc) = project_config {
+        for vol in &pc.volumes {
+            merged.retain(|(v, _, _)| v.target != vol.target);
+            merged.push((vol, pc.base_dir.as_deref(), false));
+        }
+    }
+
+    merged
+}
+
+/// Describe the configured volumes; with `detailed`, check each mount's health.
+async fn volume_infos(
+    config: &AppConfig,
+    instance_name: &str,
+    project_config: Option<&ProjectConfig>,
+    detailed: bool,
+) -> Result<Vec<VolumeInfo>> {
+    let mut infos = Vec::new();
+    for (vol, base_dir, is_global) in merge_volumes(config, project_config) {
+        let mount_point = vol.resolve_mount_point(Some(instance_name), base_dir);
+        let is_sync = vol.driver_type == VolumeType::Sync;
+
+        let (mounted, status) = if detailed {
+            let status = if is_sync {
+                SyncLocalCommands::check_status(&mount_point).await?
+            } else {
+                SshfsLocalCommands::check_status(&mount_point).await?
+            };
+            (status.mounted, Some(status))
+        } else if is_sync {
+            (SyncLocalCommands::is_running(&mount_point), None)
+        } else {
+            (SshfsLocalCommands::is_mounted(&mount_point).await, None)
+        };
+
+        infos.push(VolumeInfo {
+            target: vol.target.clone(),
+            mount_point,
+            driver: vol.driver_type,
+            read_only: vol.read_only,
+            source: if is_global { "global" } else { "project" },
+            mounted,
+            status,
+        });
+    }
+    Ok(infos)
+}
+
 /// Helper function - uses char-based truncation to avoid UTF-8 panics
 fn truncate(s: &str, max_len: usize) -> String {
     if s.chars().count() > max_len {
@@ -27,12 +105,18 @@ fn truncate(s: &str, max_len: usize) -> String {
 }
 
 /// List configured and mounted volumes
-pub async fn list(config: &AppConfig) -> Result<()> {
+pub async fn list(config: &AppConfig, output: OutputFormat) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
         .ok_or(SpuffError::NoActiveInstance)?;
 
+    if !output.is_text() {
+        let project_config = ProjectConfig::load_from_cwd().ok().flatten();
+        let infos = volume_infos(config, &instance.name, project_config.as_ref(), false).await?;
+        return output.print(&infos);
+    }
+
     println!();
     println!(
         "  {} {}",
@@ -47,23 +131,8 @@ pub async fn list(config: &AppConfig) -> Result<()> {
     // Get current mount state
     let volume_state = VolumeState::load_or_default();
 
-    // Build merged volume list: global volumes first, then project volumes
-    // Project volumes with same target override global ones
-    let mut merged_volumes: Vec<(&VolumeConfig, Option<&std::path::Path>)> = Vec::new();
-
-    // Add global volumes (no base_dir)
-    for vol in &config.volumes {
-        merged_volumes.push((vol, None));
-    }
-
-    // Add project volumes (with base_dir), overriding globals with same target
-    if let Some(ref pc) = project_config {
-        for vol in &pc.volumes {
-            // Remove any global volume with the same target
-            merged_volumes.retain(|(v, _)| v.target != vol.target);
-            merged_volumes.push((vol, pc.base_dir.as_deref()));
-        }
-    }
+    // Global volumes first, then project volumes
+    let merged_volumes = merge_volumes(config, project_config.as_ref());
 
     if !merged_volumes.is_empty() {
         println!(
@@ -75,11 +144,18 @@ pub async fn list(config: &AppConfig) -> Result<()> {
         );
         println!("  {}", style("─".repeat(85)).dim());
 
-        for (vol, base_dir) in &merged_volumes {
+        for (vol, base_dir, _) in &merged_volumes {
             let mount_point = vol.resolve_mount_point(Some(&instance.name), *base_dir);
-            let is_mounted = SshfsLocalCommands::is_mounted(&mount_point).await;
+            let is_sync = vol.driver_type == VolumeType::Sync;
+            let is_mounted = if is_sync {
+                SyncLocalCommands::is_running(&mount_point)
+            } else {
+                SshfsLocalCommands::is_mounted(&mount_point).await
+            };
 
-            let status = if is_mounted {
+            let status = if is_mounted && is_sync {
+                style("● syncing").green()
+            } else if is_mounted {
                 style("● mounted").green()
             } else {
                 style("○ not mounted").dim()
@@ -160,7 +236,7 @@ pub async fn list(config: &AppConfig) -> Result<()> {
 }
 
 /// Show detailed status of volumes
-pub async fn status(config: &AppConfig) -> Result<()> {
+pub async fn status(config: &AppConfig, output: OutputFormat) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
@@ -168,6 +244,11 @@ pub async fn status(config: &AppConfig) -> Result<()> {
 
     let project_config = ProjectConfig::load_from_cwd().ok().flatten();
 
+    if !output.is_text() {
+        let infos = volume_infos(config, &instance.name, project_config.as_ref(), true).await?;
+        return output.print(&infos);
+    }
+
     println!();
     println!(
         "  {} {}",
@@ -176,37 +257,28 @@ pub async fn status(config: &AppConfig) -> Result<()> {
     );
     println!();
 
-    // Check SSHFS availability
-    let sshfs_available = SshfsDriver::check_sshfs_installed().await;
-    let fuse_available = SshfsDriver::check_fuse_available().await;
-
-    if !sshfs_available || !fuse_available {
-        println!("  {} SSHFS not available", style("⚠").yellow().bold());
-        println!();
-        println!("{}", get_install_instructions());
-        println!();
-        return Ok(());
-    }
-
-    println!("  {} SSHFS installed and ready", style("✓").green().bold());
-    println!();
-
-    // Build merged volume list: global volumes first, then project volumes
-    // Project volumes with same target override global ones
-    let mut merged_volumes: Vec<(&VolumeConfig, Option<&std::path::Path>, bool)> = Vec::new();
+    // Global volumes first, then project volumes
+    let merged_volumes = merge_volumes(config, project_config.as_ref());
 
-    // Add global volumes (no base_dir, is_global = true)
-    for vol in &config.volumes {
-        merged_volumes.push((vol, None, true));
-    }
+    // Check SSHFS availability, unless every volume is a sync volume
+    if merged_volumes.is_empty()
+        || merged_volumes
+            .iter()
+            .any(|(v, _, _)| v.driver_type != VolumeType::Sync)
+    {
+        let sshfs_available = SshfsDriver::check_sshfs_installed().await;
+        let fuse_available = SshfsDriver::check_fuse_available().await;
 
-    // Add project volumes (with base_dir), overriding globals with same target
-    if let Some(ref pc) = project_config {
-        for vol in &pc.volumes {
-            // Remove any global volume with the same target
-            merged_volumes.retain(|(v, _, _)| v.target != vol.target);
-            merged_volumes.push((vol, pc.base_dir.as_deref(), false));
+        if !sshfs_available || !fuse_available {
+            println!("  {} SSHFS not available", style("⚠").yellow().bold());
+            println!();
+            println!("{}", get_install_instructions());
+            println!();
+            return Ok(());
         }
+
+        println!("  {} SSHFS installed and ready", style("✓").green().bold());
+        println!();
     }
 
     // Get SSH key path for remote checks
@@ -219,6 +291,19 @@ pub async fn status(config: &AppConfig) -> Result<()> {
             let mount_point = vol.resolve_mount_point(Some(&instance.name), *base_dir);
             let source_path = vol.resolve_source(*base_dir);
 
+            let source_label = if *is_global {
+                style("[global]").yellow()
+            } else {
+                style("[project]").cyan()
+            };
+
+            if vol.driver_type == VolumeType::Sync {
+                let sync_status = SyncLocalCommands::check_status(&mount_point).await?;
+                print_sync_status(vol, &mount_point, &sync_status, source_label);
+                println!();
+                continue;
+            }
+
             // Determine if this is a file using SSH check when local source doesn't exist
             let is_file = is_file_volume_async(
                 &source_path,
@@ -229,12 +314,6 @@ pub async fn status(config: &AppConfig) -> Result<()> {
             )
             .await;
 
-            let source_label = if *is_global {
-                style("[global]").yellow()
-            } else {
-                style("[project]").cyan()
-            };
-
             if is_file {
                 // For files, check if the local source exists (was synced)
                 let source_exists =
@@ -313,3 +392,67 @@ pub async fn status(config: &AppConfig) -> Result<()> {
 
     Ok(())
 }
+
+/// Print the status of a sync volume: daemon state, lag and conflicts
+fn print_sync_status(
+    vol: &VolumeConfig,
+    local_dir: &str,
+    status: &MountStatus,
+    source_label: console::StyledObject<&str>,
+) {
+    let (status_icon, status_text) = if status.mounted && status.healthy {
+        (style("●").green(), style("syncing").green())
+    } else if status.mounted {
+        (style("●").yellow(), style("reconnecting").yellow())
+    } else {
+        (style("○").red(), style("not syncing").red())
+    };
+
+    println!(
+        "  {} VM:{} ⇄ {} {}",
+        status_icon,
+        style(&vol.target).cyan(),
+        style(local_dir).white(),
+        source_label
+    );
+    println!("    {} {}", style("Status:").dim(), status_text);
+
+    if !status.mounted {
+        return;
+    }
+
+    let pending = status.pending_changes.unwrap_or(0);
+    let lag = if pending == 0 {
+        style("up to date".to_string()).green()
+    } else {
+        style(format!(
+            "{} pending, {}ms behind",
+            pending,
+            status.sync_lag_ms.unwrap_or(0)
+        ))
+        .yellow()
+    };
+    println!("    {} {}", style("Lag:").dim(), lag);
+
+    if let Some(last_sync) = status.last_sync {
+        println!(
+            "    {} {}",
+            style("Last sync:").dim(),
+            last_sync.with_timezone(&chrono::Local).format("%H:%M:%S")
+        );
+    }
+
+    let conflicts = status.conflicts.unwrap_or(0);
+    if conflicts > 0 {
+        println!(
+            "    {} {} {}",
+            style("Conflicts:").yellow(),
+            conflicts,
+            style("(VM versions kept as *.spuff-conflict)").dim()
+        );
+    }
+
+    if let Some(ref err) = status.error {
+        println!("    {} {}", style("Error:").red(), err);
+    }
+}
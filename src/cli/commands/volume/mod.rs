@@ -1,7 +1,8 @@
 //! Volume management commands
 //!
 //! Commands for mounting and managing volumes.
-//! Mounts remote VM directories locally using SSHFS.
+//! Mounts remote VM directories locally using SSHFS, or keeps a local
+//! directory synced to the VM for `sync` volumes.
 
 mod list;
 mod mount;
@@ -11,4 +12,4 @@ mod unmount;
 // Re-export public functions
 pub use list::{list, status};
 pub use mount::mount;
-pub use unmount::{remount, unmount};
+pub use unmount::{remount, remount_volumes, unmount, unmount_local};
@@ -1,52 +1,43 @@
 //! Mount commands for volumes
 //!
-//! Commands for mounting volumes via SSHFS.
+//! Commands for mounting volumes via SSHFS, or starting sync daemons for
+//! `sync` volumes.
 
 use console::style;
 
+use crate::cli::output::OutputFormat;
 use crate::config::AppConfig;
 use crate::error::{Result, SpuffError};
 use crate::project_config::ProjectConfig;
 use crate::state::StateDb;
 use crate::volume::{
-    get_install_instructions, SshfsDriver, SshfsLocalCommands, VolumeConfig, VolumeState,
+    get_install_instructions, SshfsDriver, SshfsLocalCommands, SyncLocalCommands, SyncSpec,
+    VolumeConfig, VolumeState, VolumeType,
 };
 
 use super::sync::{is_file_volume_async, sync_to_vm};
 
 /// Mount a volume (ad-hoc or from config)
-pub async fn mount(config: &AppConfig, spec: Option<&str>) -> Result<()> {
+pub async fn mount(config: &AppConfig, spec: Option<&str>, output: OutputFormat) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
         .ok_or(SpuffError::NoActiveInstance)?;
 
-    // Check SSHFS availability
-    if !SshfsDriver::check_sshfs_installed().await {
-        println!();
-        println!("{}", get_install_instructions());
-        println!();
-        return Err(SpuffError::Volume("SSHFS not installed".to_string()));
-    }
-
-    if !SshfsDriver::check_fuse_available().await {
-        println!();
-        println!("{}", get_install_instructions());
-        println!();
-        return Err(SpuffError::Volume("FUSE not available".to_string()));
-    }
-
     let ssh_key_path = crate::ssh::managed_key::get_managed_key_path()
         .unwrap_or_else(|_| std::path::PathBuf::from(&config.ssh_key_path));
 
     let ssh_key_str = ssh_key_path.to_string_lossy().to_string();
 
-    println!();
+    output.println("");
 
     match spec {
         Some(volume_spec) => {
             // Mount ad-hoc volume (no project base dir for ad-hoc)
             let volume_config = VolumeConfig::from_spec(volume_spec).map_err(SpuffError::Volume)?;
+            if volume_config.driver_type != VolumeType::Sync {
+                check_sshfs_available(output).await?;
+            }
             mount_single_volume(
                 &instance.ip,
                 &config.ssh_user,
@@ -54,6 +45,7 @@ pub async fn mount(config: &AppConfig, spec: Option<&str>) -> Result<()> {
                 &volume_config,
                 Some(&instance.name),
                 None,
+                output,
             )
             .await?;
         }
@@ -80,12 +72,20 @@ pub async fn mount(config: &AppConfig, spec: Option<&str>) -> Result<()> {
             }
 
             if !merged_volumes.is_empty() {
-                println!(
+                // Sync volumes don't need SSHFS
+                if merged_volumes
+                    .iter()
+                    .any(|(v, _)| v.driver_type != VolumeType::Sync)
+                {
+                    check_sshfs_available(output).await?;
+                }
+
+                output.println(format_args!(
                     "  {} {} volume(s)...",
                     style("Mounting").cyan(),
                     merged_volumes.len()
-                );
-                println!();
+                ));
+                output.println("");
 
                 for (vol, base_dir) in &merged_volumes {
                     match mount_single_volume(
@@ -95,31 +95,66 @@ pub async fn mount(config: &AppConfig, spec: Option<&str>) -> Result<()> {
                         vol,
                         Some(&instance.name),
                         *base_dir,
+                        output,
                     )
                     .await
                     {
                         Ok(_) => {}
                         Err(e) => {
-                            println!("  {} {} - {}", style("✕").red().bold(), vol.target, e);
+                            output.println(format_args!(
+                                "  {} {} - {}",
+                                style("✕").red().bold(),
+                                vol.target,
+                                e
+                            ));
                         }
                     }
                 }
             } else {
-                println!(
+                output.println(format_args!(
                     "  {}",
                     style("No volumes configured in config.yaml or spuff.yaml").dim()
-                );
-                println!();
-                println!("  Mount a volume ad-hoc:");
-                println!(
+                ));
+                output.println("");
+                output.println(format_args!("  Mount a volume ad-hoc:"));
+                output.println(format_args!(
                     "    {}",
                     style("spuff volume mount /home/dev/project:~/mnt/project").cyan()
-                );
+                ));
             }
         }
     }
 
-    println!();
+    output.println("");
+    print_mounts(&instance.ip, output)
+}
+
+/// Print the environment's mounts in `json`/`yaml` output.
+pub(super) fn print_mounts(vm_ip: &str, output: OutputFormat) -> Result<()> {
+    if output.is_text() {
+        return Ok(());
+    }
+    let state = VolumeState::load_or_default();
+    let mounts: Vec<_> = state.mounts_for_vm(vm_ip).collect();
+    output.print(&mounts)
+}
+
+/// Fail with install instructions if SSHFS or FUSE is missing
+async fn check_sshfs_available(output: OutputFormat) -> Result<()> {
+    if !SshfsDriver::check_sshfs_installed().await {
+        output.println("");
+        output.println(format_args!("{}", get_install_instructions()));
+        output.println("");
+        return Err(SpuffError::Volume("SSHFS not installed".to_string()));
+    }
+
+    if !SshfsDriver::check_fuse_available().await {
+        output.println("");
+        output.println(format_args!("{}", get_install_instructions()));
+        output.println("");
+        return Err(SpuffError::Volume("FUSE not available".to_string()));
+    }
+
     Ok(())
 }
 
@@ -127,6 +162,7 @@ pub async fn mount(config: &AppConfig, spec: Option<&str>) -> Result<()> {
 ///
 /// For directories: syncs local -> remote, then mounts remote -> local via SSHFS
 /// For files: only syncs local -> remote (SSHFS doesn't support mounting individual files)
+/// For `sync` volumes: starts a sync daemon for the local directory
 pub async fn mount_single_volume(
     vm_ip: &str,
     ssh_user: &str,
@@ -134,10 +170,16 @@ pub async fn mount_single_volume(
     volume: &VolumeConfig,
     instance_name: Option<&str>,
     project_base_dir: Option<&std::path::Path>,
+    output: OutputFormat,
 ) -> Result<()> {
     let mount_point = volume.resolve_mount_point(instance_name, project_base_dir);
     let source_path = volume.resolve_source(project_base_dir);
 
+    if volume.driver_type == VolumeType::Sync {
+        return start_sync_volume(vm_ip, ssh_user, ssh_key_path, volume, &mount_point, output)
+            .await;
+    }
+
     // Determine if source is a file or directory using SSH check when local source doesn't exist
     let source_is_file =
         is_file_volume_async(&source_path, &volume.target, vm_ip, ssh_user, ssh_key_path).await;
@@ -145,27 +187,27 @@ pub async fn mount_single_volume(
     // For files, we only sync - SSHFS doesn't support mounting individual files
     if source_is_file {
         if !source_path.is_empty() && std::path::Path::new(&source_path).exists() {
-            println!(
+            output.println(format_args!(
                 "  {} {} → VM:{} {}",
                 style("Syncing").cyan(),
                 style(&source_path).white(),
                 style(&volume.target).green(),
                 style("(file)").dim()
-            );
+            ));
 
             sync_to_vm(vm_ip, ssh_user, ssh_key_path, &source_path, &volume.target).await?;
 
-            println!(
+            output.println(format_args!(
                 "  {} {} (synced, SSHFS doesn't support file mounts)",
                 style("✓").green().bold(),
                 source_path
-            );
+            ));
         } else {
-            println!(
+            output.println(format_args!(
                 "  {} {} (file target, no local source to sync)",
                 style("○").dim(),
                 volume.target
-            );
+            ));
         }
         return Ok(());
     }
@@ -174,32 +216,32 @@ pub async fn mount_single_volume(
 
     // Check if already mounted
     if SshfsLocalCommands::is_mounted(&mount_point).await {
-        println!(
+        output.println(format_args!(
             "  {} {} (already mounted)",
             style("✓").green().bold(),
             mount_point
-        );
+        ));
         return Ok(());
     }
 
     // If source is defined, sync local files to VM first
     if !source_path.is_empty() && std::path::Path::new(&source_path).exists() {
-        println!(
+        output.println(format_args!(
             "  {} {} → VM:{}",
             style("Syncing").cyan(),
             style(&source_path).white(),
             style(&volume.target).green()
-        );
+        ));
 
         sync_to_vm(vm_ip, ssh_user, ssh_key_path, &source_path, &volume.target).await?;
     }
 
-    println!(
+    output.println(format_args!(
         "  {} VM:{} → {}",
         style("Mounting").cyan(),
         style(&volume.target).white(),
         style(&mount_point).green()
-    );
+    ));
 
     // Execute the mount
     SshfsLocalCommands::mount(
@@ -224,7 +266,51 @@ pub async fn mount_single_volume(
         tracing::warn!("Failed to save volume state: {}", e);
     }
 
-    println!("  {} {}", style("✓").green().bold(), mount_point);
+    output.println(format_args!(
+        "  {} {}",
+        style("✓").green().bold(),
+        mount_point
+    ));
+
+    Ok(())
+}
+
+/// Start keeping `local_dir` in sync with the volume's target
+async fn start_sync_volume(
+    vm_ip: &str,
+    ssh_user: &str,
+    ssh_key_path: &str,
+    volume: &VolumeConfig,
+    local_dir: &str,
+    output: OutputFormat,
+) -> Result<()> {
+    output.println(format_args!(
+        "  {} {} ⇄ VM:{}",
+        style("Syncing").cyan(),
+        style(local_dir).white(),
+        style(&volume.target).green()
+    ));
+
+    let spec = SyncSpec::new(vm_ip, ssh_user, ssh_key_path, local_dir, volume);
+    SyncLocalCommands::start(&spec).await?;
+
+    // Save to state - log errors but don't fail the mount
+    let mut state = VolumeState::load_or_default();
+    let handle = crate::volume::MountHandle::new("sync", &volume.target, local_dir)
+        .with_vm_info(vm_ip, ssh_user)
+        .with_source(local_dir)
+        .with_read_only(volume.read_only);
+    state.add_mount(handle);
+    if let Err(e) = state.save() {
+        tracing::warn!("Failed to save volume state: {}", e);
+    }
+
+    output.println(format_args!(
+        "  {} {} {}",
+        style("✓").green().bold(),
+        local_dir,
+        style("(syncing in the background)").dim()
+    ));
 
     Ok(())
 }
@@ -28,8 +28,9 @@ pub async fn sync_to_vm(
 
     // Build rsync command
     let ssh_cmd = format!(
-        "ssh -i \"{}\" -o IdentitiesOnly=yes -o StrictHostKeyChecking=no -o UserKnownHostsFile=/dev/null",
-        ssh_key_path
+        "ssh -i \"{}\" -o IdentitiesOnly=yes -o StrictHostKeyChecking=accept-new -o UserKnownHostsFile=\"{}\"",
+        ssh_key_path,
+        crate::ssh::host_keys::write_known_hosts()?.display()
     );
 
     // For directories: add trailing / to sync contents, not the directory itself
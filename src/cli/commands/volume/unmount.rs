@@ -1,30 +1,40 @@
 //! Unmount commands for volumes
 //!
-//! Commands for unmounting and remounting volumes.
+//! Commands for unmounting and remounting volumes. Unmounting a `sync`
+//! volume stops its sync daemon; the local copy stays.
 
 use console::style;
 
+use crate::cli::output::OutputFormat;
 use crate::config::AppConfig;
 use crate::error::{Result, SpuffError};
 use crate::project_config::ProjectConfig;
 use crate::state::StateDb;
-use crate::volume::{SshfsLocalCommands, VolumeConfig, VolumeState};
+use crate::volume::{SshfsLocalCommands, SyncLocalCommands, VolumeConfig, VolumeState};
 
-use super::mount::mount_single_volume;
+use super::mount::{mount_single_volume, print_mounts};
 
 /// Unmount a volume or all volumes
-pub async fn unmount(config: &AppConfig, target: Option<String>, all: bool) -> Result<()> {
+pub async fn unmount(
+    config: &AppConfig,
+    target: Option<String>,
+    all: bool,
+    output: OutputFormat,
+) -> Result<()> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
         .ok_or(SpuffError::NoActiveInstance)?;
 
-    println!();
+    output.println("");
 
     let mut state = VolumeState::load_or_default();
 
     if all {
-        println!("  {} all volumes...", style("Unmounting").cyan());
+        output.println(format_args!(
+            "  {} all volumes...",
+            style("Unmounting").cyan()
+        ));
 
         let project_config = ProjectConfig::load_from_cwd().ok().flatten();
 
@@ -48,13 +58,22 @@ pub async fn unmount(config: &AppConfig, target: Option<String>, all: bool) -> R
         // Unmount all configured volumes
         for (vol, base_dir) in &merged_volumes {
             let mount_point = vol.resolve_mount_point(Some(&instance.name), *base_dir);
-            match SshfsLocalCommands::unmount(&mount_point).await {
+            match unmount_local(&mount_point).await {
                 Ok(_) => {
                     state.remove_mount(&mount_point);
-                    println!("  {} {}", style("✓").green().bold(), mount_point);
+                    output.println(format_args!(
+                        "  {} {}",
+                        style("✓").green().bold(),
+                        mount_point
+                    ));
                 }
                 Err(e) => {
-                    println!("  {} {} - {}", style("✕").red().bold(), mount_point, e);
+                    output.println(format_args!(
+                        "  {} {} - {}",
+                        style("✕").red().bold(),
+                        mount_point,
+                        e
+                    ));
                 }
             }
         }
@@ -63,22 +82,22 @@ pub async fn unmount(config: &AppConfig, target: Option<String>, all: bool) -> R
         let mount_points: Vec<String> =
             state.mounts.iter().map(|m| m.mount_point.clone()).collect();
         for mp in mount_points {
-            match SshfsLocalCommands::unmount(&mp).await {
+            match unmount_local(&mp).await {
                 Ok(_) => {
                     state.remove_mount(&mp);
-                    println!("  {} {}", style("✓").green().bold(), mp);
+                    output.println(format_args!("  {} {}", style("✓").green().bold(), mp));
                 }
                 Err(e) => {
-                    println!("  {} {} - {}", style("✕").red().bold(), mp, e);
+                    output.println(format_args!("  {} {} - {}", style("✕").red().bold(), mp, e));
                 }
             }
         }
     } else if let Some(target_path) = target {
-        println!(
+        output.println(format_args!(
             "  {} {}",
             style("Unmounting").cyan(),
             style(&target_path).white()
-        );
+        ));
 
         // Try to find the mount point - could be target (remote path) or mount_point (local path)
         let mount_point = if let Some(handle) = state.find_mount(&target_path) {
@@ -88,27 +107,54 @@ pub async fn unmount(config: &AppConfig, target: Option<String>, all: bool) -> R
             target_path.clone()
         };
 
-        SshfsLocalCommands::unmount(&mount_point).await?;
+        unmount_local(&mount_point).await?;
         state.remove_mount(&target_path);
 
-        println!("  {} Volume unmounted", style("✓").green().bold());
+        output.println(format_args!(
+            "  {} Volume unmounted",
+            style("✓").green().bold()
+        ));
     } else {
-        println!(
+        output.println(format_args!(
             "  {} Specify a target path or use --all",
             style("!").yellow().bold()
-        );
+        ));
         return Ok(());
     }
 
     if let Err(e) = state.save() {
         tracing::warn!("Failed to save volume state after unmount: {}", e);
     }
-    println!();
-    Ok(())
+    output.println("");
+    print_mounts(&instance.ip, output)
+}
+
+/// Unmount a local mount point, or stop syncing it if a sync daemon runs for it
+pub async fn unmount_local(mount_point: &str) -> Result<()> {
+    if SyncLocalCommands::is_running(mount_point) {
+        SyncLocalCommands::stop(mount_point).await
+    } else {
+        SshfsLocalCommands::unmount(mount_point).await
+    }
 }
 
 /// Remount volumes (useful after connection issues)
-pub async fn remount(config: &AppConfig, target: Option<String>) -> Result<()> {
+pub async fn remount(
+    config: &AppConfig,
+    target: Option<String>,
+    output: OutputFormat,
+) -> Result<()> {
+    let ip = remount_volumes(config, target, output).await?;
+    print_mounts(&ip, output)
+}
+
+/// Remount volumes without printing the result, for commands that print
+/// their own. Returns the instance's IP.
+pub async fn remount_volumes(
+    config: &AppConfig,
+    target: Option<String>,
+    output: OutputFormat,
+) -> Result<String> {
     let db = StateDb::open()?;
     let instance = db
         .get_active_instance()?
@@ -121,7 +167,7 @@ pub async fn remount(config: &AppConfig, target: Option<String>) -> Result<()> {
 
     let ssh_key_str = ssh_key_path.to_string_lossy().to_string();
 
-    println!();
+    output.println("");
 
     // Build merged volume list: global volumes first, then project volumes
     // Project volumes with same target override global ones
@@ -147,14 +193,14 @@ pub async fn remount(config: &AppConfig, target: Option<String>) -> Result<()> {
         {
             let mount_point = vol.resolve_mount_point(Some(&instance.name), *base_dir);
 
-            println!(
+            output.println(format_args!(
                 "  {} {}",
                 style("Remounting").cyan(),
                 style(&target_path).white()
-            );
+            ));
 
             // Unmount first
-            SshfsLocalCommands::unmount(&mount_point).await.ok();
+            unmount_local(&mount_point).await.ok();
 
             // Mount again
             mount_single_volume(
@@ -164,10 +210,14 @@ pub async fn remount(config: &AppConfig, target: Option<String>) -> Result<()> {
                 vol,
                 Some(&instance.name),
                 *base_dir,
+                output,
             )
             .await?;
 
-            println!("  {} Volume remounted", style("✓").green().bold());
+            output.println(format_args!(
+                "  {} Volume remounted",
+                style("✓").green().bold()
+            ));
         } else {
             return Err(SpuffError::Volume(format!(
                 "Volume not found in config: {}",
@@ -176,13 +226,16 @@ pub async fn remount(config: &AppConfig, target: Option<String>) -> Result<()> {
         }
     } else if !merged_volumes.is_empty() {
         // Remount all volumes
-        println!("  {} all volumes...", style("Remounting").cyan());
+        output.println(format_args!(
+            "  {} all volumes...",
+            style("Remounting").cyan()
+        ));
 
         for (vol, base_dir) in &merged_volumes {
             let mount_point = vol.resolve_mount_point(Some(&instance.name), *base_dir);
 
             // Unmount first
-            SshfsLocalCommands::unmount(&mount_point).await.ok();
+            unmount_local(&mount_point).await.ok();
 
             // Mount again
             match mount_single_volume(
@@ -192,19 +245,25 @@ pub async fn remount(config: &AppConfig, target: Option<String>) -> Result<()> {
                 vol,
                 Some(&instance.name),
                 *base_dir,
+                output,
             )
             .await
             {
                 Ok(_) => {}
                 Err(e) => {
-                    println!("  {} {} - {}", style("✕").red().bold(), vol.target, e);
+                    output.println(format_args!(
+                        "  {} {} - {}",
+                        style("✕").red().bold(),
+                        vol.target,
+                        e
+                    ));
                 }
             }
         }
     } else {
-        println!("  {}", style("No volumes configured").dim());
+        output.println(format_args!("  {}", style("No volumes configured").dim()));
     }
 
-    println!();
-    Ok(())
+    output.println("");
+    Ok(instance.ip)
 }
@@ -0,0 +1,537 @@
+//! Auto-destroy watchdog.
+//!
+//! Idle VMs power themselves off (see the idle policy in spuff-agent), but a
+//! powered-off instance is still billed on most providers. `spuff up` starts
+//! one detached watchdog per environment: it polls the provider API and, once
+//! the instance is off, destroys it (optionally snapshotting it first). The
+//! outcome is recorded in the state database so `spuff status` can report it.
+//!
+//! Environments powered off with `spuff stop` are left alone: the watchdog
+//! exits when it sees one, and `spuff start` starts a new watchdog.
+//!
+//! With a `budget` that enforces a cap, every poll also checks the spend of
+//! all environments. When the next poll interval at the current rate would
+//! exceed a cap, each watchdog hibernates or destroys its own instance.
+//!
+//! The watchdog keeps provider credentials on the local machine; nothing
+//! able to destroy instances is ever written to the VM. If the laptop is
+//! asleep when the VM powers off, the next `spuff status` or `spuff ls`
+//! restarts the watchdog and it catches up.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::config::{AppConfig, BudgetAction};
+use crate::cost::{budget_spend, check_budget, CapCheck};
+use crate::error::Result;
+use crate::provider::{create_provider_for, InstanceStatus, Provider, ProviderType};
+use crate::state::{DestroyRecord, Hibernation, LocalInstance, StateDb};
+
+/// How often the watchdog asks the provider for the instance status.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The environment a watchdog process is responsible for.
+#[derive(Debug, Clone)]
+pub struct WatchTarget {
+    pub id: String,
+    pub name: String,
+    pub provider: String,
+    pub region: String,
+}
+
+/// Whether instances of this provider power off on idle and can be destroyed.
+///
+/// Docker containers don't run the idle policy and SSH pool hosts are
+/// never powered off, so neither is watched.
+pub fn supports_auto_destroy(provider: &str) -> bool {
+    !matches!(
+        ProviderType::from_str(provider),
+        Some(ProviderType::Docker | ProviderType::Ssh) | None
+    )
+}
+
+/// Start a watchdog for the instance unless one is already running.
+pub fn ensure_running(config: &AppConfig, instance: &LocalInstance) -> Result<()> {
+    if !(config.auto_destroy.enabled || config.budget.enforces())
+        || !supports_auto_destroy(&instance.provider)
+        || instance.stopped_at.is_some()
+    {
+        return Ok(());
+    }
+
+    if is_running(&instance.id) {
+        return Ok(());
+    }
+
+    let dir = watchdog_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(format!("{}.log", instance.id)))?;
+
+    let mut command = std::process::Command::new(std::env::current_exe()?);
+    command
+        .args(["watchdog", "--id", &instance.id, "--name", &instance.name])
+        .args([
+            "--provider",
+            &instance.provider,
+            "--region",
+            &instance.region,
+        ])
+        .stdin(std::process::Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+
+    // Own process group so Ctrl+C in the terminal doesn't reach it
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let child = command.spawn()?;
+    tracing::debug!(
+        "Started watchdog for {} (pid {})",
+        instance.name,
+        child.id()
+    );
+
+    Ok(())
+}
+
+/// Run the watchdog loop until the instance is destroyed or disappears.
+pub async fn run(config: &AppConfig, target: WatchTarget) -> Result<()> {
+    let pid_path = pid_path(&target.id)?;
+    if let Some(parent) = pid_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&pid_path, std::process::id().to_string())?;
+
+    let result = watch(config, &target).await;
+
+    let _ = std::fs::remove_file(&pid_path);
+    result
+}
+
+async fn watch(config: &AppConfig, target: &WatchTarget) -> Result<()> {
+    let provider = create_provider_for(config, &target.provider, &target.region)?;
+    tracing::info!(
+        "Watching {} ({}) on {}",
+        target.name,
+        target.id,
+        target.provider
+    );
+
+    // Survives failed destroy attempts so we don't snapshot on every retry
+    let mut snapshot_id: Option<String> = None;
+
+    loop {
+        match provider.get_instance(&target.id).await {
+            Ok(None) => {
+                tracing::info!("{} no longer exists, stopping watchdog", target.name);
+                return Ok(());
+            }
+            Ok(Some(_)) if config.budget.enforces() && budget_exceeded(config, target) => {
+                let record =
+                    enforce_budget(provider.as_ref(), config, target, &mut snapshot_id).await;
+                let destroyed = record.error.is_none();
+
+                if let Err(e) = save_outcome(&record, destroyed) {
+                    tracing::warn!("Failed to record budget outcome: {}", e);
+                }
+
+                if destroyed {
+                    tracing::info!("{} destroyed to stay within budget", target.name);
+                    return Ok(());
+                }
+                tracing::warn!(
+                    "Budget enforcement on {} failed: {}",
+                    target.name,
+                    record.error.as_deref().unwrap_or_default()
+                );
+            }
+            Ok(Some(instance)) if instance.status == InstanceStatus::Off => {
+                match stopped_by_user(&target.id) {
+                    Ok(true) => {
+                        tracing::info!("{} was stopped with spuff stop, exiting", target.name);
+                        return Ok(());
+                    }
+                    // Only watching for the budget
+                    Ok(false) if !config.auto_destroy.enabled => {}
+                    Ok(false) => {
+                        tracing::info!("{} powered off on idle, destroying", target.name);
+                        let record =
+                            destroy_idle(provider.as_ref(), config, target, &mut snapshot_id).await;
+                        let destroyed = record.error.is_none();
+
+                        if let Err(e) = save_outcome(&record, destroyed) {
+                            tracing::warn!("Failed to record auto-destroy outcome: {}", e);
+                        }
+
+                        if destroyed {
+                            tracing::info!("{} destroyed", target.name);
+                            return Ok(());
+                        }
+                        tracing::warn!(
+                            "Auto-destroy of {} failed: {}",
+                            target.name,
+                            record.error.as_deref().unwrap_or_default()
+                        );
+                    }
+                    // Never destroy without knowing; ask again on the next poll
+                    Err(e) => tracing::warn!("Failed to read state of {}: {}", target.name, e),
+                }
+            }
+            Ok(Some(_)) => {}
+            Err(e) => tracing::warn!("Failed to get status of {}: {}", target.name, e),
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Snapshot (if configured) and destroy a powered-off instance.
+async fn destroy_idle(
+    provider: &dyn Provider,
+    config: &AppConfig,
+    target: &WatchTarget,
+    snapshot_id: &mut Option<String>,
+) -> DestroyRecord {
+    let mut record = DestroyRecord {
+        id: target.id.clone(),
+        name: target.name.clone(),
+        provider: target.provider.clone(),
+        reason: "idle".to_string(),
+        snapshot_id: None,
+        error: None,
+        at: Utc::now(),
+    };
+
+    if config.auto_destroy.snapshot && provider.supports_snapshots() && snapshot_id.is_none() {
+        let name = format!("{}-idle-{}", target.name, Utc::now().format("%Y%m%d-%H%M"));
+        match provider.create_snapshot(&target.id, &name).await {
+            Ok(snapshot) => *snapshot_id = Some(snapshot.id),
+            Err(e) => {
+                // Never destroy without the snapshot the user asked for
+                record.error = Some(format!("snapshot failed: {}", e));
+                return record;
+            }
+        }
+    }
+    record.snapshot_id = snapshot_id.clone();
+
+    if let Err(e) = provider.destroy_instance(&target.id).await {
+        record.error = Some(e.to_string());
+    }
+
+    record
+}
+
+/// Whether the next poll interval at the current rate would exceed a cap.
+fn budget_exceeded(config: &AppConfig, target: &WatchTarget) -> bool {
+    let usage = match StateDb::open().and_then(|db| db.list_usage()) {
+        Ok(usage) => usage,
+        Err(e) => {
+            tracing::warn!("Failed to read usage for {}: {}", target.name, e);
+            return false;
+        }
+    };
+
+    let spend = budget_spend(&config.budget, &usage, Utc::now());
+    match check_budget(&config.budget, &spend, POLL_INTERVAL)
+        .into_iter()
+        .find(CapCheck::exceeded)
+    {
+        Some(check) => {
+            tracing::info!(
+                "{} budget of {:.2} would be exceeded ({:.2} projected)",
+                check.period,
+                check.cap,
+                check.projected
+            );
+            true
+        }
+        None => false,
+    }
+}
+
+/// Hibernate or destroy an instance, depending on `budget.action`.
+///
+/// Hibernation snapshots are recorded like `spuff hibernate` ones, so the
+/// environment can be brought back with `spuff resume`.
+async fn enforce_budget(
+    provider: &dyn Provider,
+    config: &AppConfig,
+    target: &WatchTarget,
+    snapshot_id: &mut Option<String>,
+) -> DestroyRecord {
+    let mut record = DestroyRecord {
+        id: target.id.clone(),
+        name: target.name.clone(),
+        provider: target.provider.clone(),
+        reason: "budget".to_string(),
+        snapshot_id: None,
+        error: None,
+        at: Utc::now(),
+    };
+
+    if config.budget.action == BudgetAction::Hibernate && snapshot_id.is_none() {
+        if !provider.supports_snapshots() {
+            record.error = Some(format!(
+                "{} does not support snapshots; use budget.action: destroy",
+                target.provider
+            ));
+            return record;
+        }
+
+        let name = format!(
+            "spuff-hibernate-{}-{}",
+            target.name,
+            Utc::now().format("%Y%m%d-%H%M")
+        );
+        let snapshot = match provider.create_snapshot(&target.id, &name).await {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                record.error = Some(format!("snapshot failed: {}", e));
+                return record;
+            }
+        };
+
+        // Record before destroying so the environment can't get lost
+        if let Err(e) = record_hibernation(target, &snapshot.id) {
+            record.error = Some(format!("failed to record hibernation: {}", e));
+            return record;
+        }
+        *snapshot_id = Some(snapshot.id);
+    }
+    record.snapshot_id = snapshot_id.clone();
+
+    if let Err(e) = provider.destroy_instance(&target.id).await {
+        record.error = Some(e.to_string());
+    }
+
+    record
+}
+
+fn record_hibernation(target: &WatchTarget, snapshot_id: &str) -> Result<()> {
+    let db = StateDb::open()?;
+    let size = db
+        .find_instance(&target.id)?
+        .map(|instance| instance.size)
+        .unwrap_or_default();
+
+    db.record_hibernation(&Hibernation {
+        name: target.name.clone(),
+        snapshot_id: snapshot_id.to_string(),
+        provider: target.provider.clone(),
+        region: target.region.clone(),
+        size,
+        project_dir: None,
+        ports: Vec::new(),
+        reverse_ports: Vec::new(),
+        socks_port: None,
+        auto_tunnel: false,
+        at: Utc::now(),
+    })
+}
+
+/// Whether the environment was powered off on purpose with `spuff stop`.
+fn stopped_by_user(id: &str) -> Result<bool> {
+    let db = StateDb::open()?;
+    Ok(db
+        .find_instance(id)?
+        .is_some_and(|instance| instance.stopped_at.is_some()))
+}
+
+fn save_outcome(record: &DestroyRecord, destroyed: bool) -> Result<()> {
+    let db = StateDb::open()?;
+    if destroyed {
+        db.remove_instance(&record.id)?;
+    }
+    db.record_destroy(record)
+}
+
+fn watchdog_dir() -> Result<PathBuf> {
+    Ok(AppConfig::config_dir()?.join("watchdog"))
+}
+
+fn pid_path(instance_id: &str) -> Result<PathBuf> {
+    Ok(watchdog_dir()?.join(format!("{}.pid", instance_id)))
+}
+
+/// Check whether a watchdog process for the instance is alive.
+fn is_running(instance_id: &str) -> bool {
+    let Some(pid) = pid_path(instance_id)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|pid| pid.trim().parse::<i32>().ok())
+    else {
+        return false;
+    };
+
+    #[cfg(unix)]
+    {
+        nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid), None).is_ok()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::digitalocean::DigitalOceanProvider;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn target() -> WatchTarget {
+        WatchTarget {
+            id: "123".to_string(),
+            name: "api".to_string(),
+            provider: "digitalocean".to_string(),
+            region: "nyc1".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_destroy_idle_without_snapshot() {
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/droplets/123"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = DigitalOceanProvider::with_base_url("token", &server.uri()).unwrap();
+        let config = AppConfig::default();
+        let mut snapshot_id = None;
+
+        let record = destroy_idle(&provider, &config, &target(), &mut snapshot_id).await;
+        assert!(record.error.is_none());
+        assert!(record.snapshot_id.is_none());
+        assert_eq!(record.reason, "idle");
+        assert_eq!(record.name, "api");
+    }
+
+    #[tokio::test]
+    async fn test_destroy_idle_keeps_instance_when_snapshot_fails() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/droplets/123/actions"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/droplets/123"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let provider = DigitalOceanProvider::with_base_url("token", &server.uri()).unwrap();
+        let mut config = AppConfig::default();
+        config.auto_destroy.snapshot = true;
+        let mut snapshot_id = None;
+
+        let record = destroy_idle(&provider, &config, &target(), &mut snapshot_id).await;
+        assert!(record.error.unwrap().starts_with("snapshot failed"));
+        assert!(snapshot_id.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_destroy_idle_reuses_previous_snapshot() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/droplets/123/actions"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/droplets/123"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+
+        let provider = DigitalOceanProvider::with_base_url("token", &server.uri()).unwrap();
+        let mut config = AppConfig::default();
+        config.auto_destroy.snapshot = true;
+        let mut snapshot_id = Some("snap-1".to_string());
+
+        let record = destroy_idle(&provider, &config, &target(), &mut snapshot_id).await;
+        assert!(record.error.is_none());
+        assert_eq!(record.snapshot_id.as_deref(), Some("snap-1"));
+    }
+
+    #[tokio::test]
+    async fn test_enforce_budget_destroy() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/droplets/123/actions"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/droplets/123"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = DigitalOceanProvider::with_base_url("token", &server.uri()).unwrap();
+        let mut config = AppConfig::default();
+        config.budget.action = BudgetAction::Destroy;
+        let mut snapshot_id = None;
+
+        let record = enforce_budget(&provider, &config, &target(), &mut snapshot_id).await;
+        assert!(record.error.is_none());
+        assert!(record.snapshot_id.is_none());
+        assert_eq!(record.reason, "budget");
+    }
+
+    #[tokio::test]
+    async fn test_enforce_budget_keeps_instance_when_snapshot_fails() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/droplets/123/actions"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/droplets/123"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let provider = DigitalOceanProvider::with_base_url("token", &server.uri()).unwrap();
+        let mut config = AppConfig::default();
+        config.budget.action = BudgetAction::Hibernate;
+        let mut snapshot_id = None;
+
+        let record = enforce_budget(&provider, &config, &target(), &mut snapshot_id).await;
+        assert!(record.error.unwrap().starts_with("snapshot failed"));
+        assert!(snapshot_id.is_none());
+    }
+
+    #[test]
+    fn test_supports_auto_destroy() {
+        assert!(supports_auto_destroy("digitalocean"));
+        assert!(supports_auto_destroy("hetzner"));
+        assert!(supports_auto_destroy("aws"));
+        assert!(supports_auto_destroy("qemu"));
+
+        assert!(!supports_auto_destroy("docker"));
+        assert!(!supports_auto_destroy("local"));
+        assert!(!supports_auto_destroy("ssh"));
+        assert!(!supports_auto_destroy("unknown"));
+    }
+}
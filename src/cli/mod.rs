@@ -1,10 +1,13 @@
 pub mod commands;
+pub mod output;
 
 use clap::{Parser, Subcommand};
 
 use crate::config::AppConfig;
 use crate::error::Result;
 
+use output::{ErrorOutput, OutputFormat};
+
 #[derive(Parser)]
 #[command(name = "spuff")]
 #[command(version)]
@@ -19,6 +22,22 @@ pub struct Cli {
     /// Enable verbose output
     #[arg(short, long, global = true)]
     pub verbose: bool,
+
+    /// Environment to operate on (default: the active one, see `spuff use`)
+    #[arg(long, global = true, env = "SPUFF_ENV", value_name = "NAME")]
+    pub env: Option<String>,
+
+    /// Output format; json and yaml print only the command's result
+    #[arg(
+        short,
+        long,
+        global = true,
+        env = "SPUFF_OUTPUT",
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        value_name = "FORMAT"
+    )]
+    pub output: OutputFormat,
 }
 
 #[derive(Subcommand)]
@@ -28,6 +47,10 @@ pub enum Commands {
 
     /// Create and connect to a new dev environment
     Up {
+        /// Environment name (default: generated, e.g. spuff-1a2b3c4d)
+        #[arg(short, long)]
+        name: Option<String>,
+
         /// Instance size (e.g., s-2vcpu-4gb)
         #[arg(short, long)]
         size: Option<String>,
@@ -52,6 +75,20 @@ pub enum Commands {
         /// (e.g., "claude-code,codex"). Overrides project and global config.
         #[arg(long, value_name = "TOOLS")]
         ai_tools: Option<String>,
+
+        /// Report progress as NDJSON on stderr and print the environment as
+        /// JSON, for CI and scripts (automatic when stdout is not a terminal)
+        #[arg(long)]
+        no_tui: bool,
+    },
+
+    /// List all environments
+    Ls,
+
+    /// Set the active environment used when --env is not given
+    Use {
+        /// Environment name (or instance ID)
+        name: String,
     },
 
     /// Destroy the current environment
@@ -65,6 +102,41 @@ pub enum Commands {
         force: bool,
     },
 
+    /// Power off the environment, keeping its disk (see `spuff start`)
+    Stop,
+
+    /// Power on a stopped environment
+    Start,
+
+    /// Change the environment's size in place, keeping its disk
+    Resize {
+        /// New size (e.g. s-4vcpu-8gb)
+        size: String,
+
+        /// Resize without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Push back the environment's maximum lifetime
+    Extend {
+        /// How much longer it may run (e.g. 2h, 30m)
+        duration: String,
+    },
+
+    /// Snapshot the active environment and destroy it until resumed
+    Hibernate,
+
+    /// Recreate a hibernated environment from its latest snapshot
+    Resume {
+        /// Environment to resume (defaults to the most recently hibernated)
+        name: Option<String>,
+
+        /// Don't connect via SSH after resuming
+        #[arg(long)]
+        no_connect: bool,
+    },
+
     /// SSH into existing environment
     Ssh,
 
@@ -106,12 +178,33 @@ pub enum Commands {
         lines: usize,
     },
 
-    /// Create SSH tunnels to the remote environment (for ports in spuff.yaml)
+    /// Create SSH tunnels to the remote environment in the background
+    /// (for ports in spuff.yaml)
     Tunnel {
         /// Specific port to tunnel (default: all ports from spuff.yaml)
         #[arg(short, long)]
         port: Option<u16>,
 
+        /// Forward a port on the VM back to the same port here (repeatable)
+        #[arg(long, value_name = "PORT")]
+        reverse: Vec<u16>,
+
+        /// Run a SOCKS5 proxy on this local port into the VM's network
+        #[arg(long, value_name = "PORT")]
+        socks: Option<u16>,
+
+        /// Forward ports as processes on the VM start listening on them
+        #[arg(long, conflicts_with = "no_auto")]
+        auto: bool,
+
+        /// Stop forwarding ports discovered on the VM
+        #[arg(long)]
+        no_auto: bool,
+
+        /// Stop forwarding this port in the running tunnels (repeatable)
+        #[arg(long, value_name = "PORT", conflicts_with_all = ["port", "reverse", "socks"])]
+        remove: Vec<u16>,
+
         /// Stop running tunnels
         #[arg(long)]
         stop: bool,
@@ -123,6 +216,29 @@ pub enum Commands {
         command: SnapshotCommands,
     },
 
+    /// List the provider's regions
+    Regions,
+
+    /// List instance sizes with their price
+    Sizes {
+        /// Region to list sizes for (defaults to the configured region)
+        #[arg(short, long)]
+        region: Option<String>,
+
+        /// List sizes in every region
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Show what environments cost, per environment and per month
+    Cost,
+
+    /// Manage persistent disks
+    Disk {
+        #[command(subcommand)]
+        command: DiskCommands,
+    },
+
     /// Manage configuration
     Config {
         #[command(subcommand)]
@@ -147,6 +263,29 @@ pub enum Commands {
         /// Disable TTY (use agent HTTP, faster for non-interactive commands)
         #[arg(short = 'T', long = "no-tty")]
         no_tty: bool,
+
+        /// Working directory on the remote
+        #[arg(short = 'w', long)]
+        workdir: Option<String>,
+
+        /// Set an environment variable (KEY=VALUE, repeatable)
+        #[arg(short = 'e', long = "set-env", value_name = "KEY=VALUE")]
+        set_env: Vec<String>,
+
+        /// Kill the command after this many seconds
+        #[arg(long, value_name = "SECS")]
+        timeout: Option<u64>,
+    },
+
+    /// Copy files to or from the remote environment (remote paths start with ':')
+    Cp {
+        /// Sources followed by the destination
+        #[arg(required = true, num_args = 2.., value_name = "PATH")]
+        paths: Vec<String>,
+
+        /// Copy directories recursively
+        #[arg(short, long)]
+        recursive: bool,
     },
 
     /// Manage AI coding tools
@@ -160,6 +299,44 @@ pub enum Commands {
         #[command(subcommand)]
         command: VolumeCommands,
     },
+
+    /// Find instances and snapshots the local state doesn't match, and fix them
+    #[command(alias = "reconcile")]
+    Gc {
+        /// Only report what doesn't match; change nothing
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Watch an environment and destroy it once it powers off on idle
+    /// (started in the background by `spuff up`)
+    #[command(hide = true)]
+    Watchdog {
+        #[arg(long)]
+        id: String,
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        provider: String,
+        #[arg(long)]
+        region: String,
+    },
+
+    /// Keep an environment's tunnels open in the background
+    /// (started by `spuff tunnel`)
+    #[command(hide = true)]
+    TunnelDaemon {
+        #[arg(long)]
+        id: String,
+    },
+
+    /// Keep a sync volume up to date in the background
+    /// (started by `spuff volume mount`)
+    #[command(hide = true)]
+    SyncDaemon {
+        #[arg(long)]
+        id: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -181,6 +358,32 @@ pub enum SnapshotCommands {
     },
 }
 
+#[derive(Subcommand)]
+pub enum DiskCommands {
+    /// List persistent disks
+    List,
+
+    /// Attach a persistent disk to the running environment and mount it
+    Attach {
+        /// Disk name
+        name: String,
+
+        /// Mount path on the VM (defaults to the disk's entry in spuff.yaml)
+        #[arg(short, long)]
+        mount: Option<String>,
+    },
+
+    /// Delete a persistent disk and its data
+    Delete {
+        /// Disk name from spuff.yaml
+        name: String,
+
+        /// Delete without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum ConfigCommands {
     /// Show current configuration
@@ -190,8 +393,8 @@ pub enum ConfigCommands {
     Set {
         /// Configuration key
         key: String,
-        /// Configuration value
-        value: String,
+        /// Configuration value (region and size offer a picker when omitted)
+        value: Option<String>,
     },
 
     /// Open configuration file in editor
@@ -230,6 +433,12 @@ pub enum AgentCommands {
     /// Show top processes
     Processes,
 
+    /// Show listening TCP ports and their processes
+    Ports,
+
+    /// Show why the environment is or isn't considered idle
+    Idle,
+
     /// View system logs from the remote environment
     Logs {
         /// Number of lines to show
@@ -295,23 +504,112 @@ pub enum VolumeCommands {
 
 impl Cli {
     pub async fn execute(self) -> Result<()> {
+        if let Some(env) = &self.env {
+            crate::state::select_env(env.clone());
+        }
+
+        // Host keys are checked against pins from the state database, which
+        // can't be opened mid-handshake; load them up front instead.
+        let pins_loaded = match crate::ssh::host_keys::load_pins() {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to load pinned host keys, unknown hosts will be rejected: {}",
+                    e
+                );
+                false
+            }
+        };
+
+        let output = self.output_format();
+        let result = self.run().await;
+
+        // Don't let a failed load overwrite pins with keys we couldn't check
+        if pins_loaded {
+            if let Err(e) = crate::ssh::host_keys::store_learned() {
+                tracing::warn!("Failed to store pinned host keys: {}", e);
+            }
+        }
+
+        // Scripts reading json/yaml get the failure on stdout too; the
+        // exit status still comes from the error
+        if let Err(e) = &result {
+            if !output.is_text() {
+                output.print(&ErrorOutput {
+                    error: e.to_string(),
+                })?;
+            }
+        }
+
+        result
+    }
+
+    /// `--output`, after the commands that pick a structured format on
+    /// their own.
+    fn output_format(&self) -> OutputFormat {
+        match &self.command {
+            // Headless `up`: nobody is there to watch the TUI or connect
+            Commands::Up { no_tui, .. }
+                if self.output.is_text() && (*no_tui || !crate::tui::is_tty_available()) =>
+            {
+                OutputFormat::Json
+            }
+            _ => self.output,
+        }
+    }
+
+    async fn run(self) -> Result<()> {
+        let output = self.output_format();
         match self.command {
             Commands::Init => commands::init::execute().await,
             Commands::Up {
+                name,
                 size,
                 snapshot,
                 region,
                 no_connect,
                 dev,
                 ai_tools,
+                no_tui: _,
             } => {
                 let config = AppConfig::load()?;
-                commands::up::execute(&config, size, snapshot, region, no_connect, dev, ai_tools)
-                    .await
+                commands::up::execute(
+                    &config, name, size, snapshot, region, no_connect, dev, ai_tools, None, output,
+                )
+                .await
             }
+            Commands::Ls => {
+                let config = AppConfig::load()?;
+                commands::env::list(&config, output).await
+            }
+            Commands::Use { name } => commands::env::use_env(&name, output).await,
             Commands::Down { snapshot, force } => {
                 let config = AppConfig::load()?;
-                commands::down::execute(&config, snapshot, force).await
+                commands::down::execute(&config, snapshot, force, output).await
+            }
+            Commands::Stop => {
+                let config = AppConfig::load()?;
+                commands::power::stop(&config, output).await
+            }
+            Commands::Start => {
+                let config = AppConfig::load()?;
+                commands::power::start(&config, output).await
+            }
+            Commands::Resize { size, force } => {
+                let config = AppConfig::load()?;
+                commands::resize::execute(&config, size, force, output).await
+            }
+            Commands::Extend { duration } => {
+                let config = AppConfig::load()?;
+                commands::agent::extend(&config, duration, output).await
+            }
+            Commands::Hibernate => {
+                let config = AppConfig::load()?;
+                commands::hibernate::hibernate(&config, output).await
+            }
+            Commands::Resume { name, no_connect } => {
+                let config = AppConfig::load()?;
+                commands::hibernate::resume(&config, name, no_connect, output).await
             }
             Commands::Ssh => {
                 let config = AppConfig::load()?;
@@ -319,7 +617,7 @@ impl Cli {
             }
             Commands::Status { detailed } => {
                 let config = AppConfig::load()?;
-                commands::status::execute(&config, detailed).await
+                commands::status::execute(&config, detailed, output).await
             }
             Commands::Logs {
                 bundle,
@@ -332,45 +630,83 @@ impl Cli {
             } => {
                 let config = AppConfig::load()?;
                 commands::logs::execute(
-                    &config, bundle, packages, repos, services, script, follow, lines,
+                    &config, bundle, packages, repos, services, script, follow, lines, output,
                 )
                 .await
             }
-            Commands::Tunnel { port, stop } => {
-                let config = AppConfig::load()?;
-                commands::ssh::tunnel(&config, port, stop).await
+            Commands::Tunnel {
+                port,
+                reverse,
+                socks,
+                auto,
+                no_auto,
+                remove,
+                stop,
+            } => {
+                let auto = match (auto, no_auto) {
+                    (true, _) => Some(true),
+                    (_, true) => Some(false),
+                    _ => None,
+                };
+                commands::tunnel::execute(port, reverse, socks, auto, remove, stop, output).await
             }
             Commands::Snapshot { command } => {
                 let config = AppConfig::load()?;
                 match command {
                     SnapshotCommands::Create { name } => {
-                        commands::snapshot::create(&config, name).await
+                        commands::snapshot::create(&config, name, output).await
                     }
-                    SnapshotCommands::List => commands::snapshot::list(&config).await,
+                    SnapshotCommands::List => commands::snapshot::list(&config, output).await,
                     SnapshotCommands::Delete { id } => {
-                        commands::snapshot::delete(&config, id).await
+                        commands::snapshot::delete(&config, id, output).await
+                    }
+                }
+            }
+            Commands::Regions => {
+                let config = AppConfig::load()?;
+                commands::catalog::regions(&config, output).await
+            }
+            Commands::Sizes { region, all } => {
+                let config = AppConfig::load()?;
+                commands::catalog::sizes(&config, region, all, output).await
+            }
+            Commands::Cost => {
+                let config = AppConfig::load()?;
+                commands::cost::execute(&config, output).await
+            }
+            Commands::Disk { command } => {
+                let config = AppConfig::load()?;
+                match command {
+                    DiskCommands::List => commands::disk::list(&config, output).await,
+                    DiskCommands::Attach { name, mount } => {
+                        commands::disk::attach(&config, name, mount, output).await
+                    }
+                    DiskCommands::Delete { name, force } => {
+                        commands::disk::delete(&config, name, force, output).await
                     }
                 }
             }
             Commands::Config { command } => match command {
-                ConfigCommands::Show => commands::config::show().await,
+                ConfigCommands::Show => commands::config::show(output).await,
                 ConfigCommands::Set { key, value } => commands::config::set(key, value).await,
                 ConfigCommands::Edit => commands::config::edit().await,
             },
             Commands::Agent { command } => {
                 let config = AppConfig::load()?;
                 match command {
-                    AgentCommands::Status => commands::agent::status(&config).await,
-                    AgentCommands::Metrics => commands::agent::metrics(&config).await,
-                    AgentCommands::Processes => commands::agent::processes(&config).await,
+                    AgentCommands::Status => commands::agent::status(&config, output).await,
+                    AgentCommands::Metrics => commands::agent::metrics(&config, output).await,
+                    AgentCommands::Processes => commands::agent::processes(&config, output).await,
+                    AgentCommands::Ports => commands::agent::ports(&config, output).await,
+                    AgentCommands::Idle => commands::agent::idle(&config, output).await,
                     AgentCommands::Logs { lines, file } => {
-                        commands::agent::logs(&config, lines, file).await
+                        commands::agent::logs(&config, lines, file, output).await
                     }
                     AgentCommands::Activity { limit, follow } => {
-                        commands::agent::activity(&config, limit, follow).await
+                        commands::agent::activity(&config, limit, follow, output).await
                     }
                     AgentCommands::ExecLog { lines } => {
-                        commands::agent::exec_log(&config, lines).await
+                        commands::agent::exec_log(&config, lines, output).await
                     }
                 }
             }
@@ -378,35 +714,70 @@ impl Cli {
                 command,
                 force_tty,
                 no_tty,
+                workdir,
+                set_env,
+                timeout,
             } => {
                 let config = AppConfig::load()?;
-                commands::agent::exec(&config, command, force_tty, no_tty).await
+                let options = commands::agent::ExecOptions::new(workdir, &set_env, timeout)?;
+                commands::agent::exec(&config, command, force_tty, no_tty, options).await
+            }
+            Commands::Cp { paths, recursive } => {
+                let config = AppConfig::load()?;
+                commands::cp::execute(&config, paths, recursive, output).await
             }
             Commands::Ai { command } => {
                 let config = AppConfig::load()?;
                 match command {
-                    AiCommands::List => commands::ai::list().await,
-                    AiCommands::Status => commands::ai::status(&config).await,
+                    AiCommands::List => commands::ai::list(output).await,
+                    AiCommands::Status => commands::ai::status(&config, output).await,
                     AiCommands::Install { tool } => commands::ai::install(&config, tool).await,
-                    AiCommands::Info { tool } => commands::ai::info(&tool).await,
+                    AiCommands::Info { tool } => commands::ai::info(&tool, output).await,
                 }
             }
             Commands::Volume { command } => {
                 let config = AppConfig::load()?;
                 match command {
-                    VolumeCommands::List => commands::volume::list(&config).await,
-                    VolumeCommands::Status => commands::volume::status(&config).await,
+                    VolumeCommands::List => commands::volume::list(&config, output).await,
+                    VolumeCommands::Status => commands::volume::status(&config, output).await,
                     VolumeCommands::Mount { spec } => {
-                        commands::volume::mount(&config, spec.as_deref()).await
+                        commands::volume::mount(&config, spec.as_deref(), output).await
                     }
                     VolumeCommands::Unmount { target, all } => {
-                        commands::volume::unmount(&config, target, all).await
+                        commands::volume::unmount(&config, target, all, output).await
                     }
                     VolumeCommands::Remount { target } => {
-                        commands::volume::remount(&config, target).await
+                        commands::volume::remount(&config, target, output).await
                     }
                 }
             }
+            Commands::Gc { dry_run } => {
+                let config = AppConfig::load()?;
+                commands::gc::execute(&config, dry_run, output).await
+            }
+            Commands::Watchdog {
+                id,
+                name,
+                provider,
+                region,
+            } => {
+                let config = AppConfig::load()?;
+                let target = commands::watchdog::WatchTarget {
+                    id,
+                    name,
+                    provider,
+                    region,
+                };
+                commands::watchdog::run(&config, target).await
+            }
+            Commands::TunnelDaemon { id } => {
+                let config = AppConfig::load()?;
+                commands::tunnel::run(&config, id).await
+            }
+            Commands::SyncDaemon { id } => {
+                let config = AppConfig::load()?;
+                crate::volume::drivers::sync::run(&id, config.agent_token).await
+            }
         }
     }
 }
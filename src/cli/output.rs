@@ -0,0 +1,128 @@
+//! Output format (`--output text|json|yaml`)
+//!
+//! Commands that report something build a serializable value for it. In
+//! `text` mode they print it styled, as always; in `json` and `yaml` mode
+//! they print only that value to stdout, so scripts and editor plugins can
+//! read it without screen-scraping. Progress and hints go to stderr or are
+//! left out.
+//!
+//! The serialized types are part of the CLI's interface: add fields, but
+//! don't rename or remove them.
+
+use std::io::Write;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::error::{Result, SpuffError};
+
+/// How a command prints its result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Styled, human-readable text
+    #[default]
+    Text,
+    /// Pretty-printed JSON
+    Json,
+    /// YAML
+    Yaml,
+}
+
+impl OutputFormat {
+    pub fn is_text(self) -> bool {
+        self == OutputFormat::Text
+    }
+
+    /// Serialize `value` in this format. Text has no serialized form of its
+    /// own and renders as JSON.
+    pub fn render<T: Serialize>(self, value: &T) -> Result<String> {
+        match self {
+            OutputFormat::Text | OutputFormat::Json => Ok(serde_json::to_string_pretty(value)?),
+            OutputFormat::Yaml => serde_yaml::to_string(value)
+                .map(|yaml| yaml.trim_end().to_string())
+                .map_err(|e| SpuffError::Other(e.into())),
+        }
+    }
+
+    /// Print `value` to stdout in this format.
+    pub fn print<T: Serialize>(self, value: &T) -> Result<()> {
+        println!("{}", self.render(value)?);
+        Ok(())
+    }
+
+    /// Print a line of progress or hints: to stdout in `text` mode, to stderr
+    /// otherwise, where it stays out of the serialized result.
+    pub fn println(self, line: impl std::fmt::Display) {
+        if self.is_text() {
+            println!("{}", line);
+        } else {
+            eprintln!("{}", line);
+        }
+    }
+
+    /// Print one item of a stream (`logs --follow`, `agent activity
+    /// --follow`): a JSON line, or a YAML document.
+    pub fn print_item<T: Serialize>(self, value: &T) -> Result<()> {
+        let mut stdout = std::io::stdout().lock();
+        match self {
+            OutputFormat::Text | OutputFormat::Json => {
+                writeln!(stdout, "{}", serde_json::to_string(value)?)?
+            }
+            OutputFormat::Yaml => writeln!(stdout, "---\n{}", self.render(value)?)?,
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Error printed to stdout when a command fails in `json`/`yaml` mode.
+#[derive(Debug, Serialize)]
+pub struct ErrorOutput {
+    pub error: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Sample {
+        name: String,
+        ports: Vec<u16>,
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            name: "dev".to_string(),
+            ports: vec![3000],
+        }
+    }
+
+    #[test]
+    fn test_render_json() {
+        let json = OutputFormat::Json.render(&sample()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["name"], "dev");
+        assert_eq!(value["ports"][0], 3000);
+    }
+
+    #[test]
+    fn test_render_yaml() {
+        let yaml = OutputFormat::Yaml.render(&sample()).unwrap();
+        assert_eq!(yaml, "name: dev\nports:\n- 3000");
+    }
+
+    #[test]
+    fn test_parse_flag_value() {
+        assert_eq!(
+            OutputFormat::from_str("json", true).unwrap(),
+            OutputFormat::Json
+        );
+        assert_eq!(
+            OutputFormat::from_str("YAML", true).unwrap(),
+            OutputFormat::Yaml
+        );
+        assert!(OutputFormat::from_str("xml", true).is_err());
+        assert!(OutputFormat::default().is_text());
+    }
+}
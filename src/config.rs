@@ -1,9 +1,13 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
 use crate::error::{Result, SpuffError};
 use crate::project_config::AiToolsConfig;
+use crate::provider::aws::AwsConfig;
+use crate::provider::qemu::QemuConfig;
+use crate::provider::ssh_pool::SshPoolConfig;
 use crate::provider::ProviderType;
 use crate::volume::VolumeConfig;
 
@@ -15,6 +19,10 @@ pub struct AppConfig {
     pub region: String,
     pub size: String,
     pub idle_timeout: String,
+    /// Hard limit on how long an instance may run, idle or not (e.g. "12h").
+    /// Enforced by spuff-agent; `spuff extend` pushes it back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_lifetime: Option<String>,
     pub environment: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub dotfiles: Option<String>,
@@ -38,12 +46,245 @@ pub struct AppConfig {
     /// These are merged with project-specific volumes from spuff.yaml.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub volumes: Vec<VolumeConfig>,
+    /// AWS-specific settings (secret key, spot mode, networking).
+    /// Only used when `provider: aws`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aws: Option<AwsConfig>,
+    /// Existing hosts to claim as instances.
+    /// Only used when `provider: ssh`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_pool: Option<SshPoolConfig>,
+    /// Local libvirt settings (connection URI, network, storage pool).
+    /// Only used when `provider: qemu`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qemu: Option<QemuConfig>,
+    /// What the local watchdog does once an idle VM has powered itself off.
+    #[serde(default, skip_serializing_if = "AutoDestroyConfig::is_default")]
+    pub auto_destroy: AutoDestroyConfig,
+    /// Which signals the agent treats as activity when checking `idle_timeout`.
+    #[serde(default, skip_serializing_if = "IdlePolicyConfig::is_default")]
+    pub idle_policy: IdlePolicyConfig,
+    /// How long `spuff hibernate` snapshots are kept.
+    #[serde(default, skip_serializing_if = "HibernateConfig::is_default")]
+    pub hibernate: HibernateConfig,
+    /// Spending caps and what to do when one would be exceeded.
+    #[serde(default, skip_serializing_if = "BudgetConfig::is_default")]
+    pub budget: BudgetConfig,
 }
 
 fn default_ssh_user() -> String {
     "dev".to_string()
 }
 
+/// Auto-destroy settings.
+///
+/// Idle VMs power themselves off, but most providers keep billing for a
+/// stopped instance. The watchdog started by `spuff up` destroys powered-off
+/// instances through the provider API, optionally snapshotting them first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoDestroyConfig {
+    /// Destroy instances that powered off on idle (default: true).
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Snapshot the instance before destroying it.
+    #[serde(default)]
+    pub snapshot: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for AutoDestroyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            snapshot: false,
+        }
+    }
+}
+
+impl AutoDestroyConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Idle policy enforced by spuff-agent on the VM.
+///
+/// The environment is idle when none of the enabled signals has been active
+/// for `idle_timeout`. Logged-in terminals are warned `grace_period` before
+/// the VM powers off.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdlePolicyConfig {
+    /// Warning lead time before shutting down (e.g. "10m", "0" disables).
+    #[serde(default = "default_grace_period")]
+    pub grace_period: String,
+    /// Interactive SSH/mosh sessions keep the environment alive.
+    #[serde(default = "default_true")]
+    pub ssh_sessions: bool,
+    /// Open SSH connections without a terminal (tunnels, volume mounts).
+    #[serde(default = "default_true")]
+    pub tunnels: bool,
+    /// 1-minute load average per CPU, in percent (null disables).
+    #[serde(default = "default_cpu_load_threshold")]
+    pub cpu_load_threshold: Option<f64>,
+    /// Network throughput in KiB/s (null disables).
+    #[serde(default = "default_network_threshold_kbps")]
+    pub network_threshold_kbps: Option<u64>,
+    /// Running processes (name or command line substring) that keep it alive.
+    #[serde(default = "default_idle_processes")]
+    pub processes: Vec<String>,
+}
+
+fn default_grace_period() -> String {
+    "10m".to_string()
+}
+
+fn default_cpu_load_threshold() -> Option<f64> {
+    Some(25.0)
+}
+
+fn default_network_threshold_kbps() -> Option<u64> {
+    Some(50)
+}
+
+fn default_idle_processes() -> Vec<String> {
+    [
+        "claude", "codex", "opencode", "node", "cargo", "python", "go build",
+    ]
+    .iter()
+    .map(|p| p.to_string())
+    .collect()
+}
+
+impl Default for IdlePolicyConfig {
+    fn default() -> Self {
+        Self {
+            grace_period: default_grace_period(),
+            ssh_sessions: true,
+            tunnels: true,
+            cpu_load_threshold: default_cpu_load_threshold(),
+            network_threshold_kbps: default_network_threshold_kbps(),
+            processes: default_idle_processes(),
+        }
+    }
+}
+
+impl IdlePolicyConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn parse_grace_period(&self) -> std::time::Duration {
+        parse_duration(&self.grace_period).unwrap_or(std::time::Duration::from_secs(600))
+    }
+}
+
+/// Hibernation settings.
+///
+/// `spuff hibernate` snapshots and destroys an environment; `spuff resume`
+/// recreates it from the latest snapshot. Older hibernation snapshots are
+/// pruned once they exceed `retention`, but the latest one of each
+/// environment is always kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HibernateConfig {
+    /// Maximum age of hibernation snapshots (e.g. "14d", "0" keeps them all).
+    #[serde(default = "default_hibernate_retention")]
+    pub retention: String,
+}
+
+fn default_hibernate_retention() -> String {
+    "14d".to_string()
+}
+
+impl Default for HibernateConfig {
+    fn default() -> Self {
+        Self {
+            retention: default_hibernate_retention(),
+        }
+    }
+}
+
+impl HibernateConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Retention as a duration; `None` keeps snapshots forever.
+    pub fn parse_retention(&self) -> Option<std::time::Duration> {
+        parse_duration(&self.retention)
+            .or(Some(std::time::Duration::from_secs(14 * 86400)))
+            .filter(|d| !d.is_zero())
+    }
+}
+
+/// Budget settings.
+///
+/// Runtime cost is tracked per environment from the hourly price of its
+/// size (see `spuff cost`). `spuff up` warns when a cap is nearly used up,
+/// and the watchdog applies `action` to running instances before a cap
+/// would be exceeded. Caps count every environment, in `currency`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    /// Maximum spend per UTC day.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily: Option<f64>,
+    /// Maximum spend per calendar month (UTC).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly: Option<f64>,
+    /// What happens when a cap would be exceeded (default: warn).
+    #[serde(default)]
+    pub action: BudgetAction,
+    /// Currency of the caps and of `prices` (default: USD).
+    #[serde(default = "default_budget_currency")]
+    pub currency: String,
+    /// Hourly price per size, used instead of the provider's catalog.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prices: BTreeMap<String, f64>,
+}
+
+/// What the watchdog does to running instances when a budget cap would be
+/// exceeded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// Only warn at `spuff up` and in `spuff cost`
+    #[default]
+    Warn,
+    /// Snapshot and destroy, like `spuff hibernate`
+    Hibernate,
+    /// Destroy without a snapshot
+    Destroy,
+}
+
+fn default_budget_currency() -> String {
+    "USD".to_string()
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            daily: None,
+            monthly: None,
+            action: BudgetAction::Warn,
+            currency: default_budget_currency(),
+            prices: BTreeMap::new(),
+        }
+    }
+}
+
+impl BudgetConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether the watchdog has to act on a cap.
+    pub fn enforces(&self) -> bool {
+        self.action != BudgetAction::Warn && (self.daily.is_some() || self.monthly.is_some())
+    }
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
@@ -52,6 +293,7 @@ impl Default for AppConfig {
             region: "nyc1".to_string(),
             size: "s-2vcpu-4gb".to_string(),
             idle_timeout: "2h".to_string(),
+            max_lifetime: None,
             environment: "devbox".to_string(),
             dotfiles: None,
             ssh_key_path: shellexpand::tilde("~/.ssh/id_ed25519").to_string(),
@@ -61,6 +303,13 @@ impl Default for AppConfig {
             agent_token: None,
             ai_tools: None, // None means use default (all)
             volumes: Vec::new(),
+            aws: None,
+            ssh_pool: None,
+            qemu: None,
+            auto_destroy: AutoDestroyConfig::default(),
+            idle_policy: IdlePolicyConfig::default(),
+            hibernate: HibernateConfig::default(),
+            budget: BudgetConfig::default(),
         }
     }
 }
@@ -147,6 +396,11 @@ impl AppConfig {
         parse_duration(&self.idle_timeout).unwrap_or(std::time::Duration::from_secs(7200))
     }
 
+    /// Maximum lifetime as a duration; `None` when unset or "0".
+    pub fn parse_max_lifetime(&self) -> Option<std::time::Duration> {
+        parse_max_lifetime(self.max_lifetime.as_deref()?)
+    }
+
     /// Validate the configuration.
     ///
     /// Returns an error if the configuration is invalid (e.g., unknown provider).
@@ -169,6 +423,42 @@ impl AppConfig {
             )));
         }
 
+        if let Some(max_lifetime) = &self.max_lifetime {
+            if parse_duration(max_lifetime).is_none() {
+                return Err(SpuffError::Config(format!(
+                    "Invalid max_lifetime '{}'. Use format like '12h', '1d' or '0'",
+                    max_lifetime
+                )));
+            }
+        }
+
+        if parse_duration(&self.hibernate.retention).is_none() {
+            return Err(SpuffError::Config(format!(
+                "Invalid hibernate.retention '{}'. Use format like '14d', '72h' or '0'",
+                self.hibernate.retention
+            )));
+        }
+
+        for (name, cap) in [
+            ("daily", self.budget.daily),
+            ("monthly", self.budget.monthly),
+        ] {
+            if cap.is_some_and(|cap| cap.is_nan() || cap <= 0.0) {
+                return Err(SpuffError::Config(format!(
+                    "Invalid budget.{} '{}'. Use a positive amount",
+                    name,
+                    cap.unwrap_or_default()
+                )));
+            }
+        }
+
+        if parse_duration(&self.idle_policy.grace_period).is_none() {
+            return Err(SpuffError::Config(format!(
+                "Invalid idle_policy.grace_period '{}'. Use format like '10m' or '0'",
+                self.idle_policy.grace_period
+            )));
+        }
+
         // Validate SSH key path exists
         let ssh_path = shellexpand::tilde(&self.ssh_key_path);
         if !std::path::Path::new(ssh_path.as_ref()).exists() {
@@ -182,7 +472,6 @@ impl AppConfig {
     }
 
     /// Get the provider type enum.
-    #[allow(dead_code)]
     pub fn provider_type(&self) -> Option<ProviderType> {
         ProviderType::from_str(&self.provider)
     }
@@ -196,10 +485,19 @@ impl AppConfig {
     }
 }
 
-fn parse_duration(s: &str) -> Option<std::time::Duration> {
+/// Parse a `max_lifetime` value; "0" means no limit.
+pub fn parse_max_lifetime(s: &str) -> Option<std::time::Duration> {
+    parse_duration(s).filter(|d| !d.is_zero())
+}
+
+pub fn parse_duration(s: &str) -> Option<std::time::Duration> {
     let s = s.trim().to_lowercase();
 
-    if let Some(hours) = s.strip_suffix('h') {
+    if let Some(days) = s.strip_suffix('d') {
+        days.parse::<u64>()
+            .ok()
+            .map(|d| std::time::Duration::from_secs(d * 86400))
+    } else if let Some(hours) = s.strip_suffix('h') {
         hours
             .parse::<u64>()
             .ok()
@@ -268,6 +566,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_duration_days() {
+        assert_eq!(
+            parse_duration("14d"),
+            Some(std::time::Duration::from_secs(14 * 86400))
+        );
+    }
+
     #[test]
     fn test_parse_duration_raw_seconds() {
         assert_eq!(
@@ -291,6 +597,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_auto_destroy_config_defaults() {
+        let yaml = r#"
+provider: digitalocean
+region: nyc1
+size: s-2vcpu-4gb
+idle_timeout: 2h
+environment: devbox
+ssh_key_path: ~/.ssh/id_ed25519
+"#;
+        let config: AppConfig = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.auto_destroy.enabled);
+        assert!(!config.auto_destroy.snapshot);
+
+        // Default settings are not written back to the config file
+        let serialized = serde_yaml::to_string(&config).unwrap();
+        assert!(!serialized.contains("auto_destroy"));
+
+        let config: AppConfig =
+            serde_yaml::from_str(&format!("{}auto_destroy:\n  snapshot: true\n", yaml)).unwrap();
+        assert!(config.auto_destroy.enabled);
+        assert!(config.auto_destroy.snapshot);
+    }
+
+    #[test]
+    fn test_idle_policy_config() {
+        let policy = IdlePolicyConfig::default();
+        assert!(policy.ssh_sessions);
+        assert!(policy.tunnels);
+        assert_eq!(policy.parse_grace_period().as_secs(), 600);
+        assert!(policy.processes.iter().any(|p| p == "cargo"));
+
+        let policy: IdlePolicyConfig =
+            serde_yaml::from_str("grace_period: 0\ncpu_load_threshold: null\nprocesses: [make]\n")
+                .unwrap();
+        assert_eq!(policy.parse_grace_period().as_secs(), 0);
+        assert!(policy.cpu_load_threshold.is_none());
+        assert_eq!(policy.network_threshold_kbps, Some(50));
+        assert_eq!(policy.processes, vec!["make"]);
+    }
+
+    #[test]
+    fn test_hibernate_retention() {
+        let config = HibernateConfig::default();
+        assert_eq!(config.parse_retention().unwrap().as_secs(), 14 * 86400);
+
+        let config: HibernateConfig = serde_yaml::from_str("retention: 0\n").unwrap();
+        assert!(config.parse_retention().is_none());
+
+        let config: HibernateConfig = serde_yaml::from_str("retention: 48h\n").unwrap();
+        assert_eq!(config.parse_retention().unwrap().as_secs(), 48 * 3600);
+    }
+
+    #[test]
+    fn test_parse_max_lifetime() {
+        let config = AppConfig::default();
+        assert!(config.max_lifetime.is_none());
+        assert!(config.parse_max_lifetime().is_none());
+
+        let config = AppConfig {
+            max_lifetime: Some("12h".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(config.parse_max_lifetime().unwrap().as_secs(), 12 * 3600);
+
+        assert!(parse_max_lifetime("0").is_none());
+        assert!(parse_max_lifetime("soon").is_none());
+        assert_eq!(parse_max_lifetime("1d").unwrap().as_secs(), 86400);
+    }
+
+    #[test]
+    fn test_budget_config() {
+        let config = BudgetConfig::default();
+        assert_eq!(config.action, BudgetAction::Warn);
+        assert_eq!(config.currency, "USD");
+        assert!(!config.enforces());
+
+        let config: BudgetConfig =
+            serde_yaml::from_str("daily: 5\naction: hibernate\nprices:\n  s-2vcpu-4gb: 0.05\n")
+                .unwrap();
+        assert_eq!(config.daily, Some(5.0));
+        assert!(config.monthly.is_none());
+        assert_eq!(config.action, BudgetAction::Hibernate);
+        assert_eq!(config.prices.get("s-2vcpu-4gb"), Some(&0.05));
+        assert!(config.enforces());
+
+        // Warn never needs the watchdog, and neither does an action without caps
+        let config: BudgetConfig = serde_yaml::from_str("monthly: 50\n").unwrap();
+        assert!(!config.enforces());
+        let config: BudgetConfig = serde_yaml::from_str("action: destroy\n").unwrap();
+        assert!(!config.enforces());
+    }
+
     #[test]
     fn test_default_config() {
         let config = AppConfig::default();
@@ -313,6 +712,7 @@ mod tests {
             region: "nyc1".to_string(),
             size: "s-2vcpu-4gb".to_string(),
             idle_timeout: "2h".to_string(),
+            max_lifetime: None,
             environment: "devbox".to_string(),
             dotfiles: Some("https://github.com/user/dotfiles".to_string()),
             ssh_key_path: "/home/user/.ssh/id_ed25519".to_string(),
@@ -322,6 +722,13 @@ mod tests {
             agent_token: None,
             ai_tools: None,
             volumes: Vec::new(),
+            aws: None,
+            ssh_pool: None,
+            qemu: None,
+            auto_destroy: AutoDestroyConfig::default(),
+            idle_policy: IdlePolicyConfig::default(),
+            hibernate: HibernateConfig::default(),
+            budget: Default::default(),
         };
 
         let yaml = serde_yaml::to_string(&config).unwrap();
@@ -409,6 +816,7 @@ tailscale_authkey: tskey-xxx
             region: "ams3".to_string(),
             size: "s-1vcpu-1gb".to_string(),
             idle_timeout: "1h".to_string(),
+            max_lifetime: None,
             environment: "devbox".to_string(),
             dotfiles: None,
             ssh_key_path: "/tmp/test-key".to_string(),
@@ -418,6 +826,13 @@ tailscale_authkey: tskey-xxx
             agent_token: None,
             ai_tools: None,
             volumes: Vec::new(),
+            aws: None,
+            ssh_pool: None,
+            qemu: None,
+            auto_destroy: AutoDestroyConfig::default(),
+            idle_policy: IdlePolicyConfig::default(),
+            hibernate: HibernateConfig::default(),
+            budget: Default::default(),
         };
 
         config.save().unwrap();
@@ -480,7 +895,28 @@ ssh_user: root
             ..Default::default()
         };
         assert!(config.provider_type().is_some());
-        assert!(!config.is_provider_implemented());
+        assert!(config.is_provider_implemented());
+
+        let config = AppConfig {
+            provider: "aws".to_string(),
+            ..Default::default()
+        };
+        assert!(config.provider_type().is_some());
+        assert!(config.is_provider_implemented());
+
+        let config = AppConfig {
+            provider: "ssh".to_string(),
+            ..Default::default()
+        };
+        assert!(config.provider_type().is_some());
+        assert!(config.is_provider_implemented());
+
+        let config = AppConfig {
+            provider: "qemu".to_string(),
+            ..Default::default()
+        };
+        assert!(config.provider_type().is_some());
+        assert!(config.is_provider_implemented());
 
         let config = AppConfig {
             provider: "unknown".to_string(),
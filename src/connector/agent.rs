@@ -0,0 +1,410 @@
+//! Client for spuff-agent's HTTP API.
+//!
+//! Requests travel over an SSH `direct-tcpip` channel to the agent's port on
+//! the VM's loopback interface and speak HTTP/1.1 directly. Nothing runs in
+//! a remote shell, so a MOTD or prompt can't end up in a response. Non-2xx
+//! responses become [`SpuffError::Agent`] with the status code and the
+//! agent's `{"error": ...}` message, as do responses that don't decode.
+//! Failures to reach the agent or to read its response are
+//! [`SpuffError::Ssh`].
+
+use std::convert::Infallible;
+
+use bytes::Bytes;
+use futures::channel::mpsc;
+use futures::StreamExt;
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Empty, Full, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::header::{CONTENT_TYPE, HOST};
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::config::AppConfig;
+use crate::error::{Result, SpuffError};
+use crate::ssh::SshClient;
+
+/// Port spuff-agent listens on, on the VM's loopback interface.
+pub const AGENT_PORT: u16 = 7575;
+
+/// Header carrying `agent_token`.
+const TOKEN_HEADER: &str = "X-Spuff-Token";
+
+type RequestBody = UnsyncBoxBody<Bytes, Infallible>;
+
+/// Connection to an environment's agent. Each request opens its own channel
+/// on a single SSH connection.
+pub struct AgentClient {
+    ssh: SshClient,
+    token: Option<String>,
+}
+
+impl AgentClient {
+    pub async fn connect(ip: &str, config: &AppConfig) -> Result<Self> {
+        let ssh = super::ssh::client(ip, config).await?;
+        Ok(Self::new(ssh, config.agent_token.clone()))
+    }
+
+    /// Talk to the agent over an existing SSH connection.
+    pub fn new(ssh: SshClient, token: Option<String>) -> Self {
+        Self { ssh, token }
+    }
+
+    /// `GET path`, decoding the JSON response.
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let body = Empty::<Bytes>::new().boxed_unsync();
+        let response = self.send(Method::GET, path, None, body).await?;
+        decode(path, response).await
+    }
+
+    /// `POST path` with a JSON body, decoding the JSON response.
+    pub async fn post<B, T>(&self, path: &str, body: &B) -> Result<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let body = Full::new(Bytes::from(serde_json::to_vec(body)?)).boxed_unsync();
+        let response = self
+            .send(Method::POST, path, Some("application/json"), body)
+            .await?;
+        decode(path, response).await
+    }
+
+    /// `POST path` with an NDJSON body sent as chunks arrive on `body`
+    /// (closing it ends the request), returning the response as it streams
+    /// back.
+    pub async fn post_stream(&self, path: &str, body: mpsc::Receiver<Bytes>) -> Result<LineStream> {
+        let body = StreamBody::new(body.map(|chunk| Ok::<_, Infallible>(Frame::data(chunk))))
+            .boxed_unsync();
+        let response = self
+            .send(Method::POST, path, Some("application/x-ndjson"), body)
+            .await?;
+        Ok(LineStream {
+            body: response.into_body(),
+            buf: Vec::new(),
+        })
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        content_type: Option<&str>,
+        body: RequestBody,
+    ) -> Result<Response<Incoming>> {
+        // Stays an `Ssh` error: the agent isn't listening (yet)
+        let stream = self.ssh.direct_tcpip("127.0.0.1", AGENT_PORT).await?;
+        let request = build_request(method, path, content_type, self.token.as_deref(), body)?;
+        exchange(stream, request).await
+    }
+}
+
+/// Response body read one line at a time (NDJSON).
+pub struct LineStream {
+    body: Incoming,
+    buf: Vec<u8>,
+}
+
+impl LineStream {
+    /// Next line, without its newline; `None` once the agent ends the
+    /// response.
+    pub async fn next_line(&mut self) -> Result<Option<String>> {
+        loop {
+            if let Some(line) = take_line(&mut self.buf) {
+                return Ok(Some(line));
+            }
+            match self.body.frame().await {
+                Some(frame) => {
+                    let frame = frame
+                        .map_err(|e| SpuffError::Ssh(format!("Lost connection to agent: {}", e)))?;
+                    if let Ok(data) = frame.into_data() {
+                        self.buf.extend_from_slice(&data);
+                    }
+                }
+                None if self.buf.is_empty() => return Ok(None),
+                // Last line without a trailing newline
+                None => {
+                    let rest = std::mem::take(&mut self.buf);
+                    return Ok(Some(String::from_utf8_lossy(&rest).into_owned()));
+                }
+            }
+        }
+    }
+}
+
+/// Remove the first complete line from `buf`.
+fn take_line(buf: &mut Vec<u8>) -> Option<String> {
+    let end = buf.iter().position(|&b| b == b'\n')?;
+    let line: Vec<u8> = buf.drain(..=end).collect();
+    Some(String::from_utf8_lossy(&line[..end]).into_owned())
+}
+
+fn build_request(
+    method: Method,
+    path: &str,
+    content_type: Option<&str>,
+    token: Option<&str>,
+    body: RequestBody,
+) -> Result<Request<RequestBody>> {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(path)
+        .header(HOST, format!("127.0.0.1:{}", AGENT_PORT));
+    if let Some(content_type) = content_type {
+        request = request.header(CONTENT_TYPE, content_type);
+    }
+    if let Some(token) = token {
+        request = request.header(TOKEN_HEADER, token);
+    }
+    request
+        .body(body)
+        .map_err(|e| SpuffError::Config(format!("Invalid agent request {}: {}", path, e)))
+}
+
+/// Send `request` as the only one on `stream`, failing on non-2xx.
+async fn exchange<S>(stream: S, request: Request<RequestBody>) -> Result<Response<Incoming>>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let path = request.uri().path().to_string();
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(|e| SpuffError::Ssh(format!("Failed to connect to spuff-agent: {}", e)))?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::debug!("Agent connection closed: {}", e);
+        }
+    });
+
+    let response = sender
+        .send_request(request)
+        .await
+        .map_err(|e| SpuffError::Ssh(format!("Agent request {} failed: {}", path, e)))?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = collect(response).await.unwrap_or_default();
+    Err(SpuffError::Agent {
+        status: status.as_u16(),
+        message: error_message(status, &body),
+    })
+}
+
+async fn collect(response: Response<Incoming>) -> Result<Bytes> {
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .map_err(|e| SpuffError::Ssh(format!("Failed to read agent response: {}", e)))?;
+    Ok(body.to_bytes())
+}
+
+async fn decode<T: DeserializeOwned>(path: &str, response: Response<Incoming>) -> Result<T> {
+    let status = response.status().as_u16();
+    let body = collect(response).await?;
+    serde_json::from_slice(&body).map_err(|e| SpuffError::Agent {
+        status,
+        message: format!(
+            "Failed to parse response from {}: {}. Response: {}",
+            path,
+            e,
+            String::from_utf8_lossy(&body)
+        ),
+    })
+}
+
+/// Message of an error response: the agent's `{"error": ...}`, else the
+/// body as text, else the status' reason.
+fn error_message(status: StatusCode, body: &[u8]) -> String {
+    #[derive(Deserialize)]
+    struct ApiError {
+        error: String,
+    }
+
+    if let Ok(api_error) = serde_json::from_slice::<ApiError>(body) {
+        return api_error.error;
+    }
+    let text = String::from_utf8_lossy(body).trim().to_string();
+    if !text.is_empty() {
+        return text;
+    }
+    status
+        .canonical_reason()
+        .unwrap_or("request failed")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::HeaderMap;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+
+    /// Serve `router` on a local port, as the agent would on the VM.
+    async fn serve(router: Router) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        port
+    }
+
+    async fn request(
+        port: u16,
+        method: Method,
+        path: &str,
+        token: Option<&str>,
+        body: RequestBody,
+    ) -> Result<Response<Incoming>> {
+        let stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        let request = build_request(method, path, Some("application/json"), token, body)?;
+        exchange(stream, request).await
+    }
+
+    fn agent() -> Router {
+        Router::new()
+            .route(
+                "/status",
+                get(|headers: HeaderMap| async move {
+                    match headers.get(TOKEN_HEADER) {
+                        Some(token) if token == "secret" => {
+                            Ok(Json(serde_json::json!({ "hostname": "dev" })))
+                        }
+                        _ => Err((
+                            StatusCode::UNAUTHORIZED,
+                            Json(serde_json::json!({ "error": "Missing X-Spuff-Token header" })),
+                        )),
+                    }
+                }),
+            )
+            .route(
+                "/echo",
+                post(|Json(body): Json<serde_json::Value>| async move { Json(body) }),
+            )
+    }
+
+    #[tokio::test]
+    async fn test_exchange_decodes_json() {
+        let port = serve(agent()).await;
+
+        let response = request(
+            port,
+            Method::GET,
+            "/status",
+            Some("secret"),
+            Empty::<Bytes>::new().boxed_unsync(),
+        )
+        .await
+        .unwrap();
+        let status: serde_json::Value = decode("/status", response).await.unwrap();
+        assert_eq!(status["hostname"], "dev");
+
+        let body = Full::new(Bytes::from(r#"{"seconds":3600}"#)).boxed_unsync();
+        let response = request(port, Method::POST, "/echo", None, body)
+            .await
+            .unwrap();
+        let echoed: serde_json::Value = decode("/echo", response).await.unwrap();
+        assert_eq!(echoed["seconds"], 3600);
+    }
+
+    #[tokio::test]
+    async fn test_exchange_reports_status_and_error_body() {
+        let port = serve(agent()).await;
+
+        let err = request(
+            port,
+            Method::GET,
+            "/status",
+            None,
+            Empty::<Bytes>::new().boxed_unsync(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            SpuffError::Agent { status: 401, ref message } if message == "Missing X-Spuff-Token header"
+        ));
+
+        let err = request(
+            port,
+            Method::GET,
+            "/missing",
+            Some("secret"),
+            Empty::<Bytes>::new().boxed_unsync(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, SpuffError::Agent { status: 404, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_error_kinds() {
+        let port = serve(agent().route("/text", get(|| async { "not json" }))).await;
+
+        // Decoding failures come from the agent
+        let response = request(
+            port,
+            Method::GET,
+            "/text",
+            None,
+            Empty::<Bytes>::new().boxed_unsync(),
+        )
+        .await
+        .unwrap();
+        let err = decode::<serde_json::Value>("/text", response)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SpuffError::Agent { status: 200, .. }));
+
+        // A connection dropped before responding is a transport failure
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                drop(stream);
+            }
+        });
+        let err = request(
+            port,
+            Method::GET,
+            "/status",
+            None,
+            Empty::<Bytes>::new().boxed_unsync(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, SpuffError::Ssh(_)));
+    }
+
+    #[test]
+    fn test_error_message() {
+        assert_eq!(
+            error_message(StatusCode::CONFLICT, br#"{"error":"already installing"}"#),
+            "already installing"
+        );
+        assert_eq!(
+            error_message(StatusCode::BAD_GATEWAY, b"upstream down\n"),
+            "upstream down"
+        );
+        assert_eq!(error_message(StatusCode::NOT_FOUND, b""), "Not Found");
+    }
+
+    #[test]
+    fn test_take_line() {
+        let mut buf = b"{\"type\":\"started\"}\n{\"type\":\"std".to_vec();
+        assert_eq!(
+            take_line(&mut buf).as_deref(),
+            Some("{\"type\":\"started\"}")
+        );
+        assert_eq!(take_line(&mut buf), None);
+        assert_eq!(buf, b"{\"type\":\"std");
+    }
+}
@@ -3,6 +3,8 @@
 //! This module provides connection functionality for various providers:
 //! - SSH: Pure Rust SSH for cloud providers (DigitalOcean, Hetzner, AWS)
 //! - Docker: Docker exec API for local containers
+//! - Agent: spuff-agent's HTTP API, over an SSH channel
 
+pub mod agent;
 pub mod docker;
 pub mod ssh;
@@ -74,6 +74,12 @@ pub async fn scp_upload(
     sftp.upload(local_path, remote_path).await
 }
 
+/// Open an SSH connection, for callers that keep a channel open.
+pub async fn client(host: &str, config: &AppConfig) -> Result<SshClient> {
+    let ssh_config = app_config_to_ssh_config(config);
+    SshClient::connect(host, 22, &ssh_config).await
+}
+
 /// Execute a non-interactive command and return stdout.
 ///
 /// Uses pure Rust SSH implementation.
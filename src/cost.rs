@@ -0,0 +1,474 @@
+//! Runtime cost tracking and budgets.
+//!
+//! Instances carry the hourly price of their size, looked up in the
+//! `budget.prices` table or the provider's size catalog when they are
+//! created. The state database keeps one usage record per stretch an
+//! instance was billed at one price; this module turns those records into
+//! spend per environment, day and month, and checks it against the caps in
+//! `budget`.
+//!
+//! Costs are prorated to the second. Providers that bill started hours
+//! charge slightly more for short-lived instances.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{AppConfig, BudgetConfig};
+use crate::provider::Provider;
+use crate::state::UsageRecord;
+
+/// Hourly price of an instance size.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HourlyPrice {
+    pub amount: f64,
+    /// ISO 4217 currency code (e.g. "USD")
+    pub currency: String,
+}
+
+/// Hourly price of `size`, from `budget.prices` or the provider's catalog.
+///
+/// `None` when neither knows the size; local providers have no catalog.
+pub async fn lookup_price(
+    config: &AppConfig,
+    provider: &dyn Provider,
+    size: &str,
+) -> Option<HourlyPrice> {
+    if let Some(amount) = config.budget.prices.get(size) {
+        return Some(HourlyPrice {
+            amount: *amount,
+            currency: config.budget.currency.clone(),
+        });
+    }
+
+    match provider.list_sizes().await {
+        Ok(sizes) => sizes
+            .into_iter()
+            .find(|s| s.slug == size)
+            .map(|s| HourlyPrice {
+                amount: s.price_hourly,
+                currency: s.currency,
+            }),
+        Err(e) => {
+            tracing::debug!("No price for {} on {}: {}", size, provider.name(), e);
+            None
+        }
+    }
+}
+
+/// Cost of a usage record between `from` and `to`.
+pub fn cost_between(record: &UsageRecord, from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    let start = record.started_at.max(from);
+    let end = record.ended_at.unwrap_or(to).min(to);
+    if end <= start {
+        return 0.0;
+    }
+    (end - start).num_seconds() as f64 / 3600.0 * record.price.amount
+}
+
+/// Spend so far in the current day and month.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Spend {
+    pub today: f64,
+    pub month: f64,
+    /// Combined hourly price of everything billed right now
+    pub hourly_rate: f64,
+}
+
+impl Spend {
+    /// Spend as of `now` on records priced in `currency`, with days and
+    /// months in UTC. Records in other currencies are left out.
+    pub fn at(records: &[UsageRecord], currency: &str, now: DateTime<Utc>) -> Self {
+        let day = start_of_day(now);
+        let month = start_of_month(now);
+
+        records
+            .iter()
+            .filter(|record| record.price.currency == currency)
+            .fold(Self::default(), |mut spend, record| {
+                spend.today += cost_between(record, day, now);
+                spend.month += cost_between(record, month, now);
+                if record.ended_at.is_none() {
+                    spend.hourly_rate += record.price.amount;
+                }
+                spend
+            })
+    }
+}
+
+/// Spend to check against `budget`'s caps: only records priced in
+/// `budget.currency` count. Logs a warning when caps are set and some
+/// records are in another currency.
+pub fn budget_spend(budget: &BudgetConfig, records: &[UsageRecord], now: DateTime<Utc>) -> Spend {
+    let others = other_currencies(records, &budget.currency);
+    if !others.is_empty() && (budget.daily.is_some() || budget.monthly.is_some()) {
+        tracing::warn!(
+            "Usage priced in {} is not counted against the {} budget",
+            others.join(", "),
+            budget.currency
+        );
+    }
+    Spend::at(records, &budget.currency, now)
+}
+
+/// Currencies of `records` other than `currency`, sorted.
+pub fn other_currencies<'a>(records: &'a [UsageRecord], currency: &str) -> Vec<&'a str> {
+    let mut currencies: Vec<&str> = records
+        .iter()
+        .map(|r| r.price.currency.as_str())
+        .filter(|c| *c != currency)
+        .collect();
+    currencies.sort_unstable();
+    currencies.dedup();
+    currencies
+}
+
+/// A budget cap and the spend projected against it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CapCheck {
+    /// "daily" or "monthly"
+    pub period: &'static str,
+    pub cap: f64,
+    /// Spend in the period so far, plus the look-ahead at the current rate
+    pub projected: f64,
+}
+
+impl CapCheck {
+    pub fn exceeded(&self) -> bool {
+        self.projected >= self.cap
+    }
+
+    /// Share of the cap used (1.0 once it is reached).
+    pub fn used(&self) -> f64 {
+        self.projected / self.cap
+    }
+}
+
+/// Check spend against the configured caps, projecting `ahead` at the
+/// current hourly rate. The cap closest to being exceeded comes first.
+///
+/// `spend` must be in `budget.currency`; see [`budget_spend`].
+pub fn check_budget(budget: &BudgetConfig, spend: &Spend, ahead: Duration) -> Vec<CapCheck> {
+    let upcoming = spend.hourly_rate * ahead.as_secs_f64() / 3600.0;
+
+    let mut checks: Vec<CapCheck> = [
+        ("daily", budget.daily, spend.today),
+        ("monthly", budget.monthly, spend.month),
+    ]
+    .into_iter()
+    .filter_map(|(period, cap, spent)| {
+        cap.map(|cap| CapCheck {
+            period,
+            cap,
+            projected: spent + upcoming,
+        })
+    })
+    .collect();
+
+    checks.sort_by(|a, b| b.used().total_cmp(&a.used()));
+    checks
+}
+
+/// Cost of one environment, across all the instances that carried its name.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnvironmentCost {
+    pub name: String,
+    pub provider: String,
+    /// Latest size the environment ran with
+    pub size: String,
+    pub month: f64,
+    pub total: f64,
+    /// Hourly price while running, 0 once destroyed
+    pub hourly_rate: f64,
+}
+
+/// Cost per environment as of `now`, highest this month first.
+pub fn by_environment(records: &[UsageRecord], now: DateTime<Utc>) -> Vec<EnvironmentCost> {
+    let month = start_of_month(now);
+    let mut environments: Vec<EnvironmentCost> = Vec::new();
+
+    // Records are oldest first, so the last one seen has the latest size
+    for record in records {
+        let index = match environments.iter().position(|e| e.name == record.name) {
+            Some(index) => index,
+            None => {
+                environments.push(EnvironmentCost {
+                    name: record.name.clone(),
+                    provider: record.provider.clone(),
+                    size: String::new(),
+                    month: 0.0,
+                    total: 0.0,
+                    hourly_rate: 0.0,
+                });
+                environments.len() - 1
+            }
+        };
+
+        let env = &mut environments[index];
+        env.provider = record.provider.clone();
+        env.size = record.size.clone();
+        env.month += cost_between(record, month, now);
+        env.total += cost_between(record, record.started_at, now);
+        if record.ended_at.is_none() {
+            env.hourly_rate += record.price.amount;
+        }
+    }
+
+    environments.sort_by(|a, b| b.month.total_cmp(&a.month));
+    environments
+}
+
+/// Cost per calendar month ("YYYY-MM", UTC) as of `now`, oldest first.
+pub fn by_month(records: &[UsageRecord], now: DateTime<Utc>) -> Vec<(String, f64)> {
+    let mut months: BTreeMap<String, f64> = BTreeMap::new();
+
+    for record in records {
+        let end = record.ended_at.unwrap_or(now).min(now);
+        let mut from = record.started_at;
+        while from < end {
+            let to = start_of_next_month(from).min(end);
+            *months.entry(from.format("%Y-%m").to_string()).or_default() +=
+                cost_between(record, from, to);
+            from = to;
+        }
+    }
+
+    months.into_iter().collect()
+}
+
+/// Currency to report totals in: the records' own when they agree,
+/// otherwise the budget's.
+pub fn report_currency<'a>(records: &'a [UsageRecord], budget: &'a BudgetConfig) -> &'a str {
+    match records.first() {
+        Some(first)
+            if records
+                .iter()
+                .all(|r| r.price.currency == first.price.currency) =>
+        {
+            &first.price.currency
+        }
+        _ => &budget.currency,
+    }
+}
+
+fn start_of_day(at: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(at.year(), at.month(), at.day(), 0, 0, 0)
+        .single()
+        .unwrap_or(at)
+}
+
+fn start_of_month(at: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(at.year(), at.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(at)
+}
+
+fn start_of_next_month(at: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = if at.month() == 12 {
+        (at.year() + 1, 1)
+    } else {
+        (at.year(), at.month() + 1)
+    };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .unwrap_or(at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::digitalocean::DigitalOceanProvider;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn record(name: &str, price: f64, started: &str, ended: Option<&str>) -> UsageRecord {
+        UsageRecord {
+            instance_id: format!("id-{}", name),
+            name: name.to_string(),
+            provider: "digitalocean".to_string(),
+            size: "s-2vcpu-4gb".to_string(),
+            price: HourlyPrice {
+                amount: price,
+                currency: "USD".to_string(),
+            },
+            started_at: at(started),
+            ended_at: ended.map(at),
+        }
+    }
+
+    #[test]
+    fn test_cost_between() {
+        let r = record(
+            "api",
+            0.5,
+            "2026-03-01T10:00:00Z",
+            Some("2026-03-01T14:00:00Z"),
+        );
+        assert_eq!(
+            cost_between(&r, at("2026-03-01T00:00:00Z"), at("2026-03-02T00:00:00Z")),
+            2.0
+        );
+        // Clipped to the window
+        assert_eq!(
+            cost_between(&r, at("2026-03-01T12:00:00Z"), at("2026-03-02T00:00:00Z")),
+            1.0
+        );
+        assert_eq!(
+            cost_between(&r, at("2026-03-01T15:00:00Z"), at("2026-03-02T00:00:00Z")),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_spend_at() {
+        let records = vec![
+            // Ended last month
+            record(
+                "old",
+                1.0,
+                "2026-02-28T20:00:00Z",
+                Some("2026-03-01T02:00:00Z"),
+            ),
+            // Ran yesterday
+            record(
+                "api",
+                0.5,
+                "2026-03-09T20:00:00Z",
+                Some("2026-03-10T04:00:00Z"),
+            ),
+            // Still running since yesterday
+            record("web", 0.25, "2026-03-09T22:00:00Z", None),
+        ];
+
+        let spend = Spend::at(&records, "USD", at("2026-03-10T06:00:00Z"));
+        assert_eq!(spend.today, 0.5 * 4.0 + 0.25 * 6.0);
+        assert_eq!(spend.month, 2.0 + 0.5 * 8.0 + 0.25 * 8.0);
+        assert_eq!(spend.hourly_rate, 0.25);
+    }
+
+    #[test]
+    fn test_budget_spend_skips_other_currencies() {
+        let mut eur = record("eu", 2.0, "2026-03-10T00:00:00Z", None);
+        eur.price.currency = "EUR".to_string();
+        let records = vec![record("us", 0.5, "2026-03-10T00:00:00Z", None), eur];
+        let budget = BudgetConfig {
+            daily: Some(5.0),
+            ..Default::default()
+        };
+
+        let spend = budget_spend(&budget, &records, at("2026-03-10T04:00:00Z"));
+        assert_eq!(spend.today, 2.0);
+        assert_eq!(spend.hourly_rate, 0.5);
+        assert_eq!(other_currencies(&records, &budget.currency), vec!["EUR"]);
+        assert!(other_currencies(&records[..1], "USD").is_empty());
+    }
+
+    #[test]
+    fn test_check_budget() {
+        let spend = Spend {
+            today: 4.5,
+            month: 20.0,
+            hourly_rate: 1.0,
+        };
+        let budget = BudgetConfig {
+            daily: Some(5.0),
+            monthly: Some(100.0),
+            ..Default::default()
+        };
+
+        let checks = check_budget(&budget, &spend, Duration::ZERO);
+        assert_eq!(checks.len(), 2);
+        assert_eq!(checks[0].period, "daily");
+        assert!(!checks[0].exceeded());
+        assert_eq!(checks[0].used(), 0.9);
+
+        // Another hour at the current rate would cross the daily cap
+        let checks = check_budget(&budget, &spend, Duration::from_secs(3600));
+        assert!(checks[0].exceeded());
+        assert!(!checks[1].exceeded());
+
+        assert!(check_budget(&BudgetConfig::default(), &spend, Duration::ZERO).is_empty());
+    }
+
+    #[test]
+    fn test_by_environment_and_month() {
+        let records = vec![
+            record(
+                "api",
+                1.0,
+                "2026-02-28T22:00:00Z",
+                Some("2026-03-01T02:00:00Z"),
+            ),
+            record("web", 0.5, "2026-03-01T00:00:00Z", None),
+            // Same environment, recreated with a bigger size
+            UsageRecord {
+                size: "s-4vcpu-8gb".to_string(),
+                ..record("api", 2.0, "2026-03-01T03:00:00Z", None)
+            },
+        ];
+        let now = at("2026-03-01T04:00:00Z");
+
+        let envs = by_environment(&records, now);
+        assert_eq!(envs.len(), 2);
+        assert_eq!(envs[0].name, "api");
+        assert_eq!(envs[0].size, "s-4vcpu-8gb");
+        assert_eq!(envs[0].month, 2.0 + 2.0);
+        assert_eq!(envs[0].total, 4.0 + 2.0);
+        assert_eq!(envs[0].hourly_rate, 2.0);
+        assert_eq!(envs[1].month, 2.0);
+
+        let months = by_month(&records, now);
+        assert_eq!(
+            months,
+            vec![
+                ("2026-02".to_string(), 2.0),
+                ("2026-03".to_string(), 2.0 + 2.0 + 2.0)
+            ]
+        );
+
+        assert_eq!(report_currency(&records, &BudgetConfig::default()), "USD");
+    }
+
+    #[tokio::test]
+    async fn test_lookup_price() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/sizes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "sizes": [
+                    {"slug": "s-2vcpu-4gb", "memory": 4096, "vcpus": 2, "disk": 80,
+                     "price_monthly": 24.0, "price_hourly": 0.03571,
+                     "regions": ["nyc1"], "available": true}
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider =
+            DigitalOceanProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let mut config = AppConfig::default();
+        config.budget.currency = "EUR".to_string();
+        config.budget.prices.insert("s-8vcpu-16gb".to_string(), 0.1);
+
+        let price = lookup_price(&config, &provider, "s-2vcpu-4gb")
+            .await
+            .unwrap();
+        assert_eq!(price.amount, 0.03571);
+        assert_eq!(price.currency, "USD");
+
+        // The table wins over the catalog
+        let price = lookup_price(&config, &provider, "s-8vcpu-16gb")
+            .await
+            .unwrap();
+        assert_eq!(price.amount, 0.1);
+        assert_eq!(price.currency, "EUR");
+
+        assert!(lookup_price(&config, &provider, "s-64vcpu").await.is_none());
+    }
+}
@@ -1,8 +1,10 @@
+use serde::Serialize;
 use tera::{Context, Tera};
 
 use crate::config::AppConfig;
 use crate::error::Result;
 use crate::project_config::ProjectConfig;
+use crate::ssh::host_keys::HostKeyPair;
 
 const CLOUD_INIT_TEMPLATE: &str = r#"#cloud-config
 package_update: true
@@ -11,6 +13,16 @@ package_upgrade: false
 # Disable root SSH login
 disable_root: true
 ssh_pwauth: false
+{%- if has_host_key %}
+
+# Host key generated by spuff, which pins it before connecting
+ssh_deletekeys: true
+ssh_genkeytypes: []
+ssh_keys:
+  ed25519_private: |
+    {{ host_key_private }}
+  ed25519_public: {{ host_key_public }}
+{%- endif %}
 
 # Minimal packages - devtools installed via agent
 packages:
@@ -167,50 +179,17 @@ write_files:
       [Install]
       WantedBy=multi-user.target
 
-  - path: /opt/spuff/idle-checker.sh
-    permissions: '0755'
+  # Idle policy enforced by spuff-agent
+  - path: /opt/spuff/idle.json
+    permissions: '0644'
     content: |
-      #!/bin/bash
-      IDLE_TIMEOUT_SECONDS={{ idle_timeout_seconds }}
-      LAST_ACTIVITY_FILE="/tmp/spuff-last-activity"
-
-      update_activity() {
-        date +%s > "$LAST_ACTIVITY_FILE"
-      }
-
-      check_idle() {
-        if [ ! -f "$LAST_ACTIVITY_FILE" ]; then
-          update_activity
-          return
-        fi
-
-        LAST_ACTIVITY=$(cat "$LAST_ACTIVITY_FILE")
-        NOW=$(date +%s)
-        IDLE_TIME=$((NOW - LAST_ACTIVITY))
-
-        # Check for active SSH sessions
-        if who | grep -q pts; then
-          update_activity
-          return
-        fi
-
-        # Check for running processes (claude, node, cargo, etc)
-        if pgrep -f "claude|node|cargo|python|go build" > /dev/null; then
-          update_activity
-          return
-        fi
-
-        if [ "$IDLE_TIME" -ge "$IDLE_TIMEOUT_SECONDS" ]; then
-          logger "spuff: Idle timeout reached ($IDLE_TIME seconds). Shutting down."
-          shutdown -h now
-        fi
-      }
-
-      check_idle
-
-  - path: /etc/cron.d/spuff-idle-checker
+      {{ idle_policy }}
+  # Maximum lifetime enforced by spuff-agent (rewritten by `spuff extend`).
+  # Always written: `null` replaces a stale deadline from a snapshot.
+  - path: /opt/spuff/lifetime.json
+    permissions: '0644'
     content: |
-      */5 * * * * root /opt/spuff/idle-checker.sh >> /var/log/spuff-idle.log 2>&1
+      {{ lifetime }}
 
   - path: /etc/systemd/system/spuff-agent.service
     permissions: '0644'
@@ -230,6 +209,48 @@ write_files:
       [Install]
       WantedBy=multi-user.target
 
+{% if disks %}
+  # Persistent disks: formatted on first use, mounted on every instance
+  - path: /opt/spuff/disks.sh
+    permissions: '0755'
+    content: |
+      #!/bin/bash
+      set -u
+
+      mount_disk() {
+        device="$1"
+        mount_point="$2"
+        fresh=0
+
+        # Attached volumes can show up a little after boot
+        for _ in $(seq 1 60); do
+          [ -e "$device" ] && break
+          sleep 2
+        done
+        if [ ! -e "$device" ]; then
+          echo "spuff: $device not found, not mounting $mount_point" >&2
+          return 1
+        fi
+
+        if ! blkid "$device" >/dev/null 2>&1; then
+          mkfs.ext4 -q "$device" || return 1
+          fresh=1
+        fi
+
+        mkdir -p "$mount_point"
+        uuid=$(blkid -s UUID -o value "$device")
+        if ! grep -q "UUID=$uuid " /etc/fstab; then
+          echo "UUID=$uuid $mount_point ext4 defaults,nofail,discard 0 2" >> /etc/fstab
+        fi
+        mountpoint -q "$mount_point" || mount "$mount_point" || return 1
+
+        if [ "$fresh" = 1 ]; then
+          chown {{ username }}:{{ username }} "$mount_point"
+        fi
+      }
+
+{% for disk in disks %}      mount_disk {{ disk.device }} {{ disk.mount }}
+{% endfor %}{% endif %}
 {% if agent_token %}
   - path: /opt/spuff/agent.env
     permissions: '0600'
@@ -412,8 +433,6 @@ runcmd:
   # Phase 1: Quick setup (sync)
   - mkdir -p /opt/spuff
   - echo "pending" > /opt/spuff/bootstrap.status
-  - date +%s > /tmp/spuff-last-activity
-  - chmod 0644 /etc/cron.d/spuff-idle-checker
 
   # Configure shell for user
   - mkdir -p {{ home_dir }}/.bashrc.d
@@ -436,6 +455,10 @@ runcmd:
     fi
   # Fix ownership - cloud-init write_files creates as root
   - chown -R {{ username }}:{{ username }} {{ home_dir }}
+{% if disks %}
+  # Mount persistent disks before anything writes to their paths
+  - /opt/spuff/disks.sh
+{% endif %}
 
   # Phase 2: Start async bootstrap
   - systemctl daemon-reload
@@ -445,26 +468,40 @@ runcmd:
 final_message: "spuff cloud-init done in $UPTIME seconds - bootstrap running async"
 "#;
 
+/// Persistent disk the bootstrap formats (once) and mounts
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskMount {
+    /// Device path on the instance
+    pub device: String,
+    /// Mount path
+    pub mount: String,
+}
+
 /// Generate cloud-init configuration (used by tests)
 #[cfg(test)]
 pub fn generate_cloud_init(
     config: &AppConfig,
     project_config: Option<&ProjectConfig>,
 ) -> Result<String> {
-    generate_cloud_init_with_ai_tools(config, project_config, None)
+    generate_cloud_init_with_ai_tools(config, project_config, None, None, &[])
 }
 
 /// Generate cloud-init with explicit AI tools override from CLI
+///
+/// `host_key` replaces the SSH host keys the image would generate, so the
+/// key is known before the first connection. `disks` are mounted before
+/// the bootstrap starts.
 pub fn generate_cloud_init_with_ai_tools(
     config: &AppConfig,
     project_config: Option<&ProjectConfig>,
     cli_ai_tools: Option<&crate::project_config::AiToolsConfig>,
+    host_key: Option<&HostKeyPair>,
+    disks: &[DiskMount],
 ) -> Result<String> {
     let mut tera = Tera::default();
     tera.add_raw_template("cloud-init", CLOUD_INIT_TEMPLATE)?;
 
     let ssh_public_key = read_ssh_public_key(&config.ssh_key_path)?;
-    let idle_timeout_seconds = config.parse_idle_timeout().as_secs();
 
     // Get spuff managed key (ed25519) - this avoids RSA SHA2 issues with russh
     let spuff_public_key = crate::ssh::managed_key::get_managed_public_key()
@@ -490,6 +527,15 @@ pub fn generate_cloud_init_with_ai_tools(
                 .join("\n")
         });
 
+    // Indent each line for YAML block scalar (ed25519_private: |) - needs 4 spaces
+    let host_key_private = host_key.map(|key| {
+        key.private_key
+            .trim_end()
+            .lines()
+            .collect::<Vec<_>>()
+            .join("\n    ")
+    });
+
     // Determine home directory based on username
     let home_dir = if config.ssh_user == "root" {
         "/root".to_string()
@@ -541,6 +587,17 @@ pub fn generate_cloud_init_with_ai_tools(
         })
         .unwrap_or_default();
 
+    // Pool hosts are shared machines; never power them off on idle
+    let shutdown_on_idle = config.provider_type() != Some(crate::provider::ProviderType::Ssh);
+    let idle_policy = idle_policy_json(config, shutdown_on_idle);
+
+    // spuff.yaml wins over the global setting, so a project can lift it with "0"
+    let max_lifetime = match project_config.and_then(|pc| pc.resources.max_lifetime.as_deref()) {
+        Some(value) => crate::config::parse_max_lifetime(value),
+        None => config.parse_max_lifetime(),
+    };
+    let lifetime = max_lifetime.map(|max| lifetime_json(max, shutdown_on_idle));
+
     let mut context = Context::new();
     context.insert("username", &config.ssh_user);
     context.insert("home_dir", &home_dir);
@@ -557,9 +614,21 @@ pub fn generate_cloud_init_with_ai_tools(
         &spuff_private_key.clone().unwrap_or_default(),
     );
     context.insert("has_spuff_private_key", &spuff_private_key.is_some());
+    context.insert("has_host_key", &host_key.is_some());
+    context.insert(
+        "host_key_private",
+        &host_key_private.clone().unwrap_or_default(),
+    );
+    context.insert(
+        "host_key_public",
+        &host_key
+            .map(|key| key.public_key.as_str())
+            .unwrap_or_default(),
+    );
     context.insert("environment", &config.environment);
     context.insert("dotfiles", &config.dotfiles);
-    context.insert("idle_timeout_seconds", &idle_timeout_seconds);
+    context.insert("idle_policy", &idle_policy);
+    context.insert("lifetime", &lifetime.unwrap_or_else(|| "null".to_string()));
     context.insert("tailscale_enabled", &config.tailscale_enabled);
     context.insert("tailscale_authkey", &config.tailscale_authkey);
     context.insert("agent_token", &config.agent_token);
@@ -572,11 +641,39 @@ pub fn generate_cloud_init_with_ai_tools(
     context.insert("ai_copilot", &ai_copilot);
     // Volume packages
     context.insert("volume_packages", &volume_packages);
+    context.insert("disks", disks);
 
     let rendered = tera.render("cloud-init", &context)?;
     Ok(rendered)
 }
 
+/// Render the agent's idle policy (`/opt/spuff/idle.json`) as one JSON line.
+fn idle_policy_json(config: &AppConfig, shutdown_on_idle: bool) -> String {
+    let policy = &config.idle_policy;
+    serde_json::json!({
+        "timeout_seconds": config.parse_idle_timeout().as_secs(),
+        "grace_period_seconds": policy.parse_grace_period().as_secs(),
+        "action": if shutdown_on_idle { "shutdown" } else { "none" },
+        "ssh_sessions": policy.ssh_sessions,
+        "tunnels": policy.tunnels,
+        "cpu_load_threshold": policy.cpu_load_threshold,
+        "network_threshold_kbps": policy.network_threshold_kbps,
+        "processes": policy.processes,
+    })
+    .to_string()
+}
+
+/// Render the agent's lifetime limit (`/opt/spuff/lifetime.json`) as one JSON
+/// line. The deadline counts from now, just before the instance is created.
+fn lifetime_json(max_lifetime: std::time::Duration, shutdown: bool) -> String {
+    let deadline = chrono::Utc::now() + chrono::Duration::seconds(max_lifetime.as_secs() as i64);
+    serde_json::json!({
+        "deadline": deadline.to_rfc3339(),
+        "action": if shutdown { "shutdown" } else { "none" },
+    })
+    .to_string()
+}
+
 fn read_ssh_public_key(private_key_path: &str) -> Result<String> {
     let public_key_path = format!("{}.pub", private_key_path);
 
@@ -649,7 +746,7 @@ mod tests {
 
         let result = generate_cloud_init(&config, None).unwrap();
         // 4h = 14400 seconds
-        assert!(result.contains("IDLE_TIMEOUT_SECONDS=14400"));
+        assert_eq!(idle_policy_from(&result)["timeout_seconds"], 14400);
     }
 
     #[test]
@@ -780,8 +877,21 @@ mod tests {
         assert!(result.contains("spuff-agent.service"));
     }
 
+    /// Extract and parse `/opt/spuff/idle.json` from rendered cloud-init.
+    fn idle_policy_from(cloud_init: &str) -> serde_json::Value {
+        let doc: serde_yaml::Value = serde_yaml::from_str(cloud_init).unwrap();
+        let content = doc["write_files"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .find(|f| f["path"] == "/opt/spuff/idle.json")
+            .and_then(|f| f["content"].as_str())
+            .unwrap();
+        serde_json::from_str(content).unwrap()
+    }
+
     #[test]
-    fn test_cloud_init_contains_idle_checker() {
+    fn test_cloud_init_contains_idle_policy() {
         let (_temp_dir, key_path) = create_test_ssh_key();
 
         let config = AppConfig {
@@ -790,9 +900,144 @@ mod tests {
         };
 
         let result = generate_cloud_init(&config, None).unwrap();
-        assert!(result.contains("idle-checker.sh"));
-        assert!(result.contains("spuff-idle-checker"));
-        assert!(result.contains("check_idle"));
+        // Idle detection lives in the agent now, not in a cron script
+        assert!(!result.contains("idle-checker.sh"));
+
+        let policy = idle_policy_from(&result);
+        assert_eq!(policy["action"], "shutdown");
+        assert_eq!(policy["timeout_seconds"], 7200);
+        assert_eq!(policy["grace_period_seconds"], 600);
+        assert_eq!(policy["ssh_sessions"], true);
+        assert_eq!(policy["tunnels"], true);
+        assert!(policy["processes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|p| p == "cargo"));
+    }
+
+    #[test]
+    fn test_cloud_init_custom_idle_policy() {
+        let (_temp_dir, key_path) = create_test_ssh_key();
+
+        let config = AppConfig {
+            ssh_key_path: key_path,
+            idle_policy: crate::config::IdlePolicyConfig {
+                grace_period: "0".to_string(),
+                tunnels: false,
+                cpu_load_threshold: None,
+                processes: vec!["make".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let policy = idle_policy_from(&generate_cloud_init(&config, None).unwrap());
+        assert_eq!(policy["grace_period_seconds"], 0);
+        assert_eq!(policy["tunnels"], false);
+        assert!(policy["cpu_load_threshold"].is_null());
+        assert_eq!(policy["network_threshold_kbps"], 50);
+        assert_eq!(policy["processes"], serde_json::json!(["make"]));
+    }
+
+    /// Extract and parse `/opt/spuff/lifetime.json` from rendered cloud-init.
+    fn lifetime_from(cloud_init: &str) -> Option<serde_json::Value> {
+        let doc: serde_yaml::Value = serde_yaml::from_str(cloud_init).unwrap();
+        let content = doc["write_files"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .find(|f| f["path"] == "/opt/spuff/lifetime.json")
+            .and_then(|f| f["content"].as_str())?;
+        Some(serde_json::from_str(content).unwrap())
+    }
+
+    #[test]
+    fn test_cloud_init_max_lifetime() {
+        let (_temp_dir, key_path) = create_test_ssh_key();
+
+        let config = AppConfig {
+            ssh_key_path: key_path,
+            ..Default::default()
+        };
+        assert_eq!(
+            lifetime_from(&generate_cloud_init(&config, None).unwrap()),
+            Some(serde_json::Value::Null)
+        );
+
+        let config = AppConfig {
+            max_lifetime: Some("12h".to_string()),
+            ..config
+        };
+        let lifetime = lifetime_from(&generate_cloud_init(&config, None).unwrap()).unwrap();
+        assert_eq!(lifetime["action"], "shutdown");
+        let deadline =
+            chrono::DateTime::parse_from_rfc3339(lifetime["deadline"].as_str().unwrap()).unwrap();
+        let minutes = (deadline.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_minutes();
+        assert!((11 * 60..=12 * 60).contains(&minutes));
+
+        // spuff.yaml overrides the global limit, "0" lifts it
+        let mut project = ProjectConfig::default();
+        project.resources.max_lifetime = Some("0".to_string());
+        assert_eq!(
+            lifetime_from(&generate_cloud_init(&config, Some(&project)).unwrap()),
+            Some(serde_json::Value::Null)
+        );
+    }
+
+    #[test]
+    fn test_cloud_init_ssh_pool_never_shuts_down() {
+        let (_temp_dir, key_path) = create_test_ssh_key();
+
+        let config = AppConfig {
+            provider: "ssh".to_string(),
+            ssh_key_path: key_path,
+            ..Default::default()
+        };
+
+        let result = generate_cloud_init(&config, None).unwrap();
+        assert_eq!(idle_policy_from(&result)["action"], "none");
+    }
+
+    #[test]
+    fn test_cloud_init_installs_host_key() {
+        let (_temp_dir, key_path) = create_test_ssh_key();
+
+        let config = AppConfig {
+            ssh_key_path: key_path,
+            ..Default::default()
+        };
+        let host_key = HostKeyPair::generate().unwrap();
+
+        let result =
+            generate_cloud_init_with_ai_tools(&config, None, None, Some(&host_key), &[]).unwrap();
+        let doc: serde_yaml::Value = serde_yaml::from_str(&result).unwrap();
+
+        assert_eq!(doc["ssh_deletekeys"], true);
+        assert_eq!(
+            doc["ssh_keys"]["ed25519_public"].as_str(),
+            Some(host_key.public_key.as_str())
+        );
+        assert_eq!(
+            doc["ssh_keys"]["ed25519_private"]
+                .as_str()
+                .unwrap()
+                .trim_end(),
+            host_key.private_key.trim_end()
+        );
+    }
+
+    #[test]
+    fn test_cloud_init_without_host_key() {
+        let (_temp_dir, key_path) = create_test_ssh_key();
+
+        let config = AppConfig {
+            ssh_key_path: key_path,
+            ..Default::default()
+        };
+
+        let result = generate_cloud_init(&config, None).unwrap();
+        assert!(!result.contains("ssh_keys:"));
     }
 
     #[test]
@@ -997,6 +1242,65 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cloud_init_mounts_persistent_disks() {
+        let (_temp_dir, key_path) = create_test_ssh_key();
+
+        let config = AppConfig {
+            ssh_key_path: key_path,
+            ..Default::default()
+        };
+
+        let disks = vec![
+            DiskMount {
+                device: "/dev/disk/by-id/scsi-0DO_Volume_spuff-pgdata".to_string(),
+                mount: "/var/lib/postgresql".to_string(),
+            },
+            DiskMount {
+                device: "/dev/disk/by-id/scsi-0HC_Volume_4242".to_string(),
+                mount: "/home/dev/cache".to_string(),
+            },
+        ];
+        let result = generate_cloud_init_with_ai_tools(&config, None, None, None, &disks).unwrap();
+
+        let doc: serde_yaml::Value = serde_yaml::from_str(&result).unwrap();
+        let script = doc["write_files"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .find(|f| f["path"].as_str() == Some("/opt/spuff/disks.sh"))
+            .and_then(|f| f["content"].as_str())
+            .unwrap();
+        assert!(script.contains(
+            "mount_disk /dev/disk/by-id/scsi-0DO_Volume_spuff-pgdata /var/lib/postgresql\n"
+        ));
+        assert!(
+            script.contains("mount_disk /dev/disk/by-id/scsi-0HC_Volume_4242 /home/dev/cache\n")
+        );
+
+        // Formatted only when blank, so a reattached disk keeps its data
+        let format = script.find("mkfs.ext4").unwrap();
+        let guard = script.find("if ! blkid \"$device\"").unwrap();
+        assert!(guard < format);
+        assert_eq!(script.matches("mkfs.ext4").count(), 1);
+
+        // Mounted by UUID through fstab, so it comes back after a reboot
+        assert!(script.contains("ext4 defaults,nofail,discard 0 2\" >> /etc/fstab"));
+        assert!(script.contains("mountpoint -q \"$mount_point\" || mount \"$mount_point\""));
+
+        // Mounted before the rest of the bootstrap runs
+        let runcmd = doc["runcmd"].as_sequence().unwrap();
+        let position = |cmd: &str| runcmd.iter().position(|c| c.as_str() == Some(cmd));
+        assert!(
+            position("/opt/spuff/disks.sh").unwrap()
+                < position("systemctl start spuff-bootstrap.service --no-block").unwrap()
+        );
+
+        // No disks, no script
+        let result = generate_cloud_init(&config, None).unwrap();
+        assert!(!result.contains("disks.sh"));
+    }
+
     #[test]
     fn test_cloud_init_without_volumes() {
         let (_temp_dir, key_path) = create_test_ssh_key();
@@ -18,9 +18,16 @@ pub enum SpuffError {
     #[error("SSH connection error: {0}")]
     Ssh(String),
 
+    /// Non-2xx response from spuff-agent, with the message from its body.
+    #[error("spuff-agent returned {status}: {message}")]
+    Agent { status: u16, message: String },
+
     #[error("No active instance found")]
     NoActiveInstance,
 
+    #[error("Environment '{0}' not found (run 'spuff ls' to see environments)")]
+    EnvironmentNotFound(String),
+
     #[error("Cloud-init failed: {0}")]
     CloudInit(String),
 
@@ -1,6 +1,7 @@
 mod cli;
 mod config;
 mod connector;
+mod cost;
 mod environment;
 mod error;
 mod project_config;
@@ -24,7 +25,12 @@ async fn main() -> Result<()> {
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "spuff=info,chrondb=off".into()),
         )
-        .with(tracing_subscriber::fmt::layer().without_time())
+        // stderr, so logs never mix with `--output json|yaml` on stdout
+        .with(
+            tracing_subscriber::fmt::layer()
+                .without_time()
+                .with_writer(std::io::stderr),
+        )
         .init();
 
     let cli = Cli::parse();
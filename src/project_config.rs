@@ -67,6 +67,10 @@ pub struct ProjectConfig {
     #[serde(default)]
     pub volumes: Vec<VolumeConfig>,
 
+    /// Provider block volumes that survive `spuff down`
+    #[serde(default)]
+    pub persistent_disks: Vec<PersistentDisk>,
+
     /// Base directory where spuff.yaml is located (not serialized)
     /// Used to resolve relative paths in the config
     #[serde(skip)]
@@ -200,6 +204,10 @@ pub struct ResourcesConfig {
     /// Region preference (e.g., nyc1)
     #[serde(default)]
     pub region: Option<String>,
+
+    /// Hard limit on how long the VM may run (e.g., 8h, "0" for none)
+    #[serde(default)]
+    pub max_lifetime: Option<String>,
 }
 
 /// Docker services configuration
@@ -273,6 +281,72 @@ pub struct HooksConfig {
     pub pre_down: Option<String>,
 }
 
+/// Block storage disk kept across environments
+///
+/// Created on the first `spuff up`, formatted once by the bootstrap and
+/// mounted on every new instance until deleted with `spuff disk delete`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistentDisk {
+    /// Disk name, unique per provider account
+    pub name: String,
+
+    /// Size in GB (used when the disk is created)
+    pub size: u64,
+
+    /// Absolute mount path on the VM
+    pub mount: String,
+}
+
+impl PersistentDisk {
+    /// Name of the provider volume backing the disk
+    pub fn volume_name(&self) -> String {
+        format!("spuff-{}", self.name)
+    }
+
+    /// Check the name and mount path are usable by providers and the bootstrap
+    pub fn validate(&self) -> Result<()> {
+        let name_ok = self.name.len() <= 56
+            && self.name.starts_with(|c: char| c.is_ascii_lowercase())
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !name_ok {
+            return Err(SpuffError::Config(format!(
+                "Invalid persistent disk name '{}': use lowercase letters, digits and hyphens, starting with a letter",
+                self.name
+            )));
+        }
+
+        if self.size == 0 {
+            return Err(SpuffError::Config(format!(
+                "Persistent disk '{}' needs a size in GB",
+                self.name
+            )));
+        }
+
+        self.validate_mount()
+    }
+
+    /// Check the mount path is absolute and safe to use in the bootstrap
+    pub fn validate_mount(&self) -> Result<()> {
+        let mount_ok = self.mount.starts_with('/')
+            && self.mount != "/"
+            && self
+                .mount
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "/._-".contains(c));
+        if !mount_ok {
+            return Err(SpuffError::Config(format!(
+                "Invalid mount path '{}' for persistent disk '{}': must be an absolute path other than /",
+                self.mount, self.name
+            )));
+        }
+
+        Ok(())
+    }
+}
+
 impl Default for ProjectConfig {
     fn default() -> Self {
         Self {
@@ -289,6 +363,7 @@ impl Default for ProjectConfig {
             hooks: HooksConfig::default(),
             ai_tools: AiToolsConfig::default(),
             volumes: Vec::new(),
+            persistent_disks: Vec::new(),
             base_dir: None,
         }
     }
@@ -339,6 +414,17 @@ impl ProjectConfig {
         // Resolve environment variables in env values
         config.resolve_env_vars();
 
+        config.validate_persistent_disks()?;
+
+        if let Some(max_lifetime) = &config.resources.max_lifetime {
+            if crate::config::parse_duration(max_lifetime).is_none() {
+                return Err(SpuffError::Config(format!(
+                    "Invalid resources.max_lifetime '{}'. Use format like '8h', '1d' or '0'",
+                    max_lifetime
+                )));
+            }
+        }
+
         Ok(config)
     }
 
@@ -350,6 +436,38 @@ impl ProjectConfig {
         }
     }
 
+    /// Load project configuration from a specific directory (no parent lookup)
+    pub fn load_from_dir(dir: &Path) -> Result<Option<Self>> {
+        ["spuff.yaml", "spuff.yml"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+            .map(|path| Self::load(&path))
+            .transpose()
+    }
+
+    /// Check each persistent disk, and that names and mount paths are unique
+    fn validate_persistent_disks(&self) -> Result<()> {
+        for (i, disk) in self.persistent_disks.iter().enumerate() {
+            disk.validate()?;
+
+            let earlier = &self.persistent_disks[..i];
+            if earlier.iter().any(|d| d.name == disk.name) {
+                return Err(SpuffError::Config(format!(
+                    "Duplicate persistent disk name '{}'",
+                    disk.name
+                )));
+            }
+            if earlier.iter().any(|d| d.mount == disk.mount) {
+                return Err(SpuffError::Config(format!(
+                    "Two persistent disks mount at {}",
+                    disk.mount
+                )));
+            }
+        }
+        Ok(())
+    }
+
     /// Merge secrets from spuff.secrets.yaml
     fn merge_secrets(&mut self, path: &Path) -> Result<()> {
         let content = std::fs::read_to_string(path)
@@ -519,6 +637,7 @@ ports:
 resources:
   size: s-4vcpu-8gb
   region: nyc1
+  max_lifetime: 8h
 bundles:
   - go
 "#;
@@ -526,6 +645,7 @@ bundles:
         let config: ProjectConfig = serde_yaml::from_str(yaml).unwrap();
         assert_eq!(config.resources.size, Some("s-4vcpu-8gb".to_string()));
         assert_eq!(config.resources.region, Some("nyc1".to_string()));
+        assert_eq!(config.resources.max_lifetime, Some("8h".to_string()));
     }
 
     #[test]
@@ -782,6 +902,48 @@ volumes:
         assert!(vol2.options.compression);
     }
 
+    #[test]
+    fn test_parse_persistent_disks() {
+        let yaml = r#"
+persistent_disks:
+  - name: pgdata
+    size: 20
+    mount: /var/lib/postgresql
+"#;
+
+        let config: ProjectConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.persistent_disks.len(), 1);
+
+        let disk = &config.persistent_disks[0];
+        assert_eq!(disk.size, 20);
+        assert_eq!(disk.volume_name(), "spuff-pgdata");
+        assert!(disk.validate().is_ok());
+        assert!(config.validate_persistent_disks().is_ok());
+    }
+
+    #[test]
+    fn test_persistent_disk_validation() {
+        let disk = |name: &str, size: u64, mount: &str| PersistentDisk {
+            name: name.to_string(),
+            size,
+            mount: mount.to_string(),
+        };
+
+        assert!(disk("PgData", 20, "/data").validate().is_err());
+        assert!(disk("1data", 20, "/data").validate().is_err());
+        assert!(disk("data", 0, "/data").validate().is_err());
+        assert!(disk("data", 20, "data").validate().is_err());
+        assert!(disk("data", 20, "/").validate().is_err());
+        assert!(disk("data", 20, "/mnt/my data").validate().is_err());
+        assert!(disk("data", 20, "/mnt/$(id)").validate().is_err());
+
+        let config = ProjectConfig {
+            persistent_disks: vec![disk("a", 10, "/data"), disk("b", 10, "/data")],
+            ..Default::default()
+        };
+        assert!(config.validate_persistent_disks().is_err());
+    }
+
     #[test]
     fn test_default_config_has_empty_volumes() {
         let config = ProjectConfig::default();
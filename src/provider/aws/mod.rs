@@ -0,0 +1,1797 @@
+//! AWS EC2 provider implementation.
+//!
+//! This module implements the Provider trait on top of the EC2 query API.
+//! Requests are signed with SigV4 (see [`sigv4`]) and responses are parsed
+//! from XML, so the provider does not depend on the AWS SDK.
+//!
+//! Snapshots are AMIs created with `CreateImage`; deleting a snapshot also
+//! removes the EBS snapshots backing the AMI so nothing is left behind.
+//! Spot instances are opt-in via `aws.spot: true` in the config file.
+
+pub mod sigv4;
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::config::{ImageSpec, InstanceRequest, ProviderTimeouts, ProviderType};
+use super::error::{ProviderError, ProviderResult};
+use super::registry::ProviderFactory;
+use super::{InstanceStatus, Provider, ProviderInstance, Snapshot};
+
+pub use sigv4::AwsCredentials;
+
+const EC2_API_VERSION: &str = "2016-11-15";
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// Name of the security group spuff creates when none is configured.
+const SPUFF_SECURITY_GROUP: &str = "spuff";
+
+/// Echoes the caller's public IP, used to scope the default SSH ingress rule.
+const CHECKIP_URL: &str = "https://checkip.amazonaws.com";
+
+/// AMI owner account for official Ubuntu images (Canonical).
+const UBUNTU_OWNER: &str = "099720109477";
+
+/// AMI owner account for official Debian images.
+const DEBIAN_OWNER: &str = "136693071363";
+
+/// AWS-specific settings (the `aws:` section of `~/.spuff/config.yaml`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AwsConfig {
+    /// Secret access key. Falls back to `AWS_SECRET_ACCESS_KEY` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_access_key: Option<String>,
+
+    /// Launch instances on the spot market instead of on-demand.
+    #[serde(default)]
+    pub spot: bool,
+
+    /// Maximum hourly price for spot instances (defaults to the on-demand price).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spot_max_price: Option<String>,
+
+    /// Subnet to launch into. Uses the default VPC when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subnet_id: Option<String>,
+
+    /// Security groups to attach. When empty, spuff creates (or reuses)
+    /// a `spuff` group that allows inbound SSH.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub security_group_ids: Vec<String>,
+
+    /// CIDR allowed to reach SSH through the `spuff` group. Defaults to
+    /// the caller's public IP when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_ingress_cidr: Option<String>,
+}
+
+impl AwsConfig {
+    /// Resolve credentials from the access key ID and this config.
+    ///
+    /// The secret comes from the config file or `AWS_SECRET_ACCESS_KEY`,
+    /// and `AWS_SESSION_TOKEN` is picked up for temporary credentials.
+    pub fn credentials(&self, access_key_id: &str) -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: access_key_id.to_string(),
+            secret_access_key: self
+                .secret_access_key
+                .clone()
+                .or_else(|| std::env::var("AWS_SECRET_ACCESS_KEY").ok())
+                .unwrap_or_default(),
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        }
+    }
+}
+
+/// AWS EC2 provider implementation.
+#[derive(Debug)]
+pub struct AwsProvider {
+    client: Client,
+    credentials: AwsCredentials,
+    region: String,
+    endpoint: String,
+    settings: AwsConfig,
+    timeouts: ProviderTimeouts,
+    checkip_url: String,
+}
+
+impl AwsProvider {
+    /// Create a new AWS provider for a region with default settings.
+    #[allow(dead_code)]
+    pub fn new(credentials: AwsCredentials, region: &str) -> ProviderResult<Self> {
+        Self::with_config(
+            credentials,
+            region,
+            &default_endpoint(region),
+            AwsConfig::default(),
+            ProviderTimeouts::default(),
+        )
+    }
+
+    /// Create a new provider with custom endpoint (for testing).
+    #[allow(dead_code)]
+    pub fn with_base_url(credentials: AwsCredentials, base_url: &str) -> ProviderResult<Self> {
+        Self::with_config(
+            credentials,
+            DEFAULT_REGION,
+            base_url,
+            AwsConfig::default(),
+            ProviderTimeouts::default(),
+        )
+    }
+
+    /// Create a new provider with full configuration.
+    pub fn with_config(
+        credentials: AwsCredentials,
+        region: &str,
+        endpoint: &str,
+        settings: AwsConfig,
+        timeouts: ProviderTimeouts,
+    ) -> ProviderResult<Self> {
+        if credentials.access_key_id.is_empty() || credentials.secret_access_key.is_empty() {
+            return Err(ProviderError::auth(
+                "aws",
+                "Credentials are required. Set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY \
+                 or configure via 'spuff init'",
+            ));
+        }
+
+        let client = Client::builder()
+            .timeout(timeouts.http_request)
+            .build()
+            .map_err(ProviderError::Network)?;
+
+        Ok(Self {
+            client,
+            credentials,
+            region: region.to_string(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            settings,
+            timeouts,
+            checkip_url: CHECKIP_URL.to_string(),
+        })
+    }
+
+    /// Call an EC2 query API action and parse the XML response.
+    async fn call<T: DeserializeOwned>(
+        &self,
+        action: &str,
+        params: Vec<(String, String)>,
+    ) -> ProviderResult<T> {
+        let mut params = params;
+        params.push(("Action".to_string(), action.to_string()));
+        params.push(("Version".to_string(), EC2_API_VERSION.to_string()));
+        params.sort();
+
+        let body = params
+            .iter()
+            .map(|(k, v)| format!("{}={}", sigv4::uri_encode(k), sigv4::uri_encode(v)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let url = reqwest::Url::parse(&self.endpoint).map_err(|e| {
+            ProviderError::invalid_config("endpoint", format!("{}: {}", self.endpoint, e))
+        })?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => {
+                return Err(ProviderError::invalid_config(
+                    "endpoint",
+                    format!("missing host in {}", self.endpoint),
+                ))
+            }
+        };
+
+        let content_type = "application/x-www-form-urlencoded; charset=utf-8";
+        let signed = sigv4::sign(
+            &sigv4::SignableRequest {
+                method: "POST",
+                host: &host,
+                path: "/",
+                query: "",
+                headers: &[("content-type", content_type)],
+                body: body.as_bytes(),
+            },
+            &self.credentials,
+            &self.region,
+            "ec2",
+            Utc::now(),
+        );
+
+        let mut request = self
+            .client
+            .post(format!("{}/", self.endpoint))
+            .header("Content-Type", content_type)
+            .body(body);
+        for (name, value) in signed {
+            request = request.header(name, value);
+        }
+
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        let text = response.text().await?;
+
+        if !(200..300).contains(&status) {
+            return Err(map_error(action, status, &text));
+        }
+
+        quick_xml::de::from_str(&text).map_err(|e| ProviderError::Other {
+            message: format!("Failed to parse {} response: {}", action, e),
+        })
+    }
+
+    /// Call a Describe* action and collect every page, following `nextToken`.
+    async fn call_pages<T: Paged>(
+        &self,
+        action: &str,
+        params: Vec<(String, String)>,
+    ) -> ProviderResult<Vec<T>> {
+        let mut pages = Vec::new();
+        let mut token: Option<String> = None;
+
+        loop {
+            let mut page_params = params.clone();
+            if let Some(token) = token.take() {
+                page_params.push(("NextToken".to_string(), token));
+            }
+
+            let page: T = self.call(action, page_params).await?;
+            token = page.next_token().map(str::to_string);
+            pages.push(page);
+
+            if token.is_none() {
+                return Ok(pages);
+            }
+        }
+    }
+
+    /// CIDR for the SSH ingress rule: the configured one, or the caller's
+    /// public IP as a single-host range.
+    async fn ssh_ingress_cidr(&self) -> ProviderResult<String> {
+        if let Some(cidr) = &self.settings.ssh_ingress_cidr {
+            return Ok(cidr.clone());
+        }
+
+        let text = self
+            .client
+            .get(&self.checkip_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let ip: IpAddr = text.trim().parse().map_err(|_| {
+            ProviderError::invalid_config(
+                "aws.ssh_ingress_cidr",
+                format!(
+                    "Could not determine your public IP (got '{}'); set it explicitly",
+                    text.trim()
+                ),
+            )
+        })?;
+
+        Ok(match ip {
+            IpAddr::V4(ip) => format!("{}/32", ip),
+            IpAddr::V6(ip) => format!("{}/128", ip),
+        })
+    }
+
+    /// Resolve ImageSpec to an AMI ID.
+    ///
+    /// Ubuntu and Debian versions are looked up among the official images
+    /// for the instance's architecture, picking the most recent one.
+    async fn resolve_image(&self, spec: &ImageSpec, size: &str) -> ProviderResult<String> {
+        let arch = architecture_for(size);
+        let (owner, pattern) = match spec {
+            ImageSpec::Custom(id) | ImageSpec::Snapshot(id) => return Ok(id.clone()),
+            ImageSpec::Ubuntu(version) => (
+                UBUNTU_OWNER,
+                format!("ubuntu/images/*/ubuntu-*-{}-{}-server-*", version, arch),
+            ),
+            ImageSpec::Debian(version) => (DEBIAN_OWNER, format!("debian-{}-{}-*", version, arch)),
+        };
+
+        let mut params = vec![("Owner.1".to_string(), owner.to_string())];
+        params.extend(filter(1, "name", &[pattern.as_str()]));
+        params.extend(filter(2, "state", &["available"]));
+
+        let pages: Vec<DescribeImagesResponse> = self.call_pages("DescribeImages", params).await?;
+        pages
+            .into_iter()
+            .flat_map(|page| page.images_set.item)
+            .max_by(|a, b| a.creation_date.cmp(&b.creation_date))
+            .map(|image| image.image_id)
+            .ok_or_else(|| {
+                ProviderError::invalid_config(
+                    "image",
+                    format!("No AMI found matching '{}' in {}", pattern, self.region),
+                )
+            })
+    }
+
+    /// Find or create the security group used for spuff instances.
+    ///
+    /// The group lives in the VPC instances are launched into. SSH from the
+    /// current CIDR is (re)authorized every time, so a reused group keeps
+    /// working after the caller's public IP changes.
+    async fn ensure_security_group(&self) -> ProviderResult<Vec<String>> {
+        if !self.settings.security_group_ids.is_empty() {
+            return Ok(self.settings.security_group_ids.clone());
+        }
+
+        let vpc_id = self.launch_vpc().await?;
+        let mut params = filter(1, "group-name", &[SPUFF_SECURITY_GROUP]);
+        params.extend(filter(2, "vpc-id", &[vpc_id.as_str()]));
+        let pages: Vec<DescribeSecurityGroupsResponse> =
+            self.call_pages("DescribeSecurityGroups", params).await?;
+        let existing = pages
+            .into_iter()
+            .flat_map(|page| page.security_group_info.item)
+            .next();
+
+        let cidr = self.ssh_ingress_cidr().await?;
+        let group_id = match existing {
+            Some(group) => group.group_id,
+            None => {
+                tracing::info!(
+                    "Creating '{}' security group in {}",
+                    SPUFF_SECURITY_GROUP,
+                    vpc_id
+                );
+                let mut params = vec![
+                    ("GroupName".to_string(), SPUFF_SECURITY_GROUP.to_string()),
+                    (
+                        "GroupDescription".to_string(),
+                        "SSH access for spuff dev environments".to_string(),
+                    ),
+                    ("VpcId".to_string(), vpc_id),
+                ];
+                params.extend(tag_specification(1, "security-group", &spuff_tags(None)));
+                let created: CreateSecurityGroupResponse =
+                    self.call("CreateSecurityGroup", params).await?;
+                created.group_id
+            }
+        };
+
+        let range = if cidr.contains(':') {
+            "IpPermissions.1.Ipv6Ranges.1.CidrIpv6"
+        } else {
+            "IpPermissions.1.IpRanges.1.CidrIp"
+        };
+        let result: ProviderResult<AckResponse> = self
+            .call(
+                "AuthorizeSecurityGroupIngress",
+                vec![
+                    ("GroupId".to_string(), group_id.clone()),
+                    ("IpPermissions.1.IpProtocol".to_string(), "tcp".to_string()),
+                    ("IpPermissions.1.FromPort".to_string(), "22".to_string()),
+                    ("IpPermissions.1.ToPort".to_string(), "22".to_string()),
+                    (range.to_string(), cidr.clone()),
+                ],
+            )
+            .await;
+        match result {
+            Ok(_) => tracing::info!(
+                "Allowing SSH from {} in the '{}' security group \
+                 (change with aws.ssh_ingress_cidr or aws.security_group_ids)",
+                cidr,
+                SPUFF_SECURITY_GROUP
+            ),
+            // Already allowed
+            Err(ProviderError::Api { message, .. })
+                if message.contains("InvalidPermission.Duplicate") => {}
+            Err(e) => return Err(e),
+        }
+
+        Ok(vec![group_id])
+    }
+
+    /// VPC instances are launched into: the configured subnet's, or the
+    /// region's default VPC.
+    async fn launch_vpc(&self) -> ProviderResult<String> {
+        let (action, params) = match &self.settings.subnet_id {
+            Some(subnet_id) => (
+                "DescribeSubnets",
+                vec![("SubnetId.1".to_string(), subnet_id.clone())],
+            ),
+            None => ("DescribeVpcs", filter(1, "is-default", &["true"])),
+        };
+
+        let data: VpcIdResponse = self.call(action, params).await?;
+        data.subnet_set
+            .item
+            .into_iter()
+            .chain(data.vpc_set.item)
+            .map(|item| item.vpc_id)
+            .next()
+            .ok_or_else(|| match &self.settings.subnet_id {
+                Some(subnet_id) => ProviderError::not_found("subnet", subnet_id),
+                None => ProviderError::invalid_config(
+                    "aws.subnet_id",
+                    format!(
+                        "{} has no default VPC; set a subnet to launch into",
+                        self.region
+                    ),
+                ),
+            })
+    }
+
+    async fn describe_image(&self, id: &str) -> ProviderResult<Option<ImageData>> {
+        let result: ProviderResult<DescribeImagesResponse> = self
+            .call(
+                "DescribeImages",
+                vec![("ImageId.1".to_string(), id.to_string())],
+            )
+            .await;
+
+        match result {
+            Ok(data) => Ok(data.images_set.item.into_iter().next()),
+            Err(ProviderError::NotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Wait for an AMI to become available.
+    async fn wait_for_image(&self, image_id: &str) -> ProviderResult<ImageData> {
+        let max_attempts = self.timeouts.action_complete_attempts();
+        let delay = self.timeouts.poll_interval;
+        let start = std::time::Instant::now();
+
+        for attempt in 0..max_attempts {
+            if let Some(image) = self.describe_image(image_id).await? {
+                match image.image_state.as_str() {
+                    "available" => return Ok(image),
+                    "failed" | "invalid" | "error" => {
+                        return Err(ProviderError::api(
+                            500,
+                            format!("Image {} is in state '{}'", image_id, image.image_state),
+                        ));
+                    }
+                    state => {
+                        tracing::debug!(
+                            "Image {} state: {} (attempt {}/{})",
+                            image_id,
+                            state,
+                            attempt + 1,
+                            max_attempts
+                        );
+                    }
+                }
+            }
+            tokio::time::sleep(delay).await;
+        }
+
+        Err(ProviderError::timeout(
+            format!("wait for image {}", image_id),
+            start.elapsed(),
+        ))
+    }
+}
+
+#[async_trait]
+impl Provider for AwsProvider {
+    fn name(&self) -> &'static str {
+        "aws"
+    }
+
+    async fn create_instance(&self, request: &InstanceRequest) -> ProviderResult<ProviderInstance> {
+        if !request.region.is_empty() && request.region != self.region {
+            return Err(ProviderError::invalid_config(
+                "region",
+                format!(
+                    "AWS provider is configured for {} but {} was requested. \
+                     Change the default with 'spuff config set region {}'",
+                    self.region, request.region, request.region
+                ),
+            ));
+        }
+
+        let image = self.resolve_image(&request.image, &request.size).await?;
+        let security_groups = self.ensure_security_group().await?;
+
+        let mut params = vec![
+            ("ImageId".to_string(), image),
+            ("InstanceType".to_string(), request.size.clone()),
+            ("MinCount".to_string(), "1".to_string()),
+            ("MaxCount".to_string(), "1".to_string()),
+        ];
+
+        if let Some(user_data) = request.user_data.as_deref().filter(|u| !u.is_empty()) {
+            params.push((
+                "UserData".to_string(),
+                base64::engine::general_purpose::STANDARD.encode(user_data),
+            ));
+        }
+
+        match &self.settings.subnet_id {
+            Some(subnet_id) => {
+                // A custom subnet needs an explicit interface to get a public IP
+                params.push((
+                    "NetworkInterface.1.DeviceIndex".to_string(),
+                    "0".to_string(),
+                ));
+                params.push(("NetworkInterface.1.SubnetId".to_string(), subnet_id.clone()));
+                params.push((
+                    "NetworkInterface.1.AssociatePublicIpAddress".to_string(),
+                    "true".to_string(),
+                ));
+                for (i, group) in security_groups.iter().enumerate() {
+                    params.push((
+                        format!("NetworkInterface.1.SecurityGroupId.{}", i + 1),
+                        group.clone(),
+                    ));
+                }
+            }
+            None => {
+                for (i, group) in security_groups.iter().enumerate() {
+                    params.push((format!("SecurityGroupId.{}", i + 1), group.clone()));
+                }
+            }
+        }
+
+        if self.settings.spot {
+            params.push((
+                "InstanceMarketOptions.MarketType".to_string(),
+                "spot".to_string(),
+            ));
+            params.push((
+                "InstanceMarketOptions.SpotOptions.SpotInstanceType".to_string(),
+                "one-time".to_string(),
+            ));
+            params.push((
+                "InstanceMarketOptions.SpotOptions.InstanceInterruptionBehavior".to_string(),
+                "terminate".to_string(),
+            ));
+            if let Some(price) = &self.settings.spot_max_price {
+                params.push((
+                    "InstanceMarketOptions.SpotOptions.MaxPrice".to_string(),
+                    price.clone(),
+                ));
+            }
+        }
+
+        let tags = spuff_tags(Some((&request.name, &request.labels)));
+        params.extend(tag_specification(1, "instance", &tags));
+        params.extend(tag_specification(2, "volume", &tags));
+
+        let data: RunInstancesResponse = self.call("RunInstances", params).await?;
+        data.instances_set
+            .item
+            .into_iter()
+            .next()
+            .map(|i| i.to_provider_instance(&self.region))
+            .ok_or_else(|| ProviderError::Other {
+                message: "RunInstances returned no instances".to_string(),
+            })
+    }
+
+    async fn destroy_instance(&self, id: &str) -> ProviderResult<()> {
+        let result: ProviderResult<AckResponse> = self
+            .call(
+                "TerminateInstances",
+                vec![("InstanceId.1".to_string(), id.to_string())],
+            )
+            .await;
+
+        match result {
+            // NotFound is OK - instance already gone
+            Ok(_) | Err(ProviderError::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn get_instance(&self, id: &str) -> ProviderResult<Option<ProviderInstance>> {
+        let result: ProviderResult<DescribeInstancesResponse> = self
+            .call(
+                "DescribeInstances",
+                vec![("InstanceId.1".to_string(), id.to_string())],
+            )
+            .await;
+
+        let data = match result {
+            Ok(data) => data,
+            Err(ProviderError::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        // Terminated instances stay visible for a while; treat them as gone
+        Ok(data
+            .instances()
+            .find(|i| i.instance_state.name != "terminated")
+            .map(|i| i.to_provider_instance(&self.region)))
+    }
+
+    async fn list_instances(&self) -> ProviderResult<Vec<ProviderInstance>> {
+        let mut params = filter(1, "tag:spuff", &["true"]);
+        params.extend(filter(
+            2,
+            "instance-state-name",
+            &["pending", "running", "stopping", "stopped"],
+        ));
+
+        let pages: Vec<DescribeInstancesResponse> =
+            self.call_pages("DescribeInstances", params).await?;
+        Ok(pages
+            .into_iter()
+            .flat_map(|page| page.instances())
+            .map(|i| i.to_provider_instance(&self.region))
+            .collect())
+    }
+
+    async fn wait_ready(&self, id: &str) -> ProviderResult<ProviderInstance> {
+        let max_attempts = self.timeouts.instance_ready_attempts();
+        let delay = self.timeouts.poll_interval;
+        let start = std::time::Instant::now();
+
+        for attempt in 0..max_attempts {
+            if let Some(instance) = self.get_instance(id).await? {
+                if instance.status == InstanceStatus::Active && !instance.ip.is_unspecified() {
+                    return Ok(instance);
+                }
+                tracing::debug!(
+                    "Instance {} status: {} ip: {} (attempt {}/{})",
+                    id,
+                    instance.status,
+                    instance.ip,
+                    attempt + 1,
+                    max_attempts
+                );
+            }
+            tokio::time::sleep(delay).await;
+        }
+
+        Err(ProviderError::timeout(
+            "wait for instance ready",
+            start.elapsed(),
+        ))
+    }
+
+    async fn create_snapshot(&self, instance_id: &str, name: &str) -> ProviderResult<Snapshot> {
+        let tags = spuff_tags(Some((name, &HashMap::new())));
+        let mut params = vec![
+            ("InstanceId".to_string(), instance_id.to_string()),
+            ("Name".to_string(), name.to_string()),
+            ("Description".to_string(), "Created by spuff".to_string()),
+        ];
+        params.extend(tag_specification(1, "image", &tags));
+        params.extend(tag_specification(2, "snapshot", &tags));
+
+        let created: CreateImageResponse = self.call("CreateImage", params).await?;
+        let image = self.wait_for_image(&created.image_id).await?;
+
+        Ok(image.to_snapshot())
+    }
+
+    async fn list_snapshots(&self) -> ProviderResult<Vec<Snapshot>> {
+        let mut params = vec![("Owner.1".to_string(), "self".to_string())];
+        params.extend(filter(1, "tag:spuff", &["true"]));
+
+        let pages: Vec<DescribeImagesResponse> = self.call_pages("DescribeImages", params).await?;
+
+        Ok(pages
+            .into_iter()
+            .flat_map(|page| page.images_set.item)
+            .map(|i| i.to_snapshot())
+            .filter(|s| s.name.starts_with("spuff"))
+            .collect())
+    }
+
+    async fn delete_snapshot(&self, id: &str) -> ProviderResult<()> {
+        // NotFound is OK - snapshot already gone
+        let Some(image) = self.describe_image(id).await? else {
+            return Ok(());
+        };
+
+        let result: ProviderResult<AckResponse> = self
+            .call(
+                "DeregisterImage",
+                vec![("ImageId".to_string(), id.to_string())],
+            )
+            .await;
+        match result {
+            Ok(_) | Err(ProviderError::NotFound { .. }) => {}
+            Err(e) => return Err(e),
+        }
+
+        // Deregistering an AMI keeps its EBS snapshots (and their cost)
+        for snapshot_id in image.backing_snapshot_ids() {
+            let result: ProviderResult<AckResponse> = self
+                .call(
+                    "DeleteSnapshot",
+                    vec![("SnapshotId".to_string(), snapshot_id.clone())],
+                )
+                .await;
+            match result {
+                Ok(_) | Err(ProviderError::NotFound { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_ssh_keys(&self) -> ProviderResult<Vec<String>> {
+        let data: DescribeKeyPairsResponse = self.call("DescribeKeyPairs", vec![]).await?;
+        Ok(data.key_set.item.into_iter().map(|k| k.key_name).collect())
+    }
+}
+
+/// Factory for creating AWS providers.
+///
+/// The default factory takes the region from `AWS_REGION`/`AWS_DEFAULT_REGION`;
+/// `create_provider` registers one built from `AppConfig` instead.
+#[derive(Debug, Clone)]
+pub struct AwsFactory {
+    region: String,
+    settings: AwsConfig,
+}
+
+impl AwsFactory {
+    /// Create a factory for a specific region and settings.
+    pub fn new(region: impl Into<String>, settings: AwsConfig) -> Self {
+        Self {
+            region: region.into(),
+            settings,
+        }
+    }
+}
+
+impl Default for AwsFactory {
+    fn default() -> Self {
+        let region = std::env::var("AWS_REGION")
+            .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|_| DEFAULT_REGION.to_string());
+        Self::new(region, AwsConfig::default())
+    }
+}
+
+impl ProviderFactory for AwsFactory {
+    fn provider_type(&self) -> ProviderType {
+        ProviderType::Aws
+    }
+
+    fn create(&self, token: &str, timeouts: ProviderTimeouts) -> ProviderResult<Box<dyn Provider>> {
+        Ok(Box::new(AwsProvider::with_config(
+            self.settings.credentials(token),
+            &self.region,
+            &default_endpoint(&self.region),
+            self.settings.clone(),
+            timeouts,
+        )?))
+    }
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+fn default_endpoint(region: &str) -> String {
+    format!("https://ec2.{}.amazonaws.com", region)
+}
+
+/// Map an EC2 error response to a ProviderError.
+fn map_error(action: &str, status: u16, body: &str) -> ProviderError {
+    let (code, message) = quick_xml::de::from_str::<ErrorResponse>(body)
+        .ok()
+        .and_then(|e| e.errors.error.into_iter().next())
+        .map(|e| (e.code, e.message))
+        .unwrap_or_else(|| (String::new(), body.to_string()));
+
+    match code.as_str() {
+        "AuthFailure"
+        | "UnauthorizedOperation"
+        | "InvalidClientTokenId"
+        | "SignatureDoesNotMatch"
+        | "OptInRequired" => ProviderError::auth("aws", message),
+        "RequestLimitExceeded" | "Throttling" => ProviderError::RateLimit {
+            retry_after: Some(Duration::from_secs(30)),
+        },
+        "InstanceLimitExceeded"
+        | "VcpuLimitExceeded"
+        | "MaxSpotInstanceCountExceeded"
+        | "InsufficientInstanceCapacity" => ProviderError::quota("instance", message),
+        "InvalidAMIID.Unavailable" => ProviderError::not_found("image", message),
+        c if c.ends_with(".NotFound") => {
+            ProviderError::not_found(c.trim_end_matches(".NotFound"), message)
+        }
+        "InvalidParameterValue"
+        | "InvalidParameterCombination"
+        | "MissingParameter"
+        | "InvalidParameter" => ProviderError::invalid_config("request", message),
+        "" if status == 401 || status == 403 => ProviderError::auth("aws", message),
+        _ => ProviderError::api(status, format!("{} failed: {} {}", action, code, message)),
+    }
+}
+
+/// Instance architecture for an instance type (Graviton families end in `g`).
+fn architecture_for(instance_type: &str) -> &'static str {
+    let family = instance_type.split('.').next().unwrap_or_default();
+    let suffix = family
+        .trim_start_matches(|c: char| c.is_ascii_alphabetic())
+        .trim_start_matches(|c: char| c.is_ascii_digit());
+    if suffix.starts_with('g') {
+        "arm64"
+    } else {
+        "amd64"
+    }
+}
+
+/// Build `Filter.N.Name` / `Filter.N.Value.M` query parameters.
+fn filter(index: usize, name: &str, values: &[&str]) -> Vec<(String, String)> {
+    let mut params = vec![(format!("Filter.{}.Name", index), name.to_string())];
+    for (i, value) in values.iter().enumerate() {
+        params.push((
+            format!("Filter.{}.Value.{}", index, i + 1),
+            value.to_string(),
+        ));
+    }
+    params
+}
+
+/// Build `TagSpecification.N` query parameters for a resource type.
+fn tag_specification(
+    index: usize,
+    resource_type: &str,
+    tags: &[(String, String)],
+) -> Vec<(String, String)> {
+    let mut params = vec![(
+        format!("TagSpecification.{}.ResourceType", index),
+        resource_type.to_string(),
+    )];
+    for (i, (key, value)) in tags.iter().enumerate() {
+        params.push((
+            format!("TagSpecification.{}.Tag.{}.Key", index, i + 1),
+            key.clone(),
+        ));
+        params.push((
+            format!("TagSpecification.{}.Tag.{}.Value", index, i + 1),
+            value.clone(),
+        ));
+    }
+    params
+}
+
+/// Tags applied to every spuff resource, plus the request labels and a
+/// `Name` tag when given.
+fn spuff_tags(named: Option<(&str, &HashMap<String, String>)>) -> Vec<(String, String)> {
+    let mut tags: Vec<(String, String)> = vec![
+        ("spuff".to_string(), "true".to_string()),
+        ("managed-by".to_string(), "spuff-cli".to_string()),
+    ];
+    if let Some((name, labels)) = named {
+        for (key, value) in labels {
+            if !tags.iter().any(|(k, _)| k == key) {
+                tags.push((key.clone(), value.clone()));
+            }
+        }
+        tags.push(("Name".to_string(), name.to_string()));
+    }
+    tags.sort();
+    tags
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+}
+
+// ============================================================================
+// API Response Types
+// ============================================================================
+
+/// EC2 wraps lists in `<fooSet><item>...</item></fooSet>`.
+#[derive(Debug, Deserialize)]
+struct ItemList<T> {
+    #[serde(default = "Vec::new")]
+    item: Vec<T>,
+}
+
+impl<T> Default for ItemList<T> {
+    fn default() -> Self {
+        Self { item: Vec::new() }
+    }
+}
+
+/// Describe* responses that may be split across pages.
+trait Paged: DeserializeOwned {
+    fn next_token(&self) -> Option<&str>;
+}
+
+/// Response for actions that only return `<return>true</return>`.
+#[derive(Debug, Deserialize)]
+struct AckResponse {}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RunInstancesResponse {
+    #[serde(default)]
+    instances_set: ItemList<InstanceData>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DescribeInstancesResponse {
+    #[serde(default)]
+    reservation_set: ItemList<Reservation>,
+    #[serde(default)]
+    next_token: Option<String>,
+}
+
+impl Paged for DescribeInstancesResponse {
+    fn next_token(&self) -> Option<&str> {
+        self.next_token.as_deref().filter(|t| !t.is_empty())
+    }
+}
+
+impl DescribeInstancesResponse {
+    fn instances(self) -> impl Iterator<Item = InstanceData> {
+        self.reservation_set
+            .item
+            .into_iter()
+            .flat_map(|r| r.instances_set.item)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Reservation {
+    #[serde(default)]
+    instances_set: ItemList<InstanceData>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstanceData {
+    instance_id: String,
+    instance_state: InstanceState,
+    #[serde(default)]
+    launch_time: Option<String>,
+    #[serde(default)]
+    ip_address: Option<String>,
+    #[serde(default)]
+    tag_set: ItemList<TagData>,
+    #[serde(default)]
+    instance_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagData {
+    key: String,
+    #[serde(default)]
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct InstanceState {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateImageResponse {
+    image_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DescribeImagesResponse {
+    #[serde(default)]
+    images_set: ItemList<ImageData>,
+    #[serde(default)]
+    next_token: Option<String>,
+}
+
+impl Paged for DescribeImagesResponse {
+    fn next_token(&self) -> Option<&str> {
+        self.next_token.as_deref().filter(|t| !t.is_empty())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageData {
+    image_id: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    creation_date: Option<String>,
+    #[serde(default)]
+    image_state: String,
+    #[serde(default)]
+    block_device_mapping: ItemList<BlockDeviceMapping>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockDeviceMapping {
+    #[serde(default)]
+    ebs: Option<EbsBlockDevice>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EbsBlockDevice {
+    #[serde(default)]
+    snapshot_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DescribeSecurityGroupsResponse {
+    #[serde(default)]
+    security_group_info: ItemList<SecurityGroupData>,
+    #[serde(default)]
+    next_token: Option<String>,
+}
+
+impl Paged for DescribeSecurityGroupsResponse {
+    fn next_token(&self) -> Option<&str> {
+        self.next_token.as_deref().filter(|t| !t.is_empty())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SecurityGroupData {
+    group_id: String,
+}
+
+/// `DescribeSubnets` or `DescribeVpcs`; only the VPC ID is needed.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VpcIdResponse {
+    #[serde(default)]
+    subnet_set: ItemList<VpcIdData>,
+    #[serde(default)]
+    vpc_set: ItemList<VpcIdData>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VpcIdData {
+    vpc_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateSecurityGroupResponse {
+    group_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DescribeKeyPairsResponse {
+    #[serde(default)]
+    key_set: ItemList<KeyPairData>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyPairData {
+    key_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ErrorResponse {
+    errors: ErrorList,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ErrorList {
+    #[serde(default)]
+    error: Vec<ErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ErrorDetail {
+    code: String,
+    message: String,
+}
+
+impl InstanceData {
+    fn to_provider_instance(&self, region: &str) -> ProviderInstance {
+        let ip: IpAddr = self
+            .ip_address
+            .as_deref()
+            .and_then(|ip| ip.parse().ok())
+            .unwrap_or_else(|| "0.0.0.0".parse().unwrap());
+
+        let created_at = self
+            .launch_time
+            .as_deref()
+            .and_then(parse_timestamp)
+            .unwrap_or_else(Utc::now);
+
+        let status = match self.instance_state.name.as_str() {
+            "pending" => InstanceStatus::New,
+            "running" => InstanceStatus::Active,
+            "stopping" | "stopped" => InstanceStatus::Off,
+            "shutting-down" | "terminated" => InstanceStatus::Archive,
+            s => InstanceStatus::Unknown(s.to_string()),
+        };
+
+        let name = self
+            .tag_set
+            .item
+            .iter()
+            .find(|tag| tag.key == "Name")
+            .map(|tag| tag.value.clone())
+            .unwrap_or_else(|| self.instance_id.clone());
+
+        ProviderInstance {
+            id: self.instance_id.clone(),
+            name,
+            region: Some(region.to_string()),
+            size: self.instance_type.clone(),
+            ip,
+            status,
+            created_at,
+        }
+    }
+}
+
+impl ImageData {
+    fn to_snapshot(&self) -> Snapshot {
+        Snapshot {
+            id: self.image_id.clone(),
+            name: self.name.clone().unwrap_or_default(),
+            created_at: self.creation_date.as_deref().and_then(parse_timestamp),
+        }
+    }
+
+    fn backing_snapshot_ids(&self) -> Vec<String> {
+        self.block_device_mapping
+            .item
+            .iter()
+            .filter_map(|m| m.ebs.as_ref().and_then(|e| e.snapshot_id.clone()))
+            .collect()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_string_contains, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn test_credentials() -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: "AKIDTEST".to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: None,
+        }
+    }
+
+    fn xml(body: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_raw(body.to_string(), "text/xml")
+    }
+
+    fn error_xml(status: u16, code: &str, message: &str) -> ResponseTemplate {
+        ResponseTemplate::new(status).set_body_raw(
+            format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                 <Response><Errors><Error><Code>{}</Code><Message>{}</Message></Error></Errors>\
+                 <RequestID>req-1</RequestID></Response>",
+                code, message
+            ),
+            "text/xml",
+        )
+    }
+
+    async fn mount_default_vpc(mock_server: &MockServer) {
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeVpcs"))
+            .respond_with(xml(
+                "<DescribeVpcsResponse><vpcSet><item><vpcId>vpc-default</vpcId></item></vpcSet></DescribeVpcsResponse>",
+            ))
+            .mount(mock_server)
+            .await;
+    }
+
+    const INSTANCE_XML: &str = r#"
+        <DescribeInstancesResponse xmlns="http://ec2.amazonaws.com/doc/2016-11-15/">
+            <requestId>req-1</requestId>
+            <reservationSet>
+                <item>
+                    <reservationId>r-1</reservationId>
+                    <instancesSet>
+                        <item>
+                            <instanceId>i-0abc</instanceId>
+                            <instanceState><code>16</code><name>running</name></instanceState>
+                            <launchTime>2024-01-01T00:00:00.000Z</launchTime>
+                            <ipAddress>3.4.5.6</ipAddress>
+                            <tagSet><item><key>spuff</key><value>true</value></item></tagSet>
+                        </item>
+                    </instancesSet>
+                </item>
+            </reservationSet>
+        </DescribeInstancesResponse>"#;
+
+    #[test]
+    fn test_provider_requires_credentials() {
+        let mut credentials = test_credentials();
+        credentials.secret_access_key.clear();
+
+        let result = AwsProvider::new(credentials, "us-east-1");
+        assert!(matches!(
+            result.unwrap_err(),
+            ProviderError::Authentication { .. }
+        ));
+    }
+
+    #[test]
+    fn test_provider_creates_with_credentials() {
+        assert!(AwsProvider::new(test_credentials(), "eu-west-1").is_ok());
+    }
+
+    #[test]
+    fn test_architecture_for_instance_type() {
+        assert_eq!(architecture_for("t3.medium"), "amd64");
+        assert_eq!(architecture_for("t4g.small"), "arm64");
+        assert_eq!(architecture_for("c7gn.large"), "arm64");
+        assert_eq!(architecture_for("g4dn.xlarge"), "amd64");
+        assert_eq!(architecture_for("m7i.large"), "amd64");
+    }
+
+    #[test]
+    fn test_spuff_tags_include_labels_and_name() {
+        let mut labels = HashMap::new();
+        labels.insert("spuff".to_string(), "true".to_string());
+        labels.insert("project".to_string(), "api".to_string());
+
+        let tags = spuff_tags(Some(("spuff-abc", &labels)));
+
+        assert!(tags.contains(&("Name".to_string(), "spuff-abc".to_string())));
+        assert!(tags.contains(&("project".to_string(), "api".to_string())));
+        assert_eq!(tags.iter().filter(|(k, _)| k == "spuff").count(), 1);
+    }
+
+    #[test]
+    fn test_map_error_codes() {
+        let body = |code: &str| {
+            format!(
+                "<Response><Errors><Error><Code>{}</Code><Message>m</Message></Error></Errors></Response>",
+                code
+            )
+        };
+
+        assert!(matches!(
+            map_error("X", 401, &body("AuthFailure")),
+            ProviderError::Authentication { .. }
+        ));
+        assert!(matches!(
+            map_error("X", 400, &body("InvalidInstanceID.NotFound")),
+            ProviderError::NotFound { .. }
+        ));
+        assert!(matches!(
+            map_error("X", 400, &body("VcpuLimitExceeded")),
+            ProviderError::QuotaExceeded { .. }
+        ));
+        assert!(matches!(
+            map_error("X", 503, &body("RequestLimitExceeded")),
+            ProviderError::RateLimit { .. }
+        ));
+        assert!(matches!(
+            map_error("X", 500, "not xml"),
+            ProviderError::Api { status: 500, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_create_instance_success() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeImages"))
+            .and(header_exists("authorization"))
+            .respond_with(xml(
+                r#"<DescribeImagesResponse><imagesSet>
+                    <item><imageId>ami-old</imageId><creationDate>2024-01-01T00:00:00.000Z</creationDate><imageState>available</imageState></item>
+                    <item><imageId>ami-new</imageId><creationDate>2024-06-01T00:00:00.000Z</creationDate><imageState>available</imageState></item>
+                </imagesSet></DescribeImagesResponse>"#,
+            ))
+            .mount(&mock_server)
+            .await;
+
+        mount_default_vpc(&mock_server).await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeSecurityGroups"))
+            .respond_with(xml(r#"<DescribeSecurityGroupsResponse><securityGroupInfo>
+                    <item><groupId>sg-123</groupId><groupName>spuff</groupName></item>
+                </securityGroupInfo></DescribeSecurityGroupsResponse>"#))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=AuthorizeSecurityGroupIngress"))
+            .respond_with(error_xml(
+                400,
+                "InvalidPermission.Duplicate",
+                "the specified rule already exists",
+            ))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=RunInstances"))
+            .and(body_string_contains("ImageId=ami-new"))
+            .and(body_string_contains("SecurityGroupId.1=sg-123"))
+            .and(body_string_contains(
+                "TagSpecification.1.ResourceType=instance",
+            ))
+            .respond_with(xml(r#"<RunInstancesResponse><instancesSet><item>
+                    <instanceId>i-0new</instanceId>
+                    <instanceState><code>0</code><name>pending</name></instanceState>
+                    <launchTime>2024-06-02T00:00:00.000Z</launchTime>
+                </item></instancesSet></RunInstancesResponse>"#))
+            .mount(&mock_server)
+            .await;
+
+        let mut provider =
+            AwsProvider::with_base_url(test_credentials(), &mock_server.uri()).unwrap();
+        provider.settings.ssh_ingress_cidr = Some("203.0.113.7/32".to_string());
+        let request = InstanceRequest::new("spuff-test", "us-east-1", "t3.medium")
+            .with_image(ImageSpec::ubuntu("24.04"))
+            .with_user_data("#cloud-config")
+            .with_label("spuff", "true");
+
+        let instance = provider.create_instance(&request).await.unwrap();
+        assert_eq!(instance.id, "i-0new");
+        assert_eq!(instance.status, InstanceStatus::New);
+        assert!(instance.ip.is_unspecified());
+    }
+
+    #[tokio::test]
+    async fn test_create_instance_spot_mode() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=RunInstances"))
+            .and(body_string_contains(
+                "InstanceMarketOptions.MarketType=spot",
+            ))
+            .and(body_string_contains(
+                "InstanceMarketOptions.SpotOptions.MaxPrice=0.05",
+            ))
+            .respond_with(xml(r#"<RunInstancesResponse><instancesSet><item>
+                    <instanceId>i-0spot</instanceId>
+                    <instanceState><name>pending</name></instanceState>
+                </item></instancesSet></RunInstancesResponse>"#))
+            .mount(&mock_server)
+            .await;
+
+        let settings = AwsConfig {
+            spot: true,
+            spot_max_price: Some("0.05".to_string()),
+            security_group_ids: vec!["sg-custom".to_string()],
+            ..Default::default()
+        };
+        let provider = AwsProvider::with_config(
+            test_credentials(),
+            "us-east-1",
+            &mock_server.uri(),
+            settings,
+            ProviderTimeouts::default(),
+        )
+        .unwrap();
+        let request = InstanceRequest::new("spuff-spot", "us-east-1", "t3.small")
+            .with_image(ImageSpec::custom("ami-custom"));
+
+        let instance = provider.create_instance(&request).await.unwrap();
+        assert_eq!(instance.id, "i-0spot");
+    }
+
+    #[tokio::test]
+    async fn test_create_instance_region_mismatch() {
+        let provider =
+            AwsProvider::with_base_url(test_credentials(), "http://127.0.0.1:1").unwrap();
+        let request = InstanceRequest::new("spuff-x", "eu-west-1", "t3.small");
+
+        let result = provider.create_instance(&request).await;
+        assert!(matches!(
+            result.unwrap_err(),
+            ProviderError::InvalidConfig { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_instance_success() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeInstances"))
+            .and(body_string_contains("InstanceId.1=i-0abc"))
+            .respond_with(xml(INSTANCE_XML))
+            .mount(&mock_server)
+            .await;
+
+        let provider = AwsProvider::with_base_url(test_credentials(), &mock_server.uri()).unwrap();
+        let instance = provider.get_instance("i-0abc").await.unwrap().unwrap();
+
+        assert_eq!(instance.id, "i-0abc");
+        assert_eq!(instance.status, InstanceStatus::Active);
+        assert_eq!(instance.ip.to_string(), "3.4.5.6");
+    }
+
+    #[tokio::test]
+    async fn test_get_instance_not_found() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeInstances"))
+            .respond_with(error_xml(
+                400,
+                "InvalidInstanceID.NotFound",
+                "The instance ID 'i-0gone' does not exist",
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let provider = AwsProvider::with_base_url(test_credentials(), &mock_server.uri()).unwrap();
+        assert!(provider.get_instance("i-0gone").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_destroy_instance_already_gone() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=TerminateInstances"))
+            .respond_with(error_xml(400, "InvalidInstanceID.NotFound", "gone"))
+            .mount(&mock_server)
+            .await;
+
+        let provider = AwsProvider::with_base_url(test_credentials(), &mock_server.uri()).unwrap();
+        assert!(provider.destroy_instance("i-0gone").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_destroy_instance_auth_error() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=TerminateInstances"))
+            .respond_with(error_xml(401, "AuthFailure", "bad credentials"))
+            .mount(&mock_server)
+            .await;
+
+        let provider = AwsProvider::with_base_url(test_credentials(), &mock_server.uri()).unwrap();
+        assert!(matches!(
+            provider.destroy_instance("i-0abc").await.unwrap_err(),
+            ProviderError::Authentication { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_instances_filters_by_tag() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeInstances"))
+            .and(body_string_contains("Filter.1.Name=tag%3Aspuff"))
+            .respond_with(xml(INSTANCE_XML))
+            .mount(&mock_server)
+            .await;
+
+        let provider = AwsProvider::with_base_url(test_credentials(), &mock_server.uri()).unwrap();
+        let instances = provider.list_instances().await.unwrap();
+
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].id, "i-0abc");
+    }
+
+    #[tokio::test]
+    async fn test_list_instances_follows_next_token() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeInstances"))
+            .respond_with(xml(&INSTANCE_XML.replace(
+                "</reservationSet>",
+                "</reservationSet><nextToken>tok-2</nextToken>",
+            )))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeInstances"))
+            .and(body_string_contains("NextToken=tok-2"))
+            .respond_with(xml(&INSTANCE_XML.replace("i-0abc", "i-0def")))
+            .with_priority(1)
+            .mount(&mock_server)
+            .await;
+
+        let provider = AwsProvider::with_base_url(test_credentials(), &mock_server.uri()).unwrap();
+        let instances = provider.list_instances().await.unwrap();
+
+        let ids: Vec<_> = instances.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, vec!["i-0abc", "i-0def"]);
+    }
+
+    #[tokio::test]
+    async fn test_security_group_allows_ssh_from_caller_ip() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/checkip"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("203.0.113.7\n"))
+            .mount(&mock_server)
+            .await;
+
+        mount_default_vpc(&mock_server).await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeSecurityGroups"))
+            .and(body_string_contains("Filter.2.Value.1=vpc-default"))
+            .respond_with(xml(
+                "<DescribeSecurityGroupsResponse><securityGroupInfo/></DescribeSecurityGroupsResponse>",
+            ))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=CreateSecurityGroup"))
+            .and(body_string_contains("VpcId=vpc-default"))
+            .respond_with(xml(
+                "<CreateSecurityGroupResponse><groupId>sg-new</groupId></CreateSecurityGroupResponse>",
+            ))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=AuthorizeSecurityGroupIngress"))
+            .and(body_string_contains(
+                "IpPermissions.1.IpRanges.1.CidrIp=203.0.113.7%2F32",
+            ))
+            .respond_with(xml(
+                "<AuthorizeSecurityGroupIngressResponse><return>true</return></AuthorizeSecurityGroupIngressResponse>",
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut provider =
+            AwsProvider::with_base_url(test_credentials(), &mock_server.uri()).unwrap();
+        provider.checkip_url = format!("{}/checkip", mock_server.uri());
+
+        let groups = provider.ensure_security_group().await.unwrap();
+        assert_eq!(groups, vec!["sg-new".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_reused_security_group_allows_current_ip_in_subnet_vpc() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeSubnets"))
+            .and(body_string_contains("SubnetId.1=subnet-0abc"))
+            .respond_with(xml(
+                "<DescribeSubnetsResponse><subnetSet><item><vpcId>vpc-custom</vpcId></item></subnetSet></DescribeSubnetsResponse>",
+            ))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeSecurityGroups"))
+            .and(body_string_contains("Filter.2.Value.1=vpc-custom"))
+            .respond_with(xml(r#"<DescribeSecurityGroupsResponse><securityGroupInfo>
+                    <item><groupId>sg-custom</groupId></item>
+                </securityGroupInfo></DescribeSecurityGroupsResponse>"#))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=CreateSecurityGroup"))
+            .respond_with(xml(
+                "<CreateSecurityGroupResponse><groupId>sg-new</groupId></CreateSecurityGroupResponse>",
+            ))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=AuthorizeSecurityGroupIngress"))
+            .and(body_string_contains("GroupId=sg-custom"))
+            .and(body_string_contains(
+                "IpPermissions.1.IpRanges.1.CidrIp=198.51.100.9%2F32",
+            ))
+            .respond_with(xml(
+                "<AuthorizeSecurityGroupIngressResponse><return>true</return></AuthorizeSecurityGroupIngressResponse>",
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let settings = AwsConfig {
+            subnet_id: Some("subnet-0abc".to_string()),
+            ssh_ingress_cidr: Some("198.51.100.9/32".to_string()),
+            ..Default::default()
+        };
+        let provider = AwsProvider::with_config(
+            test_credentials(),
+            "us-east-1",
+            &mock_server.uri(),
+            settings,
+            ProviderTimeouts::default(),
+        )
+        .unwrap();
+
+        let groups = provider.ensure_security_group().await.unwrap();
+        assert_eq!(groups, vec!["sg-custom".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_ssh_ingress_cidr_from_config() {
+        let settings = AwsConfig {
+            ssh_ingress_cidr: Some("10.0.0.0/8".to_string()),
+            ..Default::default()
+        };
+        let provider = AwsProvider::with_config(
+            test_credentials(),
+            "us-east-1",
+            "http://127.0.0.1:1",
+            settings,
+            ProviderTimeouts::default(),
+        )
+        .unwrap();
+
+        assert_eq!(provider.ssh_ingress_cidr().await.unwrap(), "10.0.0.0/8");
+    }
+
+    #[tokio::test]
+    async fn test_create_snapshot() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=CreateImage"))
+            .and(body_string_contains("InstanceId=i-0abc"))
+            .and(body_string_contains(
+                "TagSpecification.1.ResourceType=image",
+            ))
+            .respond_with(xml(
+                "<CreateImageResponse><imageId>ami-snap</imageId></CreateImageResponse>",
+            ))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeImages"))
+            .and(body_string_contains("ImageId.1=ami-snap"))
+            .respond_with(xml(r#"<DescribeImagesResponse><imagesSet><item>
+                    <imageId>ami-snap</imageId>
+                    <name>spuff-backup</name>
+                    <creationDate>2024-01-01T00:00:00.000Z</creationDate>
+                    <imageState>available</imageState>
+                </item></imagesSet></DescribeImagesResponse>"#))
+            .mount(&mock_server)
+            .await;
+
+        let provider = AwsProvider::with_base_url(test_credentials(), &mock_server.uri()).unwrap();
+        let snapshot = provider
+            .create_snapshot("i-0abc", "spuff-backup")
+            .await
+            .unwrap();
+
+        assert_eq!(snapshot.id, "ami-snap");
+        assert_eq!(snapshot.name, "spuff-backup");
+        assert!(snapshot.created_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_list_snapshots() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeImages"))
+            .and(body_string_contains("Owner.1=self"))
+            .respond_with(xml(
+                r#"<DescribeImagesResponse><imagesSet>
+                    <item><imageId>ami-1</imageId><name>spuff-one</name><imageState>available</imageState></item>
+                    <item><imageId>ami-2</imageId><name>other</name><imageState>available</imageState></item>
+                </imagesSet></DescribeImagesResponse>"#,
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let provider = AwsProvider::with_base_url(test_credentials(), &mock_server.uri()).unwrap();
+        let snapshots = provider.list_snapshots().await.unwrap();
+
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].id, "ami-1");
+    }
+
+    #[tokio::test]
+    async fn test_delete_snapshot_removes_backing_ebs_snapshots() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeImages"))
+            .respond_with(xml(
+                r#"<DescribeImagesResponse><imagesSet><item>
+                    <imageId>ami-1</imageId><name>spuff-one</name><imageState>available</imageState>
+                    <blockDeviceMapping>
+                        <item><deviceName>/dev/sda1</deviceName><ebs><snapshotId>snap-1</snapshotId></ebs></item>
+                    </blockDeviceMapping>
+                </item></imagesSet></DescribeImagesResponse>"#,
+            ))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DeregisterImage"))
+            .respond_with(xml(
+                "<DeregisterImageResponse><return>true</return></DeregisterImageResponse>",
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DeleteSnapshot"))
+            .and(body_string_contains("SnapshotId=snap-1"))
+            .respond_with(xml(
+                "<DeleteSnapshotResponse><return>true</return></DeleteSnapshotResponse>",
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let provider = AwsProvider::with_base_url(test_credentials(), &mock_server.uri()).unwrap();
+        assert!(provider.delete_snapshot("ami-1").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delete_snapshot_already_gone() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(body_string_contains("Action=DescribeImages"))
+            .respond_with(error_xml(400, "InvalidAMIID.NotFound", "gone"))
+            .mount(&mock_server)
+            .await;
+
+        let provider = AwsProvider::with_base_url(test_credentials(), &mock_server.uri()).unwrap();
+        assert!(provider.delete_snapshot("ami-gone").await.is_ok());
+    }
+
+    #[test]
+    fn test_factory_creates_provider() {
+        let factory = AwsFactory::new(
+            "eu-west-1",
+            AwsConfig {
+                secret_access_key: Some("secret".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(factory.provider_type(), ProviderType::Aws);
+        assert!(factory.is_implemented());
+
+        let result = factory.create("AKIDTEST", ProviderTimeouts::default());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_factory_empty_token_fails() {
+        let factory = AwsFactory::new("us-east-1", AwsConfig::default());
+        let result = factory.create("", ProviderTimeouts::default());
+        assert!(matches!(result, Err(ProviderError::Authentication { .. })));
+    }
+}
@@ -0,0 +1,230 @@
+//! AWS Signature Version 4 request signing.
+//!
+//! Implements just enough of SigV4 to sign EC2 query API requests without
+//! pulling in the AWS SDK. See
+//! <https://docs.aws.amazon.com/IAM/latest/UserGuide/reference_aws-signing.html>.
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// Static AWS credentials used for signing.
+#[derive(Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl std::fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"<redacted>")
+            .field(
+                "session_token",
+                &self.session_token.as_ref().map(|_| "<redacted>"),
+            )
+            .finish()
+    }
+}
+
+/// A request to be signed.
+///
+/// `query` must already be in canonical form (sorted, URI-encoded).
+pub struct SignableRequest<'a> {
+    pub method: &'a str,
+    pub host: &'a str,
+    pub path: &'a str,
+    pub query: &'a str,
+    pub headers: &'a [(&'a str, &'a str)],
+    pub body: &'a [u8],
+}
+
+/// Compute the headers that must be added to a request to sign it.
+///
+/// Returns `x-amz-date`, the optional `x-amz-security-token` and
+/// `authorization`, in that order.
+pub fn sign(
+    request: &SignableRequest<'_>,
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    now: DateTime<Utc>,
+) -> Vec<(String, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    let mut headers: Vec<(String, String)> = request
+        .headers
+        .iter()
+        .map(|(k, v)| (k.to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+    headers.push(("host".to_string(), request.host.to_string()));
+    headers.push(("x-amz-date".to_string(), amz_date.clone()));
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    headers.sort();
+
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(k, v)| format!("{}:{}\n", k, v))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(k, _)| k.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        request.method,
+        request.path,
+        request.query,
+        canonical_headers,
+        signed_headers,
+        hex::encode(Sha256::digest(request.body))
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}",
+        ALGORITHM,
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let k_date = hmac(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    let k_region = hmac(&k_date, region.as_bytes());
+    let k_service = hmac(&k_region, service.as_bytes());
+    let k_signing = hmac(&k_service, b"aws4_request");
+    let signature = hex::encode(hmac(&k_signing, string_to_sign.as_bytes()));
+
+    let authorization = format!(
+        "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+        ALGORITHM, credentials.access_key_id, scope, signed_headers, signature
+    );
+
+    let mut result = vec![("x-amz-date".to_string(), amz_date)];
+    if let Some(token) = &credentials.session_token {
+        result.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    result.push(("authorization".to_string(), authorization));
+    result
+}
+
+/// URI-encode a value as required by SigV4 (RFC 3986 unreserved set).
+pub fn uri_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn example_credentials() -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn test_sign_matches_aws_test_suite_get_vanilla() {
+        // From the official AWS SigV4 test suite ("get-vanilla").
+        let request = SignableRequest {
+            method: "GET",
+            host: "example.amazonaws.com",
+            path: "/",
+            query: "",
+            headers: &[],
+            body: b"",
+        };
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+
+        let headers = sign(
+            &request,
+            &example_credentials(),
+            "us-east-1",
+            "service",
+            now,
+        );
+
+        assert_eq!(
+            headers[0],
+            ("x-amz-date".to_string(), "20150830T123600Z".to_string())
+        );
+        assert_eq!(
+            headers[1].1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn test_sign_includes_session_token() {
+        let mut credentials = example_credentials();
+        credentials.session_token = Some("session".to_string());
+
+        let request = SignableRequest {
+            method: "POST",
+            host: "ec2.us-east-1.amazonaws.com",
+            path: "/",
+            query: "",
+            headers: &[("Content-Type", "application/x-www-form-urlencoded")],
+            body: b"Action=DescribeInstances&Version=2016-11-15",
+        };
+
+        let headers = sign(&request, &credentials, "us-east-1", "ec2", Utc::now());
+
+        assert_eq!(headers.len(), 3);
+        assert_eq!(
+            headers[1],
+            ("x-amz-security-token".to_string(), "session".to_string())
+        );
+        assert!(headers[2]
+            .1
+            .contains("SignedHeaders=content-type;host;x-amz-date;x-amz-security-token"));
+    }
+
+    #[test]
+    fn test_uri_encode() {
+        assert_eq!(uri_encode("abc-_.~"), "abc-_.~");
+        assert_eq!(uri_encode("a b/c=d"), "a%20b%2Fc%3Dd");
+        assert_eq!(uri_encode("tag:spuff"), "tag%3Aspuff");
+    }
+
+    #[test]
+    fn test_credentials_debug_redacts_secret() {
+        let debug = format!("{:?}", example_credentials());
+        assert!(debug.contains("AKIDEXAMPLE"));
+        assert!(!debug.contains("wJalrXUtnFEMI"));
+    }
+}
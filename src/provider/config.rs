@@ -36,6 +36,10 @@ pub struct InstanceRequest {
     /// Volume mounts (used by Docker provider for bind mounts)
     #[serde(default)]
     pub volumes: Vec<VolumeMount>,
+
+    /// Block volumes to attach at creation (see `Provider::create_volume`)
+    #[serde(default)]
+    pub disks: Vec<DiskAttachment>,
 }
 
 /// Volume mount configuration for Docker provider.
@@ -52,6 +56,16 @@ pub struct VolumeMount {
     pub read_only: bool,
 }
 
+/// Block volume attached to an instance when it is created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskAttachment {
+    /// Provider volume ID (the volume name for Docker)
+    pub volume_id: String,
+
+    /// Where the volume is mounted on the instance
+    pub mount_path: String,
+}
+
 impl InstanceRequest {
     /// Create a new instance request with required fields
     pub fn new(
@@ -67,6 +81,7 @@ impl InstanceRequest {
             user_data: None,
             labels: HashMap::new(),
             volumes: Vec::new(),
+            disks: Vec::new(),
         }
     }
 
@@ -100,6 +115,12 @@ impl InstanceRequest {
         self.volumes = volumes;
         self
     }
+
+    /// Attach block volumes at creation
+    pub fn with_disks(mut self, disks: Vec<DiskAttachment>) -> Self {
+        self.disks = disks;
+        self
+    }
 }
 
 /// Image specification that providers resolve to their specific format.
@@ -215,12 +236,21 @@ pub enum ProviderType {
     Hetzner,
     Aws,
     Docker,
+    Ssh,
+    Qemu,
 }
 
 impl ProviderType {
     /// Get all supported provider types
     pub fn all() -> &'static [ProviderType] {
-        &[Self::DigitalOcean, Self::Hetzner, Self::Aws, Self::Docker]
+        &[
+            Self::DigitalOcean,
+            Self::Hetzner,
+            Self::Aws,
+            Self::Docker,
+            Self::Ssh,
+            Self::Qemu,
+        ]
     }
 
     /// Get provider name as string
@@ -230,6 +260,8 @@ impl ProviderType {
             Self::Hetzner => "hetzner",
             Self::Aws => "aws",
             Self::Docker => "docker",
+            Self::Ssh => "ssh",
+            Self::Qemu => "qemu",
         }
     }
 
@@ -245,6 +277,8 @@ impl ProviderType {
             "hetzner" | "hcloud" => Some(Self::Hetzner),
             "aws" | "ec2" => Some(Self::Aws),
             "docker" | "local" => Some(Self::Docker),
+            "ssh" | "byoh" => Some(Self::Ssh),
+            "qemu" | "libvirt" | "kvm" => Some(Self::Qemu),
             _ => None,
         }
     }
@@ -257,12 +291,17 @@ impl ProviderType {
             Self::Hetzner => "HETZNER_TOKEN",
             Self::Aws => "AWS_ACCESS_KEY_ID",
             Self::Docker => "", // Docker doesn't require a token
+            Self::Ssh => "",    // Pool hosts are reached with SSH keys
+            Self::Qemu => "",   // Local VMs don't require a token
         }
     }
 
     /// Check if this provider is implemented
     pub fn is_implemented(&self) -> bool {
-        matches!(self, Self::DigitalOcean | Self::Docker)
+        matches!(
+            self,
+            Self::DigitalOcean | Self::Hetzner | Self::Aws | Self::Docker | Self::Ssh | Self::Qemu
+        )
     }
 }
 
@@ -296,9 +335,14 @@ mod tests {
             .with_image(ImageSpec::ubuntu("24.04"))
             .with_user_data("#cloud-config")
             .with_label("env", "dev")
-            .with_label("team", "platform");
+            .with_label("team", "platform")
+            .with_disks(vec![DiskAttachment {
+                volume_id: "vol-1".to_string(),
+                mount_path: "/data".to_string(),
+            }]);
 
         assert_eq!(req.name, "test");
+        assert_eq!(req.disks[0].mount_path, "/data");
         assert_eq!(req.region, "nyc1");
         assert_eq!(req.labels.get("env"), Some(&"dev".to_string()));
         assert_eq!(req.labels.get("team"), Some(&"platform".to_string()));
@@ -336,6 +380,10 @@ mod tests {
         assert_eq!(ProviderType::from_str("aws"), Some(ProviderType::Aws));
         assert_eq!(ProviderType::from_str("docker"), Some(ProviderType::Docker));
         assert_eq!(ProviderType::from_str("local"), Some(ProviderType::Docker));
+        assert_eq!(ProviderType::from_str("ssh"), Some(ProviderType::Ssh));
+        assert_eq!(ProviderType::from_str("byoh"), Some(ProviderType::Ssh));
+        assert_eq!(ProviderType::from_str("qemu"), Some(ProviderType::Qemu));
+        assert_eq!(ProviderType::from_str("libvirt"), Some(ProviderType::Qemu));
         assert_eq!(ProviderType::from_str("unknown"), None);
     }
 
@@ -348,13 +396,17 @@ mod tests {
         assert_eq!(ProviderType::Hetzner.token_env_var(), "HETZNER_TOKEN");
         assert_eq!(ProviderType::Aws.token_env_var(), "AWS_ACCESS_KEY_ID");
         assert_eq!(ProviderType::Docker.token_env_var(), "");
+        assert_eq!(ProviderType::Ssh.token_env_var(), "");
+        assert_eq!(ProviderType::Qemu.token_env_var(), "");
     }
 
     #[test]
     fn test_provider_type_is_implemented() {
         assert!(ProviderType::DigitalOcean.is_implemented());
-        assert!(!ProviderType::Hetzner.is_implemented());
-        assert!(!ProviderType::Aws.is_implemented());
+        assert!(ProviderType::Hetzner.is_implemented());
+        assert!(ProviderType::Aws.is_implemented());
         assert!(ProviderType::Docker.is_implemented());
+        assert!(ProviderType::Ssh.is_implemented());
+        assert!(ProviderType::Qemu.is_implemented());
     }
 }
@@ -1,7 +1,7 @@
 //! DigitalOcean provider implementation.
 //!
 //! This module implements the Provider trait for DigitalOcean's API.
-//! It handles droplet (instance) and snapshot management.
+//! It handles droplet (instance), snapshot and block storage volume management.
 
 use std::net::IpAddr;
 use std::time::Duration;
@@ -14,7 +14,7 @@ use serde::{Deserialize, Serialize};
 use super::config::{ImageSpec, InstanceRequest, ProviderTimeouts, ProviderType};
 use super::error::{ProviderError, ProviderResult};
 use super::registry::ProviderFactory;
-use super::{InstanceStatus, Provider, ProviderInstance, Snapshot};
+use super::{BlockVolume, InstanceStatus, Provider, ProviderInstance, Region, Size, Snapshot};
 
 const DEFAULT_API_BASE: &str = "https://api.digitalocean.com/v2";
 
@@ -120,6 +120,40 @@ impl DigitalOceanProvider {
         Ok(keys)
     }
 
+    /// Run a droplet power action (e.g. "shutdown", "power_on") to completion.
+    async fn power_action(&self, id: &str, action_type: &str) -> ProviderResult<()> {
+        self.droplet_action(id, serde_json::json!({ "type": action_type }))
+            .await
+    }
+
+    /// Run a droplet action to completion.
+    async fn droplet_action(&self, id: &str, action: serde_json::Value) -> ProviderResult<()> {
+        let action_type = action["type"].as_str().unwrap_or("act on").to_string();
+        let response = self
+            .client
+            .post(format!("{}/droplets/{}/actions", self.base_url, id))
+            .header("Authorization", self.auth_header())
+            .json(&action)
+            .send()
+            .await?;
+
+        if response.status().as_u16() == 404 {
+            return Err(ProviderError::not_found("droplet", id));
+        }
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ProviderError::api(
+                status,
+                format!("Failed to {} droplet: {}", action_type, body),
+            ));
+        }
+
+        let action: ActionResponse = response.json().await?;
+        self.wait_for_action(action.action.id).await
+    }
+
     /// Wait for an action to complete.
     async fn wait_for_action(&self, action_id: u64) -> ProviderResult<()> {
         let max_attempts = self.timeouts.action_complete_attempts();
@@ -163,6 +197,42 @@ impl DigitalOceanProvider {
             start.elapsed(),
         ))
     }
+
+    /// Run a volume action ("attach" or "detach") to completion.
+    async fn volume_action(
+        &self,
+        volume_id: &str,
+        instance_id: &str,
+        action_type: &str,
+    ) -> ProviderResult<()> {
+        let droplet_id: u64 = instance_id
+            .parse()
+            .map_err(|_| ProviderError::invalid_config("droplet_id", instance_id))?;
+
+        let response = self
+            .client
+            .post(format!("{}/volumes/{}/actions", self.base_url, volume_id))
+            .header("Authorization", self.auth_header())
+            .json(&serde_json::json!({ "type": action_type, "droplet_id": droplet_id }))
+            .send()
+            .await?;
+
+        if response.status().as_u16() == 404 {
+            return Err(ProviderError::not_found("volume", volume_id));
+        }
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ProviderError::api(
+                status,
+                format!("Failed to {} volume: {}", action_type, body),
+            ));
+        }
+
+        let action: ActionResponse = response.json().await?;
+        self.wait_for_action(action.action.id).await
+    }
 }
 
 #[async_trait]
@@ -187,6 +257,7 @@ impl Provider for DigitalOceanProvider {
             user_data: request.user_data.clone(),
             tags,
             monitoring: true,
+            volumes: request.disks.iter().map(|d| d.volume_id.clone()).collect(),
         };
 
         let response = self
@@ -217,6 +288,9 @@ impl Provider for DigitalOceanProvider {
 
         Ok(ProviderInstance {
             id: data.droplet.id.to_string(),
+            name: request.name.clone(),
+            region: Some(request.region.clone()),
+            size: Some(request.size.clone()),
             ip: "0.0.0.0".parse().unwrap(),
             status: InstanceStatus::New,
             created_at: Utc::now(),
@@ -425,6 +499,237 @@ impl Provider for DigitalOceanProvider {
     async fn get_ssh_keys(&self) -> ProviderResult<Vec<String>> {
         self.get_ssh_key_ids().await
     }
+
+    async fn stop_instance(&self, id: &str) -> ProviderResult<()> {
+        // Graceful ACPI shutdown; the droplet keeps its disk (and keeps billing)
+        self.power_action(id, "shutdown").await
+    }
+
+    async fn start_instance(&self, id: &str) -> ProviderResult<()> {
+        self.power_action(id, "power_on").await
+    }
+
+    fn supports_power_control(&self) -> bool {
+        true
+    }
+
+    async fn resize_instance(&self, id: &str, size: &str) -> ProviderResult<()> {
+        let droplet = self
+            .get_instance(id)
+            .await?
+            .ok_or_else(|| ProviderError::not_found("droplet", id))?;
+
+        // Droplets must be off to resize; try a clean shutdown first
+        if droplet.status != InstanceStatus::Off {
+            if let Err(e) = self.power_action(id, "shutdown").await {
+                if matches!(e, ProviderError::NotFound { .. }) {
+                    return Err(e);
+                }
+                tracing::warn!("Graceful shutdown failed, powering off: {}", e);
+                self.power_action(id, "power_off").await?;
+            }
+        }
+
+        // CPU and RAM only: growing the disk would rule out sizing back down
+        let resized = self
+            .droplet_action(
+                id,
+                serde_json::json!({ "type": "resize", "size": size, "disk": false }),
+            )
+            .await;
+
+        // Boot it again even if the resize was rejected
+        let powered_on = self.power_action(id, "power_on").await;
+        resized?;
+        powered_on
+    }
+
+    fn supports_resize(&self) -> bool {
+        true
+    }
+
+    async fn create_volume(
+        &self,
+        name: &str,
+        size_gb: u64,
+        region: &str,
+    ) -> ProviderResult<BlockVolume> {
+        let request = CreateVolumeRequest {
+            name: name.to_string(),
+            size_gigabytes: size_gb,
+            region: region.to_string(),
+            description: "spuff persistent disk".to_string(),
+            tags: vec!["spuff".to_string()],
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/volumes", self.base_url))
+            .header("Authorization", self.auth_header())
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+
+            return Err(match status {
+                401 => ProviderError::auth("digitalocean", "Invalid API token"),
+                403 => ProviderError::quota("volumes", body),
+                422 => ProviderError::invalid_config("volume", body),
+                _ => ProviderError::api(status, format!("Failed to create volume: {}", body)),
+            });
+        }
+
+        let data: VolumeResponse = response.json().await?;
+        Ok(data.volume.to_block_volume())
+    }
+
+    async fn attach_volume(&self, volume_id: &str, instance_id: &str) -> ProviderResult<()> {
+        self.volume_action(volume_id, instance_id, "attach").await
+    }
+
+    async fn detach_volume(&self, volume_id: &str, instance_id: &str) -> ProviderResult<()> {
+        self.volume_action(volume_id, instance_id, "detach").await
+    }
+
+    async fn list_volumes(&self) -> ProviderResult<Vec<BlockVolume>> {
+        let mut volumes = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let response = self
+                .client
+                .get(format!("{}/volumes", self.base_url))
+                .query(&[("per_page", "200"), ("page", &page.to_string())])
+                .header("Authorization", self.auth_header())
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                let status = response.status().as_u16();
+                let body = response.text().await.unwrap_or_default();
+                return Err(ProviderError::api(
+                    status,
+                    format!("Failed to list volumes: {}", body),
+                ));
+            }
+
+            let data: VolumesResponse = response.json().await?;
+            volumes.extend(
+                data.volumes
+                    .iter()
+                    .filter(|v| v.name.starts_with("spuff"))
+                    .map(VolumeData::to_block_volume),
+            );
+
+            if !data.links.has_next() {
+                return Ok(volumes);
+            }
+            page += 1;
+        }
+    }
+
+    async fn delete_volume(&self, id: &str) -> ProviderResult<()> {
+        let response = self
+            .client
+            .delete(format!("{}/volumes/{}", self.base_url, id))
+            .header("Authorization", self.auth_header())
+            .send()
+            .await?;
+
+        // 404 is OK - volume already gone
+        if !response.status().is_success() && response.status().as_u16() != 404 {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ProviderError::api(
+                status,
+                format!("Failed to delete volume: {}", body),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn volume_device(&self, volume: &BlockVolume) -> Option<String> {
+        // Stable symlink udev creates for every attached volume
+        Some(format!("/dev/disk/by-id/scsi-0DO_Volume_{}", volume.name))
+    }
+
+    fn supports_volumes(&self) -> bool {
+        true
+    }
+
+    async fn list_regions(&self) -> ProviderResult<Vec<Region>> {
+        let response = self
+            .client
+            .get(format!("{}/regions?per_page=200", self.base_url))
+            .header("Authorization", self.auth_header())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ProviderError::api(
+                status,
+                format!("Failed to list regions: {}", body),
+            ));
+        }
+
+        let data: RegionsResponse = response.json().await?;
+
+        Ok(data
+            .regions
+            .into_iter()
+            .map(|r| Region {
+                slug: r.slug,
+                name: r.name,
+                available: r.available,
+            })
+            .collect())
+    }
+
+    async fn list_sizes(&self) -> ProviderResult<Vec<Size>> {
+        let response = self
+            .client
+            .get(format!("{}/sizes?per_page=200", self.base_url))
+            .header("Authorization", self.auth_header())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ProviderError::api(
+                status,
+                format!("Failed to list sizes: {}", body),
+            ));
+        }
+
+        let data: SizesResponse = response.json().await?;
+
+        let mut sizes: Vec<Size> = data
+            .sizes
+            .into_iter()
+            .filter(|s| s.available)
+            .map(|s| Size {
+                slug: s.slug,
+                vcpus: s.vcpus,
+                memory_mb: s.memory,
+                disk_gb: s.disk,
+                price_hourly: s.price_hourly,
+                price_monthly: s.price_monthly,
+                currency: "USD".to_string(),
+                regions: s.regions,
+            })
+            .collect();
+        sizes.sort_by(|a, b| a.price_hourly.total_cmp(&b.price_hourly));
+
+        Ok(sizes)
+    }
 }
 
 /// Factory for creating DigitalOcean providers.
@@ -459,6 +764,8 @@ struct CreateDropletRequest {
     user_data: Option<String>,
     tags: Vec<String>,
     monitoring: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    volumes: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -475,9 +782,20 @@ struct DropletsResponse {
 #[derive(Debug, Deserialize)]
 struct DropletData {
     id: u64,
+    #[serde(default)]
+    name: String,
     status: String,
     created_at: String,
     networks: Networks,
+    #[serde(default)]
+    region: Option<RegionData>,
+    #[serde(default)]
+    size_slug: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegionData {
+    slug: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -506,6 +824,108 @@ struct SnapshotData {
     size_gigabytes: f64,
 }
 
+#[derive(Debug, Serialize)]
+struct CreateVolumeRequest {
+    name: String,
+    size_gigabytes: u64,
+    region: String,
+    description: String,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VolumeResponse {
+    volume: VolumeData,
+}
+
+#[derive(Debug, Deserialize)]
+struct VolumesResponse {
+    volumes: Vec<VolumeData>,
+    #[serde(default)]
+    links: Links,
+}
+
+/// Pagination links of a list response.
+#[derive(Debug, Default, Deserialize)]
+struct Links {
+    #[serde(default)]
+    pages: Option<PageLinks>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageLinks {
+    #[serde(default)]
+    next: Option<String>,
+}
+
+impl Links {
+    fn has_next(&self) -> bool {
+        self.pages.as_ref().is_some_and(|p| p.next.is_some())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VolumeData {
+    id: String,
+    name: String,
+    size_gigabytes: u64,
+    #[serde(default)]
+    region: Option<RegionData>,
+    #[serde(default)]
+    droplet_ids: Vec<u64>,
+    #[serde(default)]
+    created_at: Option<String>,
+}
+
+impl VolumeData {
+    fn to_block_volume(&self) -> BlockVolume {
+        BlockVolume {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            size_gb: self.size_gigabytes,
+            region: self.region.as_ref().map(|r| r.slug.clone()),
+            attached_to: self.droplet_ids.first().map(|id| id.to_string()),
+            created_at: self
+                .created_at
+                .as_deref()
+                .and_then(|c| DateTime::parse_from_rfc3339(c).ok())
+                .map(|dt| dt.with_timezone(&Utc)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RegionsResponse {
+    regions: Vec<RegionInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegionInfo {
+    slug: String,
+    name: String,
+    available: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SizesResponse {
+    sizes: Vec<SizeInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SizeInfo {
+    slug: String,
+    /// Memory in MB
+    memory: u64,
+    vcpus: u32,
+    /// Disk in GB
+    disk: u64,
+    price_monthly: f64,
+    price_hourly: f64,
+    #[serde(default)]
+    regions: Vec<String>,
+    available: bool,
+}
+
 #[derive(Debug, Deserialize)]
 struct ActionResponse {
     action: ActionData,
@@ -556,6 +976,9 @@ impl DropletData {
 
         ProviderInstance {
             id: self.id.to_string(),
+            name: self.name.clone(),
+            region: self.region.as_ref().map(|r| r.slug.clone()),
+            size: self.size_slug.clone(),
             ip,
             status,
             created_at,
@@ -578,7 +1001,8 @@ impl DropletData {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use wiremock::matchers::{header, method, path, path_regex};
+    use crate::provider::DiskAttachment;
+    use wiremock::matchers::{body_partial_json, header, method, path, path_regex, query_param};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     #[test]
@@ -648,9 +1072,12 @@ mod tests {
         for (status_str, expected_status) in test_cases {
             let droplet = DropletData {
                 id: 123,
+                name: "spuff-test".to_string(),
                 status: status_str.to_string(),
                 created_at: "2024-01-01T00:00:00Z".to_string(),
                 networks: Networks { v4: vec![] },
+                region: None,
+                size_slug: None,
             };
 
             let instance = droplet.to_provider_instance();
@@ -662,6 +1089,7 @@ mod tests {
     fn test_droplet_data_extracts_public_ip() {
         let droplet = DropletData {
             id: 456,
+            name: "spuff-test".to_string(),
             status: "active".to_string(),
             created_at: "2024-01-01T00:00:00Z".to_string(),
             networks: Networks {
@@ -676,6 +1104,8 @@ mod tests {
                     },
                 ],
             },
+            region: None,
+            size_slug: None,
         };
 
         let instance = droplet.to_provider_instance();
@@ -686,9 +1116,12 @@ mod tests {
     fn test_droplet_data_no_public_ip_returns_fallback() {
         let droplet = DropletData {
             id: 789,
+            name: "spuff-test".to_string(),
             status: "new".to_string(),
             created_at: "2024-01-01T00:00:00Z".to_string(),
             networks: Networks { v4: vec![] },
+            region: None,
+            size_slug: None,
         };
 
         let instance = droplet.to_provider_instance();
@@ -1015,6 +1448,419 @@ mod tests {
         assert!(result.unwrap().is_empty());
     }
 
+    #[tokio::test]
+    async fn test_stop_and_start_instance() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/droplets/12345/actions"))
+            .and(body_partial_json(serde_json::json!({"type": "shutdown"})))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "action": {"id": 1, "status": "in-progress"}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/droplets/12345/actions"))
+            .and(body_partial_json(serde_json::json!({"type": "power_on"})))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "action": {"id": 2, "status": "in-progress"}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path_regex("/actions/[12]"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "action": {"id": 1, "status": "completed"}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider =
+            DigitalOceanProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        assert!(provider.supports_power_control());
+        provider.stop_instance("12345").await.unwrap();
+        provider.start_instance("12345").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resize_instance() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/droplets/12345"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "droplet": {
+                    "id": 12345,
+                    "name": "spuff-test",
+                    "status": "active",
+                    "created_at": "2024-01-01T00:00:00Z",
+                    "networks": {"v4": [{"ip_address": "1.2.3.4", "type": "public"}]}
+                }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/droplets/12345/actions"))
+            .and(body_partial_json(serde_json::json!({"type": "shutdown"})))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "action": {"id": 1, "status": "in-progress"}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/droplets/12345/actions"))
+            .and(body_partial_json(serde_json::json!({
+                "type": "resize",
+                "size": "s-4vcpu-8gb",
+                "disk": false
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "action": {"id": 2, "status": "in-progress"}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/droplets/12345/actions"))
+            .and(body_partial_json(serde_json::json!({"type": "power_on"})))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "action": {"id": 3, "status": "in-progress"}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path_regex("/actions/[123]"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "action": {"id": 1, "status": "completed"}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider =
+            DigitalOceanProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        assert!(provider.supports_resize());
+        provider
+            .resize_instance("12345", "s-4vcpu-8gb")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resize_rejected_powers_back_on() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/droplets/12345"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "droplet": {
+                    "id": 12345,
+                    "name": "spuff-test",
+                    "status": "off",
+                    "created_at": "2024-01-01T00:00:00Z",
+                    "networks": {"v4": []}
+                }
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/droplets/12345/actions"))
+            .and(body_partial_json(serde_json::json!({"type": "resize"})))
+            .respond_with(ResponseTemplate::new(422).set_body_json(serde_json::json!({
+                "id": "unprocessable_entity",
+                "message": "invalid size"
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/droplets/12345/actions"))
+            .and(body_partial_json(serde_json::json!({"type": "power_on"})))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "action": {"id": 3, "status": "in-progress"}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/actions/3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "action": {"id": 3, "status": "completed"}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider =
+            DigitalOceanProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let err = provider.resize_instance("12345", "nope").await.unwrap_err();
+        assert!(matches!(err, ProviderError::Api { status: 422, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_list_regions_and_sizes() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/regions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "regions": [
+                    {"slug": "nyc1", "name": "New York 1", "available": true,
+                     "sizes": ["s-1vcpu-1gb"]},
+                    {"slug": "sfo1", "name": "San Francisco 1", "available": false,
+                     "sizes": []}
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/sizes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "sizes": [
+                    {"slug": "s-2vcpu-4gb", "memory": 4096, "vcpus": 2, "disk": 80,
+                     "price_monthly": 24.0, "price_hourly": 0.03571,
+                     "regions": ["nyc1", "fra1"], "available": true},
+                    {"slug": "s-1vcpu-1gb", "memory": 1024, "vcpus": 1, "disk": 25,
+                     "price_monthly": 6.0, "price_hourly": 0.00893,
+                     "regions": ["nyc1"], "available": true},
+                    {"slug": "s-old", "memory": 512, "vcpus": 1, "disk": 20,
+                     "price_monthly": 4.0, "price_hourly": 0.006,
+                     "regions": [], "available": false}
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider =
+            DigitalOceanProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+
+        let regions = provider.list_regions().await.unwrap();
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].name, "New York 1");
+        assert!(!regions[1].available);
+
+        let sizes = provider.list_sizes().await.unwrap();
+        let slugs: Vec<&str> = sizes.iter().map(|s| s.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["s-1vcpu-1gb", "s-2vcpu-4gb"]);
+        assert_eq!(sizes[1].memory_mb, 4096);
+        assert_eq!(sizes[1].currency, "USD");
+        assert!(sizes[1].available_in("fra1"));
+        assert!(!sizes[0].available_in("fra1"));
+    }
+
+    #[tokio::test]
+    async fn test_stop_instance_not_found() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/droplets/99999/actions"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+
+        let provider =
+            DigitalOceanProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let err = provider.stop_instance("99999").await.unwrap_err();
+        assert!(matches!(err, ProviderError::NotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_create_volume_and_attach_at_creation() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/volumes"))
+            .and(body_partial_json(serde_json::json!({
+                "name": "spuff-pgdata",
+                "size_gigabytes": 20,
+                "region": "nyc1"
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "volume": {
+                    "id": "vol-abc",
+                    "name": "spuff-pgdata",
+                    "size_gigabytes": 20,
+                    "region": {"slug": "nyc1"},
+                    "droplet_ids": [],
+                    "created_at": "2024-01-01T00:00:00Z"
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/account/keys"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"ssh_keys": []})),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/droplets"))
+            .and(body_partial_json(
+                serde_json::json!({"volumes": ["vol-abc"]}),
+            ))
+            .respond_with(ResponseTemplate::new(202).set_body_json(serde_json::json!({
+                "droplet": {
+                    "id": 12345,
+                    "status": "new",
+                    "created_at": "2024-01-01T00:00:00Z",
+                    "networks": {"v4": []}
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let provider =
+            DigitalOceanProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        assert!(provider.supports_volumes());
+
+        let volume = provider
+            .create_volume("spuff-pgdata", 20, "nyc1")
+            .await
+            .unwrap();
+        assert_eq!(volume.id, "vol-abc");
+        assert_eq!(volume.region.as_deref(), Some("nyc1"));
+        assert_eq!(volume.attached_to, None);
+        assert_eq!(
+            provider.volume_device(&volume).unwrap(),
+            "/dev/disk/by-id/scsi-0DO_Volume_spuff-pgdata"
+        );
+
+        let request =
+            InstanceRequest::new("test", "nyc1", "s-1vcpu-1gb").with_disks(vec![DiskAttachment {
+                volume_id: volume.id.clone(),
+                mount_path: "/var/lib/postgresql".to_string(),
+            }]);
+        provider.create_instance(&request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_volumes() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/volumes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "volumes": [
+                    {"id": "vol-1", "name": "spuff-pgdata", "size_gigabytes": 20,
+                     "region": {"slug": "nyc1"}, "droplet_ids": [12345]},
+                    {"id": "vol-2", "name": "other-volume", "size_gigabytes": 100,
+                     "droplet_ids": []}
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider =
+            DigitalOceanProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let volumes = provider.list_volumes().await.unwrap();
+
+        assert_eq!(volumes.len(), 1);
+        assert_eq!(volumes[0].name, "spuff-pgdata");
+        assert_eq!(volumes[0].size_gb, 20);
+        assert_eq!(volumes[0].attached_to.as_deref(), Some("12345"));
+    }
+
+    #[tokio::test]
+    async fn test_list_volumes_follows_pages() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/volumes"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "volumes": [{"id": "vol-1", "name": "spuff-a", "size_gigabytes": 10, "droplet_ids": []}],
+                "links": {"pages": {"next": "https://api.digitalocean.com/v2/volumes?page=2"}}
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/volumes"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "volumes": [{"id": "vol-2", "name": "spuff-b", "size_gigabytes": 10, "droplet_ids": []}],
+                "links": {"pages": {"prev": "https://api.digitalocean.com/v2/volumes?page=1"}}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider =
+            DigitalOceanProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let volumes = provider.list_volumes().await.unwrap();
+
+        let ids: Vec<_> = volumes.iter().map(|v| v.id.as_str()).collect();
+        assert_eq!(ids, vec!["vol-1", "vol-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_attach_volume() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/volumes/vol-1/actions"))
+            .and(body_partial_json(
+                serde_json::json!({"type": "attach", "droplet_id": 12345}),
+            ))
+            .respond_with(ResponseTemplate::new(202).set_body_json(serde_json::json!({
+                "action": {"id": 8, "status": "in-progress"}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/actions/8"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "action": {"id": 8, "status": "completed"}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider =
+            DigitalOceanProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        provider.attach_volume("vol-1", "12345").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_detach_and_delete_volume() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/volumes/vol-1/actions"))
+            .and(body_partial_json(
+                serde_json::json!({"type": "detach", "droplet_id": 12345}),
+            ))
+            .respond_with(ResponseTemplate::new(202).set_body_json(serde_json::json!({
+                "action": {"id": 7, "status": "in-progress"}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/actions/7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "action": {"id": 7, "status": "completed"}
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path_regex("/volumes/vol-[12]"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&mock_server)
+            .await;
+
+        let provider =
+            DigitalOceanProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        provider.detach_volume("vol-1", "12345").await.unwrap();
+        provider.delete_volume("vol-1").await.unwrap();
+
+        let err = provider.detach_volume("vol-1", "not-a-droplet").await;
+        assert!(matches!(err, Err(ProviderError::InvalidConfig { .. })));
+    }
+
     #[test]
     fn test_factory_creates_provider() {
         let factory = DigitalOceanFactory;
@@ -2,6 +2,7 @@
 //!
 //! This module implements the Provider trait for local Docker containers,
 //! enabling local development environments using the Docker API via bollard.
+//! Block volumes are Docker named volumes, mounted when a container is created.
 
 use std::collections::HashMap;
 
@@ -12,6 +13,7 @@ use bollard::container::{
 };
 use bollard::image::{CommitContainerOptions, CreateImageOptions, ListImagesOptions};
 use bollard::models::{HostConfig, PortBinding};
+use bollard::volume::{CreateVolumeOptions, ListVolumesOptions, RemoveVolumeOptions};
 use bollard::Docker;
 use chrono::{DateTime, Utc};
 use futures::TryStreamExt;
@@ -19,7 +21,7 @@ use futures::TryStreamExt;
 use super::config::{ImageSpec, InstanceRequest, ProviderTimeouts, ProviderType};
 use super::error::{ProviderError, ProviderResult};
 use super::registry::ProviderFactory;
-use super::{InstanceStatus, Provider, ProviderInstance, Snapshot};
+use super::{BlockVolume, InstanceStatus, Provider, ProviderInstance, Snapshot};
 
 /// Docker provider implementation using bollard.
 ///
@@ -96,6 +98,7 @@ impl DockerProvider {
     fn container_to_instance(
         &self,
         id: String,
+        name: Option<&str>,
         state: Option<&str>,
         created: Option<i64>,
     ) -> ProviderInstance {
@@ -111,8 +114,16 @@ impl DockerProvider {
             .and_then(|ts| DateTime::from_timestamp(ts, 0))
             .unwrap_or_else(Utc::now);
 
+        // Docker reports names with a leading slash
+        let name = name
+            .map(|n| n.trim_start_matches('/').to_string())
+            .unwrap_or_else(|| id.clone());
+
         ProviderInstance {
             id,
+            name,
+            region: None,
+            size: None,
             ip: "127.0.0.1".parse().unwrap(),
             status,
             created_at,
@@ -120,6 +131,44 @@ impl DockerProvider {
     }
 }
 
+/// Label holding the size a volume was requested with (Docker doesn't size volumes).
+const SIZE_LABEL: &str = "spuff.size_gb";
+
+impl DockerProvider {
+    /// Convert a named volume to a BlockVolume, given the containers using it.
+    fn volume_to_block_volume(
+        volume: bollard::models::Volume,
+        containers: &[bollard::models::ContainerSummary],
+    ) -> BlockVolume {
+        let attached_to = containers
+            .iter()
+            .find(|c| {
+                c.mounts
+                    .iter()
+                    .flatten()
+                    .any(|m| m.name.as_deref() == Some(volume.name.as_str()))
+            })
+            .and_then(|c| c.id.clone());
+
+        BlockVolume {
+            id: volume.name.clone(),
+            size_gb: volume
+                .labels
+                .get(SIZE_LABEL)
+                .and_then(|size| size.parse().ok())
+                .unwrap_or(0),
+            name: volume.name,
+            region: None,
+            attached_to,
+            created_at: volume
+                .created_at
+                .as_deref()
+                .and_then(|c| DateTime::parse_from_rfc3339(c).ok())
+                .map(|dt| dt.with_timezone(&Utc)),
+        }
+    }
+}
+
 #[async_trait]
 impl Provider for DockerProvider {
     fn name(&self) -> &'static str {
@@ -155,21 +204,22 @@ impl Provider for DockerProvider {
         let mut exposed_ports = HashMap::new();
         exposed_ports.insert("7575/tcp".to_string(), HashMap::new());
 
-        // Build bind mounts from volumes
-        let binds: Option<Vec<String>> = if request.volumes.is_empty() {
-            None
-        } else {
-            Some(
+        // Build bind mounts from volumes, plus named volumes for disks
+        let binds: Vec<String> = request
+            .volumes
+            .iter()
+            .map(|v| {
+                let mode = if v.read_only { "ro" } else { "rw" };
+                format!("{}:{}:{}", v.source, v.target, mode)
+            })
+            .chain(
                 request
-                    .volumes
+                    .disks
                     .iter()
-                    .map(|v| {
-                        let mode = if v.read_only { "ro" } else { "rw" };
-                        format!("{}:{}:{}", v.source, v.target, mode)
-                    })
-                    .collect(),
+                    .map(|d| format!("{}:{}:rw", d.volume_id, d.mount_path)),
             )
-        };
+            .collect();
+        let binds = (!binds.is_empty()).then_some(binds);
 
         // Create container config
         let config = Config {
@@ -214,6 +264,9 @@ impl Provider for DockerProvider {
 
         Ok(ProviderInstance {
             id: container.id,
+            name,
+            region: None,
+            size: None,
             ip: "127.0.0.1".parse().unwrap(),
             status: InstanceStatus::Active,
             created_at: Utc::now(),
@@ -268,6 +321,7 @@ impl Provider for DockerProvider {
 
                 Ok(Some(self.container_to_instance(
                     info.id.unwrap_or_else(|| id.to_string()),
+                    info.name.as_deref(),
                     state.as_deref(),
                     created,
                 )))
@@ -303,7 +357,13 @@ impl Provider for DockerProvider {
         Ok(containers
             .into_iter()
             .map(|c| {
-                self.container_to_instance(c.id.unwrap_or_default(), c.state.as_deref(), c.created)
+                let name = c.names.as_ref().and_then(|names| names.first());
+                self.container_to_instance(
+                    c.id.unwrap_or_default(),
+                    name.map(String::as_str),
+                    c.state.as_deref(),
+                    c.created,
+                )
             })
             .collect())
     }
@@ -398,6 +458,140 @@ impl Provider for DockerProvider {
     fn supports_snapshots(&self) -> bool {
         true
     }
+
+    async fn stop_instance(&self, id: &str) -> ProviderResult<()> {
+        self.client
+            .stop_container(id, Some(StopContainerOptions { t: 10 }))
+            .await
+            .or_else(|e| {
+                // 304 - container already stopped
+                if e.to_string().contains("304") {
+                    Ok(())
+                } else if e.to_string().contains("404")
+                    || e.to_string().contains("No such container")
+                {
+                    Err(ProviderError::not_found("container", id))
+                } else {
+                    Err(ProviderError::Other {
+                        message: format!("Failed to stop container: {}", e),
+                    })
+                }
+            })
+    }
+
+    async fn start_instance(&self, id: &str) -> ProviderResult<()> {
+        self.client
+            .start_container(id, None::<StartContainerOptions<String>>)
+            .await
+            .or_else(|e| {
+                // 304 - container already running
+                if e.to_string().contains("304") {
+                    Ok(())
+                } else if e.to_string().contains("404")
+                    || e.to_string().contains("No such container")
+                {
+                    Err(ProviderError::not_found("container", id))
+                } else {
+                    Err(ProviderError::Other {
+                        message: format!("Failed to start container: {}", e),
+                    })
+                }
+            })
+    }
+
+    fn supports_power_control(&self) -> bool {
+        true
+    }
+
+    async fn create_volume(
+        &self,
+        name: &str,
+        size_gb: u64,
+        _region: &str,
+    ) -> ProviderResult<BlockVolume> {
+        let size = size_gb.to_string();
+        let labels = HashMap::from([
+            ("spuff", "true"),
+            ("managed-by", "spuff-cli"),
+            (SIZE_LABEL, size.as_str()),
+        ]);
+
+        let volume = self
+            .client
+            .create_volume(CreateVolumeOptions {
+                name,
+                labels,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Failed to create volume: {}", e),
+            })?;
+
+        tracing::info!("Volume {} created", name);
+        Ok(Self::volume_to_block_volume(volume, &[]))
+    }
+
+    async fn attach_volume(&self, _volume_id: &str, _instance_id: &str) -> ProviderResult<()> {
+        // Mounts are fixed when a container is created
+        Err(ProviderError::NotSupported {
+            feature: "attaching volumes to a running container".to_string(),
+        })
+    }
+
+    async fn list_volumes(&self) -> ProviderResult<Vec<BlockVolume>> {
+        let filters: HashMap<String, Vec<String>> =
+            HashMap::from([("label".to_string(), vec!["spuff=true".to_string()])]);
+
+        let volumes = self
+            .client
+            .list_volumes(Some(ListVolumesOptions {
+                filters: filters.clone(),
+            }))
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Failed to list volumes: {}", e),
+            })?;
+
+        let containers = self
+            .client
+            .list_containers(Some(ListContainersOptions {
+                all: true,
+                filters,
+                ..Default::default()
+            }))
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Failed to list containers: {}", e),
+            })?;
+
+        Ok(volumes
+            .volumes
+            .unwrap_or_default()
+            .into_iter()
+            .map(|v| Self::volume_to_block_volume(v, &containers))
+            .collect())
+    }
+
+    async fn delete_volume(&self, id: &str) -> ProviderResult<()> {
+        self.client
+            .remove_volume(id, Some(RemoveVolumeOptions { force: false }))
+            .await
+            .or_else(|e| {
+                // 404 is OK - volume already gone
+                if e.to_string().contains("404") || e.to_string().contains("no such volume") {
+                    Ok(())
+                } else {
+                    Err(ProviderError::Other {
+                        message: format!("Failed to delete volume: {}", e),
+                    })
+                }
+            })
+    }
+
+    fn supports_volumes(&self) -> bool {
+        true
+    }
 }
 
 /// Factory for creating Docker providers.
@@ -486,11 +680,13 @@ mod tests {
 
         let instance = provider.container_to_instance(
             "abc123".to_string(),
+            Some("/spuff-abc"),
             Some("running"),
             Some(1704067200), // 2024-01-01 00:00:00 UTC
         );
 
         assert_eq!(instance.id, "abc123");
+        assert_eq!(instance.name, "spuff-abc");
         assert_eq!(instance.status, InstanceStatus::Active);
         assert_eq!(instance.ip, "127.0.0.1".parse::<IpAddr>().unwrap());
     }
@@ -502,11 +698,39 @@ mod tests {
             timeouts: ProviderTimeouts::default(),
         };
 
-        let instance = provider.container_to_instance("def456".to_string(), Some("exited"), None);
+        let instance =
+            provider.container_to_instance("def456".to_string(), None, Some("exited"), None);
 
         assert_eq!(instance.status, InstanceStatus::Off);
     }
 
+    #[test]
+    fn test_volume_to_block_volume() {
+        let volume = bollard::models::Volume {
+            name: "spuff-pgdata".to_string(),
+            labels: HashMap::from([(SIZE_LABEL.to_string(), "20".to_string())]),
+            created_at: Some("2024-01-01T00:00:00Z".to_string()),
+            ..Default::default()
+        };
+        let container = bollard::models::ContainerSummary {
+            id: Some("abc123".to_string()),
+            mounts: Some(vec![bollard::models::MountPoint {
+                name: Some("spuff-pgdata".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+
+        let block = DockerProvider::volume_to_block_volume(volume.clone(), &[container]);
+        assert_eq!(block.id, "spuff-pgdata");
+        assert_eq!(block.size_gb, 20);
+        assert_eq!(block.attached_to.as_deref(), Some("abc123"));
+        assert!(block.created_at.is_some());
+
+        let detached = DockerProvider::volume_to_block_volume(volume, &[]);
+        assert_eq!(detached.attached_to, None);
+    }
+
     #[test]
     fn test_container_to_instance_new() {
         let provider = DockerProvider {
@@ -514,7 +738,8 @@ mod tests {
             timeouts: ProviderTimeouts::default(),
         };
 
-        let instance = provider.container_to_instance("ghi789".to_string(), Some("created"), None);
+        let instance =
+            provider.container_to_instance("ghi789".to_string(), None, Some("created"), None);
 
         assert_eq!(instance.status, InstanceStatus::New);
     }
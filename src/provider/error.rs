@@ -28,7 +28,6 @@ pub enum ProviderError {
 
     /// Quota or limit exceeded
     #[error("Quota exceeded for {resource}: {message}")]
-    #[allow(dead_code)]
     QuotaExceeded { resource: String, message: String },
 
     /// Invalid configuration or request parameters
@@ -105,7 +104,6 @@ impl ProviderError {
     }
 
     /// Create a quota exceeded error
-    #[allow(dead_code)]
     pub fn quota(resource: impl Into<String>, message: impl Into<String>) -> Self {
         Self::QuotaExceeded {
             resource: resource.into(),
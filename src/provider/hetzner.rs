@@ -0,0 +1,1375 @@
+//! Hetzner Cloud provider implementation.
+//!
+//! This module implements the Provider trait for the Hetzner Cloud API.
+//! It handles server (instance) and snapshot management. Hetzner stores
+//! snapshots as images of type `snapshot`, and uses key/value labels
+//! instead of tags, so spuff labels are passed through unchanged. Prices
+//! are in EUR and vary slightly by location; sizes report the cheapest.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::config::{ImageSpec, InstanceRequest, ProviderTimeouts, ProviderType};
+use super::error::{ProviderError, ProviderResult};
+use super::registry::ProviderFactory;
+use super::{InstanceStatus, Provider, ProviderInstance, Region, Size, Snapshot};
+
+const DEFAULT_API_BASE: &str = "https://api.hetzner.cloud/v1";
+
+/// Label selector used to find resources created by spuff.
+const SPUFF_LABEL_SELECTOR: &str = "spuff";
+
+/// Largest page size the API accepts (the default is 25).
+const PER_PAGE: &str = "50";
+
+/// Hetzner Cloud provider implementation.
+#[derive(Debug)]
+pub struct HetznerProvider {
+    client: Client,
+    token: String,
+    base_url: String,
+    timeouts: ProviderTimeouts,
+}
+
+impl HetznerProvider {
+    /// Create a new Hetzner provider with default settings.
+    #[allow(dead_code)]
+    pub fn new(token: &str) -> ProviderResult<Self> {
+        Self::with_config(token, DEFAULT_API_BASE, ProviderTimeouts::default())
+    }
+
+    /// Create a new provider with custom base URL (for testing).
+    #[allow(dead_code)]
+    pub fn with_base_url(token: &str, base_url: &str) -> ProviderResult<Self> {
+        Self::with_config(token, base_url, ProviderTimeouts::default())
+    }
+
+    /// Create a new provider with full configuration.
+    pub fn with_config(
+        token: &str,
+        base_url: &str,
+        timeouts: ProviderTimeouts,
+    ) -> ProviderResult<Self> {
+        if token.is_empty() {
+            return Err(ProviderError::auth(
+                "hetzner",
+                "API token is required. Set HETZNER_TOKEN or configure via 'spuff init'",
+            ));
+        }
+
+        let client = Client::builder()
+            .timeout(timeouts.http_request)
+            .build()
+            .map_err(ProviderError::Network)?;
+
+        Ok(Self {
+            client,
+            token: token.to_string(),
+            base_url: base_url.to_string(),
+            timeouts,
+        })
+    }
+
+    fn auth_header(&self) -> String {
+        format!("Bearer {}", self.token)
+    }
+
+    /// Resolve ImageSpec to a Hetzner image name or ID.
+    ///
+    /// Hetzner uses names like `ubuntu-24.04` and `debian-12` for system
+    /// images, and numeric IDs for snapshots and custom images.
+    fn resolve_image(&self, spec: &ImageSpec) -> String {
+        match spec {
+            ImageSpec::Ubuntu(version) => format!("ubuntu-{}", version),
+            ImageSpec::Debian(version) => format!("debian-{}", version),
+            ImageSpec::Custom(id) => id.clone(),
+            ImageSpec::Snapshot(id) => id.clone(),
+        }
+    }
+
+    /// Map an error response from the Hetzner API to a ProviderError.
+    ///
+    /// Hetzner returns a JSON body of the form `{"error": {"code", "message"}}`;
+    /// the error code is more precise than the HTTP status, so it wins when
+    /// it can be parsed.
+    fn map_error(&self, status: u16, body: &str, context: &str) -> ProviderError {
+        let parsed = serde_json::from_str::<ErrorResponse>(body).ok();
+        let code = parsed.as_ref().map(|e| e.error.code.as_str());
+        let message = parsed
+            .as_ref()
+            .map(|e| e.error.message.clone())
+            .unwrap_or_else(|| body.to_string());
+
+        match (status, code) {
+            (_, Some("resource_limit_exceeded")) => ProviderError::quota("server", message),
+            (401, _) | (_, Some("unauthorized")) => {
+                ProviderError::auth("hetzner", "Invalid API token")
+            }
+            (403, _) | (_, Some("forbidden")) => ProviderError::auth("hetzner", message),
+            (422, _) | (_, Some("invalid_input")) => {
+                ProviderError::invalid_config("request", message)
+            }
+            (429, _) | (_, Some("rate_limit_exceeded")) => ProviderError::RateLimit {
+                retry_after: Some(Duration::from_secs(60)),
+            },
+            _ => ProviderError::api(status, format!("{}: {}", context, message)),
+        }
+    }
+
+    /// GET every page of a list endpoint, following `meta.pagination.next_page`.
+    async fn get_pages<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+        context: &str,
+    ) -> ProviderResult<Vec<T>> {
+        let mut pages = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let response = self
+                .client
+                .get(format!("{}{}", self.base_url, path))
+                .query(query)
+                .query(&[("per_page", PER_PAGE), ("page", &page.to_string())])
+                .header("Authorization", self.auth_header())
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                let status = response.status().as_u16();
+                let body = response.text().await.unwrap_or_default();
+                return Err(self.map_error(status, &body, context));
+            }
+
+            let data: Page<T> = response.json().await?;
+            pages.push(data.body);
+
+            match data.meta.pagination.and_then(|p| p.next_page) {
+                Some(next) if next > page => page = next,
+                _ => return Ok(pages),
+            }
+        }
+    }
+
+    /// Fetch SSH key IDs from the Hetzner project.
+    async fn get_ssh_key_ids(&self) -> ProviderResult<Vec<String>> {
+        let pages: Vec<SshKeysResponse> = match self
+            .get_pages("/ssh_keys", &[], "Failed to list SSH keys")
+            .await
+        {
+            Ok(pages) => pages,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to fetch SSH keys from Hetzner ({}). \
+                     Server will be created without pre-configured SSH keys.",
+                    e
+                );
+                return Ok(vec![]);
+            }
+        };
+
+        let keys: Vec<String> = pages
+            .into_iter()
+            .flat_map(|page| page.ssh_keys)
+            .map(|k| k.id.to_string())
+            .collect();
+
+        if keys.is_empty() {
+            tracing::debug!("No SSH keys found in Hetzner project");
+        } else {
+            tracing::debug!("Found {} SSH key(s) in Hetzner project", keys.len());
+        }
+
+        Ok(keys)
+    }
+
+    /// Wait for an action to complete.
+    async fn wait_for_action(&self, action_id: u64) -> ProviderResult<()> {
+        let max_attempts = self.timeouts.action_complete_attempts();
+        let delay = self.timeouts.poll_interval;
+        let start = std::time::Instant::now();
+
+        for attempt in 0..max_attempts {
+            let response = self
+                .client
+                .get(format!("{}/actions/{}", self.base_url, action_id))
+                .header("Authorization", self.auth_header())
+                .send()
+                .await?;
+
+            if response.status().is_success() {
+                let data: ActionResponse = response.json().await?;
+                match data.action.status.as_str() {
+                    "success" => return Ok(()),
+                    "error" => {
+                        let message = data
+                            .action
+                            .error
+                            .map(|e| e.message)
+                            .unwrap_or_else(|| "unknown error".to_string());
+                        return Err(ProviderError::api(
+                            500,
+                            format!("Action {} failed: {}", action_id, message),
+                        ));
+                    }
+                    status => {
+                        tracing::debug!(
+                            "Action {} status: {} progress: {}% (attempt {}/{})",
+                            action_id,
+                            status,
+                            data.action.progress,
+                            attempt + 1,
+                            max_attempts
+                        );
+                    }
+                }
+            }
+            tokio::time::sleep(delay).await;
+        }
+
+        Err(ProviderError::timeout(
+            format!("wait for action {}", action_id),
+            start.elapsed(),
+        ))
+    }
+}
+
+#[async_trait]
+impl Provider for HetznerProvider {
+    fn name(&self) -> &'static str {
+        "hetzner"
+    }
+
+    async fn create_instance(&self, request: &InstanceRequest) -> ProviderResult<ProviderInstance> {
+        let ssh_keys = self.get_ssh_key_ids().await?;
+        let image = self.resolve_image(&request.image);
+
+        let api_request = CreateServerRequest {
+            name: request.name.clone(),
+            server_type: request.size.clone(),
+            location: request.region.clone(),
+            image,
+            ssh_keys,
+            user_data: request.user_data.clone(),
+            labels: request.labels.clone(),
+            start_after_create: true,
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/servers", self.base_url))
+            .header("Authorization", self.auth_header())
+            .header("Content-Type", "application/json")
+            .json(&api_request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.map_error(status, &body, "Failed to create server"));
+        }
+
+        let data: ServerResponse = response.json().await?;
+        Ok(data.server.to_provider_instance())
+    }
+
+    async fn destroy_instance(&self, id: &str) -> ProviderResult<()> {
+        let response = self
+            .client
+            .delete(format!("{}/servers/{}", self.base_url, id))
+            .header("Authorization", self.auth_header())
+            .send()
+            .await?;
+
+        // 404 is OK - server already gone
+        if !response.status().is_success() && response.status().as_u16() != 404 {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.map_error(status, &body, "Failed to destroy server"));
+        }
+
+        Ok(())
+    }
+
+    async fn get_instance(&self, id: &str) -> ProviderResult<Option<ProviderInstance>> {
+        let response = self
+            .client
+            .get(format!("{}/servers/{}", self.base_url, id))
+            .header("Authorization", self.auth_header())
+            .send()
+            .await?;
+
+        if response.status().as_u16() == 404 {
+            return Ok(None);
+        }
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.map_error(status, &body, "Failed to get server"));
+        }
+
+        let data: ServerResponse = response.json().await?;
+        Ok(Some(data.server.to_provider_instance()))
+    }
+
+    async fn list_instances(&self) -> ProviderResult<Vec<ProviderInstance>> {
+        let pages: Vec<ServersResponse> = self
+            .get_pages(
+                "/servers",
+                &[("label_selector", SPUFF_LABEL_SELECTOR)],
+                "Failed to list servers",
+            )
+            .await?;
+
+        Ok(pages
+            .into_iter()
+            .flat_map(|page| page.servers)
+            .map(|s| s.to_provider_instance())
+            .collect())
+    }
+
+    async fn wait_ready(&self, id: &str) -> ProviderResult<ProviderInstance> {
+        let max_attempts = self.timeouts.instance_ready_attempts();
+        let delay = self.timeouts.poll_interval;
+        let start = std::time::Instant::now();
+
+        for attempt in 0..max_attempts {
+            if let Some(instance) = self.get_instance(id).await? {
+                if instance.status == InstanceStatus::Active && !instance.ip.is_unspecified() {
+                    return Ok(instance);
+                }
+                tracing::debug!(
+                    "Server {} status: {} ip: {} (attempt {}/{})",
+                    id,
+                    instance.status,
+                    instance.ip,
+                    attempt + 1,
+                    max_attempts
+                );
+            }
+            tokio::time::sleep(delay).await;
+        }
+
+        Err(ProviderError::timeout(
+            "wait for instance ready",
+            start.elapsed(),
+        ))
+    }
+
+    async fn create_snapshot(&self, instance_id: &str, name: &str) -> ProviderResult<Snapshot> {
+        let mut labels = HashMap::new();
+        labels.insert("spuff".to_string(), "true".to_string());
+        labels.insert("managed-by".to_string(), "spuff-cli".to_string());
+
+        let request = CreateImageRequest {
+            description: name.to_string(),
+            image_type: "snapshot".to_string(),
+            labels,
+        };
+
+        let response = self
+            .client
+            .post(format!(
+                "{}/servers/{}/actions/create_image",
+                self.base_url, instance_id
+            ))
+            .header("Authorization", self.auth_header())
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.map_error(status, &body, "Failed to create snapshot"));
+        }
+
+        let data: CreateImageResponse = response.json().await?;
+        self.wait_for_action(data.action.id).await?;
+
+        Ok(data.image.to_snapshot())
+    }
+
+    async fn list_snapshots(&self) -> ProviderResult<Vec<Snapshot>> {
+        let pages: Vec<ImagesResponse> = self
+            .get_pages(
+                "/images",
+                &[
+                    ("type", "snapshot"),
+                    ("label_selector", SPUFF_LABEL_SELECTOR),
+                ],
+                "Failed to list snapshots",
+            )
+            .await?;
+
+        Ok(pages
+            .into_iter()
+            .flat_map(|page| page.images)
+            .map(|i| i.to_snapshot())
+            .filter(|s| s.name.starts_with("spuff"))
+            .collect())
+    }
+
+    async fn delete_snapshot(&self, id: &str) -> ProviderResult<()> {
+        let response = self
+            .client
+            .delete(format!("{}/images/{}", self.base_url, id))
+            .header("Authorization", self.auth_header())
+            .send()
+            .await?;
+
+        // 404 is OK - snapshot already gone
+        if !response.status().is_success() && response.status().as_u16() != 404 {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.map_error(status, &body, "Failed to delete snapshot"));
+        }
+
+        Ok(())
+    }
+
+    async fn get_ssh_keys(&self) -> ProviderResult<Vec<String>> {
+        self.get_ssh_key_ids().await
+    }
+
+    async fn list_regions(&self) -> ProviderResult<Vec<Region>> {
+        let response = self
+            .client
+            .get(format!("{}/locations", self.base_url))
+            .query(&[("per_page", "50")])
+            .header("Authorization", self.auth_header())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.map_error(status, &body, "Failed to list locations"));
+        }
+
+        let data: LocationsResponse = response.json().await?;
+
+        Ok(data
+            .locations
+            .into_iter()
+            .map(|l| Region {
+                slug: l.name,
+                name: l.description,
+                available: true,
+            })
+            .collect())
+    }
+
+    async fn list_sizes(&self) -> ProviderResult<Vec<Size>> {
+        let response = self
+            .client
+            .get(format!("{}/server_types", self.base_url))
+            .query(&[("per_page", "50")])
+            .header("Authorization", self.auth_header())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.map_error(status, &body, "Failed to list server types"));
+        }
+
+        let data: ServerTypesResponse = response.json().await?;
+
+        let mut sizes: Vec<Size> = data
+            .server_types
+            .into_iter()
+            .filter(|t| t.deprecation.is_none() && !t.prices.is_empty())
+            .map(|t| t.to_size())
+            .collect();
+        sizes.sort_by(|a, b| a.price_hourly.total_cmp(&b.price_hourly));
+
+        Ok(sizes)
+    }
+}
+
+/// Factory for creating Hetzner providers.
+pub struct HetznerFactory;
+
+impl ProviderFactory for HetznerFactory {
+    fn provider_type(&self) -> ProviderType {
+        ProviderType::Hetzner
+    }
+
+    fn create(&self, token: &str, timeouts: ProviderTimeouts) -> ProviderResult<Box<dyn Provider>> {
+        Ok(Box::new(HetznerProvider::with_config(
+            token,
+            DEFAULT_API_BASE,
+            timeouts,
+        )?))
+    }
+}
+
+// ============================================================================
+// API Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+struct CreateServerRequest {
+    name: String,
+    server_type: String,
+    location: String,
+    image: String,
+    ssh_keys: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_data: Option<String>,
+    labels: HashMap<String, String>,
+    start_after_create: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateImageRequest {
+    description: String,
+    #[serde(rename = "type")]
+    image_type: String,
+    labels: HashMap<String, String>,
+}
+
+/// A page of a list response.
+#[derive(Debug, Deserialize)]
+struct Page<T> {
+    #[serde(flatten)]
+    body: T,
+    #[serde(default)]
+    meta: Meta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Meta {
+    #[serde(default)]
+    pagination: Option<Pagination>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pagination {
+    next_page: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerResponse {
+    server: ServerData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServersResponse {
+    servers: Vec<ServerData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerData {
+    id: u64,
+    #[serde(default)]
+    name: String,
+    status: String,
+    created: String,
+    public_net: PublicNet,
+    #[serde(default)]
+    server_type: Option<NamedData>,
+    #[serde(default)]
+    datacenter: Option<DatacenterData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NamedData {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DatacenterData {
+    location: NamedData,
+}
+
+#[derive(Debug, Deserialize)]
+struct PublicNet {
+    ipv4: Option<Ipv4Data>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Ipv4Data {
+    ip: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateImageResponse {
+    image: ImageData,
+    action: ActionData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImagesResponse {
+    images: Vec<ImageData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageData {
+    id: u64,
+    description: Option<String>,
+    created: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActionResponse {
+    action: ActionData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActionData {
+    id: u64,
+    status: String,
+    #[serde(default)]
+    progress: u32,
+    #[serde(default)]
+    error: Option<ActionError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActionError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SshKeysResponse {
+    ssh_keys: Vec<SshKeyData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SshKeyData {
+    id: u64,
+    #[allow(dead_code)]
+    fingerprint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LocationsResponse {
+    locations: Vec<LocationData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LocationData {
+    name: String,
+    description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerTypesResponse {
+    server_types: Vec<ServerTypeData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerTypeData {
+    name: String,
+    cores: u32,
+    /// Memory in GB
+    memory: f64,
+    /// Disk in GB
+    disk: u64,
+    #[serde(default)]
+    deprecation: Option<serde_json::Value>,
+    #[serde(default)]
+    prices: Vec<LocationPrice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LocationPrice {
+    location: String,
+    price_hourly: PriceData,
+    price_monthly: PriceData,
+}
+
+/// Hetzner sends prices as decimal strings.
+#[derive(Debug, Deserialize)]
+struct PriceData {
+    gross: String,
+}
+
+impl PriceData {
+    fn amount(&self) -> f64 {
+        self.gross.parse().unwrap_or(0.0)
+    }
+}
+
+impl ServerTypeData {
+    fn to_size(&self) -> Size {
+        let cheapest = self
+            .prices
+            .iter()
+            .min_by(|a, b| a.price_hourly.amount().total_cmp(&b.price_hourly.amount()));
+
+        Size {
+            slug: self.name.clone(),
+            vcpus: self.cores,
+            memory_mb: (self.memory * 1024.0) as u64,
+            disk_gb: self.disk,
+            price_hourly: cheapest.map(|p| p.price_hourly.amount()).unwrap_or(0.0),
+            price_monthly: cheapest.map(|p| p.price_monthly.amount()).unwrap_or(0.0),
+            currency: "EUR".to_string(),
+            regions: self.prices.iter().map(|p| p.location.clone()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorDetail {
+    code: String,
+    message: String,
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+}
+
+impl ServerData {
+    fn to_provider_instance(&self) -> ProviderInstance {
+        let ip = self
+            .get_public_ip()
+            .unwrap_or_else(|| "0.0.0.0".parse().unwrap());
+
+        let created_at = parse_timestamp(&self.created).unwrap_or_else(|| {
+            tracing::warn!(
+                "Failed to parse server created '{}'. Using current time.",
+                self.created
+            );
+            Utc::now()
+        });
+
+        let status = match self.status.as_str() {
+            "initializing" | "starting" => InstanceStatus::New,
+            "running" => InstanceStatus::Active,
+            "off" | "stopping" => InstanceStatus::Off,
+            s => InstanceStatus::Unknown(s.to_string()),
+        };
+
+        ProviderInstance {
+            id: self.id.to_string(),
+            name: self.name.clone(),
+            region: self.datacenter.as_ref().map(|d| d.location.name.clone()),
+            size: self.server_type.as_ref().map(|t| t.name.clone()),
+            ip,
+            status,
+            created_at,
+        }
+    }
+
+    fn get_public_ip(&self) -> Option<IpAddr> {
+        self.public_net
+            .ipv4
+            .as_ref()
+            .and_then(|n| n.ip.parse().ok())
+    }
+}
+
+impl ImageData {
+    fn to_snapshot(&self) -> Snapshot {
+        Snapshot {
+            id: self.id.to_string(),
+            name: self.description.clone().unwrap_or_default(),
+            created_at: parse_timestamp(&self.created),
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn server_json(id: u64, status: &str, ip: Option<&str>) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "name": format!("spuff-{}", id),
+            "status": status,
+            "created": "2024-01-01T00:00:00+00:00",
+            "public_net": {
+                "ipv4": ip.map(|ip| serde_json::json!({"ip": ip})),
+                "ipv6": null
+            }
+        })
+    }
+
+    #[test]
+    fn test_provider_requires_token() {
+        let result = HetznerProvider::new("");
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(matches!(err, ProviderError::Authentication { .. }));
+    }
+
+    #[test]
+    fn test_provider_creates_with_valid_token() {
+        let result = HetznerProvider::new("test-token");
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_auth_header() {
+        let provider = HetznerProvider::new("my-secret-token").unwrap();
+        assert_eq!(provider.auth_header(), "Bearer my-secret-token");
+    }
+
+    #[test]
+    fn test_resolve_image() {
+        let provider = HetznerProvider::new("token").unwrap();
+        assert_eq!(
+            provider.resolve_image(&ImageSpec::ubuntu("24.04")),
+            "ubuntu-24.04"
+        );
+        assert_eq!(
+            provider.resolve_image(&ImageSpec::debian("12")),
+            "debian-12"
+        );
+        assert_eq!(
+            provider.resolve_image(&ImageSpec::snapshot("123456")),
+            "123456"
+        );
+        assert_eq!(
+            provider.resolve_image(&ImageSpec::custom("rocky-9")),
+            "rocky-9"
+        );
+    }
+
+    #[test]
+    fn test_server_data_status_mapping() {
+        let test_cases = vec![
+            ("initializing", InstanceStatus::New),
+            ("starting", InstanceStatus::New),
+            ("running", InstanceStatus::Active),
+            ("stopping", InstanceStatus::Off),
+            ("off", InstanceStatus::Off),
+            (
+                "rebuilding",
+                InstanceStatus::Unknown("rebuilding".to_string()),
+            ),
+        ];
+
+        for (status_str, expected_status) in test_cases {
+            let server = ServerData {
+                id: 1,
+                name: "spuff-test".to_string(),
+                status: status_str.to_string(),
+                created: "2024-01-01T00:00:00+00:00".to_string(),
+                public_net: PublicNet { ipv4: None },
+                server_type: None,
+                datacenter: None,
+            };
+            assert_eq!(server.to_provider_instance().status, expected_status);
+        }
+    }
+
+    #[test]
+    fn test_server_data_without_ip_returns_fallback() {
+        let server = ServerData {
+            id: 1,
+            name: "spuff-test".to_string(),
+            status: "initializing".to_string(),
+            created: "2024-01-01T00:00:00+00:00".to_string(),
+            public_net: PublicNet { ipv4: None },
+            server_type: None,
+            datacenter: None,
+        };
+        assert!(server.to_provider_instance().ip.is_unspecified());
+    }
+
+    #[test]
+    fn test_map_error_uses_hetzner_error_code() {
+        let provider = HetznerProvider::new("token").unwrap();
+
+        let body = r#"{"error": {"code": "resource_limit_exceeded", "message": "server limit"}}"#;
+        assert!(matches!(
+            provider.map_error(403, body, "ctx"),
+            ProviderError::QuotaExceeded { .. }
+        ));
+
+        let body = r#"{"error": {"code": "invalid_input", "message": "bad location"}}"#;
+        assert!(matches!(
+            provider.map_error(400, body, "ctx"),
+            ProviderError::InvalidConfig { .. }
+        ));
+
+        assert!(matches!(
+            provider.map_error(429, "", "ctx"),
+            ProviderError::RateLimit { .. }
+        ));
+        assert!(matches!(
+            provider.map_error(500, "boom", "ctx"),
+            ProviderError::Api { status: 500, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_create_instance_success() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/ssh_keys"))
+            .and(header("Authorization", "Bearer test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "ssh_keys": [{"id": 42, "fingerprint": "aa:bb:cc"}]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/servers"))
+            .and(header("Authorization", "Bearer test-token"))
+            .and(body_partial_json(serde_json::json!({
+                "server_type": "cx22",
+                "location": "fsn1",
+                "image": "ubuntu-24.04",
+                "ssh_keys": ["42"],
+                "labels": {"spuff": "true"}
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "server": server_json(777, "initializing", None),
+                "action": {"id": 1, "status": "running", "progress": 0}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let request = InstanceRequest::new("test-instance", "fsn1", "cx22")
+            .with_image(ImageSpec::ubuntu("24.04"))
+            .with_label("spuff", "true");
+
+        let instance = provider.create_instance(&request).await.unwrap();
+        assert_eq!(instance.id, "777");
+        assert_eq!(instance.status, InstanceStatus::New);
+    }
+
+    #[tokio::test]
+    async fn test_create_instance_api_error() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/ssh_keys"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "ssh_keys": []
+            })))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/servers"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(serde_json::json!({
+                "error": {"code": "unauthorized", "message": "unable to authenticate"}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("bad-token", &mock_server.uri()).unwrap();
+        let request = InstanceRequest::new("test", "fsn1", "cx22");
+
+        let result = provider.create_instance(&request).await;
+        assert!(matches!(
+            result.unwrap_err(),
+            ProviderError::Authentication { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_instance_success() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/servers/12345"))
+            .and(header("Authorization", "Bearer test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "server": server_json(12345, "running", Some("1.2.3.4"))
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let instance = provider.get_instance("12345").await.unwrap().unwrap();
+
+        assert_eq!(instance.id, "12345");
+        assert_eq!(instance.status, InstanceStatus::Active);
+        assert_eq!(instance.ip.to_string(), "1.2.3.4");
+    }
+
+    #[tokio::test]
+    async fn test_get_instance_not_found() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/servers/99999"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({
+                "error": {"code": "not_found", "message": "server not found"}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let result = provider.get_instance("99999").await;
+
+        assert!(result.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_destroy_instance_success() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/servers/11111"))
+            .and(header("Authorization", "Bearer test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "action": {"id": 5, "status": "running", "progress": 0}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        assert!(provider.destroy_instance("11111").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_destroy_instance_already_gone() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/servers/22222"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        assert!(provider.destroy_instance("22222").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_list_instances_filters_by_label() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/servers"))
+            .and(query_param("label_selector", "spuff"))
+            .and(header("Authorization", "Bearer test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "servers": [
+                    server_json(111, "running", Some("1.1.1.1")),
+                    server_json(222, "off", Some("2.2.2.2"))
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let instances = provider.list_instances().await.unwrap();
+
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0].id, "111");
+        assert_eq!(instances[1].status, InstanceStatus::Off);
+    }
+
+    #[tokio::test]
+    async fn test_list_instances_follows_pages() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/servers"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "servers": [server_json(111, "running", Some("1.1.1.1"))],
+                "meta": {"pagination": {"page": 1, "per_page": 1, "next_page": 2}}
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/servers"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "servers": [server_json(222, "running", Some("2.2.2.2"))],
+                "meta": {"pagination": {"page": 2, "per_page": 1, "next_page": null}}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let instances = provider.list_instances().await.unwrap();
+
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[1].id, "222");
+    }
+
+    #[tokio::test]
+    async fn test_wait_ready_returns_running_server() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/servers/333"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "server": server_json(333, "running", Some("5.6.7.8"))
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let instance = provider.wait_ready("333").await.unwrap();
+
+        assert_eq!(instance.ip.to_string(), "5.6.7.8");
+    }
+
+    #[tokio::test]
+    async fn test_create_snapshot() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/servers/444/actions/create_image"))
+            .and(header("Authorization", "Bearer test-token"))
+            .and(body_partial_json(serde_json::json!({
+                "type": "snapshot",
+                "description": "spuff-backup",
+                "labels": {"spuff": "true"}
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "image": {
+                    "id": 9001,
+                    "description": "spuff-backup",
+                    "created": "2024-01-01T00:00:00+00:00"
+                },
+                "action": {"id": 77, "status": "running", "progress": 0}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/actions/77"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "action": {"id": 77, "status": "success", "progress": 100}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let snapshot = provider
+            .create_snapshot("444", "spuff-backup")
+            .await
+            .unwrap();
+
+        assert_eq!(snapshot.id, "9001");
+        assert_eq!(snapshot.name, "spuff-backup");
+        assert!(snapshot.created_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_create_snapshot_action_error() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/servers/444/actions/create_image"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "image": {"id": 9002, "description": "spuff-x", "created": "2024-01-01T00:00:00+00:00"},
+                "action": {"id": 78, "status": "running", "progress": 0}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/actions/78"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "action": {
+                    "id": 78,
+                    "status": "error",
+                    "progress": 10,
+                    "error": {"code": "action_failed", "message": "disk error"}
+                }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let err = provider
+            .create_snapshot("444", "spuff-x")
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("disk error"));
+    }
+
+    #[tokio::test]
+    async fn test_list_snapshots() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/images"))
+            .and(query_param("type", "snapshot"))
+            .and(query_param("label_selector", "spuff"))
+            .and(header("Authorization", "Bearer test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "images": [
+                    {"id": 1, "description": "spuff-backup-1", "created": "2024-01-01T00:00:00+00:00"},
+                    {"id": 2, "description": "spuff-backup-2", "created": "2024-01-02T00:00:00+00:00"},
+                    {"id": 3, "description": null, "created": "2024-01-03T00:00:00+00:00"}
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let snapshots = provider.list_snapshots().await.unwrap();
+
+        assert_eq!(snapshots.len(), 2);
+        assert!(snapshots.iter().all(|s| s.name.starts_with("spuff")));
+    }
+
+    #[tokio::test]
+    async fn test_list_regions_and_sizes() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/locations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "locations": [
+                    {"id": 1, "name": "fsn1", "description": "Falkenstein DC Park 1",
+                     "city": "Falkenstein"}
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/server_types"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "server_types": [
+                    {"name": "cx32", "cores": 4, "memory": 8.0, "disk": 80, "deprecation": null,
+                     "prices": [
+                        {"location": "fsn1",
+                         "price_hourly": {"net": "0.0110", "gross": "0.0131"},
+                         "price_monthly": {"net": "6.80", "gross": "8.09"}},
+                        {"location": "ash",
+                         "price_hourly": {"net": "0.0120", "gross": "0.0143"},
+                         "price_monthly": {"net": "7.49", "gross": "8.91"}}
+                     ]},
+                    {"name": "cx22", "cores": 2, "memory": 4.0, "disk": 40, "deprecation": null,
+                     "prices": [
+                        {"location": "fsn1",
+                         "price_hourly": {"net": "0.0060", "gross": "0.0071"},
+                         "price_monthly": {"net": "3.79", "gross": "4.51"}}
+                     ]},
+                    {"name": "cx11", "cores": 1, "memory": 2.0, "disk": 20,
+                     "deprecation": {"announced": "2024-06-06T00:00:00+00:00"},
+                     "prices": []}
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+
+        let regions = provider.list_regions().await.unwrap();
+        assert_eq!(regions[0].slug, "fsn1");
+        assert_eq!(regions[0].name, "Falkenstein DC Park 1");
+
+        let sizes = provider.list_sizes().await.unwrap();
+        let slugs: Vec<&str> = sizes.iter().map(|s| s.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["cx22", "cx32"]);
+        assert_eq!(sizes[1].memory_mb, 8192);
+        assert_eq!(sizes[1].price_hourly, 0.0131);
+        assert_eq!(sizes[1].currency, "EUR");
+        assert!(sizes[1].available_in("ash"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_snapshot_already_gone() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/images/123"))
+            .and(header("Authorization", "Bearer test-token"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        assert!(provider.delete_snapshot("123").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_get_ssh_keys_follows_pages() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/ssh_keys"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "ssh_keys": [{"id": 1, "fingerprint": "aa:bb"}],
+                "meta": {"pagination": {"page": 1, "per_page": 1, "next_page": 2}}
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ssh_keys"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "ssh_keys": [{"id": 2, "fingerprint": "cc:dd"}],
+                "meta": {"pagination": {"page": 2, "per_page": 1, "next_page": null}}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let keys = provider.get_ssh_key_ids().await.unwrap();
+
+        assert_eq!(keys, vec!["1", "2"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_ssh_keys_api_error_returns_empty() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/ssh_keys"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+
+        let provider = HetznerProvider::with_base_url("test-token", &mock_server.uri()).unwrap();
+        let keys = provider.get_ssh_key_ids().await.unwrap();
+
+        assert!(keys.is_empty());
+    }
+
+    #[test]
+    fn test_factory_creates_provider() {
+        let factory = HetznerFactory;
+        assert_eq!(factory.provider_type(), ProviderType::Hetzner);
+        assert!(factory.is_implemented());
+
+        let result = factory.create("test-token", ProviderTimeouts::default());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_factory_empty_token_fails() {
+        let factory = HetznerFactory;
+        let result = factory.create("", ProviderTimeouts::default());
+        assert!(matches!(result, Err(ProviderError::Authentication { .. })));
+    }
+}
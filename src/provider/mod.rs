@@ -31,11 +31,15 @@
 //! 3. Implement the `ProviderFactory` trait
 //! 4. Register in `ProviderRegistry::register_defaults()`
 
+pub mod aws;
 pub mod config;
 pub mod digitalocean;
 pub mod docker;
 pub mod error;
+pub mod hetzner;
+pub mod qemu;
 pub mod registry;
+pub mod ssh_pool;
 
 use std::net::IpAddr;
 
@@ -44,7 +48,9 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 // Re-export commonly used types
-pub use config::{ImageSpec, InstanceRequest, ProviderTimeouts, ProviderType, VolumeMount};
+pub use config::{
+    DiskAttachment, ImageSpec, InstanceRequest, ProviderTimeouts, ProviderType, VolumeMount,
+};
 pub use error::{ProviderError, ProviderResult};
 pub use registry::ProviderRegistry;
 
@@ -89,6 +95,7 @@ impl From<InstanceConfig> for InstanceRequest {
             user_data: config.user_data,
             labels,
             volumes: Vec::new(),
+            disks: Vec::new(),
         }
     }
 }
@@ -102,6 +109,15 @@ pub struct ProviderInstance {
     /// Provider-specific instance ID
     pub id: String,
 
+    /// Instance name at the provider (falls back to the ID when it has none)
+    pub name: String,
+
+    /// Region/datacenter, when the provider reports it
+    pub region: Option<String>,
+
+    /// Instance size/type, when the provider reports it
+    pub size: Option<String>,
+
     /// Public IP address (may be 0.0.0.0 if not yet assigned)
     pub ip: IpAddr,
 
@@ -140,7 +156,7 @@ impl std::fmt::Display for InstanceStatus {
 }
 
 /// Snapshot of an instance.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Snapshot {
     /// Provider-specific snapshot ID
     pub id: String,
@@ -152,6 +168,75 @@ pub struct Snapshot {
     pub created_at: Option<DateTime<Utc>>,
 }
 
+/// Block storage volume, kept across instances.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockVolume {
+    /// Provider-specific volume ID
+    pub id: String,
+
+    /// Volume name
+    pub name: String,
+
+    /// Size in GB (0 when the provider doesn't size volumes)
+    pub size_gb: u64,
+
+    /// Region, when the provider reports it
+    pub region: Option<String>,
+
+    /// ID of the instance the volume is attached to
+    pub attached_to: Option<String>,
+
+    /// When the volume was created
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Region instances can be created in.
+#[derive(Debug, Clone, Serialize)]
+pub struct Region {
+    /// Provider slug (e.g. "nyc1", "fsn1")
+    pub slug: String,
+
+    /// Human-readable name
+    pub name: String,
+
+    /// Whether new instances can be created there
+    pub available: bool,
+}
+
+/// Instance size with its resources and price.
+#[derive(Debug, Clone, Serialize)]
+pub struct Size {
+    /// Provider slug (e.g. "s-2vcpu-4gb", "cx22")
+    pub slug: String,
+
+    pub vcpus: u32,
+
+    /// Memory in MB
+    pub memory_mb: u64,
+
+    /// Root disk in GB
+    pub disk_gb: u64,
+
+    /// Price per hour, in `currency`
+    pub price_hourly: f64,
+
+    /// Price per month, in `currency`
+    pub price_monthly: f64,
+
+    /// ISO 4217 currency code of the prices
+    pub currency: String,
+
+    /// Slugs of the regions the size can be created in
+    pub regions: Vec<String>,
+}
+
+impl Size {
+    /// Whether the size can be created in `region`.
+    pub fn available_in(&self, region: &str) -> bool {
+        self.regions.iter().any(|r| r == region)
+    }
+}
+
 /// Core trait that all cloud providers must implement.
 ///
 /// This trait defines the contract for interacting with cloud providers.
@@ -160,7 +245,6 @@ pub struct Snapshot {
 #[async_trait]
 pub trait Provider: Send + Sync {
     /// Get the provider name
-    #[allow(dead_code)]
     fn name(&self) -> &'static str;
 
     /// Create a new instance.
@@ -181,7 +265,6 @@ pub trait Provider: Send + Sync {
     async fn get_instance(&self, id: &str) -> ProviderResult<Option<ProviderInstance>>;
 
     /// List all instances with the "spuff" tag.
-    #[allow(dead_code)]
     async fn list_instances(&self) -> ProviderResult<Vec<ProviderInstance>>;
 
     /// Wait for an instance to be ready (active status + IP assigned).
@@ -218,6 +301,117 @@ pub trait Provider: Send + Sync {
     fn supports_snapshots(&self) -> bool {
         true
     }
+
+    /// Power off an instance, keeping its disk.
+    ///
+    /// Default implementation reports the feature as unsupported.
+    async fn stop_instance(&self, _id: &str) -> ProviderResult<()> {
+        Err(ProviderError::NotSupported {
+            feature: format!("stop/start on {}", self.name()),
+        })
+    }
+
+    /// Power on a stopped instance.
+    ///
+    /// Returns once the provider accepted the request; use `wait_ready` to
+    /// wait for the instance (and its possibly new IP).
+    async fn start_instance(&self, _id: &str) -> ProviderResult<()> {
+        Err(ProviderError::NotSupported {
+            feature: format!("stop/start on {}", self.name()),
+        })
+    }
+
+    /// Check if the provider can stop and start instances.
+    fn supports_power_control(&self) -> bool {
+        false
+    }
+
+    /// Change an instance's size (CPU/RAM), keeping its disk.
+    ///
+    /// The instance is powered off for the resize and booted again.
+    async fn resize_instance(&self, _id: &str, _size: &str) -> ProviderResult<()> {
+        Err(ProviderError::NotSupported {
+            feature: format!("resizing instances on {}", self.name()),
+        })
+    }
+
+    /// Check if the provider supports `resize_instance`.
+    fn supports_resize(&self) -> bool {
+        false
+    }
+
+    /// List the regions instances can be created in.
+    async fn list_regions(&self) -> ProviderResult<Vec<Region>> {
+        Err(ProviderError::NotSupported {
+            feature: format!("listing regions on {}", self.name()),
+        })
+    }
+
+    /// List the instance sizes on offer, cheapest first.
+    async fn list_sizes(&self) -> ProviderResult<Vec<Size>> {
+        Err(ProviderError::NotSupported {
+            feature: format!("listing sizes on {}", self.name()),
+        })
+    }
+
+    /// Create a block volume of `size_gb` GB in `region`.
+    ///
+    /// The volume is unformatted; the bootstrap formats it on first use.
+    async fn create_volume(
+        &self,
+        _name: &str,
+        _size_gb: u64,
+        _region: &str,
+    ) -> ProviderResult<BlockVolume> {
+        Err(ProviderError::NotSupported {
+            feature: format!("block volumes on {}", self.name()),
+        })
+    }
+
+    /// Attach an existing volume to a running instance.
+    ///
+    /// Volumes listed in `InstanceRequest::disks` are attached when the
+    /// instance is created; this is for adding one afterwards.
+    async fn attach_volume(&self, _volume_id: &str, _instance_id: &str) -> ProviderResult<()> {
+        Err(ProviderError::NotSupported {
+            feature: format!("attaching volumes on {}", self.name()),
+        })
+    }
+
+    /// Detach a volume from an instance, keeping its data.
+    async fn detach_volume(&self, _volume_id: &str, _instance_id: &str) -> ProviderResult<()> {
+        Err(ProviderError::NotSupported {
+            feature: format!("detaching volumes on {}", self.name()),
+        })
+    }
+
+    /// List all volumes with the "spuff" prefix.
+    async fn list_volumes(&self) -> ProviderResult<Vec<BlockVolume>> {
+        Ok(vec![])
+    }
+
+    /// Delete a volume and its data.
+    ///
+    /// This operation is idempotent - deleting a non-existent volume
+    /// should succeed silently.
+    async fn delete_volume(&self, _id: &str) -> ProviderResult<()> {
+        Err(ProviderError::NotSupported {
+            feature: format!("block volumes on {}", self.name()),
+        })
+    }
+
+    /// Device path of an attached volume on the instance, which the
+    /// bootstrap formats and mounts.
+    ///
+    /// `None` when the provider mounts volumes itself (Docker).
+    fn volume_device(&self, _volume: &BlockVolume) -> Option<String> {
+        None
+    }
+
+    /// Check if the provider supports block volumes.
+    fn supports_volumes(&self) -> bool {
+        false
+    }
 }
 
 /// Create a provider from application configuration.
@@ -233,14 +427,61 @@ pub trait Provider: Send + Sync {
 /// let instance = provider.create_instance(&request).await?;
 /// ```
 pub fn create_provider(config: &AppConfig) -> crate::error::Result<Box<dyn Provider>> {
-    let registry = ProviderRegistry::with_defaults();
+    let mut registry = ProviderRegistry::with_defaults();
     let timeouts = ProviderTimeouts::default();
 
+    // EC2 endpoints are regional, so AWS needs the configured region up front
+    registry.register(aws::AwsFactory::new(
+        &config.region,
+        config.aws.clone().unwrap_or_default(),
+    ));
+
+    // The SSH pool lives in the config file and reuses the user's SSH key
+    registry.register(ssh_pool::SshPoolFactory::new(
+        config.ssh_pool.clone().unwrap_or_default(),
+        &config.ssh_key_path,
+        &config.ssh_user,
+    ));
+
+    registry.register(qemu::QemuFactory::new(
+        config.qemu.clone().unwrap_or_default(),
+    ));
+
     registry
         .create_by_name(&config.provider, &config.api_token, timeouts)
         .map_err(|e| crate::error::SpuffError::Provider(e.to_string()))
 }
 
+/// Create the provider that manages an existing instance.
+///
+/// Environments may have been created with a different provider or region
+/// than the one currently configured, so both come from the instance record.
+pub fn create_provider_for(
+    config: &AppConfig,
+    provider: &str,
+    region: &str,
+) -> crate::error::Result<Box<dyn Provider>> {
+    create_provider(&config_for(config, provider, region))
+}
+
+/// Application config retargeted at another provider and region.
+///
+/// The API token is taken from the provider's environment variable when the
+/// provider differs from the configured one.
+pub fn config_for(config: &AppConfig, provider: &str, region: &str) -> AppConfig {
+    let mut config = config.clone();
+    if provider != config.provider {
+        config.api_token = ProviderType::from_str(provider)
+            .map(|p| p.token_env_var())
+            .filter(|var| !var.is_empty())
+            .and_then(|var| std::env::var(var).ok())
+            .unwrap_or_default();
+        config.provider = provider.to_string();
+    }
+    config.region = region.to_string();
+    config
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,1011 @@
+//! Local QEMU/KVM provider via libvirt.
+//!
+//! Boots an Ubuntu (or Debian) cloud image as a libvirt domain and attaches
+//! the generated cloud-init as a NoCloud seed ISO, so local VMs go through
+//! exactly the same bootstrap, SSH and agent flow as cloud instances.
+//!
+//! Everything is driven through the `virsh` CLI. Disks and seed ISOs live in
+//! a libvirt storage pool so permissions are handled by libvirt itself, and
+//! the VM's IP comes from the DHCP leases of the libvirt network.
+
+pub mod seed;
+
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use super::config::{ImageSpec, InstanceRequest, ProviderTimeouts, ProviderType};
+use super::error::{ProviderError, ProviderResult};
+use super::registry::ProviderFactory;
+use super::{InstanceStatus, Provider, ProviderInstance, Snapshot};
+
+const DEFAULT_URI: &str = "qemu:///system";
+const DEFAULT_NETWORK: &str = "default";
+const DEFAULT_POOL: &str = "default";
+const DEFAULT_DISK_GB: u32 = 20;
+
+/// Marker stored in the domain description to recognise spuff VMs.
+const DESCRIPTION_PREFIX: &str = "spuff created_at=";
+
+/// Suffix of snapshot volumes in the storage pool.
+const SNAPSHOT_SUFFIX: &str = ".snapshot.qcow2";
+
+/// QEMU-specific settings (the `qemu:` section of `~/.spuff/config.yaml`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QemuConfig {
+    /// libvirt connection URI (defaults to `qemu:///system`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+
+    /// libvirt network to attach VMs to (defaults to `default`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+
+    /// Storage pool for disks and seed ISOs (defaults to `default`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool: Option<String>,
+
+    /// Root disk size in GB (defaults to 20).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk_gb: Option<u32>,
+}
+
+impl QemuConfig {
+    fn uri(&self) -> &str {
+        self.uri.as_deref().unwrap_or(DEFAULT_URI)
+    }
+
+    fn network(&self) -> &str {
+        self.network.as_deref().unwrap_or(DEFAULT_NETWORK)
+    }
+
+    fn pool(&self) -> &str {
+        self.pool.as_deref().unwrap_or(DEFAULT_POOL)
+    }
+
+    fn disk_gb(&self) -> u32 {
+        self.disk_gb.unwrap_or(DEFAULT_DISK_GB)
+    }
+}
+
+/// Thin async wrapper around the `virsh` binary.
+struct Virsh {
+    binary: PathBuf,
+    uri: String,
+}
+
+impl Virsh {
+    async fn run(&self, args: &[&str]) -> ProviderResult<String> {
+        let output = tokio::process::Command::new(&self.binary)
+            .arg("--connect")
+            .arg(&self.uri)
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Failed to run virsh: {}", e),
+            })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(map_error(args, stderr.trim()));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+/// QEMU/KVM provider backed by libvirt.
+pub struct QemuProvider {
+    virsh: Virsh,
+    settings: QemuConfig,
+    cache_dir: PathBuf,
+    timeouts: ProviderTimeouts,
+}
+
+impl QemuProvider {
+    /// Create a new provider with full configuration.
+    ///
+    /// `cache_dir` holds downloaded cloud images and staged seed ISOs.
+    pub fn with_config(
+        settings: QemuConfig,
+        cache_dir: PathBuf,
+        timeouts: ProviderTimeouts,
+    ) -> ProviderResult<Self> {
+        let binary = which::which("virsh").map_err(|_| {
+            ProviderError::invalid_config(
+                "qemu",
+                "virsh not found in PATH. Install libvirt (e.g. 'apt install libvirt-daemon-system qemu-kvm')",
+            )
+        })?;
+
+        Ok(Self {
+            virsh: Virsh {
+                binary,
+                uri: settings.uri().to_string(),
+            },
+            settings,
+            cache_dir,
+            timeouts,
+        })
+    }
+
+    async fn volume_exists(&self, name: &str) -> ProviderResult<bool> {
+        match self
+            .virsh
+            .run(&["vol-info", "--pool", self.settings.pool(), name])
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(ProviderError::NotFound { .. }) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Create a volume and fill it with the contents of a local file.
+    async fn upload_volume(&self, name: &str, path: &Path, format: &str) -> ProviderResult<()> {
+        let size = tokio::fs::metadata(path)
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Failed to read {}: {}", path.display(), e),
+            })?
+            .len()
+            .to_string();
+        let file = path.to_string_lossy();
+        let pool = self.settings.pool();
+
+        self.virsh
+            .run(&["vol-create-as", pool, name, &size, "--format", format])
+            .await?;
+        self.virsh
+            .run(&["vol-upload", "--pool", pool, name, &file])
+            .await?;
+        // Let libvirt re-read the uploaded image header
+        self.virsh.run(&["pool-refresh", pool]).await?;
+
+        Ok(())
+    }
+
+    async fn delete_volume(&self, name: &str) -> ProviderResult<()> {
+        match self
+            .virsh
+            .run(&["vol-delete", "--pool", self.settings.pool(), name])
+            .await
+        {
+            Ok(_) | Err(ProviderError::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Make sure the base image for a spec is in the pool; returns its volume.
+    async fn ensure_base_image(&self, spec: &ImageSpec) -> ProviderResult<String> {
+        let source = resolve_image(spec, host_arch())?;
+
+        let (volume, origin) = match source {
+            ImageSource::Volume(volume) => {
+                if !self.volume_exists(&volume).await? {
+                    return Err(ProviderError::not_found("volume", volume));
+                }
+                return Ok(volume);
+            }
+            ImageSource::Url { volume, url } => (volume, url),
+            ImageSource::File { volume, path } => (volume, path),
+        };
+
+        if self.volume_exists(&volume).await? {
+            return Ok(volume);
+        }
+
+        let path = if origin.starts_with("http://") || origin.starts_with("https://") {
+            let path = self.cache_dir.join("images").join(&volume);
+            if !path.exists() {
+                download(&origin, &path).await?;
+            }
+            path
+        } else {
+            PathBuf::from(shellexpand::tilde(&origin).to_string())
+        };
+
+        tracing::info!("Uploading base image {} to pool", volume);
+        self.upload_volume(&volume, &path, "qcow2").await?;
+        Ok(volume)
+    }
+
+    /// Create the seed ISO volume for an instance.
+    async fn create_seed(&self, name: &str, user_data: &str) -> ProviderResult<()> {
+        let meta_data = format!("instance-id: {}\nlocal-hostname: {}\n", name, name);
+        let iso = seed::build_seed_iso(user_data, &meta_data);
+
+        let path = self.cache_dir.join("seeds").join(seed_volume(name));
+        write_file(&path, &iso).await?;
+        let result = self.upload_volume(&seed_volume(name), &path, "raw").await;
+        let _ = tokio::fs::remove_file(&path).await;
+        result
+    }
+
+    async fn define_and_start(&self, name: &str, xml: &str) -> ProviderResult<()> {
+        let path = self.cache_dir.join("domains").join(format!("{}.xml", name));
+        write_file(&path, xml.as_bytes()).await?;
+        let result = self.virsh.run(&["define", &path.to_string_lossy()]).await;
+        let _ = tokio::fs::remove_file(&path).await;
+        result?;
+
+        self.virsh.run(&["start", name]).await?;
+        Ok(())
+    }
+
+    async fn get_ip(&self, name: &str) -> Option<IpAddr> {
+        for source in ["lease", "arp"] {
+            if let Ok(output) = self
+                .virsh
+                .run(&["domifaddr", name, "--source", source])
+                .await
+            {
+                if let Some(ip) = parse_domifaddr(&output) {
+                    return Some(ip);
+                }
+            }
+        }
+        None
+    }
+
+    async fn created_at(&self, name: &str) -> ProviderResult<Option<DateTime<Utc>>> {
+        let description = self.virsh.run(&["desc", name]).await?;
+        Ok(parse_description(&description))
+    }
+}
+
+#[async_trait]
+impl Provider for QemuProvider {
+    fn name(&self) -> &'static str {
+        "qemu"
+    }
+
+    async fn create_instance(&self, request: &InstanceRequest) -> ProviderResult<ProviderInstance> {
+        let name = request.name.clone();
+        let (vcpus, memory_mb) = parse_size(&request.size)?;
+        let base = self.ensure_base_image(&request.image).await?;
+        let pool = self.settings.pool();
+
+        let disk_size = format!("{}G", self.settings.disk_gb());
+        self.virsh
+            .run(&[
+                "vol-create-as",
+                pool,
+                &disk_volume(&name),
+                &disk_size,
+                "--format",
+                "qcow2",
+                "--backing-vol",
+                &base,
+                "--backing-vol-format",
+                "qcow2",
+            ])
+            .await?;
+
+        let created_at = Utc::now();
+        let result = async {
+            self.create_seed(
+                &name,
+                request.user_data.as_deref().unwrap_or("#cloud-config\n"),
+            )
+            .await?;
+
+            let xml = domain_xml(&DomainSpec {
+                name: &name,
+                vcpus,
+                memory_mb,
+                pool,
+                network: self.settings.network(),
+                kvm: Path::new("/dev/kvm").exists(),
+                arch: std::env::consts::ARCH,
+                created_at,
+            });
+            self.define_and_start(&name, &xml).await
+        }
+        .await;
+
+        if let Err(e) = result {
+            // Don't leave half-created domains and volumes behind
+            let _ = self.destroy_instance(&name).await;
+            return Err(e);
+        }
+
+        Ok(ProviderInstance {
+            id: name.clone(),
+            name,
+            region: None,
+            size: Some(request.size.clone()),
+            ip: "0.0.0.0".parse().unwrap(),
+            status: InstanceStatus::New,
+            created_at,
+        })
+    }
+
+    async fn destroy_instance(&self, id: &str) -> ProviderResult<()> {
+        // Stop the VM if it is running; fails harmlessly when it is already off
+        let _ = self.virsh.run(&["destroy", id]).await;
+
+        match self.virsh.run(&["undefine", id]).await {
+            Ok(_) | Err(ProviderError::NotFound { .. }) => {}
+            Err(e) => return Err(e),
+        }
+
+        self.delete_volume(&disk_volume(id)).await?;
+        self.delete_volume(&seed_volume(id)).await?;
+
+        Ok(())
+    }
+
+    async fn get_instance(&self, id: &str) -> ProviderResult<Option<ProviderInstance>> {
+        let state = match self.virsh.run(&["domstate", id]).await {
+            Ok(state) => state,
+            Err(ProviderError::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let status = parse_state(&state);
+        let ip = if status == InstanceStatus::Active {
+            self.get_ip(id).await
+        } else {
+            None
+        };
+
+        Ok(Some(ProviderInstance {
+            id: id.to_string(),
+            name: id.to_string(),
+            region: None,
+            size: None,
+            ip: ip.unwrap_or_else(|| "0.0.0.0".parse().unwrap()),
+            status,
+            created_at: self.created_at(id).await?.unwrap_or_else(Utc::now),
+        }))
+    }
+
+    async fn list_instances(&self) -> ProviderResult<Vec<ProviderInstance>> {
+        let output = self.virsh.run(&["list", "--all", "--name"]).await?;
+        let mut instances = Vec::new();
+
+        for name in output.lines().map(str::trim).filter(|n| !n.is_empty()) {
+            // Only domains spuff created carry the description marker
+            if self.created_at(name).await?.is_none() {
+                continue;
+            }
+            if let Some(instance) = self.get_instance(name).await? {
+                instances.push(instance);
+            }
+        }
+
+        Ok(instances)
+    }
+
+    async fn wait_ready(&self, id: &str) -> ProviderResult<ProviderInstance> {
+        let start = std::time::Instant::now();
+
+        for _ in 0..self.timeouts.instance_ready_attempts() {
+            if let Some(instance) = self.get_instance(id).await? {
+                if instance.status == InstanceStatus::Active && !instance.ip.is_unspecified() {
+                    return Ok(instance);
+                }
+            }
+
+            tokio::time::sleep(self.timeouts.poll_interval).await;
+        }
+
+        Err(ProviderError::timeout("instance ready", start.elapsed()))
+    }
+
+    async fn create_snapshot(&self, instance_id: &str, name: &str) -> ProviderResult<Snapshot> {
+        let volume = format!("{}{}", name, SNAPSHOT_SUFFIX);
+
+        // Pause the VM so the copied disk is consistent
+        let paused = self.virsh.run(&["suspend", instance_id]).await.is_ok();
+        let result = self
+            .virsh
+            .run(&[
+                "vol-clone",
+                "--pool",
+                self.settings.pool(),
+                &disk_volume(instance_id),
+                &volume,
+            ])
+            .await;
+        if paused {
+            self.virsh.run(&["resume", instance_id]).await?;
+        }
+        result?;
+
+        Ok(Snapshot {
+            id: volume,
+            name: name.to_string(),
+            created_at: Some(Utc::now()),
+        })
+    }
+
+    async fn list_snapshots(&self) -> ProviderResult<Vec<Snapshot>> {
+        let output = self
+            .virsh
+            .run(&["vol-list", "--pool", self.settings.pool()])
+            .await?;
+
+        Ok(parse_vol_list(&output)
+            .into_iter()
+            .filter_map(|volume| {
+                let name = volume.strip_suffix(SNAPSHOT_SUFFIX)?.to_string();
+                name.starts_with("spuff").then(|| Snapshot {
+                    id: volume.clone(),
+                    name,
+                    created_at: None,
+                })
+            })
+            .collect())
+    }
+
+    async fn delete_snapshot(&self, id: &str) -> ProviderResult<()> {
+        self.delete_volume(id).await
+    }
+}
+
+/// Factory for creating QEMU providers.
+#[derive(Default)]
+pub struct QemuFactory {
+    settings: QemuConfig,
+}
+
+impl QemuFactory {
+    /// Create a factory with specific settings.
+    pub fn new(settings: QemuConfig) -> Self {
+        Self { settings }
+    }
+}
+
+impl ProviderFactory for QemuFactory {
+    fn provider_type(&self) -> ProviderType {
+        ProviderType::Qemu
+    }
+
+    fn create(
+        &self,
+        _token: &str,
+        timeouts: ProviderTimeouts,
+    ) -> ProviderResult<Box<dyn Provider>> {
+        // Local VMs don't need a token
+        let cache_dir = crate::config::AppConfig::config_dir()
+            .map_err(|e| ProviderError::Other {
+                message: e.to_string(),
+            })?
+            .join("qemu");
+
+        Ok(Box::new(QemuProvider::with_config(
+            self.settings.clone(),
+            cache_dir,
+            timeouts,
+        )?))
+    }
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+fn disk_volume(name: &str) -> String {
+    format!("{}.qcow2", name)
+}
+
+fn seed_volume(name: &str) -> String {
+    format!("{}-seed.iso", name)
+}
+
+/// Map a failed virsh invocation to a ProviderError.
+fn map_error(args: &[&str], stderr: &str) -> ProviderError {
+    let command = args.first().copied().unwrap_or("virsh");
+    let target = args.last().copied().unwrap_or_default();
+
+    if stderr.contains("Domain not found") || stderr.contains("failed to get domain") {
+        ProviderError::not_found("domain", target)
+    } else if stderr.contains("Storage volume not found") || stderr.contains("failed to get vol") {
+        ProviderError::not_found("volume", target)
+    } else if stderr.contains("Permission denied")
+        || stderr.contains("authentication failed")
+        || stderr.contains("Failed to connect socket")
+    {
+        ProviderError::auth(
+            "qemu",
+            format!(
+                "{}. Make sure libvirtd is running and your user is in the 'libvirt' group",
+                stderr
+            ),
+        )
+    } else {
+        ProviderError::Other {
+            message: format!("virsh {} failed: {}", command, stderr),
+        }
+    }
+}
+
+/// Where a base image comes from.
+#[derive(Debug, PartialEq)]
+enum ImageSource {
+    /// Download from a URL into the pool as `volume`.
+    Url { volume: String, url: String },
+    /// Upload a local file into the pool as `volume`.
+    File { volume: String, path: String },
+    /// Use an existing pool volume.
+    Volume(String),
+}
+
+fn host_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "aarch64" => "arm64",
+        _ => "amd64",
+    }
+}
+
+fn resolve_image(spec: &ImageSpec, arch: &str) -> ProviderResult<ImageSource> {
+    match spec {
+        ImageSpec::Ubuntu(version) => Ok(ImageSource::Url {
+            volume: format!("spuff-base-ubuntu-{}-{}.qcow2", version, arch),
+            url: format!(
+                "https://cloud-images.ubuntu.com/releases/{v}/release/ubuntu-{v}-server-cloudimg-{a}.img",
+                v = version,
+                a = arch
+            ),
+        }),
+        ImageSpec::Debian(version) => {
+            let codename = match version.as_str() {
+                "11" => "bullseye",
+                "12" => "bookworm",
+                "13" => "trixie",
+                _ => {
+                    return Err(ProviderError::invalid_config(
+                        "image",
+                        format!("Unsupported Debian version '{}'", version),
+                    ))
+                }
+            };
+            Ok(ImageSource::Url {
+                volume: format!("spuff-base-debian-{}-{}.qcow2", version, arch),
+                url: format!(
+                    "https://cloud.debian.org/images/cloud/{}/latest/debian-{}-genericcloud-{}.qcow2",
+                    codename, version, arch
+                ),
+            })
+        }
+        ImageSpec::Custom(id) => {
+            let file_name = id.rsplit('/').next().unwrap_or(id);
+            if id.starts_with("http://") || id.starts_with("https://") {
+                Ok(ImageSource::Url {
+                    volume: format!("spuff-base-{}", file_name),
+                    url: id.clone(),
+                })
+            } else if id.contains('/') || id.starts_with('~') {
+                Ok(ImageSource::File {
+                    volume: format!("spuff-base-{}", file_name),
+                    path: id.clone(),
+                })
+            } else {
+                Ok(ImageSource::Volume(id.clone()))
+            }
+        }
+        ImageSpec::Snapshot(id) => Ok(ImageSource::Volume(id.clone())),
+    }
+}
+
+/// Parse an instance size into (vCPUs, memory in MiB).
+///
+/// Accepts named sizes (`small`, `medium`, `large`, `xlarge`) and anything
+/// of the form `<n>vcpu-<m>gb`, including DigitalOcean slugs like
+/// `s-2vcpu-4gb`.
+fn parse_size(size: &str) -> ProviderResult<(u32, u32)> {
+    let named = match size {
+        "small" => Some((1, 2048)),
+        "medium" => Some((2, 4096)),
+        "large" => Some((4, 8192)),
+        "xlarge" => Some((8, 16384)),
+        _ => None,
+    };
+    if let Some(size) = named {
+        return Ok(size);
+    }
+
+    let mut vcpus = None;
+    let mut memory_gb = None;
+    for part in size.to_lowercase().split('-') {
+        if let Some(n) = part.strip_suffix("vcpu") {
+            vcpus = n.parse::<u32>().ok();
+        } else if let Some(n) = part.strip_suffix("gb") {
+            memory_gb = n.parse::<u32>().ok();
+        }
+    }
+
+    match (vcpus, memory_gb) {
+        (Some(vcpus), Some(gb)) if vcpus > 0 && gb > 0 => Ok((vcpus, gb * 1024)),
+        _ => Err(ProviderError::invalid_config(
+            "size",
+            format!(
+                "Unknown size '{}'. Use small, medium, large, xlarge or '<n>vcpu-<m>gb'",
+                size
+            ),
+        )),
+    }
+}
+
+fn parse_state(state: &str) -> InstanceStatus {
+    match state.trim() {
+        "running" => InstanceStatus::Active,
+        "shut off" | "shutdown" | "in shutdown" | "crashed" => InstanceStatus::Off,
+        "paused" | "pmsuspended" => InstanceStatus::Archive,
+        other => InstanceStatus::Unknown(other.to_string()),
+    }
+}
+
+/// Extract the first IPv4 address from `virsh domifaddr` output.
+fn parse_domifaddr(output: &str) -> Option<IpAddr> {
+    output.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [_, _, "ipv4", addr, ..] => addr.split('/').next()?.parse().ok(),
+            _ => None,
+        }
+    })
+}
+
+fn parse_description(description: &str) -> Option<DateTime<Utc>> {
+    let value = description.trim().strip_prefix(DESCRIPTION_PREFIX)?;
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+/// Extract volume names from `virsh vol-list` output.
+fn parse_vol_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with('-'))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+struct DomainSpec<'a> {
+    name: &'a str,
+    vcpus: u32,
+    memory_mb: u32,
+    pool: &'a str,
+    network: &'a str,
+    kvm: bool,
+    arch: &'a str,
+    created_at: DateTime<Utc>,
+}
+
+/// Render the libvirt domain XML for a spuff VM.
+fn domain_xml(spec: &DomainSpec<'_>) -> String {
+    let (domain_type, cpu) = if spec.kvm {
+        ("kvm", "\n  <cpu mode='host-passthrough'/>")
+    } else {
+        ("qemu", "")
+    };
+    let os = if spec.arch == "aarch64" {
+        "<os firmware='efi'>\n    <type arch='aarch64' machine='virt'>hvm</type>"
+    } else {
+        "<os>\n    <type arch='x86_64' machine='q35'>hvm</type>"
+    };
+
+    format!(
+        r#"<domain type='{domain_type}'>
+  <name>{name}</name>
+  <description>{prefix}{created_at}</description>
+  <memory unit='MiB'>{memory}</memory>
+  <vcpu>{vcpus}</vcpu>
+  {os}
+    <boot dev='hd'/>
+  </os>
+  <features>
+    <acpi/>
+    <apic/>
+  </features>{cpu}
+  <devices>
+    <disk type='volume' device='disk'>
+      <driver name='qemu' type='qcow2'/>
+      <source pool='{pool}' volume='{disk}'/>
+      <target dev='vda' bus='virtio'/>
+    </disk>
+    <disk type='volume' device='cdrom'>
+      <driver name='qemu' type='raw'/>
+      <source pool='{pool}' volume='{seed}'/>
+      <target dev='sda' bus='sata'/>
+      <readonly/>
+    </disk>
+    <interface type='network'>
+      <source network='{network}'/>
+      <model type='virtio'/>
+    </interface>
+    <serial type='pty'/>
+    <console type='pty'/>
+    <rng model='virtio'>
+      <backend model='random'>/dev/urandom</backend>
+    </rng>
+  </devices>
+</domain>
+"#,
+        domain_type = domain_type,
+        name = spec.name,
+        prefix = DESCRIPTION_PREFIX,
+        created_at = spec.created_at.to_rfc3339(),
+        memory = spec.memory_mb,
+        vcpus = spec.vcpus,
+        os = os,
+        cpu = cpu,
+        pool = spec.pool,
+        disk = disk_volume(spec.name),
+        seed = seed_volume(spec.name),
+        network = spec.network,
+    )
+}
+
+async fn write_file(path: &Path, content: &[u8]) -> ProviderResult<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Failed to create {}: {}", parent.display(), e),
+            })?;
+    }
+
+    tokio::fs::write(path, content)
+        .await
+        .map_err(|e| ProviderError::Other {
+            message: format!("Failed to write {}: {}", path.display(), e),
+        })
+}
+
+/// Download a cloud image, writing to a `.part` file first so an
+/// interrupted download is never mistaken for a cached image.
+async fn download(url: &str, path: &Path) -> ProviderResult<()> {
+    tracing::info!("Downloading {}", url);
+
+    let mut response = reqwest::get(url).await?;
+    if !response.status().is_success() {
+        return Err(ProviderError::api(
+            response.status().as_u16(),
+            format!("Failed to download {}", url),
+        ));
+    }
+
+    let partial = path.with_extension("part");
+    if let Some(parent) = partial.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Failed to create {}: {}", parent.display(), e),
+            })?;
+    }
+    let mut file = tokio::fs::File::create(&partial)
+        .await
+        .map_err(|e| ProviderError::Other {
+            message: format!("Failed to create {}: {}", partial.display(), e),
+        })?;
+
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Failed to write {}: {}", partial.display(), e),
+            })?;
+    }
+    file.flush().await.map_err(|e| ProviderError::Other {
+        message: format!("Failed to write {}: {}", partial.display(), e),
+    })?;
+
+    tokio::fs::rename(&partial, path)
+        .await
+        .map_err(|e| ProviderError::Other {
+            message: format!("Failed to move {}: {}", path.display(), e),
+        })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_config_defaults() {
+        let config = QemuConfig::default();
+        assert_eq!(config.uri(), "qemu:///system");
+        assert_eq!(config.network(), "default");
+        assert_eq!(config.pool(), "default");
+        assert_eq!(config.disk_gb(), 20);
+    }
+
+    #[test]
+    fn test_config_deserialize() {
+        let config: QemuConfig =
+            serde_yaml::from_str("uri: qemu:///session\ndisk_gb: 40\n").unwrap();
+        assert_eq!(config.uri(), "qemu:///session");
+        assert_eq!(config.disk_gb(), 40);
+        assert_eq!(config.pool(), "default");
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("s-2vcpu-4gb").unwrap(), (2, 4096));
+        assert_eq!(parse_size("4vcpu-8gb").unwrap(), (4, 8192));
+        assert_eq!(parse_size("small").unwrap(), (1, 2048));
+        assert_eq!(parse_size("xlarge").unwrap(), (8, 16384));
+        assert!(matches!(
+            parse_size("cx22"),
+            Err(ProviderError::InvalidConfig { .. })
+        ));
+        assert!(parse_size("0vcpu-4gb").is_err());
+    }
+
+    #[test]
+    fn test_parse_state() {
+        assert_eq!(parse_state("running\n\n"), InstanceStatus::Active);
+        assert_eq!(parse_state("shut off"), InstanceStatus::Off);
+        assert_eq!(parse_state("paused"), InstanceStatus::Archive);
+        assert_eq!(
+            parse_state("blocked"),
+            InstanceStatus::Unknown("blocked".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_domifaddr() {
+        let output = " Name       MAC address          Protocol     Address
+-------------------------------------------------------------------------------
+ vnet0      52:54:00:a1:b2:c3    ipv6         fe80::5054:ff:fea1:b2c3/64
+ vnet0      52:54:00:a1:b2:c3    ipv4         192.168.122.45/24
+";
+        assert_eq!(
+            parse_domifaddr(output),
+            Some("192.168.122.45".parse().unwrap())
+        );
+        assert_eq!(
+            parse_domifaddr(" Name MAC address Protocol Address\n----\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_description() {
+        let created = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let description = format!("{}{}\n", DESCRIPTION_PREFIX, created.to_rfc3339());
+
+        assert_eq!(parse_description(&description), Some(created));
+        assert_eq!(parse_description("my own vm\n"), None);
+        assert_eq!(parse_description(""), None);
+    }
+
+    #[test]
+    fn test_parse_vol_list() {
+        let output = " Name                                Path
+------------------------------------------------------------------------------
+ spuff-abc.qcow2                     /var/lib/libvirt/images/spuff-abc.qcow2
+ spuff-abc-1.snapshot.qcow2          /var/lib/libvirt/images/spuff-abc-1.snapshot.qcow2
+
+";
+        assert_eq!(
+            parse_vol_list(output),
+            vec!["spuff-abc.qcow2", "spuff-abc-1.snapshot.qcow2"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_image() {
+        assert_eq!(
+            resolve_image(&ImageSpec::ubuntu("24.04"), "amd64").unwrap(),
+            ImageSource::Url {
+                volume: "spuff-base-ubuntu-24.04-amd64.qcow2".to_string(),
+                url: "https://cloud-images.ubuntu.com/releases/24.04/release/ubuntu-24.04-server-cloudimg-amd64.img".to_string(),
+            }
+        );
+        assert!(matches!(
+            resolve_image(&ImageSpec::debian("12"), "arm64").unwrap(),
+            ImageSource::Url { url, .. } if url.contains("bookworm") && url.ends_with("genericcloud-arm64.qcow2")
+        ));
+        assert!(resolve_image(&ImageSpec::debian("9"), "amd64").is_err());
+        assert_eq!(
+            resolve_image(&ImageSpec::custom("~/images/dev.qcow2"), "amd64").unwrap(),
+            ImageSource::File {
+                volume: "spuff-base-dev.qcow2".to_string(),
+                path: "~/images/dev.qcow2".to_string(),
+            }
+        );
+        assert_eq!(
+            resolve_image(&ImageSpec::snapshot("spuff-x.snapshot.qcow2"), "amd64").unwrap(),
+            ImageSource::Volume("spuff-x.snapshot.qcow2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_map_error() {
+        assert!(matches!(
+            map_error(
+                &["domstate", "spuff-x"],
+                "error: failed to get domain 'spuff-x'"
+            ),
+            ProviderError::NotFound { .. }
+        ));
+        assert!(matches!(
+            map_error(
+                &["vol-info", "--pool", "default", "x"],
+                "error: Storage volume not found: no storage vol with matching path"
+            ),
+            ProviderError::NotFound { .. }
+        ));
+        assert!(matches!(
+            map_error(
+                &["list"],
+                "error: Failed to connect socket to '/var/run/libvirt/libvirt-sock': Permission denied"
+            ),
+            ProviderError::Authentication { .. }
+        ));
+        assert!(matches!(
+            map_error(&["start", "x"], "error: something else"),
+            ProviderError::Other { .. }
+        ));
+    }
+
+    #[test]
+    fn test_domain_xml() {
+        let xml = domain_xml(&DomainSpec {
+            name: "spuff-abc",
+            vcpus: 2,
+            memory_mb: 4096,
+            pool: "default",
+            network: "default",
+            kvm: true,
+            arch: "x86_64",
+            created_at: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+        });
+
+        assert!(xml.starts_with("<domain type='kvm'>"));
+        assert!(xml.contains("<name>spuff-abc</name>"));
+        assert!(
+            xml.contains("<description>spuff created_at=2025-01-01T00:00:00+00:00</description>")
+        );
+        assert!(xml.contains("<memory unit='MiB'>4096</memory>"));
+        assert!(xml.contains("<vcpu>2</vcpu>"));
+        assert!(xml.contains("volume='spuff-abc.qcow2'"));
+        assert!(xml.contains("volume='spuff-abc-seed.iso'"));
+        assert!(xml.contains("<source network='default'/>"));
+        assert!(xml.contains("host-passthrough"));
+    }
+
+    #[test]
+    fn test_domain_xml_without_kvm() {
+        let xml = domain_xml(&DomainSpec {
+            name: "spuff-abc",
+            vcpus: 1,
+            memory_mb: 2048,
+            pool: "default",
+            network: "default",
+            kvm: false,
+            arch: "aarch64",
+            created_at: Utc::now(),
+        });
+
+        assert!(xml.starts_with("<domain type='qemu'>"));
+        assert!(!xml.contains("host-passthrough"));
+        assert!(xml.contains("firmware='efi'"));
+        assert!(xml.contains("machine='virt'"));
+    }
+
+    #[test]
+    fn test_factory_type() {
+        let factory = QemuFactory::default();
+        assert_eq!(factory.provider_type(), ProviderType::Qemu);
+        assert!(factory.is_implemented());
+    }
+}
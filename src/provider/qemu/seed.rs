@@ -0,0 +1,278 @@
+//! NoCloud seed ISO builder.
+//!
+//! cloud-init's NoCloud datasource looks for a filesystem labelled `cidata`
+//! containing `user-data` and `meta-data`. This writes a minimal ISO 9660
+//! image with just those files in the root directory, so no `genisoimage`
+//! or `cloud-localds` binary is needed.
+//! See <https://cloudinit.readthedocs.io/en/latest/reference/datasources/nocloud.html>.
+
+const SECTOR: usize = 2048;
+
+/// Sectors 0-15 are the system area; descriptors start at 16.
+const PVD_SECTOR: usize = 16;
+const TERMINATOR_SECTOR: usize = 17;
+const L_PATH_TABLE_SECTOR: usize = 18;
+const M_PATH_TABLE_SECTOR: usize = 19;
+const ROOT_DIR_SECTOR: usize = 20;
+const FIRST_FILE_SECTOR: usize = 21;
+
+/// Zeroed sectors appended after the last file, like `mkisofs -pad`.
+/// Some readers (libarchive, CD drivers) read ahead past the end of data.
+const PADDING_SECTORS: usize = 150;
+
+/// Volume label cloud-init looks for.
+pub const SEED_LABEL: &str = "cidata";
+
+/// Build a NoCloud seed ISO from user data and meta data.
+pub fn build_seed_iso(user_data: &str, meta_data: &str) -> Vec<u8> {
+    build_iso(
+        SEED_LABEL,
+        &[
+            ("meta-data", meta_data.as_bytes()),
+            ("user-data", user_data.as_bytes()),
+        ],
+    )
+}
+
+/// Build an ISO 9660 image with the given files in its root directory.
+///
+/// File names are stored upper-cased with a `.;1` suffix; Linux maps them
+/// back to lower case without the version, which is what cloud-init reads.
+fn build_iso(label: &str, files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut files: Vec<(String, &[u8])> = files
+        .iter()
+        .map(|(name, data)| (format!("{}.;1", name.to_ascii_uppercase()), *data))
+        .collect();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    // Lay out file extents after the root directory
+    let mut extents = Vec::with_capacity(files.len());
+    let mut next_sector = FIRST_FILE_SECTOR;
+    for (_, data) in &files {
+        extents.push(next_sector);
+        next_sector += data.len().div_ceil(SECTOR).max(1);
+    }
+    let total_sectors = next_sector + PADDING_SECTORS;
+
+    let mut image = vec![0u8; total_sectors * SECTOR];
+
+    // Root directory
+    let mut root = Vec::new();
+    root.extend(dir_record(&[0x00], ROOT_DIR_SECTOR, SECTOR, true));
+    root.extend(dir_record(&[0x01], ROOT_DIR_SECTOR, SECTOR, true));
+    for ((name, data), extent) in files.iter().zip(&extents) {
+        root.extend(dir_record(name.as_bytes(), *extent, data.len(), false));
+    }
+    assert!(root.len() <= SECTOR, "seed ISO root directory overflow");
+    write_at(&mut image, ROOT_DIR_SECTOR * SECTOR, &root);
+
+    for ((_, data), extent) in files.iter().zip(&extents) {
+        write_at(&mut image, extent * SECTOR, data);
+    }
+
+    // Path tables (root only)
+    write_at(
+        &mut image,
+        L_PATH_TABLE_SECTOR * SECTOR,
+        &path_table_record(ROOT_DIR_SECTOR as u32, false),
+    );
+    write_at(
+        &mut image,
+        M_PATH_TABLE_SECTOR * SECTOR,
+        &path_table_record(ROOT_DIR_SECTOR as u32, true),
+    );
+
+    let pvd = primary_volume_descriptor(label, total_sectors);
+    write_at(&mut image, PVD_SECTOR * SECTOR, &pvd);
+
+    let mut terminator = vec![0u8; 7];
+    terminator[0] = 255;
+    terminator[1..6].copy_from_slice(b"CD001");
+    terminator[6] = 1;
+    write_at(&mut image, TERMINATOR_SECTOR * SECTOR, &terminator);
+
+    image
+}
+
+fn primary_volume_descriptor(label: &str, total_sectors: usize) -> Vec<u8> {
+    let mut pvd = vec![0u8; SECTOR];
+    pvd[0] = 1;
+    pvd[1..6].copy_from_slice(b"CD001");
+    pvd[6] = 1;
+    write_padded(&mut pvd[8..40], "LINUX");
+    write_padded(&mut pvd[40..72], label);
+    pvd[80..88].copy_from_slice(&both_u32(total_sectors as u32));
+    pvd[120..124].copy_from_slice(&both_u16(1));
+    pvd[124..128].copy_from_slice(&both_u16(1));
+    pvd[128..132].copy_from_slice(&both_u16(SECTOR as u16));
+    pvd[132..140].copy_from_slice(&both_u32(PATH_TABLE_LEN as u32));
+    pvd[140..144].copy_from_slice(&(L_PATH_TABLE_SECTOR as u32).to_le_bytes());
+    pvd[148..152].copy_from_slice(&(M_PATH_TABLE_SECTOR as u32).to_be_bytes());
+    pvd[156..190].copy_from_slice(&dir_record(&[0x00], ROOT_DIR_SECTOR, SECTOR, true));
+    for range in [
+        190..318,
+        318..446,
+        446..574,
+        574..702,
+        702..739,
+        739..776,
+        776..813,
+    ] {
+        write_padded(&mut pvd[range], "");
+    }
+    // Creation/modification/expiration/effective dates: "not specified"
+    for start in [813, 830, 847, 864] {
+        pvd[start..start + 16].copy_from_slice(b"0000000000000000");
+    }
+    pvd[881] = 1;
+    pvd
+}
+
+/// Size of a path table holding only the root directory.
+const PATH_TABLE_LEN: usize = 10;
+
+fn path_table_record(extent: u32, big_endian: bool) -> Vec<u8> {
+    let mut record = vec![0u8; PATH_TABLE_LEN];
+    record[0] = 1;
+    if big_endian {
+        record[2..6].copy_from_slice(&extent.to_be_bytes());
+        record[6..8].copy_from_slice(&1u16.to_be_bytes());
+    } else {
+        record[2..6].copy_from_slice(&extent.to_le_bytes());
+        record[6..8].copy_from_slice(&1u16.to_le_bytes());
+    }
+    record
+}
+
+fn dir_record(id: &[u8], extent: usize, size: usize, directory: bool) -> Vec<u8> {
+    let padding = usize::from(id.len().is_multiple_of(2));
+    let len = 33 + id.len() + padding;
+
+    let mut record = vec![0u8; len];
+    record[0] = len as u8;
+    record[2..10].copy_from_slice(&both_u32(extent as u32));
+    record[10..18].copy_from_slice(&both_u32(size as u32));
+    // Recording date: 1970-01-01 00:00:00 UTC
+    record[18..25].copy_from_slice(&[70, 1, 1, 0, 0, 0, 0]);
+    record[25] = if directory { 0x02 } else { 0x00 };
+    record[28..32].copy_from_slice(&both_u16(1));
+    record[32] = id.len() as u8;
+    record[33..33 + id.len()].copy_from_slice(id);
+    record
+}
+
+/// Encode a u32 in ISO 9660 "both-endian" form (LE followed by BE).
+fn both_u32(value: u32) -> [u8; 8] {
+    let mut out = [0u8; 8];
+    out[..4].copy_from_slice(&value.to_le_bytes());
+    out[4..].copy_from_slice(&value.to_be_bytes());
+    out
+}
+
+fn both_u16(value: u16) -> [u8; 4] {
+    let mut out = [0u8; 4];
+    out[..2].copy_from_slice(&value.to_le_bytes());
+    out[2..].copy_from_slice(&value.to_be_bytes());
+    out
+}
+
+fn write_padded(field: &mut [u8], value: &str) {
+    field.fill(b' ');
+    let bytes = value.as_bytes();
+    let len = bytes.len().min(field.len());
+    field[..len].copy_from_slice(&bytes[..len]);
+}
+
+fn write_at(image: &mut [u8], offset: usize, data: &[u8]) {
+    image[offset..offset + data.len()].copy_from_slice(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32_le(image: &[u8], offset: usize) -> usize {
+        u32::from_le_bytes(image[offset..offset + 4].try_into().unwrap()) as usize
+    }
+
+    /// Walk the root directory and return (name, extent, size) entries.
+    fn root_entries(image: &[u8]) -> Vec<(String, usize, usize)> {
+        let root_extent = read_u32_le(image, PVD_SECTOR * SECTOR + 156 + 2);
+        let dir = &image[root_extent * SECTOR..(root_extent + 1) * SECTOR];
+
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while offset < dir.len() && dir[offset] != 0 {
+            let len = dir[offset] as usize;
+            let id_len = dir[offset + 32] as usize;
+            let name = String::from_utf8_lossy(&dir[offset + 33..offset + 33 + id_len]).to_string();
+            entries.push((
+                name,
+                read_u32_le(dir, offset + 2),
+                read_u32_le(dir, offset + 10),
+            ));
+            offset += len;
+        }
+        entries
+    }
+
+    #[test]
+    fn test_seed_iso_volume_descriptors() {
+        let image = build_seed_iso("#cloud-config\n", "instance-id: spuff-test\n");
+
+        assert_eq!(image.len() % SECTOR, 0);
+        let pvd = &image[PVD_SECTOR * SECTOR..];
+        assert_eq!(pvd[0], 1);
+        assert_eq!(&pvd[1..6], b"CD001");
+        assert_eq!(
+            std::str::from_utf8(&pvd[40..72]).unwrap().trim_end(),
+            "cidata"
+        );
+        assert_eq!(read_u32_le(pvd, 80), image.len() / SECTOR);
+
+        let terminator = &image[TERMINATOR_SECTOR * SECTOR..];
+        assert_eq!(terminator[0], 255);
+        assert_eq!(&terminator[1..6], b"CD001");
+    }
+
+    #[test]
+    fn test_seed_iso_contains_files() {
+        let user_data = "#cloud-config\npackages: [git]\n";
+        let meta_data = "instance-id: spuff-test\n";
+        let image = build_seed_iso(user_data, meta_data);
+
+        let entries = root_entries(&image);
+        let names: Vec<&str> = entries.iter().map(|(n, _, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["\0", "\u{1}", "META-DATA.;1", "USER-DATA.;1"]);
+
+        let (_, extent, size) = &entries[3];
+        assert_eq!(
+            &image[extent * SECTOR..extent * SECTOR + size],
+            user_data.as_bytes()
+        );
+        let (_, extent, size) = &entries[2];
+        assert_eq!(
+            &image[extent * SECTOR..extent * SECTOR + size],
+            meta_data.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_seed_iso_large_user_data_spans_sectors() {
+        let user_data = "x".repeat(SECTOR * 3 + 10);
+        let image = build_seed_iso(&user_data, "instance-id: a\n");
+
+        let entries = root_entries(&image);
+        let (_, extent, size) = &entries[3];
+        assert_eq!(*size, user_data.len());
+        assert_eq!(image.len(), (extent + 4 + PADDING_SECTORS) * SECTOR);
+    }
+
+    #[test]
+    fn test_dir_record_padding() {
+        // Even-length identifiers get a padding byte to keep records even
+        assert_eq!(dir_record(b"AB", 0, 0, false).len(), 36);
+        assert_eq!(dir_record(b"ABC", 0, 0, false).len(), 36);
+        assert_eq!(dir_record(&[0x00], 0, 0, true).len(), 34);
+    }
+}
@@ -60,14 +60,19 @@ impl ProviderRegistry {
 
     /// Register all default provider factories
     pub fn register_defaults(&mut self) {
+        use super::aws::AwsFactory;
         use super::digitalocean::DigitalOceanFactory;
         use super::docker::DockerFactory;
+        use super::hetzner::HetznerFactory;
+        use super::qemu::QemuFactory;
+        use super::ssh_pool::SshPoolFactory;
 
         self.register(DigitalOceanFactory);
         self.register(DockerFactory);
-        // Future providers:
-        // self.register(HetznerFactory);
-        // self.register(AwsFactory);
+        self.register(HetznerFactory);
+        self.register(AwsFactory::default());
+        self.register(SshPoolFactory::default());
+        self.register(QemuFactory::default());
     }
 
     /// Register a provider factory
@@ -152,6 +157,10 @@ mod tests {
         let registry = ProviderRegistry::with_defaults();
         assert!(registry.is_registered(ProviderType::DigitalOcean));
         assert!(registry.is_registered(ProviderType::Docker));
+        assert!(registry.is_registered(ProviderType::Hetzner));
+        assert!(registry.is_registered(ProviderType::Aws));
+        assert!(registry.is_registered(ProviderType::Ssh));
+        assert!(registry.is_registered(ProviderType::Qemu));
     }
 
     #[test]
@@ -176,6 +185,20 @@ mod tests {
         assert!(matches!(result, Err(ProviderError::Authentication { .. })));
     }
 
+    #[test]
+    fn test_create_hetzner() {
+        let registry = ProviderRegistry::with_defaults();
+        let result = registry.create_by_name("hcloud", "test-token", ProviderTimeouts::default());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_create_ssh_without_hosts() {
+        let registry = ProviderRegistry::with_defaults();
+        let result = registry.create_by_name("ssh", "", ProviderTimeouts::default());
+        assert!(matches!(result, Err(ProviderError::InvalidConfig { .. })));
+    }
+
     #[test]
     fn test_implemented_providers() {
         let registry = ProviderRegistry::with_defaults();
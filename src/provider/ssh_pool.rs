@@ -0,0 +1,796 @@
+//! Bring-your-own-host provider.
+//!
+//! Treats a configured pool of SSH-reachable machines as "instances".
+//! Creating an instance claims a free host from the pool and replays the
+//! cloud-init user data on it through a NoCloud seed; destroying it runs a
+//! cleanup and releases the host for the next `spuff up`.
+//!
+//! The claim is stored on the host itself (`/var/lib/spuff/claim`), so several
+//! people can share the same pool without any local coordination. Instance IDs
+//! name both the claim and the host (`<instance>@<host>`), so an environment
+//! that was already released never touches the next claim on the same host.
+
+use std::net::IpAddr;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::config::{InstanceRequest, ProviderTimeouts, ProviderType};
+use super::error::{ProviderError, ProviderResult};
+use super::registry::ProviderFactory;
+use super::{InstanceStatus, Provider, ProviderInstance, Snapshot};
+use crate::ssh::config::HostKeyPolicy;
+use crate::ssh::{SshClient, SshConfig};
+
+/// File on the host that marks it as claimed by spuff.
+const CLAIM_FILE: &str = "/var/lib/spuff/claim";
+
+/// cloud-init configuration dropped on the host to force the NoCloud seed
+/// and limit it to the modules spuff's user data needs.
+const CLOUD_CFG_FILE: &str = "/etc/cloud/cloud.cfg.d/99-spuff.cfg";
+
+/// NoCloud seed directory read by cloud-init.
+const SEED_DIR: &str = "/var/lib/cloud/seed/nocloud";
+
+/// Contents of [`CLOUD_CFG_FILE`]. A pool host is someone's machine, not a
+/// fresh VM: only users, files, packages and runcmd are applied, so its SSH
+/// host keys, sshd settings, hostname and network config are left alone.
+const CLOUD_CFG: &str = "\
+datasource_list: [ NoCloud, None ]
+preserve_hostname: true
+ssh_deletekeys: false
+network:
+  config: disabled
+cloud_init_modules:
+  - write_files
+  - users_groups
+cloud_config_modules:
+  - runcmd
+cloud_final_modules:
+  - package_update_upgrade_install
+  - write_files_deferred
+  - scripts_user
+  - final_message
+";
+
+const SSH_PORT: u16 = 22;
+
+/// Pool of existing hosts used by the `ssh` provider.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SshPoolConfig {
+    /// Hosts that can be claimed, tried in order.
+    #[serde(default)]
+    pub hosts: Vec<SshPoolHost>,
+}
+
+/// A single host in the pool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SshPoolHost {
+    /// Hostname or IP address. SSH must listen on port 22.
+    pub host: String,
+
+    /// Stable name used as the instance ID (defaults to `host`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// User for bootstrap and cleanup. Must be root or have passwordless sudo.
+    #[serde(default = "default_admin_user")]
+    pub user: String,
+
+    /// Private key for the admin user (defaults to `ssh_key_path`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_path: Option<String>,
+}
+
+fn default_admin_user() -> String {
+    "root".to_string()
+}
+
+impl SshPoolHost {
+    /// Instance ID for this host.
+    pub fn id(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.host)
+    }
+}
+
+/// Claim record stored on a host.
+#[derive(Debug, Clone, PartialEq)]
+struct Claim {
+    instance: String,
+    claimed_at: DateTime<Utc>,
+    /// Whether the dev user existed before spuff bootstrapped the host.
+    user_existed: bool,
+}
+
+impl Claim {
+    fn parse(content: &str) -> Option<Self> {
+        let mut instance = None;
+        let mut claimed_at = None;
+        let mut user_existed = true;
+
+        for line in content.lines() {
+            match line.trim().split_once('=') {
+                Some(("instance", v)) => instance = Some(v.to_string()),
+                Some(("claimed_at", v)) => {
+                    claimed_at = DateTime::parse_from_rfc3339(v)
+                        .ok()
+                        .map(|d| d.with_timezone(&Utc))
+                }
+                Some(("user_existed", v)) => user_existed = v != "false",
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            instance: instance?,
+            claimed_at: claimed_at.unwrap_or_else(Utc::now),
+            user_existed,
+        })
+    }
+}
+
+/// Provider backed by a pool of existing SSH hosts.
+pub struct SshPoolProvider {
+    hosts: Vec<SshPoolHost>,
+    key_path: String,
+    dev_user: String,
+    #[allow(dead_code)]
+    timeouts: ProviderTimeouts,
+}
+
+impl SshPoolProvider {
+    /// Create a new provider.
+    ///
+    /// `key_path` is the fallback admin key and `dev_user` is the user that
+    /// cloud-init creates on claimed hosts.
+    pub fn new(
+        pool: SshPoolConfig,
+        key_path: impl Into<String>,
+        dev_user: impl Into<String>,
+        timeouts: ProviderTimeouts,
+    ) -> ProviderResult<Self> {
+        if pool.hosts.is_empty() {
+            return Err(ProviderError::invalid_config(
+                "ssh_pool.hosts",
+                "No hosts configured. Add hosts under 'ssh_pool' in ~/.spuff/config.yaml",
+            ));
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for host in &pool.hosts {
+            if !seen.insert(host.id()) {
+                return Err(ProviderError::invalid_config(
+                    "ssh_pool.hosts",
+                    format!("Duplicate host name '{}'", host.id()),
+                ));
+            }
+        }
+
+        Ok(Self {
+            hosts: pool.hosts,
+            key_path: key_path.into(),
+            dev_user: dev_user.into(),
+            timeouts,
+        })
+    }
+
+    fn find_host(&self, id: &str) -> Option<&SshPoolHost> {
+        self.hosts.iter().find(|h| h.id() == id)
+    }
+
+    /// Host and claiming instance name behind an instance ID.
+    fn find_instance<'a>(&self, id: &'a str) -> Option<(&SshPoolHost, &'a str)> {
+        let (instance, host_id) = id.rsplit_once('@')?;
+        Some((self.find_host(host_id)?, instance))
+    }
+
+    async fn connect(&self, host: &SshPoolHost) -> ProviderResult<SshClient> {
+        let key_path = host.key_path.as_deref().unwrap_or(&self.key_path);
+        let mut config = SshConfig::new(&host.user, shellexpand::tilde(key_path).to_string());
+        config.host_key_policy = HostKeyPolicy::AcceptNew;
+
+        SshClient::connect(&host.host, SSH_PORT, &config)
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Failed to connect to {}: {}", host.host, e),
+            })
+    }
+
+    /// Run a script as root on the host, failing on a non-zero exit.
+    async fn run_privileged(
+        &self,
+        client: &SshClient,
+        host: &SshPoolHost,
+        script: &str,
+    ) -> ProviderResult<String> {
+        let output = client
+            .exec(&privileged(&host.user, script))
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Command failed on {}: {}", host.host, e),
+            })?;
+
+        if !output.success {
+            return Err(ProviderError::Other {
+                message: format!("Command failed on {}: {}", host.host, output.stderr.trim()),
+            });
+        }
+
+        Ok(output.stdout)
+    }
+
+    async fn read_claim(
+        &self,
+        client: &SshClient,
+        host: &SshPoolHost,
+    ) -> ProviderResult<Option<Claim>> {
+        let output = client
+            .exec(&format!("cat {} 2>/dev/null || true", CLAIM_FILE))
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Failed to read claim on {}: {}", host.host, e),
+            })?;
+
+        Ok(Claim::parse(&output.stdout))
+    }
+
+    async fn to_instance(
+        &self,
+        host: &SshPoolHost,
+        claim: &Claim,
+    ) -> ProviderResult<ProviderInstance> {
+        tracing::debug!("Host {} is claimed by {}", host.id(), claim.instance);
+
+        Ok(ProviderInstance {
+            id: instance_id(host, &claim.instance),
+            name: claim.instance.clone(),
+            region: None,
+            size: None,
+            ip: resolve_host(&host.host).await?,
+            status: InstanceStatus::Active,
+            created_at: claim.claimed_at,
+        })
+    }
+
+    /// Try to claim a host. Returns `Ok(false)` if it is already taken.
+    async fn try_claim(
+        &self,
+        client: &SshClient,
+        host: &SshPoolHost,
+        instance_name: &str,
+    ) -> ProviderResult<bool> {
+        let output = client
+            .exec(&privileged(
+                &host.user,
+                &claim_script(instance_name, &self.dev_user, Utc::now()),
+            ))
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Failed to claim {}: {}", host.host, e),
+            })?;
+
+        if !output.success {
+            tracing::warn!("Cannot claim {}: {}", host.id(), output.stderr.trim());
+            return Ok(false);
+        }
+
+        Ok(output.stdout.contains("claimed"))
+    }
+
+    /// Seed cloud-init with the user data and start it in the background.
+    async fn bootstrap(
+        &self,
+        client: &SshClient,
+        host: &SshPoolHost,
+        instance_name: &str,
+        user_data: &str,
+    ) -> ProviderResult<()> {
+        let staging = format!("/tmp/spuff-user-data-{}", uuid::Uuid::new_v4());
+
+        let sftp = client.sftp().await.map_err(|e| ProviderError::Other {
+            message: format!("Failed to open SFTP on {}: {}", host.host, e),
+        })?;
+        sftp.write(&staging, user_data.as_bytes())
+            .await
+            .map_err(|e| ProviderError::Other {
+                message: format!("Failed to upload user data to {}: {}", host.host, e),
+            })?;
+
+        self.run_privileged(client, host, &bootstrap_script(&staging, instance_name))
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Provider for SshPoolProvider {
+    fn name(&self) -> &'static str {
+        "ssh"
+    }
+
+    async fn create_instance(&self, request: &InstanceRequest) -> ProviderResult<ProviderInstance> {
+        for host in &self.hosts {
+            let client = match self.connect(host).await {
+                Ok(client) => client,
+                Err(e) => {
+                    tracing::warn!("Skipping host {}: {}", host.id(), e);
+                    continue;
+                }
+            };
+
+            if !self.try_claim(&client, host, &request.name).await? {
+                tracing::debug!("Host {} is already claimed", host.id());
+                continue;
+            }
+
+            if let Some(user_data) = &request.user_data {
+                if let Err(e) = self
+                    .bootstrap(&client, host, &request.name, user_data)
+                    .await
+                {
+                    // Give the host back so a failed bootstrap doesn't leak it
+                    let _ = self
+                        .run_privileged(
+                            &client,
+                            host,
+                            &cleanup_script(&self.dev_user, &request.name),
+                        )
+                        .await;
+                    return Err(e);
+                }
+            }
+
+            let claim = self
+                .read_claim(&client, host)
+                .await?
+                .ok_or_else(|| ProviderError::not_found("claim", host.id()))?;
+            return self.to_instance(host, &claim).await;
+        }
+
+        Err(ProviderError::quota(
+            "hosts",
+            "No free host in the SSH pool. Destroy an environment or add hosts to 'ssh_pool'",
+        ))
+    }
+
+    async fn destroy_instance(&self, id: &str) -> ProviderResult<()> {
+        let Some((host, instance)) = self.find_instance(id) else {
+            // Host was removed from the pool; nothing left to release
+            return Ok(());
+        };
+
+        // The script leaves the host alone unless the claim is still ours
+        let client = self.connect(host).await?;
+        self.run_privileged(&client, host, &cleanup_script(&self.dev_user, instance))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_instance(&self, id: &str) -> ProviderResult<Option<ProviderInstance>> {
+        let Some((host, instance)) = self.find_instance(id) else {
+            return Ok(None);
+        };
+
+        // Released and claimed again by another environment: ours is gone
+        let client = self.connect(host).await?;
+        match self.read_claim(&client, host).await? {
+            Some(claim) if claim.instance == instance => {
+                Ok(Some(self.to_instance(host, &claim).await?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn list_instances(&self) -> ProviderResult<Vec<ProviderInstance>> {
+        let mut instances = Vec::new();
+
+        for host in &self.hosts {
+            let client = match self.connect(host).await {
+                Ok(client) => client,
+                Err(e) => {
+                    tracing::warn!("Skipping host {}: {}", host.id(), e);
+                    continue;
+                }
+            };
+
+            if let Some(claim) = self.read_claim(&client, host).await? {
+                instances.push(self.to_instance(host, &claim).await?);
+            }
+        }
+
+        Ok(instances)
+    }
+
+    async fn wait_ready(&self, id: &str) -> ProviderResult<ProviderInstance> {
+        // Pool hosts are already running; the claim is all we need
+        self.get_instance(id)
+            .await?
+            .ok_or_else(|| ProviderError::not_found("instance", id))
+    }
+
+    async fn create_snapshot(&self, _instance_id: &str, _name: &str) -> ProviderResult<Snapshot> {
+        Err(ProviderError::NotSupported {
+            feature: "snapshots on ssh hosts".to_string(),
+        })
+    }
+
+    async fn list_snapshots(&self) -> ProviderResult<Vec<Snapshot>> {
+        Ok(vec![])
+    }
+
+    async fn delete_snapshot(&self, _id: &str) -> ProviderResult<()> {
+        Err(ProviderError::NotSupported {
+            feature: "snapshots on ssh hosts".to_string(),
+        })
+    }
+
+    fn supports_snapshots(&self) -> bool {
+        false
+    }
+}
+
+/// Factory for creating SSH pool providers.
+pub struct SshPoolFactory {
+    pool: SshPoolConfig,
+    key_path: String,
+    dev_user: String,
+}
+
+impl SshPoolFactory {
+    /// Create a factory for a pool, admin key fallback and dev user.
+    pub fn new(
+        pool: SshPoolConfig,
+        key_path: impl Into<String>,
+        dev_user: impl Into<String>,
+    ) -> Self {
+        Self {
+            pool,
+            key_path: key_path.into(),
+            dev_user: dev_user.into(),
+        }
+    }
+}
+
+impl Default for SshPoolFactory {
+    fn default() -> Self {
+        Self::new(SshPoolConfig::default(), "~/.ssh/id_ed25519", "dev")
+    }
+}
+
+impl ProviderFactory for SshPoolFactory {
+    fn provider_type(&self) -> ProviderType {
+        ProviderType::Ssh
+    }
+
+    fn create(
+        &self,
+        _token: &str,
+        timeouts: ProviderTimeouts,
+    ) -> ProviderResult<Box<dyn Provider>> {
+        // Hosts are reached with SSH keys, no API token involved
+        Ok(Box::new(SshPoolProvider::new(
+            self.pool.clone(),
+            &self.key_path,
+            &self.dev_user,
+            timeouts,
+        )?))
+    }
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+async fn resolve_host(host: &str) -> ProviderResult<IpAddr> {
+    if let Ok(ip) = host.parse() {
+        return Ok(ip);
+    }
+
+    tokio::net::lookup_host((host, SSH_PORT))
+        .await
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .map(|addr| addr.ip())
+        .ok_or_else(|| ProviderError::Other {
+            message: format!("Failed to resolve host '{}'", host),
+        })
+}
+
+/// Instance ID of `instance`'s claim on `host`.
+fn instance_id(host: &SshPoolHost, instance: &str) -> String {
+    format!("{}@{}", instance, host.id())
+}
+
+/// Quote a string for a POSIX shell.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Escape `s` for use as a literal in a sed (basic) regex delimited by `/`.
+fn sed_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | '/' | '.' | '*' | '[' | ']' | '^' | '$') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Wrap a script so it runs as root, using sudo for non-root admin users.
+fn privileged(user: &str, script: &str) -> String {
+    if user == "root" {
+        format!("sh -c {}", shell_quote(script))
+    } else {
+        format!("sudo -n sh -c {}", shell_quote(script))
+    }
+}
+
+/// Atomically create the claim file; prints "claimed" on success.
+fn claim_script(instance_name: &str, dev_user: &str, now: DateTime<Utc>) -> String {
+    format!(
+        r#"set -e
+mkdir -p "$(dirname {claim})"
+if [ -e {claim} ]; then echo busy; exit 0; fi
+if id -u {user} >/dev/null 2>&1; then existed=true; else existed=false; fi
+set -C
+printf 'instance=%s\nclaimed_at=%s\nuser_existed=%s\n' {name} {now} "$existed" > {claim}
+echo claimed"#,
+        claim = CLAIM_FILE,
+        user = shell_quote(dev_user),
+        name = shell_quote(instance_name),
+        now = now.to_rfc3339(),
+    )
+}
+
+/// Install the user data as a NoCloud seed and run the cloud-init stages in
+/// the background, restricted to the modules listed in [`CLOUD_CFG`].
+fn bootstrap_script(staging_path: &str, instance_name: &str) -> String {
+    format!(
+        r#"set -e
+if ! command -v cloud-init >/dev/null 2>&1; then
+  export DEBIAN_FRONTEND=noninteractive
+  apt-get update -qq && apt-get install -y -qq cloud-init
+fi
+mkdir -p {seed}
+install -m 0600 {staging} {seed}/user-data
+rm -f {staging}
+printf 'instance-id: %s\n' {name} > {seed}/meta-data
+cat > {cfg} <<'SPUFF_CLOUD_CFG'
+{cloud_cfg}SPUFF_CLOUD_CFG
+cloud-init clean --logs
+setsid nohup sh -c 'cloud-init init --local && cloud-init init && cloud-init modules --mode=config && cloud-init modules --mode=final' </dev/null >/dev/null 2>&1 &"#,
+        seed = SEED_DIR,
+        staging = shell_quote(staging_path),
+        name = shell_quote(instance_name),
+        cfg = CLOUD_CFG_FILE,
+        cloud_cfg = CLOUD_CFG,
+    )
+}
+
+/// Undo the bootstrap and release the host. Does nothing unless the host is
+/// claimed by `instance_name`, so it is safe to run on a free host or one
+/// claimed by another environment.
+///
+/// The dev user is only removed if spuff created it.
+fn cleanup_script(dev_user: &str, instance_name: &str) -> String {
+    format!(
+        r#"grep -qxF {owner} {claim} 2>/dev/null || exit 0
+systemctl disable --now spuff-agent.service spuff-bootstrap.service >/dev/null 2>&1 || true
+rm -f /etc/systemd/system/spuff-agent.service /etc/systemd/system/spuff-bootstrap.service
+systemctl daemon-reload >/dev/null 2>&1 || true
+if grep -qx 'user_existed=false' {claim}; then
+  pkill -KILL -u {user} >/dev/null 2>&1 || true
+  userdel -r {user} >/dev/null 2>&1 || true
+  sed -i {sudoers_line} /etc/sudoers.d/90-cloud-init-users 2>/dev/null || true
+fi
+rm -rf /opt/spuff {seed} {cfg} /tmp/spuff-last-activity
+rm -f {claim}"#,
+        owner = shell_quote(&format!("instance={}", instance_name)),
+        claim = CLAIM_FILE,
+        user = shell_quote(dev_user),
+        sudoers_line = shell_quote(&format!("/^{} /d", sed_escape(dev_user))),
+        seed = SEED_DIR,
+        cfg = CLOUD_CFG_FILE,
+    )
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn pool(hosts: &[&str]) -> SshPoolConfig {
+        SshPoolConfig {
+            hosts: hosts
+                .iter()
+                .map(|h| SshPoolHost {
+                    host: h.to_string(),
+                    name: None,
+                    user: default_admin_user(),
+                    key_path: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_pool_config_deserialize() {
+        let yaml = r#"
+hosts:
+  - host: 10.0.0.5
+  - host: build-box.lan
+    name: build
+    user: admin
+    key_path: ~/.ssh/admin
+"#;
+        let config: SshPoolConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.hosts.len(), 2);
+        assert_eq!(config.hosts[0].user, "root");
+        assert_eq!(config.hosts[0].id(), "10.0.0.5");
+        assert_eq!(config.hosts[1].id(), "build");
+        assert_eq!(config.hosts[1].key_path.as_deref(), Some("~/.ssh/admin"));
+    }
+
+    #[test]
+    fn test_new_requires_hosts() {
+        let result = SshPoolProvider::new(
+            SshPoolConfig::default(),
+            "~/.ssh/id_ed25519",
+            "dev",
+            ProviderTimeouts::default(),
+        );
+        assert!(matches!(result, Err(ProviderError::InvalidConfig { .. })));
+    }
+
+    #[test]
+    fn test_new_rejects_duplicate_names() {
+        let result = SshPoolProvider::new(
+            pool(&["a.lan", "a.lan"]),
+            "~/.ssh/id_ed25519",
+            "dev",
+            ProviderTimeouts::default(),
+        );
+        assert!(matches!(result, Err(ProviderError::InvalidConfig { .. })));
+    }
+
+    #[test]
+    fn test_find_host() {
+        let provider = SshPoolProvider::new(
+            pool(&["a.lan", "b.lan"]),
+            "~/.ssh/id_ed25519",
+            "dev",
+            ProviderTimeouts::default(),
+        )
+        .unwrap();
+
+        assert_eq!(provider.find_host("b.lan").unwrap().host, "b.lan");
+        assert!(provider.find_host("c.lan").is_none());
+
+        let (host, instance) = provider.find_instance("spuff-abc@b.lan").unwrap();
+        assert_eq!(host.host, "b.lan");
+        assert_eq!(instance, "spuff-abc");
+        assert!(provider.find_instance("b.lan").is_none());
+        assert!(provider.find_instance("spuff-abc@c.lan").is_none());
+    }
+
+    #[test]
+    fn test_instance_id() {
+        let host = &pool(&["a.lan"]).hosts[0];
+        assert_eq!(instance_id(host, "spuff-abc"), "spuff-abc@a.lan");
+    }
+
+    #[test]
+    fn test_claim_parse() {
+        let claim = Claim::parse(
+            "instance=spuff-abc\nclaimed_at=2025-01-01T00:00:00+00:00\nuser_existed=false\n",
+        )
+        .unwrap();
+
+        assert_eq!(claim.instance, "spuff-abc");
+        assert_eq!(
+            claim.claimed_at,
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+        );
+        assert!(!claim.user_existed);
+    }
+
+    #[test]
+    fn test_claim_parse_empty() {
+        assert!(Claim::parse("").is_none());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("dev"), "'dev'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn test_privileged() {
+        assert_eq!(privileged("root", "id"), "sh -c 'id'");
+        assert_eq!(privileged("admin", "id"), "sudo -n sh -c 'id'");
+    }
+
+    #[test]
+    fn test_claim_script() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let script = claim_script("spuff-abc", "dev", now);
+
+        assert!(script.contains("set -C"));
+        assert!(script.contains("id -u 'dev'"));
+        assert!(script.contains("'spuff-abc' 2025-01-01T00:00:00+00:00"));
+        assert!(script.contains(CLAIM_FILE));
+    }
+
+    #[test]
+    fn test_bootstrap_script_uses_nocloud_seed() {
+        let script = bootstrap_script("/tmp/spuff-user-data-1", "spuff-abc");
+
+        assert!(script.contains("/var/lib/cloud/seed/nocloud/user-data"));
+        assert!(script.contains("datasource_list: [ NoCloud, None ]"));
+        assert!(script.contains("cloud-init modules --mode=final"));
+    }
+
+    #[test]
+    fn test_bootstrap_script_leaves_host_keys_and_network_alone() {
+        let script = bootstrap_script("/tmp/spuff-user-data-1", "spuff-abc");
+
+        assert!(script.contains("ssh_deletekeys: false"));
+        assert!(script.contains("network:\n  config: disabled"));
+        // The ssh module would rewrite sshd_config and regenerate host keys
+        assert!(!script.contains("  - ssh\n"));
+        assert!(script.contains("  - users_groups\n"));
+        assert!(script.contains("  - scripts_user\n"));
+    }
+
+    #[test]
+    fn test_cleanup_script_only_removes_created_user() {
+        let script = cleanup_script("dev", "spuff-abc");
+
+        assert!(script.starts_with(
+            "grep -qxF 'instance=spuff-abc' /var/lib/spuff/claim 2>/dev/null || exit 0"
+        ));
+        assert!(script.contains("if grep -qx 'user_existed=false'"));
+        assert!(script.contains("userdel -r 'dev'"));
+        assert!(script.contains("sed -i '/^dev /d' /etc/sudoers.d/90-cloud-init-users"));
+        assert!(script.ends_with("rm -f /var/lib/spuff/claim"));
+    }
+
+    #[test]
+    fn test_cleanup_script_escapes_user_in_sed() {
+        assert_eq!(sed_escape("dev"), "dev");
+        assert_eq!(sed_escape("a.b/c*"), r"a\.b\/c\*");
+
+        let script = cleanup_script("x.y/z", "spuff-abc");
+        assert!(script.contains(r"sed -i '/^x\.y\/z /d'"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_host_ip_literal() {
+        let ip = resolve_host("192.168.1.10").await.unwrap();
+        assert_eq!(ip.to_string(), "192.168.1.10");
+    }
+
+    #[test]
+    fn test_factory_type() {
+        let factory = SshPoolFactory::default();
+        assert_eq!(factory.provider_type(), ProviderType::Ssh);
+        assert!(factory.is_implemented());
+    }
+
+    #[test]
+    fn test_factory_empty_pool() {
+        let factory = SshPoolFactory::default();
+        let result = factory.create("", ProviderTimeouts::default());
+        assert!(matches!(result, Err(ProviderError::InvalidConfig { .. })));
+    }
+}
@@ -6,7 +6,8 @@ use std::net::ToSocketAddrs;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use russh::client::{self, Handle};
+use russh::client::{self, Handle, Msg};
+use russh::ChannelStream;
 use tokio::net::UnixStream;
 use tokio::sync::Mutex;
 
@@ -14,12 +15,16 @@ use crate::error::{Result, SpuffError};
 use crate::ssh::config::SshConfig;
 use crate::ssh::exec::CommandOutput;
 use crate::ssh::sftp::SftpClient;
-use crate::ssh::tunnel::PortForward;
+use crate::ssh::tunnel::{PortForward, ReverseForwards};
 
 /// SSH client wrapper over russh.
+///
+/// Clones share the same connection.
+#[derive(Clone)]
 pub struct SshClient {
     session: Arc<Mutex<Handle<ClientHandler>>>,
     host: String,
+    reverse_forwards: ReverseForwards,
 }
 
 impl SshClient {
@@ -42,13 +47,21 @@ impl SshClient {
             .next()
             .ok_or_else(|| SpuffError::Ssh(format!("No address found for {}", host)))?;
 
+        let reverse_forwards = ReverseForwards::default();
         let handler = ClientHandler {
+            host: crate::ssh::host_keys::host_id(host, port),
             host_key_policy: config.host_key_policy,
+            reverse_forwards: reverse_forwards.clone(),
         };
 
-        let mut session = client::connect(russh_config, addr, handler)
-            .await
-            .map_err(|e| SpuffError::Ssh(format!("Connection failed: {}", e)))?;
+        let mut session =
+            client::connect(russh_config, addr, handler)
+                .await
+                .map_err(|e| match e {
+                    // Host key rejections carry their own explanation
+                    SpuffError::Ssh(_) => e,
+                    e => SpuffError::Ssh(format!("Connection failed: {}", e)),
+                })?;
 
         // Authenticate
         Self::authenticate(&mut session, config).await?;
@@ -56,6 +69,7 @@ impl SshClient {
         Ok(Self {
             session: Arc::new(Mutex::new(session)),
             host: host.to_string(),
+            reverse_forwards,
         })
     }
 
@@ -206,6 +220,32 @@ impl SshClient {
         crate::ssh::exec::exec_command(&session, command).await
     }
 
+    /// Start a command and return its stdin/stdout as a byte stream.
+    ///
+    /// The stream stays usable as long as this client is alive.
+    pub async fn exec_stream(&self, command: &str) -> Result<ChannelStream<Msg>> {
+        let session = self.session.lock().await;
+        crate::ssh::exec::exec_stream(&session, command).await
+    }
+
+    /// Open a `direct-tcpip` channel to `host:port` as seen from the remote
+    /// host, as a byte stream.
+    ///
+    /// The stream stays usable as long as this client is alive.
+    pub async fn direct_tcpip(&self, host: &str, port: u16) -> Result<ChannelStream<Msg>> {
+        let session = self.session.lock().await;
+        let channel = session
+            .channel_open_direct_tcpip(host, port as u32, "127.0.0.1", 0)
+            .await
+            .map_err(|e| {
+                SpuffError::Ssh(format!(
+                    "Failed to open channel to {}:{}: {}",
+                    host, port, e
+                ))
+            })?;
+        Ok(channel.into_stream())
+    }
+
     /// Start an interactive shell session with PTY.
     pub async fn shell(&self) -> Result<()> {
         let session = self.session.lock().await;
@@ -218,6 +258,11 @@ impl SshClient {
         crate::ssh::pty::exec_interactive(&session, command).await
     }
 
+    /// Whether the connection to the server is gone.
+    pub async fn is_closed(&self) -> bool {
+        self.session.lock().await.is_closed()
+    }
+
     /// Get an SFTP client for file transfers.
     pub async fn sftp(&self) -> Result<SftpClient> {
         let session = self.session.lock().await;
@@ -239,6 +284,28 @@ impl SshClient {
         .await
     }
 
+    /// Create a remote (reverse) port forward.
+    ///
+    /// Connections to `remote_port` on the remote host reach `local_port` here.
+    pub async fn forward_remote_port(
+        &self,
+        remote_port: u16,
+        local_port: u16,
+    ) -> Result<PortForward> {
+        crate::ssh::tunnel::create_remote_forward(
+            self.session.clone(),
+            self.reverse_forwards.clone(),
+            remote_port,
+            local_port,
+        )
+        .await
+    }
+
+    /// Start a SOCKS5 proxy on `local_port` that connects from the remote host.
+    pub async fn forward_dynamic_port(&self, local_port: u16) -> Result<PortForward> {
+        crate::ssh::tunnel::create_dynamic_forward(self.session.clone(), local_port).await
+    }
+
     /// Create multiple port forwards.
     pub async fn forward_ports(&self, ports: &[u16]) -> Result<Vec<PortForward>> {
         let mut forwards = Vec::new();
@@ -252,7 +319,11 @@ impl SshClient {
 
 /// Client handler for russh connection callbacks.
 pub struct ClientHandler {
+    /// Name the host key is pinned under (see [`crate::ssh::host_keys::host_id`]).
+    pub host: String,
     pub host_key_policy: crate::ssh::config::HostKeyPolicy,
+    /// Reverse forwards the server may open channels for.
+    pub(crate) reverse_forwards: ReverseForwards,
 }
 
 #[async_trait]
@@ -261,13 +332,33 @@ impl client::Handler for ClientHandler {
 
     async fn check_server_key(
         &mut self,
-        _server_public_key: &russh_keys::PublicKey,
+        server_public_key: &russh_keys::PublicKey,
     ) -> std::result::Result<bool, Self::Error> {
         match self.host_key_policy {
             crate::ssh::config::HostKeyPolicy::AcceptAny => Ok(true),
-            crate::ssh::config::HostKeyPolicy::AcceptNew => Ok(true),
+            crate::ssh::config::HostKeyPolicy::AcceptNew => {
+                crate::ssh::host_keys::verify(&self.host, server_public_key)?;
+                Ok(true)
+            }
         }
     }
+
+    async fn server_channel_open_forwarded_tcpip(
+        &mut self,
+        channel: russh::Channel<client::Msg>,
+        _connected_address: &str,
+        connected_port: u32,
+        _originator_address: &str,
+        _originator_port: u32,
+        _session: &mut client::Session,
+    ) -> std::result::Result<(), Self::Error> {
+        crate::ssh::tunnel::accept_forwarded_channel(
+            &self.reverse_forwards,
+            channel,
+            connected_port,
+        );
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -277,11 +368,39 @@ mod tests {
     #[test]
     fn test_client_handler_accepts_keys() {
         let handler = ClientHandler {
+            host: "10.0.0.1".to_string(),
             host_key_policy: crate::ssh::config::HostKeyPolicy::AcceptAny,
+            reverse_forwards: ReverseForwards::default(),
         };
         assert!(matches!(
             handler.host_key_policy,
             crate::ssh::config::HostKeyPolicy::AcceptAny
         ));
     }
+
+    #[tokio::test]
+    async fn test_client_handler_rejects_changed_key() {
+        use client::Handler;
+        use ssh_key::{Algorithm, PrivateKey};
+
+        let pinned = PrivateKey::random(&mut rand::thread_rng(), Algorithm::Ed25519).unwrap();
+        let presented = PrivateKey::random(&mut rand::thread_rng(), Algorithm::Ed25519).unwrap();
+        crate::ssh::host_keys::pin(
+            "[192.0.2.1]:2200",
+            &pinned.public_key().to_openssh().unwrap(),
+        );
+
+        let mut handler = ClientHandler {
+            host: "[192.0.2.1]:2200".to_string(),
+            host_key_policy: crate::ssh::config::HostKeyPolicy::AcceptNew,
+            reverse_forwards: ReverseForwards::default(),
+        };
+
+        assert!(handler.check_server_key(pinned.public_key()).await.unwrap());
+        let err = handler
+            .check_server_key(presented.public_key())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SpuffError::Ssh(msg) if msg.contains("has changed")));
+    }
 }
@@ -19,10 +19,13 @@ pub struct SshConfig {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum HostKeyPolicy {
     /// Accept any host key (insecure, but matches OpenSSH StrictHostKeyChecking=no).
-    #[default]
+    #[allow(dead_code)]
     AcceptAny,
 
     /// Accept new keys but reject changed keys (matches StrictHostKeyChecking=accept-new).
+    ///
+    /// Keys are pinned in [`crate::ssh::host_keys`].
+    #[default]
     AcceptNew,
 }
 
@@ -32,7 +35,7 @@ impl SshConfig {
         Self {
             user: user.into(),
             key_path: key_path.into(),
-            host_key_policy: HostKeyPolicy::AcceptAny,
+            host_key_policy: HostKeyPolicy::default(),
         }
     }
 }
@@ -52,6 +55,6 @@ mod tests {
         let config = SshConfig::new("root", "/path/to/key");
 
         assert_eq!(config.user, "root");
-        assert_eq!(config.host_key_policy, HostKeyPolicy::AcceptAny);
+        assert_eq!(config.host_key_policy, HostKeyPolicy::AcceptNew);
     }
 }
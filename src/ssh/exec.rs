@@ -1,9 +1,10 @@
 //! Remote command execution.
 //!
-//! Provides non-interactive command execution with stdout/stderr capture.
+//! Provides non-interactive command execution with stdout/stderr capture,
+//! and a raw byte stream to a running command.
 
-use russh::client::Handle;
-use russh::ChannelMsg;
+use russh::client::{Handle, Msg};
+use russh::{ChannelMsg, ChannelStream};
 
 use crate::error::{Result, SpuffError};
 use crate::ssh::client::ClientHandler;
@@ -34,18 +35,13 @@ impl CommandOutput {
 
 /// Execute a command on the remote host (non-interactive).
 pub async fn exec_command(session: &Handle<ClientHandler>, command: &str) -> Result<CommandOutput> {
-    let wrapped_command = format!(
-        "bash --norc --noprofile -c '{}'",
-        command.replace('\'', "'\\''")
-    );
-
     let mut channel = session
         .channel_open_session()
         .await
         .map_err(|e| SpuffError::Ssh(format!("Failed to open channel: {}", e)))?;
 
     channel
-        .exec(true, wrapped_command.as_bytes())
+        .exec(true, wrap_command(command).as_bytes())
         .await
         .map_err(|e| SpuffError::Ssh(format!("Failed to execute command: {}", e)))?;
 
@@ -58,10 +54,8 @@ pub async fn exec_command(session: &Handle<ClientHandler>, command: &str) -> Res
             Some(ChannelMsg::Data { data }) => {
                 stdout.extend_from_slice(&data);
             }
-            Some(ChannelMsg::ExtendedData { data, ext }) => {
-                if ext == 1 {
-                    stderr.extend_from_slice(&data);
-                }
+            Some(ChannelMsg::ExtendedData { data, ext: 1 }) => {
+                stderr.extend_from_slice(&data);
             }
             Some(ChannelMsg::ExitStatus { exit_status }) => {
                 exit_code = exit_status;
@@ -88,6 +82,36 @@ pub async fn exec_command(session: &Handle<ClientHandler>, command: &str) -> Res
     Ok(CommandOutput::new(stdout_str, stderr_str, exit_code))
 }
 
+/// Start a command on the remote host and return its stdin/stdout as a
+/// byte stream.
+///
+/// Stderr and the exit status are discarded, so this suits commands that
+/// report everything through stdout (like `tail -F` following a log).
+pub async fn exec_stream(
+    session: &Handle<ClientHandler>,
+    command: &str,
+) -> Result<ChannelStream<Msg>> {
+    let channel = session
+        .channel_open_session()
+        .await
+        .map_err(|e| SpuffError::Ssh(format!("Failed to open channel: {}", e)))?;
+
+    channel
+        .exec(true, wrap_command(command).as_bytes())
+        .await
+        .map_err(|e| SpuffError::Ssh(format!("Failed to execute command: {}", e)))?;
+
+    Ok(channel.into_stream())
+}
+
+/// Run the command in a bare bash, so profile output doesn't mix with its own.
+fn wrap_command(command: &str) -> String {
+    format!(
+        "bash --norc --noprofile -c '{}'",
+        command.replace('\'', "'\\''")
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,281 @@
+//! Host key pinning.
+//!
+//! Instances provisioned by `spuff up` get an ed25519 host key generated
+//! locally and installed by cloud-init, so their key is pinned before the
+//! first connection. Other hosts (pool hosts, adopted instances, environments
+//! created before pinning existed) are trusted on first use. Once a host is
+//! pinned, a different key fails the connection.
+//!
+//! Pins are stored in the state database, which can't be opened while an SSH
+//! handshake runs (callers often hold it open). The CLI loads them into a
+//! process-wide table before running a command and stores the keys learned
+//! on first use once it finishes. If the pins can't be loaded, no host is
+//! trusted on first use: an unknown key might be one that is pinned. The
+//! `ssh` processes spuff spawns (rsync, SSHFS) read the same pins from a
+//! generated known_hosts file.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+use ssh_key::{Algorithm, HashAlg, LineEnding, PrivateKey, PublicKey};
+
+use crate::config::AppConfig;
+use crate::error::{Result, SpuffError};
+use crate::state::StateDb;
+
+static KNOWN_HOSTS: Mutex<KnownHosts> = Mutex::new(KnownHosts::new());
+
+/// Host key generated for a new instance.
+pub struct HostKeyPair {
+    /// Private key in OpenSSH format, installed on the instance.
+    pub private_key: String,
+
+    /// Public key in OpenSSH format (`ssh-ed25519 AAAA...`), pinned locally.
+    pub public_key: String,
+}
+
+impl HostKeyPair {
+    /// Generate a new ed25519 host key.
+    pub fn generate() -> Result<Self> {
+        let key = PrivateKey::random(&mut rand::thread_rng(), Algorithm::Ed25519)
+            .map_err(|e| SpuffError::Ssh(format!("Failed to generate host key: {}", e)))?;
+
+        let private_key = key
+            .to_openssh(LineEnding::LF)
+            .map_err(|e| SpuffError::Ssh(format!("Failed to encode host key: {}", e)))?
+            .to_string();
+        let public_key = key
+            .public_key()
+            .to_openssh()
+            .map_err(|e| SpuffError::Ssh(format!("Failed to encode host key: {}", e)))?;
+
+        Ok(Self {
+            private_key,
+            public_key,
+        })
+    }
+}
+
+/// Name a host is pinned under: the host itself on port 22, `[host]:port`
+/// otherwise (as in OpenSSH's known_hosts).
+pub fn host_id(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+/// Pin `key` (OpenSSH format) for `host`, replacing any previous pin.
+pub fn pin(host: &str, key: &str) {
+    known_hosts().pin(host, key);
+}
+
+/// Load the pins stored in the state database into this process.
+///
+/// On failure, hosts without a pin are rejected until a load succeeds.
+pub fn load_pins() -> Result<()> {
+    let pins = StateDb::open().and_then(|db| db.list_host_keys());
+    let mut hosts = known_hosts();
+    match pins {
+        Ok(pins) => {
+            hosts.load_failed = false;
+            for pin in pins {
+                hosts.pin(&pin.host, &pin.key);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            hosts.load_failed = true;
+            Err(e)
+        }
+    }
+}
+
+/// Store the keys this process trusted on first use in the state database.
+pub fn store_learned() -> Result<()> {
+    let learned = std::mem::take(&mut known_hosts().learned);
+    if learned.is_empty() {
+        return Ok(());
+    }
+
+    let db = StateDb::open()?;
+    for (host, key) in learned {
+        db.pin_host_key(&host, &key)?;
+    }
+    Ok(())
+}
+
+/// Write the pinned keys to `~/.spuff/known_hosts` for the `ssh` processes
+/// spuff spawns, and return its path.
+pub fn write_known_hosts() -> Result<PathBuf> {
+    let path = AppConfig::config_dir()?.join("known_hosts");
+    let contents: String = known_hosts()
+        .pinned
+        .iter()
+        .map(|(host, key)| format!("{} {}\n", host, key))
+        .collect();
+    std::fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// Check the key a server presented against the pin for `host`.
+pub(crate) fn verify(host: &str, key: &PublicKey) -> Result<()> {
+    known_hosts().verify(host, key)
+}
+
+/// SHA256 fingerprint of an OpenSSH public key, as shown by `ssh-keygen -l`.
+pub fn fingerprint(key: &str) -> String {
+    PublicKey::from_openssh(key)
+        .map(|k| k.fingerprint(HashAlg::Sha256).to_string())
+        .unwrap_or_else(|_| "<invalid key>".to_string())
+}
+
+fn known_hosts() -> MutexGuard<'static, KnownHosts> {
+    // The table stays consistent even if a holder panicked
+    KNOWN_HOSTS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+struct KnownHosts {
+    pinned: BTreeMap<String, String>,
+    learned: Vec<(String, String)>,
+    /// The stored pins couldn't be loaded, so `pinned` may be missing some
+    load_failed: bool,
+}
+
+impl KnownHosts {
+    const fn new() -> Self {
+        Self {
+            pinned: BTreeMap::new(),
+            learned: Vec::new(),
+            load_failed: false,
+        }
+    }
+
+    fn pin(&mut self, host: &str, key: &str) {
+        self.pinned.insert(host.to_string(), key.to_string());
+    }
+
+    fn verify(&mut self, host: &str, key: &PublicKey) -> Result<()> {
+        let presented = key
+            .to_openssh()
+            .map_err(|e| SpuffError::Ssh(format!("Invalid host key from {}: {}", host, e)))?;
+
+        let Some(pinned) = self.pinned.get(host) else {
+            if self.load_failed {
+                return Err(SpuffError::Ssh(format!(
+                    "Not trusting the host key for {}: pinned host keys couldn't \
+                     be loaded from the state database, so it may be pinned to \
+                     another key. Try again once no other spuff command is using it.",
+                    host
+                )));
+            }
+            tracing::info!(
+                "Pinning host key {} for {}",
+                key.fingerprint(HashAlg::Sha256),
+                host
+            );
+            self.pin(host, &presented);
+            self.learned.push((host.to_string(), presented));
+            return Ok(());
+        };
+
+        // Compare key material only; comments don't matter
+        let matches = PublicKey::from_openssh(pinned)
+            .map(|pinned| pinned.key_data() == key.key_data())
+            .unwrap_or(false);
+        if matches {
+            return Ok(());
+        }
+
+        Err(SpuffError::Ssh(format!(
+            "Host key for {} has changed!\n\n  \
+             expected: {}\n  \
+             received: {}\n\n\
+             Someone could be intercepting the connection. If the host was \
+             rebuilt outside spuff, recreate the environment with 'spuff down' \
+             and 'spuff up'.",
+            host,
+            fingerprint(pinned),
+            key.fingerprint(HashAlg::Sha256)
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public_key(pair: &HostKeyPair) -> PublicKey {
+        PublicKey::from_openssh(&pair.public_key).unwrap()
+    }
+
+    #[test]
+    fn test_host_id() {
+        assert_eq!(host_id("10.0.0.1", 22), "10.0.0.1");
+        assert_eq!(host_id("127.0.0.1", 2222), "[127.0.0.1]:2222");
+    }
+
+    #[test]
+    fn test_generated_host_key_is_ed25519() {
+        let pair = HostKeyPair::generate().unwrap();
+
+        assert!(pair.public_key.starts_with("ssh-ed25519 "));
+        assert!(pair.private_key.contains("BEGIN OPENSSH PRIVATE KEY"));
+        assert!(fingerprint(&pair.public_key).starts_with("SHA256:"));
+    }
+
+    #[test]
+    fn test_pinned_key_must_match() {
+        let pair = HostKeyPair::generate().unwrap();
+        let other = HostKeyPair::generate().unwrap();
+        let mut hosts = KnownHosts::new();
+        hosts.pin("10.0.0.1", &pair.public_key);
+
+        assert!(hosts.verify("10.0.0.1", &public_key(&pair)).is_ok());
+
+        let err = hosts
+            .verify("10.0.0.1", &public_key(&other))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Host key for 10.0.0.1 has changed"));
+        assert!(err.contains(&fingerprint(&pair.public_key)));
+        assert!(hosts.learned.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_host_is_trusted_on_first_use() {
+        let pair = HostKeyPair::generate().unwrap();
+        let other = HostKeyPair::generate().unwrap();
+        let mut hosts = KnownHosts::new();
+
+        assert!(hosts.verify("pool-1", &public_key(&pair)).is_ok());
+        assert_eq!(hosts.learned.len(), 1);
+        assert_eq!(hosts.learned[0].0, "pool-1");
+
+        // Learned keys are enforced from then on
+        assert!(hosts.verify("pool-1", &public_key(&pair)).is_ok());
+        assert!(hosts.verify("pool-1", &public_key(&other)).is_err());
+        assert_eq!(hosts.learned.len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_host_is_rejected_when_pins_failed_to_load() {
+        let pair = HostKeyPair::generate().unwrap();
+        let other = HostKeyPair::generate().unwrap();
+        let mut hosts = KnownHosts::new();
+        hosts.pin("10.0.0.1", &pair.public_key);
+        hosts.load_failed = true;
+
+        let err = hosts
+            .verify("pool-1", &public_key(&other))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Not trusting the host key for pool-1"));
+        assert!(hosts.learned.is_empty());
+
+        // Hosts that are pinned still connect
+        assert!(hosts.verify("10.0.0.1", &public_key(&pair)).is_ok());
+    }
+}
@@ -8,6 +8,7 @@
 //! - [`keys`] - SSH key parsing, fingerprint calculation, passphrase detection
 //! - [`agent`] - SSH agent protocol communication (read-only)
 //! - [`client`] - SSH connection management
+//! - [`host_keys`] - Host key generation and pinning
 //! - [`exec`] - Remote command execution
 //! - [`sftp`] - File transfer via SFTP
 //! - [`tunnel`] - Port forwarding
@@ -17,6 +18,7 @@ mod agent;
 mod client;
 pub mod config;
 mod exec;
+pub mod host_keys;
 mod keys;
 pub mod managed_key;
 mod pty;
@@ -28,6 +30,8 @@ pub use agent::is_key_in_agent;
 pub use client::SshClient;
 pub use config::SshConfig;
 pub use keys::key_has_passphrase;
+pub use sftp::{RemoteEntry, RemoteMetadata, SftpClient};
+pub use tunnel::PortForward;
 
 use std::time::Duration;
 use tokio::net::TcpStream;
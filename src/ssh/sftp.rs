@@ -2,15 +2,47 @@
 //!
 //! Replaces `scp` binary with native SFTP over SSH.
 
+use std::io::SeekFrom;
 use std::path::Path;
 
 use russh::client::Handle;
+use russh_sftp::client::fs::File;
 use russh_sftp::client::SftpSession;
-use tokio::io::AsyncWriteExt;
+use russh_sftp::protocol::{FileAttributes, OpenFlags};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 
 use crate::error::{Result, SpuffError};
 use crate::ssh::client::ClientHandler;
 
+/// Metadata of a remote file or directory (symlinks are followed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoteMetadata {
+    pub is_dir: bool,
+    pub size: u64,
+    /// Permission bits (`0o755`)
+    pub mode: Option<u32>,
+    /// Modification time, seconds since the epoch
+    pub mtime: Option<u32>,
+}
+
+impl From<&FileAttributes> for RemoteMetadata {
+    fn from(attrs: &FileAttributes) -> Self {
+        Self {
+            is_dir: attrs.is_dir(),
+            size: attrs.size.unwrap_or(0),
+            mode: attrs.permissions.map(|mode| mode & 0o7777),
+            mtime: attrs.mtime,
+        }
+    }
+}
+
+/// An entry of a remote directory.
+#[derive(Debug, Clone)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub metadata: RemoteMetadata,
+}
+
 /// SFTP client for file transfers.
 pub struct SftpClient {
     session: SftpSession,
@@ -51,6 +83,11 @@ impl SftpClient {
             ))
         })?;
 
+        self.write(remote_path, &content).await
+    }
+
+    /// Write in-memory content to a file on the remote host.
+    pub async fn write(&self, remote_path: &str, content: &[u8]) -> Result<()> {
         // Create remote file
         let mut remote_file = self
             .session
@@ -60,7 +97,7 @@ impl SftpClient {
 
         // Write content
         remote_file
-            .write_all(&content)
+            .write_all(content)
             .await
             .map_err(|e| SpuffError::Ssh(format!("Failed to write to remote file: {}", e)))?;
 
@@ -72,4 +109,144 @@ impl SftpClient {
 
         Ok(())
     }
+
+    /// Metadata of a remote path, or `None` if it doesn't exist.
+    pub async fn metadata(&self, path: &str) -> Result<Option<RemoteMetadata>> {
+        let exists = self
+            .session
+            .try_exists(path)
+            .await
+            .map_err(|e| SpuffError::Ssh(format!("Failed to stat {}: {}", path, e)))?;
+        if !exists {
+            return Ok(None);
+        }
+
+        let attrs = self
+            .session
+            .metadata(path)
+            .await
+            .map_err(|e| SpuffError::Ssh(format!("Failed to stat {}: {}", path, e)))?;
+        Ok(Some(RemoteMetadata::from(&attrs)))
+    }
+
+    /// List a remote directory, without `.` and `..`. Entries that are
+    /// symlinks get the metadata of their target; dangling ones are left out.
+    pub async fn read_dir(&self, path: &str) -> Result<Vec<RemoteEntry>> {
+        let entries = self
+            .session
+            .read_dir(path)
+            .await
+            .map_err(|e| SpuffError::Ssh(format!("Failed to list {}: {}", path, e)))?;
+
+        let mut listed = Vec::new();
+        for entry in entries {
+            let name = entry.file_name();
+            if name == "." || name == ".." {
+                continue;
+            }
+
+            let attrs = entry.metadata();
+            let metadata = if attrs.is_symlink() {
+                let target = format!("{}/{}", path.trim_end_matches('/'), name);
+                match self.metadata(&target).await {
+                    Ok(Some(metadata)) => metadata,
+                    _ => continue,
+                }
+            } else {
+                RemoteMetadata::from(&attrs)
+            };
+            listed.push(RemoteEntry { name, metadata });
+        }
+        Ok(listed)
+    }
+
+    /// Create a remote directory and its missing parents.
+    pub async fn create_dir_all(&self, path: &str) -> Result<()> {
+        let mut current = String::new();
+        for component in path.split('/') {
+            if component.is_empty() {
+                if current.is_empty() {
+                    current.push('/');
+                }
+                continue;
+            }
+            if !current.is_empty() && !current.ends_with('/') {
+                current.push('/');
+            }
+            current.push_str(component);
+
+            if self.metadata(&current).await?.is_none() {
+                self.session
+                    .create_dir(current.as_str())
+                    .await
+                    .map_err(|e| {
+                        SpuffError::Ssh(format!("Failed to create directory {}: {}", current, e))
+                    })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Open a remote file for reading, starting at `offset`.
+    pub async fn open_read(&self, path: &str, offset: u64) -> Result<File> {
+        let mut file = self
+            .session
+            .open(path)
+            .await
+            .map_err(|e| SpuffError::Ssh(format!("Failed to open {}: {}", path, e)))?;
+        if offset > 0 {
+            file.seek(SeekFrom::Start(offset))
+                .await
+                .map_err(|e| SpuffError::Ssh(format!("Failed to seek in {}: {}", path, e)))?;
+        }
+        Ok(file)
+    }
+
+    /// Open a remote file for writing, appending to it if `append` is set
+    /// and truncating it otherwise.
+    pub async fn open_write(&self, path: &str, append: bool) -> Result<File> {
+        let mode = if append {
+            OpenFlags::APPEND
+        } else {
+            OpenFlags::TRUNCATE
+        };
+        self.session
+            .open_with_flags(path, OpenFlags::CREATE | OpenFlags::WRITE | mode)
+            .await
+            .map_err(|e| SpuffError::Ssh(format!("Failed to open {}: {}", path, e)))
+    }
+
+    /// Set the permission bits and modification time of a remote path.
+    pub async fn set_attributes(
+        &self,
+        path: &str,
+        mode: Option<u32>,
+        mtime: Option<u32>,
+    ) -> Result<()> {
+        let mut attrs = FileAttributes::empty();
+        attrs.permissions = mode;
+        // SFTP sets both times or neither
+        attrs.atime = mtime;
+        attrs.mtime = mtime;
+
+        self.session
+            .set_metadata(path, attrs)
+            .await
+            .map_err(|e| SpuffError::Ssh(format!("Failed to set attributes of {}: {}", path, e)))
+    }
+
+    /// Move a remote file over `to`, replacing it.
+    pub async fn replace(&self, from: &str, to: &str) -> Result<()> {
+        // SFTP v3 rename fails if the target exists
+        if self.metadata(to).await?.is_some() {
+            self.session
+                .remove_file(to)
+                .await
+                .map_err(|e| SpuffError::Ssh(format!("Failed to replace {}: {}", to, e)))?;
+        }
+        self.session
+            .rename(from, to)
+            .await
+            .map_err(|e| SpuffError::Ssh(format!("Failed to rename {} to {}: {}", from, to, e)))
+    }
 }
@@ -1,21 +1,38 @@
 //! SSH port forwarding (tunneling).
 //!
-//! Provides local port forwarding functionality.
+//! Provides local, remote (reverse) and dynamic (SOCKS5) port forwarding.
 
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use russh::client::Handle;
-use tokio::net::TcpListener;
+use russh::client::{Handle, Msg};
+use russh::Channel;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::Mutex;
 
 use crate::error::{Result, SpuffError};
 use crate::ssh::client::ClientHandler;
 
+/// Remote ports forwarded back to local ports, keyed by remote port.
+///
+/// Shared with [`ClientHandler`], which receives the forwarded connections.
+pub(crate) type ReverseForwards = Arc<std::sync::Mutex<HashMap<u32, u16>>>;
+
 /// A port forward handle that can be used to stop the tunnel.
 pub struct PortForward {
     running: Arc<AtomicBool>,
     task: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    /// Listener on the remote host to cancel on stop (reverse forwards).
+    remote: Option<RemoteListener>,
+}
+
+struct RemoteListener {
+    session: Arc<Mutex<Handle<ClientHandler>>>,
+    forwards: ReverseForwards,
+    port: u32,
 }
 
 impl PortForward {
@@ -27,6 +44,15 @@ impl PortForward {
         if let Some(task) = task_guard.take() {
             task.abort();
         }
+
+        if let Some(remote) = &self.remote {
+            lock_forwards(&remote.forwards).remove(&remote.port);
+            let mut session_guard = remote.session.lock().await;
+            let session: &mut Handle<ClientHandler> = &mut session_guard;
+            if let Err(e) = session.cancel_tcpip_forward("127.0.0.1", remote.port).await {
+                tracing::debug!("Failed to cancel remote forward {}: {}", remote.port, e);
+            }
+        }
     }
 }
 
@@ -116,14 +142,287 @@ pub async fn create_local_forward(
     Ok(PortForward {
         running,
         task: Arc::new(Mutex::new(Some(task))),
+        remote: None,
     })
 }
 
+/// Create a remote (reverse) port forward.
+///
+/// The remote host listens on `remote_port` on its loopback interface, and
+/// every connection to it is forwarded to `local_port` on localhost here.
+pub async fn create_remote_forward(
+    session: Arc<Mutex<Handle<ClientHandler>>>,
+    forwards: ReverseForwards,
+    remote_port: u16,
+    local_port: u16,
+) -> Result<PortForward> {
+    let port = remote_port as u32;
+
+    // Register first: connections can arrive as soon as the server listens
+    lock_forwards(&forwards).insert(port, local_port);
+
+    let result = {
+        let mut session_guard = session.lock().await;
+        let session: &mut Handle<ClientHandler> = &mut session_guard;
+        session.tcpip_forward("127.0.0.1", port).await
+    };
+    if let Err(e) = result {
+        lock_forwards(&forwards).remove(&port);
+        return Err(SpuffError::Ssh(format!(
+            "Failed to listen on remote port {}: {}",
+            remote_port, e
+        )));
+    }
+
+    Ok(PortForward {
+        running: Arc::new(AtomicBool::new(true)),
+        task: Arc::new(Mutex::new(None)),
+        remote: Some(RemoteListener {
+            session,
+            forwards,
+            port,
+        }),
+    })
+}
+
+/// Connect a channel the server opened for a reverse forward to its local port.
+pub(crate) fn accept_forwarded_channel(
+    forwards: &ReverseForwards,
+    channel: Channel<Msg>,
+    remote_port: u32,
+) {
+    let local_port = lock_forwards(forwards).get(&remote_port).copied();
+
+    tokio::spawn(async move {
+        let Some(local_port) = local_port else {
+            tracing::warn!("Reverse tunnel: no forward for remote port {}", remote_port);
+            let _ = channel.close().await;
+            return;
+        };
+
+        let mut local_stream = match TcpStream::connect(("127.0.0.1", local_port)).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                tracing::warn!(
+                    "Reverse tunnel: failed to connect to localhost:{}: {}",
+                    local_port,
+                    e
+                );
+                let _ = channel.close().await;
+                return;
+            }
+        };
+
+        tracing::info!(
+            "Reverse tunnel: vm:{} → localhost:{}",
+            remote_port,
+            local_port
+        );
+
+        let mut channel_stream = channel.into_stream();
+        if let Err(e) = tokio::io::copy_bidirectional(&mut local_stream, &mut channel_stream).await
+        {
+            tracing::debug!("Reverse tunnel: copy error: {}", e);
+        }
+    });
+}
+
+/// Create a dynamic port forward.
+///
+/// This runs a SOCKS5 proxy on `local_port` on localhost; every CONNECT
+/// request is opened from the remote host, so its network is reachable.
+pub async fn create_dynamic_forward(
+    session: Arc<Mutex<Handle<ClientHandler>>>,
+    local_port: u16,
+) -> Result<PortForward> {
+    let listener = TcpListener::bind(format!("127.0.0.1:{}", local_port))
+        .await
+        .map_err(|e| SpuffError::Ssh(format!("Failed to bind local port {}: {}", local_port, e)))?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    let running_clone = running.clone();
+
+    let task = tokio::spawn(async move {
+        while running_clone.load(Ordering::SeqCst) {
+            // Accept connections with timeout for shutdown check
+            let accept_result =
+                tokio::time::timeout(std::time::Duration::from_secs(1), listener.accept()).await;
+
+            let (mut local_stream, peer_addr) = match accept_result {
+                Ok(Ok(conn)) => conn,
+                Ok(Err(e)) => {
+                    tracing::debug!("Accept error: {}", e);
+                    continue;
+                }
+                Err(_) => continue, // timeout, check running flag
+            };
+
+            let session = session.clone();
+
+            // Handshake per connection so a slow client can't stall the others
+            tokio::spawn(async move {
+                let (host, port) = match socks5_handshake(&mut local_stream).await {
+                    Ok(target) => target,
+                    Err(e) => {
+                        tracing::debug!("SOCKS: handshake with {} failed: {}", peer_addr, e);
+                        return;
+                    }
+                };
+
+                let channel_result = {
+                    let session_guard = session.lock().await;
+                    session_guard
+                        .channel_open_direct_tcpip(
+                            host.clone(),
+                            port as u32,
+                            "127.0.0.1",
+                            local_port as u32,
+                        )
+                        .await
+                };
+
+                let channel = match channel_result {
+                    Ok(ch) => ch,
+                    Err(e) => {
+                        tracing::debug!("SOCKS: failed to open {}:{}: {}", host, port, e);
+                        let _ = socks5_reply(&mut local_stream, SOCKS5_HOST_UNREACHABLE).await;
+                        return;
+                    }
+                };
+
+                if socks5_reply(&mut local_stream, SOCKS5_SUCCEEDED)
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+
+                tracing::info!("SOCKS: {} → {}:{}", peer_addr, host, port);
+
+                let mut channel_stream = channel.into_stream();
+                if let Err(e) =
+                    tokio::io::copy_bidirectional(&mut local_stream, &mut channel_stream).await
+                {
+                    tracing::debug!("SOCKS: copy error: {}", e);
+                }
+            });
+        }
+    });
+
+    Ok(PortForward {
+        running,
+        task: Arc::new(Mutex::new(Some(task))),
+        remote: None,
+    })
+}
+
+fn lock_forwards(forwards: &ReverseForwards) -> std::sync::MutexGuard<'_, HashMap<u32, u16>> {
+    forwards.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+const SOCKS5_VERSION: u8 = 0x05;
+const SOCKS5_NO_AUTH: u8 = 0x00;
+const SOCKS5_NO_ACCEPTABLE_METHOD: u8 = 0xFF;
+const SOCKS5_CMD_CONNECT: u8 = 0x01;
+const SOCKS5_SUCCEEDED: u8 = 0x00;
+const SOCKS5_HOST_UNREACHABLE: u8 = 0x04;
+const SOCKS5_COMMAND_NOT_SUPPORTED: u8 = 0x07;
+const SOCKS5_ADDRESS_NOT_SUPPORTED: u8 = 0x08;
+
+/// Negotiate a SOCKS5 CONNECT (RFC 1928, no authentication).
+///
+/// Returns the requested target; the caller sends the final reply once it
+/// knows whether the target could be reached.
+async fn socks5_handshake<S>(stream: &mut S) -> Result<(String, u16)>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let socks_err = |e: std::io::Error| SpuffError::Ssh(format!("SOCKS: {}", e));
+
+    // Greeting: VER NMETHODS METHODS...
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header).await.map_err(socks_err)?;
+    if header[0] != SOCKS5_VERSION {
+        return Err(SpuffError::Ssh(format!(
+            "SOCKS: unsupported version {}",
+            header[0]
+        )));
+    }
+    let mut methods = vec![0u8; header[1] as usize];
+    stream.read_exact(&mut methods).await.map_err(socks_err)?;
+
+    if !methods.contains(&SOCKS5_NO_AUTH) {
+        stream
+            .write_all(&[SOCKS5_VERSION, SOCKS5_NO_ACCEPTABLE_METHOD])
+            .await
+            .map_err(socks_err)?;
+        return Err(SpuffError::Ssh(
+            "SOCKS: client requires authentication".to_string(),
+        ));
+    }
+    stream
+        .write_all(&[SOCKS5_VERSION, SOCKS5_NO_AUTH])
+        .await
+        .map_err(socks_err)?;
+
+    // Request: VER CMD RSV ATYP DST.ADDR DST.PORT
+    let mut request = [0u8; 4];
+    stream.read_exact(&mut request).await.map_err(socks_err)?;
+    if request[1] != SOCKS5_CMD_CONNECT {
+        let _ = socks5_reply(stream, SOCKS5_COMMAND_NOT_SUPPORTED).await;
+        return Err(SpuffError::Ssh(format!(
+            "SOCKS: unsupported command {}",
+            request[1]
+        )));
+    }
+
+    let host = match request[3] {
+        0x01 => {
+            let mut addr = [0u8; 4];
+            stream.read_exact(&mut addr).await.map_err(socks_err)?;
+            Ipv4Addr::from(addr).to_string()
+        }
+        0x03 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await.map_err(socks_err)?;
+            let mut name = vec![0u8; len[0] as usize];
+            stream.read_exact(&mut name).await.map_err(socks_err)?;
+            String::from_utf8(name)
+                .map_err(|_| SpuffError::Ssh("SOCKS: invalid domain name".to_string()))?
+        }
+        0x04 => {
+            let mut addr = [0u8; 16];
+            stream.read_exact(&mut addr).await.map_err(socks_err)?;
+            Ipv6Addr::from(addr).to_string()
+        }
+        atyp => {
+            let _ = socks5_reply(stream, SOCKS5_ADDRESS_NOT_SUPPORTED).await;
+            return Err(SpuffError::Ssh(format!(
+                "SOCKS: unsupported address type {}",
+                atyp
+            )));
+        }
+    };
+
+    let mut port = [0u8; 2];
+    stream.read_exact(&mut port).await.map_err(socks_err)?;
+
+    Ok((host, u16::from_be_bytes(port)))
+}
+
+/// Send a SOCKS5 reply (the bound address is not meaningful here).
+async fn socks5_reply<S>(stream: &mut S, reply: u8) -> std::io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    stream
+        .write_all(&[SOCKS5_VERSION, reply, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+        .await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
-    use tokio::net::TcpStream;
 
     #[tokio::test]
     async fn test_port_forward_stop() {
@@ -131,6 +430,7 @@ mod tests {
         let forward = PortForward {
             running: running.clone(),
             task: Arc::new(Mutex::new(None)),
+            remote: None,
         };
 
         assert!(running.load(Ordering::SeqCst));
@@ -149,6 +449,7 @@ mod tests {
         let forward = PortForward {
             running: running.clone(),
             task: Arc::new(Mutex::new(Some(task))),
+            remote: None,
         };
 
         forward.stop().await;
@@ -200,4 +501,82 @@ mod tests {
 
         assert_eq!(&response, test_data);
     }
+
+    #[tokio::test]
+    async fn test_socks5_handshake_domain() {
+        let (mut client, mut server) = tokio::io::duplex(256);
+
+        let handshake = tokio::spawn(async move { socks5_handshake(&mut server).await });
+
+        // Greeting offering "no authentication"
+        client.write_all(&[0x05, 0x01, 0x00]).await.unwrap();
+        let mut method = [0u8; 2];
+        client.read_exact(&mut method).await.unwrap();
+        assert_eq!(method, [0x05, 0x00]);
+
+        // CONNECT grafana.internal:3000
+        let mut request = vec![0x05, 0x01, 0x00, 0x03, 16];
+        request.extend_from_slice(b"grafana.internal");
+        request.extend_from_slice(&3000u16.to_be_bytes());
+        client.write_all(&request).await.unwrap();
+
+        let target = handshake.await.unwrap().unwrap();
+        assert_eq!(target, ("grafana.internal".to_string(), 3000));
+    }
+
+    #[tokio::test]
+    async fn test_socks5_handshake_ipv4() {
+        let (mut client, mut server) = tokio::io::duplex(256);
+
+        let handshake = tokio::spawn(async move { socks5_handshake(&mut server).await });
+
+        client.write_all(&[0x05, 0x01, 0x00]).await.unwrap();
+        let mut method = [0u8; 2];
+        client.read_exact(&mut method).await.unwrap();
+
+        client
+            .write_all(&[0x05, 0x01, 0x00, 0x01, 10, 0, 0, 5, 0x1F, 0x90])
+            .await
+            .unwrap();
+
+        let target = handshake.await.unwrap().unwrap();
+        assert_eq!(target, ("10.0.0.5".to_string(), 8080));
+    }
+
+    #[tokio::test]
+    async fn test_socks5_handshake_requires_no_auth() {
+        let (mut client, mut server) = tokio::io::duplex(256);
+
+        let handshake = tokio::spawn(async move { socks5_handshake(&mut server).await });
+
+        // Username/password only
+        client.write_all(&[0x05, 0x01, 0x02]).await.unwrap();
+        let mut method = [0u8; 2];
+        client.read_exact(&mut method).await.unwrap();
+        assert_eq!(method, [0x05, 0xFF]);
+
+        assert!(handshake.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_socks5_handshake_rejects_bind() {
+        let (mut client, mut server) = tokio::io::duplex(256);
+
+        let handshake = tokio::spawn(async move { socks5_handshake(&mut server).await });
+
+        client.write_all(&[0x05, 0x01, 0x00]).await.unwrap();
+        let mut method = [0u8; 2];
+        client.read_exact(&mut method).await.unwrap();
+
+        // BIND is not supported
+        client
+            .write_all(&[0x05, 0x02, 0x00, 0x01, 127, 0, 0, 1, 0, 80])
+            .await
+            .unwrap();
+        let mut reply = [0u8; 10];
+        client.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply[1], 0x07);
+
+        assert!(handshake.await.unwrap().is_err());
+    }
 }
@@ -2,9 +2,14 @@
 //!
 //! This module provides ChronDB-backed persistence for tracking which instances
 //! are currently active. This allows the CLI to maintain state across invocations.
+//!
+//! Several named environments can exist at once. One of them is the *active*
+//! environment (the last one created, or the one picked with `spuff use`), and
+//! a single invocation can target another one with the global `--env` flag.
 
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::OnceLock;
 
 use chrondb::ChronDB;
 use chrono::{DateTime, Utc};
@@ -12,9 +17,25 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 use crate::config::AppConfig;
-use crate::error::Result;
+use crate::cost::HourlyPrice;
+use crate::error::{Result, SpuffError};
 use crate::provider::ProviderInstance;
 
+/// Environment selected for this invocation via `--env` / `SPUFF_ENV`.
+static SELECTED_ENV: OnceLock<String> = OnceLock::new();
+
+/// Target a specific environment for the rest of this process.
+///
+/// Every command that calls [`StateDb::get_active_instance`] will then
+/// operate on this environment instead of the active one.
+pub fn select_env(name: impl Into<String>) {
+    let _ = SELECTED_ENV.set(name.into());
+}
+
+fn selected_env() -> Option<&'static str> {
+    SELECTED_ENV.get().map(String::as_str)
+}
+
 /// Instance information stored locally.
 ///
 /// This is separate from `ProviderInstance` which represents the provider's view.
@@ -41,6 +62,147 @@ pub struct LocalInstance {
 
     /// When the instance was created
     pub created_at: DateTime<Utc>,
+
+    /// When the instance was powered off with `spuff stop`, if it is stopped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped_at: Option<DateTime<Utc>>,
+
+    /// Hourly price of the instance size, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<HourlyPrice>,
+}
+
+/// Outcome of an automatic destroy, kept so `spuff status` can report it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DestroyRecord {
+    /// Provider-specific ID of the destroyed instance
+    pub id: String,
+
+    /// Environment name
+    pub name: String,
+
+    /// Which cloud provider managed the instance
+    pub provider: String,
+
+    /// Why the instance was destroyed (e.g. "idle")
+    pub reason: String,
+
+    /// Snapshot taken before destroying, if any
+    #[serde(default)]
+    pub snapshot_id: Option<String>,
+
+    /// Error message when the destroy (or snapshot) failed
+    #[serde(default)]
+    pub error: Option<String>,
+
+    /// When the attempt happened
+    pub at: DateTime<Utc>,
+}
+
+/// Snapshot taken by `spuff hibernate`, with what `spuff resume` needs to
+/// recreate the environment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hibernation {
+    /// Environment name
+    pub name: String,
+
+    /// Provider-specific snapshot ID
+    pub snapshot_id: String,
+
+    /// Which cloud provider holds the snapshot
+    pub provider: String,
+
+    /// Region the environment ran in
+    pub region: String,
+
+    /// Instance size/type the environment ran with
+    pub size: String,
+
+    /// Directory of the spuff.yaml the environment was used with, if any
+    #[serde(default)]
+    pub project_dir: Option<PathBuf>,
+
+    /// Local ports that were tunneled when the environment was hibernated
+    #[serde(default)]
+    pub ports: Vec<u16>,
+
+    /// Ports the tunnel daemon forwarded from the VM back here (`--reverse`)
+    #[serde(default)]
+    pub reverse_ports: Vec<u16>,
+
+    /// Local port of the tunnel daemon's SOCKS5 proxy (`--socks`)
+    #[serde(default)]
+    pub socks_port: Option<u16>,
+
+    /// Whether the tunnel daemon forwarded ports discovered on the VM (`--auto`)
+    #[serde(default)]
+    pub auto_tunnel: bool,
+
+    /// When the environment was hibernated
+    pub at: DateTime<Utc>,
+}
+
+/// Snapshot taken on request with `spuff snapshot create` or
+/// `spuff down --snapshot`, so `spuff gc` doesn't take it for a leftover.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotRecord {
+    /// Provider-specific snapshot ID
+    pub snapshot_id: String,
+
+    /// Snapshot name at the provider
+    pub name: String,
+
+    /// Environment the snapshot was taken of
+    pub environment: String,
+
+    /// Which cloud provider holds the snapshot
+    pub provider: String,
+
+    /// When the snapshot was taken
+    pub at: DateTime<Utc>,
+}
+
+/// A stretch of time an instance was billed at one hourly price.
+///
+/// A record opens when a priced instance is saved and closes when the
+/// instance is removed or resized. Stopped instances are still billed on
+/// most providers, so `spuff stop` doesn't close it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Provider-specific instance ID
+    pub instance_id: String,
+
+    /// Environment name
+    pub name: String,
+
+    /// Which cloud provider bills the instance
+    pub provider: String,
+
+    /// Instance size/type during this stretch
+    pub size: String,
+
+    /// Hourly price during this stretch
+    pub price: HourlyPrice,
+
+    /// When billing at this price started
+    pub started_at: DateTime<Utc>,
+
+    /// When it ended; `None` while the instance still runs at this price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+/// SSH host key pinned for a host (see [`crate::ssh::host_keys`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostKey {
+    /// Host the key belongs to (an IP, or `[host]:port` off port 22)
+    pub host: String,
+
+    /// Public key in OpenSSH format
+    pub key: String,
+
+    /// When the key was pinned
+    pub at: DateTime<Utc>,
 }
 
 /// Legacy type alias for backward compatibility.
@@ -65,6 +227,8 @@ impl LocalInstance {
             region,
             size,
             created_at: provider_instance.created_at,
+            stopped_at: None,
+            price: None,
         }
     }
 
@@ -86,6 +250,8 @@ impl LocalInstance {
             region: region.into(),
             size: size.into(),
             created_at: Utc::now(),
+            stopped_at: None,
+            price: None,
         }
     }
 }
@@ -100,26 +266,33 @@ impl StateDb {
     ///
     /// If the database is corrupted or incompatible, it will be automatically
     /// reset. This is safe because the state database only tracks active instances
-    /// and can be reconstructed.
+    /// and can be reconstructed. It is never reset while another process (a
+    /// watchdog, a tunnel daemon) holds its lock: that only means it's busy.
     pub fn open() -> Result<Self> {
         // Try to open with retries for lock contention
-        for attempt in 0..3 {
+        let mut attempt = 0;
+        loop {
             match Self::try_open() {
                 Ok(db) => return Ok(db),
                 Err(e) => {
-                    if attempt < 2 {
+                    attempt += 1;
+                    let locked =
+                        Self::lock_in_use(&Self::db_base_path()?.join("index").join("write.lock"));
+                    if attempt < 3 || (locked && attempt < 30) {
                         // Brief delay to allow previous process to release lock
                         std::thread::sleep(std::time::Duration::from_millis(100));
                         continue;
                     }
-                    // Final attempt failed - try reset
+                    if locked {
+                        return Err(e);
+                    }
+                    // Final attempt failed and nobody holds the lock - try reset
                     eprintln!("Database locked or corrupted, resetting...");
                     Self::reset()?;
                     return Self::try_open().map_err(|_| e);
                 }
             }
         }
-        unreachable!()
     }
 
     /// Attempt to open the database without recovery.
@@ -163,54 +336,62 @@ impl StateDb {
     /// Remove orphaned Lucene lock file if no process holds it.
     fn cleanup_stale_lock(index_path: &Path) {
         let lock_file = index_path.join("write.lock");
-        if !lock_file.exists() {
+        if !lock_file.exists() || Self::lock_in_use(&lock_file) {
             return;
         }
 
+        if std::fs::remove_file(&lock_file).is_ok() {
+            eprintln!("Removed stale database lock file");
+        }
+    }
+
+    /// Whether another process holds the Lucene lock file.
+    fn lock_in_use(lock_file: &Path) -> bool {
+        if !lock_file.exists() {
+            return false;
+        }
+
         // Try to acquire an exclusive lock non-blocking to check if the file is in use.
-        // If we can acquire it, no other process holds the lock, so it's orphaned.
+        // If we can acquire it, no other process holds the lock.
         #[cfg(unix)]
         {
             use std::fs::OpenOptions;
             use std::os::unix::io::AsRawFd;
 
-            if let Ok(file) = OpenOptions::new().read(true).write(true).open(&lock_file) {
-                // Try non-blocking exclusive lock
+            if let Ok(file) = OpenOptions::new().read(true).write(true).open(lock_file) {
                 let fd = file.as_raw_fd();
+
+                // Lucene takes a POSIX record lock, which flock() doesn't see
+                // on Linux: ask whether one would block a write lock
+                let mut record: libc::flock = unsafe { std::mem::zeroed() };
+                record.l_type = libc::F_WRLCK as libc::c_short;
+                record.l_whence = libc::SEEK_SET as libc::c_short;
+                let result = unsafe { libc::fcntl(fd, libc::F_GETLK, &mut record) };
+                if result == 0 && record.l_type != libc::F_UNLCK as libc::c_short {
+                    return true;
+                }
+
+                // Try non-blocking exclusive lock
                 let result = unsafe { libc::flock(fd, libc::LOCK_EX | libc::LOCK_NB) };
                 if result == 0 {
-                    // Successfully acquired lock - file was orphaned
-                    // Unlock and remove
                     unsafe { libc::flock(fd, libc::LOCK_UN) };
-                    drop(file);
-                    if std::fs::remove_file(&lock_file).is_ok() {
-                        eprintln!("Removed stale database lock file");
-                    }
-                    return;
+                    return false;
                 }
-                // Could not acquire lock - another process has it, don't remove
-                return;
+                // Could not acquire lock - another process has it
+                return true;
             }
         }
 
         // Fallback: use lsof (may not be available on all systems)
-        let lock_path_str = match lock_file.to_str() {
-            Some(s) => s,
-            None => return,
+        let Some(lock_path_str) = lock_file.to_str() else {
+            return false;
         };
 
-        let is_locked = Command::new("lsof")
+        Command::new("lsof")
             .arg(lock_path_str)
             .output()
             .map(|output| output.status.success())
-            .unwrap_or(false);
-
-        if !is_locked {
-            // No process holds the lock - it's orphaned
-            if std::fs::remove_file(&lock_file).is_ok() {
-                eprintln!("Removed stale database lock file");
-            }
-        }
+            .unwrap_or(false)
     }
 
     fn db_base_path() -> Result<PathBuf> {
@@ -225,29 +406,331 @@ impl StateDb {
         }
     }
 
-    /// Save an instance, marking it as the only active one.
+    /// Record the outcome of an automatic destroy attempt.
+    ///
+    /// Only the latest attempt per instance is kept.
+    pub fn record_destroy(&self, record: &DestroyRecord) -> Result<()> {
+        let doc = serde_json::to_value(record)?;
+        self.db
+            .put(&format!("destroyed:{}", record.id), &doc, None)?;
+        Ok(())
+    }
+
+    /// List automatic destroy records, most recent first.
+    pub fn list_destroy_records(&self) -> Result<Vec<DestroyRecord>> {
+        let docs = match self.db.list_by_table("destroyed", None) {
+            Ok(val) => val,
+            Err(chrondb::ChronDBError::NotFound) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut records: Vec<DestroyRecord> = docs
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| serde_json::from_value(v.clone()).ok())
+                    .map(|mut r: DestroyRecord| {
+                        // Same key-prefix quirk as instance documents
+                        if let Some(stripped) = r.id.strip_prefix("destroyed:") {
+                            r.id = stripped.to_string();
+                        }
+                        r
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        records.sort_by_key(|r| std::cmp::Reverse(r.at));
+        Ok(records)
+    }
+
+    /// Record a hibernation snapshot.
+    pub fn record_hibernation(&self, hibernation: &Hibernation) -> Result<()> {
+        let doc = serde_json::to_value(hibernation)?;
+        self.db.put(
+            &format!("hibernated:{}", hibernation.snapshot_id),
+            &doc,
+            None,
+        )?;
+        Ok(())
+    }
+
+    /// List hibernation snapshots, most recent first.
+    pub fn list_hibernations(&self) -> Result<Vec<Hibernation>> {
+        let docs = match self.db.list_by_table("hibernated", None) {
+            Ok(val) => val,
+            Err(chrondb::ChronDBError::NotFound) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut hibernations: Vec<Hibernation> = docs
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| serde_json::from_value(v.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        hibernations.sort_by_key(|h| std::cmp::Reverse(h.at));
+        Ok(hibernations)
+    }
+
+    /// Latest hibernation snapshot of an environment.
+    pub fn latest_hibernation(&self, name: &str) -> Result<Option<Hibernation>> {
+        Ok(self
+            .list_hibernations()?
+            .into_iter()
+            .find(|h| h.name == name))
+    }
+
+    /// Forget a hibernation snapshot (after deleting it at the provider).
+    pub fn remove_hibernation(&self, snapshot_id: &str) -> Result<()> {
+        match self.db.delete(&format!("hibernated:{}", snapshot_id), None) {
+            Ok(()) | Err(chrondb::ChronDBError::NotFound) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Record a snapshot the user asked for.
+    pub fn record_snapshot(&self, snapshot: &SnapshotRecord) -> Result<()> {
+        let doc = serde_json::to_value(snapshot)?;
+        self.db
+            .put(&format!("snapshot:{}", snapshot.snapshot_id), &doc, None)?;
+        Ok(())
+    }
+
+    /// List snapshots the user asked for, most recent first.
+    pub fn list_snapshots(&self) -> Result<Vec<SnapshotRecord>> {
+        let docs = match self.db.list_by_table("snapshot", None) {
+            Ok(val) => val,
+            Err(chrondb::ChronDBError::NotFound) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut snapshots: Vec<SnapshotRecord> = docs
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| serde_json::from_value(v.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        snapshots.sort_by_key(|s| std::cmp::Reverse(s.at));
+        Ok(snapshots)
+    }
+
+    /// Forget a snapshot (after deleting it at the provider).
+    pub fn remove_snapshot(&self, snapshot_id: &str) -> Result<()> {
+        match self.db.delete(&format!("snapshot:{}", snapshot_id), None) {
+            Ok(()) | Err(chrondb::ChronDBError::NotFound) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// List usage records, oldest first.
+    pub fn list_usage(&self) -> Result<Vec<UsageRecord>> {
+        let docs = match self.db.list_by_table("usage", None) {
+            Ok(val) => val,
+            Err(chrondb::ChronDBError::NotFound) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut records: Vec<UsageRecord> = docs
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| serde_json::from_value(v.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        records.sort_by_key(|r| r.started_at);
+        Ok(records)
+    }
+
+    /// Start billing an instance at its current price, if it has one.
+    fn open_usage(&self, instance: &LocalInstance, started_at: DateTime<Utc>) -> Result<()> {
+        let Some(price) = &instance.price else {
+            return Ok(());
+        };
+
+        let record = UsageRecord {
+            instance_id: instance.id.clone(),
+            name: instance.name.clone(),
+            provider: instance.provider.clone(),
+            size: instance.size.clone(),
+            price: price.clone(),
+            started_at,
+            ended_at: None,
+        };
+        self.put_usage(&record)
+    }
+
+    /// Stop billing an instance at its current price.
+    fn close_usage(&self, instance_id: &str, ended_at: DateTime<Utc>) -> Result<()> {
+        for mut record in self.list_usage()? {
+            if record.instance_id == instance_id && record.ended_at.is_none() {
+                record.ended_at = Some(ended_at);
+                self.put_usage(&record)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn put_usage(&self, record: &UsageRecord) -> Result<()> {
+        let doc = serde_json::to_value(record)?;
+        let key = format!(
+            "usage:{}-{}",
+            record.instance_id,
+            record.started_at.timestamp_millis()
+        );
+        self.db.put(&key, &doc, None)?;
+        Ok(())
+    }
+
+    /// Pin the SSH host key of a host, replacing any previous pin.
+    pub fn pin_host_key(&self, host: &str, key: &str) -> Result<()> {
+        let doc = serde_json::to_value(HostKey {
+            host: host.to_string(),
+            key: key.to_string(),
+            at: Utc::now(),
+        })?;
+        self.db.put(&format!("hostkey:{}", host), &doc, None)?;
+        Ok(())
+    }
+
+    /// Pinned SSH host key of a host, if any.
+    pub fn host_key(&self, host: &str) -> Result<Option<HostKey>> {
+        match self.db.get(&format!("hostkey:{}", host), None) {
+            Ok(doc) => Ok(Some(serde_json::from_value(doc)?)),
+            Err(chrondb::ChronDBError::NotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// List all pinned SSH host keys.
+    pub fn list_host_keys(&self) -> Result<Vec<HostKey>> {
+        let docs = match self.db.list_by_table("hostkey", None) {
+            Ok(val) => val,
+            Err(chrondb::ChronDBError::NotFound) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(docs
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| serde_json::from_value(v.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Forget the pinned SSH host key of a host.
+    pub fn forget_host_key(&self, host: &str) -> Result<()> {
+        match self.db.delete(&format!("hostkey:{}", host), None) {
+            Ok(()) | Err(chrondb::ChronDBError::NotFound) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save an instance and make it the active environment.
+    ///
+    /// Other environments are kept; only the active marker moves.
     pub fn save_instance(&self, instance: &LocalInstance) -> Result<()> {
+        self.put_instance(instance)?;
+        self.set_active(&instance.id)?;
+
+        Ok(())
+    }
+
+    /// Save an instance without taking over the active environment.
+    ///
+    /// It only becomes active when no environment is active yet.
+    pub fn add_instance(&self, instance: &LocalInstance) -> Result<()> {
+        self.put_instance(instance)?;
+        if self.active_instance_id()?.is_none() {
+            self.set_active(&instance.id)?;
+        }
+
+        Ok(())
+    }
+
+    fn put_instance(&self, instance: &LocalInstance) -> Result<()> {
         let doc = serde_json::to_value(instance)?;
         let key = format!("instance:{}", instance.id);
 
         self.db.put(&key, &doc, None)?;
-        self.db
-            .put("meta:active", &json!({"instance_id": instance.id}), None)?;
 
+        // Billing starts when the provider created the instance
+        let billed = self
+            .list_usage()?
+            .iter()
+            .any(|r| r.instance_id == instance.id && r.ended_at.is_none());
+        if !billed {
+            self.open_usage(instance, instance.created_at)?;
+        }
         Ok(())
     }
 
-    /// Get the currently active instance, if any.
+    /// Get the instance commands should operate on, if any.
+    ///
+    /// This is the environment selected with `--env` when one was given,
+    /// otherwise the active environment.
     pub fn get_active_instance(&self) -> Result<Option<LocalInstance>> {
-        let meta = match self.db.get("meta:active", None) {
-            Ok(val) => val,
-            Err(chrondb::ChronDBError::NotFound) => return Ok(None),
-            Err(e) => return Err(e.into()),
-        };
+        self.resolve_instance(selected_env())
+    }
+
+    /// Resolve an explicit environment name, falling back to the active one.
+    ///
+    /// An explicit name that matches nothing is an error rather than `None`,
+    /// so a typo in `--env` never silently targets another environment.
+    pub fn resolve_instance(&self, env: Option<&str>) -> Result<Option<LocalInstance>> {
+        match env {
+            Some(name) => self
+                .find_instance(name)?
+                .map(Some)
+                .ok_or_else(|| SpuffError::EnvironmentNotFound(name.to_string())),
+            None => self.get_marked_active_instance(),
+        }
+    }
+
+    /// Find an environment by name or provider instance ID.
+    pub fn find_instance(&self, name_or_id: &str) -> Result<Option<LocalInstance>> {
+        let instances = self.list_instances()?;
+        let found = instances
+            .iter()
+            .find(|i| i.name == name_or_id)
+            .or_else(|| instances.iter().find(|i| i.id == name_or_id))
+            .cloned();
+        Ok(found)
+    }
+
+    /// Mark an existing environment as the active one.
+    pub fn set_active(&self, id: &str) -> Result<()> {
+        self.db
+            .put("meta:active", &json!({"instance_id": id}), None)?;
+        Ok(())
+    }
+
+    /// ID of the active environment, ignoring any `--env` selection.
+    pub fn active_instance_id(&self) -> Result<Option<String>> {
+        match self.db.get("meta:active", None) {
+            Ok(meta) => Ok(meta
+                .get("instance_id")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)),
+            Err(chrondb::ChronDBError::NotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
 
-        let instance_id = match meta.get("instance_id").and_then(|v| v.as_str()) {
-            Some(id) => id.to_string(),
-            None => return Ok(None),
+    /// Get the instance marked active in the database, if any.
+    fn get_marked_active_instance(&self) -> Result<Option<LocalInstance>> {
+        let Some(instance_id) = self.active_instance_id()? else {
+            return Ok(None);
         };
 
         let key = format!("instance:{}", instance_id);
@@ -263,17 +746,20 @@ impl StateDb {
     }
 
     /// Remove an instance from the database.
+    ///
+    /// If it was the active environment, the most recently created remaining
+    /// environment (if any) becomes active.
     pub fn remove_instance(&self, id: &str) -> Result<()> {
-        // Check if this is the active instance
-        let is_active = match self.db.get("meta:active", None) {
-            Ok(meta) => meta
-                .get("instance_id")
-                .and_then(|v| v.as_str())
-                .map(|active_id| active_id == id)
-                .unwrap_or(false),
-            Err(chrondb::ChronDBError::NotFound) => false,
-            Err(e) => return Err(e.into()),
-        };
+        let is_active = self.active_instance_id()?.as_deref() == Some(id);
+
+        // Cloud IPs get recycled, so the next instance on this IP has a new
+        // host key. Pool hosts outlive their environments and keep theirs.
+        if let Some(instance) = self.find_instance(id)? {
+            if instance.provider != "ssh" {
+                self.forget_host_key(&instance.ip)?;
+            }
+        }
+        self.close_usage(id, Utc::now())?;
 
         let key = format!("instance:{}", id);
         // Treat NotFound as success to keep deletes idempotent
@@ -283,18 +769,20 @@ impl StateDb {
         }
 
         if is_active {
-            // Ignore NotFound errors when cleaning up meta:active
-            match self.db.delete("meta:active", None) {
-                Ok(()) | Err(chrondb::ChronDBError::NotFound) => {}
-                Err(e) => return Err(e.into()),
+            match self.list_instances()?.first() {
+                Some(next) => self.set_active(&next.id)?,
+                // Ignore NotFound errors when cleaning up meta:active
+                None => match self.db.delete("meta:active", None) {
+                    Ok(()) | Err(chrondb::ChronDBError::NotFound) => {}
+                    Err(e) => return Err(e.into()),
+                },
             }
         }
 
         Ok(())
     }
 
-    /// List all instances.
-    #[cfg(test)]
+    /// List all instances, most recently created first.
     pub fn list_instances(&self) -> Result<Vec<LocalInstance>> {
         let docs = match self.db.list_by_table("instance", None) {
             Ok(val) => val,
@@ -314,18 +802,66 @@ impl StateDb {
             None => Vec::new(),
         };
 
-        instances.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        instances.sort_by_key(|i| std::cmp::Reverse(i.created_at));
         Ok(instances)
     }
 
     /// Update the IP address of an instance.
-    #[cfg(test)]
+    ///
+    /// The pinned host key moves along: it survives a reboot, the IP doesn't.
     pub fn update_instance_ip(&self, id: &str, ip: &str) -> Result<()> {
+        let mut old_ip = String::new();
+        self.update_instance(id, |instance| {
+            old_ip = std::mem::replace(&mut instance.ip, ip.to_string());
+        })?;
+
+        if old_ip != ip {
+            if let Some(pin) = self.host_key(&old_ip)? {
+                self.pin_host_key(ip, &pin.key)?;
+                self.forget_host_key(&old_ip)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Update the size and price of an instance after a resize.
+    ///
+    /// Billing at the old price ends now and continues at the new one.
+    pub fn update_instance_size(
+        &self,
+        id: &str,
+        size: &str,
+        price: Option<HourlyPrice>,
+    ) -> Result<()> {
+        let now = Utc::now();
+        self.close_usage(id, now)?;
+
+        let mut resized = None;
+        self.update_instance(id, |instance| {
+            instance.size = size.to_string();
+            instance.price = price;
+            resized = Some(instance.clone());
+        })?;
+
+        match resized {
+            Some(instance) => self.open_usage(&instance, now),
+            None => Ok(()),
+        }
+    }
+
+    /// Record that an instance was stopped (`Some`) or started again (`None`).
+    pub fn set_stopped(&self, id: &str, stopped_at: Option<DateTime<Utc>>) -> Result<()> {
+        self.update_instance(id, |instance| instance.stopped_at = stopped_at)
+    }
+
+    fn update_instance(&self, id: &str, update: impl FnOnce(&mut LocalInstance)) -> Result<()> {
         let key = format!("instance:{}", id);
         let doc = self.db.get(&key, None)?;
 
         let mut instance: LocalInstance = serde_json::from_value(doc)?;
-        instance.ip = ip.to_string();
+        Self::fix_instance_id(&mut instance);
+        update(&mut instance);
 
         let updated = serde_json::to_value(&instance)?;
         self.db.put(&key, &updated, None)?;
@@ -363,9 +899,18 @@ mod tests {
             region: "nyc1".to_string(),
             size: "s-2vcpu-4gb".to_string(),
             created_at: Utc::now(),
+            stopped_at: None,
+            price: None,
         }
     }
 
+    fn usd(amount: f64) -> Option<HourlyPrice> {
+        Some(HourlyPrice {
+            amount,
+            currency: "USD".to_string(),
+        })
+    }
+
     #[test]
     fn test_save_and_get_instance() {
         let (db, _dir, _lock) = create_test_db();
@@ -415,6 +960,207 @@ mod tests {
         assert!(db.get_active_instance().unwrap().is_none());
     }
 
+    #[test]
+    fn test_find_instance_by_name_or_id() {
+        let (db, _dir, _lock) = create_test_db();
+        db.save_instance(&create_test_instance("111", "api"))
+            .unwrap();
+        db.save_instance(&create_test_instance("222", "web"))
+            .unwrap();
+
+        assert_eq!(db.find_instance("api").unwrap().unwrap().id, "111");
+        assert_eq!(db.find_instance("222").unwrap().unwrap().name, "web");
+        assert!(db.find_instance("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_resolve_instance_explicit_env() {
+        let (db, _dir, _lock) = create_test_db();
+        db.save_instance(&create_test_instance("111", "api"))
+            .unwrap();
+        db.save_instance(&create_test_instance("222", "web"))
+            .unwrap();
+
+        // Without a selection, the last saved environment is active
+        let active = db.resolve_instance(None).unwrap().unwrap();
+        assert_eq!(active.name, "web");
+
+        let selected = db.resolve_instance(Some("api")).unwrap().unwrap();
+        assert_eq!(selected.id, "111");
+
+        let err = db.resolve_instance(Some("nope")).unwrap_err();
+        assert!(matches!(err, SpuffError::EnvironmentNotFound(name) if name == "nope"));
+    }
+
+    #[test]
+    fn test_set_active() {
+        let (db, _dir, _lock) = create_test_db();
+        db.save_instance(&create_test_instance("111", "api"))
+            .unwrap();
+        db.save_instance(&create_test_instance("222", "web"))
+            .unwrap();
+
+        db.set_active("111").unwrap();
+        assert_eq!(db.get_active_instance().unwrap().unwrap().name, "api");
+        assert_eq!(db.list_instances().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_add_instance_keeps_active() {
+        let (db, _dir, _lock) = create_test_db();
+
+        // First environment becomes active even without save_instance
+        db.add_instance(&create_test_instance("111", "api"))
+            .unwrap();
+        assert_eq!(db.get_active_instance().unwrap().unwrap().name, "api");
+
+        db.add_instance(&create_test_instance("222", "adopted"))
+            .unwrap();
+        assert_eq!(db.get_active_instance().unwrap().unwrap().name, "api");
+        assert_eq!(db.list_instances().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_remove_active_instance_promotes_newest() {
+        let (db, _dir, _lock) = create_test_db();
+        let now = Utc::now();
+
+        let mut oldest = create_test_instance("111", "oldest");
+        oldest.created_at = now - chrono::Duration::hours(2);
+        let mut newer = create_test_instance("222", "newer");
+        newer.created_at = now - chrono::Duration::hours(1);
+        let mut active = create_test_instance("333", "active");
+        active.created_at = now;
+
+        db.save_instance(&oldest).unwrap();
+        db.save_instance(&newer).unwrap();
+        db.save_instance(&active).unwrap();
+
+        db.remove_instance("333").unwrap();
+        assert_eq!(db.get_active_instance().unwrap().unwrap().name, "newer");
+
+        // Removing a non-active environment leaves the active one alone
+        db.remove_instance("111").unwrap();
+        assert_eq!(db.get_active_instance().unwrap().unwrap().name, "newer");
+    }
+
+    #[test]
+    fn test_destroy_records() {
+        let (db, _dir, _lock) = create_test_db();
+        let now = Utc::now();
+
+        let record = |id: &str, at: DateTime<Utc>, error: Option<&str>| DestroyRecord {
+            id: id.to_string(),
+            name: format!("env-{}", id),
+            provider: "digitalocean".to_string(),
+            reason: "idle".to_string(),
+            snapshot_id: None,
+            error: error.map(str::to_string),
+            at,
+        };
+
+        db.record_destroy(&record("111", now - chrono::Duration::hours(1), None))
+            .unwrap();
+        db.record_destroy(&record("222", now, Some("boom")))
+            .unwrap();
+
+        let records = db.list_destroy_records().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, "222");
+        assert_eq!(records[0].error.as_deref(), Some("boom"));
+        assert_eq!(records[1].name, "env-111");
+
+        // Destroy records are not environments
+        assert!(db.list_instances().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_hibernations() {
+        let (db, _dir, _lock) = create_test_db();
+        let now = Utc::now();
+
+        let hibernation = |snapshot_id: &str, name: &str, at: DateTime<Utc>| Hibernation {
+            name: name.to_string(),
+            snapshot_id: snapshot_id.to_string(),
+            provider: "digitalocean".to_string(),
+            region: "nyc1".to_string(),
+            size: "s-2vcpu-4gb".to_string(),
+            project_dir: Some(PathBuf::from("/src/api")),
+            ports: vec![3000],
+            reverse_ports: Vec::new(),
+            socks_port: None,
+            auto_tunnel: false,
+            at,
+        };
+
+        db.record_hibernation(&hibernation(
+            "snap-1",
+            "api",
+            now - chrono::Duration::days(2),
+        ))
+        .unwrap();
+        db.record_hibernation(&hibernation("snap-2", "api", now))
+            .unwrap();
+        db.record_hibernation(&hibernation(
+            "snap-3",
+            "web",
+            now - chrono::Duration::days(1),
+        ))
+        .unwrap();
+
+        let all = db.list_hibernations().unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].snapshot_id, "snap-2");
+
+        let latest = db.latest_hibernation("api").unwrap().unwrap();
+        assert_eq!(latest.snapshot_id, "snap-2");
+        assert_eq!(latest.ports, vec![3000]);
+        assert!(db.latest_hibernation("missing").unwrap().is_none());
+
+        db.remove_hibernation("snap-2").unwrap();
+        db.remove_hibernation("snap-2").unwrap();
+        assert_eq!(
+            db.latest_hibernation("api").unwrap().unwrap().snapshot_id,
+            "snap-1"
+        );
+
+        // Hibernations are not environments
+        assert!(db.list_instances().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let (db, _dir, _lock) = create_test_db();
+        let now = Utc::now();
+
+        let snapshot = |snapshot_id: &str, at: DateTime<Utc>| SnapshotRecord {
+            snapshot_id: snapshot_id.to_string(),
+            name: format!("api-{}", snapshot_id),
+            environment: "api".to_string(),
+            provider: "digitalocean".to_string(),
+            at,
+        };
+
+        db.record_snapshot(&snapshot("snap-1", now - chrono::Duration::days(1)))
+            .unwrap();
+        db.record_snapshot(&snapshot("snap-2", now)).unwrap();
+
+        let all = db.list_snapshots().unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].snapshot_id, "snap-2");
+        assert_eq!(all[0].environment, "api");
+
+        db.remove_snapshot("snap-2").unwrap();
+        db.remove_snapshot("snap-2").unwrap();
+        let all = db.list_snapshots().unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].snapshot_id, "snap-1");
+
+        // Snapshots are neither environments nor hibernations
+        assert!(db.list_instances().unwrap().is_empty());
+        assert!(db.list_hibernations().unwrap().is_empty());
+    }
+
     #[test]
     fn test_get_active_instance_none() {
         let (db, _dir, _lock) = create_test_db();
@@ -450,6 +1196,105 @@ mod tests {
         assert_eq!(retrieved.ip, "192.168.1.100");
     }
 
+    #[test]
+    fn test_update_instance_size() {
+        let (db, _dir, _lock) = create_test_db();
+        db.save_instance(&create_test_instance("789", "spuff-resize"))
+            .unwrap();
+
+        db.update_instance_size("789", "s-4vcpu-8gb", usd(0.07))
+            .unwrap();
+
+        let retrieved = db.get_active_instance().unwrap().unwrap();
+        assert_eq!(retrieved.size, "s-4vcpu-8gb");
+        assert_eq!(retrieved.price, usd(0.07));
+    }
+
+    #[test]
+    fn test_usage_records() {
+        let (db, _dir, _lock) = create_test_db();
+        let created_at = Utc::now() - chrono::Duration::hours(3);
+
+        // Unpriced instances are not billed
+        db.save_instance(&create_test_instance("111", "local"))
+            .unwrap();
+        assert!(db.list_usage().unwrap().is_empty());
+
+        let mut instance = create_test_instance("222", "api");
+        instance.created_at = created_at;
+        instance.price = usd(0.03);
+        db.save_instance(&instance).unwrap();
+
+        // Saving again (e.g. a new IP) doesn't open a second record
+        db.save_instance(&instance).unwrap();
+        let usage = db.list_usage().unwrap();
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].started_at, created_at);
+        assert!(usage[0].ended_at.is_none());
+
+        db.update_instance_size("222", "s-4vcpu-8gb", usd(0.07))
+            .unwrap();
+        let usage = db.list_usage().unwrap();
+        assert_eq!(usage.len(), 2);
+        assert!(usage[0].ended_at.is_some());
+        assert_eq!(usage[1].size, "s-4vcpu-8gb");
+        assert_eq!(usage[1].price, usd(0.07).unwrap());
+        assert_eq!(usage[1].started_at, usage[0].ended_at.unwrap());
+
+        // Usage outlives the environment
+        db.remove_instance("222").unwrap();
+        let usage = db.list_usage().unwrap();
+        assert_eq!(usage.len(), 2);
+        assert!(usage.iter().all(|r| r.ended_at.is_some()));
+        assert_eq!(usage[1].name, "api");
+    }
+
+    #[test]
+    fn test_set_stopped() {
+        let (db, _dir, _lock) = create_test_db();
+        db.save_instance(&create_test_instance("789", "spuff-stop"))
+            .unwrap();
+
+        let at = Utc::now();
+        db.set_stopped("789", Some(at)).unwrap();
+        let stopped = db.get_active_instance().unwrap().unwrap();
+        assert_eq!(stopped.id, "789");
+        assert_eq!(stopped.stopped_at, Some(at));
+
+        db.set_stopped("789", None).unwrap();
+        assert!(db
+            .get_active_instance()
+            .unwrap()
+            .unwrap()
+            .stopped_at
+            .is_none());
+    }
+
+    #[test]
+    fn test_host_keys_follow_instance() {
+        let (db, _dir, _lock) = create_test_db();
+        db.save_instance(&create_test_instance("789", "spuff-pin"))
+            .unwrap();
+
+        db.pin_host_key("10.0.0.1", "ssh-ed25519 AAAA1").unwrap();
+        db.pin_host_key("pool.example.com", "ssh-ed25519 AAAA2")
+            .unwrap();
+        assert_eq!(db.list_host_keys().unwrap().len(), 2);
+
+        // A new IP after a reboot keeps the key
+        db.update_instance_ip("789", "10.0.0.2").unwrap();
+        assert!(db.host_key("10.0.0.1").unwrap().is_none());
+        assert_eq!(
+            db.host_key("10.0.0.2").unwrap().unwrap().key,
+            "ssh-ed25519 AAAA1"
+        );
+
+        // Removing the instance frees its IP for a new key
+        db.remove_instance("789").unwrap();
+        assert!(db.host_key("10.0.0.2").unwrap().is_none());
+        assert!(db.host_key("pool.example.com").unwrap().is_some());
+    }
+
     #[test]
     fn test_instance_replace_on_same_id() {
         let (db, _dir, _lock) = create_test_db();
@@ -462,6 +1307,8 @@ mod tests {
             region: "nyc1".to_string(),
             size: "small".to_string(),
             created_at: Utc::now(),
+            stopped_at: None,
+            price: None,
         };
 
         let instance2 = LocalInstance {
@@ -472,6 +1319,8 @@ mod tests {
             region: "fsn1".to_string(),
             size: "large".to_string(),
             created_at: Utc::now(),
+            stopped_at: None,
+            price: None,
         };
 
         db.save_instance(&instance1).unwrap();
@@ -11,7 +11,7 @@ use crossterm::{
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 
-pub use progress::{run_progress_ui, ProgressMessage};
+pub use progress::{run_event_progress, run_progress_ui, ProgressMessage};
 pub use widgets::*;
 
 use crate::error::Result;
@@ -1,6 +1,7 @@
-use std::io::{self, stdout, IsTerminal};
+use std::io::{self, stdout, IsTerminal, Write};
 use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use crossterm::{
     event::{self, Event, KeyCode},
     execute,
@@ -14,6 +15,7 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph},
     Frame, Terminal,
 };
+use serde::Serialize;
 use tokio::sync::mpsc;
 
 use super::widgets::{colors, StepState};
@@ -360,6 +362,103 @@ fn draw_progress_ui(
     frame.render_widget(hints, layout[4]);
 }
 
+/// Event of `spuff up`'s headless progress, one JSON object per line.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    Step {
+        index: usize,
+        name: &'a str,
+        state: StepState,
+    },
+    SubStep {
+        step: usize,
+        index: usize,
+        name: &'a str,
+        state: StepState,
+    },
+    Detail {
+        message: &'a str,
+    },
+    Complete {
+        name: &'a str,
+        /// IP address, or container ID on Docker
+        ip: &'a str,
+    },
+    Failed {
+        error: &'a str,
+    },
+}
+
+#[derive(Serialize)]
+struct EventLine<'a> {
+    time: DateTime<Utc>,
+    #[serde(flatten)]
+    event: ProgressEvent<'a>,
+}
+
+/// Map a progress message to the event it is reported as, if any.
+fn progress_event<'a>(
+    msg: &'a ProgressMessage,
+    steps: &'a [String],
+    sub_steps: &'a [Vec<String>],
+) -> Option<ProgressEvent<'a>> {
+    match msg {
+        ProgressMessage::SetStep(idx, state) => Some(ProgressEvent::Step {
+            index: *idx,
+            name: steps.get(*idx)?,
+            state: *state,
+        }),
+        ProgressMessage::SetSubStep(step, idx, state) => Some(ProgressEvent::SubStep {
+            step: *step,
+            index: *idx,
+            name: sub_steps.get(*step)?.get(*idx)?,
+            state: *state,
+        }),
+        ProgressMessage::SetDetail(detail) if !detail.is_empty() => {
+            Some(ProgressEvent::Detail { message: detail })
+        }
+        ProgressMessage::Complete(name, ip) => Some(ProgressEvent::Complete { name, ip }),
+        ProgressMessage::Failed(error) => Some(ProgressEvent::Failed { error }),
+        _ => None,
+    }
+}
+
+/// Report progress as NDJSON events on stderr, for `spuff up` without a
+/// terminal (CI, scripts) or with `--output json|yaml`. Stdout is left for
+/// the final result.
+pub async fn run_event_progress(
+    steps: Vec<String>,
+    mut rx: mpsc::Receiver<ProgressMessage>,
+) -> io::Result<Option<(String, String)>> {
+    let mut sub_steps: Vec<Vec<String>> = vec![Vec::new(); steps.len()];
+    let mut result = None;
+
+    while let Some(msg) = rx.recv().await {
+        if let Some(event) = progress_event(&msg, &steps, &sub_steps) {
+            let line = EventLine {
+                time: Utc::now(),
+                event,
+            };
+            let json = serde_json::to_string(&line).map_err(io::Error::other)?;
+            let mut stderr = io::stderr().lock();
+            writeln!(stderr, "{}", json)?;
+            stderr.flush()?;
+        }
+
+        match msg {
+            ProgressMessage::SetSubSteps(idx, names) if idx < sub_steps.len() => {
+                sub_steps[idx] = names;
+            }
+            ProgressMessage::Complete(name, ip) => result = Some((name, ip)),
+            ProgressMessage::Close => break,
+            _ => {}
+        }
+    }
+
+    Ok(result)
+}
+
 /// Fallback text-only progress when TUI is not available
 async fn run_text_progress(
     steps: Vec<String>,
@@ -380,24 +479,19 @@ async fn run_text_progress(
 
     loop {
         match rx.recv().await {
-            Some(ProgressMessage::SetStep(idx, state)) => match state {
+            Some(ProgressMessage::SetStep(idx, state)) if idx < steps.len() => match state {
                 StepState::InProgress => {
-                    if idx < steps.len() {
-                        println!("  {} {}", style("→").cyan(), style(&steps[idx]).white());
-                    }
+                    println!("  {} {}", style("→").cyan(), style(&steps[idx]).white());
                 }
                 StepState::Done => {
-                    if idx < steps.len() {
-                        println!("  {} {}", style("✓").green(), style(&steps[idx]).dim());
-                    }
+                    println!("  {} {}", style("✓").green(), style(&steps[idx]).dim());
                 }
                 StepState::Failed => {
-                    if idx < steps.len() {
-                        println!("  {} {}", style("✕").red(), style(&steps[idx]).red());
-                    }
+                    println!("  {} {}", style("✕").red(), style(&steps[idx]).red());
                 }
                 _ => {}
             },
+            Some(ProgressMessage::SetStep(_, _)) => {}
             Some(ProgressMessage::SetDetail(detail)) => {
                 if !detail.is_empty() {
                     println!("    {}", style(&detail).dim().italic());
@@ -434,3 +528,55 @@ async fn run_text_progress(
     println!();
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event_json(msg: &ProgressMessage, steps: &[String], sub_steps: &[Vec<String>]) -> String {
+        serde_json::to_string(&progress_event(msg, steps, sub_steps).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_progress_events() {
+        let steps = vec!["Creating instance".to_string()];
+        let sub_steps = vec![vec!["Updating packages".to_string()]];
+
+        assert_eq!(
+            event_json(
+                &ProgressMessage::SetStep(0, StepState::InProgress),
+                &steps,
+                &sub_steps
+            ),
+            r#"{"event":"step","index":0,"name":"Creating instance","state":"in_progress"}"#
+        );
+        assert_eq!(
+            event_json(
+                &ProgressMessage::SetSubStep(0, 0, StepState::Done),
+                &steps,
+                &sub_steps
+            ),
+            r#"{"event":"sub_step","step":0,"index":0,"name":"Updating packages","state":"done"}"#
+        );
+        assert_eq!(
+            event_json(
+                &ProgressMessage::Failed("quota exceeded".to_string()),
+                &steps,
+                &sub_steps
+            ),
+            r#"{"event":"failed","error":"quota exceeded"}"#
+        );
+    }
+
+    #[test]
+    fn test_progress_events_skip_internal_messages() {
+        let steps = vec!["Creating instance".to_string()];
+
+        assert!(progress_event(&ProgressMessage::Close, &steps, &[]).is_none());
+        assert!(progress_event(&ProgressMessage::SetDetail(String::new()), &steps, &[]).is_none());
+        // Unknown steps are dropped rather than reported without a name
+        assert!(
+            progress_event(&ProgressMessage::SetStep(5, StepState::Done), &steps, &[]).is_none()
+        );
+    }
+}
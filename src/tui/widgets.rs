@@ -7,6 +7,7 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
+use serde::Serialize;
 
 /// Brand colors for spuff
 pub mod colors {
@@ -176,7 +177,8 @@ pub fn render_hints(frame: &mut Frame, area: Rect, hints: &[(&str, &str)]) {
 }
 
 /// Progress step state
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum StepState {
     Pending,
     InProgress,
@@ -10,7 +10,8 @@ use serde::{Deserialize, Serialize};
 /// Volume mount configuration from spuff.yaml
 ///
 /// The SSHFS driver mounts the remote VM directory locally, allowing
-/// local editors to work with files that live on the VM.
+/// local editors to work with files that live on the VM. The sync driver
+/// (`type: sync`) instead keeps a copy of the local directory on the VM.
 ///
 /// # Example
 /// ```yaml
@@ -54,6 +55,8 @@ pub enum VolumeType {
     /// SSHFS - SSH Filesystem (default)
     #[default]
     Sshfs,
+    /// Delta sync - a copy of the local directory kept on the VM
+    Sync,
     // Future drivers:
     // Nfs,
     // NineP,
@@ -63,6 +66,7 @@ impl std::fmt::Display for VolumeType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             VolumeType::Sshfs => write!(f, "sshfs"),
+            VolumeType::Sync => write!(f, "sync"),
         }
     }
 }
@@ -73,6 +77,7 @@ impl std::str::FromStr for VolumeType {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "sshfs" => Ok(VolumeType::Sshfs),
+            "sync" => Ok(VolumeType::Sync),
             _ => Err(format!("Unknown volume type: {}", s)),
         }
     }
@@ -101,6 +106,18 @@ pub struct VolumeOptions {
     #[serde(default = "default_server_alive_count_max")]
     pub server_alive_count_max: u8,
 
+    /// Patterns to leave out, in .gitignore syntax (sync driver)
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Also skip files ignored by .gitignore files (sync driver, default: true)
+    #[serde(default = "default_true")]
+    pub gitignore: bool,
+
+    /// Pull changes made on the VM back (sync driver)
+    #[serde(default)]
+    pub sync_back: bool,
+
     /// Extra driver-specific options as key-value pairs
     #[serde(default, flatten)]
     pub extra: HashMap<String, serde_json::Value>,
@@ -126,6 +143,9 @@ impl Default for VolumeOptions {
             cache: default_true(),
             server_alive_interval: default_server_alive_interval(),
             server_alive_count_max: default_server_alive_count_max(),
+            exclude: Vec::new(),
+            gitignore: default_true(),
+            sync_back: false,
             extra: HashMap::new(),
         }
     }
@@ -287,12 +307,14 @@ mod tests {
     #[test]
     fn test_volume_type_display() {
         assert_eq!(VolumeType::Sshfs.to_string(), "sshfs");
+        assert_eq!(VolumeType::Sync.to_string(), "sync");
     }
 
     #[test]
     fn test_volume_type_from_str() {
         assert_eq!("sshfs".parse::<VolumeType>().unwrap(), VolumeType::Sshfs);
         assert_eq!("SSHFS".parse::<VolumeType>().unwrap(), VolumeType::Sshfs);
+        assert_eq!("sync".parse::<VolumeType>().unwrap(), VolumeType::Sync);
         assert!("unknown".parse::<VolumeType>().is_err());
     }
 
@@ -394,6 +416,29 @@ mod tests {
         assert!(options.cache);
         assert_eq!(options.server_alive_interval, 15);
         assert_eq!(options.server_alive_count_max, 3);
+        assert!(options.exclude.is_empty());
+        assert!(options.gitignore);
+        assert!(!options.sync_back);
+    }
+
+    #[test]
+    fn test_parse_yaml_sync_volume() {
+        let yaml = r#"
+type: sync
+source: .
+target: /home/dev/project
+options:
+  exclude: ["*.log", "tmp/"]
+  gitignore: false
+  sync_back: true
+"#;
+
+        let config: VolumeConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.driver_type, VolumeType::Sync);
+        assert_eq!(config.options.exclude, vec!["*.log", "tmp/"]);
+        assert!(!config.options.gitignore);
+        assert!(config.options.sync_back);
+        assert!(config.options.extra.is_empty());
     }
 
     #[test]
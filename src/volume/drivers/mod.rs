@@ -4,5 +4,7 @@
 //! for different mount protocols.
 
 pub mod sshfs;
+pub mod sync;
 
 pub use sshfs::{get_install_instructions, SshfsDriver, SshfsLocalCommands};
+pub use sync::{SyncDriver, SyncLocalCommands, SyncSpec};
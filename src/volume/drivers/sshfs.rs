@@ -450,13 +450,20 @@ impl SshfsLocalCommands {
 
         let wrapper_path = wrapper_dir.join(format!("ssh-wrapper-{:x}.sh", hash));
 
+        // Verify host keys against the pins instead of trusting any key
+        let known_hosts = crate::ssh::host_keys::write_known_hosts()?;
+        validate_shell_safe_path(&known_hosts.to_string_lossy())?;
+
         // Use single quotes for the path in the script to prevent expansion
         // The path has already been validated to not contain single quotes
         let script_content = format!(
             r#"#!/bin/bash
-exec ssh -i '{}' -o IdentitiesOnly=yes -o StrictHostKeyChecking=accept-new -o UserKnownHostsFile=/dev/null -o ServerAliveInterval={} -o ServerAliveCountMax={} "$@"
+exec ssh -i '{}' -o IdentitiesOnly=yes -o StrictHostKeyChecking=accept-new -o UserKnownHostsFile='{}' -o ServerAliveInterval={} -o ServerAliveCountMax={} "$@"
 "#,
-            ssh_key_path, options.server_alive_interval, options.server_alive_count_max
+            ssh_key_path,
+            known_hosts.display(),
+            options.server_alive_interval,
+            options.server_alive_count_max
         );
 
         std::fs::write(&wrapper_path, &script_content).map_err(|e| {
@@ -0,0 +1,172 @@
+//! Content-defined chunking and the delta wire format
+//!
+//! Files are cut where a rolling gear hash (as in FastCDC) hits a bit
+//! pattern, so an insertion only changes the chunks around it and the rest
+//! of the file can be copied from the version already on the other side.
+//!
+//! The agent includes this file too (`#[path]`) and must cut files exactly
+//! like the CLI does, so it only depends on external crates.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Smallest chunk, except at the end of a file.
+pub const MIN_CHUNK: usize = 2 * 1024;
+
+/// Largest chunk; cut here even without a hash match.
+pub const MAX_CHUNK: usize = 64 * 1024;
+
+/// Cut when the low 13 bits of the hash are zero: ~8 KiB chunks on average.
+const MASK: u64 = (1 << 13) - 1;
+
+/// Random values per byte for the gear hash, fixed so both sides agree.
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    // splitmix64
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x5350_5546_4643_4443;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// A chunk of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chunk {
+    pub offset: u64,
+    pub len: u64,
+    /// First 16 bytes of the SHA-256 of the chunk, hex encoded
+    pub hash: String,
+}
+
+/// Split `data` into content-defined chunks.
+pub fn chunk(data: &[u8]) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let len = cut_point(&data[offset..]);
+        let hash = Sha256::digest(&data[offset..offset + len]);
+        chunks.push(Chunk {
+            offset: offset as u64,
+            len: len as u64,
+            hash: hex::encode(&hash[..16]),
+        });
+        offset += len;
+    }
+
+    chunks
+}
+
+/// Length of the chunk at the start of `data`.
+fn cut_point(data: &[u8]) -> usize {
+    if data.len() <= MIN_CHUNK {
+        return data.len();
+    }
+
+    let end = data.len().min(MAX_CHUNK);
+    let mut hash: u64 = 0;
+    for (i, &byte) in data.iter().enumerate().take(end).skip(MIN_CHUNK) {
+        hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+        if hash & MASK == 0 {
+            return i + 1;
+        }
+    }
+    end
+}
+
+/// SHA-256 of a whole file, hex encoded.
+pub fn file_hash(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// One step of rebuilding a file, sent as a line of JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Op {
+    /// Bytes from the file's previous version
+    Copy { offset: u64, len: u64 },
+    /// New bytes, base64 encoded
+    Data { data: String },
+}
+
+impl Op {
+    /// Literal bytes.
+    pub fn data(bytes: &[u8]) -> Self {
+        Op::Data {
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random bytes.
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chunks_cover_data_within_bounds() {
+        let data = noise(300_000, 1);
+        let chunks = chunk(&data);
+
+        let mut offset = 0;
+        for (i, c) in chunks.iter().enumerate() {
+            assert_eq!(c.offset, offset);
+            assert!(c.len as usize <= MAX_CHUNK);
+            if i + 1 < chunks.len() {
+                assert!(c.len as usize > MIN_CHUNK);
+            }
+            offset += c.len;
+        }
+        assert_eq!(offset, data.len() as u64);
+        assert!(chunk(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_insert_only_changes_nearby_chunks() {
+        let data = noise(300_000, 2);
+        let mut edited = data.clone();
+        edited.splice(150_000..150_000, b"inserted line\n".iter().copied());
+
+        let before: std::collections::HashSet<String> =
+            chunk(&data).into_iter().map(|c| c.hash).collect();
+        let after = chunk(&edited);
+        let new = after.iter().filter(|c| !before.contains(&c.hash)).count();
+
+        assert!(after.len() > 10);
+        assert!(new <= 2, "{} of {} chunks changed", new, after.len());
+    }
+
+    #[test]
+    fn test_op_wire_format() {
+        assert_eq!(
+            serde_json::to_string(&Op::Copy { offset: 0, len: 5 }).unwrap(),
+            r#"{"op":"copy","offset":0,"len":5}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Op::data(b"hi")).unwrap(),
+            r#"{"op":"data","data":"aGk="}"#
+        );
+    }
+}
@@ -0,0 +1,847 @@
+//! Reconciling a local tree with its copy on the VM
+//!
+//! [`Syncer`] remembers, per file, the version both sides had after the last
+//! sync. Comparing each side against it tells which one changed: local
+//! changes are pushed as deltas against the chunks the VM already has, VM
+//! changes are pulled back when `sync_back` is on. A file changed on both
+//! sides is a conflict; the local version wins and the VM's is kept next to
+//! it as `<name>.spuff-conflict`.
+//!
+//! With no history (first pass of a daemon), the local tree wins: files that
+//! differ are overwritten and files only on the VM are deleted, like
+//! `rsync --delete`. With `sync_back`, differing files count as conflicts and
+//! VM-only files are pulled instead.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::chunker::{self, Chunk, Op};
+use super::tree::{self, IgnoreRules, TreeFile};
+use crate::error::{Result, SpuffError};
+
+/// Send changes in requests of about this many bytes of new data.
+const BATCH_BYTES: usize = 4 * 1024 * 1024;
+
+/// A file on the VM, as listed by the agent.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemoteFile {
+    pub path: String,
+    pub hash: String,
+    /// Only listed when asked for
+    #[serde(default)]
+    pub chunks: Vec<Chunk>,
+}
+
+/// A change to make on the VM.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub path: String,
+    /// Hash the file must have on the VM for the change to apply
+    /// (`None`: it must not exist)
+    pub base: Option<String>,
+    /// Keep the VM's version as `<path>.spuff-conflict`
+    pub backup: bool,
+    /// New content, or `None` to delete the file
+    pub content: Option<Content>,
+}
+
+/// New content of a file, as a delta against its `base` version.
+#[derive(Debug, Clone)]
+pub struct Content {
+    pub hash: String,
+    pub mode: u32,
+    pub mtime: u64,
+    pub ops: Vec<Op>,
+}
+
+/// What happened to a [`Change`] on the VM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Applied,
+    Deleted,
+    /// The file wasn't at its `base` version; nothing was changed
+    Conflict,
+    Error,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChangeResult {
+    pub path: String,
+    pub result: Outcome,
+    #[serde(default)]
+    pub backed_up: bool,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// The VM side of a sync volume.
+#[async_trait]
+pub trait Remote: Send + Sync {
+    /// Files under the volume's target, with their chunks if `chunks` is set.
+    async fn manifest(&self, chunks: bool) -> Result<Vec<RemoteFile>>;
+
+    /// Apply changes in order, returning one result per change.
+    async fn apply(&self, changes: &[Change]) -> Result<Vec<ChangeResult>>;
+
+    /// Full content of a file.
+    async fn download(&self, path: &str) -> Result<Vec<u8>>;
+}
+
+/// Counters reported in the volume's status.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncStats {
+    /// Files in sync
+    pub files: usize,
+
+    /// Changes not yet on the other side
+    pub pending: usize,
+
+    /// Conflicts resolved since the daemon started
+    pub conflicts: u64,
+
+    /// New data pushed since the daemon started
+    pub bytes_sent: u64,
+
+    /// When both sides last matched
+    pub last_sync: Option<DateTime<Utc>>,
+
+    /// Since when changes have been waiting (lag = now - this)
+    pub pending_since: Option<DateTime<Utc>>,
+}
+
+/// Version of a file both sides had after the last sync.
+#[derive(Debug, Clone)]
+struct Synced {
+    hash: String,
+    chunks: Vec<Chunk>,
+    /// Local size and mtime, to skip reading unchanged files
+    size: u64,
+    mtime: u64,
+}
+
+/// A local version to push.
+struct Push {
+    file: TreeFile,
+    hash: String,
+    data: Vec<u8>,
+    base: Option<String>,
+    base_chunks: Vec<Chunk>,
+    backup: bool,
+}
+
+/// Work found by a scan.
+#[derive(Default)]
+struct Plan {
+    pushes: Vec<Push>,
+    /// Deletions on the VM: path, base hash, backup
+    deletes: Vec<(String, String, bool)>,
+    /// Files to fetch from the VM
+    pulls: Vec<String>,
+    /// Local files to remove (deleted on the VM)
+    removals: Vec<String>,
+}
+
+impl Plan {
+    fn len(&self) -> usize {
+        self.pushes.len() + self.deletes.len() + self.pulls.len() + self.removals.len()
+    }
+}
+
+/// Keeps a local directory and a directory on the VM in sync.
+pub struct Syncer {
+    root: PathBuf,
+    rules: IgnoreRules,
+    sync_back: bool,
+    synced: HashMap<String, Synced>,
+    /// List the VM's files (with chunks) on the next pass
+    full: bool,
+    pub stats: SyncStats,
+}
+
+impl Syncer {
+    pub fn new(root: impl Into<PathBuf>, rules: IgnoreRules, sync_back: bool) -> Self {
+        Self {
+            root: root.into(),
+            rules,
+            sync_back,
+            synced: HashMap::new(),
+            full: true,
+            stats: SyncStats::default(),
+        }
+    }
+
+    /// Compare both sides against the VM's full listing on the next pass,
+    /// e.g. after reconnecting.
+    pub fn rescan(&mut self) {
+        self.full = true;
+    }
+
+    /// Scan for changes and send them. The VM's files are listed on the
+    /// first pass, after [`rescan`](Self::rescan), and when `poll_remote` is
+    /// set with `sync_back` on.
+    pub async fn sync_once(&mut self, remote: &dyn Remote, poll_remote: bool) -> Result<()> {
+        let full = self.full;
+        let local = tree::walk(&self.root, &self.rules).map_err(|e| {
+            SpuffError::Volume(format!("Failed to scan {}: {}", self.root.display(), e))
+        })?;
+
+        let remote_files: Option<HashMap<String, RemoteFile>> =
+            if full || (poll_remote && self.sync_back) {
+                let files = remote.manifest(full).await?;
+                Some(files.into_iter().map(|f| (f.path.clone(), f)).collect())
+            } else {
+                None
+            };
+
+        let plan = self.plan(&local, remote_files.as_ref());
+        let changes = plan.len();
+        if changes > 0 && self.stats.pending_since.is_none() {
+            self.stats.pending_since = Some(Utc::now());
+        }
+        self.full = false;
+
+        let mut unresolved = 0;
+        unresolved += self.push(remote, plan.pushes, plan.deletes).await?;
+        unresolved += self.pull(remote, plan.pulls).await;
+        self.remove_local(plan.removals);
+
+        self.stats.files = self.synced.len();
+        self.stats.pending = unresolved;
+        if unresolved == 0 {
+            self.stats.pending_since = None;
+            self.stats.last_sync = Some(Utc::now());
+        }
+        if changes > 0 {
+            tracing::debug!("Synced {} change(s), {} left", changes, unresolved);
+        }
+        Ok(())
+    }
+
+    /// Decide what each path needs.
+    fn plan(
+        &mut self,
+        local: &[TreeFile],
+        remote_files: Option<&HashMap<String, RemoteFile>>,
+    ) -> Plan {
+        let mut plan = Plan::default();
+        let mut in_sync = Vec::new();
+
+        for file in local {
+            let prev = self.synced.get(&file.path);
+            let unchanged = prev.is_some_and(|p| p.size == file.size && p.mtime == file.mtime);
+            let (hash, data) = match prev {
+                Some(p) if unchanged => (p.hash.clone(), None),
+                _ => match std::fs::read(self.root.join(&file.path)) {
+                    Ok(data) => (chunker::file_hash(&data), Some(data)),
+                    // Removed since the scan
+                    Err(_) => continue,
+                },
+            };
+
+            // What the VM has, as far as we know
+            let remote_file = remote_files.and_then(|files| files.get(&file.path));
+            let remote_hash = match remote_files {
+                Some(_) => remote_file.map(|f| f.hash.clone()),
+                None => prev.map(|p| p.hash.clone()),
+            };
+
+            if remote_hash.as_deref() == Some(hash.as_str()) {
+                if unchanged {
+                    continue;
+                }
+                // Same on both sides; remember the new local stat
+                let chunks = match (&data, prev, remote_file) {
+                    (_, Some(p), _) if p.hash == hash => p.chunks.clone(),
+                    (Some(data), _, _) => chunker::chunk(data),
+                    (None, _, Some(r)) => r.chunks.clone(),
+                    (None, _, None) => Vec::new(),
+                };
+                in_sync.push((
+                    file.path.clone(),
+                    Synced {
+                        hash,
+                        chunks,
+                        size: file.size,
+                        mtime: file.mtime,
+                    },
+                ));
+                continue;
+            }
+
+            let local_changed = !matches!(prev, Some(p) if p.hash == hash);
+            let remote_changed = match prev {
+                Some(p) => remote_hash.as_deref() != Some(p.hash.as_str()),
+                None => remote_hash.is_some(),
+            };
+
+            if !local_changed && self.sync_back {
+                // Only the VM side changed
+                match remote_hash {
+                    Some(_) => plan.pulls.push(file.path.clone()),
+                    None => plan.removals.push(file.path.clone()),
+                }
+                continue;
+            }
+
+            let data = match data {
+                Some(data) => data,
+                None => match std::fs::read(self.root.join(&file.path)) {
+                    Ok(data) => data,
+                    Err(_) => continue,
+                },
+            };
+            let base_chunks = match (prev, remote_file) {
+                (Some(p), _) if remote_hash.as_deref() == Some(p.hash.as_str()) => p.chunks.clone(),
+                (_, Some(r)) => r.chunks.clone(),
+                _ => Vec::new(),
+            };
+
+            plan.pushes.push(Push {
+                file: file.clone(),
+                hash,
+                data,
+                base: remote_hash,
+                base_chunks,
+                // Changed on both sides: keep the VM's version too
+                backup: remote_changed && (prev.is_some() || self.sync_back),
+            });
+        }
+
+        let local_paths: HashSet<&str> = local.iter().map(|f| f.path.as_str()).collect();
+
+        // Deleted locally
+        for (path, prev) in &self.synced {
+            if local_paths.contains(path.as_str()) {
+                continue;
+            }
+            let remote_hash = match remote_files {
+                Some(files) => files.get(path).map(|f| f.hash.clone()),
+                None => Some(prev.hash.clone()),
+            };
+            match remote_hash {
+                // Gone on both sides
+                None => plan.removals.push(path.clone()),
+                Some(hash) if hash == prev.hash => {
+                    plan.deletes.push((path.clone(), hash, false));
+                }
+                // Changed on the VM
+                Some(_) if self.sync_back => plan.pulls.push(path.clone()),
+                Some(hash) => plan.deletes.push((path.clone(), hash, true)),
+            }
+        }
+
+        // Only on the VM
+        if let Some(files) = remote_files {
+            for (path, file) in files {
+                if local_paths.contains(path.as_str()) || self.synced.contains_key(path) {
+                    continue;
+                }
+                if self.sync_back {
+                    plan.pulls.push(path.clone());
+                } else {
+                    plan.deletes.push((path.clone(), file.hash.clone(), false));
+                }
+            }
+        }
+
+        self.synced.extend(in_sync);
+        plan
+    }
+
+    /// Send pushes and deletions in batches. Returns how many didn't apply.
+    async fn push(
+        &mut self,
+        remote: &dyn Remote,
+        pushes: Vec<Push>,
+        deletes: Vec<(String, String, bool)>,
+    ) -> Result<usize> {
+        let mut unresolved = 0;
+        let mut batch: Vec<Change> = Vec::new();
+        let mut pushed: HashMap<String, Synced> = HashMap::new();
+        let mut batch_bytes = 0;
+
+        let deletes = deletes.into_iter().map(|(path, base, backup)| Change {
+            path,
+            base: Some(base),
+            backup,
+            content: None,
+        });
+        let pushes = pushes.into_iter().map(|push| {
+            let (ops, chunks, bytes) = delta(&push.data, &push.base_chunks);
+            pushed.insert(
+                push.file.path.clone(),
+                Synced {
+                    hash: push.hash.clone(),
+                    chunks,
+                    size: push.file.size,
+                    mtime: push.file.mtime,
+                },
+            );
+            (
+                Change {
+                    path: push.file.path,
+                    base: push.base,
+                    backup: push.backup,
+                    content: Some(Content {
+                        hash: push.hash,
+                        mode: push.file.mode,
+                        mtime: push.file.mtime,
+                        ops,
+                    }),
+                },
+                bytes,
+            )
+        });
+        let changes: Vec<(Change, usize)> = deletes.map(|c| (c, 0)).chain(pushes).collect();
+        let total = changes.len();
+
+        for (i, (change, bytes)) in changes.into_iter().enumerate() {
+            batch_bytes += bytes;
+            self.stats.bytes_sent += bytes as u64;
+            batch.push(change);
+
+            if batch_bytes >= BATCH_BYTES || i + 1 == total {
+                let results = remote.apply(&batch).await?;
+                unresolved += batch.len().saturating_sub(results.len());
+                for result in results {
+                    if !self.record(result, &mut pushed) {
+                        unresolved += 1;
+                    }
+                }
+                batch.clear();
+                batch_bytes = 0;
+            }
+        }
+
+        Ok(unresolved)
+    }
+
+    /// Update the synced state from a change's result. Returns whether it applied.
+    fn record(&mut self, result: ChangeResult, pushed: &mut HashMap<String, Synced>) -> bool {
+        if result.backed_up {
+            self.stats.conflicts += 1;
+            tracing::warn!(
+                "Conflict on {}: kept the VM's version as {}.spuff-conflict",
+                result.path,
+                result.path
+            );
+        }
+
+        match result.result {
+            Outcome::Applied => {
+                if let Some(synced) = pushed.remove(&result.path) {
+                    self.synced.insert(result.path, synced);
+                }
+                true
+            }
+            Outcome::Deleted => {
+                self.synced.remove(&result.path);
+                true
+            }
+            Outcome::Conflict => {
+                // Changed on the VM since we last looked; decide again
+                // against a fresh listing
+                tracing::debug!("{} changed on the VM, rescanning", result.path);
+                self.full = true;
+                false
+            }
+            Outcome::Error => {
+                tracing::warn!(
+                    "Failed to sync {}: {}",
+                    result.path,
+                    result.message.as_deref().unwrap_or("unknown error")
+                );
+                false
+            }
+        }
+    }
+
+    /// Fetch files changed on the VM. Returns how many failed.
+    async fn pull(&mut self, remote: &dyn Remote, paths: Vec<String>) -> usize {
+        let mut failed = 0;
+        for path in paths {
+            let data = match remote.download(&path).await {
+                Ok(data) => data,
+                Err(e) => {
+                    tracing::warn!("Failed to fetch {}: {}", path, e);
+                    failed += 1;
+                    continue;
+                }
+            };
+            match write_local(&self.root, &path, &data) {
+                Ok(file) => {
+                    self.synced.insert(
+                        path,
+                        Synced {
+                            hash: chunker::file_hash(&data),
+                            chunks: chunker::chunk(&data),
+                            size: file.size,
+                            mtime: file.mtime,
+                        },
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to write {}: {}", path, e);
+                    failed += 1;
+                }
+            }
+        }
+        failed
+    }
+
+    /// Remove local files deleted on the VM.
+    fn remove_local(&mut self, paths: Vec<String>) {
+        for path in paths {
+            match std::fs::remove_file(self.root.join(&path)) {
+                Ok(()) => tracing::debug!("Removed {} (deleted on the VM)", path),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    tracing::warn!("Failed to remove {}: {}", path, e);
+                    continue;
+                }
+            }
+            self.synced.remove(&path);
+        }
+    }
+}
+
+/// Ops rebuilding `data` from a file cut into `base` chunks, the chunks of
+/// `data`, and how many bytes the ops carry.
+fn delta(data: &[u8], base: &[Chunk]) -> (Vec<Op>, Vec<Chunk>, usize) {
+    let known: HashMap<&str, &Chunk> = base.iter().map(|c| (c.hash.as_str(), c)).collect();
+    let chunks = chunker::chunk(data);
+
+    let mut ops = Vec::new();
+    let mut literal: Vec<u8> = Vec::new();
+    let mut bytes = 0;
+
+    for chunk in &chunks {
+        match known.get(chunk.hash.as_str()) {
+            Some(existing) if existing.len == chunk.len => {
+                if !literal.is_empty() {
+                    ops.push(Op::data(&literal));
+                    literal.clear();
+                }
+                // Extend the previous copy if it continues it
+                if let Some(Op::Copy { offset, len }) = ops.last_mut() {
+                    if *offset + *len == existing.offset {
+                        *len += existing.len;
+                        continue;
+                    }
+                }
+                ops.push(Op::Copy {
+                    offset: existing.offset,
+                    len: existing.len,
+                });
+            }
+            _ => {
+                let start = chunk.offset as usize;
+                literal.extend_from_slice(&data[start..start + chunk.len as usize]);
+                bytes += chunk.len as usize;
+            }
+        }
+    }
+    if !literal.is_empty() {
+        ops.push(Op::data(&literal));
+    }
+
+    (ops, chunks, bytes)
+}
+
+/// Replace a local file through a temporary file and return its new stat.
+fn write_local(root: &Path, path: &str, data: &[u8]) -> std::io::Result<TreeFile> {
+    let target = root.join(path);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let part = root.join(format!("{}.spuff-part", path));
+    std::fs::write(&part, data)?;
+    std::fs::rename(&part, &target)?;
+
+    let metadata = std::fs::metadata(&target)?;
+    Ok(TreeFile {
+        path: path.to_string(),
+        size: metadata.len(),
+        mtime: metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|since| since.as_nanos() as u64)
+            .unwrap_or(0),
+        mode: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    /// The VM side, in memory.
+    #[derive(Default)]
+    struct FakeRemote {
+        files: Mutex<BTreeMap<String, Vec<u8>>>,
+        /// Data bytes received
+        received: Mutex<usize>,
+    }
+
+    impl FakeRemote {
+        fn file(&self, path: &str) -> Option<String> {
+            let files = self.files.lock().unwrap();
+            files
+                .get(path)
+                .map(|data| String::from_utf8_lossy(data).into_owned())
+        }
+
+        fn put(&self, path: &str, content: &[u8]) {
+            self.files
+                .lock()
+                .unwrap()
+                .insert(path.to_string(), content.to_vec());
+        }
+    }
+
+    #[async_trait]
+    impl Remote for FakeRemote {
+        async fn manifest(&self, chunks: bool) -> Result<Vec<RemoteFile>> {
+            let files = self.files.lock().unwrap();
+            Ok(files
+                .iter()
+                .filter(|(path, _)| !path.ends_with(".spuff-conflict"))
+                .map(|(path, data)| RemoteFile {
+                    path: path.clone(),
+                    hash: chunker::file_hash(data),
+                    chunks: if chunks {
+                        chunker::chunk(data)
+                    } else {
+                        Vec::new()
+                    },
+                })
+                .collect())
+        }
+
+        async fn apply(&self, changes: &[Change]) -> Result<Vec<ChangeResult>> {
+            let mut files = self.files.lock().unwrap();
+            let mut results = Vec::new();
+
+            for change in changes {
+                let current = files.get(&change.path).cloned();
+                let result = |result, backed_up| ChangeResult {
+                    path: change.path.clone(),
+                    result,
+                    backed_up,
+                    message: None,
+                };
+                if current.as_deref().map(chunker::file_hash) != change.base {
+                    results.push(result(Outcome::Conflict, false));
+                    continue;
+                }
+
+                let backed_up = change.backup && current.is_some();
+                if let Some(current) = current.clone().filter(|_| backed_up) {
+                    files.insert(format!("{}.spuff-conflict", change.path), current);
+                }
+
+                match &change.content {
+                    None => {
+                        files.remove(&change.path);
+                        results.push(result(Outcome::Deleted, backed_up));
+                    }
+                    Some(content) => {
+                        let base = current.unwrap_or_default();
+                        let mut data = Vec::new();
+                        for op in &content.ops {
+                            match op {
+                                Op::Copy { offset, len } => data.extend_from_slice(
+                                    &base[*offset as usize..(*offset + *len) as usize],
+                                ),
+                                Op::Data { data: encoded } => {
+                                    let bytes = base64::engine::general_purpose::STANDARD
+                                        .decode(encoded)
+                                        .unwrap();
+                                    *self.received.lock().unwrap() += bytes.len();
+                                    data.extend_from_slice(&bytes);
+                                }
+                            }
+                        }
+                        assert_eq!(chunker::file_hash(&data), content.hash);
+                        files.insert(change.path.clone(), data);
+                        results.push(result(Outcome::Applied, backed_up));
+                    }
+                }
+            }
+
+            Ok(results)
+        }
+
+        async fn download(&self, path: &str) -> Result<Vec<u8>> {
+            self.files
+                .lock()
+                .unwrap()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| SpuffError::Volume(format!("{} not found", path)))
+        }
+    }
+
+    fn write(root: &Path, path: &str, content: &[u8]) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    /// Make the next write visible to a stat-based scan even within the
+    /// filesystem's timestamp granularity.
+    fn rewrite(root: &Path, path: &str, content: &[u8]) {
+        write(root, path, content);
+        let file = std::fs::File::options()
+            .write(true)
+            .open(root.join(path))
+            .unwrap();
+        file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(5))
+            .unwrap();
+    }
+
+    fn syncer(root: &Path, sync_back: bool) -> Syncer {
+        Syncer::new(root, IgnoreRules::new(&[], true), sync_back)
+    }
+
+    #[tokio::test]
+    async fn test_initial_sync_mirrors_local_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "src/main.rs", b"fn main() {}");
+        write(dir.path(), ".gitignore", b"target/\n");
+        write(dir.path(), "target/debug/app", b"binary");
+
+        let remote = FakeRemote::default();
+        remote.put("stale.txt", b"only on the VM");
+
+        let mut syncer = syncer(dir.path(), false);
+        syncer.sync_once(&remote, false).await.unwrap();
+
+        assert_eq!(remote.file("src/main.rs").as_deref(), Some("fn main() {}"));
+        assert!(remote.file("target/debug/app").is_none());
+        assert!(remote.file("stale.txt").is_none());
+        assert!(syncer.stats.last_sync.is_some());
+        assert_eq!(syncer.stats.files, 2);
+        assert_eq!(syncer.stats.pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_edit_sends_only_changed_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let content: Vec<u8> = (0..200_000u32)
+            .flat_map(|i| format!("line {}\n", i.wrapping_mul(2_654_435_761)).into_bytes())
+            .collect();
+        write(dir.path(), "big.txt", &content);
+
+        let remote = FakeRemote::default();
+        let mut syncer = syncer(dir.path(), false);
+        syncer.sync_once(&remote, false).await.unwrap();
+        let initial = *remote.received.lock().unwrap();
+        assert_eq!(initial, content.len());
+
+        let mut edited = content.clone();
+        edited.splice(1_000_000..1_000_000, b"an inserted line\n".iter().copied());
+        rewrite(dir.path(), "big.txt", &edited);
+        syncer.sync_once(&remote, false).await.unwrap();
+
+        let sent = *remote.received.lock().unwrap() - initial;
+        assert!(sent < 200_000, "sent {} bytes for a one-line edit", sent);
+        assert_eq!(remote.files.lock().unwrap()["big.txt"], edited);
+    }
+
+    #[tokio::test]
+    async fn test_local_delete_is_pushed() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.txt", b"a");
+
+        let remote = FakeRemote::default();
+        let mut syncer = syncer(dir.path(), false);
+        syncer.sync_once(&remote, false).await.unwrap();
+        assert!(remote.file("a.txt").is_some());
+
+        std::fs::remove_file(dir.path().join("a.txt")).unwrap();
+        syncer.sync_once(&remote, false).await.unwrap();
+        assert!(remote.file("a.txt").is_none());
+        assert_eq!(syncer.stats.files, 0);
+    }
+
+    #[tokio::test]
+    async fn test_conflict_keeps_vm_version() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "notes.md", b"v1");
+
+        let remote = FakeRemote::default();
+        let mut syncer = syncer(dir.path(), false);
+        syncer.sync_once(&remote, false).await.unwrap();
+
+        // Both sides edit the file between passes
+        remote.put("notes.md", b"edited on the VM");
+        rewrite(dir.path(), "notes.md", b"edited locally");
+
+        // The first attempt finds the VM changed and rescans
+        syncer.sync_once(&remote, false).await.unwrap();
+        assert_eq!(syncer.stats.pending, 1);
+        syncer.sync_once(&remote, false).await.unwrap();
+
+        assert_eq!(remote.file("notes.md").as_deref(), Some("edited locally"));
+        assert_eq!(
+            remote.file("notes.md.spuff-conflict").as_deref(),
+            Some("edited on the VM")
+        );
+        assert_eq!(syncer.stats.conflicts, 1);
+        assert_eq!(syncer.stats.pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_sync_back_pulls_vm_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "Cargo.lock", b"old");
+
+        let remote = FakeRemote::default();
+        let mut syncer = syncer(dir.path(), true);
+        syncer.sync_once(&remote, true).await.unwrap();
+
+        remote.put("Cargo.lock", b"updated by cargo on the VM");
+        remote.put("generated.rs", b"// generated");
+        syncer.sync_once(&remote, true).await.unwrap();
+
+        let read = |path: &str| std::fs::read_to_string(dir.path().join(path)).unwrap();
+        assert_eq!(read("Cargo.lock"), "updated by cargo on the VM");
+        assert_eq!(read("generated.rs"), "// generated");
+
+        // Pulled files aren't pushed back
+        let received = *remote.received.lock().unwrap();
+        syncer.sync_once(&remote, true).await.unwrap();
+        assert_eq!(*remote.received.lock().unwrap(), received);
+
+        remote.files.lock().unwrap().remove("generated.rs");
+        syncer.sync_once(&remote, true).await.unwrap();
+        assert!(!dir.path().join("generated.rs").exists());
+    }
+
+    #[test]
+    fn test_delta_merges_adjacent_copies() {
+        let data: Vec<u8> = (0..100_000u32).flat_map(|i| i.to_le_bytes()).collect();
+        let base = chunker::chunk(&data);
+        let (ops, _, bytes) = delta(&data, &base);
+
+        assert_eq!(bytes, 0);
+        assert_eq!(
+            ops,
+            vec![Op::Copy {
+                offset: 0,
+                len: data.len() as u64
+            }]
+        );
+    }
+}
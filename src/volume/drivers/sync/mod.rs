@@ -0,0 +1,592 @@
+//! Delta sync volume driver
+//!
+//! An alternative to SSHFS for trees edited locally and built on the VM:
+//! files live on both sides and a background daemon keeps the VM's copy up
+//! to date, so builds and watchers on the VM see local disk speed.
+//!
+//! # Flow
+//! 1. `spuff volume mount` (or `spuff up`) starts `spuff sync-daemon` for the volume
+//! 2. The daemon lists the VM's copy through spuff-agent and pushes what differs
+//! 3. It then scans the local tree every 500ms and sends changed files as
+//!    deltas: content-defined chunks the VM already has are copied in place
+//! 4. With `sync_back`, it also polls the VM and pulls changes made there
+//!
+//! Files matched by the volume's `exclude` patterns or by a `.gitignore` are
+//! left alone on both sides. A file changed on both sides keeps the local
+//! version; the VM's is saved as `<name>.spuff-conflict`.
+//!
+//! The daemon keeps its spec, PID, status and log in `~/.spuff/sync/`,
+//! named after the local directory, and reconnects with backoff when the
+//! SSH session drops.
+//!
+//! # Example
+//! ```yaml
+//! volumes:
+//!   - type: sync
+//!     source: .
+//!     target: /home/dev/project
+//!     options:
+//!       exclude: ["*.log", "tmp/"]
+//!       sync_back: true
+//! ```
+
+pub mod chunker;
+mod engine;
+mod remote;
+pub mod tree;
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::watch;
+
+use crate::config::AppConfig;
+use crate::error::{Result, SpuffError};
+use crate::ssh::{SshClient, SshConfig};
+use crate::volume::config::VolumeConfig;
+use crate::volume::driver::VolumeDriver;
+use crate::volume::state::{MountHandle, MountStatus};
+
+use engine::{SyncStats, Syncer};
+use remote::AgentRemote;
+use tree::IgnoreRules;
+
+/// How often the local tree is scanned.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often the VM's copy is listed with `sync_back`.
+const REMOTE_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Longest wait between reconnect attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Give up on a connection attempt after this long.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Failed attempts before `start` gives up on the first sync.
+const START_ATTEMPTS: u32 = 3;
+
+/// Longest `start` waits for the first sync.
+const START_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// What a sync daemon keeps in sync, written to `<id>.json` by `start`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncSpec {
+    pub vm_ip: String,
+    pub ssh_user: String,
+    pub ssh_key_path: String,
+
+    /// Local directory
+    pub local_dir: String,
+
+    /// Directory on the VM
+    pub target: String,
+
+    pub exclude: Vec<String>,
+    pub gitignore: bool,
+    pub sync_back: bool,
+}
+
+impl SyncSpec {
+    /// Spec for `volume`, synced from `local_dir`.
+    pub fn new(
+        vm_ip: impl Into<String>,
+        ssh_user: impl Into<String>,
+        ssh_key_path: impl Into<String>,
+        local_dir: impl Into<String>,
+        volume: &VolumeConfig,
+    ) -> Self {
+        Self {
+            vm_ip: vm_ip.into(),
+            ssh_user: ssh_user.into(),
+            ssh_key_path: ssh_key_path.into(),
+            local_dir: local_dir.into(),
+            target: volume.target.clone(),
+            exclude: volume.options.exclude.clone(),
+            gitignore: volume.options.gitignore,
+            sync_back: volume.options.sync_back,
+        }
+    }
+}
+
+/// What a daemon reports in `<id>.status.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DaemonStatus {
+    connected: bool,
+
+    /// Consecutive failed connections or passes
+    attempts: u32,
+
+    error: Option<String>,
+
+    #[serde(flatten)]
+    stats: SyncStats,
+}
+
+/// Delta sync volume driver
+///
+/// Keeps a copy of a local directory on the VM, see the module docs.
+#[derive(Debug, Clone, Default)]
+pub struct SyncDriver;
+
+impl SyncDriver {
+    /// Create a new sync driver instance
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl VolumeDriver for SyncDriver {
+    fn name(&self) -> &'static str {
+        "sync"
+    }
+
+    fn description(&self) -> &'static str {
+        "Delta sync - keep a copy of a local directory on the VM"
+    }
+
+    async fn is_available(&self) -> bool {
+        // Only needs spuff itself and the agent on the VM
+        true
+    }
+
+    async fn mount(
+        &self,
+        config: &VolumeConfig,
+        _tunnel_port: Option<u16>,
+        _remote_user: &str,
+    ) -> Result<MountHandle> {
+        // The daemon is started by SyncLocalCommands::start()
+        let local_dir = config.resolve_mount_point(None, None);
+
+        Ok(MountHandle::new(self.name(), &config.target, &local_dir)
+            .with_source(&local_dir)
+            .with_read_only(config.read_only))
+    }
+
+    async fn unmount(&self, handle: &MountHandle) -> Result<()> {
+        SyncLocalCommands::stop(&handle.mount_point).await
+    }
+
+    async fn status(&self, handle: &MountHandle) -> Result<MountStatus> {
+        SyncLocalCommands::check_status(&handle.mount_point).await
+    }
+
+    fn required_packages(&self) -> Vec<&'static str> {
+        // spuff-agent applies the changes with what the image already has
+        vec![]
+    }
+}
+
+/// Commands to manage sync daemons, by local directory
+pub struct SyncLocalCommands;
+
+impl SyncLocalCommands {
+    /// Start syncing `spec.local_dir` to the VM and wait for the first sync.
+    ///
+    /// A daemon already running for the directory is replaced.
+    pub async fn start(spec: &SyncSpec) -> Result<()> {
+        std::fs::create_dir_all(&spec.local_dir).map_err(|e| {
+            SpuffError::Volume(format!("Failed to create {}: {}", spec.local_dir, e))
+        })?;
+        if !spec.target.starts_with('/') {
+            return Err(SpuffError::Volume(format!(
+                "Sync target must be an absolute path: {}",
+                spec.target
+            )));
+        }
+
+        Self::stop(&spec.local_dir).await?;
+
+        let id = daemon_id(&spec.local_dir);
+        std::fs::create_dir_all(sync_dir()?)?;
+        std::fs::write(file_path(&id, "json")?, serde_json::to_vec_pretty(spec)?)?;
+
+        let log = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_path(&id, "log")?)?;
+
+        let mut command = std::process::Command::new(std::env::current_exe()?);
+        command
+            .args(["sync-daemon", "--id", &id])
+            .stdin(std::process::Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log);
+
+        // Own process group so Ctrl+C in the terminal doesn't reach it
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+
+        let mut child = command.spawn()?;
+        tracing::debug!(
+            "Started sync daemon for {} (pid {})",
+            spec.local_dir,
+            child.id()
+        );
+
+        let log_hint = || {
+            file_path(&id, "log")
+                .map(|path| path.display().to_string())
+                .unwrap_or_default()
+        };
+        let started = Instant::now();
+        loop {
+            if child.try_wait()?.is_some() {
+                return Err(SpuffError::Volume(format!(
+                    "Sync daemon exited; see {}",
+                    log_hint()
+                )));
+            }
+
+            if let Some(status) = read_status(&id) {
+                if status.stats.last_sync.is_some() {
+                    return Ok(());
+                }
+                if status.attempts >= START_ATTEMPTS {
+                    Self::stop(&spec.local_dir).await?;
+                    return Err(SpuffError::Volume(format!(
+                        "Initial sync failed: {}",
+                        status.error.unwrap_or_else(log_hint)
+                    )));
+                }
+            }
+
+            if started.elapsed() > START_TIMEOUT {
+                Self::stop(&spec.local_dir).await?;
+                return Err(SpuffError::Volume(format!(
+                    "Initial sync didn't finish; see {}",
+                    log_hint()
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    }
+
+    /// Stop syncing a local directory. Does nothing if no daemon runs for it.
+    pub async fn stop(local_dir: &str) -> Result<()> {
+        let id = daemon_id(local_dir);
+
+        if let Some(pid) = running_pid(&id) {
+            #[cfg(unix)]
+            {
+                let _ = nix::sys::signal::kill(
+                    nix::unistd::Pid::from_raw(pid),
+                    nix::sys::signal::Signal::SIGTERM,
+                );
+            }
+            #[cfg(not(unix))]
+            let _ = pid;
+
+            // Let it finish the pass in flight
+            for _ in 0..50 {
+                if running_pid(&id).is_none() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            tracing::debug!("Stopped sync daemon for {}", local_dir);
+        }
+
+        for extension in ["json", "status.json"] {
+            let _ = std::fs::remove_file(file_path(&id, extension)?);
+        }
+        Ok(())
+    }
+
+    /// Check if a daemon is syncing a local directory
+    pub fn is_running(local_dir: &str) -> bool {
+        running_pid(&daemon_id(local_dir)).is_some()
+    }
+
+    /// Check sync status and lag
+    pub async fn check_status(local_dir: &str) -> Result<MountStatus> {
+        let id = daemon_id(local_dir);
+        if running_pid(&id).is_none() {
+            return Ok(MountStatus::not_mounted());
+        }
+
+        let Some(status) = read_status(&id) else {
+            return Ok(MountStatus::unhealthy("Starting"));
+        };
+
+        let base = if status.connected {
+            MountStatus::healthy()
+        } else {
+            MountStatus::unhealthy(format!(
+                "Disconnected, retrying: {}",
+                status.error.as_deref().unwrap_or("unknown error")
+            ))
+        };
+
+        let lag = status
+            .stats
+            .pending_since
+            .map(|since| (Utc::now() - since).num_milliseconds().max(0) as u64)
+            .unwrap_or(0);
+
+        Ok(base.with_sync(
+            lag,
+            status.stats.pending as u64,
+            status.stats.conflicts,
+            status.stats.last_sync,
+        ))
+    }
+}
+
+/// Run a sync daemon (`spuff sync-daemon`) until it's stopped.
+pub async fn run(id: &str, agent_token: Option<String>) -> Result<()> {
+    let spec: SyncSpec = serde_json::from_slice(&std::fs::read(file_path(id, "json")?)?)?;
+    let pid_path = file_path(id, "pid")?;
+    std::fs::write(&pid_path, std::process::id().to_string())?;
+    tracing::info!(
+        "Syncing {} -> {}:{}",
+        spec.local_dir,
+        spec.vm_ip,
+        spec.target
+    );
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(forward_shutdown_signals(shutdown_tx));
+
+    let result = supervise(id, &spec, agent_token.as_deref(), shutdown_rx).await;
+
+    let _ = std::fs::remove_file(&pid_path);
+    tracing::info!("Sync daemon for {} stopped", spec.local_dir);
+    result
+}
+
+/// Sync on every tick, reconnecting with backoff when a pass fails.
+async fn supervise(
+    id: &str,
+    spec: &SyncSpec,
+    agent_token: Option<&str>,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<()> {
+    let rules = IgnoreRules::new(&spec.exclude, spec.gitignore);
+    let mut syncer = Syncer::new(&spec.local_dir, rules, spec.sync_back);
+    let mut status = DaemonStatus::default();
+
+    loop {
+        if *shutdown.borrow() {
+            return Ok(());
+        }
+
+        match connect(spec, agent_token).await {
+            Ok(remote) => {
+                tracing::info!("Connected to {}", spec.vm_ip);
+                // The VM may have changed while we were away
+                syncer.rescan();
+                let mut last_remote_poll = Instant::now();
+
+                loop {
+                    let poll_remote = last_remote_poll.elapsed() >= REMOTE_POLL_INTERVAL;
+                    if poll_remote {
+                        last_remote_poll = Instant::now();
+                    }
+
+                    if let Err(e) = syncer.sync_once(&remote, poll_remote).await {
+                        tracing::warn!("Sync failed: {}", e);
+                        status.error = Some(e.to_string());
+                        break;
+                    }
+                    status.connected = true;
+                    status.attempts = 0;
+                    status.error = None;
+                    status.stats = syncer.stats.clone();
+                    write_status(id, &status);
+
+                    tokio::select! {
+                        _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                        _ = shutdown.changed() => return Ok(()),
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Connection failed: {}", e);
+                status.error = Some(e.to_string());
+            }
+        }
+
+        status.connected = false;
+        status.attempts += 1;
+        status.stats = syncer.stats.clone();
+        write_status(id, &status);
+
+        tokio::select! {
+            _ = tokio::time::sleep(backoff(status.attempts)) => {}
+            _ = shutdown.changed() => return Ok(()),
+        }
+    }
+}
+
+async fn connect(spec: &SyncSpec, agent_token: Option<&str>) -> Result<AgentRemote> {
+    // Pins follow the instance's IP, which changes on every boot
+    let pins_loaded = crate::ssh::host_keys::load_pins().is_ok();
+
+    let config = SshConfig::new(&spec.ssh_user, PathBuf::from(&spec.ssh_key_path));
+    let client = tokio::time::timeout(
+        CONNECT_TIMEOUT,
+        SshClient::connect(&spec.vm_ip, 22, &config),
+    )
+    .await
+    .map_err(|_| SpuffError::Ssh(format!("Timed out connecting to {}", spec.vm_ip)))??;
+
+    if pins_loaded {
+        if let Err(e) = crate::ssh::host_keys::store_learned() {
+            tracing::warn!("Failed to store pinned host keys: {}", e);
+        }
+    }
+
+    AgentRemote::new(
+        client,
+        agent_token.map(str::to_string),
+        &spec.target,
+        spec.exclude.clone(),
+        spec.gitignore,
+    )
+    .await
+}
+
+/// Delay before reconnect attempt `attempt` (1-based): 1s, 2s, 4s, ... up to a minute.
+fn backoff(attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(6);
+    (Duration::from_secs(1) * 2u32.pow(exponent)).min(MAX_BACKOFF)
+}
+
+#[cfg(unix)]
+async fn forward_shutdown_signals(shutdown: watch::Sender<bool>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let Ok(mut terminate) = signal(SignalKind::terminate()) else {
+        return;
+    };
+    tokio::select! {
+        _ = terminate.recv() => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    let _ = shutdown.send(true);
+}
+
+#[cfg(not(unix))]
+async fn forward_shutdown_signals(shutdown: watch::Sender<bool>) {
+    let _ = tokio::signal::ctrl_c().await;
+    let _ = shutdown.send(true);
+}
+
+fn sync_dir() -> Result<PathBuf> {
+    Ok(AppConfig::config_dir()?.join("sync"))
+}
+
+/// Daemon name for a local directory.
+fn daemon_id(local_dir: &str) -> String {
+    let digest = Sha256::digest(local_dir.trim_end_matches('/').as_bytes());
+    hex::encode(&digest[..8])
+}
+
+/// Path of the daemon's `.json`, `.pid`, `.status.json` or `.log` file.
+fn file_path(id: &str, extension: &str) -> Result<PathBuf> {
+    Ok(sync_dir()?.join(format!("{}.{}", id, extension)))
+}
+
+fn read_status(id: &str) -> Option<DaemonStatus> {
+    let data = std::fs::read(file_path(id, "status.json").ok()?).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+/// Replace the status file, through a temporary file so readers never see
+/// half of it.
+fn write_status(id: &str, status: &DaemonStatus) {
+    let write = || -> Result<()> {
+        let path = file_path(id, "status.json")?;
+        let temp = file_path(id, "status.json.tmp")?;
+        std::fs::write(&temp, serde_json::to_vec(status)?)?;
+        std::fs::rename(&temp, &path)?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        tracing::warn!("Failed to write sync status: {}", e);
+    }
+}
+
+/// PID of the daemon, if it is alive.
+fn running_pid(id: &str) -> Option<i32> {
+    let pid = file_path(id, "pid")
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|pid| pid.trim().parse::<i32>().ok())?;
+
+    #[cfg(unix)]
+    {
+        nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid), None)
+            .is_ok()
+            .then_some(pid)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_driver_name() {
+        let driver = SyncDriver::new();
+        assert_eq!(driver.name(), "sync");
+        assert!(driver.required_packages().is_empty());
+    }
+
+    #[test]
+    fn test_daemon_id_ignores_trailing_slash() {
+        assert_eq!(
+            daemon_id("/home/me/project"),
+            daemon_id("/home/me/project/")
+        );
+        assert_ne!(daemon_id("/home/me/project"), daemon_id("/home/me/other"));
+        assert_eq!(daemon_id("/home/me/project").len(), 16);
+    }
+
+    #[test]
+    fn test_spec_from_volume() {
+        let mut volume = VolumeConfig::new(".", "/home/dev/project");
+        volume.options.exclude = vec!["*.log".to_string()];
+        volume.options.sync_back = true;
+
+        let spec = SyncSpec::new("10.0.0.1", "dev", "/k", "/src/project", &volume);
+        assert_eq!(spec.target, "/home/dev/project");
+        assert_eq!(spec.exclude, vec!["*.log"]);
+        assert!(spec.gitignore);
+        assert!(spec.sync_back);
+    }
+
+    #[test]
+    fn test_status_wire_format() {
+        let status: DaemonStatus = serde_json::from_str(
+            r#"{"connected":true,"attempts":0,"error":null,"files":3,"pending":2,
+                "conflicts":1,"bytes_sent":10,"last_sync":null,"pending_since":null}"#,
+        )
+        .unwrap();
+        assert!(status.connected);
+        assert_eq!(status.stats.pending, 2);
+        assert_eq!(status.stats.conflicts, 1);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_a_minute() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(4));
+        assert_eq!(backoff(20), MAX_BACKOFF);
+    }
+}
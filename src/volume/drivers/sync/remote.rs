@@ -0,0 +1,139 @@
+//! The VM side of a sync volume, reached through spuff-agent
+//!
+//! Requests go to the agent's `/sync/*` endpoints through
+//! [`AgentClient`], over the same SSH connection the files pulled back are
+//! read from with SFTP.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::Serialize;
+use tokio::io::AsyncReadExt;
+
+use super::engine::{Change, ChangeResult, Remote, RemoteFile};
+use crate::connector::agent::AgentClient;
+use crate::error::{Result, SpuffError};
+use crate::ssh::{SftpClient, SshClient};
+
+#[derive(Serialize)]
+struct ManifestRequest<'a> {
+    root: &'a str,
+    exclude: &'a [String],
+    gitignore: bool,
+    chunks: bool,
+}
+
+/// First line of an apply request.
+#[derive(Serialize)]
+struct ApplyHeader<'a> {
+    root: &'a str,
+}
+
+/// Line introducing a change, followed by `ops` op lines.
+#[derive(Serialize)]
+struct ChangeHeader<'a> {
+    path: &'a str,
+    base: Option<&'a str>,
+    backup: bool,
+    file: Option<FileHeader<'a>>,
+}
+
+#[derive(Serialize)]
+struct FileHeader<'a> {
+    hash: &'a str,
+    mode: u32,
+    mtime: u64,
+    ops: usize,
+}
+
+/// A synced directory on the VM.
+pub struct AgentRemote {
+    agent: AgentClient,
+    sftp: SftpClient,
+    root: String,
+    exclude: Vec<String>,
+    gitignore: bool,
+}
+
+impl AgentRemote {
+    /// Use `root` on the VM over `client`, creating it if needed.
+    pub async fn new(
+        client: SshClient,
+        agent_token: Option<String>,
+        root: &str,
+        exclude: Vec<String>,
+        gitignore: bool,
+    ) -> Result<Self> {
+        let sftp = client.sftp().await?;
+        // Created as the SSH user, so the agent gives files the same owner
+        sftp.create_dir_all(root).await?;
+
+        Ok(Self {
+            agent: AgentClient::new(client, agent_token),
+            sftp,
+            root: root.trim_end_matches('/').to_string(),
+            exclude,
+            gitignore,
+        })
+    }
+}
+
+#[async_trait]
+impl Remote for AgentRemote {
+    async fn manifest(&self, chunks: bool) -> Result<Vec<RemoteFile>> {
+        let request = ManifestRequest {
+            root: &self.root,
+            exclude: &self.exclude,
+            gitignore: self.gitignore,
+            chunks,
+        };
+        self.agent.post("/sync/manifest", &request).await
+    }
+
+    async fn apply(&self, changes: &[Change]) -> Result<Vec<ChangeResult>> {
+        let mut body = serde_json::to_vec(&ApplyHeader { root: &self.root })?;
+        body.push(b'\n');
+
+        for change in changes {
+            let header = ChangeHeader {
+                path: &change.path,
+                base: change.base.as_deref(),
+                backup: change.backup,
+                file: change.content.as_ref().map(|content| FileHeader {
+                    hash: &content.hash,
+                    mode: content.mode,
+                    mtime: content.mtime,
+                    ops: content.ops.len(),
+                }),
+            };
+            serde_json::to_writer(&mut body, &header)?;
+            body.push(b'\n');
+
+            for op in change.content.iter().flat_map(|content| &content.ops) {
+                serde_json::to_writer(&mut body, op)?;
+                body.push(b'\n');
+            }
+        }
+
+        let (mut sender, request_body) = futures::channel::mpsc::channel::<Bytes>(1);
+        sender
+            .try_send(Bytes::from(body))
+            .map_err(|e| SpuffError::Volume(format!("Failed to send changes: {}", e)))?;
+        drop(sender);
+
+        let mut response = self.agent.post_stream("/sync/apply", request_body).await?;
+        let line = response.next_line().await?.ok_or_else(|| {
+            SpuffError::Volume("No response from spuff-agent on /sync/apply".to_string())
+        })?;
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    async fn download(&self, path: &str) -> Result<Vec<u8>> {
+        let remote_path = format!("{}/{}", self.root, path);
+        let mut file = self.sftp.open_read(&remote_path, 0).await?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)
+            .await
+            .map_err(|e| SpuffError::Ssh(format!("Failed to read {}: {}", remote_path, e)))?;
+        Ok(data)
+    }
+}
@@ -0,0 +1,365 @@
+//! Which files a sync volume covers
+//!
+//! Walks a tree applying the volume's `exclude` patterns and, unless turned
+//! off, every `.gitignore` in it, both with gitignore syntax. The agent
+//! includes this file too (`#[path]`) to list the VM's side with the same
+//! rules, so it only depends on std.
+
+use std::path::Path;
+
+/// Files spuff itself leaves around; never synced.
+const ALWAYS_EXCLUDED: &[&str] = &["*.spuff-part", "*.spuff-conflict"];
+
+/// A regular file in a synced tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeFile {
+    /// Path relative to the root, `/`-separated
+    pub path: String,
+    pub size: u64,
+    /// Modification time, nanoseconds since the epoch
+    pub mtime: u64,
+    /// Permission bits (`0o644`)
+    pub mode: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Directory of the `.gitignore` the rule comes from ("" for the root)
+    base: String,
+    pattern: String,
+    negate: bool,
+    dir_only: bool,
+    /// Matched against the whole path under `base` rather than the file name
+    anchored: bool,
+}
+
+/// Exclude patterns in gitignore syntax; the last matching rule wins.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+    gitignore: bool,
+}
+
+impl IgnoreRules {
+    /// Rules from `exclude`, plus the `.gitignore` files found while walking
+    /// if `gitignore` is set.
+    pub fn new(exclude: &[String], gitignore: bool) -> Self {
+        let mut rules = Self {
+            rules: Vec::new(),
+            gitignore,
+        };
+        for pattern in ALWAYS_EXCLUDED
+            .iter()
+            .copied()
+            .chain(exclude.iter().map(String::as_str))
+        {
+            rules.add("", pattern);
+        }
+        rules
+    }
+
+    /// Add the lines of a `.gitignore` found in directory `base`.
+    pub fn add_lines(&mut self, base: &str, text: &str) {
+        for line in text.lines() {
+            self.add(base, line);
+        }
+    }
+
+    fn add(&mut self, base: &str, line: &str) {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return;
+        }
+
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let pattern = line.trim_start_matches('/');
+        if pattern.is_empty() {
+            return;
+        }
+
+        self.rules.push(Rule {
+            base: base.to_string(),
+            pattern: pattern.to_string(),
+            negate,
+            dir_only,
+            anchored,
+        });
+    }
+
+    /// Whether `path` (relative to the root) is excluded. Its parent
+    /// directories are checked separately, as the walk descends.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let rel = match rule.base.as_str() {
+                "" => path,
+                base => match path
+                    .strip_prefix(base)
+                    .and_then(|rest| rest.strip_prefix('/'))
+                {
+                    Some(rel) => rel,
+                    None => continue,
+                },
+            };
+            let subject = if rule.anchored {
+                rel
+            } else {
+                rel.rsplit('/').next().unwrap_or(rel)
+            };
+            if glob(rule.pattern.as_bytes(), subject.as_bytes()) {
+                ignored = !rule.negate;
+            }
+        }
+        ignored
+    }
+}
+
+/// List the files under `root` that aren't ignored, sorted by path.
+///
+/// Symlinks and special files are skipped, as are names that aren't UTF-8.
+pub fn walk(root: &Path, rules: &IgnoreRules) -> std::io::Result<Vec<TreeFile>> {
+    let mut rules = rules.clone();
+    let mut files = Vec::new();
+    walk_dir(root, "", &mut rules, &mut files)?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn walk_dir(
+    root: &Path,
+    dir: &str,
+    rules: &mut IgnoreRules,
+    files: &mut Vec<TreeFile>,
+) -> std::io::Result<()> {
+    let path = root.join(dir);
+    if rules.gitignore {
+        if let Ok(text) = std::fs::read_to_string(path.join(".gitignore")) {
+            rules.add_lines(dir, &text);
+        }
+    }
+
+    let entries = match std::fs::read_dir(&path) {
+        Ok(entries) => entries,
+        Err(e) if dir.is_empty() => return Err(e),
+        // Removed while walking, or unreadable
+        Err(_) => return Ok(()),
+    };
+
+    for entry in entries.flatten() {
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        let rel = if dir.is_empty() {
+            name
+        } else {
+            format!("{}/{}", dir, name)
+        };
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if file_type.is_dir() {
+            if !rules.is_ignored(&rel, true) {
+                walk_dir(root, &rel, rules, files)?;
+            }
+        } else if file_type.is_file() && !rules.is_ignored(&rel, false) {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            files.push(TreeFile {
+                path: rel,
+                size: metadata.len(),
+                mtime: mtime_nanos(&metadata),
+                mode: mode(&metadata),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn mtime_nanos(metadata: &std::fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|since| since.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(unix)]
+fn mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode(metadata: &std::fs::Metadata) -> u32 {
+    if metadata.permissions().readonly() {
+        0o444
+    } else {
+        0o644
+    }
+}
+
+/// Gitignore-style glob: `*` and `?` stay within a path segment, `**`
+/// spans segments, `[a-z]` matches a class.
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'*') if pattern.get(1) == Some(&b'*') => {
+            let rest = &pattern[2..];
+            match rest.strip_prefix(b"/") {
+                // `**/` matches zero or more directories
+                Some(rest) => {
+                    glob(rest, text)
+                        || text
+                            .iter()
+                            .enumerate()
+                            .any(|(i, &c)| c == b'/' && glob(rest, &text[i + 1..]))
+                }
+                None => (0..=text.len()).any(|i| glob(rest, &text[i..])),
+            }
+        }
+        Some(b'*') => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| glob(&pattern[1..], &text[i..])),
+        Some(b'?') => {
+            matches!(text.first(), Some(&c) if c != b'/') && glob(&pattern[1..], &text[1..])
+        }
+        Some(b'[') => match text.first() {
+            Some(&c) if c != b'/' => match class(&pattern[1..], c) {
+                Some((true, rest)) => glob(rest, &text[1..]),
+                Some((false, _)) => false,
+                // Unterminated class: a literal `[`
+                None => c == b'[' && glob(&pattern[1..], &text[1..]),
+            },
+            _ => false,
+        },
+        Some(b'\\') if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && glob(&pattern[2..], &text[1..])
+        }
+        Some(&c) => text.first() == Some(&c) && glob(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Match `c` against the class starting after a `[`. Returns whether it
+/// matched and the pattern after the closing `]`.
+fn class(pattern: &[u8], c: u8) -> Option<(bool, &[u8])> {
+    let (negate, mut i) = match pattern.first() {
+        Some(b'!') | Some(b'^') => (true, 1),
+        _ => (false, 0),
+    };
+    let start = i;
+    let mut matched = false;
+
+    while i < pattern.len() {
+        if pattern[i] == b']' && i > start {
+            return Some((matched != negate, &pattern[i + 1..]));
+        }
+        if i + 2 < pattern.len() && pattern[i + 1] == b'-' && pattern[i + 2] != b']' {
+            matched |= pattern[i] <= c && c <= pattern[i + 2];
+            i += 3;
+        } else {
+            matched |= pattern[i] == c;
+            i += 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(gitignore: &str) -> IgnoreRules {
+        let mut rules = IgnoreRules::new(&[], true);
+        rules.add_lines("", gitignore);
+        rules
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(glob(b"*.log", b"app.log"));
+        assert!(!glob(b"*.log", b"logs/app.log"));
+        assert!(glob(b"**/*.log", b"logs/app.log"));
+        assert!(glob(b"**/*.log", b"app.log"));
+        assert!(glob(b"a/**/b", b"a/x/y/b"));
+        assert!(glob(b"a/**/b", b"a/b"));
+        assert!(glob(b"*.py[cod]", b"mod.pyc"));
+        assert!(!glob(b"*.py[cod]", b"mod.pyx"));
+        assert!(glob(b"[!a-c]x", b"dx"));
+        assert!(!glob(b"[!a-c]x", b"bx"));
+    }
+
+    #[test]
+    fn test_gitignore_rules() {
+        let rules = rules("# build output\ntarget/\n*.log\n!keep.log\n/dist\ndocs/*.html\n");
+
+        assert!(rules.is_ignored("target", true));
+        assert!(rules.is_ignored("crates/x/target", true));
+        // Directory-only rule
+        assert!(!rules.is_ignored("target", false));
+        assert!(rules.is_ignored("logs/app.log", false));
+        assert!(!rules.is_ignored("logs/keep.log", false));
+        // Anchored to the root
+        assert!(rules.is_ignored("dist", true));
+        assert!(!rules.is_ignored("web/dist", true));
+        assert!(rules.is_ignored("docs/index.html", false));
+        assert!(!rules.is_ignored("docs/api/index.html", false));
+        assert!(rules.is_ignored("main.rs.spuff-conflict", false));
+    }
+
+    #[test]
+    fn test_nested_gitignore_is_scoped() {
+        let mut rules = IgnoreRules::new(&["*.tmp".to_string()], true);
+        rules.add_lines("web", "/build\n");
+
+        assert!(rules.is_ignored("web/build", true));
+        assert!(!rules.is_ignored("build", true));
+        assert!(rules.is_ignored("a.tmp", false));
+    }
+
+    #[test]
+    fn test_walk_skips_ignored() {
+        let root = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = root.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write(".gitignore", "node_modules/\n");
+        write("src/main.rs", "fn main() {}");
+        write("node_modules/x/index.js", "");
+        write("web/.gitignore", "*.map\n");
+        write("web/app.js", "");
+        write("web/app.js.map", "");
+        write("secrets.env", "");
+
+        let rules = IgnoreRules::new(&["*.env".to_string()], true);
+        let paths: Vec<String> = walk(root.path(), &rules)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![".gitignore", "src/main.rs", "web/.gitignore", "web/app.js"]
+        );
+
+        // Without gitignore only the exclude list applies
+        let rules = IgnoreRules::new(&[], false);
+        assert_eq!(walk(root.path(), &rules).unwrap().len(), 7);
+    }
+}
@@ -1,7 +1,7 @@
 //! Volume mounting module for spuff
 //!
 //! Provides an extensible architecture for mounting local directories on remote VMs.
-//! Currently supports SSHFS with plans for NFS, 9P, and other protocols.
+//! Currently supports SSHFS and delta sync, with plans for NFS, 9P, and other protocols.
 //!
 //! # Architecture
 //!
@@ -18,8 +18,8 @@
 //! │         ┌────────────────────┼────────────────────┐         │
 //! │         ▼                    ▼                    ▼         │
 //! │  ┌─────────────┐     ┌─────────────┐      ┌─────────────┐   │
-//! │  │ SshfsDriver │     │  NfsDriver  │      │  9PDriver   │   │
-//! │  │ (v1 - now)  │     │  (future)   │      │  (future)   │   │
+//! │  │ SshfsDriver │     │ SyncDriver  │      │  NfsDriver  │   │
+//! │  │ (v1 - now)  │     │   (now)     │      │  (future)   │   │
 //! │  └─────────────┘     └─────────────┘      └─────────────┘   │
 //! └─────────────────────────────────────────────────────────────┘
 //! ```
@@ -43,7 +43,10 @@ pub mod state;
 
 pub use config::{VolumeConfig, VolumeOptions, VolumeType};
 pub use driver::{DriverInfo, VolumeDriver};
-pub use drivers::{get_install_instructions, SshfsDriver, SshfsLocalCommands};
+pub use drivers::{
+    get_install_instructions, SshfsDriver, SshfsLocalCommands, SyncDriver, SyncLocalCommands,
+    SyncSpec,
+};
 pub use state::{MountHandle, MountStatus, VolumeState};
 
 use std::collections::HashMap;
@@ -75,6 +78,7 @@ impl VolumeManager {
 
         // Register default drivers
         drivers.insert(VolumeType::Sshfs, Box::new(SshfsDriver::new()));
+        drivers.insert(VolumeType::Sync, Box::new(SyncDriver::new()));
 
         // Load persisted state or create new (log errors but don't fail)
         let state = VolumeState::load_or_default();
@@ -86,6 +90,7 @@ impl VolumeManager {
     pub fn new_strict() -> Result<Self> {
         let mut drivers: HashMap<VolumeType, Box<dyn VolumeDriver>> = HashMap::new();
         drivers.insert(VolumeType::Sshfs, Box::new(SshfsDriver::new()));
+        drivers.insert(VolumeType::Sync, Box::new(SyncDriver::new()));
 
         let state = VolumeState::load()?;
 
@@ -254,6 +259,7 @@ mod tests {
     fn test_volume_manager_new() {
         let manager = VolumeManager::new();
         assert!(manager.get_driver(&VolumeType::Sshfs).is_some());
+        assert!(manager.get_driver(&VolumeType::Sync).is_some());
     }
 
     #[test]
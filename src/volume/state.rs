@@ -112,6 +112,18 @@ pub struct MountStatus {
 
     /// Error message if unhealthy
     pub error: Option<String>,
+
+    /// How long local changes have been waiting to reach the VM (sync driver)
+    pub sync_lag_ms: Option<u64>,
+
+    /// Changes not yet synced (sync driver)
+    pub pending_changes: Option<u64>,
+
+    /// Conflicts resolved since the sync started (sync driver)
+    pub conflicts: Option<u64>,
+
+    /// When both sides last matched (sync driver)
+    pub last_sync: Option<DateTime<Utc>>,
 }
 
 impl MountStatus {
@@ -144,6 +156,21 @@ impl MountStatus {
         self.latency_ms = Some(latency_ms);
         self
     }
+
+    /// Set sync progress (sync driver)
+    pub fn with_sync(
+        mut self,
+        lag_ms: u64,
+        pending_changes: u64,
+        conflicts: u64,
+        last_sync: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.sync_lag_ms = Some(lag_ms);
+        self.pending_changes = Some(pending_changes);
+        self.conflicts = Some(conflicts);
+        self.last_sync = last_sync;
+        self
+    }
 }
 
 /// Persistent state for volumes
@@ -346,6 +373,13 @@ impl VolumeState {
             .find(|m| m.target == path || m.mount_point == path)
     }
 
+    /// Mounts of the VM at `ip`, leaving other environments' mounts out
+    pub fn mounts_for_vm<'a>(&'a self, ip: &'a str) -> impl Iterator<Item = &'a MountHandle> {
+        self.mounts
+            .iter()
+            .filter(move |m| m.vm_ip.as_deref() == Some(ip))
+    }
+
     /// Find a mount handle by local mount point
     pub fn find_by_mount_point(&self, mount_point: &str) -> Option<&MountHandle> {
         self.mounts.iter().find(|m| m.mount_point == mount_point)
@@ -411,6 +445,15 @@ mod tests {
         assert_eq!(status.latency_ms, Some(25));
     }
 
+    #[test]
+    fn test_mount_status_with_sync() {
+        let status = MountStatus::healthy().with_sync(1200, 3, 1, None);
+        assert_eq!(status.sync_lag_ms, Some(1200));
+        assert_eq!(status.pending_changes, Some(3));
+        assert_eq!(status.conflicts, Some(1));
+        assert!(status.latency_ms.is_none());
+    }
+
     #[test]
     fn test_volume_state_add_remove() {
         let mut state = VolumeState::default();
@@ -457,4 +500,23 @@ mod tests {
         assert!(state.find_by_mount_point("/local/mnt").is_some());
         assert!(state.find_by_mount_point("/home/dev/project").is_none());
     }
+
+    #[test]
+    fn test_volume_state_mounts_for_vm() {
+        let mut state = VolumeState::default();
+        state.add_mount(
+            MountHandle::new("sshfs", "/home/dev/api", "/local/api")
+                .with_vm_info("10.0.0.1", "dev"),
+        );
+        state.add_mount(
+            MountHandle::new("sync", "/home/dev/web", "/local/web").with_vm_info("10.0.0.2", "dev"),
+        );
+        state.add_mount(MountHandle::new("sshfs", "/home/dev/old", "/local/old"));
+
+        let mounts: Vec<_> = state
+            .mounts_for_vm("10.0.0.1")
+            .map(|m| m.mount_point.as_str())
+            .collect();
+        assert_eq!(mounts, vec!["/local/api"]);
+    }
 }